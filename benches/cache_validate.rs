@@ -0,0 +1,66 @@
+//! Wall time of opening a synthetic shared cache with the full CSC check
+//! (every row index, parallel over cell ranges), the sampled quick check and
+//! the col_ptr-only check. Writing the cache is not timed.
+//!
+//! ```text
+//! cargo bench --bench cache_validate            # 100k cells x 500 genes each
+//! KIRA_BENCH_CELLS=1000000 cargo bench --bench cache_validate
+//! ```
+
+use std::time::Instant;
+
+use kira_secretion::expr::csc::ExprCsc;
+use kira_secretion::input::cache::{
+    mmap_shared_cache, mmap_shared_cache_sampled, mmap_shared_cache_unchecked, write_shared_cache,
+};
+
+const N_GENES: usize = 30_000;
+const GENES_PER_CELL: usize = 500;
+
+fn main() {
+    let n_cells: usize = std::env::var("KIRA_BENCH_CELLS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(100_000);
+    let nnz = n_cells * GENES_PER_CELL;
+    let mut row_idx = Vec::with_capacity(nnz);
+    for cell in 0..n_cells {
+        // Strictly increasing rows with a per-cell offset.
+        let start = (cell * 7) % (N_GENES - GENES_PER_CELL * 50);
+        row_idx.extend((0..GENES_PER_CELL).map(|i| (start + i * 50) as u32));
+    }
+    let expr = ExprCsc {
+        n_genes: N_GENES,
+        n_cells,
+        nnz,
+        col_ptr: (0..=n_cells).map(|c| (c * GENES_PER_CELL) as u64).collect(),
+        row_idx,
+        values: vec![1; nnz],
+    };
+    let genes: Vec<String> = (0..N_GENES).map(|g| format!("G{g}")).collect();
+    let barcodes: Vec<String> = (0..n_cells).map(|c| format!("C{c}")).collect();
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("bench.kira-organelle.bin");
+    write_shared_cache(&path, &genes, &barcodes, &expr).expect("write cache");
+    drop(expr);
+
+    println!(
+        "cells={n_cells} nnz={nnz} threads={}",
+        rayon::current_num_threads()
+    );
+    let time = |label: &str, open: &dyn Fn() -> usize| {
+        let start = Instant::now();
+        let n = open();
+        println!(
+            "{label:>9}: {:>8.1} ms (n_cells {n})",
+            start.elapsed().as_secs_f64() * 1e3
+        );
+    };
+    time("col_ptr", &|| {
+        mmap_shared_cache_unchecked(&path).expect("open").n_cells
+    });
+    time("sampled", &|| {
+        mmap_shared_cache_sampled(&path).expect("open").n_cells
+    });
+    time("full", &|| mmap_shared_cache(&path).expect("open").n_cells);
+}
@@ -0,0 +1,178 @@
+//! Wall time of stage 7 on a synthetic dataset, single-threaded versus the
+//! default rayon pool. Stages 4-6 run once up front and are not timed.
+//!
+//! ```text
+//! cargo bench --bench stage7_rows            # 1M cells
+//! KIRA_BENCH_CELLS=2000000 cargo bench --bench stage7_rows
+//! ```
+
+use std::collections::HashMap;
+use std::time::Instant;
+
+use kira_secretion::expr::csc::{CellStats, ExprCsc};
+use kira_secretion::expr::normalize::Normalization;
+use kira_secretion::input::detect::TenXFormat;
+use kira_secretion::input::features::GeneIndex;
+use kira_secretion::model::axes::AxisConfig;
+use kira_secretion::panels::defs::{PanelDef, PanelGene, PanelSet};
+use kira_secretion::panels::mapping::GeneMapping;
+use kira_secretion::pipeline::stage1_load::{DatasetCtx, RunMode};
+use kira_secretion::pipeline::stage2_normalize::{ExprContext, ExprMatrix};
+use kira_secretion::pipeline::stage3_panels::{PanelCellPacked, PanelsContext};
+use kira_secretion::pipeline::stage4_axes::{CORE_AXES, run_stage4_axes_with_config};
+use kira_secretion::pipeline::stage5_scores::run_stage5_scores;
+use kira_secretion::pipeline::stage6_classify::run_stage6_classify;
+use kira_secretion::pipeline::stage7_report::{ReportOptions, run_stage7_report_with_options};
+
+fn main() {
+    let n_cells: usize = std::env::var("KIRA_BENCH_CELLS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(1_000_000);
+    let dir = tempfile::tempdir().expect("tempdir");
+    let out = dir.path();
+
+    let barcodes: Vec<String> = (0..n_cells)
+        .map(|i| format!("{:016X}-1", (i as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)))
+        .collect();
+    let panels = PanelSet {
+        panels: CORE_AXES
+            .iter()
+            .map(|axis| PanelDef {
+                id: format!("P_{axis}"),
+                description: String::new(),
+                axis: axis.to_string(),
+                group: None,
+                genes: vec![PanelGene {
+                    symbol: axis.to_string(),
+                }],
+                required: vec![axis.to_string()],
+                weights: None,
+                custom_axis: false,
+                version: None,
+                source: None,
+            })
+            .collect(),
+        files: vec![],
+        skipped: vec![],
+        required_autofixes: vec![],
+    };
+    let mappings = panels
+        .panels
+        .iter()
+        .enumerate()
+        .map(|(i, p)| GeneMapping {
+            panel_id: p.id.clone(),
+            mapped: vec![Some(i as u32)],
+            required_hits: 1,
+            required_total: 1,
+        })
+        .collect();
+    let per_cell = (0..n_cells)
+        .map(|i| PanelCellPacked {
+            sums: (0..CORE_AXES.len())
+                .map(|p| ((i * 31 + p * 17) % 97) as f32 / 24.0)
+                .collect(),
+            hits: vec![1; CORE_AXES.len()],
+            required_missing: vec![0; CORE_AXES.len()],
+        })
+        .collect();
+    let panels_ctx = PanelsContext {
+        panels,
+        mappings,
+        warnings: Vec::new(),
+        cell_ids: barcodes.clone(),
+        per_cell,
+        dead_panels: Vec::new(),
+    };
+    let expr = ExprContext {
+        expr: ExprMatrix::Owned(ExprCsc {
+            n_genes: CORE_AXES.len(),
+            n_cells,
+            nnz: 0,
+            col_ptr: vec![0; n_cells + 1],
+            row_idx: Vec::new(),
+            values: Vec::new(),
+        }),
+        cell_stats: (0..n_cells)
+            .map(|i| CellStats {
+                libsize: 500 + (i % 5000) as u64,
+                detected: 100 + (i % 900) as u32,
+            })
+            .collect(),
+        normalization: Normalization::default(),
+        value_warnings: Default::default(),
+    };
+    let dataset = DatasetCtx {
+        format: TenXFormat::TenXv3,
+        matrix_path: "matrix.mtx".into(),
+        features_path: "features.tsv".into(),
+        barcodes_path: "barcodes.tsv".into(),
+        features_delimiter: Default::default(),
+        barcodes_delimiter: Default::default(),
+        barcodes_header: None,
+        features_single_column_rows: 0,
+        shared_cache_path: None,
+        resolved_shared_cache_path: None,
+        shared_cache: None,
+        shared_cache_identity: None,
+        gene_index: GeneIndex {
+            rows: vec![],
+            duplicates: vec![],
+            first_index_by_symbol: HashMap::new(),
+        },
+        barcodes,
+        n_genes: CORE_AXES.len(),
+        n_cells,
+        nnz: 0,
+        duplicate_gene_symbols_count: 0,
+        duplicate_gene_symbols: vec![],
+        meta_present: false,
+        meta_cells_matched: 0,
+        meta_cells_missing: 0,
+        meta_issues: Default::default(),
+        gene_set_hash: String::new(),
+        upstream: None,
+        dense: None,
+        matrix_value_type: None,
+        matrix_orientation: None,
+    };
+
+    let axes = run_stage4_axes_with_config(&dataset, &panels_ctx, out, &AxisConfig::default())
+        .expect("stage4");
+    let scores = run_stage5_scores(&axes, out).expect("stage5");
+    let classify = run_stage6_classify(&dataset, &expr, &axes, &scores, out).expect("stage6");
+
+    let stage7 = || {
+        let start = Instant::now();
+        run_stage7_report_with_options(
+            &dataset,
+            &expr,
+            &axes,
+            &scores,
+            &classify,
+            &panels_ctx,
+            out,
+            "cell",
+            RunMode::Standalone,
+            None,
+            &ReportOptions::default(),
+        )
+        .expect("stage7");
+        start.elapsed()
+    };
+
+    let single = rayon::ThreadPoolBuilder::new()
+        .num_threads(1)
+        .build()
+        .expect("pool")
+        .install(stage7);
+    let pooled = stage7();
+    println!(
+        "stage7_rows n_cells={} threads=1: {:.1} ms, threads={}: {:.1} ms",
+        n_cells,
+        single.as_secs_f64() * 1e3,
+        rayon::current_num_threads(),
+        pooled.as_secs_f64() * 1e3,
+    );
+}
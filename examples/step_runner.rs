@@ -0,0 +1,45 @@
+//! Runs the pipeline in 50 ms steps, printing where each step paused and the
+//! dataset figures known so far.
+//!
+//!     cargo run --release --example step_runner -- <input> <out_dir>
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use kira_secretion::panels::loader::{default_panels_dir, load_panels_from_dir};
+use kira_secretion::prelude::{Runner, RunnerConfig, StepStatus};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut args = std::env::args_os().skip(1).map(PathBuf::from);
+    let (Some(input), Some(out_dir)) = (args.next(), args.next()) else {
+        eprintln!("usage: step_runner <input> <out_dir>");
+        std::process::exit(2);
+    };
+
+    let panels = load_panels_from_dir(&default_panels_dir())?;
+    let mut config = RunnerConfig::new(input, out_dir, panels);
+    // The bundled panels cover only SIA and EEB; the other core axes run as NaN.
+    config.axes.allow_missing_axes = true;
+    let mut runner = Runner::start(config)?;
+    loop {
+        match runner.step(Duration::from_millis(50))? {
+            StepStatus::Paused(progress) => {
+                let partial = runner.partial();
+                println!(
+                    "{} {}/{} cells; secretory_load median {:?}",
+                    progress.stage.unwrap_or("starting"),
+                    progress.cells_done,
+                    progress.cells_total,
+                    partial.secretory_load.map(|q| q.median),
+                );
+            }
+            StepStatus::Finished(summary) => {
+                println!("done: {} cells", summary.input.n_cells);
+                for (regime, count) in &summary.regimes.counts {
+                    println!("  {regime}: {count}");
+                }
+                return Ok(());
+            }
+        }
+    }
+}
@@ -0,0 +1,425 @@
+//! Cohort tracking TSV (`run --append-cohort`) and its trend report
+//! (`cohort report`).
+//!
+//! Each run appends one row. Writers serialize on a `<file>.lock` sibling, so
+//! concurrent jobs never interleave rows. A file whose header predates newer
+//! columns is rewritten once with the extended header, and older rows get
+//! [`NA`] in the new columns.
+
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use thiserror::Error;
+
+use crate::model::pipeline_regime::RegimeLabels;
+use crate::pipeline::stage7_report::FinalSummary;
+use crate::report::format::fixed6;
+use crate::report::text::sparkline;
+use crate::report::{html, markdown};
+
+/// Value for a column the row was written without.
+pub const NA: &str = "NA";
+
+/// Prefix of the per-regime fraction columns (`frac_<regime label>`).
+pub const REGIME_PREFIX: &str = "frac_";
+
+#[derive(Debug, Error)]
+pub enum CohortError {
+    #[error("io error on cohort file {path}: {source}")]
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+    #[error("invalid cohort file {path}: {message}")]
+    Format { path: String, message: String },
+}
+
+fn io_error(path: &Path) -> impl FnOnce(std::io::Error) -> CohortError + '_ {
+    move |source| CohortError::Io {
+        path: path.display().to_string(),
+        source,
+    }
+}
+
+/// One run's entry, as ordered `(column, value)` pairs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CohortRow {
+    pub columns: Vec<(String, String)>,
+}
+
+impl CohortRow {
+    /// Row for a finished run; regime fractions follow the display order of
+    /// `labels`.
+    pub fn from_summary(
+        summary: &FinalSummary,
+        labels: &RegimeLabels,
+        timestamp: &str,
+        label: &str,
+        panels_hash: &str,
+    ) -> Self {
+        let mut columns: Vec<(String, String)> = vec![
+            ("timestamp".to_string(), timestamp.to_string()),
+            ("label".to_string(), label.to_string()),
+            ("n_cells".to_string(), summary.input.n_cells.to_string()),
+            ("tool_version".to_string(), summary.tool.version.clone()),
+            ("panels_hash".to_string(), panels_hash.to_string()),
+            (
+                "low_confidence_fraction".to_string(),
+                fixed6(summary.qc.low_confidence_fraction),
+            ),
+            (
+                "low_secretory_signal_fraction".to_string(),
+                fixed6(summary.qc.low_secretory_signal_fraction),
+            ),
+            (
+                "low_axis_coverage_fraction".to_string(),
+                fixed6(summary.caveats.low_axis_coverage_fraction),
+            ),
+        ];
+        for label in labels.ordered_labels() {
+            let fraction = summary.regimes.fractions.get(label).copied().unwrap_or(0.0);
+            columns.push((format!("{REGIME_PREFIX}{label}"), fixed6(fraction)));
+        }
+        Self { columns }
+    }
+
+    fn value(&self, column: &str) -> &str {
+        self.columns
+            .iter()
+            .find(|(c, _)| c == column)
+            .map_or(NA, |(_, v)| v.as_str())
+    }
+}
+
+/// Appends `row` to the cohort TSV at `path`, creating it with a header when
+/// absent. Holds an exclusive lock on `<path>.lock` throughout.
+pub fn append_row(path: &Path, row: &CohortRow) -> Result<(), CohortError> {
+    let lock_path = sibling(path, "lock");
+    let lock = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)
+        .map_err(io_error(&lock_path))?;
+    lock.lock().map_err(io_error(&lock_path))?;
+
+    let existing = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(io_error(path)(e)),
+    };
+    let Some(header_line) = existing.lines().next() else {
+        let header: Vec<&str> = row.columns.iter().map(|(c, _)| c.as_str()).collect();
+        return replace(
+            path,
+            &format!("{}\n{}\n", header.join("\t"), line(&header, row)),
+        );
+    };
+
+    let mut header: Vec<&str> = header_line.split('\t').collect();
+    let added: Vec<&str> = row
+        .columns
+        .iter()
+        .map(|(c, _)| c.as_str())
+        .filter(|c| !header.contains(c))
+        .collect();
+    if added.is_empty() {
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(path)
+            .map_err(io_error(path))?;
+        let separator = if existing.ends_with('\n') { "" } else { "\n" };
+        writeln!(file, "{separator}{}", line(&header, row)).map_err(io_error(path))?;
+        return file.sync_all().map_err(io_error(path));
+    }
+
+    let old_width = header.len();
+    header.extend(added);
+    let mut out = header.join("\t");
+    out.push('\n');
+    for old in existing.lines().skip(1).filter(|l| !l.is_empty()) {
+        let mut fields: Vec<&str> = old.split('\t').collect();
+        fields.resize(old_width, NA);
+        fields.resize(header.len(), NA);
+        out.push_str(&fields.join("\t"));
+        out.push('\n');
+    }
+    out.push_str(&line(&header, row));
+    out.push('\n');
+    replace(path, &out)
+}
+
+fn line(header: &[&str], row: &CohortRow) -> String {
+    let values: Vec<&str> = header.iter().map(|c| row.value(c)).collect();
+    values.join("\t")
+}
+
+fn sibling(path: &Path, extension: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(extension);
+    path.with_file_name(name)
+}
+
+/// Writes through a temp file and a rename, so readers that do not take the
+/// lock never see a half-written file.
+fn replace(path: &Path, contents: &str) -> Result<(), CohortError> {
+    let tmp = sibling(path, "tmp");
+    let result = File::create(&tmp)
+        .and_then(|mut file| {
+            file.write_all(contents.as_bytes())?;
+            file.sync_all()
+        })
+        .and_then(|()| std::fs::rename(&tmp, path));
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp);
+    }
+    result.map_err(io_error(path))
+}
+
+/// A cohort TSV read back for reporting; rows are padded with [`NA`] to the
+/// header width.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CohortTable {
+    pub header: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl CohortTable {
+    pub fn read(path: &Path) -> Result<Self, CohortError> {
+        let text = std::fs::read_to_string(path).map_err(io_error(path))?;
+        Self::parse(&text).map_err(|message| CohortError::Format {
+            path: path.display().to_string(),
+            message,
+        })
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut lines = text.lines().filter(|l| !l.is_empty());
+        let header: Vec<String> = lines
+            .next()
+            .ok_or("empty file")?
+            .split('\t')
+            .map(str::to_string)
+            .collect();
+        if !header.iter().any(|c| c == "timestamp") {
+            return Err("header has no timestamp column".to_string());
+        }
+        let mut rows = Vec::new();
+        for (i, line) in lines.enumerate() {
+            let mut fields: Vec<String> = line.split('\t').map(str::to_string).collect();
+            if fields.len() > header.len() {
+                return Err(format!(
+                    "row {} has {} fields, header has {}",
+                    i + 2,
+                    fields.len(),
+                    header.len()
+                ));
+            }
+            fields.resize(header.len(), NA.to_string());
+            rows.push(fields);
+        }
+        Ok(Self { header, rows })
+    }
+
+    fn column(&self, name: &str) -> Option<usize> {
+        self.header.iter().position(|c| c == name)
+    }
+
+    /// Rows in timestamp order; ties keep file order.
+    fn chronological(&self) -> Vec<&Vec<String>> {
+        let mut rows: Vec<&Vec<String>> = self.rows.iter().collect();
+        if let Some(ts) = self.column("timestamp") {
+            rows.sort_by(|a, b| a[ts].cmp(&b[ts]));
+        }
+        rows
+    }
+
+    /// `(regime label, column index)` for every `frac_` column.
+    fn regime_columns(&self) -> Vec<(&str, usize)> {
+        self.header
+            .iter()
+            .enumerate()
+            .filter_map(|(i, c)| c.strip_prefix(REGIME_PREFIX).map(|r| (r, i)))
+            .collect()
+    }
+}
+
+/// Output format of `cohort report`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CohortReportFormat {
+    Md,
+    Html,
+}
+
+struct Table {
+    title: &'static str,
+    header: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+fn pct(value: &str) -> String {
+    value
+        .parse::<f32>()
+        .ok()
+        .filter(|v| v.is_finite())
+        .map_or_else(|| NA.to_string(), |v| format!("{:.2}%", v * 100.0))
+}
+
+/// Per-regime trend (first, last, change, range, sparkline) followed by the
+/// runs themselves, oldest first.
+pub fn render_report(table: &CohortTable, format: CohortReportFormat) -> String {
+    let rows = table.chronological();
+    let regimes = table.regime_columns();
+    let cell = |row: &[String], name: &str| {
+        table
+            .column(name)
+            .map_or_else(|| NA.to_string(), |i| row[i].clone())
+    };
+
+    let mut trends = Table {
+        title: "Regime trends",
+        header: [
+            "Regime", "Runs", "First", "Last", "Change", "Min", "Max", "Trend",
+        ]
+        .map(str::to_string)
+        .to_vec(),
+        rows: Vec::new(),
+    };
+    for (regime, col) in &regimes {
+        let values: Vec<f32> = rows
+            .iter()
+            .filter_map(|r| r[*col].parse::<f32>().ok())
+            .filter(|v| v.is_finite())
+            .collect();
+        let (Some(first), Some(last)) = (values.first(), values.last()) else {
+            trends.rows.push(vec![
+                regime.to_string(),
+                "0".to_string(),
+                NA.to_string(),
+                NA.to_string(),
+                NA.to_string(),
+                NA.to_string(),
+                NA.to_string(),
+                String::new(),
+            ]);
+            continue;
+        };
+        let min = values.iter().copied().fold(f32::INFINITY, f32::min);
+        let max = values.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        let per_mille: Vec<u32> = values.iter().map(|v| (v * 1000.0).round() as u32).collect();
+        trends.rows.push(vec![
+            regime.to_string(),
+            values.len().to_string(),
+            format!("{:.2}%", first * 100.0),
+            format!("{:.2}%", last * 100.0),
+            format!("{:+.2} pp", (last - first) * 100.0),
+            format!("{:.2}%", min * 100.0),
+            format!("{:.2}%", max * 100.0),
+            sparkline(&per_mille, false),
+        ]);
+    }
+
+    let mut runs = Table {
+        title: "Runs",
+        header: ["Timestamp", "Label", "Cells", "Low confidence"]
+            .map(str::to_string)
+            .to_vec(),
+        rows: Vec::new(),
+    };
+    runs.header
+        .extend(regimes.iter().map(|(regime, _)| regime.to_string()));
+    for row in &rows {
+        let mut out = vec![
+            cell(row, "timestamp"),
+            cell(row, "label"),
+            cell(row, "n_cells"),
+            pct(&cell(row, "low_confidence_fraction")),
+        ];
+        out.extend(regimes.iter().map(|(_, col)| pct(&row[*col])));
+        runs.rows.push(out);
+    }
+
+    let tables = [trends, runs];
+    match format {
+        CohortReportFormat::Md => render_markdown(&tables, rows.len()),
+        CohortReportFormat::Html => render_html(&tables, rows.len()),
+    }
+}
+
+const TITLE: &str = "Kira Secretion Cohort Report";
+
+fn render_markdown(tables: &[Table], n_runs: usize) -> String {
+    let mut out = format!("# {TITLE}\n\n{n_runs} run(s).\n\n");
+    let push_row = |out: &mut String, cells: &[String]| {
+        out.push('|');
+        for cell in cells {
+            out.push(' ');
+            out.push_str(&markdown::escape_cell(cell));
+            out.push_str(" |");
+        }
+        out.push('\n');
+    };
+    for table in tables {
+        out.push_str(&format!("## {}\n\n", table.title));
+        push_row(&mut out, &table.header);
+        push_row(&mut out, &vec!["---".to_string(); table.header.len()]);
+        for row in &table.rows {
+            push_row(&mut out, row);
+        }
+        out.push('\n');
+    }
+    out
+}
+
+fn render_html(tables: &[Table], n_runs: usize) -> String {
+    let mut out = String::new();
+    out.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    out.push_str(&format!("<title>{TITLE}</title>\n</head>\n<body>\n"));
+    out.push_str(&format!("<h1>{TITLE}</h1>\n<p>{n_runs} run(s).</p>\n"));
+    for table in tables {
+        out.push_str(&format!("<h2>{}</h2>\n<table>\n<thead>\n<tr>", table.title));
+        for cell in &table.header {
+            out.push_str(&format!("<th>{}</th>", html::escape(cell)));
+        }
+        out.push_str("</tr>\n</thead>\n<tbody>\n");
+        for row in &table.rows {
+            out.push_str("<tr>");
+            for cell in row {
+                out.push_str(&format!("<td>{}</td>", html::escape(cell)));
+            }
+            out.push_str("</tr>\n");
+        }
+        out.push_str("</tbody>\n</table>\n");
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+/// `SystemTime` as an RFC 3339 UTC timestamp with second precision.
+pub fn utc_timestamp(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let (days, rem) = (secs / 86_400, secs % 86_400);
+    // Civil date from days since 1970-01-01 (H. Hinnant's algorithm).
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        rem / 3_600,
+        rem % 3_600 / 60,
+        rem % 60
+    )
+}
+
+#[cfg(test)]
+#[path = "../../tests/src_inline/aggregate/cohort.rs"]
+mod tests;
@@ -0,0 +1,163 @@
+use serde::Serialize;
+
+/// Default minimum number of cells each group needs before it is compared.
+pub const DEFAULT_MIN_CELLS_TO_COMPARE: usize = 20;
+
+/// Two-proportion z-test of one regime between the two groups.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct ProportionTest {
+    pub fraction_a: f64,
+    pub fraction_b: f64,
+    pub z: f64,
+    pub p_value: f64,
+}
+
+/// Mann–Whitney U of one metric between the two groups; `u` is the statistic
+/// of group a, counted over the non-NaN values only.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct RankTest {
+    pub n_a: usize,
+    pub n_b: usize,
+    pub u: f64,
+    pub z: f64,
+    pub p_value: f64,
+}
+
+/// Comparison of two groups, or the reason it was skipped.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GroupComparison {
+    /// Group names and cell counts, in comparison order (a, then b).
+    pub groups: Vec<(String, usize)>,
+    pub min_cells: usize,
+    pub skipped: Option<String>,
+    pub regimes: Vec<(String, ProportionTest)>,
+    /// `None` for a metric with no non-NaN value in one of the groups.
+    pub metrics: Vec<(String, Option<RankTest>)>,
+}
+
+impl GroupComparison {
+    pub fn skipped(groups: Vec<(String, usize)>, min_cells: usize, reason: String) -> Self {
+        Self {
+            groups,
+            min_cells,
+            skipped: Some(reason),
+            regimes: Vec::new(),
+            metrics: Vec::new(),
+        }
+    }
+
+    /// Number of p-values reported, for the multiple-testing note.
+    pub fn n_tests(&self) -> usize {
+        self.regimes.len() + self.metrics.iter().filter(|(_, t)| t.is_some()).count()
+    }
+}
+
+/// Why `groups` cannot be compared: not exactly two of them, or one below
+/// `min_cells`.
+pub fn skip_reason(groups: &[(String, usize)], min_cells: usize) -> Option<String> {
+    if groups.len() != 2 {
+        return Some(format!("needs exactly two groups, found {}", groups.len()));
+    }
+    groups
+        .iter()
+        .find(|(_, n)| *n < min_cells)
+        .map(|(name, n)| format!("group {name} has {n} cells, fewer than {min_cells}"))
+}
+
+/// Pooled two-proportion z-test of `k_a / n_a` against `k_b / n_b`. A pooled
+/// proportion of 0 or 1 has no variance and gives `z = 0`, `p = 1`.
+pub fn two_proportion_z(k_a: usize, n_a: usize, k_b: usize, n_b: usize) -> ProportionTest {
+    let fraction = |k: usize, n: usize| if n == 0 { 0.0 } else { k as f64 / n as f64 };
+    let fraction_a = fraction(k_a, n_a);
+    let fraction_b = fraction(k_b, n_b);
+    let pooled = fraction(k_a + k_b, n_a + n_b);
+    let variance = pooled * (1.0 - pooled) * (1.0 / n_a as f64 + 1.0 / n_b as f64);
+    let z = if variance > 0.0 && variance.is_finite() {
+        (fraction_a - fraction_b) / variance.sqrt()
+    } else {
+        0.0
+    };
+    ProportionTest {
+        fraction_a,
+        fraction_b,
+        z,
+        p_value: two_sided_p(z),
+    }
+}
+
+/// Mann–Whitney U of `a` against `b` with midranks for ties and the
+/// tie-corrected normal approximation (no continuity correction). NaN values
+/// are dropped; `None` when either side has nothing left.
+pub fn mann_whitney_u(a: &[f32], b: &[f32]) -> Option<RankTest> {
+    let mut pooled: Vec<(f32, bool)> = a
+        .iter()
+        .map(|&v| (v, true))
+        .chain(b.iter().map(|&v| (v, false)))
+        .filter(|(v, _)| !v.is_nan())
+        .collect();
+    let n_a = pooled.iter().filter(|(_, in_a)| *in_a).count();
+    let n_b = pooled.len() - n_a;
+    if n_a == 0 || n_b == 0 {
+        return None;
+    }
+    pooled.sort_by(|x, y| x.0.total_cmp(&y.0));
+
+    let mut rank_sum_a = 0.0f64;
+    let mut tie_term = 0.0f64;
+    let mut start = 0;
+    while start < pooled.len() {
+        let mut end = start + 1;
+        while end < pooled.len() && pooled[end].0 == pooled[start].0 {
+            end += 1;
+        }
+        // Ranks start + 1 ..= end share their mean.
+        let midrank = (start + 1 + end) as f64 / 2.0;
+        let in_a = pooled[start..end].iter().filter(|(_, in_a)| *in_a).count();
+        rank_sum_a += midrank * in_a as f64;
+        let t = (end - start) as f64;
+        tie_term += t * t * t - t;
+        start = end;
+    }
+
+    let (na, nb) = (n_a as f64, n_b as f64);
+    let n = na + nb;
+    let u = rank_sum_a - na * (na + 1.0) / 2.0;
+    let mean = na * nb / 2.0;
+    let variance = na * nb / 12.0 * ((n + 1.0) - tie_term / (n * (n - 1.0)));
+    let z = if variance > 0.0 {
+        (u - mean) / variance.sqrt()
+    } else {
+        0.0
+    };
+    Some(RankTest {
+        n_a,
+        n_b,
+        u,
+        z,
+        p_value: two_sided_p(z),
+    })
+}
+
+/// Two-sided p-value of a standard normal `z`, `erfc(|z| / sqrt(2))`.
+pub fn two_sided_p(z: f64) -> f64 {
+    erfc(z.abs() / std::f64::consts::SQRT_2).min(1.0)
+}
+
+/// Complementary error function for `x >= 0` (Chebyshev fit, relative error
+/// below 1.2e-7); no sampling or iteration, so reruns agree exactly.
+fn erfc(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.5 * x);
+    let poly = -x * x - 1.265_512_23
+        + t * (1.000_023_68
+            + t * (0.374_091_96
+                + t * (0.096_784_18
+                    + t * (-0.186_288_06
+                        + t * (0.278_868_07
+                            + t * (-1.135_203_98
+                                + t * (1.488_515_87 + t * (-0.822_152_23 + t * 0.170_872_77))))))));
+    t * poly.exp()
+}
+
+#[cfg(test)]
+#[path = "../../tests/src_inline/aggregate/compare.rs"]
+mod tests;
@@ -0,0 +1,97 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::aggregate::sample::ge_fraction;
+
+/// Combinations listed individually; the rest are pooled under [`OTHER_COMBINATIONS`].
+pub const MAX_FLAG_COMBINATIONS: usize = 20;
+
+/// Key of the pooled entry for combinations past [`MAX_FLAG_COMBINATIONS`].
+pub const OTHER_COMBINATIONS: &str = "other";
+
+/// Cells carrying exactly one combination of flags.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FlagCombination {
+    /// Canonical key, see [`combination_key`].
+    pub flags: String,
+    pub n_cells: usize,
+    /// Share of all cells, unflagged ones included.
+    pub fraction: f32,
+}
+
+impl FlagCombination {
+    /// One-line reading of the combination for the reports, if it is a known one.
+    pub fn interpretation(&self) -> Option<&'static str> {
+        interpretation(&self.flags)
+    }
+}
+
+/// Flag names sorted and joined by `+`, so the key of a combination does not
+/// depend on the order flags were set in; `None` for an unflagged cell.
+pub fn combination_key(flags: &[&str]) -> Option<String> {
+    if flags.is_empty() {
+        return None;
+    }
+    let mut sorted = flags.to_vec();
+    sorted.sort_unstable();
+    sorted.dedup();
+    Some(sorted.join("+"))
+}
+
+/// Counts flagged cells per combination key, most frequent first (ties by
+/// key). Past [`MAX_FLAG_COMBINATIONS`] the remaining combinations are
+/// pooled into one trailing [`OTHER_COMBINATIONS`] entry.
+pub fn count_combinations(
+    keys: impl IntoIterator<Item = Option<String>>,
+    n_cells: usize,
+) -> Vec<FlagCombination> {
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    for key in keys.into_iter().flatten() {
+        *counts.entry(key).or_default() += 1;
+    }
+    let mut sorted: Vec<(String, usize)> = counts.into_iter().collect();
+    sorted.sort_by(|(ka, a), (kb, b)| b.cmp(a).then_with(|| ka.cmp(kb)));
+
+    let pooled: usize = sorted
+        .iter()
+        .skip(MAX_FLAG_COMBINATIONS)
+        .map(|(_, n)| n)
+        .sum();
+    let has_other = sorted.len() > MAX_FLAG_COMBINATIONS;
+    sorted.truncate(MAX_FLAG_COMBINATIONS);
+    if has_other {
+        sorted.push((OTHER_COMBINATIONS.to_string(), pooled));
+    }
+    sorted
+        .into_iter()
+        .map(|(flags, n)| FlagCombination {
+            flags,
+            n_cells: n,
+            fraction: ge_fraction(n, n_cells),
+        })
+        .collect()
+}
+
+/// Triage reading of a combination key; the first matching rule wins.
+pub fn interpretation(key: &str) -> Option<&'static str> {
+    let flags: Vec<&str> = key.split('+').collect();
+    let has = |name: &str| flags.contains(&name);
+    if key == OTHER_COMBINATIONS {
+        None
+    } else if has("HIGH_AMBIENT_RISK") && has("FEW_DETECTED_GENES") {
+        Some("likely empty droplets or ambient-only barcodes; consider filtering")
+    } else if key == "LOW_COUNTS" {
+        Some("shallow sequencing depth; scores are valid but noisy")
+    } else if has("LOW_CONFIDENCE") && !has("LOW_COUNTS") {
+        Some("panel coverage problem, not depth; check panel gene mapping")
+    } else if key == "EXTREME_NORMALIZATION" {
+        Some("tiny library where single counts dominate; treat scores as unreliable")
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+#[path = "../../tests/src_inline/aggregate/flag_combos.rs"]
+mod tests;
@@ -1 +1,4 @@
+pub mod cohort;
+pub mod compare;
+pub mod flag_combos;
 pub mod sample;
@@ -2,28 +2,50 @@ use std::collections::BTreeMap;
 
 use crate::model::flags::Flags;
 use crate::model::regimes::Regime;
+use crate::model::stats::{NanPolicy, percentiles_interpolated};
 
-pub fn median_f32(values: &mut [f32]) -> f32 {
-    values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
-    percentile(values, 0.5)
+/// Regime label written for samples below the `min_cells_for_stats` gate.
+pub const INSUFFICIENT_CELLS: &str = "INSUFFICIENT_CELLS";
+
+/// Default minimum number of cells a sample needs before its statistics are reported.
+pub const DEFAULT_MIN_CELLS_FOR_STATS: usize = 10;
+
+/// Median and IQR (p75 - p25) of one metric within a sample.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MetricSummary {
+    pub median: f32,
+    pub iqr: f32,
 }
 
-pub fn median_ignore_nan(values: &mut Vec<f32>) -> f32 {
-    values.retain(|v| !v.is_nan());
-    if values.is_empty() {
-        return f32::NAN;
+/// Summarizes one metric, or `None` when fewer than `min_cells` non-NaN values
+/// remain, so NaN-heavy samples are gated like small ones.
+pub fn gated_metric(values: &[f32], min_cells: usize) -> Option<MetricSummary> {
+    let n_finite = values.iter().filter(|v| !v.is_nan()).count();
+    if n_finite == 0 || n_finite < min_cells {
+        return None;
     }
-    values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
-    percentile(values, 0.5)
+    let q = percentiles_interpolated(values, &[0.25, 0.5, 0.75], NanPolicy::Skip);
+    Some(MetricSummary {
+        median: q[1],
+        iqr: q[2] - q[0],
+    })
 }
 
-fn percentile(values: &[f32], p: f32) -> f32 {
-    if values.is_empty() {
-        return f32::NAN;
+/// Per cut-off, how many `values` are at or above it; NaN never counts.
+pub fn count_ge(values: &[f32], cutoffs: &[f32]) -> Vec<usize> {
+    cutoffs
+        .iter()
+        .map(|&t| values.iter().filter(|&&v| v >= t).count())
+        .collect()
+}
+
+/// `n_ge / n_cells`, or 0 for an empty group so summaries never carry NaN.
+pub fn ge_fraction(n_ge: usize, n_cells: usize) -> f32 {
+    if n_cells == 0 {
+        0.0
+    } else {
+        n_ge as f32 / n_cells as f32
     }
-    let n = values.len();
-    let idx = ((p * (n as f32 - 1.0)).floor() as usize).min(n - 1);
-    values[idx]
 }
 
 pub fn majority_regime(regimes: &[Regime]) -> Regime {
@@ -54,6 +76,7 @@ pub fn flags_from_fraction(flags: &[Flags], threshold: f32) -> Flags {
         Flags::FEW_DETECTED_GENES,
         Flags::LOW_COUNTS,
         Flags::HIGH_AMBIENT_RISK,
+        Flags::EXTREME_NORMALIZATION,
     ];
     for bit in bits {
         let c = flags.iter().filter(|f| f.contains(bit)).count() as f32;
@@ -63,3 +86,7 @@ pub fn flags_from_fraction(flags: &[Flags], threshold: f32) -> Flags {
     }
     out
 }
+
+#[cfg(test)]
+#[path = "../../tests/src_inline/aggregate/sample.rs"]
+mod tests;
@@ -0,0 +1,199 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use clap::Args;
+use tracing::info;
+
+use crate::input::mtx::{MatrixEntries, parse_coordinate_entries};
+use crate::input::{is_gz, open_mtx_reader, open_reader};
+use crate::pipeline::resources::per_sec;
+use crate::report::artifact::{
+    Artifact, DEFAULT_IO_BUFFER_SIZE, WriteOptions, enter_write_options,
+};
+
+/// Buffer size of the `artifact-write-small` baseline (the std default).
+const SMALL_BUFFER_SIZE: usize = 8 * 1024;
+
+#[derive(Args, Debug)]
+pub struct BenchArgs {
+    /// Matrix Market file to parse (`matrix.mtx` or `matrix.mtx.gz`)
+    #[arg(long)]
+    input: PathBuf,
+
+    /// Timed repetitions per scenario; the best time is reported
+    #[arg(long, default_value_t = 3)]
+    repeat: usize,
+
+    /// Synthetic cells written per repetition by the artifact-write scenarios
+    #[arg(long, default_value_t = 500_000)]
+    artifact_cells: usize,
+
+    /// Buffer size (bytes) of the `artifact-write` scenario
+    #[arg(long, default_value_t = DEFAULT_IO_BUFFER_SIZE as u64, value_parser = clap::value_parser!(u64).range(1..))]
+    io_buffer_size: u64,
+
+    /// Directory for the artifact-write scenarios [default: a directory under
+    /// the system temp dir, removed afterwards]
+    #[arg(long, value_name = "DIR")]
+    scratch_dir: Option<PathBuf>,
+}
+
+pub fn handle(args: BenchArgs) -> anyhow::Result<()> {
+    let repeat = args.repeat.max(1);
+    let serial = time_scenario(repeat, || {
+        parse_coordinate_entries(open_reader(&args.input)?).map_err(Into::into)
+    })?;
+    report("mtx-serial", &serial);
+    bench_pipelined(&args, repeat, &serial)?;
+    bench_artifact_write(&args, repeat)
+}
+
+fn bench_pipelined(args: &BenchArgs, repeat: usize, serial: &ScenarioResult) -> anyhow::Result<()> {
+    if !is_gz(&args.input) {
+        println!("mtx-pipelined\tskipped (input is not .gz)");
+        return Ok(());
+    }
+    if !cfg!(feature = "gz-parallel") {
+        println!("mtx-pipelined\tskipped (build with --features gz-parallel)");
+        return Ok(());
+    }
+    let pipelined = time_scenario(repeat, || {
+        parse_coordinate_entries(open_mtx_reader(&args.input)?).map_err(Into::into)
+    })?;
+    report("mtx-pipelined", &pipelined);
+
+    let identical = match (&serial.entries, &pipelined.entries) {
+        (Some(a), Some(b)) => a.1 == b.1,
+        _ => false,
+    };
+    println!(
+        "speedup\t{:.2}x\tidentical\t{}",
+        serial.best_ms / pipelined.best_ms.max(1e-6),
+        identical
+    );
+    if !identical {
+        anyhow::bail!("pipelined gzip output differs from serial output");
+    }
+    Ok(())
+}
+
+struct ScenarioResult {
+    best_ms: f64,
+    entries: Option<MatrixEntries>,
+}
+
+fn time_scenario<F>(repeat: usize, mut run: F) -> anyhow::Result<ScenarioResult>
+where
+    F: FnMut() -> anyhow::Result<MatrixEntries>,
+{
+    let mut best_ms = f64::INFINITY;
+    let mut entries = None;
+    for _ in 0..repeat {
+        let start = Instant::now();
+        let parsed = run()?;
+        best_ms = best_ms.min(start.elapsed().as_secs_f64() * 1000.0);
+        entries = Some(parsed);
+    }
+    Ok(ScenarioResult { best_ms, entries })
+}
+
+/// Logs with the field names `run` uses for stage throughput.
+fn report(name: &str, result: &ScenarioResult) {
+    let nnz = result.entries.as_ref().map_or(0, |(h, _)| h.nnz);
+    let nnz_per_sec = per_sec(nnz, Duration::from_secs_f64(result.best_ms / 1000.0));
+    info!(
+        scenario = name,
+        best_ms = result.best_ms,
+        nnz,
+        nnz_per_sec,
+        "bench scenario"
+    );
+    println!(
+        "{}\t{:.1} ms\tnnz\t{}\tnnz_per_sec\t{:.0}",
+        name,
+        result.best_ms,
+        nnz,
+        nnz_per_sec.unwrap_or(f64::NAN)
+    );
+}
+
+/// Writes a secretion.tsv-shaped table for a large synthetic cohort through
+/// [`Artifact`], once with an 8 KiB buffer and once with `--io-buffer-size`.
+fn bench_artifact_write(args: &BenchArgs, repeat: usize) -> anyhow::Result<()> {
+    let (dir, owned) = match &args.scratch_dir {
+        Some(dir) => (dir.clone(), false),
+        None => (
+            std::env::temp_dir().join(format!("kira-secretion-bench-{}", std::process::id())),
+            true,
+        ),
+    };
+    std::fs::create_dir_all(&dir)?;
+    let result = (|| {
+        let small = time_artifact_write(&dir, repeat, args.artifact_cells, SMALL_BUFFER_SIZE)?;
+        report_write("artifact-write-small", SMALL_BUFFER_SIZE, small);
+        let large = time_artifact_write(
+            &dir,
+            repeat,
+            args.artifact_cells,
+            args.io_buffer_size as usize,
+        )?;
+        report_write("artifact-write", args.io_buffer_size as usize, large);
+        println!("speedup\t{:.2}x", small.0 / large.0.max(1e-6));
+        Ok(())
+    })();
+    if owned {
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+    result
+}
+
+/// Best wall time (ms) and bytes written per repetition.
+fn time_artifact_write(
+    dir: &Path,
+    repeat: usize,
+    n_cells: usize,
+    buffer_size: usize,
+) -> anyhow::Result<(f64, u64)> {
+    let _options = enter_write_options(WriteOptions {
+        buffer_size,
+        ..WriteOptions::default()
+    });
+    let mut best_ms = f64::INFINITY;
+    let mut bytes = 0;
+    for _ in 0..repeat {
+        let start = Instant::now();
+        let mut artifact = Artifact::create(dir, "bench_secretion.tsv")?;
+        writeln!(
+            artifact,
+            "barcode\tregime\tconfidence\tSIA\tSCM\tGDI\tflags"
+        )?;
+        for cell in 0..n_cells {
+            let x = (cell % 997) as f32 / 997.0;
+            writeln!(
+                artifact,
+                "CELL{:08}-1\tBalanced\t{:.6}\t{:.6}\t{:.6}\t{:.6}\t",
+                cell,
+                x,
+                1.0 - x,
+                x * 0.5,
+                x * x
+            )?;
+        }
+        artifact.commit()?;
+        best_ms = best_ms.min(start.elapsed().as_secs_f64() * 1000.0);
+        bytes = std::fs::metadata(dir.join("bench_secretion.tsv"))?.len();
+    }
+    Ok((best_ms, bytes))
+}
+
+fn report_write(name: &str, buffer_size: usize, (best_ms, bytes): (f64, u64)) {
+    info!(
+        scenario = name,
+        best_ms, buffer_size, bytes, "bench scenario"
+    );
+    println!(
+        "{}\t{:.1} ms\tbuffer\t{}\tbytes\t{}",
+        name, best_ms, buffer_size, bytes
+    );
+}
@@ -0,0 +1,61 @@
+use std::path::PathBuf;
+
+use clap::{Args, Subcommand, ValueEnum};
+
+use crate::aggregate::cohort::{CohortReportFormat, CohortTable, render_report};
+
+#[derive(Args, Debug)]
+pub struct CohortArgs {
+    #[command(subcommand)]
+    command: CohortCommand,
+}
+
+#[derive(Subcommand, Debug)]
+enum CohortCommand {
+    /// Render per-regime fraction trends from a `run --append-cohort` TSV
+    Report(CohortReportArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct CohortReportArgs {
+    /// Cohort TSV written by `run --append-cohort`
+    file: PathBuf,
+
+    #[arg(long, value_enum, default_value = "md")]
+    format: CohortFormatArg,
+
+    /// Write the report here instead of stdout
+    #[arg(long)]
+    out: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum CohortFormatArg {
+    Md,
+    Html,
+}
+
+impl From<CohortFormatArg> for CohortReportFormat {
+    fn from(value: CohortFormatArg) -> Self {
+        match value {
+            CohortFormatArg::Md => CohortReportFormat::Md,
+            CohortFormatArg::Html => CohortReportFormat::Html,
+        }
+    }
+}
+
+pub fn handle(args: CohortArgs) -> anyhow::Result<()> {
+    match args.command {
+        CohortCommand::Report(args) => report(args),
+    }
+}
+
+fn report(args: CohortReportArgs) -> anyhow::Result<()> {
+    let table = CohortTable::read(&args.file)?;
+    let rendered = render_report(&table, args.format.into());
+    match &args.out {
+        Some(path) => std::fs::write(path, rendered)?,
+        None => print!("{rendered}"),
+    }
+    Ok(())
+}
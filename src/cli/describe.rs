@@ -0,0 +1,46 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use clap::{Args, ValueEnum};
+
+use crate::model::catalog::Catalog;
+use crate::report::artifact::write_artifact;
+
+#[derive(Args, Debug)]
+pub struct DescribeArgs {
+    /// Output layout
+    #[arg(long, value_enum, default_value = "markdown")]
+    format: DescribeFormatArg,
+
+    /// Write to this file instead of stdout (which also carries the logs)
+    #[arg(long)]
+    out: Option<PathBuf>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DescribeFormatArg {
+    Markdown,
+    Json,
+}
+
+pub fn handle(args: DescribeArgs) -> anyhow::Result<()> {
+    let catalog = Catalog::current();
+    let text = match args.format {
+        DescribeFormatArg::Markdown => catalog.to_markdown(),
+        DescribeFormatArg::Json => serde_json::to_string_pretty(&catalog)? + "\n",
+    };
+    let Some(out) = args.out else {
+        print!("{text}");
+        return Ok(());
+    };
+    let dir = out
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let name = out
+        .file_name()
+        .and_then(|n| n.to_str())
+        .context("--out needs a UTF-8 file name")?;
+    write_artifact(dir, name, text)?;
+    Ok(())
+}
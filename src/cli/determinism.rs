@@ -0,0 +1,268 @@
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use clap::{Args, Parser};
+use tracing::info;
+
+use super::Cli;
+use crate::pipeline::cancel::CancellationToken;
+
+/// Bytes shown per side around the first difference.
+const HEXDUMP_BYTES: usize = 16;
+
+/// Written during the run with wall-clock timestamps; never compared.
+const SKIPPED_FILES: [&str; 1] = ["progress.json"];
+
+#[derive(Args, Debug)]
+pub struct VerifyDeterminismArgs {
+    /// 10x directory (or shared cache directory with `--run-mode pipeline`
+    /// among the run arguments) [default: a synthetic dataset; needs a build
+    /// with the `testing` feature]
+    #[arg(long)]
+    input: Option<PathBuf>,
+
+    /// Worker threads for the two runs, e.g. `1,8`; both use rayon's default
+    /// when not given
+    #[arg(long, value_name = "A,B", value_parser = parse_thread_pair)]
+    threads: Option<[u32; 2]>,
+
+    /// Directory for the two runs' outputs [default: a directory under the
+    /// system temp dir, removed afterwards]
+    #[arg(long, value_name = "DIR")]
+    scratch_dir: Option<PathBuf>,
+
+    /// Extra `run` arguments for both runs, after `--`
+    #[arg(last = true, value_name = "RUN_ARGS")]
+    run_args: Vec<String>,
+}
+
+fn parse_thread_pair(value: &str) -> Result<[u32; 2], String> {
+    let parse = |n: &str| match n.trim().parse::<u32>() {
+        Ok(n) if n > 0 => Ok(n),
+        _ => Err(format!("'{n}' is not a positive thread count")),
+    };
+    match value.split_once(',') {
+        Some((a, b)) => Ok([parse(a)?, parse(b)?]),
+        None => Err("expected two thread counts, e.g. 1,8".to_string()),
+    }
+}
+
+/// How one artifact compares between the two runs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Comparison {
+    Identical,
+    /// Bytes from `offset` on, up to [`HEXDUMP_BYTES`] per run.
+    Differs {
+        offset: usize,
+        a: Vec<u8>,
+        b: Vec<u8>,
+    },
+    OnlyIn(char),
+}
+
+pub fn handle(args: VerifyDeterminismArgs, token: &CancellationToken) -> anyhow::Result<()> {
+    let (scratch, owned) = match &args.scratch_dir {
+        Some(dir) => (dir.clone(), false),
+        None => (
+            std::env::temp_dir().join(format!("kira-secretion-determinism-{}", std::process::id())),
+            true,
+        ),
+    };
+    std::fs::create_dir_all(&scratch)?;
+    let result = verify(&args, &scratch, token);
+    if owned {
+        let _ = std::fs::remove_dir_all(&scratch);
+    }
+    result
+}
+
+fn verify(
+    args: &VerifyDeterminismArgs,
+    scratch: &Path,
+    token: &CancellationToken,
+) -> anyhow::Result<()> {
+    let input = match &args.input {
+        Some(input) => input.clone(),
+        None => synthetic_input(scratch)?,
+    };
+    let threads = match args.threads {
+        Some([a, b]) => [Some(a), Some(b)],
+        None => [None, None],
+    };
+    for (run, threads) in ['a', 'b'].into_iter().zip(threads) {
+        let out = scratch.join(format!("run_{run}"));
+        info!(run = %run, threads = ?threads, out = %out.display(), "determinism run");
+        run_once(&input, &out, threads, &args.run_args, token)?;
+    }
+
+    let comparisons = compare_dirs(&scratch.join("run_a"), &scratch.join("run_b"))?;
+    print!("{}", render(&comparisons));
+    let failed = comparisons
+        .values()
+        .filter(|c| **c != Comparison::Identical)
+        .count();
+    if failed > 0 {
+        anyhow::bail!(
+            "{failed} of {} artifacts differ between the two runs",
+            comparisons.len()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(feature = "testing")]
+fn synthetic_input(scratch: &Path) -> anyhow::Result<PathBuf> {
+    use crate::testing::{SyntheticDataset, SyntheticParams};
+
+    let dir = scratch.join("input");
+    SyntheticDataset::generate(&SyntheticParams::default(), 0x5EC7).write_mtx(&dir)?;
+    Ok(dir)
+}
+
+#[cfg(not(feature = "testing"))]
+fn synthetic_input(_scratch: &Path) -> anyhow::Result<PathBuf> {
+    Err(clap::Error::raw(
+        clap::error::ErrorKind::MissingRequiredArgument,
+        "--input is required: this build has no synthetic dataset (build with --features testing)\n",
+    )
+    .into())
+}
+
+/// One `run` through the library entry point, on a dedicated pool of
+/// `threads` workers when given (the global pool can only be sized once).
+fn run_once(
+    input: &Path,
+    out: &Path,
+    threads: Option<u32>,
+    run_args: &[String],
+    token: &CancellationToken,
+) -> anyhow::Result<()> {
+    let mut argv: Vec<OsString> = ["kira-secretion", "run", "--force", "--input"]
+        .map(OsString::from)
+        .to_vec();
+    argv.push(input.into());
+    argv.push("--out".into());
+    argv.push(out.into());
+    argv.extend(run_args.iter().map(OsString::from));
+    let cli = Cli::try_parse_from(argv)?;
+    match threads {
+        Some(n) => rayon::ThreadPoolBuilder::new()
+            .num_threads(n as usize)
+            .build()?
+            .install(|| cli.dispatch_with(token)),
+        None => cli.dispatch_with(token),
+    }
+}
+
+/// Every file under `a` and `b` (relative paths), except [`SKIPPED_FILES`].
+fn compare_dirs(a: &Path, b: &Path) -> std::io::Result<BTreeMap<String, Comparison>> {
+    let files_a = artifacts(a)?;
+    let mut files_b = artifacts(b)?;
+    let mut out = BTreeMap::new();
+    for (name, bytes_a) in files_a {
+        let comparison = match files_b.remove(&name) {
+            Some(bytes_b) => compare_bytes(&bytes_a, &bytes_b),
+            None => Comparison::OnlyIn('a'),
+        };
+        out.insert(name, comparison);
+    }
+    for name in files_b.into_keys() {
+        out.insert(name, Comparison::OnlyIn('b'));
+    }
+    Ok(out)
+}
+
+fn compare_bytes(a: &[u8], b: &[u8]) -> Comparison {
+    let offset = match a.iter().zip(b).position(|(x, y)| x != y) {
+        Some(offset) => offset,
+        None if a.len() == b.len() => return Comparison::Identical,
+        None => a.len().min(b.len()),
+    };
+    let window = |bytes: &[u8]| -> Vec<u8> {
+        bytes
+            .iter()
+            .skip(offset)
+            .take(HEXDUMP_BYTES)
+            .copied()
+            .collect()
+    };
+    Comparison::Differs {
+        offset,
+        a: window(a),
+        b: window(b),
+    }
+}
+
+fn artifacts(dir: &Path) -> std::io::Result<BTreeMap<String, Vec<u8>>> {
+    let mut out = BTreeMap::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        for entry in std::fs::read_dir(&current)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                pending.push(path);
+                continue;
+            }
+            let name = path
+                .strip_prefix(dir)
+                .unwrap_or(&path)
+                .to_string_lossy()
+                .replace('\\', "/");
+            if !SKIPPED_FILES.contains(&name.as_str()) {
+                out.insert(name, std::fs::read(&path)?);
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// PASS/FAIL table, then a hexdump at the first differing offset of each
+/// failing file.
+fn render(comparisons: &BTreeMap<String, Comparison>) -> String {
+    let mut out = String::from("file\tresult\tdetail\n");
+    for (name, comparison) in comparisons {
+        let detail = match comparison {
+            Comparison::Identical => "PASS\t.".to_string(),
+            Comparison::Differs { offset, .. } => format!("FAIL\tfirst difference at {offset:#x}"),
+            Comparison::OnlyIn(run) => format!("FAIL\tonly written by run {run}"),
+        };
+        let _ = writeln!(out, "{name}\t{detail}");
+    }
+    for (name, comparison) in comparisons {
+        if let Comparison::Differs { offset, a, b } = comparison {
+            let _ = writeln!(out, "\n{name} at {offset:#010x}:");
+            let _ = writeln!(out, "  a: {}", hexdump(a));
+            let _ = writeln!(out, "  b: {}", hexdump(b));
+        }
+    }
+    out
+}
+
+/// Hex bytes padded to [`HEXDUMP_BYTES`], then the printable ASCII.
+fn hexdump(bytes: &[u8]) -> String {
+    let mut out = String::new();
+    for i in 0..HEXDUMP_BYTES {
+        match bytes.get(i) {
+            Some(byte) => {
+                let _ = write!(out, "{byte:02x} ");
+            }
+            None => out.push_str("   "),
+        }
+    }
+    out.push('|');
+    out.extend(bytes.iter().map(|&b| {
+        if b.is_ascii_graphic() || b == b' ' {
+            b as char
+        } else {
+            '.'
+        }
+    }));
+    out.push('|');
+    out
+}
+
+#[cfg(test)]
+#[path = "../../tests/src_inline/cli/determinism.rs"]
+mod tests;
@@ -0,0 +1,42 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use clap::Args;
+
+use super::run::NanTokenArg;
+use crate::report::artifact::Artifact;
+use crate::report::binfmt::read_bin;
+
+#[derive(Args, Debug)]
+pub struct DumpBinArgs {
+    /// secretion.bin written by `run --format bin`
+    input: PathBuf,
+
+    /// TSV file to write (logs share stdout, so there is no stdout mode)
+    #[arg(long)]
+    out: PathBuf,
+
+    /// Token for missing values; pass the run's `--nan-token` to reproduce
+    /// its secretion.tsv byte for byte
+    #[arg(long, value_enum, default_value = "nan")]
+    nan_token: NanTokenArg,
+}
+
+pub fn handle(args: DumpBinArgs) -> anyhow::Result<()> {
+    let table =
+        read_bin(&args.input).with_context(|| format!("reading {}", args.input.display()))?;
+    let dir = args
+        .out
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let name = args
+        .out
+        .file_name()
+        .and_then(|n| n.to_str())
+        .context("--out needs a UTF-8 file name")?;
+    let mut artifact = Artifact::create(dir, name)?;
+    table.write_tsv(&mut artifact, args.nan_token.into())?;
+    artifact.commit()?;
+    Ok(())
+}
@@ -0,0 +1,285 @@
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::aggregate::cohort::CohortError;
+use crate::input::InputError;
+use crate::input::cache::CacheError;
+use crate::model::axis_curves::AxisCurvesError;
+use crate::model::pipeline_regime::RegimeLabelsError;
+use crate::model::qc_expectations::{QcExpectationsError, QcGateFailed};
+use crate::model::reference::ReferenceError;
+use crate::model::thresholds::ThresholdsError;
+use crate::panels::loader::PanelLoadError;
+use crate::pipeline::cancel::Cancelled;
+use crate::pipeline::explain::ExplainError;
+use crate::pipeline::reclassify::ReclassifyError;
+use crate::pipeline::stage1_load::Stage1Error;
+use crate::pipeline::stage2_normalize::Stage2Error;
+use crate::pipeline::stage3_panels::Stage3Error;
+use crate::pipeline::stage4_axes::Stage4Error;
+use crate::pipeline::stage5_scores::Stage5Error;
+use crate::pipeline::stage6_classify::Stage6Error;
+use crate::pipeline::stage7_report::Stage7Error;
+use crate::report::tsv::UnsafeField;
+
+pub const EXIT_CODES_HELP: &str = "\
+Exit codes:
+  0    success
+  2    invalid command-line usage (including conflicting options such as
+       --outputs summary-only with --run-mode pipeline)
+  3    input or validation error (malformed matrix/features/barcodes/meta, shared cache format,
+       tab/newline in a TSV string field without --lenient, malformed cohort TSV)
+  4    configuration error (panels, thresholds, weights, cohort reference,
+       regime labels, QC expectations, core axis without a mapped panel unless
+       --allow-missing-axes)
+  5    internal or I/O error
+  6    QC gate failed under --qc-gate-strict (all outputs are written; see qc_gate.json)
+  130  interrupted (run_aborted.json records the last completed stage)";
+
+/// Failure category reported through the process exit code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitCategory {
+    Usage,
+    Input,
+    Config,
+    Internal,
+    QcGate,
+    Cancelled,
+}
+
+impl ExitCategory {
+    pub fn code(self) -> u8 {
+        match self {
+            ExitCategory::Usage => 2,
+            ExitCategory::Input => 3,
+            ExitCategory::Config => 4,
+            ExitCategory::Internal => 5,
+            ExitCategory::QcGate => 6,
+            ExitCategory::Cancelled => 130,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ExitCategory::Usage => "usage",
+            ExitCategory::Input => "input",
+            ExitCategory::Config => "config",
+            ExitCategory::Internal => "internal",
+            ExitCategory::QcGate => "qc_gate",
+            ExitCategory::Cancelled => "cancelled",
+        }
+    }
+
+    /// Classifies by the outermost known error type in the chain; anything
+    /// unrecognized (including bare I/O errors on outputs) is internal.
+    /// Cancellation anywhere in the chain wins.
+    pub fn classify(err: &anyhow::Error) -> Self {
+        if err.chain().any(|cause| cause.is::<Cancelled>()) {
+            return ExitCategory::Cancelled;
+        }
+        for cause in err.chain() {
+            if cause.is::<clap::Error>() {
+                return ExitCategory::Usage;
+            }
+            if let Some(e) = cause.downcast_ref::<Stage1Error>() {
+                return match e {
+                    Stage1Error::Input(_)
+                    | Stage1Error::Cache(_)
+                    | Stage1Error::DimensionMismatch { .. }
+                    | Stage1Error::NnzMismatch { .. }
+                    | Stage1Error::MetaMismatch { .. }
+                    | Stage1Error::MetaStrict { .. } => ExitCategory::Input,
+                };
+            }
+            if let Some(e) = cause.downcast_ref::<Stage2Error>() {
+                return match e {
+                    Stage2Error::Input(_)
+                    | Stage2Error::CacheReopen { .. }
+                    | Stage2Error::CacheChanged { .. } => ExitCategory::Input,
+                    Stage2Error::Cancelled(_) => ExitCategory::Cancelled,
+                };
+            }
+            if let Some(e) = cause.downcast_ref::<Stage3Error>() {
+                return match e {
+                    Stage3Error::Input(_) | Stage3Error::Field(_) => ExitCategory::Input,
+                    Stage3Error::Io(_) | Stage3Error::Write { .. } => ExitCategory::Internal,
+                    Stage3Error::Cancelled(_) => ExitCategory::Cancelled,
+                };
+            }
+            if let Some(Stage4Error::MissingAxes { .. }) = cause.downcast_ref::<Stage4Error>() {
+                return ExitCategory::Config;
+            }
+            // Reading back our own secretion.tsv is not an input problem.
+            if let Some(Stage7Error::Consistency(_)) = cause.downcast_ref::<Stage7Error>() {
+                return ExitCategory::Internal;
+            }
+            if let Some(e) = cause.downcast_ref::<ReclassifyError>() {
+                return match e {
+                    ReclassifyError::Input(_)
+                    | ReclassifyError::CellMismatch { .. }
+                    | ReclassifyError::CellCount { .. } => ExitCategory::Input,
+                    ReclassifyError::Io(_)
+                    | ReclassifyError::Json(_)
+                    | ReclassifyError::Stage6(_)
+                    | ReclassifyError::Stage7(_) => ExitCategory::Internal,
+                };
+            }
+            if let Some(e) = cause.downcast_ref::<CohortError>() {
+                return match e {
+                    CohortError::Format { .. } => ExitCategory::Input,
+                    CohortError::Io { .. } => ExitCategory::Internal,
+                };
+            }
+            if cause.is::<QcGateFailed>() {
+                return ExitCategory::QcGate;
+            }
+            if cause.is::<InputError>() || cause.is::<CacheError>() || cause.is::<UnsafeField>() {
+                return ExitCategory::Input;
+            }
+            if cause.is::<PanelLoadError>()
+                || cause.is::<ReferenceError>()
+                || cause.is::<ThresholdsError>()
+                || cause.is::<AxisCurvesError>()
+                || cause.is::<RegimeLabelsError>()
+                || cause.is::<ExplainError>()
+                || cause.is::<QcExpectationsError>()
+            {
+                return ExitCategory::Config;
+            }
+        }
+        ExitCategory::Internal
+    }
+}
+
+/// What a `run` stage was doing when it failed. Attached to stage results
+/// with `with_context`, so the printed chain starts with the stage and path.
+#[derive(Debug)]
+pub struct StageContext {
+    pub stage: &'static str,
+    pub action: StageAction,
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StageAction {
+    CreateOutput,
+    ReadInput,
+    LoadPanels,
+    Write,
+    /// A stage that computes and writes into the output directory failed
+    /// for a reason other than a write.
+    Run,
+}
+
+impl StageContext {
+    pub fn new(stage: &'static str, action: StageAction, path: impl Into<PathBuf>) -> Self {
+        Self {
+            stage,
+            action,
+            path: path.into(),
+        }
+    }
+}
+
+impl fmt::Display for StageContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let action = match self.action {
+            StageAction::CreateOutput => "creating output directory",
+            StageAction::ReadInput => "reading input",
+            StageAction::LoadPanels => "loading panels from",
+            StageAction::Write => "writing to",
+            StageAction::Run => "running, output in",
+        };
+        write!(f, "{}: {} {}", self.stage, action, self.path.display())
+    }
+}
+
+/// Stage errors that can tell an artifact write failure from the rest.
+pub trait WriteFailure {
+    fn is_write(&self) -> bool;
+}
+
+impl WriteFailure for Stage3Error {
+    fn is_write(&self) -> bool {
+        matches!(self, Stage3Error::Write { .. })
+    }
+}
+
+impl WriteFailure for Stage4Error {
+    fn is_write(&self) -> bool {
+        matches!(self, Stage4Error::Write { .. })
+    }
+}
+
+impl WriteFailure for Stage5Error {
+    fn is_write(&self) -> bool {
+        matches!(self, Stage5Error::Write { .. })
+    }
+}
+
+impl WriteFailure for Stage6Error {
+    fn is_write(&self) -> bool {
+        matches!(self, Stage6Error::Write { .. })
+    }
+}
+
+impl WriteFailure for Stage7Error {
+    fn is_write(&self) -> bool {
+        matches!(self, Stage7Error::Write { .. })
+    }
+}
+
+/// `with_context` for a stage writing into `out_dir`: the action is
+/// [`StageAction::Write`] for write failures and [`StageAction::Run`] for
+/// everything else, so a configuration error does not read as an I/O one.
+pub trait OutputContext<T> {
+    fn output_context(self, stage: &'static str, out_dir: &Path) -> anyhow::Result<T>;
+}
+
+impl<T, E> OutputContext<T> for Result<T, E>
+where
+    E: WriteFailure + std::error::Error + Send + Sync + 'static,
+{
+    fn output_context(self, stage: &'static str, out_dir: &Path) -> anyhow::Result<T> {
+        self.map_err(|e| {
+            let action = if e.is_write() {
+                StageAction::Write
+            } else {
+                StageAction::Run
+            };
+            anyhow::Error::new(e).context(StageContext::new(stage, action, out_dir))
+        })
+    }
+}
+
+/// A fix-it line for a missing or unreadable input or panels directory.
+pub fn error_hint(err: &anyhow::Error) -> Option<&'static str> {
+    let ctx = err.downcast_ref::<StageContext>()?;
+    let kind = err.chain().find_map(|cause| {
+        if let Some(e) = cause.downcast_ref::<io::Error>() {
+            return Some(e.kind());
+        }
+        matches!(
+            cause.downcast_ref::<InputError>(),
+            Some(InputError::MissingFile { .. } | InputError::InvalidLayout { .. })
+        )
+        .then_some(io::ErrorKind::NotFound)
+    })?;
+    match (ctx.action, kind) {
+        (StageAction::LoadPanels, io::ErrorKind::NotFound) => Some(
+            "the panels directory does not exist; run from a directory containing assets/panels or install assets/panels next to the binary",
+        ),
+        (StageAction::ReadInput, io::ErrorKind::NotFound) => Some(
+            "check that --input is a 10x directory (matrix.mtx, features.tsv, barcodes.tsv) or, with --run-mode pipeline, contains the shared cache",
+        ),
+        (StageAction::LoadPanels | StageAction::ReadInput, io::ErrorKind::PermissionDenied) => {
+            Some("the directory is not readable by the current user; check its permissions")
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+#[path = "../../tests/src_inline/cli/exit.rs"]
+mod tests;
@@ -1,11 +1,29 @@
 use clap::{Parser, Subcommand};
+use thiserror::Error;
 
+use crate::pipeline::cancel::CancellationToken;
+
+mod bench;
+mod cohort;
+mod describe;
+mod determinism;
+mod dump_bin;
+mod exit;
 mod panels;
+mod reclassify;
 mod run;
 mod validate;
+mod verify;
+
+pub use exit::{ExitCategory, OutputContext, StageAction, StageContext, error_hint};
 
 #[derive(Parser, Debug)]
-#[command(name = "kira-secretion", version, about = "Kira Secretion CLI")]
+#[command(
+    name = "kira-secretion",
+    version,
+    about = "Kira Secretion CLI",
+    after_help = exit::EXIT_CODES_HELP
+)]
 pub struct Cli {
     #[command(subcommand)]
     command: Command,
@@ -13,19 +31,79 @@ pub struct Cli {
 
 #[derive(Subcommand, Debug)]
 enum Command {
-    Run(run::RunArgs),
+    Run(Box<run::RunArgs>),
     Validate(validate::ValidateArgs),
     Panels(panels::PanelsArgs),
+    Verify(verify::VerifyArgs),
+    /// Run the pipeline twice and byte-compare every artifact
+    VerifyDeterminism(determinism::VerifyDeterminismArgs),
+    /// Time matrix parsing, including the gzip decompression paths
+    Bench(bench::BenchArgs),
+    /// Rerun classification and reporting on a previous run's per-cell tables
+    Reclassify(reclassify::ReclassifyArgs),
+    /// Cohort tracking files written by `run --append-cohort`
+    Cohort(cohort::CohortArgs),
+    /// Convert a secretion.bin back to secretion.tsv
+    DumpBin(dump_bin::DumpBinArgs),
+    /// Print the axis and composite definitions as markdown or JSON
+    Describe(describe::DescribeArgs),
+}
+
+/// Outcome of [`Cli::run`] for callers embedding the pipeline.
+#[derive(Debug, Error)]
+pub enum PipelineError {
+    /// The token was cancelled; the interrupted stage's partial outputs were
+    /// removed and `run_aborted.json` written in place of `_SUCCESS`.
+    #[error("run cancelled")]
+    Cancelled,
+    #[error(transparent)]
+    Failed(anyhow::Error),
+}
+
+impl PipelineError {
+    pub fn category(&self) -> ExitCategory {
+        match self {
+            PipelineError::Cancelled => ExitCategory::Cancelled,
+            PipelineError::Failed(err) => ExitCategory::classify(err),
+        }
+    }
 }
 
 impl Cli {
+    /// Runs the command with a token nobody cancels.
     pub fn dispatch(self) -> anyhow::Result<()> {
+        self.dispatch_with(&CancellationToken::new())
+    }
+
+    /// Runs the command, stopping `run` at the next stage checkpoint once
+    /// `token` is cancelled.
+    pub fn dispatch_with(self, token: &CancellationToken) -> anyhow::Result<()> {
         match self.command {
-            Command::Run(args) => run::handle(args),
+            Command::Run(args) => run::handle(*args, token),
             Command::Validate(args) => validate::handle(args),
             Command::Panels(args) => panels::handle(args),
+            Command::Verify(args) => verify::handle(args),
+            Command::VerifyDeterminism(args) => determinism::handle(args, token),
+            Command::Bench(args) => bench::handle(args),
+            Command::Reclassify(args) => reclassify::handle(args),
+            Command::Cohort(args) => cohort::handle(args),
+            Command::DumpBin(args) => dump_bin::handle(args),
+            Command::Describe(args) => describe::handle(args),
         }
     }
+
+    /// Library entry point: [`Cli::dispatch_with`] with cancellation reported
+    /// as [`PipelineError::Cancelled`], e.g. for
+    /// `Cli::try_parse_from(["kira-secretion", "run", ...])?.run(&token)`.
+    pub fn run(self, token: &CancellationToken) -> Result<(), PipelineError> {
+        self.dispatch_with(token).map_err(|err| {
+            if ExitCategory::classify(&err) == ExitCategory::Cancelled {
+                PipelineError::Cancelled
+            } else {
+                PipelineError::Failed(err)
+            }
+        })
+    }
 }
 
 #[cfg(test)]
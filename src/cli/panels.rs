@@ -1,8 +1,10 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use clap::{Args, Subcommand};
 
-use crate::panels::loader::{default_panels_dir, load_panels_from_dir};
+use crate::panels::defs::PanelSet;
+use crate::panels::loader::{PanelLoadError, default_panels_dir, load_panels_from_dir};
+use crate::report::artifact::write_artifact;
 
 #[derive(Args, Debug)]
 pub struct PanelsArgs {
@@ -32,7 +34,7 @@ pub fn handle(args: PanelsArgs) -> anyhow::Result<()> {
 
 fn list_panels() -> anyhow::Result<()> {
     let dir = default_panels_dir();
-    let panels = load_panels_from_dir(&dir)?;
+    let panels = load_panels(&dir)?;
     println!("panel_id\taxis\tn_genes\tn_required");
     for panel in panels.panels {
         println!(
@@ -49,9 +51,20 @@ fn list_panels() -> anyhow::Result<()> {
 fn dump_panels(args: PanelsDumpArgs) -> anyhow::Result<()> {
     std::fs::create_dir_all(&args.out)?;
     let dir = default_panels_dir();
-    let panels = load_panels_from_dir(&dir)?;
+    let panels = load_panels(&dir)?;
     let json = serde_json::to_string_pretty(&panels)?;
-    let path = args.out.join("panels_manifest.json");
-    std::fs::write(path, json)?;
+    write_artifact(&args.out, "panels_manifest.json", json)?;
     Ok(())
 }
+
+/// Loads panels with the fail policy regardless of any run flag, so validation
+/// failures carry every issue, not only the first.
+fn load_panels(dir: &Path) -> anyhow::Result<PanelSet> {
+    match load_panels_from_dir(dir) {
+        Ok(panels) => Ok(panels),
+        Err(e @ PanelLoadError::Invalid(_)) => {
+            Err(anyhow::Error::new(e).context(format!("invalid panels in {}", dir.display())))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
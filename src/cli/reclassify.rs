@@ -0,0 +1,45 @@
+use std::path::PathBuf;
+
+use clap::Args;
+use tracing::info;
+
+use crate::model::thresholds::ThresholdsConfig;
+use crate::pipeline::reclassify::run_reclassify;
+use crate::pipeline::verify::run_verify;
+
+#[derive(Args, Debug)]
+pub struct ReclassifyArgs {
+    /// Output directory of a previous run (axes.tsv, composites.tsv, expr_stats.tsv)
+    #[arg(long)]
+    out: PathBuf,
+
+    /// Directory for the reclassified outputs
+    #[arg(long)]
+    new_out: PathBuf,
+
+    /// Thresholds JSON; fields left out keep their defaults. Per-species
+    /// overrides under `species` apply by the --meta species column
+    #[arg(long)]
+    thresholds: Option<PathBuf>,
+
+    /// Optional metadata TSV for the sample/condition/species columns
+    #[arg(long)]
+    meta: Option<PathBuf>,
+}
+
+pub fn handle(args: ReclassifyArgs) -> anyhow::Result<()> {
+    let thresholds = args
+        .thresholds
+        .as_deref()
+        .map(ThresholdsConfig::load)
+        .transpose()?
+        .unwrap_or_default();
+    let summary = run_reclassify(&args.out, &args.new_out, &thresholds, args.meta.as_deref())?;
+    info!(
+        source = %args.out.display(),
+        n_cells = summary.input.n_cells,
+        "reclassified previous run"
+    );
+    let report = run_verify(&args.new_out, None)?;
+    super::verify::enforce(&args.new_out, &report)
+}
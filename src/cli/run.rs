@@ -1,30 +1,84 @@
-use std::path::PathBuf;
-use std::time::Instant;
-
-use clap::Args;
-use tracing::info;
-
-use crate::expr::normalize::Normalization;
-use crate::panels::loader::{default_panels_dir, load_panels_from_dir};
-use crate::pipeline::stage1_load::{DatasetCtx, RunMode, run_stage1};
-use crate::pipeline::stage2_normalize::run_stage2;
-use crate::pipeline::stage3_panels::run_stage3_panels;
-use crate::pipeline::stage4_axes::run_stage4_axes;
-use crate::pipeline::stage5_scores::run_stage5_scores;
-use crate::pipeline::stage6_classify::run_stage6_classify;
-use crate::pipeline::stage7_report::run_stage7_report;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use anyhow::Context;
+use clap::error::ErrorKind;
+use clap::{Args, CommandFactory};
+use tracing::{Span, debug, field, info, info_span, warn};
+
+use crate::aggregate::cohort::{CohortRow, append_row, utc_timestamp};
+use crate::aggregate::compare::DEFAULT_MIN_CELLS_TO_COMPARE;
+use crate::aggregate::sample::DEFAULT_MIN_CELLS_FOR_STATS;
+use crate::cli::{Cli, ExitCategory, OutputContext, StageAction, StageContext};
+use crate::expr::normalize::{Normalization, NormalizationMethod};
+use crate::input::cache::CscCheck;
+use crate::input::h5ad::H5adLayer;
+use crate::input::mtx::{DEFAULT_MAX_COUNT_VALUE, DEFAULT_WARN_COUNT_VALUE, MtxValueLimits};
+use crate::model::axes::{AxisConfig, CoverageMode};
+use crate::model::axis_curves::AxisCurves;
+use crate::model::catalog::PanelAxis;
+use crate::model::chemistry::{Chemistry, ChemistryChoice};
+use crate::model::drivers::ZeroDrivers;
+use crate::model::pipeline_regime::{PipelineContract, RegimeLabels};
+use crate::model::qc_expectations::{QcExpectations, QcGateFailed};
+use crate::model::reference::CohortReference;
+use crate::model::run_label::{RunLabel, validate_description, validate_label};
+use crate::model::thresholds::ThresholdsConfig;
+use crate::panels::loader::{
+    PanelErrorPolicy, PanelLoadError, PanelLoadOptions, default_panels_dir,
+    load_panels_with_options,
+};
+use crate::panels::mapping::DEFAULT_MIN_PANEL_GENE_FRACTION;
+use crate::pipeline::cancel::{self, CancellationToken, remove_abort_marker};
+use crate::pipeline::explain::{DEFAULT_MAX_EXPLAIN_CELLS, ExplainCells};
+use crate::pipeline::fingerprint::{RunFingerprint, stale_reason};
+use crate::pipeline::outputs::{self, OutputSet, Retention, apply_retention};
+use crate::pipeline::panel_expr::PanelMatrixFormat;
+use crate::pipeline::progress::{self, DEFAULT_PROGRESS_INTERVAL_MS, ProgressReporter, RunStatus};
+use crate::pipeline::resources::{ResourceTracker, StageWork, write_resources_to_pipeline_step};
+use crate::pipeline::rng::{DEFAULT_SEED, RunRng};
+use crate::pipeline::stage1_load::{
+    DEFAULT_MIN_META_MATCH_FRAC, DatasetCtx, InputFormat, RunMode, Stage1Options,
+    run_stage1_with_options,
+};
+use crate::pipeline::stage2_normalize::{CellExprSource, Stage2Options, run_stage2_with_options};
+use crate::pipeline::stage3_panels::{
+    RowRangeWarnings, Stage3Options, run_stage3_panels_with_options,
+};
+use crate::pipeline::stage4_axes::run_stage4_axes_with_config;
+use crate::pipeline::stage5_scores::{ScoreOptions, run_stage5_scores_with_options};
+use crate::pipeline::stage6_classify::{
+    resolve_cell_thresholds, run_stage6_classify_with_cell_thresholds,
+};
+use crate::pipeline::stage7_report::{ReportOptions, run_stage7_report_with_options};
+use crate::pipeline::verify::{remove_success_marker, run_verify};
+use crate::report::artifact::{
+    DEFAULT_IO_BUFFER_SIZE, WriteOptions, enter_write_options, write_artifact,
+};
+use crate::report::compress::CompressFormat;
+use crate::report::format::{NanToken, enter_nan_token, signed_or_nan};
+use crate::report::render::ReportFormat;
+use crate::report::tsv::{self, FieldPolicy};
 
 #[derive(Args, Debug)]
 pub struct RunArgs {
-    /// Input 10x directory
+    /// Input 10x directory, an AnnData .h5ad file, or a dense table with
+    /// --input-format dense
     #[arg(long)]
     input: PathBuf,
 
+    /// `10x`: a 10x directory (or the shared cache in pipeline mode);
+    /// `dense`: a genes × cells count table (TSV, or CSV for *.csv), header
+    /// row of barcodes, gene symbol first on every row
+    #[arg(long, value_enum, default_value = "10x")]
+    input_format: InputFormatArg,
+
     /// Output directory
     #[arg(long)]
     out: PathBuf,
 
-    /// Mode for downstream processing (placeholder)
+    /// Mode for downstream processing; `sample` also writes samples.tsv
     #[arg(long, default_value = "cell")]
     mode: Mode,
 
@@ -32,6 +86,48 @@ pub struct RunArgs {
     #[arg(long)]
     meta: Option<PathBuf>,
 
+    /// Fail when fewer than this fraction of barcodes match a meta cell_id
+    #[arg(long, default_value_t = DEFAULT_MIN_META_MATCH_FRAC)]
+    min_meta_match_frac: f32,
+
+    /// Reject meta files with unknown species, short rows or duplicate
+    /// cell_ids instead of counting them
+    #[arg(long)]
+    meta_strict: bool,
+
+    /// Whether barcodes.tsv starts with a header line; detected when not given
+    #[arg(long, value_name = "BOOL")]
+    barcodes_has_header: Option<bool>,
+
+    /// Accept a features/genes file of gene symbols only, using each value as
+    /// both id and symbol
+    #[arg(long)]
+    features_single_column: bool,
+
+    /// Read matrix.mtx as cells x genes (cells as rows, genes as columns), as
+    /// some conversion tools write it
+    #[arg(long)]
+    transpose_matrix: bool,
+
+    /// Matrix of an .h5ad input holding the raw counts: `x` (X) or `counts`
+    /// (layers/counts)
+    #[arg(long, value_enum, default_value = "x")]
+    layer: LayerArg,
+
+    /// Leave the shared cache's per-cell sidecar (`*.kira-organelle.cells.tsv`)
+    /// out of secretion.tsv and summary.json
+    #[arg(long)]
+    no_upstream_columns: bool,
+
+    /// Fail when a matrix entry exceeds this count (such values usually mean
+    /// the matrix does not hold raw counts)
+    #[arg(long, default_value_t = DEFAULT_MAX_COUNT_VALUE)]
+    max_count_value: u32,
+
+    /// Warn with the number of matrix entries above this count
+    #[arg(long, default_value_t = DEFAULT_WARN_COUNT_VALUE)]
+    warn_count_value: u32,
+
     /// Input source mode
     #[arg(long, value_enum, default_value = "standalone")]
     pub(crate) run_mode: RunModeArg,
@@ -39,6 +135,382 @@ pub struct RunArgs {
     /// Optional explicit shared cache path (kira-organelle.bin)
     #[arg(long)]
     cache: Option<PathBuf>,
+
+    /// Shared cache CSC check: `full` checks every row index, `quick` an evenly
+    /// spaced sample of cells
+    #[arg(long, value_enum, default_value = "full")]
+    verify_cache: VerifyCacheArg,
+
+    /// Axis coverage definition used for composite coverage and confidence
+    #[arg(long, value_enum, default_value = "required")]
+    pub(crate) coverage_mode: CoverageModeArg,
+
+    /// Only load panel files matching this glob (relative to the panels dir; repeatable)
+    #[arg(long = "panels-include")]
+    panels_include: Vec<String>,
+
+    /// Skip panel files matching this glob (relative to the panels dir; repeatable)
+    #[arg(long = "panels-exclude")]
+    panels_exclude: Vec<String>,
+
+    /// Panel files that fail to read, parse or validate: abort the run, or skip
+    /// them and record the errors in summary.json caveats
+    #[arg(long, value_enum, default_value = "fail")]
+    panels_on_error: PanelsOnErrorArg,
+
+    /// Append `required` genes that a panel does not list in `genes` (weight
+    /// 1.0) instead of rejecting the panel
+    #[arg(long)]
+    panels_autofix_required: bool,
+
+    /// Render report.txt sparklines with plain ASCII characters
+    #[arg(long)]
+    ascii_only: bool,
+
+    /// Human-readable reports to write: report.txt, report.md, report.html
+    /// (repeatable or comma-separated)
+    #[arg(long, value_enum, value_delimiter = ',', default_value = "txt")]
+    report_format: Vec<ReportFormatArg>,
+
+    /// Write flagged_cells.tsv: only flagged cells, worst confidence first
+    /// [default: true in standalone mode, false in pipeline mode]
+    #[arg(long, num_args = 0..=1, default_missing_value = "true", value_name = "BOOL")]
+    flagged_output: Option<bool>,
+
+    /// Per-cell results format: `tsv` writes secretion.tsv; `bin` also
+    /// writes secretion.bin, typed columns readable with `dump-bin`
+    #[arg(long, value_enum, default_value = "tsv")]
+    format: TableFormatArg,
+
+    /// Barcode list (one per line): write explain/<barcode>.json with every
+    /// intermediate value for those cells; unknown barcodes go to
+    /// explain/_missing.txt
+    #[arg(long, value_name = "FILE")]
+    explain_cells: Option<PathBuf>,
+
+    /// Do not read secretion.tsv back to check its regime counts and
+    /// medians against summary.json (the check fails the run on mismatch)
+    #[arg(long)]
+    skip_output_consistency_check: bool,
+
+    /// Warn in the report and flag the dataset in pipeline_step.json when
+    /// fewer than this fraction of the unique panel genes are in the matrix
+    #[arg(long, default_value_t = DEFAULT_MIN_PANEL_GENE_FRACTION)]
+    min_panel_gene_fraction: f32,
+
+    /// Most cells --explain-cells writes sidecars for
+    #[arg(
+        long,
+        default_value_t = DEFAULT_MAX_EXPLAIN_CELLS as u64,
+        value_parser = clap::value_parser!(u64).range(1..),
+        requires = "explain_cells"
+    )]
+    explain_max_cells: u64,
+
+    /// Cohort reference JSON; adds `*_ref_pctl` columns and a drift section
+    #[arg(long)]
+    reference: Option<PathBuf>,
+
+    /// Only warn when --reference was built from a different gene list
+    /// (features file contents or order)
+    #[arg(long, requires = "reference")]
+    allow_gene_set_mismatch: bool,
+
+    /// Thresholds JSON (classification cut-offs and `frac_ge` lists); fields
+    /// left out keep their defaults. Per-species overrides under `species`
+    /// apply by the --meta species column
+    #[arg(long)]
+    thresholds: Option<PathBuf>,
+
+    /// Library chemistry picking the `low_counts` / `few_detected` defaults
+    /// (fields set in --thresholds win); auto infers it from the median
+    /// detected genes and libsize. Recorded in summary.json
+    #[arg(long, value_enum, default_value_t = ChemistryArg::Auto)]
+    chemistry: ChemistryArg,
+
+    /// Flag FEW_DETECTED_GENES below this many detected genes per 1000 UMIs
+    /// instead of below `few_detected`, so shallow but complex libraries
+    /// are not penalized (overrides `low_complexity` in --thresholds)
+    #[arg(long, value_name = "THRESH")]
+    flag_low_complexity: Option<f32>,
+
+    /// Regime labels JSON: display names (and optional order) for regimes in
+    /// secretion.tsv, summary.json and pipeline_step.json
+    #[arg(long, value_name = "FILE")]
+    regime_labels: Option<PathBuf>,
+
+    /// Pipeline contract version: 1 emits exactly the original six regimes
+    /// (newer ones fold via the `fold` map of --regime-labels), 2 emits the
+    /// extended list; recorded in pipeline_step.json
+    #[arg(
+        long,
+        default_value_t = 1,
+        value_parser = clap::value_parser!(u32).range(1..=i64::from(PipelineContract::LATEST.version()))
+    )]
+    pipeline_contract_version: u32,
+
+    /// QC expectations TOML: acceptable ranges for regime fractions and QC
+    /// metrics, evaluated into qc_gate.json and the report
+    #[arg(long, value_name = "FILE")]
+    qc_expectations: Option<PathBuf>,
+
+    /// Exit with code 6 when a --qc-expectations rule fails (all artifacts
+    /// are still written)
+    #[arg(long, requires = "qc_expectations")]
+    qc_gate_strict: bool,
+
+    /// Milliseconds between rewrites of progress.json while the run is going
+    #[arg(long, default_value_t = DEFAULT_PROGRESS_INTERVAL_MS, value_parser = clap::value_parser!(u64).range(1..))]
+    progress_interval_ms: u64,
+
+    /// Worker threads for the parallel stages [default: one per core];
+    /// outputs are identical at any count
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    threads: Option<u64>,
+
+    /// Append one row for this run (timestamp, --label or the input
+    /// directory name, cell count, regime and QC fractions, tool version,
+    /// panels hash) to a cohort TSV, creating it with a header when absent
+    #[arg(long, value_name = "FILE")]
+    append_cohort: Option<PathBuf>,
+
+    /// Run label embedded in summary.json, provenance.json,
+    /// pipeline_step.json, the report titles and the cohort row (letters,
+    /// digits, spaces and ._-+:@# only)
+    #[arg(long, value_parser = parse_label)]
+    label: Option<String>,
+
+    /// Free-text description stored next to --label
+    #[arg(long, requires = "label", value_parser = parse_description)]
+    description: Option<String>,
+
+    /// Write this run's axis/composite quantile grids as a cohort reference
+    #[arg(long)]
+    save_reference: Option<PathBuf>,
+
+    /// Token for missing values in axes.tsv/composites.tsv/panels_report.tsv
+    #[arg(long, value_enum, default_value = "nan")]
+    nan_token: NanTokenArg,
+
+    /// Master seed for stochastic features; per-purpose seeds are derived from it
+    #[arg(long, default_value_t = DEFAULT_SEED)]
+    seed: u64,
+
+    /// In sample mode, samples with fewer cells get NA statistics in samples.tsv
+    #[arg(long, default_value_t = DEFAULT_MIN_CELLS_FOR_STATS)]
+    min_cells_for_stats: usize,
+
+    /// Compare the two meta conditions in summary.json only when each has at
+    /// least this many cells
+    #[arg(long, default_value_t = DEFAULT_MIN_CELLS_TO_COMPARE)]
+    compare_min_cells: usize,
+
+    /// Replace tabs/newlines in barcodes, meta and panel strings with spaces
+    /// instead of failing; the count is reported in summary.json caveats
+    #[arg(long)]
+    lenient: bool,
+
+    /// Mark core axes without a mapped panel as not present (NaN, coverage 0)
+    /// instead of failing
+    #[arg(long)]
+    allow_missing_axes: bool,
+
+    /// Keep zero-contribution panels/components in driver columns
+    #[arg(long)]
+    keep_zero_drivers: bool,
+
+    /// Leave panels that are zero in at least 99% of cells out of axis
+    /// computation; they are listed in summary.json caveats either way
+    #[arg(long)]
+    drop_dead_panels: bool,
+
+    /// Append raw pre-saturation axis sums (raw_SIA ... raw_GDI) to axes.tsv
+    #[arg(long)]
+    axes_raw: bool,
+
+    /// TOML of per-axis piecewise-linear calibration curves (raw panel sum to
+    /// [0, 1]) used instead of the saturating map for the axes it names;
+    /// copied into provenance.json
+    #[arg(long, value_name = "FILE.toml")]
+    axis_curves: Option<PathBuf>,
+
+    /// Debug: also sum panels under this normalization and write
+    /// normalization_sensitivity.tsv (per-panel median absolute difference and
+    /// rank correlation against the default); other outputs are unchanged
+    #[arg(long, value_enum, value_name = "METHOD2")]
+    debug_dual_normalization: Option<NormalizationMethodArg>,
+
+    /// Export normalized expression of mapped panel genes: `tsv` writes
+    /// panel_expr.tsv.gz, `mtx` a sparse MatrixMarket triple
+    #[arg(long, value_enum, num_args = 0..=1, default_missing_value = "tsv")]
+    export_panel_matrix: Option<PanelMatrixArg>,
+
+    /// Compression of the panel expression export and --compress-output:
+    /// `gzip` writes one gzip member, `bgzf` block-compressed gzip (seekable,
+    /// still readable by any gzip reader)
+    #[arg(long, value_enum, default_value = "gzip")]
+    compress_format: CompressFormatArg,
+
+    /// Also write secretion.tsv.gz, compressed per --compress-format (`bgzf`
+    /// for tabix-style indexing)
+    #[arg(long)]
+    compress_output: bool,
+
+    /// Artifacts to write: `summary-only` skips the per-cell tables, `full`
+    /// adds the panel expression export (tsv unless --export-panel-matrix is given)
+    #[arg(long, value_enum, default_value = "standard")]
+    outputs: OutputsArg,
+
+    /// Artifacts kept once the run verifies: `final` removes the intermediate
+    /// per-cell tables (expr_stats, axes, composites, classify), `minimal`
+    /// also panels_report.tsv
+    #[arg(long, value_enum, default_value = "all")]
+    retain: RetainArg,
+
+    /// Bytes buffered per artifact before a write reaches the file; large
+    /// buffers keep write counts low on network filesystems
+    #[arg(long, default_value_t = DEFAULT_IO_BUFFER_SIZE as u64, value_parser = clap::value_parser!(u64).range(1..))]
+    io_buffer_size: u64,
+
+    /// Sync each artifact before it is renamed into place and the output
+    /// directory after, so finished artifacts survive a host crash (meant for
+    /// pipeline mode on shared storage)
+    #[arg(long)]
+    durable: bool,
+
+    /// Run even when the output directory already holds a verified run with
+    /// the same fingerprint (inputs, options, panels and version)
+    #[arg(long)]
+    force: bool,
+}
+
+impl RunArgs {
+    fn panel_matrix(&self) -> Option<PanelMatrixFormat> {
+        match (self.export_panel_matrix, self.outputs) {
+            (Some(format), _) => Some(format.into()),
+            (None, OutputsArg::Full) => Some(PanelMatrixFormat::Tsv),
+            (None, _) => None,
+        }
+    }
+
+    /// Requested report formats, deduplicated in txt, md, html order.
+    fn report_formats(&self) -> Vec<ReportFormat> {
+        let formats: std::collections::BTreeSet<ReportFormat> =
+            self.report_format.iter().map(|&f| f.into()).collect();
+        formats.into_iter().collect()
+    }
+
+    fn panel_load_options(&self) -> PanelLoadOptions {
+        PanelLoadOptions {
+            include: self.panels_include.clone(),
+            exclude: self.panels_exclude.clone(),
+            on_error: self.panels_on_error.into(),
+            autofix_required: self.panels_autofix_required,
+        }
+    }
+
+    /// Options that change the outputs, keyed by flag name. Paths are left
+    /// out; [`run_fingerprint`] digests the files they name instead.
+    fn fingerprint_parameters(&self) -> BTreeMap<String, String> {
+        let parameters = [
+            ("mode", format!("{:?}", self.mode)),
+            ("run-mode", format!("{:?}", self.run_mode)),
+            ("input-format", format!("{:?}", self.input_format)),
+            ("min-meta-match-frac", self.min_meta_match_frac.to_string()),
+            ("meta-strict", self.meta_strict.to_string()),
+            ("chemistry", format!("{:?}", self.chemistry)),
+            (
+                "flag-low-complexity",
+                format!("{:?}", self.flag_low_complexity),
+            ),
+            (
+                "barcodes-has-header",
+                format!("{:?}", self.barcodes_has_header),
+            ),
+            (
+                "features-single-column",
+                self.features_single_column.to_string(),
+            ),
+            ("transpose-matrix", self.transpose_matrix.to_string()),
+            ("layer", format!("{:?}", self.layer)),
+            ("no-upstream-columns", self.no_upstream_columns.to_string()),
+            ("max-count-value", self.max_count_value.to_string()),
+            ("warn-count-value", self.warn_count_value.to_string()),
+            ("coverage-mode", format!("{:?}", self.coverage_mode)),
+            (
+                "debug-dual-normalization",
+                format!("{:?}", self.debug_dual_normalization),
+            ),
+            ("panels-include", self.panels_include.join(",")),
+            ("panels-exclude", self.panels_exclude.join(",")),
+            ("panels-on-error", format!("{:?}", self.panels_on_error)),
+            (
+                "panels-autofix-required",
+                self.panels_autofix_required.to_string(),
+            ),
+            ("ascii-only", self.ascii_only.to_string()),
+            ("report-format", format!("{:?}", self.report_formats())),
+            ("flagged-output", format!("{:?}", self.flagged_output)),
+            ("format", format!("{:?}", self.format)),
+            ("explain-max-cells", self.explain_max_cells.to_string()),
+            (
+                "skip-output-consistency-check",
+                self.skip_output_consistency_check.to_string(),
+            ),
+            (
+                "min-panel-gene-fraction",
+                self.min_panel_gene_fraction.to_string(),
+            ),
+            (
+                "allow-gene-set-mismatch",
+                self.allow_gene_set_mismatch.to_string(),
+            ),
+            ("qc-gate-strict", self.qc_gate_strict.to_string()),
+            ("nan-token", format!("{:?}", self.nan_token)),
+            ("seed", self.seed.to_string()),
+            ("min-cells-for-stats", self.min_cells_for_stats.to_string()),
+            ("compare-min-cells", self.compare_min_cells.to_string()),
+            ("lenient", self.lenient.to_string()),
+            ("allow-missing-axes", self.allow_missing_axes.to_string()),
+            ("keep-zero-drivers", self.keep_zero_drivers.to_string()),
+            ("drop-dead-panels", self.drop_dead_panels.to_string()),
+            ("axes-raw", self.axes_raw.to_string()),
+            ("export-panel-matrix", format!("{:?}", self.panel_matrix())),
+            ("compress-format", format!("{:?}", self.compress_format)),
+            ("compress-output", self.compress_output.to_string()),
+            ("outputs", format!("{:?}", self.outputs)),
+            ("retain", format!("{:?}", self.retain)),
+            ("label", format!("{:?}", self.label)),
+            ("description", format!("{:?}", self.description)),
+            (
+                "pipeline-contract-version",
+                self.pipeline_contract_version.to_string(),
+            ),
+        ];
+        parameters
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value))
+            .collect()
+    }
+
+    fn run_label(&self) -> Option<RunLabel> {
+        self.label.clone().map(|label| RunLabel {
+            label,
+            description: self.description.clone(),
+        })
+    }
+
+    fn pipeline_contract(&self) -> PipelineContract {
+        PipelineContract::from_version(self.pipeline_contract_version).unwrap_or_default()
+    }
+
+    fn zero_drivers(&self) -> ZeroDrivers {
+        if self.keep_zero_drivers {
+            ZeroDrivers::Keep
+        } else {
+            ZeroDrivers::Drop
+        }
+    }
 }
 
 #[derive(clap::ValueEnum, Clone, Debug)]
@@ -47,12 +519,238 @@ pub enum Mode {
     Sample,
 }
 
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LayerArg {
+    #[value(name = "x")]
+    X,
+    Counts,
+}
+
+impl From<LayerArg> for H5adLayer {
+    fn from(value: LayerArg) -> Self {
+        match value {
+            LayerArg::X => H5adLayer::X,
+            LayerArg::Counts => H5adLayer::Counts,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InputFormatArg {
+    #[value(name = "10x")]
+    TenX,
+    Dense,
+}
+
+impl From<InputFormatArg> for InputFormat {
+    fn from(value: InputFormatArg) -> Self {
+        match value {
+            InputFormatArg::TenX => InputFormat::TenX,
+            InputFormatArg::Dense => InputFormat::Dense,
+        }
+    }
+}
+
 #[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
 pub enum RunModeArg {
     Standalone,
     Pipeline,
 }
 
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VerifyCacheArg {
+    Full,
+    Quick,
+}
+
+impl From<VerifyCacheArg> for CscCheck {
+    fn from(value: VerifyCacheArg) -> Self {
+        match value {
+            VerifyCacheArg::Full => CscCheck::Full,
+            VerifyCacheArg::Quick => CscCheck::Sampled,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CoverageModeArg {
+    Required,
+    Detection,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NormalizationMethodArg {
+    Cp10k,
+    Cpm,
+    Raw,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NanTokenArg {
+    Nan,
+    Empty,
+    Na,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReportFormatArg {
+    Txt,
+    Md,
+    Html,
+}
+
+impl From<ReportFormatArg> for ReportFormat {
+    fn from(value: ReportFormatArg) -> Self {
+        match value {
+            ReportFormatArg::Txt => ReportFormat::Txt,
+            ReportFormatArg::Md => ReportFormat::Md,
+            ReportFormatArg::Html => ReportFormat::Html,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TableFormatArg {
+    Tsv,
+    Bin,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChemistryArg {
+    Auto,
+    #[value(name = "3p_v2")]
+    ThreePrimeV2,
+    #[value(name = "3p_v3")]
+    ThreePrimeV3,
+    #[value(name = "5p")]
+    FivePrime,
+    #[value(name = "snRNA")]
+    SnRna,
+    Flex,
+}
+
+impl ChemistryArg {
+    /// `None` for auto.
+    fn chemistry(self) -> Option<Chemistry> {
+        match self {
+            ChemistryArg::Auto => None,
+            ChemistryArg::ThreePrimeV2 => Some(Chemistry::ThreePrimeV2),
+            ChemistryArg::ThreePrimeV3 => Some(Chemistry::ThreePrimeV3),
+            ChemistryArg::FivePrime => Some(Chemistry::FivePrime),
+            ChemistryArg::SnRna => Some(Chemistry::SnRna),
+            ChemistryArg::Flex => Some(Chemistry::Flex),
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PanelMatrixArg {
+    Tsv,
+    Mtx,
+}
+
+impl From<PanelMatrixArg> for PanelMatrixFormat {
+    fn from(value: PanelMatrixArg) -> Self {
+        match value {
+            PanelMatrixArg::Tsv => PanelMatrixFormat::Tsv,
+            PanelMatrixArg::Mtx => PanelMatrixFormat::Mtx,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompressFormatArg {
+    Gzip,
+    Bgzf,
+}
+
+impl From<CompressFormatArg> for CompressFormat {
+    fn from(value: CompressFormatArg) -> Self {
+        match value {
+            CompressFormatArg::Gzip => CompressFormat::Gzip,
+            CompressFormatArg::Bgzf => CompressFormat::Bgzf,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputsArg {
+    SummaryOnly,
+    Standard,
+    Full,
+}
+
+impl From<OutputsArg> for OutputSet {
+    fn from(value: OutputsArg) -> Self {
+        match value {
+            OutputsArg::SummaryOnly => OutputSet::SummaryOnly,
+            OutputsArg::Standard => OutputSet::Standard,
+            OutputsArg::Full => OutputSet::Full,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RetainArg {
+    All,
+    Final,
+    Minimal,
+}
+
+impl From<RetainArg> for Retention {
+    fn from(value: RetainArg) -> Self {
+        match value {
+            RetainArg::All => Retention::All,
+            RetainArg::Final => Retention::Final,
+            RetainArg::Minimal => Retention::Minimal,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PanelsOnErrorArg {
+    Fail,
+    Skip,
+}
+
+impl From<PanelsOnErrorArg> for PanelErrorPolicy {
+    fn from(value: PanelsOnErrorArg) -> Self {
+        match value {
+            PanelsOnErrorArg::Fail => PanelErrorPolicy::Fail,
+            PanelsOnErrorArg::Skip => PanelErrorPolicy::Skip,
+        }
+    }
+}
+
+impl From<NanTokenArg> for NanToken {
+    fn from(value: NanTokenArg) -> Self {
+        match value {
+            NanTokenArg::Nan => NanToken::Nan,
+            NanTokenArg::Empty => NanToken::Empty,
+            NanTokenArg::Na => NanToken::Na,
+        }
+    }
+}
+
+impl From<NormalizationMethodArg> for NormalizationMethod {
+    fn from(value: NormalizationMethodArg) -> Self {
+        match value {
+            NormalizationMethodArg::Cp10k => NormalizationMethod::Cp10k,
+            NormalizationMethodArg::Cpm => NormalizationMethod::Cpm,
+            NormalizationMethodArg::Raw => NormalizationMethod::Raw,
+        }
+    }
+}
+
+impl From<CoverageModeArg> for CoverageMode {
+    fn from(value: CoverageModeArg) -> Self {
+        match value {
+            CoverageModeArg::Required => CoverageMode::Required,
+            CoverageModeArg::Detection => CoverageMode::Detection,
+        }
+    }
+}
+
 impl From<RunModeArg> for RunMode {
     fn from(value: RunModeArg) -> Self {
         match value {
@@ -62,131 +760,643 @@ impl From<RunModeArg> for RunMode {
     }
 }
 
-pub fn handle(args: RunArgs) -> anyhow::Result<()> {
+pub fn handle(args: RunArgs, token: &CancellationToken) -> anyhow::Result<()> {
+    if args.outputs == OutputsArg::SummaryOnly && args.run_mode == RunModeArg::Pipeline {
+        return Err(Cli::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "--outputs summary-only cannot be combined with --run-mode pipeline: \
+                 pipeline_step.json must list secretion.tsv, which summary-only does not write",
+            )
+            .into());
+    }
     let stage_out = match args.run_mode {
         RunModeArg::Pipeline => args.out.join("kira-secretion"),
         RunModeArg::Standalone => args.out.clone(),
     };
-    std::fs::create_dir_all(&stage_out)?;
-
-    let start = Instant::now();
-    info!(stage = "stage1_load", "starting stage");
-    let ctx = run_stage1(
-        &args.input,
-        args.meta.as_deref(),
-        &stage_out,
-        true,
-        args.run_mode.into(),
-        args.cache.as_deref(),
-    )?;
-    info!(
-        stage = "stage1_load",
-        elapsed_ms = start.elapsed().as_millis(),
-        "finished stage"
-    );
+    std::fs::create_dir_all(&stage_out)
+        .with_context(|| StageContext::new("setup", StageAction::CreateOutput, &stage_out))?;
 
-    let start = Instant::now();
-    info!(stage = "stage2_normalize", "starting stage");
-    let expr_ctx = run_stage2(&ctx, &stage_out, Normalization::default(), true)?;
-    info!(
-        stage = "stage2_normalize",
-        elapsed_ms = start.elapsed().as_millis(),
-        nnz = expr_ctx.expr.nnz(),
-        "finished stage"
-    );
+    let fingerprint = run_fingerprint(&args);
+    // Cohort rows and saved references land outside the output directory, so
+    // runs that write them are never skipped.
+    if let Some(fingerprint) = &fingerprint
+        && !args.force
+        && args.append_cohort.is_none()
+        && args.save_reference.is_none()
+    {
+        let digest = fingerprint.digest();
+        match stale_reason(&stage_out, &digest, args.qc_gate_strict) {
+            None => {
+                info!(
+                    out = %stage_out.display(),
+                    fingerprint = %digest,
+                    "up to date; skipping run (--force re-runs it)"
+                );
+                return Ok(());
+            }
+            Some(reason) => debug!(reason = %reason, "output directory is not up to date"),
+        }
+    }
 
-    write_expr_stats(&stage_out, &ctx, &expr_ctx.cell_stats)?;
-
-    let start = Instant::now();
-    info!(stage = "stage3_panels", "starting stage");
-    let panels_dir = default_panels_dir();
-    let panels = load_panels_from_dir(&panels_dir)?;
-    if panels.panels.is_empty() {
-        anyhow::bail!("no panels loaded");
-    }
-    let panels_ctx = run_stage3_panels(
-        &expr_ctx,
-        &panels,
-        &ctx.gene_index,
-        &ctx.barcodes,
-        &stage_out,
-    )?;
-    let mapped_genes: usize = panels_ctx
-        .mappings
-        .iter()
-        .map(|m| m.mapped.iter().filter(|v| v.is_some()).count())
-        .sum();
-    info!(
-        stage = "stage3_panels",
-        elapsed_ms = start.elapsed().as_millis(),
-        panels = panels.panels.len(),
-        genes = mapped_genes,
-        "finished stage"
-    );
+    remove_success_marker(&stage_out)?;
+    remove_abort_marker(&stage_out)?;
 
-    let start = Instant::now();
-    info!(stage = "stage4_axes", "starting stage");
-    let axes_ctx = run_stage4_axes(&ctx, &panels_ctx, &stage_out)?;
-    let axis_counts = count_axis_panels(&panels_ctx);
-    info!(
-        stage = "stage4_axes",
-        elapsed_ms = start.elapsed().as_millis(),
-        sia = axis_counts.sia,
-        eeb_export = axis_counts.eeb_export,
-        eeb_degrade = axis_counts.eeb_degrade,
-        sli = axis_counts.sli,
-        mei = axis_counts.mei,
-        ecmi = axis_counts.ecmi,
-        apci = axis_counts.apci,
-        gdi = axis_counts.gdi,
-        "finished stage"
-    );
+    if let Some(threads) = args.threads {
+        // The per-thread guards below stay on this thread; only the global
+        // pool behind the parallel stages is sized.
+        if let Err(err) = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads as usize)
+            .build_global()
+        {
+            warn!(error = %err, "--threads ignored: thread pool already started");
+        }
+    }
+    let _cancel = cancel::enter(token);
+    let _fields = tsv::enter(if args.lenient {
+        FieldPolicy::Lenient
+    } else {
+        FieldPolicy::Strict
+    });
+    let _outputs = outputs::enter(args.outputs.into());
+    let _nan_token = enter_nan_token(args.nan_token.into());
+    let _write_options = enter_write_options(WriteOptions {
+        buffer_size: args.io_buffer_size as usize,
+        durable: args.durable,
+    });
+    let reporter =
+        ProgressReporter::start(&stage_out, Duration::from_millis(args.progress_interval_ms));
+    let progress_guard = progress::enter(reporter.counters());
+    let mut tracker = ResourceTracker::new();
+    let result = run_stages(&args, &stage_out, &mut tracker, fingerprint);
+    drop(progress_guard);
+    reporter.finish(match &result {
+        Ok(()) => RunStatus::Complete,
+        Err(err) => {
+            let category = ExitCategory::classify(err);
+            RunStatus::Failed {
+                category: category.as_str(),
+                exit_code: category.code(),
+            }
+        }
+    });
+    if let Err(err) = &result
+        && ExitCategory::classify(err) == ExitCategory::Cancelled
+    {
+        let completed: Vec<&str> = tracker.stages().iter().map(|s| s.stage).collect();
+        cancel::write_abort_marker(&stage_out, &completed)?;
+        remove_success_marker(&stage_out)?;
+        warn!(
+            last_completed_stage = completed.last().copied().unwrap_or("none"),
+            "run interrupted; partial outputs removed"
+        );
+    }
+    result
+}
 
-    let start = Instant::now();
-    info!(stage = "stage5_scores", "starting stage");
-    let scores_ctx = run_stage5_scores(&axes_ctx, &stage_out)?;
-    info!(
-        stage = "stage5_scores",
-        elapsed_ms = start.elapsed().as_millis(),
-        "finished stage"
-    );
+fn run_stages(
+    args: &RunArgs,
+    stage_out: &Path,
+    tracker: &mut ResourceTracker,
+    fingerprint: Option<RunFingerprint>,
+) -> anyhow::Result<()> {
+    let reference = args
+        .reference
+        .as_deref()
+        .map(CohortReference::load)
+        .transpose()?;
+    let axis_curves = args
+        .axis_curves
+        .as_deref()
+        .map(AxisCurves::load)
+        .transpose()?;
+    let mut thresholds = args
+        .thresholds
+        .as_deref()
+        .map(ThresholdsConfig::load)
+        .transpose()?
+        .unwrap_or_default();
+    if let Some(min) = args.flag_low_complexity {
+        thresholds.set_low_complexity(min);
+    }
+    let regime_labels = args
+        .regime_labels
+        .as_deref()
+        .map(RegimeLabels::load)
+        .transpose()?
+        .unwrap_or_default()
+        .with_contract(args.pipeline_contract());
+    let explain_cells = args
+        .explain_cells
+        .as_deref()
+        .map(|path| ExplainCells::load(path, args.explain_max_cells as usize))
+        .transpose()?;
+    let qc_expectations = args
+        .qc_expectations
+        .as_deref()
+        .map(QcExpectations::load)
+        .transpose()?;
 
-    let start = Instant::now();
-    info!(stage = "stage6_classify", "starting stage");
-    let classify_ctx = run_stage6_classify(&ctx, &expr_ctx, &axes_ctx, &scores_ctx, &stage_out)?;
-    log_regime_counts(&classify_ctx);
-    info!(
-        stage = "stage6_classify",
-        elapsed_ms = start.elapsed().as_millis(),
-        "finished stage"
-    );
+    let rng = RunRng::new(args.seed);
+    info!(seed = rng.master_seed(), "run seed");
+
+    let run_span = info_span!("run", n_cells = field::Empty, nnz = field::Empty);
+    let _run = run_span.enter();
+
+    let ctx = {
+        let span = info_span!(
+            "stage",
+            stage = "stage1_load",
+            n_cells = field::Empty,
+            nnz = field::Empty
+        );
+        let _enter = span.enter();
+        let start = tracker.begin();
+        info!(stage = "stage1_load", "starting stage");
+        progress::start_stage("stage1_load");
+        let ctx = run_stage1_with_options(
+            &args.input,
+            args.meta.as_deref(),
+            stage_out,
+            true,
+            args.run_mode.into(),
+            args.cache.as_deref(),
+            &Stage1Options {
+                min_meta_match_frac: args.min_meta_match_frac,
+                meta_strict: args.meta_strict,
+                barcodes_has_header: args.barcodes_has_header,
+                features_single_column: args.features_single_column,
+                upstream_columns: !args.no_upstream_columns,
+                cache_check: args.verify_cache.into(),
+                input_format: args.input_format.into(),
+                transpose_matrix: args.transpose_matrix,
+                h5ad_layer: args.layer.into(),
+            },
+        )
+        .with_context(|| StageContext::new("stage1_load", StageAction::ReadInput, &args.input))?;
+        for s in [&span, &run_span] {
+            s.record("n_cells", ctx.n_cells);
+            s.record("nnz", ctx.nnz);
+        }
+        info!(
+            stage = "stage1_load",
+            elapsed_ms = start.elapsed().as_millis(),
+            "finished stage"
+        );
+        tracker.record("stage1_load", start, StageWork::default());
+        ctx
+    };
+
+    if let (Some(reference), Some(path)) = (&reference, &args.reference)
+        && let Some(mismatch) =
+            reference.check_gene_set(path, &ctx.gene_set_hash, args.allow_gene_set_mismatch)?
+    {
+        warn!(%mismatch, "continuing with --allow-gene-set-mismatch");
+    }
+
+    let expr_ctx = {
+        let _enter = stage_span("stage2_normalize", ctx.n_cells, ctx.nnz).entered();
+        let start = tracker.begin();
+        info!(stage = "stage2_normalize", "starting stage");
+        progress::start_stage("stage2_normalize");
+        let expr_ctx = run_stage2_with_options(
+            &ctx,
+            stage_out,
+            Normalization::default(),
+            true,
+            &Stage2Options {
+                value_limits: MtxValueLimits {
+                    max_value: args.max_count_value,
+                    warn_value: args.warn_count_value,
+                },
+            },
+        )
+        .with_context(|| {
+            StageContext::new("stage2_normalize", StageAction::ReadInput, &args.input)
+        })?;
+        let value_warnings = &expr_ctx.value_warnings;
+        if value_warnings.n_above_warn > 0 {
+            warn!(
+                entries = value_warnings.n_above_warn,
+                threshold = args.warn_count_value,
+                max_value = value_warnings.max_seen,
+                first = value_warnings.first_location.as_deref().unwrap_or(""),
+                "matrix entries above the --warn-count-value threshold; check that the matrix holds raw counts"
+            );
+        }
+        if value_warnings.n_rounded > 0 {
+            warn!(
+                entries = value_warnings.n_rounded,
+                dropped_as_zero = value_warnings.n_rounded_to_zero,
+                first = value_warnings
+                    .first_rounded_location
+                    .as_deref()
+                    .unwrap_or(""),
+                "fractional `real` matrix entries rounded to the nearest count, dropping those that round to 0; the matrix may hold normalized values"
+            );
+        }
+        if value_warnings.duplicate_entries_merged > 0 {
+            warn!(
+                entries = value_warnings.duplicate_entries_merged,
+                "matrix entries repeat an earlier (row, column); their values were summed"
+            );
+        }
+        let stage = tracker.record(
+            "stage2_normalize",
+            start,
+            StageWork::nnz(expr_ctx.expr.nnz()),
+        );
+        info!(
+            stage = "stage2_normalize",
+            elapsed_ms = stage.elapsed_ms,
+            nnz = expr_ctx.expr.nnz(),
+            nnz_per_sec = stage.nnz_per_sec,
+            "finished stage"
+        );
+        expr_ctx
+    };
+    let nnz = expr_ctx.expr.nnz();
+
+    if outputs::per_cell_tables() {
+        write_expr_stats(stage_out, &ctx, &expr_ctx.cell_stats).with_context(|| {
+            StageContext::new("stage2_normalize", StageAction::Write, stage_out)
+        })?;
+    }
+
+    let panels_ctx = {
+        let _enter = stage_span("stage3_panels", ctx.n_cells, nnz).entered();
+        let start = tracker.begin();
+        info!(stage = "stage3_panels", "starting stage");
+        progress::start_stage("stage3_panels");
+        let panels_dir = default_panels_dir();
+        let panel_opts = args.panel_load_options();
+        let panels = load_panels_with_options(&panels_dir, &panel_opts).with_context(|| {
+            StageContext::new("stage3_panels", StageAction::LoadPanels, &panels_dir)
+        })?;
+        for skipped in &panels.skipped {
+            warn!(
+                stage = "stage3_panels",
+                file = %skipped.file,
+                errors = %skipped.errors.join("; "),
+                "skipped unreadable panel file"
+            );
+        }
+        for fix in &panels.required_autofixes {
+            warn!(
+                stage = "stage3_panels",
+                file = %fix.file,
+                panel = %fix.panel_id,
+                genes = %fix.genes.join(","),
+                "appended required genes missing from the panel's gene list"
+            );
+        }
+        info!(
+            stage = "stage3_panels",
+            files = %panels.files.join(","),
+            "resolved panel files"
+        );
+        if panels.panels.is_empty() {
+            return Err(PanelLoadError::Empty(panels_dir.display().to_string()).into());
+        }
+        if let (Some(reference), Some(path)) = (&reference, &args.reference) {
+            reference.check_panel_set(path, &panels.content_hash())?;
+        }
+        let stage3_opts = Stage3Options {
+            dual_normalization: args.debug_dual_normalization.map(Into::into),
+        };
+        let mut row_warnings = RowRangeWarnings::default();
+        let panels_ctx = run_stage3_panels_with_options(
+            &expr_ctx,
+            &panels,
+            &ctx.gene_index,
+            &ctx.barcodes,
+            stage_out,
+            &stage3_opts,
+            &mut row_warnings,
+        )
+        .output_context("stage3_panels", stage_out)?;
+        if row_warnings.n_out_of_range > 0 {
+            warn!(
+                entries = row_warnings.n_out_of_range,
+                n_genes = expr_ctx.expr.n_genes(),
+                max_row = row_warnings.max_row,
+                first_cell = row_warnings.first_cell.as_deref().unwrap_or(""),
+                "matrix entries with a row outside the gene range were skipped; the shared cache \
+                 is corrupt (rerun with --verify-cache full)"
+            );
+        }
+        let mapped_genes: usize = panels_ctx
+            .mappings
+            .iter()
+            .map(|m| m.mapped.iter().filter(|v| v.is_some()).count())
+            .sum();
+        let stage = tracker.record(
+            "stage3_panels",
+            start,
+            StageWork {
+                cells: Some(ctx.n_cells),
+                nnz: Some(nnz),
+            },
+        );
+        info!(
+            stage = "stage3_panels",
+            elapsed_ms = stage.elapsed_ms,
+            panels = panels.panels.len(),
+            genes = mapped_genes,
+            cells_per_sec = stage.cells_per_sec,
+            nnz_per_sec = stage.nnz_per_sec,
+            "finished stage"
+        );
+        panels_ctx
+    };
 
-    let start = Instant::now();
-    info!(stage = "stage7_report", "starting stage");
-    let mode_str = match args.mode {
-        Mode::Cell => "cell",
-        Mode::Sample => "sample",
+    let axes_ctx = {
+        let _enter = stage_span("stage4_axes", ctx.n_cells, nnz).entered();
+        let start = tracker.begin();
+        info!(stage = "stage4_axes", "starting stage");
+        progress::start_stage("stage4_axes");
+        let axis_cfg = AxisConfig {
+            coverage_mode: args.coverage_mode.into(),
+            allow_missing_axes: args.allow_missing_axes,
+            zero_drivers: args.zero_drivers(),
+            drop_dead_panels: args.drop_dead_panels,
+            raw_sums: args.axes_raw,
+            curves: axis_curves,
+            ..AxisConfig::default()
+        };
+        let axes_ctx = run_stage4_axes_with_config(&ctx, &panels_ctx, stage_out, &axis_cfg)
+            .output_context("stage4_axes", stage_out)?;
+        let axis_counts = count_axis_panels(&panels_ctx);
+        let stage = tracker.record("stage4_axes", start, StageWork::cells(ctx.n_cells));
+        info!(
+            stage = "stage4_axes",
+            elapsed_ms = stage.elapsed_ms,
+            cells_per_sec = stage.cells_per_sec,
+            coverage_mode = axes_ctx.coverage_mode.as_str(),
+            sia = axis_counts.sia,
+            eeb_export = axis_counts.eeb_export,
+            eeb_degrade = axis_counts.eeb_degrade,
+            sli = axis_counts.sli,
+            mei = axis_counts.mei,
+            ecmi = axis_counts.ecmi,
+            apci = axis_counts.apci,
+            gdi = axis_counts.gdi,
+            "finished stage"
+        );
+        axes_ctx
     };
-    let _summary = run_stage7_report(
-        &ctx,
-        &expr_ctx,
-        &axes_ctx,
-        &scores_ctx,
-        &classify_ctx,
-        &panels_ctx,
-        &stage_out,
-        mode_str,
-        args.run_mode.into(),
-        args.meta.as_deref(),
-    )?;
+
+    let scores_ctx = {
+        let _enter = stage_span("stage5_scores", ctx.n_cells, nnz).entered();
+        let start = tracker.begin();
+        info!(stage = "stage5_scores", "starting stage");
+        progress::start_stage("stage5_scores");
+        let score_opts = ScoreOptions {
+            zero_drivers: args.zero_drivers(),
+        };
+        let scores_ctx = run_stage5_scores_with_options(&axes_ctx, stage_out, &score_opts)
+            .output_context("stage5_scores", stage_out)?;
+        let stage = tracker.record("stage5_scores", start, StageWork::cells(ctx.n_cells));
+        info!(
+            stage = "stage5_scores",
+            elapsed_ms = stage.elapsed_ms,
+            cells_per_sec = stage.cells_per_sec,
+            "finished stage"
+        );
+        scores_ctx
+    };
+
+    let (detected, libsize): (Vec<f32>, Vec<f32>) = expr_ctx
+        .cell_stats
+        .iter()
+        .map(|s| (s.detected as f32, s.libsize as f32))
+        .unzip();
+    let chemistry = ChemistryChoice::resolve(args.chemistry.chemistry(), &detected, &libsize);
+    thresholds.apply_chemistry(chemistry.chemistry);
     info!(
-        stage = "stage7_report",
-        elapsed_ms = start.elapsed().as_millis(),
-        "finished stage"
+        chemistry = chemistry.chemistry.as_str(),
+        source = chemistry.source.as_str(),
+        low_counts = thresholds.global.low_counts,
+        few_detected = thresholds.global.few_detected,
+        "library chemistry"
     );
+
+    let classify_ctx = {
+        let _enter = stage_span("stage6_classify", ctx.n_cells, nnz).entered();
+        let start = tracker.begin();
+        info!(stage = "stage6_classify", "starting stage");
+        progress::start_stage("stage6_classify");
+        let cell_thresholds =
+            resolve_cell_thresholds(&thresholds, args.meta.as_deref(), &ctx.barcodes)?;
+        let classify_ctx = run_stage6_classify_with_cell_thresholds(
+            &ctx,
+            &expr_ctx,
+            &axes_ctx,
+            &scores_ctx,
+            stage_out,
+            &cell_thresholds,
+        )
+        .output_context("stage6_classify", stage_out)?;
+        log_regime_counts(&classify_ctx);
+        let stage = tracker.record("stage6_classify", start, StageWork::cells(ctx.n_cells));
+        info!(
+            stage = "stage6_classify",
+            elapsed_ms = stage.elapsed_ms,
+            cells_per_sec = stage.cells_per_sec,
+            "finished stage"
+        );
+        classify_ctx
+    };
+
+    let summary = {
+        let _enter = stage_span("stage7_report", ctx.n_cells, nnz).entered();
+        let start = tracker.begin();
+        info!(stage = "stage7_report", "starting stage");
+        progress::start_stage("stage7_report");
+        let mode_str = match args.mode {
+            Mode::Cell => "cell",
+            Mode::Sample => "sample",
+        };
+        let report_opts = ReportOptions {
+            ascii_only: args.ascii_only,
+            report_formats: args.report_formats(),
+            reference,
+            min_cells_for_stats: args.min_cells_for_stats,
+            rng: rng.clone(),
+            panel_matrix: args.panel_matrix(),
+            panel_matrix_compression: args.compress_format.into(),
+            secretion_compression: args.compress_output.then(|| args.compress_format.into()),
+            thresholds: thresholds.global,
+            regime_labels: regime_labels.clone(),
+            qc_expectations,
+            flagged_output: args
+                .flagged_output
+                .unwrap_or(args.run_mode == RunModeArg::Standalone),
+            binary_output: args.format == TableFormatArg::Bin,
+            fingerprint,
+            explain_cells,
+            run_label: args.run_label(),
+            chemistry: Some(chemistry),
+            retention: args.retain.into(),
+            compare_min_cells: args.compare_min_cells,
+            consistency_check: !args.skip_output_consistency_check,
+            min_panel_gene_fraction: args.min_panel_gene_fraction,
+            panel_gene_universe: None,
+        };
+        let summary = run_stage7_report_with_options(
+            &ctx,
+            &expr_ctx,
+            &axes_ctx,
+            &scores_ctx,
+            &classify_ctx,
+            &panels_ctx,
+            stage_out,
+            mode_str,
+            args.run_mode.into(),
+            args.meta.as_deref(),
+            &report_opts,
+        )
+        .output_context("stage7_report", stage_out)?;
+        let stage = tracker.record("stage7_report", start, StageWork::cells(ctx.n_cells));
+        info!(
+            stage = "stage7_report",
+            elapsed_ms = stage.elapsed_ms,
+            cells_per_sec = stage.cells_per_sec,
+            "finished stage"
+        );
+        summary
+    };
+    if tsv::sanitized_fields() > 0 {
+        warn!(
+            fields = tsv::sanitized_fields(),
+            "replaced tabs/newlines in TSV string fields (--lenient)"
+        );
+    }
+
+    if let Some(path) = &args.save_reference {
+        let reference = CohortReference::build(
+            &axes_ctx.values,
+            &scores_ctx.oii,
+            &scores_ctx.iai,
+            &scores_ctx.esi,
+            &panels_ctx.panels.content_hash(),
+            &ctx.gene_set_hash,
+        );
+        reference.save(path)?;
+        info!(path = %path.display(), n_cells = reference.n_cells, "saved cohort reference");
+    }
+
+    if args.run_mode == RunModeArg::Pipeline {
+        write_resources_to_pipeline_step(stage_out, tracker)?;
+    }
+
+    let written_barcodes: Vec<String> = ctx
+        .barcodes
+        .iter()
+        .map(|b| tsv::replace_separators(b).into_owned())
+        .collect();
+    let report = run_verify(stage_out, Some(&written_barcodes))?;
+    super::verify::enforce(stage_out, &report)?;
+    let removed = apply_retention(stage_out, args.retain.into())?;
+    if !removed.is_empty() {
+        info!(
+            retain = Retention::from(args.retain).as_str(),
+            removed = %removed.join(","),
+            "removed artifacts not retained"
+        );
+    }
+
+    if let Some(path) = &args.append_cohort {
+        let label = match &args.label {
+            Some(label) => label.clone(),
+            None => tsv::field("label", &input_label(&args.input))?.into_owned(),
+        };
+        let row = CohortRow::from_summary(
+            &summary,
+            &regime_labels,
+            &utc_timestamp(SystemTime::now()),
+            &label,
+            &panels_ctx.panels.content_hash(),
+        );
+        append_row(path, &row)?;
+        info!(path = %path.display(), label = %label, "appended cohort row");
+    }
+
+    if args.qc_gate_strict
+        && let Some(gate) = summary.qc_gate.as_ref().filter(|g| !g.passed())
+    {
+        let rules: Vec<String> = gate.failed_rules().map(|r| r.describe()).collect();
+        return Err(QcGateFailed {
+            failed: gate.n_failed,
+            total: gate.n_rules,
+            rules: rules.join(", "),
+        }
+        .into());
+    }
     Ok(())
 }
 
+/// Fingerprint of this run, or `None` when an input cannot be read yet; the
+/// run then goes ahead and reports the problem from the stage that hits it.
+fn run_fingerprint(args: &RunArgs) -> Option<RunFingerprint> {
+    let build = || -> anyhow::Result<RunFingerprint> {
+        let panels = load_panels_with_options(&default_panels_dir(), &args.panel_load_options())?;
+        let mut fingerprint = RunFingerprint::new(
+            &args.input,
+            args.run_mode.into(),
+            args.input_format.into(),
+            args.cache.as_deref(),
+            &Normalization::default(),
+            panels.content_hash(),
+            args.fingerprint_parameters(),
+        )?;
+        for (role, path) in [
+            ("meta", &args.meta),
+            ("reference", &args.reference),
+            ("thresholds", &args.thresholds),
+            ("axis_curves", &args.axis_curves),
+            ("regime_labels", &args.regime_labels),
+            ("qc_expectations", &args.qc_expectations),
+            ("explain_cells", &args.explain_cells),
+        ] {
+            if let Some(path) = path {
+                fingerprint.add_file(role, path)?;
+            }
+        }
+        Ok(fingerprint)
+    };
+    build()
+        .map_err(|err| debug!(error = %err, "no run fingerprint"))
+        .ok()
+}
+
+fn parse_label(value: &str) -> Result<String, String> {
+    validate_label(value)
+        .map(|()| value.to_string())
+        .map_err(|e| e.to_string())
+}
+
+fn parse_description(value: &str) -> Result<String, String> {
+    validate_description(value)
+        .map(|()| value.to_string())
+        .map_err(|e| e.to_string())
+}
+
+/// Name of the input directory, resolving `.` and `..`.
+fn input_label(input: &Path) -> String {
+    std::fs::canonicalize(input)
+        .ok()
+        .and_then(|p| {
+            p.file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+        })
+        .unwrap_or_else(|| input.display().to_string())
+}
+
+fn stage_span(stage: &'static str, n_cells: usize, nnz: usize) -> Span {
+    info_span!("stage", stage, n_cells, nnz)
+}
+
 struct AxisCounts {
     sia: usize,
     eeb_export: usize,
@@ -210,38 +1420,42 @@ fn count_axis_panels(panels_ctx: &crate::pipeline::stage3_panels::PanelsContext)
         gdi: 0,
     };
     for panel in &panels_ctx.panels.panels {
-        match panel.axis.as_str() {
-            "SIA" => counts.sia += 1,
-            "EEB_EXPORT" => counts.eeb_export += 1,
-            "EEB_DEGRADE" => counts.eeb_degrade += 1,
-            "SLI" => counts.sli += 1,
-            "MEI" => counts.mei += 1,
-            "ECMI" => counts.ecmi += 1,
-            "APCI" => counts.apci += 1,
-            "GDI" => counts.gdi += 1,
-            _ => {}
-        }
+        let Some(axis) = PanelAxis::from_tag(&panel.axis) else {
+            continue;
+        };
+        let count = match axis {
+            PanelAxis::Sia => &mut counts.sia,
+            PanelAxis::EebExport => &mut counts.eeb_export,
+            PanelAxis::EebDegrade => &mut counts.eeb_degrade,
+            PanelAxis::Sli => &mut counts.sli,
+            PanelAxis::Mei => &mut counts.mei,
+            PanelAxis::Ecmi => &mut counts.ecmi,
+            PanelAxis::Apci => &mut counts.apci,
+            PanelAxis::Gdi => &mut counts.gdi,
+        };
+        *count += 1;
     }
     counts
 }
 
 fn write_expr_stats(
-    out_dir: &PathBuf,
+    out_dir: &Path,
     ctx: &DatasetCtx,
     cell_stats: &[crate::expr::csc::CellStats],
 ) -> anyhow::Result<()> {
-    let path = out_dir.join("expr_stats.tsv");
     let mut buf = String::new();
-    buf.push_str("cell_id\tlibsize\tdetected\n");
+    buf.push_str("cell_id\tlibsize\tdetected\tgenes_per_1k_umis\n");
     for (barcode, stats) in ctx.barcodes.iter().zip(cell_stats.iter()) {
-        buf.push_str(barcode);
+        buf.push_str(&tsv::field("cell_id", barcode)?);
         buf.push('\t');
         buf.push_str(&stats.libsize.to_string());
         buf.push('\t');
         buf.push_str(&stats.detected.to_string());
+        buf.push('\t');
+        buf.push_str(&signed_or_nan(stats.genes_per_1k_umis()));
         buf.push('\n');
     }
-    std::fs::write(path, buf)?;
+    write_artifact(out_dir, "expr_stats.tsv", buf)?;
     Ok(())
 }
 
@@ -4,14 +4,23 @@ use std::time::Instant;
 use clap::Args;
 use tracing::info;
 
-use crate::pipeline::stage1_load::{DatasetCtx, RunMode, run_stage1};
+use super::run::{InputFormatArg, LayerArg};
+use crate::pipeline::stage1_load::{
+    DEFAULT_MIN_META_MATCH_FRAC, DatasetCtx, RunMode, Stage1Options, run_stage1_with_options,
+};
+use crate::report::artifact::write_artifact;
 
 #[derive(Args, Debug)]
 pub struct ValidateArgs {
-    /// Input 10x directory
+    /// Input 10x directory, an AnnData .h5ad file, or a dense table with
+    /// --input-format dense
     #[arg(long)]
     input: PathBuf,
 
+    /// `10x` or `dense`, as for `run`
+    #[arg(long, value_enum, default_value = "10x")]
+    input_format: InputFormatArg,
+
     /// Output directory
     #[arg(long)]
     out: PathBuf,
@@ -23,6 +32,33 @@ pub struct ValidateArgs {
     /// Skip full nnz line counting
     #[arg(long, default_value_t = true)]
     fast: bool,
+
+    /// Fail when fewer than this fraction of barcodes match a meta cell_id
+    #[arg(long, default_value_t = DEFAULT_MIN_META_MATCH_FRAC)]
+    min_meta_match_frac: f32,
+
+    /// Reject meta files with unknown species, short rows or duplicate
+    /// cell_ids instead of counting them
+    #[arg(long)]
+    meta_strict: bool,
+
+    /// Whether barcodes.tsv starts with a header line; detected when not given
+    #[arg(long, value_name = "BOOL")]
+    barcodes_has_header: Option<bool>,
+
+    /// Accept a features/genes file of gene symbols only, using each value as
+    /// both id and symbol
+    #[arg(long)]
+    features_single_column: bool,
+
+    /// Read matrix.mtx as cells x genes (cells as rows, genes as columns), as
+    /// some conversion tools write it
+    #[arg(long)]
+    transpose_matrix: bool,
+
+    /// `x` or `counts`, as for `run`
+    #[arg(long, value_enum, default_value = "x")]
+    layer: LayerArg,
 }
 
 pub fn handle(args: ValidateArgs) -> anyhow::Result<()> {
@@ -30,13 +66,23 @@ pub fn handle(args: ValidateArgs) -> anyhow::Result<()> {
 
     let start = Instant::now();
     info!(stage = "stage1_load", "starting stage");
-    let ctx = run_stage1(
+    let ctx = run_stage1_with_options(
         &args.input,
         args.meta.as_deref(),
         &args.out,
         args.fast,
         RunMode::Standalone,
         None,
+        &Stage1Options {
+            min_meta_match_frac: args.min_meta_match_frac,
+            meta_strict: args.meta_strict,
+            barcodes_has_header: args.barcodes_has_header,
+            features_single_column: args.features_single_column,
+            input_format: args.input_format.into(),
+            transpose_matrix: args.transpose_matrix,
+            h5ad_layer: args.layer.into(),
+            ..Stage1Options::default()
+        },
     )?;
     info!(
         stage = "stage1_load",
@@ -63,12 +109,55 @@ fn write_validate(out_dir: &PathBuf, ctx: &DatasetCtx) -> anyhow::Result<()> {
         "barcodes_file",
         ctx.barcodes_path.to_string_lossy().to_string(),
     ));
+    lines.push((
+        "features_delimiter",
+        ctx.features_delimiter.as_str().to_string(),
+    ));
+    lines.push((
+        "features_single_column_rows",
+        ctx.features_single_column_rows.to_string(),
+    ));
+    lines.push((
+        "barcodes_delimiter",
+        ctx.barcodes_delimiter.as_str().to_string(),
+    ));
+    lines.push((
+        "barcodes_header",
+        ctx.barcodes_header
+            .clone()
+            .unwrap_or_else(|| ".".to_string()),
+    ));
     lines.push(("matrix_file", ctx.matrix_path.to_string_lossy().to_string()));
+    lines.push((
+        "matrix_value_type",
+        ctx.matrix_value_type
+            .map_or(".", |field| field.as_str())
+            .to_string(),
+    ));
+    lines.push((
+        "matrix_orientation",
+        ctx.matrix_orientation
+            .map_or(".", |orientation| orientation.as_str())
+            .to_string(),
+    ));
     lines.push(("meta_present", ctx.meta_present.to_string()));
     lines.push(("meta_cells_matched", ctx.meta_cells_matched.to_string()));
     lines.push(("meta_cells_missing", ctx.meta_cells_missing.to_string()));
+    lines.push((
+        "meta_match_fraction",
+        ctx.meta_match_fraction()
+            .map_or_else(|| ".".to_string(), |f| format!("{:.6}", f)),
+    ));
+    lines.push((
+        "meta_unknown_species",
+        ctx.meta_issues.unknown_species.to_string(),
+    ));
+    lines.push(("meta_short_rows", ctx.meta_issues.short_rows.to_string()));
+    lines.push((
+        "meta_duplicate_cell_ids",
+        ctx.meta_issues.duplicate_cell_ids.to_string(),
+    ));
 
-    let path = out_dir.join("validate.tsv");
     let mut buf = String::new();
     for (k, v) in lines {
         buf.push_str(k);
@@ -76,12 +165,11 @@ fn write_validate(out_dir: &PathBuf, ctx: &DatasetCtx) -> anyhow::Result<()> {
         buf.push_str(&v);
         buf.push('\n');
     }
-    std::fs::write(path, buf)?;
+    write_artifact(out_dir, "validate.tsv", buf)?;
     Ok(())
 }
 
 fn write_gene_warnings(out_dir: &PathBuf, ctx: &DatasetCtx) -> anyhow::Result<()> {
-    let path = out_dir.join("gene_mapping_warnings.tsv");
     let mut buf = String::new();
     buf.push_str("symbol\tfirst_row\tdup_row\n");
     for dup in &ctx.duplicate_gene_symbols {
@@ -92,6 +180,6 @@ fn write_gene_warnings(out_dir: &PathBuf, ctx: &DatasetCtx) -> anyhow::Result<()
         buf.push_str(&dup.dup_row.to_string());
         buf.push('\n');
     }
-    std::fs::write(path, buf)?;
+    write_artifact(out_dir, "gene_mapping_warnings.tsv", buf)?;
     Ok(())
 }
@@ -0,0 +1,47 @@
+use std::path::{Path, PathBuf};
+
+use clap::Args;
+use tracing::{error, info};
+
+use crate::pipeline::verify::{VerifyReport, apply_success_marker, run_verify};
+
+#[derive(Args, Debug)]
+pub struct VerifyArgs {
+    /// Output directory of a previous run
+    #[arg(long)]
+    out: PathBuf,
+}
+
+pub fn handle(args: VerifyArgs) -> anyhow::Result<()> {
+    let out_dir = resolve_out_dir(&args.out);
+    let report = run_verify(&out_dir, None)?;
+    print!("{}", report.render());
+    enforce(&out_dir, &report)
+}
+
+/// Accepts either the stage directory itself or a pipeline root containing
+/// `kira-secretion/`.
+fn resolve_out_dir(out: &Path) -> PathBuf {
+    let nested = out.join("kira-secretion");
+    if !out.join("secretion.tsv").is_file() && nested.is_dir() {
+        nested
+    } else {
+        out.to_path_buf()
+    }
+}
+
+pub(crate) fn enforce(out_dir: &Path, report: &VerifyReport) -> anyhow::Result<()> {
+    for check in &report.checks {
+        if check.passed {
+            info!(check = check.name, detail = %check.detail, "self-check passed");
+        } else {
+            error!(check = check.name, detail = %check.detail, "self-check failed");
+        }
+    }
+    apply_success_marker(out_dir, report)?;
+    if !report.passed() {
+        let failed: Vec<&str> = report.failed().map(|c| c.name).collect();
+        anyhow::bail!("artifact self-check failed: {}", failed.join(", "));
+    }
+    Ok(())
+}
@@ -2,7 +2,10 @@ use std::path::Path;
 
 use crate::expr::normalize::Normalization;
 use crate::input::InputError;
-use crate::input::mtx::{MatrixHeader, read_entries};
+use crate::input::mtx::{
+    MatrixHeader, MtxOrientation, MtxValueLimits, MtxValueWarnings, read_entries_with_limits,
+};
+use crate::pipeline::cancel::entry_checkpoint;
 
 #[derive(Debug, Clone)]
 pub struct ExprCsc {
@@ -20,6 +23,18 @@ pub struct CellStats {
     pub detected: u32,
 }
 
+impl CellStats {
+    /// Library complexity, `detected / (libsize / 1000)`: unlike the detected
+    /// count it does not shrink with depth. NaN for an empty cell.
+    pub fn genes_per_1k_umis(&self) -> f32 {
+        if self.libsize == 0 {
+            f32::NAN
+        } else {
+            (self.detected as f64 * 1000.0 / self.libsize as f64) as f32
+        }
+    }
+}
+
 impl ExprCsc {
     pub fn from_mtx(
         path: &Path,
@@ -27,18 +42,62 @@ impl ExprCsc {
         n_cells: usize,
         fast: bool,
     ) -> Result<(Self, Vec<CellStats>), InputError> {
-        let (header, mut entries) = read_entries(path)?;
-        validate_header(&header, n_genes, n_cells, fast)?;
+        Self::from_mtx_with_limits(
+            path,
+            n_genes,
+            n_cells,
+            fast,
+            MtxOrientation::GenesByCells,
+            &MtxValueLimits::default(),
+            &mut MtxValueWarnings::default(),
+        )
+    }
+
+    /// [`from_mtx`](Self::from_mtx) with the file's orientation and explicit
+    /// per-entry value limits.
+    pub fn from_mtx_with_limits(
+        path: &Path,
+        n_genes: usize,
+        n_cells: usize,
+        fast: bool,
+        orientation: MtxOrientation,
+        limits: &MtxValueLimits,
+        warnings: &mut MtxValueWarnings,
+    ) -> Result<(Self, Vec<CellStats>), InputError> {
+        Self::build_from_mtx(path, n_genes, n_cells, fast, orientation, limits, warnings)
+            .map_err(InputError::in_matrix(path))
+    }
+
+    fn build_from_mtx(
+        path: &Path,
+        n_genes: usize,
+        n_cells: usize,
+        fast: bool,
+        orientation: MtxOrientation,
+        limits: &MtxValueLimits,
+        warnings: &mut MtxValueWarnings,
+    ) -> Result<(Self, Vec<CellStats>), InputError> {
+        let (header, mut entries) = read_entries_with_limits(path, limits, warnings)?;
+        let (n_rows, n_cols) = orientation.dims(n_genes, n_cells);
+        validate_header(&header, n_rows, n_cols, fast)?;
         if !fast && header.nnz != entries.len() {
             return Err(InputError::InvalidMtxDimensions(
                 "nnz count does not match header".to_string(),
             ));
         }
+        // Entries are (column, row, value); a cells x genes file has the
+        // cell in the row, so swapping makes every entry (cell, gene, value).
+        if orientation == MtxOrientation::CellsByGenes {
+            for entry in &mut entries {
+                *entry = (entry.1, entry.0, entry.2);
+            }
+        }
 
         entries.sort_by(|a, b| match a.0.cmp(&b.0) {
             std::cmp::Ordering::Equal => a.1.cmp(&b.1),
             other => other,
         });
+        warnings.duplicate_entries_merged += merge_duplicate_entries(&mut entries)?;
 
         let mut col_counts = vec![0u64; n_cells];
         for (col, _row, _val) in &entries {
@@ -61,9 +120,8 @@ impl ExprCsc {
         let mut values = Vec::with_capacity(nnz);
         let mut stats = vec![CellStats::default(); n_cells];
 
-        let mut current_col: Option<u32> = None;
-        let mut last_row: u32 = 0;
-        for (col, row, val) in entries {
+        for (i, (col, row, val)) in entries.into_iter().enumerate() {
+            entry_checkpoint(i + 1)?;
             if !fast {
                 if row as usize >= n_genes {
                     return Err(InputError::InvalidMtxDimensions(
@@ -77,15 +135,8 @@ impl ExprCsc {
                 }
             }
 
-            if current_col != Some(col) {
-                current_col = Some(col);
-                last_row = row;
-                stats[col as usize].detected += 1;
-            } else if row != last_row {
-                stats[col as usize].detected += 1;
-                last_row = row;
-            }
-
+            // Rows are unique within a column after the merge.
+            stats[col as usize].detected += 1;
             stats[col as usize].libsize += val as u64;
             row_idx.push(row);
             values.push(val);
@@ -114,20 +165,12 @@ impl ExprCsc {
         let end = self.col_ptr[cell_idx + 1] as usize;
         let rows = &self.row_idx[start..end];
         let vals = &self.values[start..end];
+        let inv_denom = norm.inv_denom(cell_stats.libsize);
 
         rows.iter()
             .copied()
             .zip(vals.iter().copied())
-            .map(move |(row, v)| {
-                let raw = v as f32;
-                if norm.enabled {
-                    let denom = cell_stats.libsize as f32 + norm.epsilon;
-                    let scaled = raw * (norm.scale / denom);
-                    (row, scaled.ln_1p())
-                } else {
-                    (row, raw)
-                }
-            })
+            .map(move |(row, v)| (row, norm.apply(v, inv_denom)))
     }
 
     pub fn iter_cell_raw<'a>(&'a self, cell_idx: usize) -> impl Iterator<Item = (u32, u32)> + 'a {
@@ -139,14 +182,43 @@ impl ExprCsc {
     }
 }
 
+/// Sums entries of `(col, row)`-sorted `entries` that share a coordinate, so
+/// every column has strictly increasing rows. Returns how many entries were
+/// folded into an earlier one.
+fn merge_duplicate_entries(entries: &mut Vec<(u32, u32, u32)>) -> Result<usize, InputError> {
+    let before = entries.len();
+    let mut kept = 0usize;
+    for i in 0..entries.len() {
+        let (col, row, value) = entries[i];
+        if kept > 0 && entries[kept - 1].0 == col && entries[kept - 1].1 == row {
+            let sum = &mut entries[kept - 1].2;
+            *sum = sum
+                .checked_add(value)
+                .ok_or_else(|| InputError::InvalidMtxValue {
+                    location: format!("row {}, column {}", row + 1, col + 1),
+                    token: value.to_string(),
+                    reason: format!(
+                        "duplicate entries for this coordinate sum past the u32 count range (max {})",
+                        u32::MAX
+                    ),
+                })?;
+        } else {
+            entries[kept] = entries[i];
+            kept += 1;
+        }
+    }
+    entries.truncate(kept);
+    Ok(before - kept)
+}
+
 fn validate_header(
     header: &MatrixHeader,
-    n_genes: usize,
-    n_cells: usize,
+    n_rows: usize,
+    n_cols: usize,
     fast: bool,
 ) -> Result<(), InputError> {
     if !fast {
-        if header.n_rows != n_genes || header.n_cols != n_cells {
+        if header.n_rows != n_rows || header.n_cols != n_cols {
             return Err(InputError::InvalidMtxDimensions(
                 "matrix dims do not match stage1".to_string(),
             ));
@@ -14,3 +14,70 @@ impl Default for Normalization {
         }
     }
 }
+
+impl Normalization {
+    /// Per-cell multiplier `scale / (libsize + epsilon)`.
+    ///
+    /// The division is done in f64: libsizes above 2^24 are not exactly
+    /// representable in f32 and would otherwise skew every normalized value.
+    pub fn inv_denom(&self, libsize: u64) -> f32 {
+        if !self.enabled {
+            return 1.0;
+        }
+        (self.scale as f64 / (libsize as f64 + self.epsilon as f64)) as f32
+    }
+
+    /// What one count scales to before `ln_1p`, the largest per-count
+    /// weight a cell of `libsize` gives; 1 without normalization.
+    pub fn single_count_scale(&self, libsize: u64) -> f32 {
+        self.inv_denom(libsize)
+    }
+
+    pub fn apply(&self, raw: u32, inv_denom: f32) -> f32 {
+        if self.enabled {
+            (raw as f32 * inv_denom).ln_1p()
+        } else {
+            raw as f32
+        }
+    }
+}
+
+/// Named normalizations, for comparing against the default under
+/// `--debug-dual-normalization`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NormalizationMethod {
+    /// `ln(1 + 10^4 * count / libsize)`, the default.
+    Cp10k,
+    /// `ln(1 + 10^6 * count / libsize)`.
+    Cpm,
+    /// Raw counts.
+    Raw,
+}
+
+impl NormalizationMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NormalizationMethod::Cp10k => "cp10k",
+            NormalizationMethod::Cpm => "cpm",
+            NormalizationMethod::Raw => "raw",
+        }
+    }
+
+    pub fn normalization(&self) -> Normalization {
+        match self {
+            NormalizationMethod::Cp10k => Normalization::default(),
+            NormalizationMethod::Cpm => Normalization {
+                scale: 1_000_000.0,
+                ..Normalization::default()
+            },
+            NormalizationMethod::Raw => Normalization {
+                enabled: false,
+                ..Normalization::default()
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "../../tests/src_inline/expr/normalize.rs"]
+mod tests;
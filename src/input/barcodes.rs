@@ -1,30 +1,98 @@
 use std::path::Path;
 
-use kira_scio::api::{Reader, ReaderOptions};
-use kira_scio::detect::DetectedFormat;
+use tracing::warn;
 
 use crate::input::InputError;
+use crate::input::delimiter::{Delimiter, read_delimited};
 
-pub fn read_barcodes(path: &Path) -> Result<Vec<String>, InputError> {
-    let md = Reader::with_options(
-        path,
-        ReaderOptions {
-            force_format: Some(DetectedFormat::Mtx10x),
-            strict: true,
-        },
-    )
-    .read_metadata()
-    .map_err(|e| InputError::InvalidTsvRow {
-        line: 0,
-        reason: e.message,
-    })?;
-
-    if md.barcodes.is_empty() {
-        return Err(InputError::InvalidTsvRow {
-            line: 0,
-            reason: "no barcodes found".to_string(),
+/// First-line values (compared case-insensitively) always taken for a header.
+pub const HEADER_NAMES: [&str; 3] = ["barcode", "barcodes", "cell_id"];
+
+/// Shortest run of `ACGTN` that makes a value look like a sequenced barcode.
+const MIN_BARCODE_BASES: usize = 6;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BarcodesFile {
+    pub barcodes: Vec<String>,
+    pub delimiter: Delimiter,
+    /// First line skipped as a column header.
+    pub header: Option<String>,
+}
+
+/// Reads barcodes from the first column of a tab- or whitespace-delimited
+/// file; see [`read_delimited`]. `has_header` forces the first line to be
+/// skipped (`Some(true)`) or kept (`Some(false)`); `None` skips it when
+/// [`looks_like_header`] says so.
+pub fn read_barcodes(path: &Path, has_header: Option<bool>) -> Result<BarcodesFile, InputError> {
+    let (delimiter, rows) = read_delimited(path)?;
+    let mut barcodes = rows
+        .into_iter()
+        .map(|mut row| match row.fields.swap_remove(0) {
+            barcode if barcode.is_empty() => Err(InputError::EmptyBarcode {
+                path: path.to_path_buf(),
+                line: row.line,
+            }),
+            barcode => Ok(barcode),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let skip = has_header.unwrap_or_else(|| looks_like_header(&barcodes));
+    let header = (skip && !barcodes.is_empty()).then(|| barcodes.remove(0));
+    if let Some(header) = &header
+        && has_header.is_none()
+    {
+        warn!(
+            path = %path.display(),
+            header = %header,
+            "barcodes file starts with a header line; skipped it (--barcodes-has-header false keeps it)"
+        );
+    }
+
+    if barcodes.is_empty() {
+        return Err(InputError::EmptyFile {
+            path: path.to_path_buf(),
+            expected: "barcodes".to_string(),
         });
     }
 
-    Ok(md.barcodes)
+    Ok(BarcodesFile {
+        barcodes,
+        delimiter,
+        header,
+    })
 }
+
+/// The first value is a header when it is one of [`HEADER_NAMES`], or when it
+/// is the only value that does not look like a sequenced barcode.
+pub fn looks_like_header(barcodes: &[String]) -> bool {
+    let Some((first, rest)) = barcodes.split_first() else {
+        return false;
+    };
+    if HEADER_NAMES
+        .iter()
+        .any(|name| first.eq_ignore_ascii_case(name))
+    {
+        return true;
+    }
+    !rest.is_empty() && !looks_like_barcode(first) && rest.iter().all(|b| looks_like_barcode(b))
+}
+
+/// `ACGTN` bases with an optional `<prefix>_`/`:`/`#` in front and an
+/// optional `-<digits>` suffix, e.g. `AAACCTGAGAAACCAT-1` or
+/// `s1_AAACCTGAGAAACCAT`.
+fn looks_like_barcode(value: &str) -> bool {
+    let core = value.rsplit(['_', ':', '#']).next().unwrap_or(value);
+    let core = match core.rsplit_once('-') {
+        Some((bases, suffix))
+            if !suffix.is_empty() && suffix.bytes().all(|b| b.is_ascii_digit()) =>
+        {
+            bases
+        }
+        _ => core,
+    };
+    core.len() >= MIN_BARCODE_BASES && core.bytes().all(|b| b"ACGTN".contains(&b))
+}
+
+#[cfg(test)]
+#[path = "../../tests/src_inline/input/barcodes.rs"]
+mod tests;
@@ -2,13 +2,16 @@ use std::fs::File;
 use std::io::{BufReader, BufWriter, Read, Write};
 use std::path::Path;
 use std::sync::Arc;
+use std::time::SystemTime;
 
 use crc::{CRC_64_ECMA_182, Crc};
 use memmap2::Mmap;
+use rayon::prelude::*;
 use thiserror::Error;
 
 use crate::expr::csc::{CellStats, ExprCsc};
 use crate::expr::normalize::Normalization;
+use crate::report::artifact::write_options;
 use crate::simd;
 
 const MAGIC_EXPR: &[u8; 8] = b"KIRAEXPR";
@@ -40,9 +43,60 @@ pub struct SharedCacheMetadata {
     pub barcodes: Vec<String>,
 }
 
+/// Which file a shared cache mapping was read from, taken from the open
+/// handle: device and inode (0 off Unix), length, modification time and the
+/// header CRC64. A cache regenerated in place or swapped by rename changes
+/// at least one of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheIdentity {
+    pub dev: u64,
+    pub ino: u64,
+    pub len: u64,
+    pub modified: Option<SystemTime>,
+    pub header_crc64: u64,
+}
+
+impl std::fmt::Display for CacheIdentity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let modified = self
+            .modified
+            .and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok())
+            .map_or_else(
+                || "?".to_string(),
+                |d| format!("{}.{:09}", d.as_secs(), d.subsec_nanos()),
+            );
+        write!(
+            f,
+            "dev {} inode {} {} bytes mtime {} header crc {:016x}",
+            self.dev, self.ino, self.len, modified, self.header_crc64
+        )
+    }
+}
+
+impl CacheIdentity {
+    fn of(file: &File, header_crc64: u64) -> std::io::Result<Self> {
+        let meta = file.metadata()?;
+        #[cfg(unix)]
+        let (dev, ino) = {
+            use std::os::unix::fs::MetadataExt;
+            (meta.dev(), meta.ino())
+        };
+        #[cfg(not(unix))]
+        let (dev, ino) = (0, 0);
+        Ok(Self {
+            dev,
+            ino,
+            len: meta.len(),
+            modified: meta.modified().ok(),
+            header_crc64,
+        })
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct SharedCacheMapped {
     mmap: Arc<Mmap>,
+    identity: CacheIdentity,
     pub n_genes: usize,
     pub n_cells: usize,
     pub nnz: usize,
@@ -54,6 +108,11 @@ pub struct SharedCacheMapped {
 }
 
 impl SharedCacheMapped {
+    /// The file this mapping was opened from.
+    pub fn identity(&self) -> CacheIdentity {
+        self.identity
+    }
+
     pub fn metadata(&self) -> SharedCacheMetadata {
         SharedCacheMetadata {
             n_genes: self.n_genes,
@@ -79,11 +138,19 @@ impl SharedCacheMapped {
         read_u32_slice(&self.mmap[base..base + 4])
     }
 
+    /// Entry range of `cell`, clamped to `nnz`. Parsing already rejects
+    /// out-of-range `col_ptr`; the clamp keeps the unchecked slices below in
+    /// bounds even if the mapped file changes underneath.
+    fn cell_range(&self, cell: usize) -> (usize, usize) {
+        let end = (self.col_ptr_at(cell + 1) as usize).min(self.nnz);
+        let start = (self.col_ptr_at(cell) as usize).min(end);
+        (start, end)
+    }
+
     pub fn compute_cell_stats(&self) -> Vec<CellStats> {
         let mut stats = vec![CellStats::default(); self.n_cells];
         for (cell, stat) in stats.iter_mut().enumerate().take(self.n_cells) {
-            let start = self.col_ptr_at(cell) as usize;
-            let end = self.col_ptr_at(cell + 1) as usize;
+            let (start, end) = self.cell_range(cell);
             stat.detected = (end - start) as u32;
             stat.libsize = self.sum_values_range(start, end);
         }
@@ -99,16 +166,9 @@ impl SharedCacheMapped {
     ) where
         F: FnMut(u32, f32),
     {
+        let inv_denom = norm.inv_denom(cell_stats.libsize);
         self.for_each_cell_raw(cell_idx, |row, raw_count| {
-            let raw = raw_count as f32;
-            let out = if norm.enabled {
-                let denom = cell_stats.libsize as f32 + norm.epsilon;
-                let scaled = raw * (norm.scale / denom);
-                scaled.ln_1p()
-            } else {
-                raw
-            };
-            f(row, out);
+            f(row, norm.apply(raw_count, inv_denom));
         });
     }
 
@@ -116,12 +176,12 @@ impl SharedCacheMapped {
     where
         F: FnMut(u32, u32),
     {
-        let start = self.col_ptr_at(cell_idx) as usize;
-        let end = self.col_ptr_at(cell_idx + 1) as usize;
+        let (start, end) = self.cell_range(cell_idx);
 
         #[cfg(target_endian = "little")]
         {
-            // SAFETY: row/value sections are validated and range comes from validated col_ptr.
+            // SAFETY: row/value sections hold `nnz` entries (bounds-checked at parse)
+            // and `cell_range` clamps the range to `nnz`.
             unsafe {
                 let rows_ptr =
                     self.mmap.as_ptr().add(self.row_idx_offset + start * 4) as *const u32;
@@ -147,7 +207,7 @@ impl SharedCacheMapped {
     fn sum_values_range(&self, start: usize, end: usize) -> u64 {
         #[cfg(target_endian = "little")]
         {
-            // SAFETY: values_u32 section is validated; bounds are constrained by caller using col_ptr.
+            // SAFETY: values section holds `nnz` entries; callers pass a `cell_range`.
             unsafe {
                 let ptr = self.mmap.as_ptr().add(self.values_offset + start * 4) as *const u32;
                 let slice = std::slice::from_raw_parts(ptr, end - start);
@@ -170,27 +230,48 @@ pub fn read_shared_cache_metadata(path: &Path) -> Result<SharedCacheMetadata, Ca
     Ok(mapped.metadata())
 }
 
+/// How much of the CSC structure a shared cache is checked for when mapped.
+/// `col_ptr` is always checked in full, since the accessors slice by it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CscCheck {
+    /// Every row index ([`validate_csc`]).
+    #[default]
+    Full,
+    /// Row indices of an evenly spaced sample of cells
+    /// ([`validate_csc_sampled`]); `--verify-cache quick`.
+    Sampled,
+    /// `col_ptr` only.
+    ColPtr,
+}
+
+/// Cells whose row indices [`validate_csc_sampled`] checks, at most.
+pub const CSC_SAMPLE_CELLS: usize = 4096;
+
 pub fn mmap_shared_cache(path: &Path) -> Result<SharedCacheMapped, CacheError> {
-    let file = File::open(path)?;
-    let mmap = {
-        // SAFETY: mapping file read-only and holding Arc<Mmap> for lifetime of view.
-        unsafe { Mmap::map(&file)? }
-    };
-    parse_shared_cache(Arc::new(mmap), true)
+    map_shared_cache(path, CscCheck::Full)
+}
+
+pub fn mmap_shared_cache_sampled(path: &Path) -> Result<SharedCacheMapped, CacheError> {
+    map_shared_cache(path, CscCheck::Sampled)
 }
 
 pub fn mmap_shared_cache_unchecked(path: &Path) -> Result<SharedCacheMapped, CacheError> {
+    map_shared_cache(path, CscCheck::ColPtr)
+}
+
+pub fn map_shared_cache(path: &Path, check: CscCheck) -> Result<SharedCacheMapped, CacheError> {
     let file = File::open(path)?;
     let mmap = {
         // SAFETY: mapping file read-only and holding Arc<Mmap> for lifetime of view.
         unsafe { Mmap::map(&file)? }
     };
-    parse_shared_cache(Arc::new(mmap), false)
+    parse_shared_cache(Arc::new(mmap), check, |crc| CacheIdentity::of(&file, crc))
 }
 
 fn parse_shared_cache(
     mmap: Arc<Mmap>,
-    validate_csc_strict: bool,
+    check: CscCheck,
+    identity: impl FnOnce(u64) -> std::io::Result<CacheIdentity>,
 ) -> Result<SharedCacheMapped, CacheError> {
     if mmap.len() < SHARED_HEADER_SIZE {
         return Err(CacheError::InvalidFormat(
@@ -301,11 +382,18 @@ fn parse_shared_cache(
         "barcodes",
     )?;
 
-    if validate_csc_strict {
-        validate_csc(&mmap, n_genes, n_cells, nnz, col_ptr_offset, row_idx_offset)?;
+    // Always checked, even unchecked: the accessors slice the mmap by col_ptr.
+    let col_ptr = &mmap[col_ptr_offset..col_ptr_offset + col_ptr_bytes];
+    validate_col_ptr(col_ptr, nnz)?;
+    let row_idx = &mmap[row_idx_offset..row_idx_offset + row_idx_bytes];
+    match check {
+        CscCheck::Full => validate_csc(col_ptr, row_idx, n_genes)?,
+        CscCheck::Sampled => validate_csc_sampled(col_ptr, row_idx, n_genes, CSC_SAMPLE_CELLS)?,
+        CscCheck::ColPtr => {}
     }
 
     Ok(SharedCacheMapped {
+        identity: identity(header_crc64)?,
         mmap,
         n_genes,
         n_cells,
@@ -342,7 +430,7 @@ fn check_bounds(
     Ok(())
 }
 
-fn parse_string_table(
+pub(crate) fn parse_string_table(
     mmap: &[u8],
     offset: usize,
     bytes: usize,
@@ -409,62 +497,103 @@ fn parse_string_table(
     Ok(out)
 }
 
-fn validate_csc(
-    mmap: &[u8],
-    n_genes: usize,
-    n_cells: usize,
-    nnz: usize,
-    col_ptr_offset: usize,
-    row_idx_offset: usize,
-) -> Result<(), CacheError> {
-    let mut prev_col_ptr = 0u64;
-    for i in 0..=n_cells {
-        let base = col_ptr_offset + i * 8;
-        let v = read_u64_slice(&mmap[base..base + 8]);
-        if i == 0 && v != 0 {
-            return Err(CacheError::InvalidFormat(
-                "col_ptr[0] must be 0".to_string(),
-            ));
-        }
-        if i > 0 && v < prev_col_ptr {
-            return Err(CacheError::InvalidFormat(
-                "col_ptr must be monotonic".to_string(),
-            ));
-        }
-        prev_col_ptr = v;
+/// `col_ptr[0] == 0`, non-decreasing, `col_ptr[n_cells] == nnz`.
+fn validate_col_ptr(bytes: &[u8], nnz: usize) -> Result<(), CacheError> {
+    let mut values = bytes.chunks_exact(8).map(read_u64_slice);
+    if values.next() != Some(0) {
+        return Err(CacheError::InvalidFormat(
+            "col_ptr[0] must be 0".to_string(),
+        ));
+    }
+    // Branch-free fold so the scan vectorizes; position of a failure is not needed.
+    let (last, decreasing) = values.fold((0u64, false), |(prev, bad), v| (v, bad | (v < prev)));
+    if decreasing {
+        return Err(CacheError::InvalidFormat(
+            "col_ptr must be monotonic".to_string(),
+        ));
     }
-    if prev_col_ptr as usize != nnz {
+    if last != nnz as u64 {
         return Err(CacheError::InvalidFormat(
             "col_ptr[n_cells] must equal nnz".to_string(),
         ));
     }
+    Ok(())
+}
 
-    for cell in 0..n_cells {
-        let start = read_u64_slice(&mmap[col_ptr_offset + cell * 8..col_ptr_offset + cell * 8 + 8])
-            as usize;
-        let end = read_u64_slice(
-            &mmap[col_ptr_offset + (cell + 1) * 8..col_ptr_offset + (cell + 1) * 8 + 8],
-        ) as usize;
-        let mut prev_row: Option<u32> = None;
-        for i in start..end {
-            let row = read_u32_slice(&mmap[row_idx_offset + i * 4..row_idx_offset + i * 4 + 4]);
-            if row as usize >= n_genes {
-                return Err(CacheError::InvalidFormat(
-                    "row_idx out of bounds".to_string(),
-                ));
-            }
-            if let Some(prev) = prev_row {
-                if row <= prev {
-                    return Err(CacheError::InvalidFormat(
-                        "row_idx must be strictly increasing per column".to_string(),
-                    ));
-                }
-            }
-            prev_row = Some(row);
+/// Cells per rayon task in [`validate_csc`].
+const CSC_CHECK_CHUNK_CELLS: usize = 4096;
+
+/// Row-index checks on top of [`validate_col_ptr`] (which `col_ptr` must have
+/// passed): every row below `n_genes` and strictly increasing within its
+/// cell. Cell ranges are checked in parallel; the error names the first bad
+/// entry in matrix order, so it does not depend on the thread count.
+pub fn validate_csc(col_ptr: &[u8], row_idx: &[u8], n_genes: usize) -> Result<(), CacheError> {
+    let n_cells = col_ptr.len() / 8 - 1;
+    let chunks: Vec<usize> = (0..n_cells).step_by(CSC_CHECK_CHUNK_CELLS).collect();
+    let first_bad = chunks.par_iter().find_map_first(|&first| {
+        let last = (first + CSC_CHECK_CHUNK_CELLS).min(n_cells);
+        (first..last).find_map(|cell| check_cell_rows(col_ptr, row_idx, n_genes, cell).err())
+    });
+    first_bad.map_or(Ok(()), Err)
+}
+
+/// [`validate_csc`] on at most `max_cells` evenly spaced cells (always the
+/// first and the last), stopping at the first bad one. Catches a truncated
+/// or misaligned `row_idx` section quickly; a single bad cell can slip by.
+pub fn validate_csc_sampled(
+    col_ptr: &[u8],
+    row_idx: &[u8],
+    n_genes: usize,
+    max_cells: usize,
+) -> Result<(), CacheError> {
+    let n_cells = col_ptr.len() / 8 - 1;
+    if n_cells == 0 || max_cells == 0 {
+        return Ok(());
+    }
+    let step = n_cells.div_ceil(max_cells).max(1);
+    (0..n_cells)
+        .step_by(step)
+        .chain(std::iter::once(n_cells - 1))
+        .try_for_each(|cell| check_cell_rows(col_ptr, row_idx, n_genes, cell))
+}
+
+/// Row checks of one cell. The scan is a branch-free fold over unaligned
+/// little-endian reads; only a failing cell is walked again for the entry.
+fn check_cell_rows(
+    col_ptr: &[u8],
+    row_idx: &[u8],
+    n_genes: usize,
+    cell: usize,
+) -> Result<(), CacheError> {
+    let start = read_u64_slice(&col_ptr[cell * 8..cell * 8 + 8]) as usize;
+    let end = read_u64_slice(&col_ptr[cell * 8 + 8..cell * 8 + 16]) as usize;
+    let rows = row_idx[start * 4..end * 4]
+        .chunks_exact(4)
+        .map(read_u32_slice);
+    let n_genes = n_genes.min(u32::MAX as usize + 1) as u64;
+    let (_, bad) = rows.clone().fold((None::<u32>, false), |(prev, bad), row| {
+        let out_of_order = prev.is_some_and(|p| row <= p);
+        (Some(row), bad | out_of_order | (row as u64 >= n_genes))
+    });
+    if !bad {
+        return Ok(());
+    }
+    let mut prev: Option<u32> = None;
+    for (entry, row) in (start..end).zip(rows) {
+        if row as u64 >= n_genes {
+            return Err(CacheError::InvalidFormat(format!(
+                "row_idx out of bounds at cell {cell}, entry {entry}: row {row}, n_genes {n_genes}"
+            )));
         }
+        if let Some(prev) = prev.filter(|&p| row <= p) {
+            return Err(CacheError::InvalidFormat(format!(
+                "row_idx must be strictly increasing per column at cell {cell}, entry {entry}: \
+                 row {row} after {prev}"
+            )));
+        }
+        prev = Some(row);
     }
-
-    Ok(())
+    unreachable!("fold and walk disagree on cell {cell}")
 }
 
 fn read_u16_slice(slice: &[u8]) -> u16 {
@@ -485,13 +614,99 @@ fn read_u64_slice(slice: &[u8]) -> u64 {
     u64::from_le_bytes(buf)
 }
 
+/// Writes `expr` as a v1 shared cache (`KORG`) readable by
+/// [`mmap_shared_cache`]. Sections are 64-byte aligned; no optional blocks.
+pub fn write_shared_cache(
+    path: &Path,
+    genes: &[String],
+    barcodes: &[String],
+    expr: &ExprCsc,
+) -> Result<(), CacheError> {
+    if genes.len() != expr.n_genes || barcodes.len() != expr.n_cells {
+        return Err(CacheError::InvalidFormat(
+            "gene/barcode counts do not match matrix".to_string(),
+        ));
+    }
+    let genes_table = encode_string_table(genes, "genes")?;
+    let barcodes_table = encode_string_table(barcodes, "barcodes")?;
+
+    let align64 = |x: usize| (x + 63) & !63;
+    let genes_off = align64(SHARED_HEADER_SIZE);
+    let barcodes_off = align64(genes_off + genes_table.len());
+    let col_ptr_off = align64(barcodes_off + barcodes_table.len());
+    let row_idx_off = align64(col_ptr_off + expr.col_ptr.len() * 8);
+    let values_off = align64(row_idx_off + expr.row_idx.len() * 4);
+    let file_bytes = values_off + expr.values.len() * 4;
+
+    let mut out = vec![0u8; file_bytes];
+    out[0..4].copy_from_slice(SHARED_MAGIC);
+    out[4..6].copy_from_slice(&1u16.to_le_bytes());
+    out[8..12].copy_from_slice(&SHARED_ENDIAN_TAG.to_le_bytes());
+    out[12..16].copy_from_slice(&(SHARED_HEADER_SIZE as u32).to_le_bytes());
+    let fields = [
+        expr.n_genes,
+        expr.n_cells,
+        expr.nnz,
+        genes_off,
+        genes_table.len(),
+        barcodes_off,
+        barcodes_table.len(),
+        col_ptr_off,
+        row_idx_off,
+        values_off,
+        0,
+        0,
+        file_bytes,
+    ];
+    for (i, v) in fields.iter().enumerate() {
+        out[16 + i * 8..24 + i * 8].copy_from_slice(&(*v as u64).to_le_bytes());
+    }
+    let crc = CRC64.checksum(&out[..SHARED_HEADER_SIZE]);
+    out[120..128].copy_from_slice(&crc.to_le_bytes());
+
+    out[genes_off..genes_off + genes_table.len()].copy_from_slice(&genes_table);
+    out[barcodes_off..barcodes_off + barcodes_table.len()].copy_from_slice(&barcodes_table);
+    for (i, v) in expr.col_ptr.iter().enumerate() {
+        out[col_ptr_off + i * 8..col_ptr_off + i * 8 + 8].copy_from_slice(&v.to_le_bytes());
+    }
+    for (i, v) in expr.row_idx.iter().enumerate() {
+        out[row_idx_off + i * 4..row_idx_off + i * 4 + 4].copy_from_slice(&v.to_le_bytes());
+    }
+    for (i, v) in expr.values.iter().enumerate() {
+        out[values_off + i * 4..values_off + i * 4 + 4].copy_from_slice(&v.to_le_bytes());
+    }
+
+    std::fs::write(path, out)?;
+    Ok(())
+}
+
+/// `count`, `count + 1` offsets into the blob, then the concatenated bytes.
+pub(crate) fn encode_string_table(values: &[String], label: &str) -> Result<Vec<u8>, CacheError> {
+    let too_large = || CacheError::InvalidFormat(format!("{} table exceeds 4 GiB", label));
+    let mut blob = Vec::new();
+    let mut out = Vec::with_capacity(4 + (values.len() + 1) * 4);
+    out.extend_from_slice(
+        &u32::try_from(values.len())
+            .map_err(|_| too_large())?
+            .to_le_bytes(),
+    );
+    out.extend_from_slice(&0u32.to_le_bytes());
+    for s in values {
+        blob.extend_from_slice(s.as_bytes());
+        let end = u32::try_from(blob.len()).map_err(|_| too_large())?;
+        out.extend_from_slice(&end.to_le_bytes());
+    }
+    out.extend_from_slice(&blob);
+    Ok(out)
+}
+
 pub fn write_expr_cache(
     path: &Path,
     expr: &ExprCsc,
     stats: &[CellStats],
 ) -> Result<(), CacheError> {
     let file = File::create(path)?;
-    let mut writer = BufWriter::new(file);
+    let mut writer = BufWriter::with_capacity(write_options().buffer_size, file);
 
     writer.write_all(MAGIC_EXPR)?;
     writer.write_all(&VERSION_EXPR.to_le_bytes())?;
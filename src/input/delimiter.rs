@@ -0,0 +1,95 @@
+//! Column delimiter sniffing for features and barcodes files.
+//!
+//! Tab is preferred. Files whose first non-empty line has no tab are split on
+//! any run of whitespace, which covers conversion scripts that write
+//! space-separated `genes.tsv`. A line that does not match the sniffed
+//! delimiter is rejected with its line number instead of being misparsed.
+
+use std::io::BufRead;
+use std::path::Path;
+
+use crate::input::{InputError, open_reader};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Delimiter {
+    #[default]
+    Tab,
+    Whitespace,
+}
+
+impl Delimiter {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Delimiter::Tab => "tab",
+            Delimiter::Whitespace => "whitespace",
+        }
+    }
+
+    /// Tab when `first_line` contains one, otherwise any whitespace.
+    pub fn sniff(first_line: &str) -> Self {
+        if first_line.contains('\t') {
+            Delimiter::Tab
+        } else {
+            Delimiter::Whitespace
+        }
+    }
+
+    /// Whether `line` could come from a file with this delimiter: tab files
+    /// may have spaces inside fields but not space-only separators, and
+    /// whitespace files may not contain tabs.
+    fn matches(&self, line: &str) -> bool {
+        match self {
+            Delimiter::Tab => line.contains('\t') || !line.contains(' '),
+            Delimiter::Whitespace => !line.contains('\t'),
+        }
+    }
+
+    fn split<'a>(&self, line: &'a str) -> Vec<&'a str> {
+        match self {
+            Delimiter::Tab => line.split('\t').collect(),
+            Delimiter::Whitespace => line.split_whitespace().collect(),
+        }
+    }
+}
+
+/// One non-empty line split into fields, with its 1-based line number.
+#[derive(Debug, Clone)]
+pub struct DelimitedRow {
+    pub line: usize,
+    pub fields: Vec<String>,
+}
+
+/// Reads every non-empty line of `path` split on the delimiter sniffed from
+/// the first one.
+pub fn read_delimited(path: &Path) -> Result<(Delimiter, Vec<DelimitedRow>), InputError> {
+    let reader = open_reader(path)?;
+    let mut delimiter = None;
+    let mut rows = Vec::new();
+    for (idx, line) in reader.lines().enumerate() {
+        let line = line.map_err(InputError::io(path))?;
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() {
+            continue;
+        }
+        let delimiter = *delimiter.get_or_insert_with(|| Delimiter::sniff(line));
+        if !delimiter.matches(line) {
+            return Err(InputError::InvalidTsvRow {
+                path: path.to_path_buf(),
+                line: idx + 1,
+                reason: format!(
+                    "mixed delimiters (file is {}-delimited)",
+                    delimiter.as_str()
+                ),
+            });
+        }
+        rows.push(DelimitedRow {
+            line: idx + 1,
+            fields: delimiter
+                .split(line)
+                .into_iter()
+                .map(str::to_string)
+                .collect(),
+        });
+    }
+    Ok((delimiter.unwrap_or_default(), rows))
+}
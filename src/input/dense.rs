@@ -0,0 +1,161 @@
+//! Dense genes × cells count tables, as sent for small plate-based datasets.
+//!
+//! The first non-empty line holds a corner cell (ignored) and one barcode per
+//! cell; every further line holds a gene symbol and one count per cell.
+//! Fields are tab-separated, or comma-separated for `*.csv` / `*.csv.gz`;
+//! surrounding double quotes (R and pandas exports) are dropped. Counts must
+//! be non-negative integers. Zeros are skipped, and the CSC matrix and the
+//! per-cell stats are built in the single pass over the file.
+
+use std::io::BufRead;
+use std::path::Path;
+
+use crate::expr::csc::{CellStats, ExprCsc};
+use crate::input::features::FeatureRow;
+use crate::input::{InputError, open_reader};
+
+/// A dense table read into the same shapes the 10x loaders produce.
+#[derive(Debug, Clone)]
+pub struct DenseMatrix {
+    /// Gene rows in file order; the symbol doubles as the id.
+    pub genes: Vec<FeatureRow>,
+    pub barcodes: Vec<String>,
+    pub expr: ExprCsc,
+    pub cell_stats: Vec<CellStats>,
+}
+
+/// Whether `path` names a comma-separated table.
+fn is_csv(path: &Path) -> bool {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    name.ends_with(".csv") || name.ends_with(".csv.gz")
+}
+
+fn unquote(field: &str) -> &str {
+    let field = field.trim();
+    field
+        .strip_prefix('"')
+        .and_then(|f| f.strip_suffix('"'))
+        .unwrap_or(field)
+}
+
+pub fn read_dense_matrix(path: &Path) -> Result<DenseMatrix, InputError> {
+    let separator = if is_csv(path) { ',' } else { '\t' };
+    let reader = open_reader(path)?;
+
+    let mut barcodes: Vec<String> = Vec::new();
+    let mut genes = Vec::new();
+    let mut cells: Vec<(Vec<u32>, Vec<u32>)> = Vec::new();
+    let mut cell_stats: Vec<CellStats> = Vec::new();
+    for (idx, line) in reader.lines().enumerate() {
+        let line_no = idx + 1;
+        let line = line.map_err(InputError::io(path))?;
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() {
+            continue;
+        }
+        let mut fields = line.split(separator).map(unquote);
+
+        if barcodes.is_empty() {
+            fields.next();
+            barcodes = fields.map(str::to_string).collect();
+            if barcodes.is_empty() {
+                return Err(InputError::InvalidTsvRow {
+                    path: path.to_path_buf(),
+                    line: line_no,
+                    reason: "header row has no barcodes after the gene column".to_string(),
+                });
+            }
+            if barcodes.iter().any(String::is_empty) {
+                return Err(InputError::EmptyBarcode {
+                    path: path.to_path_buf(),
+                    line: line_no,
+                });
+            }
+            cells = vec![(Vec::new(), Vec::new()); barcodes.len()];
+            cell_stats = vec![CellStats::default(); barcodes.len()];
+            continue;
+        }
+
+        let symbol = fields.next().unwrap_or_default().to_string();
+        if symbol.is_empty() {
+            return Err(InputError::InvalidTsvRow {
+                path: path.to_path_buf(),
+                line: line_no,
+                reason: "empty gene symbol".to_string(),
+            });
+        }
+        let row = genes.len() as u32;
+        let mut n_values = 0usize;
+        for (cell, token) in fields.enumerate() {
+            n_values += 1;
+            if cell >= barcodes.len() {
+                continue;
+            }
+            let value: u32 = token.parse().map_err(|_| InputError::InvalidTsvField {
+                file: path.to_path_buf(),
+                line: line_no,
+                column: barcodes[cell].clone(),
+                reason: format!("expected a non-negative integer count, found '{token}'"),
+            })?;
+            if value == 0 {
+                continue;
+            }
+            let (rows, values) = &mut cells[cell];
+            rows.push(row);
+            values.push(value);
+            cell_stats[cell].libsize += u64::from(value);
+            cell_stats[cell].detected += 1;
+        }
+        if n_values != barcodes.len() {
+            return Err(InputError::InvalidTsvRow {
+                path: path.to_path_buf(),
+                line: line_no,
+                reason: format!(
+                    "gene {symbol} has {n_values} values, the header has {} barcodes",
+                    barcodes.len()
+                ),
+            });
+        }
+        genes.push(FeatureRow {
+            id: symbol.clone(),
+            symbol,
+        });
+    }
+    if genes.is_empty() {
+        return Err(InputError::EmptyFile {
+            path: path.to_path_buf(),
+            expected: "gene rows".to_string(),
+        });
+    }
+
+    let nnz: usize = cells.iter().map(|(rows, _)| rows.len()).sum();
+    let mut col_ptr = Vec::with_capacity(cells.len() + 1);
+    let mut row_idx = Vec::with_capacity(nnz);
+    let mut values = Vec::with_capacity(nnz);
+    col_ptr.push(0u64);
+    for (rows, vals) in cells {
+        row_idx.extend(rows);
+        values.extend(vals);
+        col_ptr.push(row_idx.len() as u64);
+    }
+    Ok(DenseMatrix {
+        expr: ExprCsc {
+            n_genes: genes.len(),
+            n_cells: barcodes.len(),
+            nnz,
+            col_ptr,
+            row_idx,
+            values,
+        },
+        genes,
+        barcodes,
+        cell_stats,
+    })
+}
+
+#[cfg(test)]
+#[path = "../../tests/src_inline/input/dense.rs"]
+mod tests;
@@ -8,6 +8,10 @@ use crate::input::InputError;
 pub enum TenXFormat {
     TenXv2,
     TenXv3,
+    /// A dense genes × cells table (`--input-format dense`).
+    Dense,
+    /// An AnnData `.h5ad` file.
+    H5ad,
     Unknown,
 }
 
@@ -16,6 +20,8 @@ impl std::fmt::Display for TenXFormat {
         match self {
             TenXFormat::TenXv2 => write!(f, "tenx_v2"),
             TenXFormat::TenXv3 => write!(f, "tenx_v3"),
+            TenXFormat::Dense => write!(f, "dense"),
+            TenXFormat::H5ad => write!(f, "h5ad"),
             TenXFormat::Unknown => write!(f, "unknown"),
         }
     }
@@ -30,20 +36,121 @@ pub struct TenXLayout {
     pub prefix: Option<String>,
 }
 
+/// Name parts that mark a file as one of the three 10x inputs.
+const TENX_NAME_PARTS: [&str; 4] = ["matrix", "features", "genes", "barcodes"];
+
+/// Whether `--input` is an AnnData file (`*.h5ad`, any case).
+pub fn is_h5ad(path: &Path) -> bool {
+    path.is_file()
+        && path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("h5ad"))
+}
+
+/// `--input` as given when it is a directory (or missing, which the loaders
+/// report). A matrix file (`*.mtx`, `*.mtx.gz`) stands for its directory,
+/// with a warning; any other file is an [`InputError::InputIsFile`] listing
+/// the 10x-looking files next to it. AnnData files ([`is_h5ad`]) are read
+/// before this is reached.
+pub fn resolve_input_dir(path: &Path) -> Result<PathBuf, InputError> {
+    if !path.is_file() {
+        return Ok(path.to_path_buf());
+    }
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    if name.ends_with(".mtx") || name.ends_with(".mtx.gz") {
+        warn!(
+            input = %path.display(),
+            using = %parent.display(),
+            "--input is a matrix file; reading its directory instead"
+        );
+        return Ok(parent);
+    }
+    let candidates = file_names(&parent, |name| {
+        let name = name.to_ascii_lowercase();
+        TENX_NAME_PARTS.iter().any(|part| name.contains(part))
+    })?;
+    Err(InputError::InputIsFile {
+        path: path.to_path_buf(),
+        parent,
+        candidates,
+    })
+}
+
+/// `--cache` as given unless it is a directory: then the shared cache in it
+/// (see [`find_shared_cache_file`]) with a warning, or an
+/// [`InputError::CacheIsDirectory`] listing its files when there is none.
+pub fn resolve_cache_path(path: &Path) -> Result<PathBuf, InputError> {
+    if !path.is_dir() {
+        return Ok(path.to_path_buf());
+    }
+    let prefix = detect_prefix(path)?;
+    if let Some(cache) = find_shared_cache_file(path, prefix.as_deref())? {
+        warn!(
+            cache = %path.display(),
+            using = %cache.display(),
+            "--cache is a directory; reading the shared cache in it"
+        );
+        return Ok(cache);
+    }
+    Err(InputError::CacheIsDirectory {
+        path: path.to_path_buf(),
+        candidates: file_names(path, |_| true)?,
+    })
+}
+
+/// Sorted names of the regular files in `dir` that `keep` accepts.
+fn file_names(dir: &Path, keep: impl Fn(&str) -> bool) -> Result<Vec<String>, InputError> {
+    let mut names = Vec::new();
+    for entry in std::fs::read_dir(dir).map_err(InputError::io(dir))? {
+        let entry = entry.map_err(InputError::io(dir))?;
+        if !entry.file_type().map_err(InputError::io(dir))?.is_file() {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if keep(&name) {
+            names.push(name);
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+/// The matrix, features (v3) or genes (v2) and barcodes files of a 10x
+/// directory. Having both a features and a genes file is an
+/// [`InputError::LayoutAmbiguous`]: either could be the one the matrix rows
+/// follow.
 pub fn detect_10x_dir(dir: &Path) -> Result<TenXLayout, InputError> {
-    let ds = kira_scio::discover(dir).map_err(|e| InputError::MissingFile(e.message))?;
-    let barcodes = ds
-        .barcodes
-        .ok_or_else(|| InputError::MissingFile("barcodes.tsv[.gz]".to_string()))?;
-
-    let (features_path, format) = if let Some(features) = ds.features {
-        (features, TenXFormat::TenXv3)
-    } else if let Some(genes) = ds.genes {
-        (genes, TenXFormat::TenXv2)
-    } else {
-        return Err(InputError::MissingFile(
-            "features.tsv/genes.tsv[.gz]".to_string(),
-        ));
+    let prefix = detect_prefix(dir)?;
+    let ds = kira_scio::discover(dir).map_err(|e| InputError::InvalidLayout {
+        dir: dir.to_path_buf(),
+        reason: e.message,
+    })?;
+    let expected = |name: &str| InputError::MissingFile {
+        path: dir.join(match &prefix {
+            Some(prefix) => format!("{prefix}_{name}"),
+            None => name.to_string(),
+        }),
+    };
+    let barcodes = ds.barcodes.ok_or_else(|| expected("barcodes.tsv[.gz]"))?;
+
+    let (features_path, format) = match (ds.features, ds.genes) {
+        (Some(features), Some(genes)) => {
+            return Err(InputError::LayoutAmbiguous {
+                dir: dir.to_path_buf(),
+                what: "gene table".to_string(),
+                candidates: [features, genes].iter().map(|p| file_name(p)).collect(),
+            });
+        }
+        (Some(features), None) => (features, TenXFormat::TenXv3),
+        (None, Some(genes)) => (genes, TenXFormat::TenXv2),
+        (None, None) => return Err(expected("features.tsv/genes.tsv[.gz]")),
     };
 
     Ok(TenXLayout {
@@ -56,12 +163,18 @@ pub fn detect_10x_dir(dir: &Path) -> Result<TenXLayout, InputError> {
 }
 
 pub fn detect_prefix(dir: &Path) -> Result<Option<String>, InputError> {
-    kira_scio::detect_prefix(dir).map_err(|e| InputError::InvalidTsvRow {
-        line: 0,
+    kira_scio::detect_prefix(dir).map_err(|e| InputError::PrefixConflict {
+        dir: dir.to_path_buf(),
         reason: e.to_string(),
     })
 }
 
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
 pub fn resolve_shared_cache_file_name(prefix: Option<&str>) -> String {
     kira_scio::resolve_shared_cache_filename(prefix)
 }
@@ -76,9 +189,9 @@ pub fn find_shared_cache_file(
     }
 
     let mut candidates = Vec::new();
-    for entry in std::fs::read_dir(dir)? {
-        let entry = entry?;
-        if !entry.file_type()?.is_file() {
+    for entry in std::fs::read_dir(dir).map_err(InputError::io(dir))? {
+        let entry = entry.map_err(InputError::io(dir))?;
+        if !entry.file_type().map_err(InputError::io(dir))?.is_file() {
             continue;
         }
         let name = entry.file_name();
@@ -1,10 +1,11 @@
 use std::collections::HashMap;
 use std::path::Path;
 
-use kira_scio::api::{Reader, ReaderOptions};
-use kira_scio::detect::DetectedFormat;
+use crc::{CRC_64_ECMA_182, Crc};
+use tracing::warn;
 
 use crate::input::InputError;
+use crate::input::delimiter::{Delimiter, read_delimited};
 
 #[derive(Debug, Clone)]
 pub struct FeatureRow {
@@ -26,42 +27,86 @@ pub struct GeneIndex {
     pub first_index_by_symbol: HashMap<String, usize>,
 }
 
-pub fn read_features(path: &Path) -> Result<GeneIndex, InputError> {
-    let md = Reader::with_options(
-        path,
-        ReaderOptions {
-            force_format: Some(DetectedFormat::Mtx10x),
-            strict: true,
-        },
-    )
-    .read_metadata()
-    .map_err(|e| InputError::InvalidTsvRow {
-        line: 0,
-        reason: e.message,
-    })?;
+const CRC64: Crc<u64> = Crc::<u64>::new(&CRC_64_ECMA_182);
 
-    let rows = md
-        .gene_symbols
+impl GeneIndex {
+    /// CRC-64 over the `id`/`symbol` rows in matrix order, as 16 hex digits.
+    /// Equal only when both runs index the same genes in the same order.
+    pub fn identity_hash(&self) -> String {
+        let mut digest = CRC64.digest();
+        for row in &self.rows {
+            digest.update(row.id.as_bytes());
+            digest.update(b"\t");
+            digest.update(row.symbol.as_bytes());
+            digest.update(b"\n");
+        }
+        format!("{:016x}", digest.finalize())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FeaturesFile {
+    pub gene_index: GeneIndex,
+    pub delimiter: Delimiter,
+    /// Rows whose only value was taken as both id and symbol.
+    pub single_column_rows: usize,
+}
+
+/// Reads a 10x features/genes file (`id`, `symbol`, ...), tab- or
+/// whitespace-delimited; see [`read_delimited`]. With `single_column`
+/// (`--features-single-column`) a row holding one value uses it as both id
+/// and symbol, with a warning; otherwise such rows fail.
+pub fn read_features(path: &Path, single_column: bool) -> Result<FeaturesFile, InputError> {
+    let (delimiter, lines) = read_delimited(path)?;
+    let symbols_only = !lines.is_empty() && lines.iter().all(|row| row.fields.len() == 1);
+    let mut single_column_rows = 0;
+    let rows = lines
         .into_iter()
-        .enumerate()
-        .map(|(idx, symbol)| FeatureRow {
-            id: md
-                .gene_ids
-                .get(idx)
-                .cloned()
-                .unwrap_or_else(|| format!("GENE_{}", idx + 1)),
-            symbol,
+        .map(|row| match row.fields.as_slice() {
+            [id, symbol, ..] => Ok(FeatureRow {
+                id: id.clone(),
+                symbol: symbol.clone(),
+            }),
+            [symbol] if single_column => {
+                single_column_rows += 1;
+                Ok(FeatureRow {
+                    id: symbol.clone(),
+                    symbol: symbol.clone(),
+                })
+            }
+            _ => Err(InputError::InvalidTsvRow {
+                path: path.to_path_buf(),
+                line: row.line,
+                reason: if symbols_only {
+                    "expected at least 2 columns, found 1 on every row; for a symbols-only \
+                     file pass --features-single-column"
+                        .to_string()
+                } else {
+                    format!("expected at least 2 columns, found {}", row.fields.len())
+                },
+            }),
         })
-        .collect::<Vec<_>>();
+        .collect::<Result<Vec<_>, _>>()?;
 
     if rows.is_empty() {
-        return Err(InputError::InvalidTsvRow {
-            line: 0,
-            reason: "no feature rows found".to_string(),
+        return Err(InputError::EmptyFile {
+            path: path.to_path_buf(),
+            expected: "feature rows".to_string(),
         });
     }
 
-    Ok(build_gene_index(rows))
+    if single_column_rows > 0 {
+        warn!(
+            path = %path.display(),
+            rows = single_column_rows,
+            "features rows with a single column; using the value as both id and symbol"
+        );
+    }
+    Ok(FeaturesFile {
+        gene_index: build_gene_index(rows),
+        delimiter,
+        single_column_rows,
+    })
 }
 
 pub fn build_gene_index(rows: Vec<FeatureRow>) -> GeneIndex {
@@ -0,0 +1,137 @@
+use std::io::{self, BufRead, Read};
+use std::path::Path;
+use std::sync::mpsc::{Receiver, SyncSender, sync_channel};
+use std::thread::JoinHandle;
+
+/// Size of each decompressed buffer handed from the decoder thread to the parser.
+pub const GZ_CHUNK_BYTES: usize = 1 << 20;
+
+/// Number of decompressed buffers that may be queued ahead of the parser.
+pub const GZ_QUEUE_DEPTH: usize = 8;
+
+/// Gzip reader that decompresses on a background thread into a bounded queue
+/// of buffers, so inflation overlaps with parsing on the consuming thread.
+/// Yields exactly the bytes `flate2::read::GzDecoder` would.
+pub struct PipelinedGzReader {
+    rx: Option<Receiver<io::Result<Vec<u8>>>>,
+    recycle: Option<SyncSender<Vec<u8>>>,
+    buf: Vec<u8>,
+    pos: usize,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl PipelinedGzReader {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = std::fs::File::open(path)?;
+        Ok(Self::new(file))
+    }
+
+    pub fn new<R: Read + Send + 'static>(inner: R) -> Self {
+        let (tx, rx) = sync_channel::<io::Result<Vec<u8>>>(GZ_QUEUE_DEPTH);
+        let (recycle_tx, recycle_rx) = sync_channel::<Vec<u8>>(GZ_QUEUE_DEPTH + 1);
+        let worker = std::thread::Builder::new()
+            .name("gz-inflate".to_string())
+            .spawn(move || {
+                let mut decoder = flate2::read::GzDecoder::new(inner);
+                loop {
+                    let mut chunk = recycle_rx
+                        .try_recv()
+                        .unwrap_or_else(|_| Vec::with_capacity(GZ_CHUNK_BYTES));
+                    chunk.resize(GZ_CHUNK_BYTES, 0);
+                    match fill_chunk(&mut decoder, &mut chunk) {
+                        Ok(0) => return,
+                        Ok(n) => {
+                            chunk.truncate(n);
+                            if tx.send(Ok(chunk)).is_err() {
+                                return;
+                            }
+                        }
+                        Err(e) => {
+                            let _ = tx.send(Err(e));
+                            return;
+                        }
+                    }
+                }
+            })
+            .expect("spawn gz-inflate thread");
+        Self {
+            rx: Some(rx),
+            recycle: Some(recycle_tx),
+            buf: Vec::new(),
+            pos: 0,
+            worker: Some(worker),
+        }
+    }
+}
+
+/// Reads until `chunk` is full or the decoder is exhausted.
+fn fill_chunk<R: Read>(decoder: &mut R, chunk: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < chunk.len() {
+        match decoder.read(&mut chunk[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+impl Read for PipelinedGzReader {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let n = available.len().min(out.len());
+        out[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl BufRead for PipelinedGzReader {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.pos >= self.buf.len() {
+            let Some(rx) = &self.rx else {
+                return Ok(&[]);
+            };
+            match rx.recv() {
+                Ok(Ok(next)) => {
+                    let spent = std::mem::replace(&mut self.buf, next);
+                    if let Some(recycle) = &self.recycle {
+                        let _ = recycle.try_send(spent);
+                    }
+                    self.pos = 0;
+                }
+                Ok(Err(e)) => {
+                    self.rx = None;
+                    return Err(e);
+                }
+                Err(_) => {
+                    self.rx = None;
+                    self.buf.clear();
+                    self.pos = 0;
+                }
+            }
+        }
+        Ok(&self.buf[self.pos..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.buf.len());
+    }
+}
+
+impl Drop for PipelinedGzReader {
+    fn drop(&mut self) {
+        // Closing the queue makes a blocked worker's `send` fail so it exits.
+        self.rx = None;
+        self.recycle = None;
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "../../tests/src_inline/input/gz.rs"]
+mod tests;
@@ -0,0 +1,322 @@
+//! AnnData `.h5ad` input, as written by Scanpy.
+//!
+//! `X` (or `layers/counts` with `--layer counts`) is a cells × genes matrix
+//! stored as a CSR group (`encoding-type` `csr_matrix`), a CSC group
+//! (`csc_matrix`) or a dense dataset. The `_index` columns of `var` and `obs`
+//! give the gene symbols and the barcodes. Values must be raw counts: floats
+//! within 1e-6 of a count are rounded to it, anything else (normalized or
+//! scaled data) fails with a hint to read the counts layer.
+//!
+//! Reading the HDF5 file needs the `h5ad` cargo feature, which links the
+//! system libhdf5; without it `.h5ad` input fails with
+//! [`InputError::H5adUnsupported`]. What is read is adapted here into the
+//! shapes of a dense table ([`DenseMatrix`]), which stage2 takes as is.
+
+use std::path::Path;
+
+use crate::expr::csc::{CellStats, ExprCsc};
+use crate::input::InputError;
+use crate::input::dense::DenseMatrix;
+use crate::input::features::FeatureRow;
+
+/// Which AnnData matrix holds the counts (`--layer`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum H5adLayer {
+    /// `X`.
+    #[default]
+    X,
+    /// `layers/counts`, where Scanpy workflows keep the raw counts once `X`
+    /// is normalized.
+    Counts,
+}
+
+impl H5adLayer {
+    /// HDF5 path of the matrix.
+    pub fn dataset(self) -> &'static str {
+        match self {
+            H5adLayer::X => "X",
+            H5adLayer::Counts => "layers/counts",
+        }
+    }
+}
+
+/// How a matrix is stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum H5adEncoding {
+    /// Compressed rows: `indptr` runs over cells.
+    Csr,
+    /// Compressed columns: `indptr` runs over genes.
+    Csc,
+    /// A cells × genes dataset in row-major order.
+    Dense,
+}
+
+/// A cells × genes matrix as stored in the file, its values already checked
+/// with [`float_count`].
+#[derive(Debug, Clone)]
+pub struct AnnDataMatrix {
+    pub encoding: H5adEncoding,
+    pub n_obs: usize,
+    pub n_vars: usize,
+    /// `indptr` of a sparse matrix; empty for dense.
+    pub indptr: Vec<u64>,
+    /// `indices` of a sparse matrix; empty for dense.
+    pub indices: Vec<u32>,
+    /// `data` of a sparse matrix, or every value of a dense one.
+    pub counts: Vec<u32>,
+}
+
+/// Distance from an integer below which a float is taken as a count, as for
+/// `real` MTX entries.
+const COUNT_TOLERANCE: f64 = 1e-6;
+
+/// `value` as a count: finite, non-negative, within [`COUNT_TOLERANCE`] of an
+/// integer and in the u32 range; `None` otherwise.
+pub fn float_count(value: f64) -> Option<u32> {
+    let rounded = value.round();
+    if !(0.0..=f64::from(u32::MAX)).contains(&rounded) || (value - rounded).abs() > COUNT_TOLERANCE
+    {
+        return None;
+    }
+    Some(rounded as u32)
+}
+
+/// What to do when `layer` holds something other than counts.
+pub fn not_counts_hint(layer: H5adLayer, has_counts_layer: bool) -> String {
+    match (layer, has_counts_layer) {
+        (H5adLayer::X, true) => {
+            "X looks normalized; pass --layer counts to read the raw counts in layers/counts"
+                .to_string()
+        }
+        (H5adLayer::X, false) => "X looks normalized and the file has no layers/counts; write the raw counts to X or layers/counts".to_string(),
+        (H5adLayer::Counts, _) => {
+            "layers/counts does not hold raw counts either; write the raw counts to it".to_string()
+        }
+    }
+}
+
+/// Reads the `.h5ad` file at `path` into the dense-table shapes; see the
+/// module docs.
+pub fn read_h5ad(path: &Path, layer: H5adLayer) -> Result<DenseMatrix, InputError> {
+    #[cfg(feature = "h5ad")]
+    {
+        let (matrix, var_names, obs_names) = crate::input::hdf5::read_anndata(path, layer)?;
+        to_dense_matrix(path, matrix, var_names, obs_names)
+    }
+    #[cfg(not(feature = "h5ad"))]
+    {
+        let _ = layer;
+        Err(InputError::H5adUnsupported {
+            path: path.to_path_buf(),
+        })
+    }
+}
+
+/// Adapts an AnnData matrix and its names: genes from `var_names` (the
+/// symbol doubles as the id), barcodes from `obs_names`, and the matrix as
+/// genes × cells CSC. Zeros are dropped and repeated coordinates summed.
+pub fn to_dense_matrix(
+    path: &Path,
+    matrix: AnnDataMatrix,
+    var_names: Vec<String>,
+    obs_names: Vec<String>,
+) -> Result<DenseMatrix, InputError> {
+    let invalid = |reason: String| InputError::InvalidH5ad {
+        path: path.to_path_buf(),
+        reason,
+    };
+    let AnnDataMatrix {
+        encoding,
+        n_obs,
+        n_vars,
+        indptr,
+        indices,
+        counts,
+    } = matrix;
+    if var_names.len() != n_vars {
+        return Err(invalid(format!(
+            "var has {} names, the matrix {n_vars} genes",
+            var_names.len()
+        )));
+    }
+    if obs_names.len() != n_obs {
+        return Err(invalid(format!(
+            "obs has {} names, the matrix {n_obs} cells",
+            obs_names.len()
+        )));
+    }
+    if n_vars == 0 {
+        return Err(InputError::EmptyFile {
+            path: path.to_path_buf(),
+            expected: "genes in var".to_string(),
+        });
+    }
+    if let Some(i) = obs_names.iter().position(String::is_empty) {
+        return Err(invalid(format!("obs name {i} is empty")));
+    }
+    if let Some(i) = var_names.iter().position(String::is_empty) {
+        return Err(invalid(format!("var name {i} is empty")));
+    }
+
+    let mut builder = CscBuilder::new(n_obs);
+    match encoding {
+        H5adEncoding::Csr => {
+            check_sparse(&indptr, &indices, &counts, n_obs, n_vars).map_err(invalid)?;
+            for cell in 0..n_obs {
+                let range = indptr[cell] as usize..indptr[cell + 1] as usize;
+                builder.push_cell(
+                    indices[range.clone()]
+                        .iter()
+                        .copied()
+                        .zip(counts[range].iter().copied()),
+                );
+            }
+        }
+        H5adEncoding::Csc => {
+            check_sparse(&indptr, &indices, &counts, n_vars, n_obs).map_err(invalid)?;
+            // Counting sort of the entries by cell; genes stay in order.
+            let mut cell_ptr = vec![0usize; n_obs + 1];
+            for &cell in &indices {
+                cell_ptr[cell as usize + 1] += 1;
+            }
+            for i in 0..n_obs {
+                cell_ptr[i + 1] += cell_ptr[i];
+            }
+            let mut next = cell_ptr.clone();
+            let mut by_cell = vec![(0u32, 0u32); indices.len()];
+            for gene in 0..n_vars {
+                for k in indptr[gene] as usize..indptr[gene + 1] as usize {
+                    let slot = &mut next[indices[k] as usize];
+                    by_cell[*slot] = (gene as u32, counts[k]);
+                    *slot += 1;
+                }
+            }
+            for cell in 0..n_obs {
+                builder.push_cell(by_cell[cell_ptr[cell]..cell_ptr[cell + 1]].iter().copied());
+            }
+        }
+        H5adEncoding::Dense => {
+            if counts.len() != n_obs * n_vars {
+                return Err(invalid(format!(
+                    "dense matrix has {} values, expected {n_obs} x {n_vars}",
+                    counts.len()
+                )));
+            }
+            for row in counts.chunks_exact(n_vars).take(n_obs) {
+                builder.push_cell((0..n_vars as u32).zip(row.iter().copied()));
+            }
+        }
+    }
+
+    let genes = var_names
+        .into_iter()
+        .map(|symbol| FeatureRow {
+            id: symbol.clone(),
+            symbol,
+        })
+        .collect::<Vec<_>>();
+    let CscBuilder {
+        col_ptr,
+        row_idx,
+        values,
+        cell_stats,
+        ..
+    } = builder;
+    Ok(DenseMatrix {
+        expr: ExprCsc {
+            n_genes: genes.len(),
+            n_cells: n_obs,
+            nnz: row_idx.len(),
+            col_ptr,
+            row_idx,
+            values,
+        },
+        genes,
+        barcodes: obs_names,
+        cell_stats,
+    })
+}
+
+/// Checks a compressed matrix with `n_major` compressed rows (or columns)
+/// whose indices run over `n_minor`.
+fn check_sparse(
+    indptr: &[u64],
+    indices: &[u32],
+    counts: &[u32],
+    n_major: usize,
+    n_minor: usize,
+) -> Result<(), String> {
+    if indptr.len() != n_major + 1 {
+        return Err(format!(
+            "indptr has {} entries, expected {}",
+            indptr.len(),
+            n_major + 1
+        ));
+    }
+    if indptr[0] != 0 || indptr.windows(2).any(|w| w[0] > w[1]) {
+        return Err("indptr does not rise from 0".to_string());
+    }
+    let nnz = indptr[n_major] as usize;
+    if indices.len() != nnz || counts.len() != nnz {
+        return Err(format!(
+            "indptr ends at {nnz}, but indices has {} entries and data {}",
+            indices.len(),
+            counts.len()
+        ));
+    }
+    if let Some(&index) = indices.iter().find(|&&i| i as usize >= n_minor) {
+        return Err(format!(
+            "index {index} is out of range (dimension {n_minor})"
+        ));
+    }
+    Ok(())
+}
+
+/// Genes × cells CSC built one cell at a time, with the per-cell stats.
+struct CscBuilder {
+    col_ptr: Vec<u64>,
+    row_idx: Vec<u32>,
+    values: Vec<u32>,
+    cell_stats: Vec<CellStats>,
+    scratch: Vec<(u32, u32)>,
+}
+
+impl CscBuilder {
+    fn new(n_cells: usize) -> Self {
+        let mut col_ptr = Vec::with_capacity(n_cells + 1);
+        col_ptr.push(0);
+        Self {
+            col_ptr,
+            row_idx: Vec::new(),
+            values: Vec::new(),
+            cell_stats: Vec::with_capacity(n_cells),
+            scratch: Vec::new(),
+        }
+    }
+
+    /// Appends the next cell from its `(gene, count)` entries, in any order.
+    fn push_cell(&mut self, entries: impl Iterator<Item = (u32, u32)>) {
+        self.scratch.clear();
+        self.scratch.extend(entries.filter(|&(_, count)| count > 0));
+        self.scratch.sort_by_key(|&(gene, _)| gene);
+        let start = self.row_idx.len();
+        let mut stats = CellStats::default();
+        for &(gene, count) in &self.scratch {
+            stats.libsize += u64::from(count);
+            if self.row_idx.len() > start && self.row_idx.last() == Some(&gene) {
+                let last = self.values.last_mut().expect("value for the last row");
+                *last = last.saturating_add(count);
+                continue;
+            }
+            self.row_idx.push(gene);
+            self.values.push(count);
+            stats.detected += 1;
+        }
+        self.cell_stats.push(stats);
+        self.col_ptr.push(self.row_idx.len() as u64);
+    }
+}
+
+#[cfg(test)]
+#[path = "../../tests/src_inline/input/h5ad.rs"]
+mod tests;
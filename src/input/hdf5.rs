@@ -0,0 +1,557 @@
+//! The libhdf5 side of `.h5ad` input (`h5ad` feature): reads the arrays that
+//! [`crate::input::h5ad`] checks and adapts, through the HDF5 C API.
+//!
+//! Values are read in slabs and converted to counts as they arrive, so only
+//! the u32 counts and indices of the matrix are held in memory.
+
+use std::ffi::{CStr, CString, c_char, c_void};
+use std::path::Path;
+use std::ptr;
+
+use hdf5_metno_sys::h5::{H5free_memory, H5open, herr_t, hsize_t};
+use hdf5_metno_sys::h5a::{H5Aclose, H5Aexists, H5Aget_space, H5Aget_type, H5Aopen, H5Aread};
+use hdf5_metno_sys::h5d::{H5Dclose, H5Dget_space, H5Dget_type, H5Dopen2, H5Dread};
+use hdf5_metno_sys::h5e::{H5E_DEFAULT, H5Eset_auto2};
+use hdf5_metno_sys::h5f::{H5F_ACC_RDONLY, H5Fclose, H5Fopen};
+use hdf5_metno_sys::h5i::{H5I_GROUP, H5Iget_type, hid_t};
+use hdf5_metno_sys::h5l::H5Lexists;
+use hdf5_metno_sys::h5o::{H5Oclose, H5Oopen};
+use hdf5_metno_sys::h5p::H5P_DEFAULT;
+use hdf5_metno_sys::h5s::{
+    H5S_ALL, H5S_SELECT_SET, H5Sclose, H5Screate_simple, H5Sget_simple_extent_dims,
+    H5Sget_simple_extent_ndims, H5Sselect_hyperslab,
+};
+use hdf5_metno_sys::h5t::{
+    H5T_C_S1, H5T_CSET_UTF8, H5T_NATIVE_DOUBLE, H5T_NATIVE_UINT64, H5T_STRING, H5T_VARIABLE,
+    H5Tclose, H5Tcopy, H5Tget_class, H5Tget_size, H5Tis_variable_str, H5Tset_cset, H5Tset_size,
+};
+
+use crate::input::InputError;
+use crate::input::h5ad::{AnnDataMatrix, H5adEncoding, H5adLayer, float_count, not_counts_hint};
+
+/// Values read per slab.
+const SLAB_VALUES: usize = 1 << 20;
+
+type Close = unsafe extern "C" fn(hid_t) -> herr_t;
+
+/// An open HDF5 identifier, closed on drop.
+struct Handle {
+    id: hid_t,
+    close: Close,
+}
+
+impl Drop for Handle {
+    fn drop(&mut self) {
+        // SAFETY: `id` was returned open by the call `close` pairs with.
+        unsafe { (self.close)(self.id) };
+    }
+}
+
+/// The matrix named by `layer`, then the `var` and `obs` names.
+pub(crate) fn read_anndata(
+    path: &Path,
+    layer: H5adLayer,
+) -> Result<(AnnDataMatrix, Vec<String>, Vec<String>), InputError> {
+    // libhdf5 is not thread-safe unless built so; every call goes through
+    // the crate-wide lock.
+    let _lock = hdf5_metno_sys::LOCK.lock();
+    let file = AnnDataFile::open(path)?;
+
+    let name = layer.dataset();
+    if !file.exists(name) {
+        return Err(file.invalid(match layer {
+            H5adLayer::X => "no X matrix".to_string(),
+            H5adLayer::Counts => {
+                "no layers/counts; --layer counts needs the raw counts there".to_string()
+            }
+        }));
+    }
+    let hint = not_counts_hint(layer, file.exists(H5adLayer::Counts.dataset()));
+    let object = file.open_object(name, H5Oopen, H5Oclose)?;
+    // SAFETY: `object` is an open object identifier.
+    let matrix = if unsafe { H5Iget_type(object.id) } == H5I_GROUP {
+        let encoding = match file
+            .string_attr(object.id, name, "encoding-type")?
+            .as_deref()
+        {
+            Some("csr_matrix") => H5adEncoding::Csr,
+            Some("csc_matrix") => H5adEncoding::Csc,
+            other => {
+                return Err(file.invalid(format!(
+                    "{name} has encoding-type {other:?}, expected csr_matrix or csc_matrix"
+                )));
+            }
+        };
+        let [n_obs, n_vars] = file.shape_attr(object.id, name)?;
+        let indptr = file.read_u64(&format!("{name}/indptr"))?;
+        let indices = file.read_indices(&format!("{name}/indices"))?;
+        let counts = file.read_counts(&format!("{name}/data"), &hint)?;
+        AnnDataMatrix {
+            encoding,
+            n_obs,
+            n_vars,
+            indptr,
+            indices,
+            counts,
+        }
+    } else {
+        let dims = file.dims(name)?;
+        let [n_obs, n_vars] = dims[..] else {
+            return Err(file.invalid(format!("{name} has {} dimensions, expected 2", dims.len())));
+        };
+        AnnDataMatrix {
+            encoding: H5adEncoding::Dense,
+            n_obs,
+            n_vars,
+            indptr: Vec::new(),
+            indices: Vec::new(),
+            counts: file.read_counts(name, &hint)?,
+        }
+    };
+    let var_names = file.read_index("var")?;
+    let obs_names = file.read_index("obs")?;
+    Ok((matrix, var_names, obs_names))
+}
+
+struct AnnDataFile<'a> {
+    path: &'a Path,
+    file: Handle,
+}
+
+impl<'a> AnnDataFile<'a> {
+    fn open(path: &'a Path) -> Result<Self, InputError> {
+        let c_path = CString::new(path.as_os_str().as_encoded_bytes()).map_err(|_| {
+            InputError::InvalidH5ad {
+                path: path.to_path_buf(),
+                reason: "path contains a NUL byte".to_string(),
+            }
+        })?;
+        // SAFETY: plain library calls; the error stack printer is turned off
+        // because failures are reported through the returned errors.
+        let id = unsafe {
+            H5open();
+            H5Eset_auto2(H5E_DEFAULT, None, ptr::null_mut());
+            H5Fopen(c_path.as_ptr(), H5F_ACC_RDONLY, H5P_DEFAULT)
+        };
+        if id < 0 {
+            return Err(InputError::InvalidH5ad {
+                path: path.to_path_buf(),
+                reason: "cannot open as an HDF5 file".to_string(),
+            });
+        }
+        Ok(Self {
+            path,
+            file: Handle {
+                id,
+                close: H5Fclose,
+            },
+        })
+    }
+
+    fn invalid(&self, reason: String) -> InputError {
+        InputError::InvalidH5ad {
+            path: self.path.to_path_buf(),
+            reason,
+        }
+    }
+
+    /// Whether every link on the path `name` exists.
+    fn exists(&self, name: &str) -> bool {
+        let mut prefix = String::new();
+        for part in name.split('/') {
+            if !prefix.is_empty() {
+                prefix.push('/');
+            }
+            prefix.push_str(part);
+            let Ok(c_name) = CString::new(prefix.as_str()) else {
+                return false;
+            };
+            // SAFETY: the file is open and `c_name` is NUL-terminated.
+            if unsafe { H5Lexists(self.file.id, c_name.as_ptr(), H5P_DEFAULT) } <= 0 {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Opens `name` with `open` (`H5Oopen`, `H5Dopen2`), to be closed by `close`.
+    fn open_object(
+        &self,
+        name: &str,
+        open: unsafe extern "C" fn(hid_t, *const c_char, hid_t) -> hid_t,
+        close: Close,
+    ) -> Result<Handle, InputError> {
+        let c_name = CString::new(name).map_err(|_| self.invalid(format!("bad name {name:?}")))?;
+        // SAFETY: the file is open and `c_name` is NUL-terminated.
+        let id = unsafe { open(self.file.id, c_name.as_ptr(), H5P_DEFAULT) };
+        if id < 0 {
+            return Err(self.invalid(format!("cannot open {name}")));
+        }
+        Ok(Handle { id, close })
+    }
+
+    fn dataset(&self, name: &str) -> Result<Handle, InputError> {
+        self.open_object(name, H5Dopen2, H5Dclose)
+    }
+
+    /// Extent of the dataspace `space`.
+    fn extent(&self, space: &Handle, name: &str) -> Result<Vec<hsize_t>, InputError> {
+        // SAFETY: `space` is an open dataspace; `dims` has room for its rank.
+        let rank = unsafe { H5Sget_simple_extent_ndims(space.id) };
+        if rank < 0 {
+            return Err(self.invalid(format!("cannot read the shape of {name}")));
+        }
+        let mut dims = vec![0 as hsize_t; rank as usize];
+        if unsafe { H5Sget_simple_extent_dims(space.id, dims.as_mut_ptr(), ptr::null_mut()) } < 0 {
+            return Err(self.invalid(format!("cannot read the shape of {name}")));
+        }
+        Ok(dims)
+    }
+
+    fn dims(&self, name: &str) -> Result<Vec<usize>, InputError> {
+        let dataset = self.dataset(name)?;
+        let space = Handle {
+            // SAFETY: `dataset` is open.
+            id: unsafe { H5Dget_space(dataset.id) },
+            close: H5Sclose,
+        };
+        Ok(self
+            .extent(&space, name)?
+            .into_iter()
+            .map(|d| d as usize)
+            .collect())
+    }
+
+    /// Reads the 1-D or 2-D dataset `name` as `mem_type` in row slabs of
+    /// about [`SLAB_VALUES`] values, handing `each` every slab with the
+    /// flat index of its first value.
+    fn read_slabs<T: Copy + Default>(
+        &self,
+        name: &str,
+        mem_type: hid_t,
+        mut each: impl FnMut(usize, &[T]) -> Result<(), InputError>,
+    ) -> Result<(), InputError> {
+        let dataset = self.dataset(name)?;
+        let space = Handle {
+            // SAFETY: `dataset` is open.
+            id: unsafe { H5Dget_space(dataset.id) },
+            close: H5Sclose,
+        };
+        let dims = self.extent(&space, name)?;
+        if dims.is_empty() || dims.len() > 2 {
+            return Err(self.invalid(format!(
+                "{name} has {} dimensions, expected 1 or 2",
+                dims.len()
+            )));
+        }
+        let row_len = dims[1..].iter().product::<hsize_t>();
+        if row_len == 0 {
+            return Ok(());
+        }
+        let rows_per_slab = (SLAB_VALUES as hsize_t / row_len).max(1);
+        let mut buf = vec![T::default(); (rows_per_slab * row_len) as usize];
+        let mut row: hsize_t = 0;
+        while row < dims[0] {
+            let n_rows = rows_per_slab.min(dims[0] - row);
+            let mut start = vec![0 as hsize_t; dims.len()];
+            start[0] = row;
+            let mut count = dims.clone();
+            count[0] = n_rows;
+            let n = (n_rows * row_len) as usize;
+            // SAFETY: `start`/`count` have the dataset's rank and select rows
+            // inside it; `buf` holds at least `n` values of `mem_type`.
+            let read = unsafe {
+                if H5Sselect_hyperslab(
+                    space.id,
+                    H5S_SELECT_SET,
+                    start.as_ptr(),
+                    ptr::null(),
+                    count.as_ptr(),
+                    ptr::null(),
+                ) < 0
+                {
+                    -1
+                } else {
+                    let memory = Handle {
+                        id: H5Screate_simple(dims.len() as i32, count.as_ptr(), ptr::null()),
+                        close: H5Sclose,
+                    };
+                    H5Dread(
+                        dataset.id,
+                        mem_type,
+                        memory.id,
+                        space.id,
+                        H5P_DEFAULT,
+                        buf.as_mut_ptr().cast::<c_void>(),
+                    )
+                }
+            };
+            if read < 0 {
+                return Err(self.invalid(format!("cannot read {name}")));
+            }
+            each((row * row_len) as usize, &buf[..n])?;
+            row += n_rows;
+        }
+        Ok(())
+    }
+
+    fn read_u64(&self, name: &str) -> Result<Vec<u64>, InputError> {
+        let mut out = Vec::new();
+        self.read_slabs::<u64>(name, *H5T_NATIVE_UINT64, |_, slab| {
+            out.extend_from_slice(slab);
+            Ok(())
+        })?;
+        Ok(out)
+    }
+
+    fn read_indices(&self, name: &str) -> Result<Vec<u32>, InputError> {
+        let mut out = Vec::new();
+        self.read_slabs::<u64>(name, *H5T_NATIVE_UINT64, |_, slab| {
+            for &index in slab {
+                out.push(
+                    u32::try_from(index)
+                        .map_err(|_| self.invalid(format!("{name} holds index {index}")))?,
+                );
+            }
+            Ok(())
+        })?;
+        Ok(out)
+    }
+
+    /// Values of `name` as counts; the first that is not one fails with `hint`.
+    fn read_counts(&self, name: &str, hint: &str) -> Result<Vec<u32>, InputError> {
+        let mut out = Vec::new();
+        self.read_slabs::<f64>(name, *H5T_NATIVE_DOUBLE, |start, slab| {
+            for (i, &value) in slab.iter().enumerate() {
+                out.push(float_count(value).ok_or_else(|| InputError::H5adNotCounts {
+                    path: self.path.to_path_buf(),
+                    dataset: name.to_string(),
+                    index: start + i,
+                    value,
+                    hint: hint.to_string(),
+                })?);
+            }
+            Ok(())
+        })?;
+        Ok(out)
+    }
+
+    /// The names of a dataframe group (`var`, `obs`): the column its
+    /// `_index` attribute names.
+    fn read_index(&self, group: &str) -> Result<Vec<String>, InputError> {
+        let object = self.open_object(group, H5Oopen, H5Oclose)?;
+        // SAFETY: `object` is an open object identifier.
+        if unsafe { H5Iget_type(object.id) } != H5I_GROUP {
+            return Err(self.invalid(format!(
+                "{group} is stored in the pre-0.7 AnnData layout; save the file again with a current anndata"
+            )));
+        }
+        let column = self
+            .string_attr(object.id, group, "_index")?
+            .unwrap_or_else(|| "_index".to_string());
+        self.read_strings(&format!("{group}/{column}"))
+    }
+
+    /// A 1-D string dataset, fixed or variable length.
+    fn read_strings(&self, name: &str) -> Result<Vec<String>, InputError> {
+        let dataset = self.dataset(name)?;
+        let file_type = Handle {
+            // SAFETY: `dataset` is open.
+            id: unsafe { H5Dget_type(dataset.id) },
+            close: H5Tclose,
+        };
+        let dims = self.dims(name)?;
+        let [n] = dims[..] else {
+            return Err(self.invalid(format!("{name} is not a 1-D list of names")));
+        };
+        // SAFETY: `file_type` is an open datatype.
+        if unsafe { H5Tget_class(file_type.id) } != H5T_STRING {
+            return Err(self.invalid(format!("{name} does not hold strings")));
+        }
+        let (memory_type, size) = string_type(file_type.id);
+        if size == H5T_VARIABLE {
+            let mut pointers: Vec<*mut c_char> = vec![ptr::null_mut(); n];
+            // SAFETY: `pointers` holds one slot per element for the library's
+            // variable-length strings, freed below with `H5free_memory`.
+            let read = unsafe {
+                H5Dread(
+                    dataset.id,
+                    memory_type.id,
+                    H5S_ALL,
+                    H5S_ALL,
+                    H5P_DEFAULT,
+                    pointers.as_mut_ptr().cast::<c_void>(),
+                )
+            };
+            let names = (read >= 0).then(|| pointers.iter().map(|&p| owned_c_str(p)).collect());
+            for p in pointers {
+                if !p.is_null() {
+                    // SAFETY: allocated by the library in `H5Dread`.
+                    unsafe { H5free_memory(p.cast::<c_void>()) };
+                }
+            }
+            names.ok_or_else(|| self.invalid(format!("cannot read {name}")))
+        } else {
+            let mut bytes = vec![0u8; n * size];
+            // SAFETY: `bytes` holds `n` fixed-length strings of `size` bytes.
+            let read = unsafe {
+                H5Dread(
+                    dataset.id,
+                    memory_type.id,
+                    H5S_ALL,
+                    H5S_ALL,
+                    H5P_DEFAULT,
+                    bytes.as_mut_ptr().cast::<c_void>(),
+                )
+            };
+            if read < 0 {
+                return Err(self.invalid(format!("cannot read {name}")));
+            }
+            Ok(bytes.chunks(size.max(1)).map(fixed_str).collect())
+        }
+    }
+
+    /// A scalar string attribute of `object`, `None` when absent.
+    fn string_attr(
+        &self,
+        object: hid_t,
+        object_name: &str,
+        attr: &str,
+    ) -> Result<Option<String>, InputError> {
+        let c_attr = CString::new(attr).expect("attribute names have no NUL");
+        // SAFETY: `object` is open and `c_attr` is NUL-terminated.
+        if unsafe { H5Aexists(object, c_attr.as_ptr()) } <= 0 {
+            return Ok(None);
+        }
+        let failed = || self.invalid(format!("cannot read the {attr} attribute of {object_name}"));
+        let attribute = Handle {
+            // SAFETY: as above; the attribute exists.
+            id: unsafe { H5Aopen(object, c_attr.as_ptr(), H5P_DEFAULT) },
+            close: H5Aclose,
+        };
+        if attribute.id < 0 {
+            return Err(failed());
+        }
+        let file_type = Handle {
+            // SAFETY: `attribute` is open.
+            id: unsafe { H5Aget_type(attribute.id) },
+            close: H5Tclose,
+        };
+        // SAFETY: `file_type` is an open datatype.
+        if unsafe { H5Tget_class(file_type.id) } != H5T_STRING {
+            return Err(failed());
+        }
+        let (memory_type, size) = string_type(file_type.id);
+        if size == H5T_VARIABLE {
+            let mut p: *mut c_char = ptr::null_mut();
+            // SAFETY: one variable-length string into `p`, freed below.
+            let read = unsafe {
+                H5Aread(
+                    attribute.id,
+                    memory_type.id,
+                    (&mut p as *mut *mut c_char).cast::<c_void>(),
+                )
+            };
+            let value = (read >= 0).then(|| owned_c_str(p));
+            if !p.is_null() {
+                // SAFETY: allocated by the library in `H5Aread`.
+                unsafe { H5free_memory(p.cast::<c_void>()) };
+            }
+            value.map(Some).ok_or_else(failed)
+        } else {
+            let mut bytes = vec![0u8; size];
+            // SAFETY: `bytes` holds one fixed-length string of `size` bytes.
+            let read = unsafe {
+                H5Aread(
+                    attribute.id,
+                    memory_type.id,
+                    bytes.as_mut_ptr().cast::<c_void>(),
+                )
+            };
+            if read < 0 {
+                return Err(failed());
+            }
+            Ok(Some(fixed_str(&bytes)))
+        }
+    }
+
+    /// The `shape` attribute of a sparse matrix group, `[n_obs, n_vars]`.
+    fn shape_attr(&self, object: hid_t, name: &str) -> Result<[usize; 2], InputError> {
+        let failed = || self.invalid(format!("{name} has no readable 2-element shape attribute"));
+        let c_attr = CString::new("shape").expect("no NUL");
+        // SAFETY: `object` is open and `c_attr` is NUL-terminated.
+        if unsafe { H5Aexists(object, c_attr.as_ptr()) } <= 0 {
+            return Err(failed());
+        }
+        let attribute = Handle {
+            // SAFETY: as above; the attribute exists.
+            id: unsafe { H5Aopen(object, c_attr.as_ptr(), H5P_DEFAULT) },
+            close: H5Aclose,
+        };
+        if attribute.id < 0 {
+            return Err(failed());
+        }
+        let space = Handle {
+            // SAFETY: `attribute` is open.
+            id: unsafe { H5Aget_space(attribute.id) },
+            close: H5Sclose,
+        };
+        if self.extent(&space, name)? != [2] {
+            return Err(failed());
+        }
+        let mut shape = [0u64; 2];
+        // SAFETY: the attribute holds two values, read into `shape`.
+        let read = unsafe {
+            H5Aread(
+                attribute.id,
+                *H5T_NATIVE_UINT64,
+                shape.as_mut_ptr().cast::<c_void>(),
+            )
+        };
+        if read < 0 {
+            return Err(failed());
+        }
+        Ok([shape[0] as usize, shape[1] as usize])
+    }
+}
+
+/// The in-memory UTF-8 C string type matching `file_type`, and its size
+/// ([`H5T_VARIABLE`] for variable-length strings).
+fn string_type(file_type: hid_t) -> (Handle, usize) {
+    // SAFETY: `file_type` is an open string datatype; the copy is closed by
+    // the returned handle.
+    unsafe {
+        let size = if H5Tis_variable_str(file_type) > 0 {
+            H5T_VARIABLE
+        } else {
+            H5Tget_size(file_type)
+        };
+        let memory_type = H5Tcopy(*H5T_C_S1);
+        H5Tset_size(memory_type, size);
+        H5Tset_cset(memory_type, H5T_CSET_UTF8);
+        (
+            Handle {
+                id: memory_type,
+                close: H5Tclose,
+            },
+            size,
+        )
+    }
+}
+
+/// Copies a library-owned C string; empty for a null pointer.
+fn owned_c_str(p: *const c_char) -> String {
+    if p.is_null() {
+        return String::new();
+    }
+    // SAFETY: non-null strings from the library are NUL-terminated.
+    unsafe { CStr::from_ptr(p) }.to_string_lossy().into_owned()
+}
+
+/// A fixed-length string without its NUL or space padding.
+fn fixed_str(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end])
+        .trim_end()
+        .to_string()
+}
@@ -1,6 +1,9 @@
 use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::path::Path;
 
+use serde::Serialize;
+
 use crate::input::{InputError, open_reader};
 
 #[derive(Debug, Default, Clone)]
@@ -9,6 +12,126 @@ pub struct MetaStats {
     pub missing: usize,
     pub duplicate_rows: usize,
     pub sample_counts: Option<HashMap<String, usize>>,
+    /// First few meta cell_ids that matched no barcode, for diagnostics.
+    pub unmatched_examples: Vec<String>,
+    /// Problem rows in file order; fatal under `--meta-strict`.
+    pub issues: Vec<MetaIssue>,
+}
+
+/// A meta row that is read leniently but would be rejected by `--meta-strict`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetaIssue {
+    /// 1-based line number (the header is line 1).
+    pub line: usize,
+    pub kind: MetaIssueKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MetaIssueKind {
+    /// A `species` value [`normalize_species`] does not recognize.
+    UnknownSpecies(String),
+    /// Fewer columns than the header.
+    ShortRow { found: usize, expected: usize },
+    /// A `cell_id` already seen on an earlier line; the later row is ignored.
+    DuplicateCellId(String),
+}
+
+impl fmt::Display for MetaIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            MetaIssueKind::UnknownSpecies(value) => {
+                write!(f, "line {}: unknown species '{value}'", self.line)
+            }
+            MetaIssueKind::ShortRow { found, expected } => write!(
+                f,
+                "line {}: {found} columns, header has {expected}",
+                self.line
+            ),
+            MetaIssueKind::DuplicateCellId(cell_id) => {
+                write!(f, "line {}: duplicate cell_id '{cell_id}'", self.line)
+            }
+        }
+    }
+}
+
+/// Per-kind counts of [`MetaIssue`]s, reported in `summary.json`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct MetaIssueCounts {
+    pub unknown_species: usize,
+    pub short_rows: usize,
+    pub duplicate_cell_ids: usize,
+}
+
+impl MetaIssueCounts {
+    pub fn total(&self) -> usize {
+        self.unknown_species + self.short_rows + self.duplicate_cell_ids
+    }
+}
+
+/// `human`, `mouse` or `unknown` for a meta `species` value.
+pub fn normalize_species(s: &str) -> &'static str {
+    let x = s.trim().to_ascii_lowercase();
+    if x.contains("human") || x == "hs" || x == "homo_sapiens" {
+        "human"
+    } else if x.contains("mouse") || x == "mm" || x == "mus_musculus" {
+        "mouse"
+    } else {
+        "unknown"
+    }
+}
+
+/// How many unmatched meta cell_ids are kept in [`MetaStats::unmatched_examples`].
+pub const META_EXAMPLE_COUNT: usize = 3;
+
+impl MetaStats {
+    fn record_unmatched(&mut self, cell_id: &str) {
+        self.missing += 1;
+        if self.unmatched_examples.len() < META_EXAMPLE_COUNT {
+            self.unmatched_examples.push(cell_id.to_string());
+        }
+    }
+
+    /// Records short rows and unrecognized species for one data row.
+    fn check_row(&mut self, line: usize, parts: &[&str], columns: usize, species: Option<usize>) {
+        if parts.len() < columns {
+            self.issues.push(MetaIssue {
+                line,
+                kind: MetaIssueKind::ShortRow {
+                    found: parts.len(),
+                    expected: columns,
+                },
+            });
+        }
+        if let Some(value) = species.and_then(|idx| parts.get(idx))
+            && !value.trim().is_empty()
+            && normalize_species(value) == "unknown"
+        {
+            self.issues.push(MetaIssue {
+                line,
+                kind: MetaIssueKind::UnknownSpecies(value.to_string()),
+            });
+        }
+    }
+
+    fn record_duplicate(&mut self, line: usize, cell_id: &str) {
+        self.duplicate_rows += 1;
+        self.issues.push(MetaIssue {
+            line,
+            kind: MetaIssueKind::DuplicateCellId(cell_id.to_string()),
+        });
+    }
+
+    pub fn issue_counts(&self) -> MetaIssueCounts {
+        let mut counts = MetaIssueCounts::default();
+        for issue in &self.issues {
+            match issue.kind {
+                MetaIssueKind::UnknownSpecies(_) => counts.unknown_species += 1,
+                MetaIssueKind::ShortRow { .. } => counts.short_rows += 1,
+                MetaIssueKind::DuplicateCellId(_) => counts.duplicate_cell_ids += 1,
+            }
+        }
+        counts
+    }
 }
 
 pub fn read_meta(path: &Path, barcodes: &[String]) -> Result<MetaStats, InputError> {
@@ -16,11 +139,13 @@ pub fn read_meta(path: &Path, barcodes: &[String]) -> Result<MetaStats, InputErr
     let mut line = String::new();
 
     let mut header_line = String::new();
-    let read = reader.read_line(&mut header_line)?;
+    let read = reader
+        .read_line(&mut header_line)
+        .map_err(InputError::io(path))?;
     if read == 0 {
-        return Err(InputError::InvalidTsvRow {
-            line: 0,
-            reason: "empty meta file".to_string(),
+        return Err(InputError::EmptyFile {
+            path: path.to_path_buf(),
+            expected: "header line".to_string(),
         });
     }
 
@@ -29,8 +154,12 @@ pub fn read_meta(path: &Path, barcodes: &[String]) -> Result<MetaStats, InputErr
     let cell_idx = columns
         .iter()
         .position(|c| *c == "cell_id")
-        .ok_or_else(|| InputError::MissingMetaColumn("cell_id".to_string()))?;
+        .ok_or_else(|| InputError::MissingMetaColumn {
+            path: path.to_path_buf(),
+            column: "cell_id".to_string(),
+        })?;
     let sample_idx = columns.iter().position(|c| *c == "sample_id");
+    let species_idx = columns.iter().position(|c| *c == "species");
 
     let barcode_set: HashSet<&str> = barcodes.iter().map(|s| s.as_str()).collect();
     let mut seen_cells: HashSet<String> = HashSet::new();
@@ -43,7 +172,7 @@ pub fn read_meta(path: &Path, barcodes: &[String]) -> Result<MetaStats, InputErr
     let mut line_no = 1usize;
     loop {
         line.clear();
-        let read = reader.read_line(&mut line)?;
+        let read = reader.read_line(&mut line).map_err(InputError::io(path))?;
         if read == 0 {
             break;
         }
@@ -53,21 +182,28 @@ pub fn read_meta(path: &Path, barcodes: &[String]) -> Result<MetaStats, InputErr
             continue;
         }
         let parts: Vec<&str> = value.split('\t').collect();
+        stats.check_row(line_no, &parts, columns.len(), species_idx);
         if cell_idx >= parts.len() {
-            return Err(InputError::MissingMetaCellId(line_no));
+            return Err(InputError::MissingMetaCellId {
+                path: path.to_path_buf(),
+                line: line_no,
+            });
         }
         let cell_id = parts[cell_idx];
         if cell_id.is_empty() {
-            return Err(InputError::MissingMetaCellId(line_no));
+            return Err(InputError::MissingMetaCellId {
+                path: path.to_path_buf(),
+                line: line_no,
+            });
         }
         if !seen_cells.insert(cell_id.to_string()) {
-            stats.duplicate_rows += 1;
+            stats.record_duplicate(line_no, cell_id);
             continue;
         }
         if barcode_set.contains(cell_id) {
             stats.matched += 1;
         } else {
-            stats.missing += 1;
+            stats.record_unmatched(cell_id);
         }
         if let (Some(sample_col), Some(counts)) = (sample_idx, stats.sample_counts.as_mut()) {
             if sample_col < parts.len() {
@@ -90,11 +226,13 @@ pub fn read_meta_mapping(
     let mut line = String::new();
 
     let mut header_line = String::new();
-    let read = reader.read_line(&mut header_line)?;
+    let read = reader
+        .read_line(&mut header_line)
+        .map_err(InputError::io(path))?;
     if read == 0 {
-        return Err(InputError::InvalidTsvRow {
-            line: 0,
-            reason: "empty meta file".to_string(),
+        return Err(InputError::EmptyFile {
+            path: path.to_path_buf(),
+            expected: "header line".to_string(),
         });
     }
 
@@ -103,8 +241,12 @@ pub fn read_meta_mapping(
     let cell_idx = columns
         .iter()
         .position(|c| *c == "cell_id")
-        .ok_or_else(|| InputError::MissingMetaColumn("cell_id".to_string()))?;
+        .ok_or_else(|| InputError::MissingMetaColumn {
+            path: path.to_path_buf(),
+            column: "cell_id".to_string(),
+        })?;
     let sample_idx = columns.iter().position(|c| *c == "sample_id");
+    let species_idx = columns.iter().position(|c| *c == "species");
 
     let mut index_by_cell: HashMap<&str, usize> = HashMap::new();
     for (i, c) in barcodes.iter().enumerate() {
@@ -121,7 +263,7 @@ pub fn read_meta_mapping(
     let mut line_no = 1usize;
     loop {
         line.clear();
-        let read = reader.read_line(&mut line)?;
+        let read = reader.read_line(&mut line).map_err(InputError::io(path))?;
         if read == 0 {
             break;
         }
@@ -131,15 +273,22 @@ pub fn read_meta_mapping(
             continue;
         }
         let parts: Vec<&str> = value.split('\t').collect();
+        stats.check_row(line_no, &parts, columns.len(), species_idx);
         if cell_idx >= parts.len() {
-            return Err(InputError::MissingMetaCellId(line_no));
+            return Err(InputError::MissingMetaCellId {
+                path: path.to_path_buf(),
+                line: line_no,
+            });
         }
         let cell_id = parts[cell_idx];
         if cell_id.is_empty() {
-            return Err(InputError::MissingMetaCellId(line_no));
+            return Err(InputError::MissingMetaCellId {
+                path: path.to_path_buf(),
+                line: line_no,
+            });
         }
         if !seen_cells.insert(cell_id.to_string()) {
-            stats.duplicate_rows += 1;
+            stats.record_duplicate(line_no, cell_id);
             continue;
         }
         if let Some(&idx) = index_by_cell.get(cell_id) {
@@ -154,9 +303,74 @@ pub fn read_meta_mapping(
                 }
             }
         } else {
-            stats.missing += 1;
+            stats.record_unmatched(cell_id);
         }
     }
 
     Ok((sample_ids, stats))
 }
+
+/// Normalized species per barcode (see [`normalize_species`]); `unknown` for
+/// barcodes without a meta row or species value, and for every cell when the
+/// meta file has no `species` column. The first row of a repeated `cell_id`
+/// wins, as in [`read_meta`].
+pub fn read_meta_species(
+    path: &Path,
+    barcodes: &[String],
+) -> Result<Vec<&'static str>, InputError> {
+    let mut species = vec!["unknown"; barcodes.len()];
+    let mut reader = open_reader(path)?;
+    let mut header_line = String::new();
+    if reader
+        .read_line(&mut header_line)
+        .map_err(InputError::io(path))?
+        == 0
+    {
+        return Err(InputError::EmptyFile {
+            path: path.to_path_buf(),
+            expected: "header line".to_string(),
+        });
+    }
+    let columns: Vec<&str> = header_line
+        .trim_end_matches(['\n', '\r'])
+        .split('\t')
+        .collect();
+    let cell_idx = columns
+        .iter()
+        .position(|c| *c == "cell_id")
+        .ok_or_else(|| InputError::MissingMetaColumn {
+            path: path.to_path_buf(),
+            column: "cell_id".to_string(),
+        })?;
+    let Some(species_idx) = columns.iter().position(|c| *c == "species") else {
+        return Ok(species);
+    };
+
+    let index_by_cell: HashMap<&str, usize> = barcodes
+        .iter()
+        .enumerate()
+        .map(|(i, c)| (c.as_str(), i))
+        .collect();
+    let mut seen_cells: HashSet<usize> = HashSet::new();
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).map_err(InputError::io(path))? == 0 {
+            break;
+        }
+        let parts: Vec<&str> = line.trim_end_matches(['\n', '\r']).split('\t').collect();
+        let Some(&idx) = parts.get(cell_idx).and_then(|c| index_by_cell.get(c)) else {
+            continue;
+        };
+        if seen_cells.insert(idx)
+            && let Some(value) = parts.get(species_idx)
+        {
+            species[idx] = normalize_species(value);
+        }
+    }
+    Ok(species)
+}
+
+#[cfg(test)]
+#[path = "../../tests/src_inline/input/meta.rs"]
+mod tests;
@@ -1,40 +1,168 @@
 pub mod barcodes;
 pub mod cache;
+pub mod delimiter;
+pub mod dense;
 pub mod detect;
 pub mod features;
+#[cfg(feature = "gz-parallel")]
+pub mod gz;
+pub mod h5ad;
+#[cfg(feature = "h5ad")]
+mod hdf5;
 pub mod meta;
 pub mod mtx;
+pub mod table;
+pub mod upstream;
 
 use std::path::{Path, PathBuf};
 use std::{fmt, io};
 
 use thiserror::Error;
 
+use crate::pipeline::cancel::Cancelled;
+
+/// Input read failures. Errors about a file name it; the bare Matrix Market
+/// variants come from stream parsers and are wrapped in
+/// [`InputError::Matrix`] by the file readers.
 #[derive(Error, Debug)]
 pub enum InputError {
-    #[error("missing required file: {0}")]
-    MissingFile(String),
+    #[error("missing required file: {}", path.display())]
+    MissingFile { path: PathBuf },
+    #[error("{} is not a readable 10x directory: {reason}", dir.display())]
+    InvalidLayout { dir: PathBuf, reason: String },
+    #[error("{} holds more than one candidate {what}: [{}]", dir.display(), candidates.join(", "))]
+    LayoutAmbiguous {
+        dir: PathBuf,
+        what: String,
+        candidates: Vec<String>,
+    },
+    #[error("cannot pick a dataset prefix in {}: {reason}", dir.display())]
+    PrefixConflict { dir: PathBuf, reason: String },
     #[error("invalid matrix market header: {0}")]
     InvalidMtxHeader(String),
     #[error("invalid matrix dimensions: {0}")]
     InvalidMtxDimensions(String),
-    #[error("invalid TSV row at line {line}: {reason}")]
-    InvalidTsvRow { line: usize, reason: String },
-    #[error("empty barcode at line {0}")]
-    EmptyBarcode(usize),
-    #[error("meta file missing required column: {0}")]
-    MissingMetaColumn(String),
-    #[error("meta row missing cell_id at line {0}")]
-    MissingMetaCellId(usize),
+    #[error("invalid matrix value {token} at {location}: {reason}")]
+    InvalidMtxValue {
+        location: String,
+        token: String,
+        reason: String,
+    },
+    #[error("{}: {error}", path.display())]
+    Matrix {
+        path: PathBuf,
+        error: Box<InputError>,
+    },
+    #[error("{} line {line}: {reason}", path.display())]
+    InvalidTsvRow {
+        path: PathBuf,
+        line: usize,
+        reason: String,
+    },
+    #[error("{}: no {expected} found", path.display())]
+    EmptyFile { path: PathBuf, expected: String },
+    #[error("{} line {line}: empty barcode", path.display())]
+    EmptyBarcode { path: PathBuf, line: usize },
+    #[error("{}: meta file missing required column {column}", path.display())]
+    MissingMetaColumn { path: PathBuf, column: String },
+    #[error(
+        "{} missing required column(s) {}: expected [{}], found [{}]",
+        file.display(),
+        missing.join(", "),
+        expected.join(", "),
+        found.join(", ")
+    )]
+    MissingColumns {
+        file: PathBuf,
+        missing: Vec<String>,
+        expected: Vec<String>,
+        found: Vec<String>,
+    },
+    #[error("{} line {line}, column {column}: {reason}", file.display())]
+    InvalidTsvField {
+        file: PathBuf,
+        line: usize,
+        column: String,
+        reason: String,
+    },
+    #[error("{} line {line}: meta row missing cell_id", path.display())]
+    MissingMetaCellId { path: PathBuf, line: usize },
+    #[error(
+        "--input {} is a file, expected a directory containing matrix.mtx, features.tsv and barcodes.tsv; 10x files in {}: [{}]",
+        path.display(),
+        parent.display(),
+        candidates.join(", ")
+    )]
+    InputIsFile {
+        path: PathBuf,
+        parent: PathBuf,
+        candidates: Vec<String>,
+    },
+    #[error(
+        "--cache {} is a directory without a shared cache (*kira-organelle.bin), expected the cache file; files in it: [{}]",
+        path.display(),
+        candidates.join(", ")
+    )]
+    CacheIsDirectory {
+        path: PathBuf,
+        candidates: Vec<String>,
+    },
+    #[error(
+        "--input {} is an AnnData .h5ad file, which this build cannot read: rebuild with `--features h5ad` (links libhdf5), or write X as raw counts with var_names and obs_names to a 10x directory (matrix.mtx, features.tsv, barcodes.tsv) and pass that",
+        path.display()
+    )]
+    H5adUnsupported { path: PathBuf },
+    #[error("{}: {reason}", path.display())]
+    InvalidH5ad { path: PathBuf, reason: String },
+    #[error("{} {dataset} entry {index} is {value}, not a raw count; {hint}", path.display())]
+    H5adNotCounts {
+        path: PathBuf,
+        dataset: String,
+        index: usize,
+        value: f64,
+        hint: String,
+    },
     #[error("unsupported gzip input without feature enabled: {0}")]
     GzipNotEnabled(PathBuf),
-    #[error("io error: {0}")]
-    Io(#[from] io::Error),
+    #[error("io error reading {}: {source}", path.display())]
+    Io { path: PathBuf, source: io::Error },
+    /// From a stream with no file behind it yet; file readers wrap it in
+    /// [`InputError::Matrix`].
+    #[error("read error: {0}")]
+    Read(io::Error),
+    /// The run was cancelled while the matrix was being read.
+    #[error("{0}")]
+    Cancelled(#[from] Cancelled),
+}
+
+impl InputError {
+    /// `map_err` adapter for io errors on `path`.
+    pub fn io(path: &Path) -> impl FnOnce(io::Error) -> InputError + '_ {
+        move |source| InputError::Io {
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+
+    /// `map_err` adapter naming the matrix file `path` on stream-level
+    /// errors; errors that already carry a path pass through.
+    pub fn in_matrix(path: &Path) -> impl FnOnce(InputError) -> InputError + '_ {
+        move |error| match error {
+            InputError::InvalidMtxHeader(_)
+            | InputError::InvalidMtxDimensions(_)
+            | InputError::InvalidMtxValue { .. }
+            | InputError::Read(_) => InputError::Matrix {
+                path: path.to_path_buf(),
+                error: Box::new(error),
+            },
+            error => error,
+        }
+    }
 }
 
 pub fn open_reader(path: &Path) -> Result<Box<dyn io::BufRead>, InputError> {
-    let file = std::fs::File::open(path)?;
-    if path.extension().and_then(|s| s.to_str()) == Some("gz") {
+    let file = std::fs::File::open(path).map_err(InputError::io(path))?;
+    if is_gz(path) {
         #[cfg(feature = "gz")]
         {
             let decoder = flate2::read::GzDecoder::new(file);
@@ -48,6 +176,24 @@ pub fn open_reader(path: &Path) -> Result<Box<dyn io::BufRead>, InputError> {
     Ok(Box::new(io::BufReader::new(file)))
 }
 
+/// Opens a matrix file. With the `gz-parallel` feature, `.gz` input is inflated
+/// on a background thread; otherwise this is [`open_reader`].
+pub fn open_mtx_reader(path: &Path) -> Result<Box<dyn io::BufRead>, InputError> {
+    #[cfg(feature = "gz-parallel")]
+    {
+        if is_gz(path) {
+            return Ok(Box::new(
+                gz::PipelinedGzReader::open(path).map_err(InputError::io(path))?,
+            ));
+        }
+    }
+    open_reader(path)
+}
+
+pub fn is_gz(path: &Path) -> bool {
+    path.extension().and_then(|s| s.to_str()) == Some("gz")
+}
+
 pub fn path_display(path: &Path) -> impl fmt::Display + '_ {
     path.to_string_lossy()
 }
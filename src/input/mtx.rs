@@ -1,40 +1,329 @@
+use std::io::BufRead;
 use std::path::Path;
 
 use kira_scio::api::{Reader, ReaderOptions};
 use kira_scio::detect::DetectedFormat;
 
-use crate::input::InputError;
+use crate::input::{InputError, is_gz, open_mtx_reader, open_reader};
+use crate::pipeline::cancel::{checkpoint, entry_checkpoint};
 
 #[derive(Debug, Clone, Copy)]
 pub struct MatrixHeader {
     pub n_rows: usize,
     pub n_cols: usize,
     pub nnz: usize,
+    /// Value type named by the banner.
+    pub field: MtxField,
 }
 
-pub fn read_header(path: &Path) -> Result<MatrixHeader, InputError> {
-    let matrix = Reader::with_options(
-        path,
-        ReaderOptions {
-            force_format: Some(DetectedFormat::Mtx10x),
-            strict: true,
-        },
-    )
-    .read_matrix()
-    .map_err(|e| InputError::InvalidMtxHeader(e.message))?;
+/// Field type from the Matrix Market banner.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MtxField {
+    Integer,
+    /// Non-integral values are rounded to the nearest count and counted in
+    /// [`MtxValueWarnings::n_rounded`]; those rounding to 0 are dropped.
+    Real,
+    /// Entries carry no value; each stored entry counts as 1.
+    Pattern,
+}
+
+impl MtxField {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MtxField::Integer => "integer",
+            MtxField::Real => "real",
+            MtxField::Pattern => "pattern",
+        }
+    }
+}
+
+/// Which MTX dimension holds the genes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MtxOrientation {
+    /// The 10x layout: genes as rows, cells as columns.
+    #[default]
+    GenesByCells,
+    /// Cells as rows, genes as columns, as some converters write it
+    /// (`--transpose-matrix`).
+    CellsByGenes,
+}
+
+impl MtxOrientation {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MtxOrientation::GenesByCells => "genes_by_cells",
+            MtxOrientation::CellsByGenes => "cells_by_genes",
+        }
+    }
+
+    /// `(rows, columns)` the header declares for this orientation.
+    pub fn dims(self, n_genes: usize, n_cells: usize) -> (usize, usize) {
+        match self {
+            MtxOrientation::GenesByCells => (n_genes, n_cells),
+            MtxOrientation::CellsByGenes => (n_cells, n_genes),
+        }
+    }
+}
 
+/// Parses a `%%MatrixMarket matrix coordinate <field> general` banner.
+/// A leading UTF-8 BOM, repeated whitespace and keyword case are tolerated;
+/// every error quotes the banner as found.
+pub fn parse_banner(line: &str) -> Result<MtxField, InputError> {
+    let text = line.trim_start_matches('\u{feff}').trim();
+    let tokens: Vec<String> = text
+        .split_ascii_whitespace()
+        .map(|t| t.to_ascii_lowercase())
+        .collect();
+    let expected = || {
+        InputError::InvalidMtxHeader(format!(
+            "expected `%%MatrixMarket matrix coordinate <integer|real|pattern> general`, found {:?}",
+            text
+        ))
+    };
+    if tokens.len() != 5 || tokens[0] != "%%matrixmarket" || tokens[1] != "matrix" {
+        return Err(expected());
+    }
+    if tokens[2] != "coordinate" {
+        return Err(InputError::InvalidMtxHeader(format!(
+            "only sparse `coordinate` matrices are supported, found {:?}",
+            text
+        )));
+    }
+    let field = match tokens[3].as_str() {
+        "integer" => MtxField::Integer,
+        "real" => MtxField::Real,
+        "pattern" => MtxField::Pattern,
+        _ => return Err(expected()),
+    };
+    match tokens[4].as_str() {
+        "general" => Ok(field),
+        symmetry @ ("symmetric" | "skew-symmetric" | "hermitian") => {
+            Err(InputError::InvalidMtxHeader(format!(
+                "{} matrices are not supported: a genes x cells count matrix must store every \
+                 entry; re-export it as `general` (banner: {:?})",
+                symmetry, text
+            )))
+        }
+        _ => Err(expected()),
+    }
+}
+
+/// Reads the banner, skips comments and parses the dimensions line.
+/// `line_no` is left at the dimensions line.
+fn read_banner_and_dims<R: BufRead>(
+    reader: &mut R,
+    line: &mut String,
+    line_no: &mut usize,
+) -> Result<MatrixHeader, InputError> {
+    line.clear();
+    *line_no = 1;
+    if reader.read_line(line).map_err(InputError::Read)? == 0 {
+        return Err(InputError::InvalidMtxHeader(
+            "empty matrix file".to_string(),
+        ));
+    }
+    let field = parse_banner(line)?;
+
+    let dims = loop {
+        line.clear();
+        *line_no += 1;
+        if reader.read_line(line).map_err(InputError::Read)? == 0 {
+            return Err(InputError::InvalidMtxHeader(
+                "missing dimensions line".to_string(),
+            ));
+        }
+        let trimmed = line.trim();
+        if !trimmed.is_empty() && !trimmed.starts_with('%') {
+            break trimmed
+                .split_ascii_whitespace()
+                .map(|t| t.parse::<usize>())
+                .collect::<Result<Vec<_>, _>>()
+                .ok()
+                .filter(|d| d.len() == 3)
+                .ok_or_else(|| {
+                    InputError::InvalidMtxHeader(format!("invalid dimensions line: {}", trimmed))
+                })?;
+        }
+    };
     Ok(MatrixHeader {
-        n_rows: matrix.n_genes,
-        n_cols: matrix.n_cells,
-        nnz: matrix.values.len(),
+        n_rows: dims[0],
+        n_cols: dims[1],
+        nnz: dims[2],
+        field,
     })
 }
 
+/// Banner and dimensions line; `nnz` is the declared entry count.
+pub fn read_header(path: &Path) -> Result<MatrixHeader, InputError> {
+    let mut reader = open_reader(path)?;
+    read_banner_and_dims(&mut reader, &mut String::new(), &mut 0)
+        .map_err(InputError::in_matrix(path))
+}
+
+/// Number of entry lines after the dimensions line.
 pub fn count_nnz_lines(path: &Path) -> Result<usize, InputError> {
-    Ok(read_header(path)?.nnz)
+    count_entry_lines(open_reader(path)?).map_err(InputError::in_matrix(path))
 }
 
-pub fn read_entries(path: &Path) -> Result<(MatrixHeader, Vec<(u32, u32, u32)>), InputError> {
+fn count_entry_lines<R: BufRead>(mut reader: R) -> Result<usize, InputError> {
+    let mut line = String::new();
+    read_banner_and_dims(&mut reader, &mut line, &mut 0)?;
+    let mut count = 0;
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).map_err(InputError::Read)? == 0 {
+            return Ok(count);
+        }
+        let trimmed = line.trim();
+        if !trimmed.is_empty() && !trimmed.starts_with('%') {
+            count += 1;
+        }
+    }
+}
+
+pub type MatrixEntries = (MatrixHeader, Vec<(u32, u32, u32)>);
+
+/// Default for [`MtxValueLimits::max_value`].
+pub const DEFAULT_MAX_COUNT_VALUE: u32 = 10_000_000;
+/// Default for [`MtxValueLimits::warn_value`].
+pub const DEFAULT_WARN_COUNT_VALUE: u32 = 100_000;
+
+/// Bounds on a single matrix entry. UMI counts never get near these; values
+/// above them usually mean cumulative sums or scaled data were exported.
+#[derive(Debug, Clone, Copy)]
+pub struct MtxValueLimits {
+    /// Entries above this fail the read.
+    pub max_value: u32,
+    /// Entries above this are counted into [`MtxValueWarnings`].
+    pub warn_value: u32,
+}
+
+impl Default for MtxValueLimits {
+    fn default() -> Self {
+        Self {
+            max_value: DEFAULT_MAX_COUNT_VALUE,
+            warn_value: DEFAULT_WARN_COUNT_VALUE,
+        }
+    }
+}
+
+/// Entries above [`MtxValueLimits::warn_value`], `real` entries rounded to a
+/// count and repeated coordinates, seen while reading.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MtxValueWarnings {
+    pub n_above_warn: usize,
+    pub max_seen: u32,
+    /// Location of the first such entry (`line N`, or row/column when the
+    /// reader does not track lines).
+    pub first_location: Option<String>,
+    /// `real` entries with a fractional part, rounded to the nearest count.
+    pub n_rounded: usize,
+    /// Location of the first rounded entry.
+    pub first_rounded_location: Option<String>,
+    /// Rounded entries that became 0 (below 0.5); they are dropped rather
+    /// than stored, so they do not count as detected genes.
+    pub n_rounded_to_zero: usize,
+    /// Entries repeating an earlier (row, column); their values were added
+    /// to that entry when building the CSC matrix.
+    pub duplicate_entries_merged: usize,
+}
+
+impl MtxValueWarnings {
+    fn record(&mut self, value: u32, location: impl FnOnce() -> String) {
+        if self.first_location.is_none() {
+            self.first_location = Some(location());
+        }
+        self.n_above_warn += 1;
+        self.max_seen = self.max_seen.max(value);
+    }
+
+    fn record_rounded(&mut self, location: impl FnOnce() -> String) {
+        if self.first_rounded_location.is_none() {
+            self.first_rounded_location = Some(location());
+        }
+        self.n_rounded += 1;
+    }
+}
+
+/// Rounds a `real` entry to the nearest count (halves away from zero),
+/// recording it when it had a fractional part. `None` when a fractional
+/// entry rounds to 0: the entry is dropped.
+fn round_real(
+    value: f64,
+    location: impl FnOnce() -> String,
+    warnings: &mut MtxValueWarnings,
+) -> Option<u64> {
+    let rounded = value.round();
+    if (value - rounded).abs() > 1e-6 {
+        warnings.record_rounded(location);
+        if rounded == 0.0 {
+            warnings.n_rounded_to_zero += 1;
+            return None;
+        }
+    }
+    Some(rounded as u64)
+}
+
+/// Checks an integral, non-negative entry against the u32 range and `limits`.
+fn check_count(
+    value: u64,
+    token: &str,
+    location: impl Fn() -> String,
+    limits: &MtxValueLimits,
+    warnings: &mut MtxValueWarnings,
+) -> Result<u32, InputError> {
+    let invalid = |reason: String| InputError::InvalidMtxValue {
+        location: location(),
+        token: token.to_string(),
+        reason,
+    };
+    let Ok(count) = u32::try_from(value) else {
+        return Err(invalid(format!(
+            "exceeds the u32 count range (max {}); the matrix may hold cumulative sums instead of raw counts",
+            u32::MAX
+        )));
+    };
+    if count > limits.max_value {
+        return Err(invalid(format!(
+            "exceeds the per-entry maximum of {} (--max-count-value); the matrix may not contain raw counts",
+            limits.max_value
+        )));
+    }
+    if count > limits.warn_value {
+        warnings.record(count, &location);
+    }
+    Ok(count)
+}
+
+pub fn read_entries(path: &Path) -> Result<MatrixEntries, InputError> {
+    read_entries_with_limits(
+        path,
+        &MtxValueLimits::default(),
+        &mut MtxValueWarnings::default(),
+    )
+}
+
+/// [`read_entries`] with explicit per-entry limits; entries above the warning
+/// threshold are counted into `warnings`.
+pub fn read_entries_with_limits(
+    path: &Path,
+    limits: &MtxValueLimits,
+    warnings: &mut MtxValueWarnings,
+) -> Result<MatrixEntries, InputError> {
+    read_file_entries(path, limits, warnings).map_err(InputError::in_matrix(path))
+}
+
+fn read_file_entries(
+    path: &Path,
+    limits: &MtxValueLimits,
+    warnings: &mut MtxValueWarnings,
+) -> Result<MatrixEntries, InputError> {
+    // The streaming parser reports line numbers and tolerates banner quirks;
+    // gzip goes through it whenever a decoder is built in.
+    if !is_gz(path) || cfg!(feature = "gz") {
+        return parse_coordinate_entries_with_limits(open_mtx_reader(path)?, limits, warnings);
+    }
+
     let matrix = Reader::with_options(
         path,
         ReaderOptions {
@@ -45,26 +334,146 @@ pub fn read_entries(path: &Path) -> Result<(MatrixHeader, Vec<(u32, u32, u32)>),
     .read_matrix()
     .map_err(|e| InputError::InvalidMtxHeader(e.message))?;
 
-    let header = MatrixHeader {
-        n_rows: matrix.n_genes,
-        n_cols: matrix.n_cells,
-        nnz: matrix.values.len(),
-    };
-
-    let mut entries = Vec::with_capacity(header.nnz);
+    // This reader does not expose the banner: fractional values are taken as
+    // `real` and rounded like the streaming parser does.
+    let rounded_before = warnings.n_rounded;
+    let mut entries = Vec::with_capacity(matrix.values.len());
     for (col, w) in matrix.col_ptr.windows(2).enumerate() {
+        checkpoint(col + 1)?;
         for idx in w[0]..w[1] {
             let row = matrix.row_idx[idx];
-            let value = matrix.values[idx];
-            if value < 0.0 || value.fract().abs() > 1e-6 {
+            let value = f64::from(matrix.values[idx]);
+            if !value.is_finite() || value < 0.0 {
                 return Err(InputError::InvalidMtxDimensions(
-                    "non-integer matrix value".to_string(),
+                    "negative or non-finite matrix value".to_string(),
                 ));
             }
-            entries.push((col as u32, row as u32, value as u32));
+            let location = || format!("row {}, column {}", row + 1, col + 1);
+            let Some(value) = round_real(value, location, warnings) else {
+                continue;
+            };
+            let count = check_count(
+                value,
+                &matrix.values[idx].to_string(),
+                location,
+                limits,
+                warnings,
+            )?;
+            entries.push((col as u32, row as u32, count));
         }
     }
 
+    let header = MatrixHeader {
+        n_rows: matrix.n_genes,
+        n_cols: matrix.n_cells,
+        nnz: entries.len(),
+        field: if warnings.n_rounded > rounded_before {
+            MtxField::Real
+        } else {
+            MtxField::Integer
+        },
+    };
+    Ok((header, entries))
+}
+
+/// Streams a `coordinate integer|real|pattern general` Matrix Market body
+/// into `(col, row, value)` entries with the same validation as
+/// [`read_entries`]; unsupported banners are errors.
+pub fn parse_coordinate_entries<R: BufRead>(reader: R) -> Result<MatrixEntries, InputError> {
+    parse_coordinate_entries_with_limits(
+        reader,
+        &MtxValueLimits::default(),
+        &mut MtxValueWarnings::default(),
+    )
+}
+
+/// [`parse_coordinate_entries`] with explicit per-entry limits.
+pub fn parse_coordinate_entries_with_limits<R: BufRead>(
+    mut reader: R,
+    limits: &MtxValueLimits,
+    warnings: &mut MtxValueWarnings,
+) -> Result<MatrixEntries, InputError> {
+    let mut line = String::new();
+    let mut line_no = 0;
+    let header = read_banner_and_dims(&mut reader, &mut line, &mut line_no)?;
+    let (n_rows, n_cols, declared_nnz) = (header.n_rows, header.n_cols, header.nnz);
+
+    let mut entries = Vec::with_capacity(declared_nnz);
+    // Entry lines read, including `real` entries dropped after rounding to 0.
+    let mut n_read = 0usize;
+    loop {
+        line.clear();
+        line_no += 1;
+        if reader.read_line(&mut line).map_err(InputError::Read)? == 0 {
+            break;
+        }
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('%') {
+            continue;
+        }
+        let malformed =
+            || InputError::InvalidMtxDimensions(format!("malformed matrix entry: {}", trimmed));
+        let mut parts = trimmed.split_ascii_whitespace();
+        let (Some(row), Some(col)) = (parts.next(), parts.next()) else {
+            return Err(malformed());
+        };
+        // Pattern entries are `row col` only and count as 1.
+        let token = match (header.field, parts.next()) {
+            (MtxField::Pattern, None) => "1",
+            (MtxField::Pattern, Some(_)) | (_, None) => return Err(malformed()),
+            (_, Some(token)) => token,
+        };
+        let row: usize = row.parse().map_err(|_| malformed())?;
+        let col: usize = col.parse().map_err(|_| malformed())?;
+        n_read += 1;
+        entry_checkpoint(n_read)?;
+        // Integers are parsed exactly; `integer` files may still write `3.0`,
+        // and `real` values are rounded to a count.
+        let value = match token.parse::<u64>() {
+            Ok(v) => Some(v),
+            Err(_) => {
+                let v: f64 = token.parse().map_err(|_| malformed())?;
+                let integral = v.fract().abs() <= 1e-6;
+                if !v.is_finite() || v < 0.0 || (header.field != MtxField::Real && !integral) {
+                    return Err(InputError::InvalidMtxDimensions(format!(
+                        "non-integer matrix value {} at line {} (the banner declares `{}`)",
+                        token,
+                        line_no,
+                        header.field.as_str()
+                    )));
+                }
+                round_real(v, || format!("line {}", line_no), warnings)
+            }
+        };
+        if row == 0 || col == 0 || row > n_rows || col > n_cols {
+            return Err(InputError::InvalidMtxDimensions(
+                "matrix index out of bounds".to_string(),
+            ));
+        }
+        let Some(value) = value else {
+            continue;
+        };
+        let count = check_count(
+            value,
+            token,
+            || format!("line {}", line_no),
+            limits,
+            warnings,
+        )?;
+        entries.push(((col - 1) as u32, (row - 1) as u32, count));
+    }
+    if n_read != declared_nnz {
+        return Err(InputError::InvalidMtxDimensions(
+            "nnz count does not match header".to_string(),
+        ));
+    }
+
+    // Match the column-major order produced by the CSC-based reader.
+    entries.sort_by_key(|&(col, _, _)| col);
+    let header = MatrixHeader {
+        nnz: entries.len(),
+        ..header
+    };
     Ok((header, entries))
 }
 
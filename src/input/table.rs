@@ -0,0 +1,196 @@
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+
+use crate::input::{InputError, open_reader};
+
+/// Streaming reader for the crate's own header-first TSV artifacts.
+///
+/// Columns are looked up by header name, so appended or reordered columns do
+/// not break readers that only ask for the columns they need.
+pub struct TsvReader {
+    reader: Box<dyn BufRead>,
+    file: PathBuf,
+    columns: Vec<String>,
+    index: HashMap<String, usize>,
+    line: String,
+    line_no: usize,
+    /// Byte ranges of the current row's fields, reused across rows.
+    spans: Vec<(usize, usize)>,
+}
+
+impl TsvReader {
+    pub fn open(path: &Path) -> Result<Self, InputError> {
+        if !path.exists() {
+            return Err(InputError::MissingFile {
+                path: path.to_path_buf(),
+            });
+        }
+        let mut reader = open_reader(path)?;
+        let mut header = String::new();
+        if reader
+            .read_line(&mut header)
+            .map_err(InputError::io(path))?
+            == 0
+        {
+            return Err(InputError::EmptyFile {
+                path: path.to_path_buf(),
+                expected: "header line".to_string(),
+            });
+        }
+        let columns: Vec<String> = header
+            .trim_end_matches(['\n', '\r'])
+            .split('\t')
+            .map(str::to_string)
+            .collect();
+        let columns_len = columns.len();
+        let index = columns
+            .iter()
+            .enumerate()
+            .map(|(i, c)| (c.clone(), i))
+            .collect();
+        Ok(Self {
+            reader,
+            file: path.to_path_buf(),
+            columns,
+            index,
+            line: String::new(),
+            line_no: 1,
+            spans: Vec::with_capacity(columns_len),
+        })
+    }
+
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    pub fn has_column(&self, name: &str) -> bool {
+        self.index.contains_key(name)
+    }
+
+    /// Position of a required column.
+    pub fn column(&self, name: &str) -> Result<usize, InputError> {
+        Ok(self.require(&[name])?[0])
+    }
+
+    /// Positions of required columns, in `names` order. Every missing name is
+    /// reported at once, with the expected and found column lists.
+    pub fn require<S: AsRef<str>>(&self, names: &[S]) -> Result<Vec<usize>, InputError> {
+        let missing: Vec<String> = names
+            .iter()
+            .map(AsRef::as_ref)
+            .filter(|name| !self.has_column(name))
+            .map(str::to_string)
+            .collect();
+        if !missing.is_empty() {
+            return Err(InputError::MissingColumns {
+                file: self.file.clone(),
+                missing,
+                expected: names.iter().map(|n| n.as_ref().to_string()).collect(),
+                found: self.columns.clone(),
+            });
+        }
+        Ok(names.iter().map(|n| self.index[n.as_ref()]).collect())
+    }
+
+    /// Next non-empty data row, or `None` at end of file.
+    pub fn next_row(&mut self) -> Result<Option<TsvRow<'_>>, InputError> {
+        loop {
+            self.line.clear();
+            if self
+                .reader
+                .read_line(&mut self.line)
+                .map_err(InputError::io(&self.file))?
+                == 0
+            {
+                return Ok(None);
+            }
+            self.line_no += 1;
+            if !self.line.trim_end_matches(['\n', '\r']).is_empty() {
+                break;
+            }
+        }
+        let text = self.line.trim_end_matches(['\n', '\r']);
+        self.spans.clear();
+        let mut start = 0;
+        for field in text.split('\t') {
+            self.spans.push((start, start + field.len()));
+            start += field.len() + 1;
+        }
+        Ok(Some(TsvRow {
+            text,
+            spans: &self.spans,
+            line: self.line_no,
+            file: &self.file,
+            columns: &self.columns,
+        }))
+    }
+}
+
+pub struct TsvRow<'a> {
+    text: &'a str,
+    spans: &'a [(usize, usize)],
+    line: usize,
+    file: &'a Path,
+    columns: &'a [String],
+}
+
+impl<'a> TsvRow<'a> {
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    pub fn str(&self, col: usize) -> Result<&'a str, InputError> {
+        let &(start, end) = self.spans.get(col).ok_or_else(|| {
+            self.error(
+                col,
+                format!(
+                    "row has {} fields, the header has {}",
+                    self.spans.len(),
+                    self.columns.len()
+                ),
+            )
+        })?;
+        Ok(&self.text[start..end])
+    }
+
+    /// Float written by `signed_or_nan`; any NaN token (`nan`, `NA`, empty) reads as NaN.
+    pub fn f32_or_nan(&self, col: usize) -> Result<f32, InputError> {
+        let raw = self.str(col)?;
+        if raw.is_empty() || raw == "NA" || raw.eq_ignore_ascii_case("nan") {
+            return Ok(f32::NAN);
+        }
+        raw.parse().map_err(|_| self.invalid(col, raw))
+    }
+
+    pub fn u64(&self, col: usize) -> Result<u64, InputError> {
+        let raw = self.str(col)?;
+        raw.parse().map_err(|_| self.invalid(col, raw))
+    }
+
+    pub fn u32(&self, col: usize) -> Result<u32, InputError> {
+        let raw = self.str(col)?;
+        raw.parse().map_err(|_| self.invalid(col, raw))
+    }
+
+    fn invalid(&self, col: usize, raw: &str) -> InputError {
+        self.error(col, format!("not a number: {:?}", raw))
+    }
+
+    fn error(&self, col: usize, reason: String) -> InputError {
+        InputError::InvalidTsvField {
+            file: self.file.to_path_buf(),
+            line: self.line,
+            column: self
+                .columns
+                .get(col)
+                .cloned()
+                .unwrap_or_else(|| format!("#{}", col + 1)),
+            reason,
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "../../tests/src_inline/input/table.rs"]
+mod tests;
@@ -0,0 +1,154 @@
+use std::collections::{HashMap, HashSet};
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+
+use tracing::warn;
+
+use crate::input::{InputError, open_reader};
+
+/// Prefix of the pass-through columns in `secretion.tsv` and `summary.json`.
+pub const UPSTREAM_PREFIX: &str = "upstream_";
+
+/// Per-cell float columns the shared cache producer wrote next to the cache,
+/// in cache barcode order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpstreamColumns {
+    pub source: PathBuf,
+    pub columns: Vec<UpstreamColumn>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpstreamColumn {
+    /// Name as in the sidecar header, without [`UPSTREAM_PREFIX`].
+    pub name: String,
+    pub values: Vec<f32>,
+}
+
+impl UpstreamColumn {
+    /// Column name in the outputs.
+    pub fn output_name(&self) -> String {
+        format!("{UPSTREAM_PREFIX}{}", self.name)
+    }
+}
+
+/// Sidecar of a shared cache: `X.kira-organelle.bin` → `X.kira-organelle.cells.tsv`.
+pub fn upstream_sidecar_path(cache: &Path) -> PathBuf {
+    let name = cache
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let stem = name.strip_suffix(".bin").unwrap_or(&name);
+    cache.with_file_name(format!("{stem}.cells.tsv"))
+}
+
+/// Reads a tab-separated sidecar whose header is `barcode` followed by column
+/// names, one row per cell. Rows are matched to `barcodes` by their first
+/// field. A column is skipped with a warning when its name is empty or
+/// repeated, when a value does not parse as a float (`NaN` does), or when it
+/// does not have a value for every cell; rows for unknown barcodes are
+/// ignored with a warning.
+pub fn read_upstream_columns(
+    path: &Path,
+    barcodes: &[String],
+) -> Result<UpstreamColumns, InputError> {
+    let mut lines = open_reader(path)?.lines();
+    let header = match lines.next() {
+        Some(line) => line.map_err(InputError::io(path))?,
+        None => {
+            return Err(InputError::EmptyFile {
+                path: path.to_path_buf(),
+                expected: "header line".to_string(),
+            });
+        }
+    };
+    let names: Vec<String> = header
+        .trim_end_matches('\r')
+        .split('\t')
+        .skip(1)
+        .map(str::to_string)
+        .collect();
+
+    let index: HashMap<&str, usize> = barcodes
+        .iter()
+        .enumerate()
+        .map(|(i, b)| (b.as_str(), i))
+        .collect();
+    let mut values = vec![vec![f32::NAN; barcodes.len()]; names.len()];
+    let mut filled = vec![0usize; names.len()];
+    let mut invalid: Vec<Option<(usize, String)>> = vec![None; names.len()];
+    let mut seen = vec![false; barcodes.len()];
+    let mut unknown_rows = 0usize;
+    for (line_no, line) in lines.enumerate() {
+        let line = line.map_err(InputError::io(path))?;
+        let line = line.trim_end_matches('\r');
+        if line.is_empty() {
+            continue;
+        }
+        let mut fields = line.split('\t');
+        let barcode = fields.next().unwrap_or("");
+        let Some(&cell) = index.get(barcode) else {
+            unknown_rows += 1;
+            continue;
+        };
+        if std::mem::replace(&mut seen[cell], true) {
+            continue;
+        }
+        for (col, token) in fields.take(names.len()).enumerate() {
+            if invalid[col].is_some() {
+                continue;
+            }
+            match token.trim().parse::<f32>() {
+                Ok(v) => {
+                    values[col][cell] = v;
+                    filled[col] += 1;
+                }
+                Err(_) => invalid[col] = Some((line_no + 2, token.to_string())),
+            }
+        }
+    }
+    if unknown_rows > 0 {
+        warn!(
+            path = %path.display(),
+            rows = unknown_rows,
+            "upstream columns: rows for barcodes not in the cache ignored"
+        );
+    }
+
+    let mut used = HashSet::new();
+    let mut columns = Vec::new();
+    for ((name, values), (filled, invalid)) in names
+        .into_iter()
+        .zip(values)
+        .zip(filled.into_iter().zip(invalid))
+    {
+        let skip = if name.is_empty() {
+            Some("empty column name".to_string())
+        } else if !used.insert(name.clone()) {
+            Some("repeated column name".to_string())
+        } else if let Some((line, token)) = invalid {
+            Some(format!("non-numeric value '{token}' at line {line}"))
+        } else if filled != barcodes.len() {
+            Some(format!("{filled} values for {} cells", barcodes.len()))
+        } else {
+            None
+        };
+        match skip {
+            Some(reason) => warn!(
+                path = %path.display(),
+                column = %name,
+                reason = %reason,
+                "upstream column skipped"
+            ),
+            None => columns.push(UpstreamColumn { name, values }),
+        }
+    }
+
+    Ok(UpstreamColumns {
+        source: path.to_path_buf(),
+        columns,
+    })
+}
+
+#[cfg(test)]
+#[path = "../../tests/src_inline/input/upstream.rs"]
+mod tests;
@@ -7,8 +7,12 @@ pub mod panels;
 pub mod pipeline;
 pub mod report;
 pub mod simd;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 
 pub mod prelude {
     pub use crate::input::detect::TenXFormat;
+    pub use crate::pipeline::runner::{Runner, RunnerConfig, StepStatus};
     pub use crate::pipeline::stage1_load::DatasetCtx;
+    pub use crate::pipeline::stage2_normalize::{CellExprSource, ExprContext};
 }
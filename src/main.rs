@@ -1,22 +1,133 @@
+use std::process::ExitCode;
+
 use clap::Parser;
-use kira_secretion::cli::Cli;
+use kira_secretion::cli::{Cli, ExitCategory, error_hint};
+use kira_secretion::pipeline::cancel::CancellationToken;
 use kira_secretion::simd;
 use tracing_subscriber::EnvFilter;
 use tracing_subscriber::fmt::time::UtcTime;
 
-fn main() -> anyhow::Result<()> {
-    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
-    tracing_subscriber::fmt()
-        .with_env_filter(filter)
-        .with_timer(UtcTime::rfc_3339())
-        .with_target(false)
-        .init();
+fn main() -> anyhow::Result<ExitCode> {
+    #[cfg(feature = "otel")]
+    let provider = otel::provider_from_env()?;
+    #[cfg(feature = "otel")]
+    let installed = provider.as_ref().map(otel::init_with_exporter).is_some();
+    #[cfg(not(feature = "otel"))]
+    let installed = false;
+
+    if !installed {
+        let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+        tracing_subscriber::fmt()
+            .with_env_filter(filter)
+            .with_timer(UtcTime::rfc_3339())
+            .with_target(false)
+            .init();
+    }
 
     tracing::info!(
         simd_backend = simd::backend_name(),
         "simd backend selected at build time"
     );
 
+    let token = install_interrupt_handler();
+
     let cli = Cli::parse();
-    cli.dispatch()
+    let result = cli.dispatch_with(&token);
+
+    #[cfg(feature = "otel")]
+    if let Some(provider) = provider {
+        let _ = provider.shutdown();
+    }
+
+    match result {
+        Ok(()) => Ok(ExitCode::SUCCESS),
+        Err(err) => {
+            let category = ExitCategory::classify(&err);
+            tracing::error!(
+                exit_code = category.code(),
+                category = category.as_str(),
+                error = %format!("{err:#}"),
+                "run failed"
+            );
+            eprintln!("Error: {err:?}");
+            if let Some(hint) = error_hint(&err) {
+                eprintln!("\nhint: {hint}");
+            }
+            Ok(ExitCode::from(category.code()))
+        }
+    }
+}
+
+/// First Ctrl-C requests a clean unwind at the next stage checkpoint; a second
+/// one exits immediately. Returns the token the handler cancels.
+fn install_interrupt_handler() -> CancellationToken {
+    let token = CancellationToken::new();
+    let handler_token = token.clone();
+    let result = ctrlc::set_handler(move || {
+        if handler_token.is_cancelled() {
+            std::process::exit(i32::from(ExitCategory::Cancelled.code()));
+        }
+        eprintln!(
+            "interrupt received, stopping at the next checkpoint (press Ctrl-C again to force)"
+        );
+        handler_token.cancel();
+    });
+    if let Err(err) = result {
+        tracing::warn!(error = %err, "failed to install interrupt handler");
+    }
+    token
+}
+
+#[cfg(feature = "otel")]
+mod otel {
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry_sdk::Resource;
+    use opentelemetry_sdk::trace::SdkTracerProvider;
+    use tracing_subscriber::EnvFilter;
+    use tracing_subscriber::fmt::time::UtcTime;
+    use tracing_subscriber::layer::SubscriberExt;
+    use tracing_subscriber::util::SubscriberInitExt;
+
+    const ENDPOINT_VARS: [&str; 2] = [
+        "OTEL_EXPORTER_OTLP_TRACES_ENDPOINT",
+        "OTEL_EXPORTER_OTLP_ENDPOINT",
+    ];
+
+    /// Builds an OTLP tracer provider when an endpoint is configured via the
+    /// standard OTEL_* environment variables; returns `None` otherwise.
+    pub fn provider_from_env() -> anyhow::Result<Option<SdkTracerProvider>> {
+        let configured = ENDPOINT_VARS
+            .iter()
+            .any(|var| std::env::var(var).is_ok_and(|v| !v.trim().is_empty()));
+        if !configured {
+            return Ok(None);
+        }
+
+        let exporter = opentelemetry_otlp::SpanExporter::builder()
+            .with_http()
+            .build()?;
+        let provider = SdkTracerProvider::builder()
+            .with_batch_exporter(exporter)
+            .with_resource(
+                Resource::builder()
+                    .with_service_name(env!("CARGO_PKG_NAME"))
+                    .build(),
+            )
+            .build();
+        Ok(Some(provider))
+    }
+
+    pub fn init_with_exporter(provider: &SdkTracerProvider) {
+        let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+        let tracer = provider.tracer(env!("CARGO_PKG_NAME"));
+        tracing_subscriber::registry()
+            .with(filter)
+            .with(
+                tracing_subscriber::fmt::layer()
+                    .with_timer(UtcTime::rfc_3339())
+                    .with_target(false),
+            )
+            .with(tracing_opentelemetry::layer().with_tracer(tracer))
+            .init();
+    }
 }
@@ -1,7 +1,31 @@
-#[derive(Debug, Clone, Copy)]
+use crate::model::axis_curves::AxisCurves;
+use crate::model::drivers::ZeroDrivers;
+
+#[derive(Debug, Clone)]
 pub struct AxisConfig {
     pub k: f32,
     pub epsilon: f32,
+    pub coverage_mode: CoverageMode,
+    /// Mark core axes without a mapped panel as not present instead of failing.
+    pub allow_missing_axes: bool,
+    pub zero_drivers: ZeroDrivers,
+    /// Leave stage3 dead panels out of axis sums, coverage and drivers.
+    pub drop_dead_panels: bool,
+    /// Append the pre-map panel sums per axis to `axes.tsv` (`--axes-raw`).
+    pub raw_sums: bool,
+    /// `--axis-curves`: calibration curves replacing [`saturating_map`] for
+    /// the axes they name.
+    pub curves: Option<AxisCurves>,
+}
+
+impl AxisConfig {
+    /// Maps an axis panel sum to [0, 1] with its curve, else [`saturating_map`].
+    pub fn map_axis(&self, axis: &str, raw: f32) -> f32 {
+        match self.curves.as_ref().and_then(|c| c.get(axis)) {
+            Some(curve) => curve.apply(raw),
+            None => saturating_map(raw, self.k),
+        }
+    }
 }
 
 impl Default for AxisConfig {
@@ -9,6 +33,33 @@ impl Default for AxisConfig {
         Self {
             k: 1.0,
             epsilon: 1e-8,
+            coverage_mode: CoverageMode::Required,
+            allow_missing_axes: false,
+            zero_drivers: ZeroDrivers::Drop,
+            drop_dead_panels: false,
+            raw_sums: false,
+            curves: None,
+        }
+    }
+}
+
+/// How per-axis coverage is derived from panel hits.
+///
+/// `Required` counts missing required genes; `Detection` uses the fraction of
+/// mappable panel genes detected in the cell, so shallow cells score lower.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CoverageMode {
+    #[default]
+    Required,
+    Detection,
+}
+
+impl CoverageMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CoverageMode::Required => "required",
+            CoverageMode::Detection => "detection",
         }
     }
 }
@@ -17,7 +68,7 @@ pub fn saturating_map(x: f32, k: f32) -> f32 {
     if x <= 0.0 { 0.0 } else { x / (x + k) }
 }
 
-#[derive(Debug, Clone, serde::Serialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct AxisValues {
     pub sia: f32,
     pub eeb: f32,
@@ -28,7 +79,7 @@ pub struct AxisValues {
     pub gdi: f32,
 }
 
-#[derive(Debug, Clone, serde::Serialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct AxisCoverage {
     pub sia: f32,
     pub eeb: f32,
@@ -0,0 +1,135 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Axes whose panel sum goes through `saturating_map` and so may take a
+/// curve instead; EEB is an export/degrade ratio and keeps its formula.
+pub const CURVE_AXES: [&str; 6] = ["SIA", "SLI", "MEI", "ECMI", "APCI", "GDI"];
+
+#[derive(Debug, Error)]
+pub enum AxisCurvesError {
+    #[error("io error reading axis curves {path}: {source}")]
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+    #[error("invalid axis curves {path}: {message}")]
+    Format { path: String, message: String },
+}
+
+/// Monotone piecewise-linear map from a raw panel sum to [0, 1], given as
+/// `(raw, value)` breakpoints with strictly increasing `raw`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CalibrationCurve {
+    pub breakpoints: Vec<(f32, f32)>,
+}
+
+impl CalibrationCurve {
+    /// Needs two or more finite breakpoints, `raw` strictly increasing and
+    /// starting at or below 0 (panel sums are never negative), and `value`
+    /// non-decreasing within [0, 1].
+    pub fn validate(&self) -> Result<(), String> {
+        let points = &self.breakpoints;
+        if points.len() < 2 {
+            return Err("needs at least two breakpoints".to_string());
+        }
+        if let Some((raw, value)) = points
+            .iter()
+            .find(|(raw, value)| !raw.is_finite() || !value.is_finite())
+        {
+            return Err(format!("breakpoint ({raw}, {value}) is not finite"));
+        }
+        if points[0].0 > 0.0 {
+            return Err(format!(
+                "first breakpoint raw {} must be at or below 0 to cover every panel sum",
+                points[0].0
+            ));
+        }
+        if let Some((_, value)) = points.iter().find(|(_, v)| !(0.0..=1.0).contains(v)) {
+            return Err(format!("value {value} is outside [0, 1]"));
+        }
+        for pair in points.windows(2) {
+            let ((r0, v0), (r1, v1)) = (pair[0], pair[1]);
+            if r1 <= r0 {
+                return Err(format!("raw breakpoints must increase: {r0} then {r1}"));
+            }
+            if v1 < v0 {
+                return Err(format!(
+                    "values must not decrease: {v0} at {r0} then {v1} at {r1}"
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Interpolates between the surrounding breakpoints; raw values outside
+    /// the breakpoints clamp to the end values, NaN stays NaN.
+    pub fn apply(&self, raw: f32) -> f32 {
+        let points = &self.breakpoints;
+        let (Some(&(first_raw, first_value)), Some(&(last_raw, last_value))) =
+            (points.first(), points.last())
+        else {
+            return f32::NAN;
+        };
+        if raw.is_nan() {
+            return f32::NAN;
+        }
+        if raw <= first_raw {
+            return first_value;
+        }
+        if raw >= last_raw {
+            return last_value;
+        }
+        let upper = points.partition_point(|&(r, _)| r <= raw);
+        let (r0, v0) = points[upper - 1];
+        let (r1, v1) = points[upper];
+        v0 + (v1 - v0) * (raw - r0) / (r1 - r0)
+    }
+}
+
+/// `--axis-curves` file: a table per axis, e.g.
+/// `[SIA]\nbreakpoints = [[0.0, 0.0], [2.0, 0.5], [8.0, 1.0]]`. Axes left out
+/// keep `saturating_map`. Copied into `provenance.json` as `axis_curves`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct AxisCurves {
+    pub curves: BTreeMap<String, CalibrationCurve>,
+}
+
+impl AxisCurves {
+    pub fn load(path: &Path) -> Result<Self, AxisCurvesError> {
+        let display = path.display().to_string();
+        let text = std::fs::read_to_string(path).map_err(|source| AxisCurvesError::Io {
+            path: display.clone(),
+            source,
+        })?;
+        let invalid = |message: String| AxisCurvesError::Format {
+            path: display.clone(),
+            message,
+        };
+        let curves: Self = toml::from_str(&text).map_err(|e| invalid(e.to_string()))?;
+        for (axis, curve) in &curves.curves {
+            if !CURVE_AXES.contains(&axis.as_str()) {
+                return Err(invalid(format!(
+                    "unknown axis '{axis}' (expected one of {})",
+                    CURVE_AXES.join(", ")
+                )));
+            }
+            curve
+                .validate()
+                .map_err(|message| invalid(format!("{axis}: {message}")))?;
+        }
+        Ok(curves)
+    }
+
+    pub fn get(&self, axis: &str) -> Option<&CalibrationCurve> {
+        self.curves.get(axis)
+    }
+}
+
+#[cfg(test)]
+#[path = "../../tests/src_inline/model/axis_curves.rs"]
+mod tests;
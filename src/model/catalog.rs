@@ -0,0 +1,358 @@
+//! Machine-readable registry of the axes and composite scores, for
+//! `kira-secretion describe` and generated docs.
+//!
+//! Stage4 builds its per-axis panel index lists from [`PanelAxis`], so every
+//! panel axis tag in [`AXES`] is one the pipeline sums. Composite terms are
+//! read from [`WeightsDefault`], so the documented weights are the ones
+//! stage5 applies.
+
+use serde::Serialize;
+
+use crate::model::scores::WeightsDefault;
+
+/// Panel `axis` tag that stage4 sums into an axis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum PanelAxis {
+    #[serde(rename = "SIA")]
+    Sia,
+    #[serde(rename = "EEB_EXPORT")]
+    EebExport,
+    #[serde(rename = "EEB_DEGRADE")]
+    EebDegrade,
+    #[serde(rename = "SLI")]
+    Sli,
+    #[serde(rename = "MEI")]
+    Mei,
+    #[serde(rename = "ECMI")]
+    Ecmi,
+    #[serde(rename = "APCI")]
+    Apci,
+    #[serde(rename = "GDI")]
+    Gdi,
+}
+
+impl PanelAxis {
+    pub const ALL: [PanelAxis; 8] = [
+        PanelAxis::Sia,
+        PanelAxis::EebExport,
+        PanelAxis::EebDegrade,
+        PanelAxis::Sli,
+        PanelAxis::Mei,
+        PanelAxis::Ecmi,
+        PanelAxis::Apci,
+        PanelAxis::Gdi,
+    ];
+
+    pub const fn tag(self) -> &'static str {
+        match self {
+            PanelAxis::Sia => "SIA",
+            PanelAxis::EebExport => "EEB_EXPORT",
+            PanelAxis::EebDegrade => "EEB_DEGRADE",
+            PanelAxis::Sli => "SLI",
+            PanelAxis::Mei => "MEI",
+            PanelAxis::Ecmi => "ECMI",
+            PanelAxis::Apci => "APCI",
+            PanelAxis::Gdi => "GDI",
+        }
+    }
+
+    /// The tag a panel file uses; `None` for tags no axis sums (`NONE`,
+    /// custom axes).
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|axis| axis.tag() == tag)
+    }
+}
+
+/// Tags of [`PanelAxis::ALL`], in order: the panel `axis` values stage4 sums.
+pub const PANEL_AXIS_TAGS: [&str; PanelAxis::ALL.len()] = {
+    let mut tags = [""; PanelAxis::ALL.len()];
+    let mut i = 0;
+    while i < tags.len() {
+        tags[i] = PanelAxis::ALL[i].tag();
+        i += 1;
+    }
+    tags
+};
+
+/// Number of panel axis tags feeding a required axis.
+pub const N_REQUIRED_PANEL_AXES: usize = {
+    let mut n = 0;
+    let mut i = 0;
+    while i < AXES.len() {
+        if AXES[i].required {
+            n += AXES[i].panel_axes.len();
+        }
+        i += 1;
+    }
+    n
+};
+
+/// Panel axis tags of the required axes, in [`AXES`] order.
+pub const REQUIRED_PANEL_AXIS_TAGS: [&str; N_REQUIRED_PANEL_AXES] = {
+    let mut tags = [""; N_REQUIRED_PANEL_AXES];
+    let mut n = 0;
+    let mut i = 0;
+    while i < AXES.len() {
+        let mut j = 0;
+        while AXES[i].required && j < AXES[i].panel_axes.len() {
+            tags[n] = AXES[i].panel_axes[j].tag();
+            n += 1;
+            j += 1;
+        }
+        i += 1;
+    }
+    tags
+};
+
+/// One per-cell axis of `axes.tsv`.
+#[derive(Debug, Clone, Serialize)]
+pub struct AxisDef {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Panel axis tags whose panel sums feed the axis.
+    pub panel_axes: &'static [PanelAxis],
+    /// How the panel sums become the axis value.
+    pub scaling: &'static str,
+    /// A core axis fails the run without a mapped panel unless
+    /// `--allow-missing-axes`; an optional one is NaN when absent.
+    pub required: bool,
+}
+
+const SATURATING: &str =
+    "raw / (raw + k) of the panel sum (k = 1), or its --axis-curves curve; in [0, 1]";
+
+/// Axes in `axes.tsv` column order.
+pub const AXES: [AxisDef; 7] = [
+    AxisDef {
+        id: "SIA",
+        name: "Secretory infrastructure",
+        description: "ER-to-Golgi trafficking and vesicle fusion machinery; reported as er_golgi_pressure",
+        panel_axes: &[PanelAxis::Sia],
+        scaling: SATURATING,
+        required: true,
+    },
+    AxisDef {
+        id: "EEB",
+        name: "Export/degradation balance",
+        description: "Exosome export routing against endosome-lysosome degradation; positive when export dominates; reported as exocytosis_bias",
+        panel_axes: &[PanelAxis::EebExport, PanelAxis::EebDegrade],
+        scaling: "(export - degrade) / (epsilon + export + degrade) of the two panel sums; in [-1, 1]",
+        required: true,
+    },
+    AxisDef {
+        id: "SLI",
+        name: "Secretory lysosome",
+        description: "Secretory-lysosome and regulated vesicle traffic; reported as vesicle_traffic_intensity",
+        panel_axes: &[PanelAxis::Sli],
+        scaling: SATURATING,
+        required: true,
+    },
+    AxisDef {
+        id: "MEI",
+        name: "Metabolic",
+        description: "Metabolic and suppressive secretion programs",
+        panel_axes: &[PanelAxis::Mei],
+        scaling: SATURATING,
+        required: true,
+    },
+    AxisDef {
+        id: "ECMI",
+        name: "Extracellular matrix",
+        description: "Extracellular matrix production and remodeling",
+        panel_axes: &[PanelAxis::Ecmi],
+        scaling: SATURATING,
+        required: true,
+    },
+    AxisDef {
+        id: "APCI",
+        name: "Antigen presentation",
+        description: "Antigen processing and presentation; picks the IAI weight set",
+        panel_axes: &[PanelAxis::Apci],
+        scaling: SATURATING,
+        required: false,
+    },
+    AxisDef {
+        id: "GDI",
+        name: "Inflammatory and stress secretion",
+        description: "Inflammatory and stress-induced secretion; reported as stress_secretion_index",
+        panel_axes: &[PanelAxis::Gdi],
+        scaling: SATURATING,
+        required: true,
+    },
+];
+
+/// One weighted input of a composite.
+#[derive(Debug, Clone, Serialize)]
+pub struct WeightTerm {
+    /// `WeightsDefault` field holding the weight, e.g. `oii.sia`.
+    pub field: &'static str,
+    /// Axis id, or `pos(EEB)` for `(EEB + 1) / 2`.
+    pub input: &'static str,
+    pub weight: f32,
+}
+
+impl WeightTerm {
+    /// Component name in the `drivers_*` columns: the axis id, `EEB_POS` for
+    /// `pos(EEB)`.
+    pub fn driver_name(&self) -> &'static str {
+        match self.input {
+            "pos(EEB)" => "EEB_POS",
+            input => input,
+        }
+    }
+}
+
+/// Terms of a composite under one weight set.
+#[derive(Debug, Clone, Serialize)]
+pub struct WeightSet {
+    /// `default`, or the `iai_weightset` value that selects it.
+    pub name: &'static str,
+    pub terms: Vec<WeightTerm>,
+}
+
+impl WeightSet {
+    /// `clamp01(w1 * A + w2 * B + ...)` with the weights written out.
+    pub fn formula(&self) -> String {
+        let terms: Vec<String> = self
+            .terms
+            .iter()
+            .map(|t| format!("{} * {}", t.weight, t.input))
+            .collect();
+        format!("clamp01({})", terms.join(" + "))
+    }
+}
+
+/// One composite score of `composites.tsv`.
+#[derive(Debug, Clone, Serialize)]
+pub struct CompositeDef {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    pub weight_sets: Vec<WeightSet>,
+}
+
+/// Composites in `composites.tsv` column order, with `weights`' values.
+pub fn composites(weights: &WeightsDefault) -> Vec<CompositeDef> {
+    let term = |field, input, weight| WeightTerm {
+        field,
+        input,
+        weight,
+    };
+    let w = weights;
+    vec![
+        CompositeDef {
+            id: "OII",
+            name: "Overall secretory output",
+            description: "Weighted sum over every core axis; reported as secretory_load",
+            weight_sets: vec![WeightSet {
+                name: "default",
+                terms: vec![
+                    term("oii.sia", "SIA", w.oii.sia),
+                    term("oii.pos_eeb", "pos(EEB)", w.oii.pos_eeb),
+                    term("oii.sli", "SLI", w.oii.sli),
+                    term("oii.mei", "MEI", w.oii.mei),
+                    term("oii.ecmi", "ECMI", w.oii.ecmi),
+                    term("oii.gdi", "GDI", w.oii.gdi),
+                ],
+            }],
+        },
+        CompositeDef {
+            id: "IAI",
+            name: "Immune activation",
+            description: "Metabolic, inflammatory and presentation signal; the weight set depends on whether the dataset has APCI panels",
+            weight_sets: vec![
+                WeightSet {
+                    name: "with_apci",
+                    terms: vec![
+                        term("iai_with_apci.mei", "MEI", w.iai_with_apci.mei),
+                        term("iai_with_apci.gdi", "GDI", w.iai_with_apci.gdi),
+                        term("iai_with_apci.apci", "APCI", w.iai_with_apci.apci),
+                        term("iai_with_apci.sia", "SIA", w.iai_with_apci.sia),
+                        term("iai_with_apci.pos_eeb", "pos(EEB)", w.iai_with_apci.pos_eeb),
+                    ],
+                },
+                WeightSet {
+                    name: "no_apci",
+                    terms: vec![
+                        term("iai_no_apci.mei", "MEI", w.iai_no_apci.mei),
+                        term("iai_no_apci.gdi", "GDI", w.iai_no_apci.gdi),
+                        term("iai_no_apci.sia", "SIA", w.iai_no_apci.sia),
+                        term("iai_no_apci.pos_eeb", "pos(EEB)", w.iai_no_apci.pos_eeb),
+                    ],
+                },
+            ],
+        },
+        CompositeDef {
+            id: "ESI",
+            name: "Environment shaping",
+            description: "Matrix, metabolic and export signal acting on the surroundings; reported as paracrine_signal_potential",
+            weight_sets: vec![WeightSet {
+                name: "default",
+                terms: vec![
+                    term("esi.ecmi", "ECMI", w.esi.ecmi),
+                    term("esi.mei", "MEI", w.esi.mei),
+                    term("esi.pos_eeb", "pos(EEB)", w.esi.pos_eeb),
+                    term("esi.sli", "SLI", w.esi.sli),
+                ],
+            }],
+        },
+    ]
+}
+
+/// Everything `describe` prints.
+#[derive(Debug, Clone, Serialize)]
+pub struct Catalog {
+    pub axes: &'static [AxisDef],
+    pub composites: Vec<CompositeDef>,
+}
+
+impl Catalog {
+    /// The registry with the built-in weights.
+    pub fn current() -> Self {
+        Self {
+            axes: &AXES,
+            composites: composites(&WeightsDefault::default()),
+        }
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::from("# Axes\n\n");
+        out.push_str("| Axis | Name | Panel axes | Scaling | Required | Description |\n");
+        out.push_str("|---|---|---|---|---|---|\n");
+        for axis in self.axes {
+            let tags: Vec<&str> = axis.panel_axes.iter().map(|a| a.tag()).collect();
+            out.push_str(&format!(
+                "| {} | {} | {} | {} | {} | {} |\n",
+                axis.id,
+                axis.name,
+                tags.join(", "),
+                axis.scaling,
+                if axis.required { "yes" } else { "no" },
+                axis.description
+            ));
+        }
+        out.push_str("\n# Composites\n\n");
+        out.push_str("`pos(EEB)` is `(EEB + 1) / 2`.\n");
+        for composite in &self.composites {
+            out.push_str(&format!(
+                "\n## {} ({})\n\n{}\n\n",
+                composite.id, composite.name, composite.description
+            ));
+            for set in &composite.weight_sets {
+                let fields: Vec<&str> = set.terms.iter().map(|t| t.field).collect();
+                out.push_str(&format!(
+                    "- `{}`: `{}` (weights: {})\n",
+                    set.name,
+                    set.formula(),
+                    fields.join(", ")
+                ));
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+#[path = "../../tests/src_inline/model/catalog.rs"]
+mod tests;
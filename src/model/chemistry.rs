@@ -0,0 +1,118 @@
+use crate::model::stats::{NanPolicy, median};
+
+/// Below this median detected genes (and [`SNRNA_MAX_MEDIAN_LIBSIZE`])
+/// `auto` picks [`Chemistry::SnRna`].
+pub const SNRNA_MAX_MEDIAN_DETECTED: f32 = 800.0;
+/// Below this median libsize (and [`SNRNA_MAX_MEDIAN_DETECTED`]) `auto` picks
+/// [`Chemistry::SnRna`].
+pub const SNRNA_MAX_MEDIAN_LIBSIZE: f32 = 1500.0;
+/// Below this median libsize `auto` picks [`Chemistry::ThreePrimeV2`], at or
+/// above it [`Chemistry::ThreePrimeV3`].
+pub const V2_MAX_MEDIAN_LIBSIZE: f32 = 5000.0;
+
+/// Library chemistry; picks the `low_counts` / `few_detected` defaults.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Chemistry {
+    ThreePrimeV2,
+    ThreePrimeV3,
+    FivePrime,
+    SnRna,
+    Flex,
+}
+
+impl Chemistry {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Chemistry::ThreePrimeV2 => "3p_v2",
+            Chemistry::ThreePrimeV3 => "3p_v3",
+            Chemistry::FivePrime => "5p",
+            Chemistry::SnRna => "snRNA",
+            Chemistry::Flex => "flex",
+        }
+    }
+
+    /// Bundled `low_counts` (libsize) cut-off. 3' v3 keeps the original
+    /// whole-cell defaults.
+    pub fn low_counts(self) -> u64 {
+        match self {
+            Chemistry::ThreePrimeV2 => 400,
+            Chemistry::ThreePrimeV3 | Chemistry::FivePrime => 500,
+            Chemistry::SnRna => 200,
+            Chemistry::Flex => 250,
+        }
+    }
+
+    /// Bundled `few_detected` cut-off.
+    pub fn few_detected(self) -> u32 {
+        match self {
+            Chemistry::ThreePrimeV2 => 250,
+            Chemistry::ThreePrimeV3 | Chemistry::FivePrime => 300,
+            Chemistry::SnRna => 150,
+            Chemistry::Flex => 200,
+        }
+    }
+}
+
+/// Chemistry from per-cell medians. 5' and Flex libraries cannot be told
+/// apart from 3' ones by counts alone, so they are never inferred; with no
+/// cells (NaN medians) the result is 3' v3.
+pub fn infer_chemistry(median_detected: f32, median_libsize: f32) -> Chemistry {
+    if median_detected < SNRNA_MAX_MEDIAN_DETECTED && median_libsize < SNRNA_MAX_MEDIAN_LIBSIZE {
+        Chemistry::SnRna
+    } else if median_libsize < V2_MAX_MEDIAN_LIBSIZE {
+        Chemistry::ThreePrimeV2
+    } else {
+        Chemistry::ThreePrimeV3
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChemistrySource {
+    Explicit,
+    Inferred,
+}
+
+impl ChemistrySource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ChemistrySource::Explicit => "explicit",
+            ChemistrySource::Inferred => "inferred",
+        }
+    }
+}
+
+/// The chemistry a run used, how it was chosen and the medians `auto` looks
+/// at; written to `summary.json` with the resulting cut-offs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChemistryChoice {
+    pub chemistry: Chemistry,
+    pub source: ChemistrySource,
+    pub median_detected: f32,
+    pub median_libsize: f32,
+}
+
+impl ChemistryChoice {
+    /// `explicit` when given, otherwise [`infer_chemistry`] over the cells'
+    /// detected genes and libsizes.
+    pub fn resolve(explicit: Option<Chemistry>, detected: &[f32], libsize: &[f32]) -> Self {
+        let median_detected = median(detected, NanPolicy::Skip);
+        let median_libsize = median(libsize, NanPolicy::Skip);
+        let (chemistry, source) = match explicit {
+            Some(chemistry) => (chemistry, ChemistrySource::Explicit),
+            None => (
+                infer_chemistry(median_detected, median_libsize),
+                ChemistrySource::Inferred,
+            ),
+        };
+        Self {
+            chemistry,
+            source,
+            median_detected,
+            median_libsize,
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "../../tests/src_inline/model/chemistry.rs"]
+mod tests;
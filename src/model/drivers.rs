@@ -4,10 +4,35 @@ pub struct PanelDriver {
     pub score: f32,
 }
 
-pub fn top_k_panels(panel_ids: &[String], contributions: &[f32], k: usize) -> Vec<PanelDriver> {
+/// Whether driver lists keep entries that contributed nothing.
+///
+/// `Drop` (default) removes zero, negative and NaN contributions before the
+/// top-k cut, so a cell with one active panel reports one driver, and a list
+/// with nothing left is written as `.`. EEB degrade drivers are filtered on
+/// their raw (positive) sums before being negated for display.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ZeroDrivers {
+    #[default]
+    Drop,
+    Keep,
+}
+
+impl ZeroDrivers {
+    fn keeps(self, score: f32) -> bool {
+        self == ZeroDrivers::Keep || score > 0.0
+    }
+}
+
+pub fn top_k_panels(
+    panel_ids: &[String],
+    contributions: &[f32],
+    k: usize,
+    zero: ZeroDrivers,
+) -> Vec<PanelDriver> {
     let mut pairs: Vec<PanelDriver> = panel_ids
         .iter()
         .zip(contributions.iter())
+        .filter(|(_, v)| zero.keeps(**v))
         .map(|(id, v)| PanelDriver {
             panel_id: id.clone(),
             score: *v,
@@ -35,9 +60,10 @@ pub fn top_k_eeb_drivers(
     degrade_ids: &[String],
     degrade_vals: &[f32],
     k: usize,
+    zero: ZeroDrivers,
 ) -> (Vec<PanelDriver>, Vec<PanelDriver>) {
-    let export = top_k_panels(export_ids, export_vals, k);
-    let mut degrade = top_k_panels(degrade_ids, degrade_vals, k);
+    let export = top_k_panels(export_ids, export_vals, k, zero);
+    let mut degrade = top_k_panels(degrade_ids, degrade_vals, k, zero);
     for d in &mut degrade {
         d.score = -d.score.abs();
     }
@@ -61,15 +87,16 @@ pub fn format_eeb_drivers(export: &[PanelDriver], degrade: &[PanelDriver]) -> St
     format!("EXPORT:{};DEGRADE:{}", export_str, degrade_str)
 }
 
-pub fn top_k_components(names: &[&str], contribs: &[f32], k: usize) -> String {
-    if names.is_empty() || contribs.is_empty() {
-        return ".".to_string();
-    }
+pub fn top_k_components(names: &[&str], contribs: &[f32], k: usize, zero: ZeroDrivers) -> String {
     let mut pairs: Vec<(String, f32)> = names
         .iter()
         .zip(contribs.iter())
+        .filter(|(_, v)| zero.keeps(**v))
         .map(|(n, v)| ((*n).to_string(), *v))
         .collect();
+    if pairs.is_empty() {
+        return ".".to_string();
+    }
     pairs.sort_by(
         |a, b| match b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal) {
             std::cmp::Ordering::Equal => a.0.cmp(&b.0),
@@ -86,6 +113,26 @@ pub fn top_k_components(names: &[&str], contribs: &[f32], k: usize) -> String {
     out.join(",")
 }
 
+/// Concentration of a composite over its weighted contributions:
+/// `1 - H / ln(n)` with `H` the Shannon entropy of the positive contributions
+/// normalized to sum to 1. 1 means a single component carries the score, 0 an
+/// even spread over all `n`. NaN when no contribution is positive.
+pub fn contribution_concentration(contribs: &[f32]) -> f32 {
+    let total: f32 = contribs.iter().filter(|v| **v > 0.0).sum();
+    if total <= 0.0 || contribs.len() < 2 {
+        return f32::NAN;
+    }
+    let entropy: f32 = contribs
+        .iter()
+        .filter(|v| **v > 0.0)
+        .map(|v| {
+            let p = v / total;
+            -p * p.ln()
+        })
+        .sum();
+    (1.0 - entropy / (contribs.len() as f32).ln()).clamp(0.0, 1.0)
+}
+
 #[cfg(test)]
 #[path = "../../tests/src_inline/model/drivers.rs"]
 mod tests;
@@ -10,6 +10,9 @@ impl Flags {
     pub const FEW_DETECTED_GENES: u8 = 0b0010;
     pub const LOW_COUNTS: u8 = 0b0100;
     pub const HIGH_AMBIENT_RISK: u8 = 0b1000;
+    /// A single count normalizes to more than `extreme_count_scale`, so one
+    /// stray count can dominate a panel.
+    pub const EXTREME_NORMALIZATION: u8 = 0b1_0000;
 
     pub fn empty() -> Self {
         Self { bits: 0 }
@@ -40,6 +43,9 @@ impl Flags {
         if self.contains(Self::HIGH_AMBIENT_RISK) {
             parts.push("HIGH_AMBIENT_RISK");
         }
+        if self.contains(Self::EXTREME_NORMALIZATION) {
+            parts.push("EXTREME_NORMALIZATION");
+        }
         parts.join(",")
     }
 }
@@ -1,6 +1,14 @@
 pub mod axes;
+pub mod axis_curves;
+pub mod catalog;
+pub mod chemistry;
 pub mod drivers;
 pub mod flags;
+pub mod pipeline_regime;
+pub mod qc_expectations;
+pub mod reference;
 pub mod regimes;
+pub mod run_label;
 pub mod scores;
+pub mod stats;
 pub mod thresholds;
@@ -0,0 +1,286 @@
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+
+use serde::Deserialize;
+use thiserror::Error;
+
+/// Regime written to the pipeline contract (`secretion.tsv`, `summary.json`,
+/// `pipeline_step.json`), derived from the stage6 [`Regime`](super::regimes::Regime).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PipelineRegime {
+    HomeostaticSecretion,
+    AdaptiveSecretion,
+    InflammatorySecretion,
+    HypersecretoryState,
+    SecretoryCollapse,
+    /// Contract version 2 only; folded into another regime under version 1.
+    LysosomalSecretion,
+    Unclassified,
+}
+
+impl PipelineRegime {
+    /// Every regime of the newest contract version, in default display order;
+    /// ties in majority votes resolve to the earlier one.
+    pub const ALL: [PipelineRegime; 7] = [
+        PipelineRegime::HomeostaticSecretion,
+        PipelineRegime::AdaptiveSecretion,
+        PipelineRegime::InflammatorySecretion,
+        PipelineRegime::HypersecretoryState,
+        PipelineRegime::SecretoryCollapse,
+        PipelineRegime::LysosomalSecretion,
+        PipelineRegime::Unclassified,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PipelineRegime::HomeostaticSecretion => "HomeostaticSecretion",
+            PipelineRegime::AdaptiveSecretion => "AdaptiveSecretion",
+            PipelineRegime::InflammatorySecretion => "InflammatorySecretion",
+            PipelineRegime::HypersecretoryState => "HypersecretoryState",
+            PipelineRegime::SecretoryCollapse => "SecretoryCollapse",
+            PipelineRegime::LysosomalSecretion => "LysosomalSecretion",
+            PipelineRegime::Unclassified => "Unclassified",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|r| r.as_str() == name)
+    }
+
+    fn index(self) -> usize {
+        Self::ALL.iter().position(|r| *r == self).unwrap_or(0)
+    }
+}
+
+/// `--pipeline-contract-version`: which regimes the run may emit. Version 1
+/// is the original six-regime list; version 2 adds
+/// [`PipelineRegime::LysosomalSecretion`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PipelineContract {
+    #[default]
+    V1,
+    V2,
+}
+
+impl PipelineContract {
+    pub const LATEST: PipelineContract = PipelineContract::V2;
+
+    const V1_REGIMES: [PipelineRegime; 6] = [
+        PipelineRegime::HomeostaticSecretion,
+        PipelineRegime::AdaptiveSecretion,
+        PipelineRegime::InflammatorySecretion,
+        PipelineRegime::HypersecretoryState,
+        PipelineRegime::SecretoryCollapse,
+        PipelineRegime::Unclassified,
+    ];
+
+    pub fn from_version(version: u32) -> Option<Self> {
+        match version {
+            1 => Some(PipelineContract::V1),
+            2 => Some(PipelineContract::V2),
+            _ => None,
+        }
+    }
+
+    pub fn version(self) -> u32 {
+        match self {
+            PipelineContract::V1 => 1,
+            PipelineContract::V2 => 2,
+        }
+    }
+
+    /// Regimes of this version, in default display order.
+    pub fn regimes(self) -> &'static [PipelineRegime] {
+        match self {
+            PipelineContract::V1 => &Self::V1_REGIMES,
+            PipelineContract::V2 => &PipelineRegime::ALL,
+        }
+    }
+
+    pub fn carries(self, regime: PipelineRegime) -> bool {
+        self.regimes().contains(&regime)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum RegimeLabelsError {
+    #[error("io error reading regime labels {path}: {source}")]
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+    #[error("invalid regime labels {path}: {message}")]
+    Format { path: String, message: String },
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RegimeLabelsFile {
+    labels: BTreeMap<String, String>,
+    order: Vec<String>,
+    fold: BTreeMap<String, String>,
+}
+
+/// Display labels, order and version-1 folding for [`PipelineRegime`]s
+/// (`--regime-labels`).
+///
+/// The JSON file maps internal names to labels and may list an `order`:
+/// `{"labels": {"SecretoryCollapse": "Quiescent/Collapsed"},
+/// "order": ["SecretoryCollapse"]}`. Unmapped regimes keep their internal
+/// name; regimes missing from `order` follow in default order. `fold` maps a
+/// regime newer than contract version 1 to the version-1 regime it is
+/// reported as under that version, e.g. `{"fold": {"LysosomalSecretion":
+/// "InflammatorySecretion"}}`; by default [`PipelineRegime::LysosomalSecretion`]
+/// folds into [`PipelineRegime::AdaptiveSecretion`], where version 1 always
+/// put those cells.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegimeLabels {
+    labels: [String; 7],
+    order: Vec<PipelineRegime>,
+    fold: [PipelineRegime; 7],
+    contract: PipelineContract,
+}
+
+impl Default for RegimeLabels {
+    fn default() -> Self {
+        Self {
+            labels: PipelineRegime::ALL.map(|r| r.as_str().to_string()),
+            order: PipelineRegime::ALL.to_vec(),
+            fold: PipelineRegime::ALL.map(|r| match r {
+                PipelineRegime::LysosomalSecretion => PipelineRegime::AdaptiveSecretion,
+                other => other,
+            }),
+            contract: PipelineContract::default(),
+        }
+    }
+}
+
+impl RegimeLabels {
+    pub fn load(path: &Path) -> Result<Self, RegimeLabelsError> {
+        let display = path.display().to_string();
+        let bytes = std::fs::read(path).map_err(|source| RegimeLabelsError::Io {
+            path: display.clone(),
+            source,
+        })?;
+        let format = |message: String| RegimeLabelsError::Format {
+            path: display.clone(),
+            message,
+        };
+        let file: RegimeLabelsFile =
+            serde_json::from_slice(&bytes).map_err(|e| format(e.to_string()))?;
+        Self::from_parts(&file.labels, &file.order, &file.fold).map_err(format)
+    }
+
+    /// Validates that every name is a known regime, every label is a
+    /// non-empty, TSV-safe string used by only one regime, and every fold
+    /// maps a version-2 regime onto a version-1 one.
+    pub fn from_parts(
+        labels: &BTreeMap<String, String>,
+        order: &[String],
+        fold: &BTreeMap<String, String>,
+    ) -> Result<Self, String> {
+        let parse = |name: &str| {
+            PipelineRegime::parse(name).ok_or_else(|| {
+                let known: Vec<&str> = PipelineRegime::ALL.iter().map(|r| r.as_str()).collect();
+                format!(
+                    "unknown regime '{name}' (expected one of {})",
+                    known.join(", ")
+                )
+            })
+        };
+        let mut out = Self::default();
+        for (name, label) in labels {
+            let regime = parse(name)?;
+            if label.trim().is_empty() || label.contains(['\t', '\n', '\r']) {
+                return Err(format!(
+                    "label for {name} must be non-empty without tabs or newlines"
+                ));
+            }
+            out.labels[regime.index()] = label.clone();
+        }
+        let mut seen = HashSet::new();
+        for regime in PipelineRegime::ALL {
+            let label = out.label(regime);
+            if !seen.insert(label) {
+                return Err(format!("label '{label}' is used for more than one regime"));
+            }
+        }
+
+        let mut ordered = Vec::with_capacity(PipelineRegime::ALL.len());
+        for name in order {
+            let regime = parse(name)?;
+            if ordered.contains(&regime) {
+                return Err(format!("regime '{name}' is listed twice in order"));
+            }
+            ordered.push(regime);
+        }
+        for regime in PipelineRegime::ALL {
+            if !ordered.contains(&regime) {
+                ordered.push(regime);
+            }
+        }
+        out.order = ordered;
+
+        let v1 = PipelineContract::V1;
+        for (name, target) in fold {
+            let regime = parse(name)?;
+            let target = parse(target)?;
+            if v1.carries(regime) {
+                return Err(format!(
+                    "cannot fold '{name}': it is already a version 1 regime"
+                ));
+            }
+            if !v1.carries(target) {
+                return Err(format!(
+                    "'{name}' must fold into a version 1 regime, not '{}'",
+                    target.as_str()
+                ));
+            }
+            out.fold[regime.index()] = target;
+        }
+        Ok(out)
+    }
+
+    /// Limits the listed regimes to those `contract` carries; cells are
+    /// mapped onto them by [`RegimeLabels::resolve`].
+    pub fn with_contract(mut self, contract: PipelineContract) -> Self {
+        self.contract = contract;
+        self
+    }
+
+    pub fn contract(&self) -> PipelineContract {
+        self.contract
+    }
+
+    /// The regime a cell is reported as under the configured contract:
+    /// `regime` itself, or its fold target when the contract predates it.
+    pub fn resolve(&self, regime: PipelineRegime) -> PipelineRegime {
+        if self.contract.carries(regime) {
+            regime
+        } else {
+            self.fold[regime.index()]
+        }
+    }
+
+    pub fn label(&self, regime: PipelineRegime) -> &str {
+        &self.labels[regime.index()]
+    }
+
+    /// Regimes of the configured contract, in display order.
+    pub fn ordered(&self) -> Vec<PipelineRegime> {
+        self.order
+            .iter()
+            .copied()
+            .filter(|r| self.contract.carries(*r))
+            .collect()
+    }
+
+    /// Labels in display order, as listed in `pipeline_step.json`.
+    pub fn ordered_labels(&self) -> Vec<&str> {
+        self.ordered().into_iter().map(|r| self.label(r)).collect()
+    }
+}
+
+#[cfg(test)]
+#[path = "../../tests/src_inline/model/pipeline_regime.rs"]
+mod tests;
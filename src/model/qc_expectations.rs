@@ -0,0 +1,236 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::model::pipeline_regime::{PipelineRegime, RegimeLabels};
+use crate::pipeline::stage7_report::FinalSummary;
+
+#[derive(Debug, Error)]
+pub enum QcExpectationsError {
+    #[error("io error reading qc expectations {path}: {source}")]
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+    #[error("invalid qc expectations {path}: {message}")]
+    Format { path: String, message: String },
+}
+
+/// Returned by `run --qc-gate-strict` after all artifacts are written.
+#[derive(Debug, Error)]
+#[error("qc gate failed: {failed} of {total} rules out of range ({rules}); see qc_gate.json")]
+pub struct QcGateFailed {
+    pub failed: usize,
+    pub total: usize,
+    pub rules: String,
+}
+
+/// QC metrics of the final summary that expectations may reference.
+pub const QC_METRICS: [&str; 4] = [
+    "low_confidence_fraction",
+    "low_secretory_signal_fraction",
+    "high_proliferation_fraction",
+    "low_axis_coverage_fraction",
+];
+
+/// Acceptable fraction range; a missing bound is open.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FractionRange {
+    pub min: Option<f32>,
+    pub max: Option<f32>,
+}
+
+impl FractionRange {
+    fn contains(&self, value: f32) -> bool {
+        value.is_finite()
+            && self.min.is_none_or(|min| value >= min)
+            && self.max.is_none_or(|max| value <= max)
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct QcExpectationsFile {
+    regimes: BTreeMap<String, FractionRange>,
+    qc: BTreeMap<String, FractionRange>,
+}
+
+/// Expected ranges for regime fractions and QC metrics (`--qc-expectations`):
+///
+/// ```toml
+/// [regimes]
+/// HomeostaticSecretion = { min = 0.40, max = 0.70 }
+/// SecretoryCollapse = { max = 0.15 }
+///
+/// [qc]
+/// low_confidence_fraction = { max = 0.30 }
+/// ```
+///
+/// Regimes use their internal names, also under `--regime-labels`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QcExpectations {
+    pub regimes: Vec<(PipelineRegime, FractionRange)>,
+    pub qc: Vec<(String, FractionRange)>,
+}
+
+impl QcExpectations {
+    pub fn load(path: &Path) -> Result<Self, QcExpectationsError> {
+        let display = path.display().to_string();
+        let text = std::fs::read_to_string(path).map_err(|source| QcExpectationsError::Io {
+            path: display.clone(),
+            source,
+        })?;
+        Self::parse(&text).map_err(|message| QcExpectationsError::Format {
+            path: display,
+            message,
+        })
+    }
+
+    /// Parses TOML, checking names and that every range is a non-empty
+    /// subset of [0, 1].
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let file: QcExpectationsFile = toml::from_str(text).map_err(|e| e.to_string())?;
+        let mut out = Self::default();
+        for (name, range) in file.regimes {
+            let regime = PipelineRegime::parse(&name).ok_or_else(|| {
+                let known: Vec<&str> = PipelineRegime::ALL.iter().map(|r| r.as_str()).collect();
+                format!(
+                    "unknown regime '{name}' (expected one of {})",
+                    known.join(", ")
+                )
+            })?;
+            check_range(&name, &range)?;
+            out.regimes.push((regime, range));
+        }
+        for (name, range) in file.qc {
+            if !QC_METRICS.contains(&name.as_str()) {
+                return Err(format!(
+                    "unknown qc metric '{name}' (expected one of {})",
+                    QC_METRICS.join(", ")
+                ));
+            }
+            check_range(&name, &range)?;
+            out.qc.push((name, range));
+        }
+        Ok(out)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.regimes.is_empty() && self.qc.is_empty()
+    }
+
+    /// Checks every rule against `summary`, regimes first (in
+    /// [`PipelineRegime::ALL`] order), then QC metrics.
+    pub fn evaluate(&self, summary: &FinalSummary, labels: &RegimeLabels) -> QcGate {
+        let mut ordered: Vec<&(PipelineRegime, FractionRange)> = self.regimes.iter().collect();
+        ordered.sort_by_key(|(r, _)| PipelineRegime::ALL.iter().position(|a| a == r));
+        let mut rules = Vec::with_capacity(self.regimes.len() + self.qc.len());
+        for (regime, range) in ordered {
+            let label = labels.label(*regime);
+            let value = summary.regimes.fractions.get(label).copied().unwrap_or(0.0);
+            rules.push(QcRule::new("regime", regime.as_str(), label, value, range));
+        }
+        for (metric, range) in &self.qc {
+            let value = match metric.as_str() {
+                "low_confidence_fraction" => summary.qc.low_confidence_fraction,
+                "low_secretory_signal_fraction" => summary.qc.low_secretory_signal_fraction,
+                "high_proliferation_fraction" => summary.qc.high_proliferation_fraction,
+                _ => summary.caveats.low_axis_coverage_fraction,
+            };
+            rules.push(QcRule::new("qc", metric, metric, value, range));
+        }
+        let failed = rules.iter().filter(|r| !r.pass).count();
+        QcGate {
+            verdict: if failed == 0 { "pass" } else { "fail" }.to_string(),
+            n_rules: rules.len(),
+            n_failed: failed,
+            rules,
+        }
+    }
+}
+
+fn check_range(name: &str, range: &FractionRange) -> Result<(), String> {
+    if range.min.is_none() && range.max.is_none() {
+        return Err(format!("{name}: set min, max or both"));
+    }
+    for bound in [range.min, range.max].into_iter().flatten() {
+        if !(0.0..=1.0).contains(&bound) {
+            return Err(format!("{name}: bound {bound} is outside [0, 1]"));
+        }
+    }
+    if let (Some(min), Some(max)) = (range.min, range.max)
+        && min > max
+    {
+        return Err(format!("{name}: min {min} is greater than max {max}"));
+    }
+    Ok(())
+}
+
+/// `qc_gate.json`: one entry per rule and the overall verdict.
+#[derive(Debug, Clone, Serialize)]
+pub struct QcGate {
+    /// `pass` when every rule holds, otherwise `fail`.
+    pub verdict: String,
+    pub n_rules: usize,
+    pub n_failed: usize,
+    pub rules: Vec<QcRule>,
+}
+
+impl QcGate {
+    pub fn passed(&self) -> bool {
+        self.n_failed == 0
+    }
+
+    pub fn failed_rules(&self) -> impl Iterator<Item = &QcRule> {
+        self.rules.iter().filter(|r| !r.pass)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QcRule {
+    /// `regime` or `qc`.
+    pub kind: String,
+    pub metric: String,
+    /// Name the value was looked up under (the regime's display label).
+    pub label: String,
+    #[serde(serialize_with = "crate::report::json::fixed6")]
+    pub value: f32,
+    #[serde(serialize_with = "crate::report::json::fixed6_opt")]
+    pub min: Option<f32>,
+    #[serde(serialize_with = "crate::report::json::fixed6_opt")]
+    pub max: Option<f32>,
+    pub pass: bool,
+}
+
+impl QcRule {
+    fn new(kind: &str, metric: &str, label: &str, value: f32, range: &FractionRange) -> Self {
+        Self {
+            kind: kind.to_string(),
+            metric: metric.to_string(),
+            label: label.to_string(),
+            value,
+            min: range.min,
+            max: range.max,
+            pass: range.contains(value),
+        }
+    }
+
+    /// `metric=value (min..max)` for logs and reports.
+    pub fn describe(&self) -> String {
+        let bound = |b: Option<f32>| b.map_or_else(String::new, |v| format!("{v}"));
+        format!(
+            "{}={:.4} ({}..{})",
+            self.label,
+            self.value,
+            bound(self.min),
+            bound(self.max)
+        )
+    }
+}
+
+#[cfg(test)]
+#[path = "../../tests/src_inline/model/qc_expectations.rs"]
+mod tests;
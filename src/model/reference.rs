@@ -0,0 +1,263 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::model::axes::AxisValues;
+use crate::model::stats::{NanPolicy, percentiles_interpolated};
+use crate::report::artifact::write_artifact;
+
+/// Bumped whenever the reference file layout changes.
+pub const REFERENCE_FORMAT_VERSION: u32 = 1;
+
+/// Quantile grid resolution: one point per integer percentile, 0..=100.
+pub const REFERENCE_GRID_POINTS: usize = 101;
+
+pub const REFERENCE_AXES: [&str; 7] = ["SIA", "EEB", "SLI", "MEI", "ECMI", "APCI", "GDI"];
+pub const REFERENCE_COMPOSITES: [&str; 3] = ["OII", "IAI", "ESI"];
+
+#[derive(Debug, Error)]
+pub enum ReferenceError {
+    #[error("io error reading reference {path}: {source}")]
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+    #[error("invalid reference {path}: {message}")]
+    Format { path: String, message: String },
+    #[error(
+        "reference {path} has format version {found}, this build reads version {REFERENCE_FORMAT_VERSION}"
+    )]
+    Version { path: String, found: u32 },
+    #[error(
+        "reference {path} was built from panel set {reference}, current panel set is {current}"
+    )]
+    PanelSetMismatch {
+        path: String,
+        reference: String,
+        current: String,
+    },
+    #[error(
+        "reference {path} was built from gene set {reference}, current gene set is {current}; the runs used different feature annotations (--allow-gene-set-mismatch to proceed)"
+    )]
+    GeneSetMismatch {
+        path: String,
+        reference: String,
+        current: String,
+    },
+}
+
+/// Frozen per-axis and per-composite quantile grids from a reference cohort.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CohortReference {
+    pub format_version: u32,
+    pub tool_version: String,
+    pub panel_set_hash: String,
+    /// Gene list digest of the reference run; empty in references written
+    /// before it was recorded.
+    #[serde(default)]
+    pub gene_set_hash: String,
+    pub n_cells: usize,
+    /// `REFERENCE_GRID_POINTS` values per metric; empty when the metric had no
+    /// finite values (e.g. an absent axis).
+    pub axes: BTreeMap<String, Vec<f32>>,
+    pub composites: BTreeMap<String, Vec<f32>>,
+}
+
+impl CohortReference {
+    pub fn build(
+        values: &[AxisValues],
+        oii: &[f32],
+        iai: &[f32],
+        esi: &[f32],
+        panel_set_hash: &str,
+        gene_set_hash: &str,
+    ) -> Self {
+        let axes = REFERENCE_AXES
+            .iter()
+            .map(|name| {
+                let column: Vec<f32> = values.iter().map(|v| axis_value(v, name)).collect();
+                (name.to_string(), quantile_grid(&column))
+            })
+            .collect();
+        let composites = [("OII", oii), ("IAI", iai), ("ESI", esi)]
+            .into_iter()
+            .map(|(name, column)| (name.to_string(), quantile_grid(column)))
+            .collect();
+        Self {
+            format_version: REFERENCE_FORMAT_VERSION,
+            tool_version: env!("CARGO_PKG_VERSION").to_string(),
+            panel_set_hash: panel_set_hash.to_string(),
+            gene_set_hash: gene_set_hash.to_string(),
+            n_cells: values.len(),
+            axes,
+            composites,
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self, ReferenceError> {
+        let display = path.display().to_string();
+        let bytes = std::fs::read(path).map_err(|source| ReferenceError::Io {
+            path: display.clone(),
+            source,
+        })?;
+        let probe: serde_json::Value =
+            serde_json::from_slice(&bytes).map_err(|e| ReferenceError::Format {
+                path: display.clone(),
+                message: e.to_string(),
+            })?;
+        let found = probe
+            .get("format_version")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0) as u32;
+        if found != REFERENCE_FORMAT_VERSION {
+            return Err(ReferenceError::Version {
+                path: display,
+                found,
+            });
+        }
+        let reference: Self =
+            serde_json::from_value(probe).map_err(|e| ReferenceError::Format {
+                path: display.clone(),
+                message: e.to_string(),
+            })?;
+        let bad_grid = reference
+            .axes
+            .iter()
+            .chain(&reference.composites)
+            .find(|(_, grid)| !grid.is_empty() && grid.len() != REFERENCE_GRID_POINTS);
+        if let Some((name, grid)) = bad_grid {
+            return Err(ReferenceError::Format {
+                path: display,
+                message: format!(
+                    "grid '{}' has {} points, expected {}",
+                    name,
+                    grid.len(),
+                    REFERENCE_GRID_POINTS
+                ),
+            });
+        }
+        Ok(reference)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), std::io::Error> {
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let name = path.file_name().ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("{} is not a file path", path.display()),
+            )
+        })?;
+        write_artifact(
+            dir,
+            &name.to_string_lossy(),
+            serde_json::to_string_pretty(self)?,
+        )
+    }
+
+    /// Fails unless the reference was built from the same panel definitions.
+    pub fn check_panel_set(&self, path: &Path, current: &str) -> Result<(), ReferenceError> {
+        if self.panel_set_hash != current {
+            return Err(ReferenceError::PanelSetMismatch {
+                path: path.display().to_string(),
+                reference: self.panel_set_hash.clone(),
+                current: current.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Fails unless the reference was built from the same gene list; with
+    /// `allow` the mismatch is only returned for the caller to warn about.
+    /// References without a recorded digest are not checked.
+    pub fn check_gene_set(
+        &self,
+        path: &Path,
+        current: &str,
+        allow: bool,
+    ) -> Result<Option<ReferenceError>, ReferenceError> {
+        if self.gene_set_hash.is_empty() || self.gene_set_hash == current {
+            return Ok(None);
+        }
+        let mismatch = ReferenceError::GeneSetMismatch {
+            path: path.display().to_string(),
+            reference: self.gene_set_hash.clone(),
+            current: current.to_string(),
+        };
+        if allow {
+            Ok(Some(mismatch))
+        } else {
+            Err(mismatch)
+        }
+    }
+
+    pub fn axis_grid(&self, name: &str) -> &[f32] {
+        self.axes.get(name).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    pub fn composite_grid(&self, name: &str) -> &[f32] {
+        self.composites.get(name).map(Vec::as_slice).unwrap_or(&[])
+    }
+}
+
+pub fn axis_value(v: &AxisValues, name: &str) -> f32 {
+    match name {
+        "SIA" => v.sia,
+        "EEB" => v.eeb,
+        "SLI" => v.sli,
+        "MEI" => v.mei,
+        "ECMI" => v.ecmi,
+        "APCI" => v.apci,
+        "GDI" => v.gdi,
+        _ => f32::NAN,
+    }
+}
+
+/// Percentile grid over the finite values; empty if there are none.
+pub fn quantile_grid(values: &[f32]) -> Vec<f32> {
+    let vals: Vec<f32> = values.iter().copied().filter(|v| v.is_finite()).collect();
+    if vals.is_empty() {
+        return Vec::new();
+    }
+    let ps: Vec<f64> = (0..REFERENCE_GRID_POINTS)
+        .map(|k| k as f64 / (REFERENCE_GRID_POINTS - 1) as f64)
+        .collect();
+    percentiles_interpolated(&vals, &ps, NanPolicy::Skip)
+}
+
+/// Reference percentile (0–100) of `value`, linearly interpolated between grid
+/// points; values tied with a run of grid points get the run's midpoint. NaN
+/// for non-finite values or an empty grid.
+pub fn reference_percentile(grid: &[f32], value: f32) -> f32 {
+    if grid.is_empty() || !value.is_finite() {
+        return f32::NAN;
+    }
+    let step = 100.0 / (grid.len() - 1).max(1) as f32;
+    let lo = grid.partition_point(|g| *g < value);
+    let hi = grid.partition_point(|g| *g <= value);
+    if lo < hi {
+        return (lo + hi - 1) as f32 * 0.5 * step;
+    }
+    if lo == 0 {
+        return 0.0;
+    }
+    if lo == grid.len() {
+        return 100.0;
+    }
+    let (a, b) = (grid[lo - 1], grid[lo]);
+    ((lo - 1) as f32 + (value - a) / (b - a)) * step
+}
+
+/// Median of a reference grid (its 50th percentile point).
+pub fn grid_median(grid: &[f32]) -> f32 {
+    if grid.is_empty() {
+        f32::NAN
+    } else {
+        grid[grid.len() / 2]
+    }
+}
+
+#[cfg(test)]
+#[path = "../../tests/src_inline/model/reference.rs"]
+mod tests;
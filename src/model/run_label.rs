@@ -0,0 +1,85 @@
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Longest accepted `--label`.
+pub const MAX_LABEL_LEN: usize = 128;
+/// Longest accepted `--description`.
+pub const MAX_DESCRIPTION_LEN: usize = 1024;
+
+#[derive(Debug, Error)]
+pub enum RunLabelError {
+    #[error("invalid --label '{label}': {message}")]
+    Label { label: String, message: String },
+    #[error("invalid --description: {0}")]
+    Description(String),
+}
+
+/// Run identity from `--label` / `--description`, embedded in summary.json,
+/// provenance.json, pipeline_step.json, the reports and the cohort row so it
+/// survives copying the output directory.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RunLabel {
+    pub label: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+impl RunLabel {
+    pub fn new(label: &str, description: Option<&str>) -> Result<Self, RunLabelError> {
+        validate_label(label)?;
+        if let Some(description) = description {
+            validate_description(description)?;
+        }
+        Ok(Self {
+            label: label.to_string(),
+            description: description.map(str::to_string),
+        })
+    }
+}
+
+/// A label starts with an ASCII letter or digit and uses only letters,
+/// digits, spaces and `._-+:@#`.
+pub fn validate_label(label: &str) -> Result<(), RunLabelError> {
+    let invalid = |message: String| RunLabelError::Label {
+        label: label.to_string(),
+        message,
+    };
+    if !label.starts_with(|c: char| c.is_ascii_alphanumeric()) {
+        return Err(invalid("must start with a letter or digit".to_string()));
+    }
+    if label.len() > MAX_LABEL_LEN {
+        return Err(invalid(format!("longer than {MAX_LABEL_LEN} characters")));
+    }
+    if label.ends_with(' ') {
+        return Err(invalid("must not end with a space".to_string()));
+    }
+    if let Some(c) = label
+        .chars()
+        .find(|c| !c.is_ascii_alphanumeric() && !" ._-+:@#".contains(*c))
+    {
+        return Err(invalid(format!(
+            "character {c:?} is not allowed (letters, digits, spaces and ._-+:@# only)"
+        )));
+    }
+    Ok(())
+}
+
+/// A description is free text without control characters (tabs and
+/// newlines included).
+pub fn validate_description(description: &str) -> Result<(), RunLabelError> {
+    if description.chars().count() > MAX_DESCRIPTION_LEN {
+        return Err(RunLabelError::Description(format!(
+            "longer than {MAX_DESCRIPTION_LEN} characters"
+        )));
+    }
+    if description.chars().any(char::is_control) {
+        return Err(RunLabelError::Description(
+            "must not contain tabs, newlines or other control characters".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+#[path = "../../tests/src_inline/model/run_label.rs"]
+mod tests;
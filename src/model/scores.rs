@@ -75,6 +75,30 @@ impl Default for WeightsDefault {
     }
 }
 
+/// IAI weight set, chosen once per dataset from APCI panel availability.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum IaiWeightSet {
+    WithApci,
+    NoApci,
+}
+
+impl IaiWeightSet {
+    pub fn from_apci_present(present: bool) -> Self {
+        if present {
+            IaiWeightSet::WithApci
+        } else {
+            IaiWeightSet::NoApci
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            IaiWeightSet::WithApci => "with_apci",
+            IaiWeightSet::NoApci => "no_apci",
+        }
+    }
+}
+
 pub fn clamp01(x: f32) -> f32 {
     if x.is_nan() {
         0.0
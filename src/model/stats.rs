@@ -0,0 +1,152 @@
+//! Order statistics and fractions shared by the stage summaries, the sample
+//! aggregates and the cohort reference.
+//!
+//! Every function takes a [`NanPolicy`] saying what a NaN value means.
+//! Percentiles interpolate linearly between the two nearest ranks, so the
+//! median of an even number of values is the mean of the middle two. With no
+//! values to rank, percentiles, [`median`] and [`mad`] are NaN and
+//! [`fraction_ge`] is 0.
+
+/// What NaN values mean to a statistic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NanPolicy {
+    /// NaN values are left out, as if never recorded.
+    Skip,
+    /// Any NaN makes the result NaN.
+    Propagate,
+    /// NaN ranks below every number: it stays in the count, is never at or
+    /// above a cut-off, and a percentile that lands on it is NaN.
+    Low,
+}
+
+/// Values sorted ascending under `nan`; `None` when `nan` is
+/// [`NanPolicy::Propagate`] and a NaN is present.
+fn ranked(values: &[f32], nan: NanPolicy) -> Option<Vec<f32>> {
+    let n_nan = values.iter().filter(|v| v.is_nan()).count();
+    if n_nan > 0 && nan == NanPolicy::Propagate {
+        return None;
+    }
+    let mut sorted: Vec<f32> = values.iter().copied().filter(|v| !v.is_nan()).collect();
+    sorted.sort_by(f32::total_cmp);
+    if nan == NanPolicy::Low && n_nan > 0 {
+        sorted.splice(0..0, std::iter::repeat_n(f32::NAN, n_nan));
+    }
+    Some(sorted)
+}
+
+/// Percentile `p` (0–1) of already ranked values.
+fn interpolate(sorted: &[f32], p: f64) -> f32 {
+    let Some(last) = sorted.len().checked_sub(1) else {
+        return f32::NAN;
+    };
+    let pos = p.clamp(0.0, 1.0) * last as f64;
+    let lo = pos.floor() as usize;
+    let hi = pos.ceil() as usize;
+    if lo == hi {
+        return sorted[lo];
+    }
+    let frac = (pos - lo as f64) as f32;
+    sorted[lo] + (sorted[hi] - sorted[lo]) * frac
+}
+
+/// Percentile `p` (0–1) of `values`, linearly interpolated between ranks.
+pub fn percentile_interpolated(values: &[f32], p: f64, nan: NanPolicy) -> f32 {
+    ranked(values, nan).map_or(f32::NAN, |sorted| interpolate(&sorted, p))
+}
+
+/// [`percentile_interpolated`] for several `ps` with a single sort.
+pub fn percentiles_interpolated(values: &[f32], ps: &[f64], nan: NanPolicy) -> Vec<f32> {
+    match ranked(values, nan) {
+        Some(sorted) => ps.iter().map(|&p| interpolate(&sorted, p)).collect(),
+        None => vec![f32::NAN; ps.len()],
+    }
+}
+
+pub fn median(values: &[f32], nan: NanPolicy) -> f32 {
+    percentile_interpolated(values, 0.5, nan)
+}
+
+/// Median absolute deviation from the median (unscaled).
+pub fn mad(values: &[f32], nan: NanPolicy) -> f32 {
+    let Some(sorted) = ranked(values, nan) else {
+        return f32::NAN;
+    };
+    let center = interpolate(&sorted, 0.5);
+    let deviations: Vec<f32> = sorted.iter().map(|v| (v - center).abs()).collect();
+    // NaN deviations (from Low) must keep ranking low.
+    percentile_interpolated(&deviations, 0.5, nan)
+}
+
+/// Share of `values` at or above `threshold`; 0 when there is nothing to count.
+pub fn fraction_ge(values: &[f32], threshold: f32, nan: NanPolicy) -> f32 {
+    let n_nan = values.iter().filter(|v| v.is_nan()).count();
+    let total = match nan {
+        NanPolicy::Propagate if n_nan > 0 => return f32::NAN,
+        NanPolicy::Skip => values.len() - n_nan,
+        NanPolicy::Propagate | NanPolicy::Low => values.len(),
+    };
+    if total == 0 {
+        return 0.0;
+    }
+    values.iter().filter(|&&v| v >= threshold).count() as f32 / total as f32
+}
+
+/// Ranks of `values` (1-based), ties sharing their mean rank; NaN ranks below
+/// every number and NaNs tie with each other.
+fn midranks(values: &[f32]) -> Vec<f64> {
+    let low_nan = |v: &f32| (!v.is_nan(), if v.is_nan() { 0.0 } else { *v });
+    let mut order: Vec<usize> = (0..values.len()).collect();
+    order.sort_by(|&i, &j| {
+        let (a, b) = (low_nan(&values[i]), low_nan(&values[j]));
+        a.0.cmp(&b.0).then(a.1.total_cmp(&b.1))
+    });
+    let mut ranks = vec![0.0; values.len()];
+    let mut start = 0;
+    while start < order.len() {
+        let key = low_nan(&values[order[start]]);
+        let mut end = start + 1;
+        while end < order.len() && low_nan(&values[order[end]]) == key {
+            end += 1;
+        }
+        let midrank = (start + 1 + end) as f64 / 2.0;
+        for &i in &order[start..end] {
+            ranks[i] = midrank;
+        }
+        start = end;
+    }
+    ranks
+}
+
+/// Spearman rank correlation of the pairs `(a[i], b[i])`, ties given their
+/// mean rank. Under [`NanPolicy::Skip`] pairs with a NaN are left out. NaN
+/// with fewer than two pairs or when either side is constant.
+pub fn spearman(a: &[f32], b: &[f32], nan: NanPolicy) -> f32 {
+    debug_assert_eq!(a.len(), b.len());
+    let has_nan = |(x, y): &(f32, f32)| x.is_nan() || y.is_nan();
+    let mut pairs: Vec<(f32, f32)> = a.iter().copied().zip(b.iter().copied()).collect();
+    match nan {
+        NanPolicy::Propagate if pairs.iter().any(has_nan) => return f32::NAN,
+        NanPolicy::Skip => pairs.retain(|p| !has_nan(p)),
+        NanPolicy::Propagate | NanPolicy::Low => {}
+    }
+    if pairs.len() < 2 {
+        return f32::NAN;
+    }
+    let (xs, ys): (Vec<f32>, Vec<f32>) = pairs.into_iter().unzip();
+    let (rx, ry) = (midranks(&xs), midranks(&ys));
+    let mean = (rx.len() + 1) as f64 / 2.0;
+    let (mut cov, mut var_x, mut var_y) = (0.0f64, 0.0f64, 0.0f64);
+    for (x, y) in rx.iter().zip(&ry) {
+        cov += (x - mean) * (y - mean);
+        var_x += (x - mean) * (x - mean);
+        var_y += (y - mean) * (y - mean);
+    }
+    if var_x == 0.0 || var_y == 0.0 {
+        return f32::NAN;
+    }
+    (cov / (var_x * var_y).sqrt()) as f32
+}
+
+#[cfg(test)]
+#[path = "../../tests/src_inline/model/stats.rs"]
+mod tests;
@@ -1,7 +1,39 @@
-#[derive(Debug, Clone, Copy)]
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::model::chemistry::Chemistry;
+
+#[derive(Debug, Error)]
+pub enum ThresholdsError {
+    #[error("io error reading thresholds {path}: {source}")]
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+    #[error("invalid thresholds {path}: {message}")]
+    Format { path: String, message: String },
+}
+
+/// Species keys accepted under `species` in a thresholds file, as produced by
+/// [`normalize_species`](crate::input::meta::normalize_species).
+pub const THRESHOLDS_SPECIES: [&str; 2] = ["human", "mouse"];
+
+/// `applies_to` entry of the set used by cells without a species override.
+pub const DEFAULT_THRESHOLDS_SCOPE: &str = "default";
+
+/// Classification cut-offs. A thresholds JSON file may set any subset of the
+/// fields; the rest keep their defaults.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct Thresholds {
     pub low_counts: u64,
     pub few_detected: u32,
+    /// When set, `FEW_DETECTED_GENES` fires below this many detected genes
+    /// per 1000 UMIs (and on empty cells) instead of below `few_detected`.
+    pub low_complexity: Option<f32>,
     pub cov_min: f32,
     pub oii_hi: f32,
     pub esi_hi: f32,
@@ -17,8 +49,69 @@ pub struct Thresholds {
     pub ecmi_hi: f32,
     pub gdi_hi: f32,
     pub apci_hi: f32,
+    /// `HIGH_AMBIENT_RISK` needs fewer detected genes than this; unset means
+    /// `few_detected`. Nuclei data detect fewer genes per cell and want it lower.
+    pub ambient_detected: Option<u32>,
+    /// ... and GDI at or above this ...
     pub ambient_gdi: f32,
+    /// ... and SIA below this.
     pub ambient_sia: f32,
+    /// `EXTREME_NORMALIZATION` is set when one count scales to more than
+    /// this (`scale / libsize`, 100 below 100 counts under CP10K).
+    pub extreme_count_scale: f32,
+    /// `proliferation_score` at or above which a cell is flagged
+    /// `HIGH_PROLIFERATION`.
+    pub high_proliferation: f32,
+    /// Cut-offs for the fraction-of-cells-at-or-above summaries in stage7.
+    pub frac_ge: FracGeThresholds,
+}
+
+/// Per contract metric, the values reported as "fraction of cells ≥ t"
+/// globally and per sample/condition. Every list defaults to `[0.65, 0.80]`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FracGeThresholds {
+    pub secretory_load: Vec<f32>,
+    pub exocytosis_bias: Vec<f32>,
+    pub vesicle_traffic_intensity: Vec<f32>,
+    pub er_golgi_pressure: Vec<f32>,
+    pub paracrine_signal_potential: Vec<f32>,
+    pub stress_secretion_index: Vec<f32>,
+    pub confidence: Vec<f32>,
+}
+
+/// Default `frac_ge` cut-offs, the two fixed ones from the axis summaries.
+pub const DEFAULT_FRAC_GE: [f32; 2] = [0.65, 0.80];
+
+impl Default for FracGeThresholds {
+    fn default() -> Self {
+        let d = DEFAULT_FRAC_GE.to_vec();
+        Self {
+            secretory_load: d.clone(),
+            exocytosis_bias: d.clone(),
+            vesicle_traffic_intensity: d.clone(),
+            er_golgi_pressure: d.clone(),
+            paracrine_signal_potential: d.clone(),
+            stress_secretion_index: d.clone(),
+            confidence: d,
+        }
+    }
+}
+
+impl FracGeThresholds {
+    /// Cut-offs for a metric named as in `secretion.tsv`; empty for unknown names.
+    pub fn get(&self, metric: &str) -> &[f32] {
+        match metric {
+            "secretory_load" => &self.secretory_load,
+            "exocytosis_bias" => &self.exocytosis_bias,
+            "vesicle_traffic_intensity" => &self.vesicle_traffic_intensity,
+            "er_golgi_pressure" => &self.er_golgi_pressure,
+            "paracrine_signal_potential" => &self.paracrine_signal_potential,
+            "stress_secretion_index" => &self.stress_secretion_index,
+            "confidence" => &self.confidence,
+            _ => &[],
+        }
+    }
 }
 
 impl Default for Thresholds {
@@ -26,6 +119,7 @@ impl Default for Thresholds {
         Self {
             low_counts: 500,
             few_detected: 300,
+            low_complexity: None,
             cov_min: 0.60,
             oii_hi: 0.65,
             esi_hi: 0.65,
@@ -41,8 +135,225 @@ impl Default for Thresholds {
             ecmi_hi: 0.70,
             gdi_hi: 0.75,
             apci_hi: 0.70,
+            ambient_detected: None,
             ambient_gdi: 0.75,
             ambient_sia: 0.45,
+            extreme_count_scale: 100.0,
+            high_proliferation: 0.75,
+            frac_ge: FracGeThresholds::default(),
+        }
+    }
+}
+
+impl Thresholds {
+    /// Detected-gene gate of the `HIGH_AMBIENT_RISK` rule.
+    pub fn ambient_detected_gate(&self) -> u32 {
+        self.ambient_detected.unwrap_or(self.few_detected)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, ThresholdsError> {
+        let display = path.display().to_string();
+        let bytes = std::fs::read(path).map_err(|source| ThresholdsError::Io {
+            path: display.clone(),
+            source,
+        })?;
+        serde_json::from_slice(&bytes).map_err(|e| ThresholdsError::Format {
+            path: display,
+            message: e.to_string(),
+        })
+    }
+}
+
+/// A `--thresholds` file: global cut-offs plus optional per-species
+/// overrides under `species`, e.g. `{"low_counts": 800, "species": {"mouse":
+/// {"low_counts": 400}}}`. An override sets a subset of top-level fields on
+/// top of the file's global values.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ThresholdsConfig {
+    pub global: Thresholds,
+    pub species: BTreeMap<String, Thresholds>,
+    /// Fields the file set, per scope (`default` or a species); the rest
+    /// may take [`Chemistry`] defaults.
+    pub file_fields: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl ThresholdsConfig {
+    pub fn load(path: &Path) -> Result<Self, ThresholdsError> {
+        let display = path.display().to_string();
+        let bytes = std::fs::read(path).map_err(|source| ThresholdsError::Io {
+            path: display.clone(),
+            source,
+        })?;
+        let invalid = |message: String| ThresholdsError::Format {
+            path: display.clone(),
+            message,
+        };
+        let value: serde_json::Value =
+            serde_json::from_slice(&bytes).map_err(|e| invalid(e.to_string()))?;
+        let serde_json::Value::Object(mut base) = value else {
+            return Err(invalid("expected a JSON object".to_string()));
+        };
+        let overrides = base.remove("species");
+        let global: Thresholds =
+            serde_json::from_value(base.clone().into()).map_err(|e| invalid(e.to_string()))?;
+        let mut file_fields = BTreeMap::from([(
+            DEFAULT_THRESHOLDS_SCOPE.to_string(),
+            base.keys().cloned().collect::<BTreeSet<_>>(),
+        )]);
+
+        let mut species = BTreeMap::new();
+        match overrides {
+            None => {}
+            Some(serde_json::Value::Object(entries)) => {
+                for (name, fields) in entries {
+                    if !THRESHOLDS_SPECIES.contains(&name.as_str()) {
+                        return Err(invalid(format!(
+                            "unknown species '{name}' (expected one of {})",
+                            THRESHOLDS_SPECIES.join(", ")
+                        )));
+                    }
+                    let serde_json::Value::Object(fields) = fields else {
+                        return Err(invalid(format!("species.{name} must be an object")));
+                    };
+                    let mut merged = base.clone();
+                    merged.extend(fields);
+                    file_fields.insert(name.clone(), merged.keys().cloned().collect());
+                    let thresholds = serde_json::from_value(merged.into())
+                        .map_err(|e| invalid(format!("species.{name}: {e}")))?;
+                    species.insert(name, thresholds);
+                }
+            }
+            Some(_) => return Err(invalid("species must be an object".to_string())),
         }
+        Ok(Self {
+            global,
+            species,
+            file_fields,
+        })
     }
+
+    /// Sets `low_counts` and `few_detected` to the `chemistry` defaults in
+    /// every scope whose file left them out.
+    pub fn apply_chemistry(&mut self, chemistry: Chemistry) {
+        let scopes = std::iter::once((DEFAULT_THRESHOLDS_SCOPE, &mut self.global)).chain(
+            self.species
+                .iter_mut()
+                .map(|(name, thresholds)| (name.as_str(), thresholds)),
+        );
+        for (scope, thresholds) in scopes {
+            let set = |field: &str| {
+                self.file_fields
+                    .get(scope)
+                    .is_some_and(|fields| fields.contains(field))
+            };
+            if !set("low_counts") {
+                thresholds.low_counts = chemistry.low_counts();
+            }
+            if !set("few_detected") {
+                thresholds.few_detected = chemistry.few_detected();
+            }
+        }
+    }
+
+    /// Sets `low_complexity` in every scope (`--flag-low-complexity`).
+    pub fn set_low_complexity(&mut self, min: f32) {
+        self.global.low_complexity = Some(min);
+        for thresholds in self.species.values_mut() {
+            thresholds.low_complexity = Some(min);
+        }
+    }
+
+    /// Cut-offs for a cell of `species` and the scope they are recorded under.
+    pub fn for_species<'a>(&'a self, species: &'a str) -> (&'a Thresholds, &'a str) {
+        match self.species.get(species) {
+            Some(thresholds) => (thresholds, species),
+            None => (&self.global, DEFAULT_THRESHOLDS_SCOPE),
+        }
+    }
+}
+
+/// Global cut-offs only.
+impl From<Thresholds> for ThresholdsConfig {
+    fn from(global: Thresholds) -> Self {
+        Self {
+            global,
+            species: BTreeMap::new(),
+            file_fields: BTreeMap::new(),
+        }
+    }
+}
+
+/// One distinct set of cut-offs a run classified cells with.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ThresholdsSet {
+    /// `t0`, `t1`, ... in order of first use by cell index.
+    pub id: String,
+    /// `default` and/or the species whose cells used this set.
+    pub applies_to: Vec<String>,
+    pub thresholds: Thresholds,
 }
+
+/// Registry of the [`ThresholdsSet`]s of a run and the set each cell used;
+/// recorded in `provenance.json` and referenced by `thresholds_id` in
+/// `classify.tsv`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CellThresholds {
+    sets: Vec<ThresholdsSet>,
+    /// Index into `sets` per cell; empty when every cell uses set 0.
+    cell_sets: Vec<u16>,
+}
+
+impl CellThresholds {
+    /// The same cut-offs for every cell: one registry entry.
+    pub fn uniform(thresholds: Thresholds) -> Self {
+        Self {
+            sets: vec![ThresholdsSet {
+                id: "t0".to_string(),
+                applies_to: vec![DEFAULT_THRESHOLDS_SCOPE.to_string()],
+                thresholds,
+            }],
+            cell_sets: Vec::new(),
+        }
+    }
+
+    /// Picks each cell's cut-offs by its species. Scopes that resolve to equal
+    /// cut-offs share one entry; unused overrides get none.
+    pub fn by_species(config: &ThresholdsConfig, species: &[&str]) -> Self {
+        let mut sets: Vec<ThresholdsSet> = Vec::new();
+        let mut cell_sets = Vec::with_capacity(species.len());
+        for cell_species in species {
+            let (thresholds, scope) = config.for_species(cell_species);
+            let idx = match sets.iter().position(|s| &s.thresholds == thresholds) {
+                Some(idx) => idx,
+                None => {
+                    sets.push(ThresholdsSet {
+                        id: format!("t{}", sets.len()),
+                        applies_to: Vec::new(),
+                        thresholds: thresholds.clone(),
+                    });
+                    sets.len() - 1
+                }
+            };
+            if !sets[idx].applies_to.iter().any(|s| s == scope) {
+                sets[idx].applies_to.push(scope.to_string());
+            }
+            cell_sets.push(idx as u16);
+        }
+        if sets.is_empty() {
+            return Self::uniform(config.global.clone());
+        }
+        Self { sets, cell_sets }
+    }
+
+    pub fn sets(&self) -> &[ThresholdsSet] {
+        &self.sets
+    }
+
+    pub fn for_cell(&self, cell: usize) -> &ThresholdsSet {
+        &self.sets[self.cell_sets.get(cell).map_or(0, |&i| usize::from(i))]
+    }
+}
+
+#[cfg(test)]
+#[path = "../../tests/src_inline/model/thresholds.rs"]
+mod tests;
@@ -1,5 +1,17 @@
+use crc::{CRC_64_ECMA_182, Crc};
 use serde::{Deserialize, Serialize};
 
+use crate::model::catalog::{PANEL_AXIS_TAGS, PanelAxis};
+
+const CRC64: Crc<u64> = Crc::<u64>::new(&CRC_64_ECMA_182);
+
+/// Axis identifiers understood by stage4, from the catalog's [`PanelAxis`].
+pub const KNOWN_AXES: [&str; PanelAxis::ALL.len()] = PANEL_AXIS_TAGS;
+
+/// Axis of covariate panels: scored in stage3 and reported, but left out of
+/// every axis index.
+pub const COVARIATE_AXIS: &str = "NONE";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(transparent)]
 pub struct PanelGene {
@@ -7,26 +19,137 @@ pub struct PanelGene {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct PanelDef {
     pub id: String,
     pub description: String,
     pub axis: String,
+    /// Reporting group (e.g. "cytokines"); independent of the scoring axis.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
     #[serde(default)]
     pub genes: Vec<PanelGene>,
     #[serde(default)]
     pub required: Vec<String>,
     #[serde(default)]
     pub weights: Option<Vec<f32>>,
+    /// Allows an axis outside `KNOWN_AXES`; such panels are not scored.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub custom_axis: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// File the panel was loaded from; filled in by the loader.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct PanelSet {
     #[serde(default)]
     pub panels: Vec<PanelDef>,
+    /// Panel files the set was loaded from, relative to the panels directory.
+    #[serde(default)]
+    pub files: Vec<String>,
+    /// Files dropped under `PanelErrorPolicy::Skip`, with their errors.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skipped: Vec<SkippedPanelFile>,
+    /// Required genes appended to `genes` under `--panels-autofix-required`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub required_autofixes: Vec<RequiredAutofix>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SkippedPanelFile {
+    pub file: String,
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RequiredAutofix {
+    pub file: String,
+    pub panel_id: String,
+    pub genes: Vec<String>,
 }
 
 impl PanelDef {
     pub fn gene_symbols(&self) -> impl Iterator<Item = &str> {
         self.genes.iter().map(|g| g.symbol.as_str())
     }
+
+    /// Reporting group, falling back to the axis when no group is set.
+    pub fn group_name(&self) -> &str {
+        self.group.as_deref().unwrap_or(&self.axis)
+    }
+
+    /// Appends `required` genes missing from `genes`, with weight 1.0 when
+    /// the panel has weights. Returns the appended symbols.
+    pub fn append_missing_required(&mut self) -> Vec<String> {
+        let mut appended: Vec<String> = Vec::new();
+        for req in &self.required {
+            if !self.genes.iter().any(|g| &g.symbol == req) && !appended.contains(req) {
+                appended.push(req.clone());
+            }
+        }
+        if appended.is_empty() {
+            return appended;
+        }
+        let n_listed = self.genes.len();
+        if let Some(weights) = &mut self.weights {
+            // Explicit 1.0 for listed genes without a weight keeps the new
+            // weights aligned with the appended genes.
+            weights.resize(n_listed, 1.0);
+            weights.resize(n_listed + appended.len(), 1.0);
+        }
+        self.genes.extend(appended.iter().map(|symbol| PanelGene {
+            symbol: symbol.clone(),
+        }));
+        appended
+    }
+
+    /// Semantic checks that TOML deserialization cannot express.
+    pub fn validate(&self) -> Vec<String> {
+        let mut issues = Vec::new();
+        if !self.custom_axis
+            && self.axis != COVARIATE_AXIS
+            && !KNOWN_AXES.contains(&self.axis.as_str())
+        {
+            issues.push(format!(
+                "unknown axis '{}' (expected one of {}, {} for a covariate, or set custom_axis = true)",
+                self.axis,
+                KNOWN_AXES.join(", "),
+                COVARIATE_AXIS
+            ));
+        }
+        if self.genes.is_empty() {
+            issues.push("genes must not be empty".to_string());
+        }
+        for req in &self.required {
+            if !self.genes.iter().any(|g| &g.symbol == req) {
+                issues.push(format!("required gene '{}' is not listed in genes", req));
+            }
+        }
+        issues
+    }
+
+    /// CRC-64 of the panel definition, excluding `source`, as 16 hex digits.
+    pub fn content_hash(&self) -> String {
+        let mut def = self.clone();
+        def.source = None;
+        let bytes = serde_json::to_vec(&def).unwrap_or_default();
+        format!("{:016x}", CRC64.checksum(&bytes))
+    }
+}
+
+impl PanelSet {
+    /// CRC-64 over the sorted per-panel content hashes; independent of file
+    /// layout and load order.
+    pub fn content_hash(&self) -> String {
+        let mut entries: Vec<String> = self
+            .panels
+            .iter()
+            .map(|p| format!("{}:{}", p.id, p.content_hash()))
+            .collect();
+        entries.sort();
+        format!("{:016x}", CRC64.checksum(entries.join("\n").as_bytes()))
+    }
 }
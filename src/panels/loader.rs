@@ -1,42 +1,220 @@
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
 
+use glob::Pattern;
 use thiserror::Error;
 
-use crate::panels::defs::PanelSet;
+use crate::panels::defs::{PanelDef, PanelSet, RequiredAutofix, SkippedPanelFile};
 
 #[derive(Debug, Error)]
 pub enum PanelLoadError {
     #[error("io error: {0}")]
     Io(#[from] std::io::Error),
-    #[error("toml parse error: {0}")]
-    Toml(#[from] toml::de::Error),
     #[error("no panels found in {0}")]
     Empty(String),
+    #[error("invalid panel glob '{pattern}': {message}")]
+    Pattern { pattern: String, message: String },
+    #[error("{}", format_issues(.0))]
+    Invalid(Vec<PanelIssue>),
+}
+
+/// A single problem found in a panel file, with file and panel context.
+#[derive(Debug, Clone)]
+pub struct PanelIssue {
+    pub file: String,
+    pub panel: Option<String>,
+    pub message: String,
+}
+
+impl std::fmt::Display for PanelIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.panel {
+            Some(id) => write!(f, "{}: panel '{}': {}", self.file, id, self.message),
+            None => write!(f, "{}: {}", self.file, self.message),
+        }
+    }
+}
+
+fn format_issues(issues: &[PanelIssue]) -> String {
+    let lines: Vec<String> = issues.iter().map(|i| i.to_string()).collect();
+    format!("{} panel error(s):\n{}", issues.len(), lines.join("\n"))
+}
+
+/// What to do with a panel file that cannot be read, parsed or validated.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PanelErrorPolicy {
+    /// Abort with every issue found across all files.
+    #[default]
+    Fail,
+    /// Drop the whole file and record it in `PanelSet::skipped`.
+    Skip,
+}
+
+/// File selection for panel loading. Patterns are matched against the path
+/// relative to the panels directory, using `/` as separator.
+#[derive(Debug, Clone, Default)]
+pub struct PanelLoadOptions {
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    pub on_error: PanelErrorPolicy,
+    /// Append `required` genes missing from `genes` instead of rejecting the
+    /// panel; each fix is listed in `PanelSet::required_autofixes`.
+    pub autofix_required: bool,
 }
 
 #[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
 struct PanelFile {
     #[serde(default)]
     panel: Vec<crate::panels::defs::PanelDef>,
 }
 
 pub fn load_panels_from_dir(dir: &Path) -> Result<PanelSet, PanelLoadError> {
-    let mut files = list_toml_files(dir)?;
+    load_panels_with_options(dir, &PanelLoadOptions::default())
+}
+
+pub fn load_panels_with_options(
+    dir: &Path,
+    opts: &PanelLoadOptions,
+) -> Result<PanelSet, PanelLoadError> {
+    let include = compile_patterns(&opts.include)?;
+    let exclude = compile_patterns(&opts.exclude)?;
+
+    let mut files = Vec::new();
+    list_toml_files(dir, dir, &mut files)?;
     files.sort();
+    files.retain(|(rel, _)| {
+        (include.is_empty() || include.iter().any(|p| p.matches(rel)))
+            && !exclude.iter().any(|p| p.matches(rel))
+    });
 
-    let mut panels = Vec::new();
-    for file in files {
-        let text = fs::read_to_string(&file)?;
-        let parsed: PanelFile = toml::from_str(&text)?;
-        panels.extend(parsed.panel.into_iter());
+    let mut panels: Vec<PanelDef> = Vec::new();
+    let mut issues: Vec<PanelIssue> = Vec::new();
+    let mut skipped: Vec<SkippedPanelFile> = Vec::new();
+    let mut loaded: Vec<String> = Vec::new();
+    let mut required_autofixes: Vec<RequiredAutofix> = Vec::new();
+    let mut seen: HashMap<String, String> = HashMap::new();
+    for (rel, path) in &files {
+        let (file_panels, file_issues, file_fixes) =
+            load_file(rel, path, &seen, opts.autofix_required);
+        if file_issues.is_empty() {
+            required_autofixes.extend(file_fixes);
+            for panel in file_panels {
+                seen.insert(panel.id.clone(), rel.clone());
+                panels.push(panel);
+            }
+            loaded.push(rel.clone());
+            continue;
+        }
+        match opts.on_error {
+            PanelErrorPolicy::Fail => {
+                // Keep ids registered so later duplicates are still reported.
+                for panel in file_panels {
+                    seen.entry(panel.id).or_insert_with(|| rel.clone());
+                }
+                issues.extend(file_issues);
+            }
+            PanelErrorPolicy::Skip => skipped.push(SkippedPanelFile {
+                file: rel.clone(),
+                errors: file_issues
+                    .into_iter()
+                    .map(|i| match i.panel {
+                        Some(id) => format!("panel '{}': {}", id, i.message),
+                        None => i.message,
+                    })
+                    .collect(),
+            }),
+        }
     }
 
+    if !issues.is_empty() {
+        return Err(PanelLoadError::Invalid(issues));
+    }
     if panels.is_empty() {
         return Err(PanelLoadError::Empty(dir.to_string_lossy().to_string()));
     }
 
-    Ok(PanelSet { panels })
+    Ok(PanelSet {
+        panels,
+        files: loaded,
+        skipped,
+        required_autofixes,
+    })
+}
+
+/// Reads, parses and validates one panel file. Panels are returned even when
+/// issues were found; `seen` holds ids accepted from earlier files.
+fn load_file(
+    rel: &str,
+    path: &Path,
+    seen: &HashMap<String, String>,
+    autofix_required: bool,
+) -> (Vec<PanelDef>, Vec<PanelIssue>, Vec<RequiredAutofix>) {
+    let issue = |panel: Option<&str>, message: String| PanelIssue {
+        file: rel.to_string(),
+        panel: panel.map(str::to_string),
+        message,
+    };
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) => {
+            return (
+                Vec::new(),
+                vec![issue(None, format!("read error: {e}"))],
+                Vec::new(),
+            );
+        }
+    };
+    let parsed: PanelFile = match toml::from_str(&text) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            let message = match e.span() {
+                Some(span) => format!(
+                    "toml parse error at line {}: {}",
+                    text[..span.start].matches('\n').count() + 1,
+                    e.message()
+                ),
+                None => format!("toml parse error: {}", e.message()),
+            };
+            return (Vec::new(), vec![issue(None, message)], Vec::new());
+        }
+    };
+
+    let mut panels: Vec<PanelDef> = Vec::new();
+    let mut issues = Vec::new();
+    let mut fixes = Vec::new();
+    let mut local: HashSet<String> = HashSet::new();
+    for mut panel in parsed.panel {
+        if autofix_required {
+            let genes = panel.append_missing_required();
+            if !genes.is_empty() {
+                fixes.push(RequiredAutofix {
+                    file: rel.to_string(),
+                    panel_id: panel.id.clone(),
+                    genes,
+                });
+            }
+        }
+        for message in panel.validate() {
+            issues.push(issue(Some(&panel.id), message));
+        }
+        let first = seen
+            .get(&panel.id)
+            .cloned()
+            .or_else(|| local.contains(&panel.id).then(|| rel.to_string()));
+        if let Some(first) = first {
+            issues.push(issue(
+                Some(&panel.id),
+                format!("duplicate panel id (first defined in {})", first),
+            ));
+            continue;
+        }
+        local.insert(panel.id.clone());
+        panel.source = Some(rel.to_string());
+        panels.push(panel);
+    }
+    (panels, issues, fixes)
 }
 
 pub fn default_panels_dir() -> PathBuf {
@@ -68,16 +246,41 @@ pub fn default_panels_dir() -> PathBuf {
     relative
 }
 
-fn list_toml_files(dir: &Path) -> Result<Vec<PathBuf>, std::io::Error> {
-    let mut files = Vec::new();
+fn compile_patterns(patterns: &[String]) -> Result<Vec<Pattern>, PanelLoadError> {
+    patterns
+        .iter()
+        .map(|p| {
+            Pattern::new(p).map_err(|e| PanelLoadError::Pattern {
+                pattern: p.clone(),
+                message: e.msg.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Collects `(relative path, full path)` for every `.toml` file under `dir`.
+fn list_toml_files(
+    root: &Path,
+    dir: &Path,
+    files: &mut Vec<(String, PathBuf)>,
+) -> Result<(), std::io::Error> {
     for entry in fs::read_dir(dir)? {
         let entry = entry?;
         let path = entry.path();
-        if path.extension().and_then(|s| s.to_str()) == Some("toml") {
-            files.push(path);
+        if entry.file_type()?.is_dir() {
+            list_toml_files(root, &path, files)?;
+        } else if path.extension().and_then(|s| s.to_str()) == Some("toml") {
+            let rel = path
+                .strip_prefix(root)
+                .unwrap_or(&path)
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            files.push((rel, path));
         }
     }
-    Ok(files)
+    Ok(())
 }
 
 #[cfg(test)]
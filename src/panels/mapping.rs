@@ -1,7 +1,9 @@
+use std::collections::BTreeMap;
+
 use crate::input::features::GeneIndex;
 use crate::panels::defs::PanelDef;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct GeneMapping {
     pub panel_id: String,
     pub mapped: Vec<Option<u32>>,
@@ -9,10 +11,74 @@ pub struct GeneMapping {
     pub required_total: usize,
 }
 
-#[derive(Debug, Clone)]
+/// A panel with required genes absent from the features file; one per
+/// panel per dataset, written to `mapping_warnings.tsv`.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct MappingWarning {
     pub panel_id: String,
+    pub axis: String,
     pub missing_required: Vec<String>,
+    /// Fraction of the panel's genes found in the features file.
+    pub mappable_fraction: f32,
+}
+
+/// Default `--min-panel-gene-fraction`: below it the report warns and
+/// `pipeline_step.json` flags the dataset.
+pub const DEFAULT_MIN_PANEL_GENE_FRACTION: f32 = 0.5;
+
+/// Unique panel gene symbols found in the features file, of those defined.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct GeneUniverseCount {
+    pub mapped: usize,
+    pub total: usize,
+}
+
+impl GeneUniverseCount {
+    pub fn unmapped(&self) -> usize {
+        self.total - self.mapped
+    }
+
+    /// `mapped / total`; 1 without panel genes, where nothing is missing.
+    pub fn mapped_fraction(&self) -> f32 {
+        if self.total == 0 {
+            1.0
+        } else {
+            self.mapped as f32 / self.total as f32
+        }
+    }
+}
+
+/// The panel gene universe of a dataset: unique symbols across all panels,
+/// and per panel `axis` tag. A symbol listed by several panels counts once.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct PanelGeneUniverse {
+    pub overall: GeneUniverseCount,
+    pub by_axis: BTreeMap<String, GeneUniverseCount>,
+}
+
+/// Counts the unique mapped and defined symbols of `panels`, given their
+/// [`map_panel`] results in the same order.
+pub fn panel_gene_universe(panels: &[PanelDef], mappings: &[GeneMapping]) -> PanelGeneUniverse {
+    let mut overall: BTreeMap<&str, bool> = BTreeMap::new();
+    let mut by_axis: BTreeMap<&str, BTreeMap<&str, bool>> = BTreeMap::new();
+    for (panel, mapping) in panels.iter().zip(mappings) {
+        let axis = by_axis.entry(panel.axis.as_str()).or_default();
+        for (gene, row) in panel.genes.iter().zip(&mapping.mapped) {
+            *overall.entry(&gene.symbol).or_default() |= row.is_some();
+            *axis.entry(&gene.symbol).or_default() |= row.is_some();
+        }
+    }
+    let count = |genes: &BTreeMap<&str, bool>| GeneUniverseCount {
+        mapped: genes.values().filter(|&&mapped| mapped).count(),
+        total: genes.len(),
+    };
+    PanelGeneUniverse {
+        overall: count(&overall),
+        by_axis: by_axis
+            .iter()
+            .map(|(axis, genes)| (axis.to_string(), count(genes)))
+            .collect(),
+    }
 }
 
 pub fn map_panel(
@@ -41,9 +107,12 @@ pub fn map_panel(
     let warning = if missing_required.is_empty() {
         None
     } else {
+        let n_mapped = mapped.iter().filter(|m| m.is_some()).count();
         Some(MappingWarning {
             panel_id: panel.id.clone(),
+            axis: panel.axis.clone(),
             missing_required,
+            mappable_fraction: n_mapped as f32 / panel.genes.len().max(1) as f32,
         })
     };
 
@@ -0,0 +1,186 @@
+use std::cell::RefCell;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use thiserror::Error;
+
+use crate::report::artifact::write_artifact;
+
+/// Cells processed between cancellation checks in per-cell loops.
+pub const CANCEL_CHECK_CELLS: usize = 4_096;
+
+/// Matrix entries between cancellation checks while stage 2 parses the MTX
+/// and builds the CSC matrix.
+pub const CANCEL_CHECK_ENTRIES: usize = 65_536;
+
+#[derive(Debug, Error, Clone, Copy, PartialEq, Eq)]
+#[error("run cancelled")]
+pub struct Cancelled;
+
+/// Shared cancellation flag; clones observe the same state. The CLI cancels
+/// it from the Ctrl-C handler, an embedding caller from wherever it likes.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<CancellationToken>> = const { RefCell::new(None) };
+}
+
+/// Makes `token` the one checked by stage loops on this thread until the
+/// guard is dropped.
+pub fn enter(token: &CancellationToken) -> CancelGuard {
+    let previous = CURRENT.with(|c| c.replace(Some(token.clone())));
+    CancelGuard { previous }
+}
+
+pub struct CancelGuard {
+    previous: Option<CancellationToken>,
+}
+
+impl Drop for CancelGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        CURRENT.with(|c| *c.borrow_mut() = previous);
+    }
+}
+
+/// Fails if the current thread's token is cancelled. On a
+/// [`Runner`](crate::pipeline::runner::Runner) worker this is also where the
+/// run pauses once its step budget is spent.
+pub fn check() -> Result<(), Cancelled> {
+    crate::pipeline::runner::pause_if_due();
+    let cancelled = CURRENT.with(|c| {
+        c.borrow()
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled)
+    });
+    if cancelled { Err(Cancelled) } else { Ok(()) }
+}
+
+/// Cheap per-cell hook: only consults the token every [`CANCEL_CHECK_CELLS`] cells.
+#[inline]
+pub fn checkpoint(done: usize) -> Result<(), Cancelled> {
+    if done.is_multiple_of(CANCEL_CHECK_CELLS) {
+        check()
+    } else {
+        Ok(())
+    }
+}
+
+/// Per-entry hook for matrix reading: consults the token every
+/// [`CANCEL_CHECK_ENTRIES`] entries.
+#[inline]
+pub fn entry_checkpoint(done: usize) -> Result<(), Cancelled> {
+    if done.is_multiple_of(CANCEL_CHECK_ENTRIES) {
+        check()
+    } else {
+        Ok(())
+    }
+}
+
+/// Written in place of `_SUCCESS` when a run is interrupted.
+pub const ABORT_MARKER: &str = "run_aborted.json";
+
+/// Stages in execution order, as recorded by the resource tracker.
+pub const STAGE_ORDER: [&str; 7] = [
+    "stage1_load",
+    "stage2_normalize",
+    "stage3_panels",
+    "stage4_axes",
+    "stage5_scores",
+    "stage6_classify",
+    "stage7_report",
+];
+
+/// Files a stage writes into the output directory; removed when the stage is
+/// interrupted so no half-written artifact is left behind.
+pub fn stage_artifacts(stage: &str) -> &'static [&'static str] {
+    match stage {
+        "stage2_normalize" => &["expr_stats.tsv"],
+        "stage3_panels" => &[
+            "panels_report.tsv",
+            "panel_gene_mapping.tsv",
+            "mapping_warnings.tsv",
+        ],
+        "stage4_axes" => &["axes.tsv", "axes_summary.json"],
+        "stage5_scores" => &["composites.tsv", "composites_summary.json"],
+        "stage6_classify" => &["classify.tsv"],
+        "stage7_report" => &[
+            "secretion.tsv",
+            "secretion.tsv.gz",
+            "secretion.bin",
+            "flagged_cells.tsv",
+            "panels_report.tsv",
+            "panels_by_sample.tsv",
+            "provenance.json",
+            "summary.json",
+            "condition_summary.tsv",
+            "report.txt",
+            "report.md",
+            "report.html",
+            "qc_gate.json",
+            "pipeline_step.json",
+            "panel_expr.tsv.gz",
+            "panel_expr.mtx.gz",
+            "panel_expr_genes.tsv.gz",
+            "panel_expr_barcodes.tsv.gz",
+        ],
+        _ => &[],
+    }
+}
+
+/// Removes the interrupted stage's partial outputs and writes [`ABORT_MARKER`].
+/// `completed` lists finished stages in order.
+pub fn write_abort_marker(out_dir: &Path, completed: &[&str]) -> Result<(), std::io::Error> {
+    let last_completed = completed.last().copied();
+    let aborted = STAGE_ORDER
+        .iter()
+        .copied()
+        .find(|stage| !completed.contains(stage));
+    if let Some(stage) = aborted {
+        for name in stage_artifacts(stage) {
+            match std::fs::remove_file(out_dir.join(name)) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+        }
+    }
+    let marker = serde_json::json!({
+        "reason": "interrupted",
+        "last_completed_stage": last_completed,
+        "aborted_stage": aborted,
+        "completed_stages": completed,
+    });
+    write_artifact(
+        out_dir,
+        ABORT_MARKER,
+        serde_json::to_string_pretty(&marker)?,
+    )
+}
+
+/// Clears a marker left by an earlier interrupted run in the same directory.
+pub fn remove_abort_marker(out_dir: &Path) -> Result<(), std::io::Error> {
+    match std::fs::remove_file(out_dir.join(ABORT_MARKER)) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+#[path = "../../tests/src_inline/pipeline/cancel.rs"]
+mod tests;
@@ -0,0 +1,167 @@
+//! Binary checkpoints of the stage contexts ([`PanelsContext`],
+//! [`AxesContext`], [`ScoresContext`], [`ClassifyContext`]) so a later stage
+//! can start from a saved context instead of recomputing it.
+//!
+//! A file is an 8-byte magic, the format version (`u16`, little-endian), a
+//! context tag byte and the bincode encoding of the context. Values
+//! round-trip bit for bit, NaN included; a file written by another format
+//! version is rejected before its payload is decoded.
+
+use std::path::Path;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use thiserror::Error;
+
+use crate::pipeline::stage3_panels::PanelsContext;
+use crate::pipeline::stage4_axes::AxesContext;
+use crate::pipeline::stage5_scores::ScoresContext;
+use crate::pipeline::stage6_classify::ClassifyContext;
+use crate::report::artifact::write_artifact;
+
+pub const CHECKPOINT_MAGIC: [u8; 8] = *b"KSECCKPT";
+/// Bumped whenever a context struct changes shape.
+pub const CHECKPOINT_VERSION: u16 = 1;
+
+const HEADER_LEN: usize = CHECKPOINT_MAGIC.len() + 2 + 1;
+
+#[derive(Debug, Error)]
+pub enum CheckpointError {
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("not a checkpoint file (bad magic)")]
+    InvalidMagic,
+    #[error("checkpoint format version {found}, this build reads version {expected}")]
+    VersionMismatch { found: u16, expected: u16 },
+    #[error("checkpoint holds {found}, expected {expected}")]
+    WrongContext {
+        found: String,
+        expected: &'static str,
+    },
+    #[error("invalid checkpoint payload: {0}")]
+    Payload(#[from] bincode::Error),
+}
+
+/// A context that can be written as a checkpoint; `TAG` tells the kinds apart.
+pub trait Checkpoint: Serialize + DeserializeOwned {
+    const TAG: u8;
+    const NAME: &'static str;
+}
+
+impl Checkpoint for PanelsContext {
+    const TAG: u8 = 3;
+    const NAME: &'static str = "panels";
+}
+
+impl Checkpoint for AxesContext {
+    const TAG: u8 = 4;
+    const NAME: &'static str = "axes";
+}
+
+impl Checkpoint for ScoresContext {
+    const TAG: u8 = 5;
+    const NAME: &'static str = "scores";
+}
+
+impl Checkpoint for ClassifyContext {
+    const TAG: u8 = 6;
+    const NAME: &'static str = "classify";
+}
+
+fn context_name(tag: u8) -> String {
+    match tag {
+        PanelsContext::TAG => PanelsContext::NAME.to_string(),
+        AxesContext::TAG => AxesContext::NAME.to_string(),
+        ScoresContext::TAG => ScoresContext::NAME.to_string(),
+        ClassifyContext::TAG => ClassifyContext::NAME.to_string(),
+        other => format!("unknown context tag {other}"),
+    }
+}
+
+pub fn encode_checkpoint<T: Checkpoint>(ctx: &T) -> Result<Vec<u8>, CheckpointError> {
+    let mut bytes = Vec::with_capacity(HEADER_LEN);
+    bytes.extend_from_slice(&CHECKPOINT_MAGIC);
+    bytes.extend_from_slice(&CHECKPOINT_VERSION.to_le_bytes());
+    bytes.push(T::TAG);
+    bincode::serialize_into(&mut bytes, ctx)?;
+    Ok(bytes)
+}
+
+/// Checks the header, then decodes the payload; trailing bytes are an error.
+pub fn decode_checkpoint<T: Checkpoint>(bytes: &[u8]) -> Result<T, CheckpointError> {
+    if bytes.len() < HEADER_LEN || bytes[..CHECKPOINT_MAGIC.len()] != CHECKPOINT_MAGIC {
+        return Err(CheckpointError::InvalidMagic);
+    }
+    let version_at = CHECKPOINT_MAGIC.len();
+    let found = u16::from_le_bytes([bytes[version_at], bytes[version_at + 1]]);
+    if found != CHECKPOINT_VERSION {
+        return Err(CheckpointError::VersionMismatch {
+            found,
+            expected: CHECKPOINT_VERSION,
+        });
+    }
+    let tag = bytes[HEADER_LEN - 1];
+    if tag != T::TAG {
+        return Err(CheckpointError::WrongContext {
+            found: context_name(tag),
+            expected: T::NAME,
+        });
+    }
+    let payload = &bytes[HEADER_LEN..];
+    let mut reader = payload;
+    let ctx = bincode::deserialize_from(&mut reader)?;
+    if !reader.is_empty() {
+        return Err(CheckpointError::Payload(Box::new(
+            bincode::ErrorKind::Custom(format!("{} trailing bytes", reader.len())),
+        )));
+    }
+    Ok(ctx)
+}
+
+/// Writes `ctx` to `dir/name` atomically (see [`write_artifact`]).
+pub fn write_checkpoint<T: Checkpoint>(
+    dir: &Path,
+    name: &str,
+    ctx: &T,
+) -> Result<(), CheckpointError> {
+    write_artifact(dir, name, encode_checkpoint(ctx)?)?;
+    Ok(())
+}
+
+pub fn read_checkpoint<T: Checkpoint>(path: &Path) -> Result<T, CheckpointError> {
+    decode_checkpoint(&std::fs::read(path)?)
+}
+
+/// `with` helper storing a value as its JSON text in binary formats, for
+/// types whose serde layout uses `skip_serializing_if` (which bincode cannot
+/// read back). Human-readable formats get the value itself.
+pub(crate) mod as_json {
+    use serde::de::{DeserializeOwned, Error as _};
+    use serde::ser::Error as _;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<T: Serialize, S: Serializer>(
+        value: &T,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            return value.serialize(serializer);
+        }
+        let json = serde_json::to_string(value).map_err(S::Error::custom)?;
+        serializer.serialize_str(&json)
+    }
+
+    pub fn deserialize<'de, T: DeserializeOwned, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<T, D::Error> {
+        if deserializer.is_human_readable() {
+            return T::deserialize(deserializer);
+        }
+        let json = String::deserialize(deserializer)?;
+        serde_json::from_str(&json).map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+#[path = "../../tests/src_inline/pipeline/checkpoint_file.rs"]
+mod tests;
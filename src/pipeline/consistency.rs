@@ -0,0 +1,119 @@
+//! End-of-stage-7 guard: `secretion.tsv` is read back and must agree with the
+//! in-memory [`FinalSummary`](crate::pipeline::stage7_report::FinalSummary)
+//! that becomes `summary.json`.
+//!
+//! The regime counts and the medians of the three summarized distributions
+//! are recomputed from the written file in one streaming pass, looking
+//! columns up by name. Medians may differ by the six-decimal rounding of the
+//! table; anything beyond [`MEDIAN_TOLERANCE`] means the two outputs were
+//! formatted or clamped differently.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+
+use crate::input::InputError;
+use crate::input::table::TsvReader;
+use crate::model::stats::{NanPolicy, median};
+use crate::pipeline::stage7_report::{DistributionSummary, Quantiles, RegimeSummary};
+
+/// Largest accepted gap between a median in memory and one recomputed from
+/// the six-decimal values in `secretion.tsv`.
+pub const MEDIAN_TOLERANCE: f32 = 1e-5;
+
+type QuantilesOf = fn(&DistributionSummary) -> &Quantiles;
+
+/// Distributions checked, by `secretion.tsv` column.
+const MEDIAN_COLUMNS: [(&str, QuantilesOf); 3] = [
+    ("secretory_load", |d| &d.secretory_load),
+    ("er_golgi_pressure", |d| &d.er_golgi_pressure),
+    ("stress_secretion_index", |d| &d.stress_secretion_index),
+];
+
+#[derive(Debug, Error)]
+pub enum ConsistencyError {
+    #[error("reading back for the output consistency check: {0}")]
+    Read(#[from] InputError),
+    #[error(
+        "{} disagrees with summary.json: {}; this is a bug in the report writers \
+         (--skip-output-consistency-check writes the outputs anyway)",
+        path.display(),
+        mismatches.join("; ")
+    )]
+    Diverged {
+        path: PathBuf,
+        mismatches: Vec<String>,
+    },
+}
+
+/// Recomputes the regime counts and distribution medians from `path` and
+/// compares them with the summary's.
+///
+/// A metric whose in-memory median is NaN (an absent axis: every value NaN,
+/// which the table writes as 0) is not compared.
+pub fn check_secretion_tsv(
+    path: &Path,
+    regimes: &RegimeSummary,
+    distributions: &DistributionSummary,
+) -> Result<(), ConsistencyError> {
+    let mut reader = TsvReader::open(path)?;
+    let regime_col = reader.column("regime")?;
+    let metric_cols = reader.require(&MEDIAN_COLUMNS.map(|(name, _)| name))?;
+
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    let mut values: Vec<Vec<f32>> = vec![Vec::new(); metric_cols.len()];
+    while let Some(row) = reader.next_row()? {
+        let regime = row.str(regime_col)?;
+        match counts.get_mut(regime) {
+            Some(count) => *count += 1,
+            None => {
+                counts.insert(regime.to_string(), 1);
+            }
+        }
+        for (column, &col) in values.iter_mut().zip(&metric_cols) {
+            column.push(row.f32_or_nan(col)?);
+        }
+    }
+
+    let mut mismatches = Vec::new();
+    let expected = &regimes.counts;
+    let mut labels: Vec<&String> = expected.keys().chain(counts.keys()).collect();
+    labels.sort();
+    labels.dedup();
+    for label in labels {
+        let in_summary = expected.get(label).copied().unwrap_or(0);
+        let in_table = counts.get(label).copied().unwrap_or(0);
+        if in_summary != in_table {
+            mismatches.push(format!(
+                "regime {label}: {in_table} rows, summary counts {in_summary}"
+            ));
+        }
+    }
+    for ((name, quantiles), column) in MEDIAN_COLUMNS.iter().zip(&values) {
+        let in_summary = quantiles(distributions).median;
+        if in_summary.is_nan() {
+            continue;
+        }
+        let in_table = median(column, NanPolicy::Skip);
+        // A NaN here means the table has no readable values at all.
+        if in_table.is_nan() || (in_table - in_summary).abs() > MEDIAN_TOLERANCE {
+            mismatches.push(format!(
+                "{name} median {in_table}, summary has {in_summary}"
+            ));
+        }
+    }
+
+    if mismatches.is_empty() {
+        Ok(())
+    } else {
+        Err(ConsistencyError::Diverged {
+            path: path.to_path_buf(),
+            mismatches,
+        })
+    }
+}
+
+#[cfg(test)]
+#[path = "../../tests/src_inline/pipeline/consistency.rs"]
+mod tests;
@@ -0,0 +1,138 @@
+//! Optional stage7 `explain/<barcode>.json` sidecars for a named subset of
+//! cells (`--explain-cells`).
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use thiserror::Error;
+use tracing::warn;
+
+use crate::pipeline::stage7_report::Stage7Error;
+use crate::report::artifact::write_artifact;
+use crate::report::file_name::{NAME_INDEX, name_index_tsv, safe_file_name};
+
+pub const EXPLAIN_DIR: &str = "explain";
+pub const EXPLAIN_MISSING: &str = "_missing.txt";
+pub const DEFAULT_MAX_EXPLAIN_CELLS: usize = 100;
+
+#[derive(Debug, Error)]
+pub enum ExplainError {
+    #[error("io error reading explain cells {path}: {source}")]
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+    #[error("invalid explain cells {path}: {message}")]
+    Format { path: String, message: String },
+}
+
+/// Barcodes to explain, in file order without repeats, and the most cells
+/// one run writes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExplainCells {
+    pub barcodes: Vec<String>,
+    pub max_cells: usize,
+}
+
+impl ExplainCells {
+    /// One barcode per line; blank lines and surrounding whitespace are ignored.
+    pub fn load(path: &Path, max_cells: usize) -> Result<Self, ExplainError> {
+        let display = path.display().to_string();
+        let text = std::fs::read_to_string(path).map_err(|source| ExplainError::Io {
+            path: display.clone(),
+            source,
+        })?;
+        let mut barcodes: Vec<String> = Vec::new();
+        for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            if !barcodes.iter().any(|b| b == line) {
+                barcodes.push(line.to_string());
+            }
+        }
+        if barcodes.is_empty() {
+            return Err(ExplainError::Format {
+                path: display,
+                message: "no barcodes listed".to_string(),
+            });
+        }
+        Ok(Self {
+            barcodes,
+            max_cells,
+        })
+    }
+}
+
+/// What [`write_explain`] wrote.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExplainOutcome {
+    pub explained: usize,
+    pub missing: Vec<String>,
+    /// Known barcodes left out by [`ExplainCells::max_cells`].
+    pub over_limit: usize,
+}
+
+/// File name of a cell's sidecar inside [`EXPLAIN_DIR`] (see
+/// [`safe_file_name`]); [`NAME_INDEX`] maps it back to the barcode.
+pub fn explain_file_name(barcode: &str) -> String {
+    safe_file_name(barcode, "json")
+}
+
+/// Writes `explain/<barcode>.json` for each requested barcode found in
+/// `barcodes`, with the JSON built by `explain_cell` from the cell index,
+/// up to `requested.max_cells` files, listed with their barcodes in
+/// `explain/_index.tsv`. Unknown barcodes go to `explain/_missing.txt`.
+pub fn write_explain(
+    out_dir: &Path,
+    requested: &ExplainCells,
+    barcodes: &[String],
+    mut explain_cell: impl FnMut(usize) -> serde_json::Value,
+) -> Result<ExplainOutcome, Stage7Error> {
+    let index: HashMap<&str, usize> = barcodes
+        .iter()
+        .enumerate()
+        .map(|(i, b)| (b.as_str(), i))
+        .collect();
+    let dir = out_dir.join(EXPLAIN_DIR);
+    std::fs::create_dir_all(&dir)?;
+
+    let mut outcome = ExplainOutcome::default();
+    let mut written: Vec<(String, &str)> = Vec::new();
+    for barcode in &requested.barcodes {
+        let Some(&cell) = index.get(barcode.as_str()) else {
+            outcome.missing.push(barcode.clone());
+            continue;
+        };
+        if outcome.explained == requested.max_cells {
+            outcome.over_limit += 1;
+            continue;
+        }
+        let json = serde_json::to_string_pretty(&explain_cell(cell))?;
+        let file = explain_file_name(barcode);
+        write_artifact(&dir, &file, json)?;
+        written.push((file, barcode));
+        outcome.explained += 1;
+    }
+    let index = name_index_tsv(
+        "barcode",
+        written
+            .iter()
+            .map(|(file, barcode)| (file.as_str(), *barcode)),
+    )?;
+    write_artifact(&dir, NAME_INDEX, index)?;
+    if !outcome.missing.is_empty() {
+        let mut text = outcome.missing.join("\n");
+        text.push('\n');
+        write_artifact(&dir, EXPLAIN_MISSING, text)?;
+        warn!(
+            n_missing = outcome.missing.len(),
+            "--explain-cells barcodes not in the matrix; listed in {EXPLAIN_DIR}/{EXPLAIN_MISSING}"
+        );
+    }
+    if outcome.over_limit > 0 {
+        warn!(
+            max_cells = requested.max_cells,
+            n_skipped = outcome.over_limit,
+            "--explain-cells lists more cells than --explain-max-cells; explained the first ones"
+        );
+    }
+    Ok(outcome)
+}
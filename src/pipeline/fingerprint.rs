@@ -0,0 +1,195 @@
+//! Run fingerprint recorded in `provenance.json`: digests of the input files
+//! plus every option that changes the outputs. A re-run with the same
+//! fingerprint into a directory that still verifies can be skipped.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use crc::{CRC_64_ECMA_182, Crc};
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::expr::normalize::Normalization;
+use crate::input::InputError;
+use crate::input::cache::{CacheError, read_shared_cache_metadata};
+use crate::input::detect::{detect_10x_dir, detect_prefix, find_shared_cache_file, is_h5ad};
+use crate::input::mtx::read_header;
+use crate::input::upstream::upstream_sidecar_path;
+use crate::pipeline::cancel::ABORT_MARKER;
+use crate::pipeline::stage1_load::{InputFormat, RunMode};
+use crate::pipeline::verify::{SUCCESS_MARKER, run_verify};
+
+const CRC64: Crc<u64> = Crc::<u64>::new(&CRC_64_ECMA_182);
+
+#[derive(Debug, Error)]
+pub enum FingerprintError {
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("input error: {0}")]
+    Input(#[from] InputError),
+    #[error("cache error: {0}")]
+    Cache(#[from] CacheError),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RunFingerprint {
+    /// Crate version; scoring weights and default thresholds change with it.
+    pub version: String,
+    /// Input role (`matrix`, `features`, `barcodes`, `shared_cache`, `meta`,
+    /// `thresholds`, ...) to the CRC-64 of the file's bytes.
+    pub inputs: BTreeMap<String, String>,
+    pub nnz: usize,
+    pub normalization: String,
+    pub panels_hash: String,
+    /// Output-affecting options, by long flag name.
+    pub parameters: BTreeMap<String, String>,
+}
+
+impl RunFingerprint {
+    /// Resolves the dataset files the way stage1 does (shared cache first in
+    /// pipeline mode) and digests them. A dense table or `.h5ad` file is
+    /// digested as the `matrix` input; its `nnz` is left at 0 rather than
+    /// parsing it.
+    pub fn new(
+        input_dir: &Path,
+        run_mode: RunMode,
+        input_format: InputFormat,
+        cache_override: Option<&Path>,
+        normalization: &Normalization,
+        panels_hash: String,
+        parameters: BTreeMap<String, String>,
+    ) -> Result<Self, FingerprintError> {
+        let mut fingerprint = Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            inputs: BTreeMap::new(),
+            nnz: 0,
+            normalization: format!(
+                "enabled={} scale={} epsilon={}",
+                normalization.enabled, normalization.scale, normalization.epsilon
+            ),
+            panels_hash,
+            parameters,
+        };
+        if input_format == InputFormat::Dense || is_h5ad(input_dir) {
+            fingerprint.add_file("matrix", input_dir)?;
+            return Ok(fingerprint);
+        }
+        let shared_cache = match (run_mode, cache_override) {
+            (RunMode::Pipeline, Some(path)) => Some(path.to_path_buf()),
+            (RunMode::Pipeline, None) => {
+                let prefix = detect_prefix(input_dir)?;
+                find_shared_cache_file(input_dir, prefix.as_deref())?
+            }
+            (RunMode::Standalone, _) => None,
+        };
+        if let Some(path) = shared_cache {
+            fingerprint.nnz = read_shared_cache_metadata(&path)?.nnz;
+            fingerprint.add_file("shared_cache", &path)?;
+            let sidecar = upstream_sidecar_path(&path);
+            if sidecar.is_file() {
+                fingerprint.add_file("upstream_columns", &sidecar)?;
+            }
+        } else {
+            let layout = detect_10x_dir(input_dir)?;
+            fingerprint.nnz = read_header(&layout.matrix_path)?.nnz;
+            fingerprint.add_file("matrix", &layout.matrix_path)?;
+            fingerprint.add_file("features", &layout.features_path)?;
+            fingerprint.add_file("barcodes", &layout.barcodes_path)?;
+        }
+        Ok(fingerprint)
+    }
+
+    /// Adds the digest of an optional input (meta, thresholds, reference, ...).
+    pub fn add_file(&mut self, role: &str, path: &Path) -> Result<(), FingerprintError> {
+        self.inputs.insert(role.to_string(), file_digest(path)?);
+        Ok(())
+    }
+
+    /// CRC-64 over the serialized fingerprint, as 16 hex digits.
+    pub fn digest(&self) -> String {
+        let bytes = serde_json::to_vec(self).unwrap_or_default();
+        format!("{:016x}", CRC64.checksum(&bytes))
+    }
+
+    /// The `fingerprint` object written to `provenance.json`.
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut value = serde_json::to_value(self).unwrap_or_default();
+        if let Some(obj) = value.as_object_mut() {
+            obj.insert("digest".to_string(), self.digest().into());
+        }
+        value
+    }
+}
+
+/// CRC-64 of a file's bytes, as 16 hex digits.
+pub fn file_digest(path: &Path) -> Result<String, std::io::Error> {
+    let mut file = File::open(path)?;
+    let mut digest = CRC64.digest();
+    let mut buf = vec![0u8; 1 << 16];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        digest.update(&buf[..n]);
+    }
+    Ok(format!("{:016x}", digest.finalize()))
+}
+
+/// Why `out_dir` does not hold a finished run with fingerprint `digest`, or
+/// `None` when it does: `provenance.json` records the same digest, `_SUCCESS`
+/// is present, no interrupted-run marker or `.tmp` file is left, and the
+/// artifacts verify. `require_gate_pass` also requires a passing
+/// `qc_gate.json`.
+pub fn stale_reason(out_dir: &Path, digest: &str, require_gate_pass: bool) -> Option<String> {
+    let read_json = |name: &str| -> Option<serde_json::Value> {
+        serde_json::from_slice(&std::fs::read(out_dir.join(name)).ok()?).ok()
+    };
+    let Some(provenance) = read_json("provenance.json") else {
+        return Some("no readable provenance.json".to_string());
+    };
+    match provenance["fingerprint"]["digest"].as_str() {
+        None => return Some("provenance.json has no fingerprint".to_string()),
+        Some(recorded) if recorded != digest => {
+            return Some(format!("fingerprint changed ({recorded} -> {digest})"));
+        }
+        Some(_) => {}
+    }
+    if !out_dir.join(SUCCESS_MARKER).is_file() {
+        return Some(format!("no {SUCCESS_MARKER} marker"));
+    }
+    if out_dir.join(ABORT_MARKER).exists() {
+        return Some(format!("{ABORT_MARKER} present"));
+    }
+    let entries = match std::fs::read_dir(out_dir) {
+        Ok(entries) => entries,
+        Err(err) => return Some(format!("cannot list output directory: {err}")),
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.ends_with(".tmp") {
+            return Some(format!("partial artifact {name}"));
+        }
+    }
+    match run_verify(out_dir, None) {
+        Ok(report) if report.passed() => {}
+        Ok(report) => {
+            let failed: Vec<&str> = report.failed().map(|c| c.name).collect();
+            return Some(format!("verification failed: {}", failed.join(", ")));
+        }
+        Err(err) => return Some(format!("verification failed: {err}")),
+    }
+    if require_gate_pass
+        && read_json("qc_gate.json").and_then(|gate| gate["verdict"].as_str().map(|v| v == "pass"))
+            != Some(true)
+    {
+        return Some("qc gate did not pass".to_string());
+    }
+    None
+}
+
+#[cfg(test)]
+#[path = "../../tests/src_inline/pipeline/fingerprint.rs"]
+mod tests;
@@ -1,3 +1,31 @@
+//! The run stages (`stage1_load` .. `stage7_report`) and their shared
+//! plumbing.
+//!
+//! # Determinism across thread counts
+//!
+//! Every artifact is byte-identical whatever the rayon thread count
+//! (`run --threads`). Per-cell values never depend on other cells, so
+//! parallel per-cell loops only have to keep matrix order when collecting
+//! (stage 7 builds its rows with an order-preserving `par_extend`). Values
+//! combined across cells (dataset-level summaries, per-panel and per-sample
+//! aggregates) are either computed sequentially or go through
+//! [`reduce::fold_cell_chunks`], whose chunk boundaries come from the cell
+//! index and whose partials are combined in chunk order, never in the order
+//! threads finish. Quantiles sort the complete value vector first.
+
+pub mod cancel;
+pub mod checkpoint_file;
+pub mod consistency;
+pub mod explain;
+pub mod fingerprint;
+pub mod outputs;
+pub mod panel_expr;
+pub mod progress;
+pub mod reclassify;
+pub mod reduce;
+pub mod resources;
+pub mod rng;
+pub mod runner;
 pub mod stage1_load;
 pub mod stage2_normalize;
 pub mod stage3_panels;
@@ -5,3 +33,21 @@ pub mod stage4_axes;
 pub mod stage5_scores;
 pub mod stage6_classify;
 pub mod stage7_report;
+pub mod verify;
+
+/// Cells processed between progress events emitted from per-cell loops.
+pub const PROGRESS_CHUNK_CELLS: usize = 16_384;
+
+/// Emits a `chunk processed` debug event every [`PROGRESS_CHUNK_CELLS`] cells
+/// and once more for the trailing partial chunk; also feeds `progress.json`.
+pub(crate) fn chunk_progress(stage: &'static str, done: usize, total: usize) {
+    progress::cells(done, total);
+    if done.is_multiple_of(PROGRESS_CHUNK_CELLS) || done == total {
+        tracing::debug!(
+            stage,
+            cells_done = done,
+            cells_total = total,
+            "chunk processed"
+        );
+    }
+}
@@ -0,0 +1,141 @@
+//! Which artifacts a run writes.
+//!
+//! Under [`OutputSet::SummaryOnly`] stages still compute every per-cell value
+//! in memory but skip their per-cell tables (`expr_stats.tsv`, the stage 3
+//! `panels_report.tsv`, `axes.tsv`, `composites.tsv`, `classify.tsv`,
+//! `secretion.tsv`, `flagged_cells.tsv`). The set is per thread, installed
+//! with [`enter`] like the TSV field policy.
+//!
+//! [`Retention`] decides what stays once a run has verified: intermediate
+//! tables are written either way, so they exist to debug a failed run.
+
+use std::cell::Cell;
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::report::artifact::write_artifact;
+
+/// Per-cell tables only the stages themselves need; removed under
+/// [`Retention::Final`] and [`Retention::Minimal`].
+pub const INTERMEDIATE_TABLES: [&str; 4] = [
+    "expr_stats.tsv",
+    "axes.tsv",
+    "composites.tsv",
+    "classify.tsv",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputSet {
+    /// Aggregates only: summaries, the panel-level report and `report.txt`.
+    SummaryOnly,
+    /// Every per-cell table plus the aggregates.
+    #[default]
+    Standard,
+    /// Standard plus optional extras (the panel expression export).
+    Full,
+}
+
+impl OutputSet {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            OutputSet::SummaryOnly => "summary-only",
+            OutputSet::Standard => "standard",
+            OutputSet::Full => "full",
+        }
+    }
+}
+
+thread_local! {
+    static OUTPUTS: Cell<OutputSet> = const { Cell::new(OutputSet::Standard) };
+}
+
+/// Sets the output set for this thread until the guard is dropped.
+pub fn enter(outputs: OutputSet) -> OutputSetGuard {
+    OutputSetGuard {
+        previous: OUTPUTS.replace(outputs),
+    }
+}
+
+pub struct OutputSetGuard {
+    previous: OutputSet,
+}
+
+impl Drop for OutputSetGuard {
+    fn drop(&mut self) {
+        OUTPUTS.set(self.previous);
+    }
+}
+
+pub fn current() -> OutputSet {
+    OUTPUTS.get()
+}
+
+/// Whether stages write their per-cell tables.
+pub fn per_cell_tables() -> bool {
+    current() != OutputSet::SummaryOnly
+}
+
+/// Artifacts kept after a successful run (`--retain`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Retention {
+    #[default]
+    All,
+    /// Drops [`INTERMEDIATE_TABLES`].
+    Final,
+    /// Also drops `panels_report.tsv`.
+    Minimal,
+}
+
+impl Retention {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Retention::All => "all",
+            Retention::Final => "final",
+            Retention::Minimal => "minimal",
+        }
+    }
+
+    /// Files removed once the run has verified.
+    pub fn removed(self) -> Vec<&'static str> {
+        let mut files = Vec::new();
+        if self != Retention::All {
+            files.extend(INTERMEDIATE_TABLES);
+        }
+        if self == Retention::Minimal {
+            files.push("panels_report.tsv");
+        }
+        files
+    }
+}
+
+/// Removes what `retention` does not keep from a verified `out_dir` and
+/// returns the names actually deleted. `pipeline_step.json`, when present,
+/// stops listing them first, so it never points at a missing file.
+pub fn apply_retention(out_dir: &Path, retention: Retention) -> std::io::Result<Vec<&'static str>> {
+    let removed = retention.removed();
+    if removed.is_empty() {
+        return Ok(Vec::new());
+    }
+    let step_path = out_dir.join("pipeline_step.json");
+    if step_path.is_file() {
+        let mut step: Value = serde_json::from_slice(&std::fs::read(&step_path)?)?;
+        if let Some(artifacts) = step["artifacts"].as_object_mut() {
+            artifacts.retain(|_, file| !file.as_str().is_some_and(|f| removed.contains(&f)));
+        }
+        write_artifact(
+            out_dir,
+            "pipeline_step.json",
+            serde_json::to_string_pretty(&step)?,
+        )?;
+    }
+    let mut deleted = Vec::new();
+    for name in removed {
+        match std::fs::remove_file(out_dir.join(name)) {
+            Ok(()) => deleted.push(name),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(deleted)
+}
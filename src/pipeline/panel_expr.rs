@@ -0,0 +1,185 @@
+//! Optional stage7 export of normalized expression for mapped panel genes.
+
+use std::io::Write;
+use std::path::Path;
+
+use crate::pipeline::cancel::checkpoint;
+use crate::pipeline::stage2_normalize::{CellExprSource, ExprContext};
+use crate::pipeline::stage3_panels::PanelsContext;
+use crate::pipeline::stage7_report::Stage7Error;
+use crate::report::artifact::Artifact;
+use crate::report::compress::{CompressFormat, CompressedWriter};
+use crate::report::format::fixed6;
+use crate::report::tsv::field;
+
+pub const PANEL_EXPR_TSV: &str = "panel_expr.tsv.gz";
+pub const PANEL_EXPR_MTX: &str = "panel_expr.mtx.gz";
+pub const PANEL_EXPR_GENES: &str = "panel_expr_genes.tsv.gz";
+pub const PANEL_EXPR_BARCODES: &str = "panel_expr_barcodes.tsv.gz";
+
+/// Layout of the panel expression export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PanelMatrixFormat {
+    /// Dense `panel_expr.tsv.gz`: one row per cell, one column per gene.
+    Tsv,
+    /// Sparse MatrixMarket triple (genes × cells) with gene and barcode lists.
+    Mtx,
+}
+
+/// One exported column: matrix row and the panel symbol that mapped to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PanelGeneColumn {
+    pub row: u32,
+    pub symbol: String,
+}
+
+/// Mapped panel genes in panel order, then gene order within the panel.
+/// A matrix row shared by several panels appears once, at its first use.
+pub fn panel_gene_columns(panels: &PanelsContext) -> Vec<PanelGeneColumn> {
+    let mut seen = std::collections::HashSet::new();
+    let mut columns = Vec::new();
+    for (panel, mapping) in panels.panels.panels.iter().zip(&panels.mappings) {
+        for (gene, mapped) in panel.genes.iter().zip(&mapping.mapped) {
+            if let Some(row) = mapped
+                && seen.insert(*row)
+            {
+                columns.push(PanelGeneColumn {
+                    row: *row,
+                    symbol: gene.symbol.clone(),
+                });
+            }
+        }
+    }
+    columns
+}
+
+/// Writes the export for cells in `order` (indices into `barcodes`) and
+/// returns the written file names keyed by their `pipeline_step.json` role.
+/// Every file is gzip; `compression` picks plain gzip or BGZF.
+pub fn write_panel_expr<M: CellExprSource>(
+    out_dir: &Path,
+    format: PanelMatrixFormat,
+    compression: CompressFormat,
+    expr: &ExprContext<M>,
+    panels: &PanelsContext,
+    barcodes: &[String],
+    order: &[u32],
+) -> Result<Vec<(&'static str, &'static str)>, Stage7Error> {
+    let columns = panel_gene_columns(panels);
+    let mut col_of_row = vec![u32::MAX; expr.expr.n_genes()];
+    for (col, c) in columns.iter().enumerate() {
+        if let Some(slot) = col_of_row.get_mut(c.row as usize) {
+            *slot = col as u32;
+        }
+    }
+
+    match format {
+        PanelMatrixFormat::Tsv => {
+            let mut writer = gz_writer(out_dir, PANEL_EXPR_TSV, compression)?;
+            writer.write_all(b"barcode")?;
+            for c in &columns {
+                write!(writer, "\t{}", field("gene", &c.symbol)?)?;
+            }
+            writer.write_all(b"\n")?;
+            let mut values = vec![0.0f32; columns.len()];
+            for (done, &cell) in order.iter().enumerate() {
+                let cell = cell as usize;
+                values.fill(0.0);
+                for_each_panel_value(expr, &col_of_row, cell, |col, value| values[col] = value);
+                let mut line = field("barcode", &barcodes[cell])?.into_owned();
+                for value in &values {
+                    line.push('\t');
+                    line.push_str(&fixed6(*value));
+                }
+                line.push('\n');
+                writer.write_all(line.as_bytes())?;
+                checkpoint(done + 1)?;
+            }
+            writer.finish()?.commit()?;
+            Ok(vec![("panel_expr", PANEL_EXPR_TSV)])
+        }
+        PanelMatrixFormat::Mtx => {
+            let mut nnz = 0usize;
+            for &cell in order {
+                for_each_panel_value(expr, &col_of_row, cell as usize, |_, value| {
+                    if value != 0.0 {
+                        nnz += 1;
+                    }
+                });
+            }
+
+            let mut writer = gz_writer(out_dir, PANEL_EXPR_MTX, compression)?;
+            writer.write_all(b"%%MatrixMarket matrix coordinate real general\n")?;
+            writeln!(writer, "{} {} {}", columns.len(), order.len(), nnz)?;
+            let mut entries: Vec<(usize, f32)> = Vec::new();
+            for (done, &cell) in order.iter().enumerate() {
+                entries.clear();
+                for_each_panel_value(expr, &col_of_row, cell as usize, |col, value| {
+                    if value != 0.0 {
+                        entries.push((col, value));
+                    }
+                });
+                entries.sort_by_key(|(col, _)| *col);
+                for (col, value) in &entries {
+                    writeln!(writer, "{} {} {}", col + 1, done + 1, fixed6(*value))?;
+                }
+                checkpoint(done + 1)?;
+            }
+            writer.finish()?.commit()?;
+
+            let mut genes = gz_writer(out_dir, PANEL_EXPR_GENES, compression)?;
+            for c in &columns {
+                writeln!(genes, "{}", field("gene", &c.symbol)?)?;
+            }
+            genes.finish()?.commit()?;
+
+            let mut cells = gz_writer(out_dir, PANEL_EXPR_BARCODES, compression)?;
+            for &cell in order {
+                writeln!(cells, "{}", field("barcode", &barcodes[cell as usize])?)?;
+            }
+            cells.finish()?.commit()?;
+
+            Ok(vec![
+                ("panel_expr_matrix", PANEL_EXPR_MTX),
+                ("panel_expr_genes", PANEL_EXPR_GENES),
+                ("panel_expr_barcodes", PANEL_EXPR_BARCODES),
+            ])
+        }
+    }
+}
+
+/// Normalized values of `cell` for exported genes, as `(column, value)`.
+fn for_each_panel_value<M: CellExprSource>(
+    expr: &ExprContext<M>,
+    col_of_row: &[u32],
+    cell: usize,
+    mut f: impl FnMut(usize, f32),
+) {
+    expr.expr.for_each_cell_norm(
+        cell,
+        &expr.normalization,
+        &expr.cell_stats[cell],
+        |row, value| {
+            if let Some(&col) = col_of_row.get(row as usize)
+                && col != u32::MAX
+            {
+                f(col as usize, value);
+            }
+        },
+    );
+}
+
+fn gz_writer(
+    out_dir: &Path,
+    name: &str,
+    compression: CompressFormat,
+) -> Result<CompressedWriter<Artifact>, std::io::Error> {
+    Ok(CompressedWriter::new(
+        Artifact::create(out_dir, name)?,
+        compression,
+    ))
+}
+
+#[cfg(test)]
+#[path = "../../tests/src_inline/pipeline/panel_expr.rs"]
+mod tests;
@@ -0,0 +1,306 @@
+//! Live run status in `progress.json`.
+//!
+//! Stage loops bump atomic [`ProgressCounters`] through [`cells`], installed
+//! per thread with [`enter`] like the cancel flag. A [`ProgressReporter`]
+//! thread samples the counters every interval and rewrites the file through
+//! an [`Artifact`](crate::report::artifact::Artifact), so the compute loops
+//! never wait on I/O and readers only ever see complete JSON.
+
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tracing::warn;
+
+use crate::pipeline::cancel::STAGE_ORDER;
+use crate::report::artifact::write_artifact;
+
+pub const PROGRESS_FILE: &str = "progress.json";
+
+/// Default interval between rewrites of [`PROGRESS_FILE`].
+pub const DEFAULT_PROGRESS_INTERVAL_MS: u64 = 2_000;
+
+/// Cells processed between counter updates in per-cell loops.
+pub const PROGRESS_UPDATE_CELLS: usize = 1_024;
+
+/// Shared progress state; stage loops write it, the reporter thread reads it.
+#[derive(Debug)]
+pub struct ProgressCounters {
+    run_start: Instant,
+    /// 1-based position in [`STAGE_ORDER`]; 0 before the first stage.
+    stage: AtomicUsize,
+    stage_start_ms: AtomicU64,
+    cells_done: AtomicUsize,
+    cells_total: AtomicUsize,
+}
+
+impl Default for ProgressCounters {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProgressCounters {
+    pub fn new() -> Self {
+        Self {
+            run_start: Instant::now(),
+            stage: AtomicUsize::new(0),
+            stage_start_ms: AtomicU64::new(0),
+            cells_done: AtomicUsize::new(0),
+            cells_total: AtomicUsize::new(0),
+        }
+    }
+
+    /// Marks `stage` as running and resets the cell counters.
+    pub fn start_stage(&self, stage: &str) {
+        let index = STAGE_ORDER
+            .iter()
+            .position(|s| *s == stage)
+            .map_or(0, |i| i + 1);
+        self.cells_done.store(0, Ordering::Relaxed);
+        self.cells_total.store(0, Ordering::Relaxed);
+        self.stage_start_ms
+            .store(self.elapsed_ms(), Ordering::Relaxed);
+        self.stage.store(index, Ordering::Relaxed);
+    }
+
+    pub fn set_cells(&self, done: usize, total: usize) {
+        self.cells_total.store(total, Ordering::Relaxed);
+        self.cells_done.store(done, Ordering::Relaxed);
+    }
+
+    fn elapsed_ms(&self) -> u64 {
+        self.run_start.elapsed().as_millis() as u64
+    }
+
+    pub fn snapshot(&self, status: RunStatus) -> ProgressSnapshot {
+        let stage_index = self.stage.load(Ordering::Relaxed);
+        let elapsed_ms = self.elapsed_ms();
+        let stage_elapsed_ms =
+            elapsed_ms.saturating_sub(self.stage_start_ms.load(Ordering::Relaxed));
+        let cells_done = self.cells_done.load(Ordering::Relaxed);
+        let cells_total = self.cells_total.load(Ordering::Relaxed);
+        let eta_ms = match status {
+            RunStatus::Running if cells_done > 0 && cells_total >= cells_done => Some(
+                (stage_elapsed_ms as f64 * (cells_total - cells_done) as f64 / cells_done as f64)
+                    .round() as u64,
+            ),
+            RunStatus::Complete => Some(0),
+            _ => None,
+        };
+        let (exit_category, exit_code) = match status {
+            RunStatus::Failed {
+                category,
+                exit_code,
+            } => (Some(category), Some(exit_code)),
+            _ => (None, None),
+        };
+        ProgressSnapshot {
+            status: status.as_str(),
+            stage: stage_index.checked_sub(1).map(|i| STAGE_ORDER[i]),
+            stage_index,
+            n_stages: STAGE_ORDER.len(),
+            cells_done,
+            cells_total,
+            elapsed_ms,
+            stage_elapsed_ms,
+            eta_ms,
+            exit_category,
+            exit_code,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunStatus {
+    Running,
+    Complete,
+    Failed {
+        category: &'static str,
+        exit_code: u8,
+    },
+}
+
+impl RunStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RunStatus::Running => "running",
+            RunStatus::Complete => "complete",
+            RunStatus::Failed { .. } => "failed",
+        }
+    }
+}
+
+/// Contents of `progress.json`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProgressSnapshot {
+    /// `running`, `complete` or `failed`.
+    pub status: &'static str,
+    /// Current (or last started) stage; null before stage 1.
+    pub stage: Option<&'static str>,
+    pub stage_index: usize,
+    pub n_stages: usize,
+    /// Cells processed by the current stage's per-cell loop so far.
+    pub cells_done: usize,
+    pub cells_total: usize,
+    pub elapsed_ms: u64,
+    pub stage_elapsed_ms: u64,
+    /// Remaining time of the current stage at its rate so far; null until
+    /// the stage has processed cells.
+    pub eta_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_category: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<u8>,
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<Arc<ProgressCounters>>> = const { RefCell::new(None) };
+}
+
+/// Makes `counters` the ones updated by stage loops on this thread until the
+/// guard is dropped.
+pub fn enter(counters: &Arc<ProgressCounters>) -> ProgressGuard {
+    let previous = CURRENT.with(|c| c.replace(Some(Arc::clone(counters))));
+    ProgressGuard { previous }
+}
+
+pub struct ProgressGuard {
+    previous: Option<Arc<ProgressCounters>>,
+}
+
+impl Drop for ProgressGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        CURRENT.with(|c| *c.borrow_mut() = previous);
+    }
+}
+
+/// Records the start of `stage` on this thread's counters, if any.
+pub fn start_stage(stage: &str) {
+    CURRENT.with(|c| {
+        if let Some(counters) = c.borrow().as_ref() {
+            counters.start_stage(stage);
+        }
+    });
+}
+
+/// Cheap per-cell hook: updates this thread's counters every
+/// [`PROGRESS_UPDATE_CELLS`] cells and at the last cell.
+#[inline]
+pub fn cells(done: usize, total: usize) {
+    if done.is_multiple_of(PROGRESS_UPDATE_CELLS) || done == total {
+        CURRENT.with(|c| {
+            if let Some(counters) = c.borrow().as_ref() {
+                counters.set_cells(done, total);
+            }
+        });
+    }
+}
+
+/// Background thread rewriting [`PROGRESS_FILE`] every interval until
+/// [`finish`](Self::finish) writes the final status.
+pub struct ProgressReporter {
+    counters: Arc<ProgressCounters>,
+    stop: Option<mpsc::Sender<RunStatus>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl ProgressReporter {
+    /// Writes an initial snapshot and starts the reporter thread.
+    pub fn start(out_dir: &Path, interval: Duration) -> Self {
+        let counters = Arc::new(ProgressCounters::new());
+        let (stop, rx) = mpsc::channel();
+        let out_dir = out_dir.to_path_buf();
+        let shared = Arc::clone(&counters);
+        let handle = std::thread::Builder::new()
+            .name("progress".to_string())
+            .spawn(move || {
+                let mut writer = SnapshotWriter::new(out_dir);
+                writer.write(&shared.snapshot(RunStatus::Running));
+                loop {
+                    match rx.recv_timeout(interval) {
+                        Ok(status) => {
+                            writer.write(&shared.snapshot(status));
+                            break;
+                        }
+                        Err(RecvTimeoutError::Timeout) => {
+                            writer.write(&shared.snapshot(RunStatus::Running));
+                        }
+                        Err(RecvTimeoutError::Disconnected) => break,
+                    }
+                }
+            });
+        let handle = match handle {
+            Ok(handle) => Some(handle),
+            Err(error) => {
+                warn!(%error, "could not start the progress reporter; progress.json is not written");
+                None
+            }
+        };
+        Self {
+            counters,
+            stop: Some(stop),
+            handle,
+        }
+    }
+
+    pub fn counters(&self) -> &Arc<ProgressCounters> {
+        &self.counters
+    }
+
+    /// Writes the final status and stops the thread.
+    pub fn finish(mut self, status: RunStatus) {
+        if let Some(stop) = self.stop.take() {
+            let _ = stop.send(status);
+        }
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for ProgressReporter {
+    fn drop(&mut self) {
+        self.stop.take();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Writes snapshots, warning only on the first failure.
+struct SnapshotWriter {
+    out_dir: PathBuf,
+    warned: bool,
+}
+
+impl SnapshotWriter {
+    fn new(out_dir: PathBuf) -> Self {
+        Self {
+            out_dir,
+            warned: false,
+        }
+    }
+
+    fn write(&mut self, snapshot: &ProgressSnapshot) {
+        let result = serde_json::to_string_pretty(snapshot)
+            .map_err(std::io::Error::from)
+            .and_then(|json| write_artifact(&self.out_dir, PROGRESS_FILE, json));
+        if let Err(error) = result
+            && !self.warned
+        {
+            self.warned = true;
+            warn!(%error, "could not update progress.json");
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "../../tests/src_inline/pipeline/progress.rs"]
+mod tests;
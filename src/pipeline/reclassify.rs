@@ -0,0 +1,598 @@
+//! Re-runs stages 6 and 7 from the per-cell artifacts of an earlier run.
+//!
+//! `axes.tsv`, `composites.tsv` and `expr_stats.tsv` carry everything
+//! classification and reporting read from stages 1-5, so the matrix and
+//! panels are not needed. Values are re-read at the six decimals they were
+//! written with.
+
+use std::path::{Path, PathBuf};
+
+use serde_json::json;
+use thiserror::Error;
+
+use crate::expr::csc::{CellStats, ExprCsc};
+use crate::expr::normalize::Normalization;
+use crate::input::InputError;
+use crate::input::delimiter::Delimiter;
+use crate::input::detect::TenXFormat;
+use crate::input::features::GeneIndex;
+use crate::input::table::TsvReader;
+use crate::model::axes::{AxisCoverage, AxisValues, CoverageMode};
+use crate::model::run_label::RunLabel;
+use crate::model::scores::IaiWeightSet;
+use crate::model::thresholds::{CellThresholds, ThresholdsConfig};
+use crate::panels::defs::{PanelSet, SkippedPanelFile};
+use crate::panels::mapping::{DEFAULT_MIN_PANEL_GENE_FRACTION, MappingWarning, PanelGeneUniverse};
+use crate::pipeline::stage1_load::{DatasetCtx, RunMode};
+use crate::pipeline::stage2_normalize::{ExprContext, ExprMatrix};
+use crate::pipeline::stage3_panels::{DeadPanel, PanelsContext};
+use crate::pipeline::stage4_axes::{self, AxesContext, AxisDrivers, AxisPanelCounts, AxisPresence};
+use crate::pipeline::stage5_scores::{self, ScoresContext};
+use crate::pipeline::stage6_classify::{
+    Stage6Error, resolve_cell_thresholds, run_stage6_classify_with_cell_thresholds,
+};
+use crate::pipeline::stage7_report::{
+    FinalSummary, ReportOptions, Stage7Error, run_stage7_report_with_options,
+};
+use crate::report::artifact::write_artifact;
+
+const AXES: [&str; 7] = ["SIA", "EEB", "SLI", "MEI", "ECMI", "APCI", "GDI"];
+const COMPOSITES: [&str; 3] = ["OII", "IAI", "ESI"];
+
+#[derive(Debug, Error)]
+pub enum ReclassifyError {
+    #[error("input error: {0}")]
+    Input(#[from] InputError),
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("json error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("{file} line {line}: cell_id {found} does not match axes.tsv ({expected})")]
+    CellMismatch {
+        file: &'static str,
+        line: usize,
+        expected: String,
+        found: String,
+    },
+    #[error("{file} has {found} cells, axes.tsv has {expected}")]
+    CellCount {
+        file: &'static str,
+        expected: usize,
+        found: usize,
+    },
+    #[error("stage6 error: {0}")]
+    Stage6(#[from] Stage6Error),
+    #[error("stage7 error: {0}")]
+    Stage7(#[from] Stage7Error),
+}
+
+/// Stage contexts rebuilt from a previous output directory. Dataset, expression
+/// and panel contexts are stand-ins carrying only what stages 6 and 7 read.
+pub struct PreviousRun {
+    pub source_dir: PathBuf,
+    pub dataset: DatasetCtx,
+    pub expr: ExprContext,
+    pub axes: AxesContext,
+    pub scores: ScoresContext,
+    pub panels: PanelsContext,
+    /// `--mode` of the source run; `cell` when its summary predates the field.
+    pub mode: String,
+    /// `--label` of the source run, kept on the reclassified outputs.
+    pub run_label: Option<RunLabel>,
+    /// Panel gene universe of the source run, which needs its panel mappings.
+    pub panel_genes: Option<PanelGeneUniverse>,
+    /// `--min-panel-gene-fraction` of the source run.
+    pub min_panel_gene_fraction: f32,
+}
+
+/// Accepts either the directory holding the artifacts or a pipeline-mode
+/// `--out` directory containing `kira-secretion/`.
+pub fn resolve_source_dir(dir: &Path) -> PathBuf {
+    let nested = dir.join("kira-secretion");
+    if !dir.join("axes.tsv").exists() && nested.join("axes.tsv").exists() {
+        nested
+    } else {
+        dir.to_path_buf()
+    }
+}
+
+pub fn load_previous_run(dir: &Path) -> Result<PreviousRun, ReclassifyError> {
+    let source_dir = resolve_source_dir(dir);
+    let (cell_ids, values, coverage, drivers) = read_axes(&source_dir.join("axes.tsv"))?;
+    let composites = read_composites(&source_dir.join("composites.tsv"), &cell_ids)?;
+    let cell_stats = read_expr_stats(&source_dir.join("expr_stats.tsv"), &cell_ids)?;
+    let previous_summary = read_previous_summary(&source_dir.join("summary.json"))?;
+
+    let apci_present = composites.iai_weightset.map_or_else(
+        || values.iter().any(|v| !v.apci.is_nan()),
+        |w| w == IaiWeightSet::WithApci,
+    );
+    // Axes marked not present under --allow-missing-axes are NaN in every row.
+    let has_values = |axis: fn(&AxisValues) -> f32| values.iter().any(|v| !axis(v).is_nan());
+    let presence = AxisPresence {
+        sia: has_values(|v| v.sia),
+        eeb: has_values(|v| v.eeb),
+        sli: has_values(|v| v.sli),
+        mei: has_values(|v| v.mei),
+        ecmi: has_values(|v| v.ecmi),
+        apci: apci_present,
+        gdi: has_values(|v| v.gdi),
+    };
+    let n_cells = cell_ids.len();
+    let axes = AxesContext {
+        stats: stage4_axes::compute_summary(&values, &coverage, &presence),
+        cell_ids: cell_ids.clone(),
+        values,
+        coverage,
+        drivers,
+        coverage_mode: previous_summary.coverage_mode,
+        panel_counts: previous_summary.axis_panels,
+        dropped_panels: previous_summary.dropped_dead_panels,
+        curves: None,
+    };
+    let scores = ScoresContext {
+        summary: stage5_scores::compute_summary(
+            &composites.oii,
+            &composites.iai,
+            &composites.esi,
+            &composites.score_concentration,
+        ),
+        oii: composites.oii,
+        iai: composites.iai,
+        esi: composites.esi,
+        cov_oii: composites.cov_oii,
+        cov_iai: composites.cov_iai,
+        cov_esi: composites.cov_esi,
+        drivers_oii: composites.drivers_oii,
+        drivers_iai: composites.drivers_iai,
+        drivers_esi: composites.drivers_esi,
+        score_concentration: composites.score_concentration,
+        iai_weightset: IaiWeightSet::from_apci_present(apci_present),
+        apci_nan_cells: 0,
+    };
+    let expr = ExprContext {
+        expr: ExprMatrix::Owned(ExprCsc {
+            n_genes: 0,
+            n_cells,
+            nnz: 0,
+            col_ptr: vec![0; n_cells + 1],
+            row_idx: Vec::new(),
+            values: Vec::new(),
+        }),
+        cell_stats,
+        normalization: Normalization::default(),
+        value_warnings: Default::default(),
+    };
+    let dataset = DatasetCtx {
+        format: TenXFormat::Unknown,
+        matrix_path: PathBuf::new(),
+        features_path: PathBuf::new(),
+        barcodes_path: PathBuf::new(),
+        features_delimiter: Delimiter::Tab,
+        barcodes_delimiter: Delimiter::Tab,
+        barcodes_header: None,
+        features_single_column_rows: 0,
+        shared_cache_path: None,
+        resolved_shared_cache_path: None,
+        shared_cache: None,
+        shared_cache_identity: None,
+        gene_index: GeneIndex {
+            rows: Vec::new(),
+            duplicates: Vec::new(),
+            first_index_by_symbol: Default::default(),
+        },
+        barcodes: cell_ids.clone(),
+        n_genes: 0,
+        n_cells,
+        nnz: 0,
+        duplicate_gene_symbols_count: 0,
+        duplicate_gene_symbols: Vec::new(),
+        meta_present: false,
+        meta_cells_matched: 0,
+        meta_cells_missing: 0,
+        meta_issues: Default::default(),
+        gene_set_hash: previous_summary.gene_set_hash.clone(),
+        upstream: None,
+        dense: None,
+        matrix_value_type: None,
+        matrix_orientation: None,
+    };
+    let panels = PanelsContext {
+        panels: PanelSet {
+            skipped: previous_summary.skipped_panel_files,
+            ..PanelSet::default()
+        },
+        mappings: Vec::new(),
+        warnings: previous_summary.panel_warnings,
+        cell_ids,
+        per_cell: Vec::new(),
+        dead_panels: previous_summary.dead_panels,
+    };
+
+    Ok(PreviousRun {
+        source_dir,
+        dataset,
+        expr,
+        axes,
+        scores,
+        panels,
+        mode: previous_summary.mode,
+        run_label: previous_summary.run_label,
+        panel_genes: previous_summary.panel_genes,
+        min_panel_gene_fraction: previous_summary.min_panel_gene_fraction,
+    })
+}
+
+/// Reclassifies `source` with `thresholds` into `out_dir` (species overrides
+/// apply by the `meta_path` species column): writes fresh
+/// `classify.tsv`, `secretion.tsv`, `summary.json` and `report.txt`, carries
+/// the source `panels_report.tsv` over unchanged and records the source run
+/// in `provenance.json` under `reclassified_from`.
+pub fn run_reclassify(
+    source: &Path,
+    out_dir: &Path,
+    thresholds: &ThresholdsConfig,
+    meta_path: Option<&Path>,
+) -> Result<FinalSummary, ReclassifyError> {
+    let prev = load_previous_run(source)?;
+    std::fs::create_dir_all(out_dir)?;
+
+    let cell_thresholds = resolve_cell_thresholds(thresholds, meta_path, &prev.dataset.barcodes)?;
+    let classify = run_stage6_classify_with_cell_thresholds(
+        &prev.dataset,
+        &prev.expr,
+        &prev.axes,
+        &prev.scores,
+        out_dir,
+        &cell_thresholds,
+    )?;
+    let opts = ReportOptions {
+        thresholds: thresholds.global.clone(),
+        run_label: prev.run_label.clone(),
+        panel_gene_universe: prev.panel_genes.clone(),
+        min_panel_gene_fraction: prev.min_panel_gene_fraction,
+        ..ReportOptions::default()
+    };
+    let summary = run_stage7_report_with_options(
+        &prev.dataset,
+        &prev.expr,
+        &prev.axes,
+        &prev.scores,
+        &classify,
+        &prev.panels,
+        out_dir,
+        &prev.mode,
+        RunMode::Standalone,
+        meta_path,
+        &opts,
+    )?;
+
+    for name in ["panels_report.tsv", "mapping_warnings.tsv"] {
+        let source = prev.source_dir.join(name);
+        if source.exists() {
+            std::fs::copy(&source, out_dir.join(name))?;
+        }
+    }
+    write_provenance(&prev.source_dir, out_dir, thresholds, &classify.thresholds)?;
+    Ok(summary)
+}
+
+fn write_provenance(
+    source_dir: &Path,
+    out_dir: &Path,
+    thresholds: &ThresholdsConfig,
+    cell_thresholds: &CellThresholds,
+) -> Result<(), ReclassifyError> {
+    let source_path = source_dir.join("provenance.json");
+    let mut provenance: serde_json::Value = if source_path.exists() {
+        serde_json::from_slice(&std::fs::read(&source_path)?)?
+    } else {
+        json!({})
+    };
+    let absolute = std::fs::canonicalize(source_dir).unwrap_or_else(|_| source_dir.to_path_buf());
+    if let Some(obj) = provenance.as_object_mut() {
+        // The outputs no longer match the source run's fingerprint.
+        obj.remove("fingerprint");
+        obj.insert("thresholds_sets".to_string(), json!(cell_thresholds.sets()));
+        obj.insert(
+            "reclassified_from".to_string(),
+            json!({
+                "source_dir": absolute.display().to_string(),
+                "thresholds": thresholds.global,
+            }),
+        );
+    }
+    write_artifact(
+        out_dir,
+        "provenance.json",
+        serde_json::to_string_pretty(&provenance)?,
+    )?;
+    Ok(())
+}
+
+type AxesColumns = (
+    Vec<String>,
+    Vec<AxisValues>,
+    Vec<AxisCoverage>,
+    Vec<AxisDrivers>,
+);
+
+fn read_axes(path: &Path) -> Result<AxesColumns, ReclassifyError> {
+    let mut reader = TsvReader::open(path)?;
+    let (cell_col, [value_cols, cov_cols, driver_cols]) = column_groups(&reader, &AXES)?;
+
+    let mut cell_ids = Vec::new();
+    let mut values = Vec::new();
+    let mut coverage = Vec::new();
+    let mut drivers = Vec::new();
+    while let Some(row) = reader.next_row()? {
+        let mut v = [0.0f32; 7];
+        let mut c = [0.0f32; 7];
+        for i in 0..AXES.len() {
+            v[i] = row.f32_or_nan(value_cols[i])?;
+            c[i] = row.f32_or_nan(cov_cols[i])?;
+        }
+        let d = |i: usize| row.str(driver_cols[i]).map(str::to_string);
+        cell_ids.push(row.str(cell_col)?.to_string());
+        values.push(AxisValues {
+            sia: v[0],
+            eeb: v[1],
+            sli: v[2],
+            mei: v[3],
+            ecmi: v[4],
+            apci: v[5],
+            gdi: v[6],
+        });
+        coverage.push(AxisCoverage {
+            sia: c[0],
+            eeb: c[1],
+            sli: c[2],
+            mei: c[3],
+            ecmi: c[4],
+            apci: c[5],
+            gdi: c[6],
+        });
+        drivers.push(AxisDrivers {
+            sia: d(0)?,
+            eeb: d(1)?,
+            sli: d(2)?,
+            mei: d(3)?,
+            ecmi: d(4)?,
+            apci: d(5)?,
+            gdi: d(6)?,
+        });
+    }
+    Ok((cell_ids, values, coverage, drivers))
+}
+
+/// Value, `cov_` and `drivers_` column positions for `names`, plus `cell_id`.
+/// Resolved in one lookup so a missing-column error lists every absent column.
+fn column_groups<const N: usize>(
+    reader: &TsvReader,
+    names: &[&str; N],
+) -> Result<(usize, [[usize; N]; 3]), InputError> {
+    let prefixes = ["", "cov_", "drivers_"];
+    let mut required = vec!["cell_id".to_string()];
+    for prefix in prefixes {
+        required.extend(names.iter().map(|name| format!("{prefix}{name}")));
+    }
+    let positions = reader.require(&required)?;
+    let mut groups = [[0usize; N]; 3];
+    for (g, group) in groups.iter_mut().enumerate() {
+        group.copy_from_slice(&positions[1 + g * N..1 + (g + 1) * N]);
+    }
+    Ok((positions[0], groups))
+}
+
+#[derive(Default)]
+struct CompositeColumns {
+    oii: Vec<f32>,
+    iai: Vec<f32>,
+    esi: Vec<f32>,
+    cov_oii: Vec<f32>,
+    cov_iai: Vec<f32>,
+    cov_esi: Vec<f32>,
+    drivers_oii: Vec<String>,
+    drivers_iai: Vec<String>,
+    drivers_esi: Vec<String>,
+    /// NaN in outputs that predate the column.
+    score_concentration: Vec<f32>,
+    /// Read from the first row; absent in outputs that predate the column.
+    iai_weightset: Option<IaiWeightSet>,
+}
+
+fn read_composites(path: &Path, cell_ids: &[String]) -> Result<CompositeColumns, ReclassifyError> {
+    const FILE: &str = "composites.tsv";
+    let mut reader = TsvReader::open(path)?;
+    let (cell_col, [value_cols, cov_cols, driver_cols]) = column_groups(&reader, &COMPOSITES)?;
+    let weightset_col = reader
+        .has_column("iai_weightset")
+        .then(|| reader.column("iai_weightset"))
+        .transpose()?;
+    let concentration_col = reader
+        .has_column("score_concentration")
+        .then(|| reader.column("score_concentration"))
+        .transpose()?;
+
+    let mut out = CompositeColumns::default();
+    let mut n = 0usize;
+    while let Some(row) = reader.next_row()? {
+        check_cell(FILE, cell_ids, n, row.str(cell_col)?, row.line())?;
+        out.oii.push(row.f32_or_nan(value_cols[0])?);
+        out.iai.push(row.f32_or_nan(value_cols[1])?);
+        out.esi.push(row.f32_or_nan(value_cols[2])?);
+        out.cov_oii.push(row.f32_or_nan(cov_cols[0])?);
+        out.cov_iai.push(row.f32_or_nan(cov_cols[1])?);
+        out.cov_esi.push(row.f32_or_nan(cov_cols[2])?);
+        out.drivers_oii.push(row.str(driver_cols[0])?.to_string());
+        out.drivers_iai.push(row.str(driver_cols[1])?.to_string());
+        out.drivers_esi.push(row.str(driver_cols[2])?.to_string());
+        out.score_concentration.push(match concentration_col {
+            Some(col) => row.f32_or_nan(col)?,
+            None => f32::NAN,
+        });
+        if n == 0
+            && let Some(col) = weightset_col
+        {
+            out.iai_weightset = match row.str(col)? {
+                "with_apci" => Some(IaiWeightSet::WithApci),
+                "no_apci" => Some(IaiWeightSet::NoApci),
+                _ => None,
+            };
+        }
+        n += 1;
+    }
+    check_count(FILE, cell_ids, n)?;
+    Ok(out)
+}
+
+fn read_expr_stats(path: &Path, cell_ids: &[String]) -> Result<Vec<CellStats>, ReclassifyError> {
+    const FILE: &str = "expr_stats.tsv";
+    let mut reader = TsvReader::open(path)?;
+    let cols = reader.require(&["cell_id", "libsize", "detected"])?;
+    let (cell_col, libsize_col, detected_col) = (cols[0], cols[1], cols[2]);
+
+    let mut stats = Vec::with_capacity(cell_ids.len());
+    while let Some(row) = reader.next_row()? {
+        check_cell(FILE, cell_ids, stats.len(), row.str(cell_col)?, row.line())?;
+        stats.push(CellStats {
+            libsize: row.u64(libsize_col)?,
+            detected: row.u32(detected_col)?,
+        });
+    }
+    check_count(FILE, cell_ids, stats.len())?;
+    Ok(stats)
+}
+
+fn check_cell(
+    file: &'static str,
+    cell_ids: &[String],
+    idx: usize,
+    found: &str,
+    line: usize,
+) -> Result<(), ReclassifyError> {
+    match cell_ids.get(idx) {
+        Some(expected) if expected == found => Ok(()),
+        Some(expected) => Err(ReclassifyError::CellMismatch {
+            file,
+            line,
+            expected: expected.clone(),
+            found: found.to_string(),
+        }),
+        None => Err(ReclassifyError::CellCount {
+            file,
+            expected: cell_ids.len(),
+            found: idx + 1,
+        }),
+    }
+}
+
+fn check_count(
+    file: &'static str,
+    cell_ids: &[String],
+    found: usize,
+) -> Result<(), ReclassifyError> {
+    if found == cell_ids.len() {
+        Ok(())
+    } else {
+        Err(ReclassifyError::CellCount {
+            file,
+            expected: cell_ids.len(),
+            found,
+        })
+    }
+}
+
+struct PreviousSummary {
+    coverage_mode: CoverageMode,
+    panel_warnings: Vec<MappingWarning>,
+    axis_panels: AxisPanelCounts,
+    skipped_panel_files: Vec<SkippedPanelFile>,
+    dead_panels: Vec<DeadPanel>,
+    dropped_dead_panels: Vec<String>,
+    gene_set_hash: String,
+    mode: String,
+    run_label: Option<RunLabel>,
+    /// `None` for a source summary without `input.panel_genes`.
+    panel_genes: Option<PanelGeneUniverse>,
+    min_panel_gene_fraction: f32,
+}
+
+/// Coverage mode and panel caveats from the source `summary.json`, which
+/// the per-cell tables do not carry. Defaults apply when it is missing.
+fn read_previous_summary(path: &Path) -> Result<PreviousSummary, ReclassifyError> {
+    let mut out = PreviousSummary {
+        coverage_mode: CoverageMode::default(),
+        panel_warnings: Vec::new(),
+        axis_panels: AxisPanelCounts::default(),
+        skipped_panel_files: Vec::new(),
+        dead_panels: Vec::new(),
+        dropped_dead_panels: Vec::new(),
+        gene_set_hash: String::new(),
+        mode: "cell".to_string(),
+        run_label: None,
+        panel_genes: None,
+        min_panel_gene_fraction: DEFAULT_MIN_PANEL_GENE_FRACTION,
+    };
+    if !path.exists() {
+        return Ok(out);
+    }
+    let summary: serde_json::Value = serde_json::from_slice(&std::fs::read(path)?)?;
+    if summary["provenance"]["coverage_mode"].as_str() == Some("detection") {
+        out.coverage_mode = CoverageMode::Detection;
+    }
+    if let Some(entries) = summary["caveats"]["panels_missing_required"].as_array() {
+        out.panel_warnings = entries
+            .iter()
+            .filter_map(|e| {
+                Some(MappingWarning {
+                    panel_id: e["panel_id"].as_str()?.to_string(),
+                    axis: e["axis"].as_str().unwrap_or_default().to_string(),
+                    missing_required: e["missing_required"]
+                        .as_array()?
+                        .iter()
+                        .filter_map(|g| g.as_str().map(str::to_string))
+                        .collect(),
+                    mappable_fraction: e["mappable_fraction"]
+                        .as_f64()
+                        .map_or(f32::NAN, |f| f as f32),
+                })
+            })
+            .collect();
+    }
+    if let Some(counts) = summary["caveats"].get("axis_panels") {
+        out.axis_panels = serde_json::from_value(counts.clone())?;
+    }
+    if let Some(skipped) = summary["caveats"].get("skipped_panel_files") {
+        out.skipped_panel_files = serde_json::from_value(skipped.clone())?;
+    }
+    if let Some(dead) = summary["caveats"].get("dead_panels") {
+        out.dead_panels = serde_json::from_value(dead.clone())?;
+    }
+    if let Some(dropped) = summary["caveats"].get("dropped_dead_panels") {
+        out.dropped_dead_panels = serde_json::from_value(dropped.clone())?;
+    }
+    if let Some(hash) = summary["input"]["gene_set_hash"].as_str() {
+        out.gene_set_hash = hash.to_string();
+    }
+    if let Some(genes) = summary["input"].get("panel_genes") {
+        out.panel_genes = Some(PanelGeneUniverse {
+            overall: serde_json::from_value(genes.clone())?,
+            by_axis: serde_json::from_value(genes["by_axis"].clone())?,
+        });
+        if let Some(min) = genes["min_mapped_fraction"].as_f64() {
+            out.min_panel_gene_fraction = min as f32;
+        }
+    }
+    if let Some(mode) = summary["provenance"]["mode"].as_str() {
+        out.mode = mode.to_string();
+    }
+    if let Some(run) = summary.get("run") {
+        out.run_label = Some(serde_json::from_value(run.clone())?);
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+#[path = "../../tests/src_inline/pipeline/reclassify.rs"]
+mod tests;
@@ -0,0 +1,37 @@
+//! Reductions over cells whose result does not depend on the thread count.
+//!
+//! Cells are split into chunks of [`REDUCE_CHUNK_CELLS`] by index. Each chunk
+//! is folded sequentially, chunks may run on any thread, and the per-chunk
+//! partials are combined one after another in chunk order. Floating-point
+//! sums therefore always add the same numbers in the same order.
+
+use std::ops::Range;
+
+use rayon::prelude::*;
+
+use crate::pipeline::PROGRESS_CHUNK_CELLS;
+
+/// Cells per partial; fixed so chunk boundaries never follow the scheduler.
+pub const REDUCE_CHUNK_CELLS: usize = PROGRESS_CHUNK_CELLS;
+
+/// Folds cells `0..n` chunk by chunk with `fold` (in parallel) and merges the
+/// partials with `combine` in chunk order; `None` when `n` is 0.
+pub fn fold_cell_chunks<P, F, C>(n: usize, fold: F, mut combine: C) -> Option<P>
+where
+    P: Send,
+    F: Fn(Range<usize>) -> P + Sync,
+    C: FnMut(P, P) -> P,
+{
+    let partials: Vec<P> = (0..n.div_ceil(REDUCE_CHUNK_CELLS))
+        .into_par_iter()
+        .map(|chunk| {
+            let start = chunk * REDUCE_CHUNK_CELLS;
+            fold(start..(start + REDUCE_CHUNK_CELLS).min(n))
+        })
+        .collect();
+    partials.into_iter().reduce(&mut combine)
+}
+
+#[cfg(test)]
+#[path = "../../tests/src_inline/pipeline/reduce.rs"]
+mod tests;
@@ -0,0 +1,234 @@
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::report::artifact::write_artifact;
+
+/// Timing and memory sample taken at the end of a stage.
+#[derive(Debug, Clone, Serialize)]
+pub struct StageResources {
+    pub stage: &'static str,
+    /// Offset of the stage start from run start.
+    pub start_ms: u64,
+    pub elapsed_ms: u64,
+    /// Resident set size when the stage started; `rss_bytes - start_rss_bytes`
+    /// is what the stage kept resident.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_rss_bytes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rss_bytes: Option<u64>,
+    /// Process high-water mark observed at the end of the stage.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub peak_rss_bytes: Option<u64>,
+    /// Cells processed per second, for stages that go cell by cell.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cells_per_sec: Option<f64>,
+    /// Matrix entries parsed or accumulated per second.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nnz_per_sec: Option<f64>,
+}
+
+/// What a stage worked through; each count given becomes a `*_per_sec`
+/// rate in [`StageResources`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StageWork {
+    pub cells: Option<usize>,
+    pub nnz: Option<usize>,
+}
+
+impl StageWork {
+    pub fn cells(cells: usize) -> Self {
+        Self {
+            cells: Some(cells),
+            nnz: None,
+        }
+    }
+
+    pub fn nnz(nnz: usize) -> Self {
+        Self {
+            cells: None,
+            nnz: Some(nnz),
+        }
+    }
+}
+
+/// `count` items over `elapsed`; `None` when no time was measured.
+pub fn per_sec(count: usize, elapsed: Duration) -> Option<f64> {
+    let secs = elapsed.as_secs_f64();
+    (secs > 0.0).then(|| count as f64 / secs)
+}
+
+#[derive(Debug, Clone)]
+pub struct ResourceTracker {
+    run_start: Instant,
+    stages: Vec<StageResources>,
+    /// Memory sampled by the last [`ResourceTracker::begin`].
+    pending_start: MemorySample,
+}
+
+impl Default for ResourceTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ResourceTracker {
+    pub fn new() -> Self {
+        Self {
+            run_start: Instant::now(),
+            stages: Vec::with_capacity(7),
+            pending_start: MemorySample::default(),
+        }
+    }
+
+    /// Marks a stage start: samples memory for the next [`record`] and
+    /// returns the start time to pass to it.
+    ///
+    /// [`record`]: ResourceTracker::record
+    pub fn begin(&mut self) -> Instant {
+        self.pending_start = sample_memory();
+        Instant::now()
+    }
+
+    /// Records a stage that began at `started` and went through `work`;
+    /// samples memory now. Returns the record for the stage's log line.
+    pub fn record(
+        &mut self,
+        stage: &'static str,
+        started: Instant,
+        work: StageWork,
+    ) -> &StageResources {
+        let mem = sample_memory();
+        let start = std::mem::take(&mut self.pending_start);
+        let elapsed = started.elapsed();
+        self.stages.push(StageResources {
+            stage,
+            start_ms: started
+                .saturating_duration_since(self.run_start)
+                .as_millis() as u64,
+            elapsed_ms: elapsed.as_millis() as u64,
+            start_rss_bytes: start.rss_bytes,
+            rss_bytes: mem.rss_bytes,
+            peak_rss_bytes: mem.peak_rss_bytes,
+            cells_per_sec: work.cells.and_then(|n| per_sec(n, elapsed)),
+            nnz_per_sec: work.nnz.and_then(|n| per_sec(n, elapsed)),
+        });
+        &self.stages[self.stages.len() - 1]
+    }
+
+    pub fn stages(&self) -> &[StageResources] {
+        &self.stages
+    }
+
+    pub fn to_json(&self) -> Value {
+        let mut obj = serde_json::Map::new();
+        obj.insert(
+            "total_elapsed_ms".to_string(),
+            Value::from(self.run_start.elapsed().as_millis() as u64),
+        );
+        if let Some(peak) = self.stages.iter().filter_map(|s| s.peak_rss_bytes).max() {
+            obj.insert("peak_rss_bytes".to_string(), Value::from(peak));
+        }
+        obj.insert(
+            "stages".to_string(),
+            serde_json::to_value(&self.stages).unwrap_or(Value::Null),
+        );
+        Value::Object(obj)
+    }
+}
+
+/// Adds the tracker as a `resources` key to an existing `pipeline_step.json`.
+pub fn write_resources_to_pipeline_step(
+    out_dir: &Path,
+    tracker: &ResourceTracker,
+) -> Result<(), std::io::Error> {
+    let path = out_dir.join("pipeline_step.json");
+    let mut step: Value = serde_json::from_slice(&std::fs::read(&path)?)?;
+    if let Value::Object(map) = &mut step {
+        map.insert("resources".to_string(), tracker.to_json());
+    }
+    write_artifact(
+        out_dir,
+        "pipeline_step.json",
+        serde_json::to_string_pretty(&step)?,
+    )?;
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MemorySample {
+    pub rss_bytes: Option<u64>,
+    pub peak_rss_bytes: Option<u64>,
+}
+
+/// Best-effort process memory sample; fields are `None` where unsupported.
+#[cfg(target_os = "linux")]
+pub fn sample_memory() -> MemorySample {
+    let Ok(status) = std::fs::read_to_string("/proc/self/status") else {
+        return MemorySample::default();
+    };
+    let field_kb = |name: &str| {
+        status
+            .lines()
+            .find_map(|l| l.strip_prefix(name))
+            .and_then(|rest| {
+                rest.trim()
+                    .trim_end_matches("kB")
+                    .trim()
+                    .parse::<u64>()
+                    .ok()
+            })
+            .map(|kb| kb * 1024)
+    };
+    MemorySample {
+        rss_bytes: field_kb("VmRSS:"),
+        peak_rss_bytes: field_kb("VmHWM:"),
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub fn sample_memory() -> MemorySample {
+    #[repr(C)]
+    struct Timeval {
+        tv_sec: i64,
+        tv_usec: i32,
+    }
+
+    #[repr(C)]
+    struct Rusage {
+        ru_utime: Timeval,
+        ru_stime: Timeval,
+        ru_maxrss: i64,
+        ru_rest: [i64; 13],
+    }
+
+    unsafe extern "C" {
+        fn getrusage(who: i32, usage: *mut Rusage) -> i32;
+    }
+
+    const RUSAGE_SELF: i32 = 0;
+    let mut usage = std::mem::MaybeUninit::<Rusage>::zeroed();
+    // SAFETY: getrusage fills the provided struct, whose layout matches Darwin's `struct rusage`.
+    let rc = unsafe { getrusage(RUSAGE_SELF, usage.as_mut_ptr()) };
+    if rc != 0 {
+        return MemorySample::default();
+    }
+    // SAFETY: initialized by the successful call above.
+    let usage = unsafe { usage.assume_init() };
+    MemorySample {
+        rss_bytes: None,
+        // Darwin reports ru_maxrss in bytes.
+        peak_rss_bytes: u64::try_from(usage.ru_maxrss).ok(),
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+pub fn sample_memory() -> MemorySample {
+    MemorySample::default()
+}
+
+#[cfg(test)]
+#[path = "../../tests/src_inline/pipeline/resources.rs"]
+mod tests;
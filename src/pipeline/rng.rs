@@ -0,0 +1,137 @@
+//! Run-wide seed plumbing for stochastic features.
+//!
+//! One master seed per run (`--seed`, default [`DEFAULT_SEED`]). Each
+//! stochastic component asks [`RunRng::child`] for its own seed by purpose
+//! name instead of taking a flag of its own:
+//!
+//! ```text
+//! child(purpose) = splitmix64(master XOR fnv1a64(purpose))
+//! ```
+//!
+//! Derived seeds are remembered and written with the master seed to
+//! `provenance.json`, so any stochastic artifact can be reproduced.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+
+/// Master seed when `--seed` is not given (ASCII "kiraSECR"); never time-based.
+pub const DEFAULT_SEED: u64 = 0x6B69_7261_5345_4352;
+
+/// Description of the derivation, recorded next to the seeds.
+pub const SEED_SCHEME: &str = "splitmix64(master ^ fnv1a64(purpose))";
+
+#[derive(Debug, Clone)]
+pub struct RunRng {
+    master: u64,
+    derived: Arc<Mutex<BTreeMap<String, u64>>>,
+}
+
+impl Default for RunRng {
+    fn default() -> Self {
+        Self::new(DEFAULT_SEED)
+    }
+}
+
+impl RunRng {
+    pub fn new(master: u64) -> Self {
+        Self {
+            master,
+            derived: Arc::default(),
+        }
+    }
+
+    pub fn master_seed(&self) -> u64 {
+        self.master
+    }
+
+    /// Seed for one stochastic purpose (e.g. `"subsample"`); recorded for provenance.
+    pub fn child(&self, purpose: &str) -> u64 {
+        let seed = derive_seed(self.master, purpose);
+        self.derived
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(purpose.to_string(), seed);
+        seed
+    }
+
+    /// Generator seeded with [`RunRng::child`] for `purpose`.
+    pub fn stream(&self, purpose: &str) -> SplitMix64 {
+        SplitMix64::new(self.child(purpose))
+    }
+
+    pub fn provenance(&self) -> RngProvenance {
+        RngProvenance {
+            master_seed: self.master,
+            scheme: SEED_SCHEME,
+            derived: self
+                .derived
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .clone(),
+        }
+    }
+}
+
+/// `rng` section of `provenance.json`.
+#[derive(Debug, Clone, Serialize)]
+pub struct RngProvenance {
+    pub master_seed: u64,
+    pub scheme: &'static str,
+    /// Purpose name to derived seed, for every purpose used in the run.
+    pub derived: BTreeMap<String, u64>,
+}
+
+pub fn derive_seed(master: u64, purpose: &str) -> u64 {
+    splitmix64(master ^ fnv1a64(purpose.as_bytes()))
+}
+
+fn fnv1a64(bytes: &[u8]) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325u64;
+    for b in bytes {
+        hash ^= u64::from(*b);
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Small deterministic generator for stochastic components; the same seed
+/// yields the same sequence on every platform.
+#[derive(Debug, Clone)]
+pub struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let out = splitmix64(self.state);
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        out
+    }
+
+    /// Uniform in `[0, 1)`.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Uniform in `[0, n)`; `n` must be non-zero.
+    pub fn below(&mut self, n: u64) -> u64 {
+        ((u128::from(self.next_u64()) * u128::from(n)) >> 64) as u64
+    }
+}
+
+#[cfg(test)]
+#[path = "../../tests/src_inline/pipeline/rng.rs"]
+mod tests;
@@ -0,0 +1,583 @@
+//! Time-boxed pipeline runs for embedding callers (notebooks, services) that
+//! must hand control back at regular intervals.
+//!
+//! A [`Runner`] runs stages 1-7 on a worker thread that only makes progress
+//! while [`Runner::step`] is waiting on it. Once the step's budget is spent
+//! the worker pauses at the next [`cancel`] check: between stages, every
+//! [`CANCEL_CHECK_CELLS`](cancel::CANCEL_CHECK_CELLS) cells in the per-cell
+//! loops, and every [`CANCEL_CHECK_ENTRIES`](cancel::CANCEL_CHECK_ENTRIES)
+//! entries while stage 2 reads the matrix. Pausing never changes what is computed, so the artifacts are
+//! byte-identical to a run stepped with any other budget.
+//!
+//! The budget is a target, not a hard limit: a step overruns by up to one
+//! checkpoint interval, or by the whole of a piece of work without checkpoints
+//! (stage 1 loading, stage 2 sorting the matrix entries, stage 7 writing
+//! reports).
+//! Stage 7 builds its rows in batches of
+//! [`PROGRESS_CHUNK_CELLS`](crate::pipeline::PROGRESS_CHUNK_CELLS) and checks
+//! only between them.
+//!
+//! The runner uses the library defaults of every stage, like the standalone
+//! `run` without options. CLI-only outputs (`progress.json`, `_SUCCESS`,
+//! resource tracking, cohort rows) are not written.
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::expr::normalize::Normalization;
+use crate::model::axes::{AxisConfig, AxisValues};
+use crate::model::regimes::Regime;
+use crate::panels::defs::PanelSet;
+use crate::pipeline::cancel::{self, CANCEL_CHECK_CELLS, CancellationToken, Cancelled};
+use crate::pipeline::progress::{self, ProgressCounters, ProgressSnapshot, RunStatus};
+use crate::pipeline::stage1_load::{RunMode, Stage1Error, run_stage1};
+use crate::pipeline::stage2_normalize::{Stage2Error, run_stage2};
+use crate::pipeline::stage3_panels::{Stage3Error, run_stage3_panels};
+use crate::pipeline::stage4_axes::{Stage4Error, run_stage4_axes_with_config};
+use crate::pipeline::stage5_scores::{Stage5Error, run_stage5_scores};
+use crate::pipeline::stage6_classify::{Stage6Error, run_stage6_classify};
+use crate::pipeline::stage7_report::{
+    FinalSummary, Quantiles, Stage7Error, run_stage7_report, stats,
+};
+
+#[derive(Debug, Error)]
+pub enum RunnerError {
+    #[error("stage1 error: {0}")]
+    Stage1(#[from] Stage1Error),
+    #[error("stage2 error: {0}")]
+    Stage2(#[from] Stage2Error),
+    #[error("stage3 error: {0}")]
+    Stage3(#[from] Stage3Error),
+    #[error("stage4 error: {0}")]
+    Stage4(#[from] Stage4Error),
+    #[error("stage5 error: {0}")]
+    Stage5(#[from] Stage5Error),
+    #[error("stage6 error: {0}")]
+    Stage6(#[from] Stage6Error),
+    #[error("stage7 error: {0}")]
+    Stage7(#[from] Stage7Error),
+    #[error(transparent)]
+    Cancelled(#[from] Cancelled),
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not start the pipeline worker thread: {0}")]
+    Spawn(std::io::Error),
+    #[error("the pipeline worker panicked")]
+    Panicked,
+    #[error("the run already failed; its error was returned by an earlier step")]
+    Stopped,
+}
+
+/// What a [`Runner`] processes and where it writes.
+#[derive(Debug, Clone)]
+pub struct RunnerConfig {
+    /// 10x directory, MTX triplet or shared cache, as for `run --input`.
+    pub input: PathBuf,
+    pub out_dir: PathBuf,
+    pub panels: PanelSet,
+    pub meta: Option<PathBuf>,
+    /// Stage4 options, as `run`'s axis flags set them. The default fails on
+    /// core axes whose panels map no gene; set `allow_missing_axes` to mark
+    /// them not present instead.
+    pub axes: AxisConfig,
+}
+
+impl RunnerConfig {
+    pub fn new(input: impl Into<PathBuf>, out_dir: impl Into<PathBuf>, panels: PanelSet) -> Self {
+        Self {
+            input: input.into(),
+            out_dir: out_dir.into(),
+            panels,
+            meta: None,
+            axes: AxisConfig::default(),
+        }
+    }
+}
+
+/// Outcome of one [`Runner::step`].
+#[derive(Debug, Clone)]
+pub enum StepStatus {
+    /// The budget ran out; the snapshot says which stage and cell the run
+    /// paused at.
+    Paused(ProgressSnapshot),
+    /// Every stage is done; the artifacts are in the output directory.
+    Finished(Box<FinalSummary>),
+}
+
+/// Dataset-level figures known so far.
+///
+/// While stages 4-6 run, their figures cover the cells done up to the last
+/// [`CANCEL_CHECK_CELLS`] boundary: quantiles come from a [`QuantileSketch`]
+/// and regime counts are running totals. When a stage finishes its figures
+/// are replaced by exact ones over every cell; those quantiles use the same
+/// clamped values and interpolation as `summary.json`, so they equal its
+/// `distributions` entries.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PartialSummary {
+    /// Set after stage 1.
+    pub n_cells: Option<usize>,
+    /// Running during stage 4, exact after it.
+    pub er_golgi_pressure: Option<Quantiles>,
+    /// Running during stage 4, exact after it.
+    pub stress_secretion_index: Option<Quantiles>,
+    /// Running during stage 5, exact after it.
+    pub secretory_load: Option<Quantiles>,
+    /// Stage 6 regime counts (the `classify.tsv` labels, before stage 7
+    /// folds them into the `secretion.tsv` regimes); running during stage 6,
+    /// empty before it.
+    pub classify_regimes: BTreeMap<String, usize>,
+}
+
+/// Bins of a [`QuantileSketch`] over [0, 1].
+pub const SKETCH_BINS: usize = 1024;
+
+/// Fixed-bin histogram of values clamped to [0, 1], giving running quantiles
+/// within about one bin width (`1 / SKETCH_BINS`) of the exact ones. NaN is
+/// skipped, as in `summary.json`.
+#[derive(Debug, Clone)]
+pub struct QuantileSketch {
+    bins: Vec<u64>,
+    n: u64,
+}
+
+impl Default for QuantileSketch {
+    fn default() -> Self {
+        Self {
+            bins: vec![0; SKETCH_BINS],
+            n: 0,
+        }
+    }
+}
+
+impl QuantileSketch {
+    pub fn push(&mut self, value: f32) {
+        if value.is_nan() {
+            return;
+        }
+        let bin = (value.clamp(0.0, 1.0) * SKETCH_BINS as f32) as usize;
+        self.bins[bin.min(SKETCH_BINS - 1)] += 1;
+        self.n += 1;
+    }
+
+    /// Values counted so far (NaN excluded).
+    pub fn len(&self) -> u64 {
+        self.n
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    /// Estimated median, p90 and p99; NaN while empty.
+    pub fn quantiles(&self) -> Quantiles {
+        Quantiles {
+            median: self.quantile(0.5),
+            p90: self.quantile(0.9),
+            p99: self.quantile(0.99),
+        }
+    }
+
+    /// The value at rank `q * (n - 1)`, taking each bin's values as spread
+    /// evenly across it.
+    fn quantile(&self, q: f64) -> f32 {
+        if self.n == 0 {
+            return f32::NAN;
+        }
+        let rank = q * (self.n - 1) as f64;
+        let mut below = 0u64;
+        for (bin, &count) in self.bins.iter().enumerate() {
+            if count > 0 && (below + count) as f64 > rank {
+                let within = ((rank - below as f64 + 0.5) / count as f64).min(1.0);
+                return ((bin as f64 + within) / SKETCH_BINS as f64) as f32;
+            }
+            below += count;
+        }
+        1.0
+    }
+}
+
+/// Per-cell results a stage has produced so far, in cell order.
+pub(crate) enum StageCells<'a> {
+    /// Stage 4's axis values.
+    Axes(&'a [AxisValues]),
+    /// Stage 5's OII values (`secretory_load`).
+    SecretoryLoad(&'a [f32]),
+    /// Stage 6's regimes.
+    Regimes(&'a [Regime]),
+}
+
+/// Running figures of the worker's stages, published to the shared
+/// [`PartialSummary`] each time a stage feeds them.
+struct Live {
+    partial: Arc<Mutex<PartialSummary>>,
+    axes_fed: usize,
+    er_golgi: QuantileSketch,
+    stress: QuantileSketch,
+    scores_fed: usize,
+    secretory: QuantileSketch,
+    regimes_fed: usize,
+    regimes: BTreeMap<String, usize>,
+}
+
+impl Live {
+    fn new(partial: Arc<Mutex<PartialSummary>>) -> Self {
+        Self {
+            partial,
+            axes_fed: 0,
+            er_golgi: QuantileSketch::default(),
+            stress: QuantileSketch::default(),
+            scores_fed: 0,
+            secretory: QuantileSketch::default(),
+            regimes_fed: 0,
+            regimes: BTreeMap::new(),
+        }
+    }
+
+    fn feed(&mut self, cells: StageCells<'_>) {
+        let mut partial = self.partial.lock().unwrap_or_else(|e| e.into_inner());
+        match cells {
+            StageCells::Axes(values) => {
+                for v in &values[self.axes_fed..] {
+                    self.er_golgi.push(v.sia);
+                    self.stress.push(v.gdi);
+                }
+                self.axes_fed = values.len();
+                partial.er_golgi_pressure = Some(self.er_golgi.quantiles());
+                partial.stress_secretion_index = Some(self.stress.quantiles());
+            }
+            StageCells::SecretoryLoad(values) => {
+                for &v in &values[self.scores_fed..] {
+                    self.secretory.push(v);
+                }
+                self.scores_fed = values.len();
+                partial.secretory_load = Some(self.secretory.quantiles());
+            }
+            StageCells::Regimes(regimes) => {
+                for regime in &regimes[self.regimes_fed..] {
+                    *self.regimes.entry(regime.as_str().to_string()).or_default() += 1;
+                }
+                self.regimes_fed = regimes.len();
+                partial.classify_regimes = self.regimes.clone();
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct GateState {
+    /// Whether the worker may run; cleared when it pauses or ends.
+    running: bool,
+    deadline: Option<Instant>,
+    /// Set on drop: the worker stops pausing so it can reach a cancel check.
+    released: bool,
+    outcome: Option<Result<Box<FinalSummary>, RunnerError>>,
+}
+
+/// Hand-off between [`Runner::step`] and the worker thread.
+#[derive(Default)]
+struct Gate {
+    state: Mutex<GateState>,
+    turn: Condvar,
+}
+
+impl Gate {
+    fn lock(&self) -> MutexGuard<'_, GateState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Worker side: blocks until a step lets the worker run, if the current
+    /// step's budget is spent.
+    fn pause_if_due(&self) {
+        let mut state = self.lock();
+        if state.released || state.deadline.is_some_and(|d| Instant::now() < d) {
+            return;
+        }
+        state.running = false;
+        self.turn.notify_all();
+        while !state.running && !state.released {
+            state = self.turn.wait(state).unwrap_or_else(|e| e.into_inner());
+        }
+    }
+
+    fn finish(&self, outcome: Result<Box<FinalSummary>, RunnerError>) {
+        let mut state = self.lock();
+        state.outcome = Some(outcome);
+        state.running = false;
+        self.turn.notify_all();
+    }
+}
+
+thread_local! {
+    static GATE: RefCell<Option<Arc<Gate>>> = const { RefCell::new(None) };
+    static LIVE: RefCell<Option<Live>> = const { RefCell::new(None) };
+}
+
+/// Pauses the runner worker when called on it and its step budget is spent;
+/// a no-op on every other thread. Called from [`cancel::check`].
+pub(crate) fn pause_if_due() {
+    let gate = GATE.with(|g| g.borrow().clone());
+    if let Some(gate) = gate {
+        gate.pause_if_due();
+    }
+}
+
+/// Updates the runner's running [`PartialSummary`] with the cells a stage
+/// added since its last call. Stages call it before each checkpoint; it only
+/// acts on the runner worker, at [`CANCEL_CHECK_CELLS`] boundaries.
+pub(crate) fn observe(cells: StageCells<'_>) {
+    let done = match &cells {
+        StageCells::Axes(values) => values.len(),
+        StageCells::SecretoryLoad(values) => values.len(),
+        StageCells::Regimes(regimes) => regimes.len(),
+    };
+    if !done.is_multiple_of(CANCEL_CHECK_CELLS) {
+        return;
+    }
+    LIVE.with(|live| {
+        if let Some(live) = live.borrow_mut().as_mut() {
+            live.feed(cells);
+        }
+    });
+}
+
+/// A pipeline run advanced in time-boxed steps; see the module docs.
+///
+/// Dropping an unfinished runner cancels the run and waits for the worker to
+/// reach its next cancel check.
+pub struct Runner {
+    gate: Arc<Gate>,
+    counters: Arc<ProgressCounters>,
+    partial: Arc<Mutex<PartialSummary>>,
+    token: CancellationToken,
+    worker: Option<JoinHandle<()>>,
+    finished: Option<Box<FinalSummary>>,
+    failed: bool,
+}
+
+impl Runner {
+    /// Starts the worker; no stage runs before the first [`step`](Self::step).
+    pub fn start(config: RunnerConfig) -> Result<Self, RunnerError> {
+        let gate = Arc::new(Gate::default());
+        let counters = Arc::new(ProgressCounters::new());
+        let partial = Arc::new(Mutex::new(PartialSummary::default()));
+        let token = CancellationToken::new();
+
+        let worker = {
+            let gate = Arc::clone(&gate);
+            let counters = Arc::clone(&counters);
+            let partial = Arc::clone(&partial);
+            let token = token.clone();
+            std::thread::Builder::new()
+                .name("kira-secretion-runner".to_string())
+                .spawn(move || {
+                    GATE.with(|g| *g.borrow_mut() = Some(Arc::clone(&gate)));
+                    LIVE.with(|l| *l.borrow_mut() = Some(Live::new(Arc::clone(&partial))));
+                    let _cancel = cancel::enter(&token);
+                    let _progress = progress::enter(&counters);
+                    let outcome = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                        run_stages(&config, &partial)
+                    }))
+                    .unwrap_or(Err(RunnerError::Panicked));
+                    gate.finish(outcome);
+                })
+                .map_err(RunnerError::Spawn)?
+        };
+        Ok(Self {
+            gate,
+            counters,
+            partial,
+            token,
+            worker: Some(worker),
+            finished: None,
+            failed: false,
+        })
+    }
+
+    /// Lets the run proceed for about `budget`, then returns where it is.
+    ///
+    /// A zero budget still advances the run to its next pause point. After
+    /// the run finishes every call returns the same
+    /// [`Finished`](StepStatus::Finished); after it fails, the first call
+    /// returns the stage error and later ones [`RunnerError::Stopped`].
+    pub fn step(&mut self, budget: Duration) -> Result<StepStatus, RunnerError> {
+        if let Some(summary) = &self.finished {
+            return Ok(StepStatus::Finished(summary.clone()));
+        }
+        if self.failed {
+            return Err(RunnerError::Stopped);
+        }
+
+        let mut state = self.gate.lock();
+        state.deadline = Some(Instant::now() + budget);
+        state.running = true;
+        self.gate.turn.notify_all();
+        while state.running {
+            state = self
+                .gate
+                .turn
+                .wait(state)
+                .unwrap_or_else(|e| e.into_inner());
+        }
+        let outcome = state.outcome.take();
+        drop(state);
+
+        match outcome {
+            None => Ok(StepStatus::Paused(
+                self.counters.snapshot(RunStatus::Running),
+            )),
+            Some(Ok(summary)) => {
+                self.join_worker();
+                self.finished = Some(summary.clone());
+                Ok(StepStatus::Finished(summary))
+            }
+            Some(Err(error)) => {
+                self.join_worker();
+                self.failed = true;
+                Err(error)
+            }
+        }
+    }
+
+    /// Steps with `budget` until the run finishes.
+    pub fn run_to_end(&mut self, budget: Duration) -> Result<Box<FinalSummary>, RunnerError> {
+        loop {
+            if let StepStatus::Finished(summary) = self.step(budget)? {
+                return Ok(summary);
+            }
+        }
+    }
+
+    /// Dataset-level figures so far: exact for finished stages, running for
+    /// the one in progress.
+    pub fn partial(&self) -> PartialSummary {
+        self.partial
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Current stage and cell counters, without advancing the run.
+    pub fn progress(&self) -> ProgressSnapshot {
+        let status = if self.finished.is_some() {
+            RunStatus::Complete
+        } else {
+            RunStatus::Running
+        };
+        self.counters.snapshot(status)
+    }
+
+    fn join_worker(&mut self) {
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl Drop for Runner {
+    fn drop(&mut self) {
+        if self.worker.is_none() {
+            return;
+        }
+        self.token.cancel();
+        {
+            let mut state = self.gate.lock();
+            state.released = true;
+            self.gate.turn.notify_all();
+        }
+        self.join_worker();
+    }
+}
+
+/// Stages 1-7 with their defaults. The cancel checks before each stage are
+/// also its pause points.
+fn run_stages(
+    config: &RunnerConfig,
+    partial: &Mutex<PartialSummary>,
+) -> Result<Box<FinalSummary>, RunnerError> {
+    let out = config.out_dir.as_path();
+    let update = |f: &dyn Fn(&mut PartialSummary)| {
+        f(&mut partial.lock().unwrap_or_else(|e| e.into_inner()));
+    };
+
+    cancel::check()?;
+    std::fs::create_dir_all(out)?;
+    progress::start_stage("stage1_load");
+    let dataset = run_stage1(
+        &config.input,
+        config.meta.as_deref(),
+        out,
+        false,
+        RunMode::Standalone,
+        None,
+    )?;
+    update(&|p| p.n_cells = Some(dataset.barcodes.len()));
+    cancel::check()?;
+
+    progress::start_stage("stage2_normalize");
+    let expr = run_stage2(&dataset, out, Normalization::default(), false)?;
+    cancel::check()?;
+
+    progress::start_stage("stage3_panels");
+    let panels = run_stage3_panels(
+        &expr,
+        &config.panels,
+        &dataset.gene_index,
+        &dataset.barcodes,
+        out,
+    )?;
+    cancel::check()?;
+
+    progress::start_stage("stage4_axes");
+    let axes = run_stage4_axes_with_config(&dataset, &panels, out, &config.axes)?;
+    let er_golgi: Vec<f32> = axes.values.iter().map(|v| v.sia.clamp(0.0, 1.0)).collect();
+    let stress: Vec<f32> = axes.values.iter().map(|v| v.gdi.clamp(0.0, 1.0)).collect();
+    update(&|p| {
+        p.er_golgi_pressure = Some(stats(&er_golgi));
+        p.stress_secretion_index = Some(stats(&stress));
+    });
+    cancel::check()?;
+
+    progress::start_stage("stage5_scores");
+    let scores = run_stage5_scores(&axes, out)?;
+    let secretory: Vec<f32> = scores.oii.iter().map(|v| v.clamp(0.0, 1.0)).collect();
+    update(&|p| p.secretory_load = Some(stats(&secretory)));
+    cancel::check()?;
+
+    progress::start_stage("stage6_classify");
+    let classify = run_stage6_classify(&dataset, &expr, &axes, &scores, out)?;
+    update(&|p| {
+        p.classify_regimes = classify
+            .summary
+            .counts
+            .iter()
+            .map(|(regime, n)| (regime.as_str().to_string(), *n))
+            .collect();
+    });
+    cancel::check()?;
+
+    progress::start_stage("stage7_report");
+    let summary = run_stage7_report(
+        &dataset,
+        &expr,
+        &axes,
+        &scores,
+        &classify,
+        &panels,
+        out,
+        "cell",
+        RunMode::Standalone,
+        config.meta.as_deref(),
+    )?;
+    Ok(Box::new(summary))
+}
+
+#[cfg(test)]
+#[path = "../../tests/src_inline/pipeline/runner.rs"]
+mod tests;
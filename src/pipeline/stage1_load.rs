@@ -1,18 +1,25 @@
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use thiserror::Error;
-use tracing::warn;
+use tracing::{info, warn};
 
 use crate::input::InputError;
-use crate::input::barcodes::read_barcodes;
-use crate::input::cache::read_shared_cache_metadata;
+use crate::input::barcodes::{BarcodesFile, read_barcodes};
+use crate::input::cache::{CacheIdentity, CscCheck, SharedCacheMapped, map_shared_cache};
+use crate::input::delimiter::Delimiter;
+use crate::input::dense::{DenseMatrix, read_dense_matrix};
 use crate::input::detect::{
-    TenXFormat, TenXLayout, detect_10x_dir, detect_prefix, find_shared_cache_file,
-    resolve_shared_cache_file_name,
+    TenXFormat, TenXLayout, detect_10x_dir, detect_prefix, find_shared_cache_file, is_h5ad,
+    resolve_cache_path, resolve_input_dir, resolve_shared_cache_file_name,
 };
-use crate::input::features::{DuplicateGene, FeatureRow, build_gene_index, read_features};
-use crate::input::meta::read_meta;
-use crate::input::mtx::{count_nnz_lines, read_header};
+use crate::input::features::{
+    DuplicateGene, FeatureRow, FeaturesFile, build_gene_index, read_features,
+};
+use crate::input::h5ad::{H5adLayer, read_h5ad};
+use crate::input::meta::{META_EXAMPLE_COUNT, MetaIssueCounts, MetaStats, read_meta};
+use crate::input::mtx::{MatrixHeader, MtxField, MtxOrientation, count_nnz_lines, read_header};
+use crate::input::upstream::{UpstreamColumns, read_upstream_columns, upstream_sidecar_path};
 
 #[derive(Debug, Error)]
 pub enum Stage1Error {
@@ -20,15 +27,90 @@ pub enum Stage1Error {
     Input(#[from] InputError),
     #[error("cache error: {0}")]
     Cache(#[from] crate::input::cache::CacheError),
-    #[error("matrix dimensions do not match features/barcodes")]
+    #[error(
+        "matrix dimensions do not match features/barcodes: matrix is {found_rows} x {found_cols}, features/barcodes give {expected_rows} x {expected_cols}{hint}"
+    )]
     DimensionMismatch {
         expected_rows: usize,
         expected_cols: usize,
         found_rows: usize,
         found_cols: usize,
+        /// Points at a barcodes header line when that explains an
+        /// off-by-one column count; empty otherwise.
+        hint: String,
     },
     #[error("nnz line count mismatch: expected {expected}, found {found}")]
     NnzMismatch { expected: usize, found: usize },
+    #[error(
+        "only {matched} of {n_cells} barcodes ({fraction:.3}) matched a meta cell_id, below --min-meta-match-frac {threshold}; barcodes look like [{barcode_examples}], meta cell_ids look like [{meta_examples}]"
+    )]
+    MetaMismatch {
+        matched: usize,
+        n_cells: usize,
+        fraction: f32,
+        threshold: f32,
+        barcode_examples: String,
+        meta_examples: String,
+    },
+    #[error("meta file has {count} problem rows (--meta-strict): {issues}")]
+    MetaStrict { count: usize, issues: String },
+}
+
+/// Default for [`Stage1Options::min_meta_match_frac`].
+pub const DEFAULT_MIN_META_MATCH_FRAC: f32 = 0.5;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Stage1Options {
+    /// Minimum fraction of barcodes that must match a meta `cell_id` when meta
+    /// is provided; below it stage1 fails.
+    pub min_meta_match_frac: f32,
+    /// Fail on unknown species values, short rows and duplicate cell_ids in
+    /// meta instead of counting them.
+    pub meta_strict: bool,
+    /// Whether barcodes.tsv starts with a header line; `None` detects it.
+    pub barcodes_has_header: Option<bool>,
+    /// Take a lone features value as both id and symbol
+    /// (`--features-single-column`) instead of failing.
+    pub features_single_column: bool,
+    /// Read the shared cache's per-cell sidecar (`--no-upstream-columns`
+    /// turns it off).
+    pub upstream_columns: bool,
+    /// How much of the shared cache's CSC structure is checked
+    /// (`--verify-cache`).
+    pub cache_check: CscCheck,
+    /// Layout of `--input`.
+    pub input_format: InputFormat,
+    /// Read the MTX as cells x genes (`--transpose-matrix`).
+    pub transpose_matrix: bool,
+    /// Matrix of an `.h5ad` input that holds the counts (`--layer`).
+    pub h5ad_layer: H5adLayer,
+}
+
+impl Default for Stage1Options {
+    fn default() -> Self {
+        Self {
+            min_meta_match_frac: DEFAULT_MIN_META_MATCH_FRAC,
+            meta_strict: false,
+            barcodes_has_header: None,
+            features_single_column: false,
+            upstream_columns: true,
+            cache_check: CscCheck::Full,
+            input_format: InputFormat::TenX,
+            transpose_matrix: false,
+            h5ad_layer: H5adLayer::X,
+        }
+    }
+}
+
+/// What `--input` points at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InputFormat {
+    /// A 10x directory (or its matrix file), or the shared cache in pipeline
+    /// mode.
+    #[default]
+    TenX,
+    /// A dense genes × cells count table, see [`crate::input::dense`].
+    Dense,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -43,8 +125,22 @@ pub struct DatasetCtx {
     pub matrix_path: PathBuf,
     pub features_path: PathBuf,
     pub barcodes_path: PathBuf,
+    /// Column delimiters sniffed from the features and barcodes files;
+    /// `Tab` when they were not read (shared cache input).
+    pub features_delimiter: Delimiter,
+    pub barcodes_delimiter: Delimiter,
+    /// First line of barcodes.tsv skipped as a header.
+    pub barcodes_header: Option<String>,
+    /// Features rows read with `--features-single-column`; 0 otherwise.
+    pub features_single_column_rows: usize,
     pub shared_cache_path: Option<PathBuf>,
     pub resolved_shared_cache_path: Option<PathBuf>,
+    /// The shared cache as validated here; stage2 reads this mapping rather
+    /// than reopening `shared_cache_path`.
+    pub shared_cache: Option<SharedCacheMapped>,
+    /// File identity of `shared_cache`, checked by stage2 if it has to
+    /// reopen the path (the mapping is not carried along).
+    pub shared_cache_identity: Option<CacheIdentity>,
     pub gene_index: crate::input::features::GeneIndex,
     pub barcodes: Vec<String>,
     pub n_genes: usize,
@@ -55,6 +151,30 @@ pub struct DatasetCtx {
     pub meta_present: bool,
     pub meta_cells_matched: usize,
     pub meta_cells_missing: usize,
+    /// Lenient-mode meta problems; all zero without meta.
+    pub meta_issues: MetaIssueCounts,
+    /// [`GeneIndex::identity_hash`](crate::input::features::GeneIndex::identity_hash)
+    /// of the gene list, recorded in the outputs so runs against different
+    /// annotations are not mixed.
+    pub gene_set_hash: String,
+    /// Pipeline-mode per-cell columns from the shared cache sidecar, passed
+    /// through to `secretion.tsv`; `None` without a sidecar.
+    pub upstream: Option<UpstreamColumns>,
+    /// Dense or `.h5ad` input, already read into CSC form with its cell
+    /// stats; stage2 takes it from here instead of reading `matrix_path`.
+    pub dense: Option<Arc<DenseMatrix>>,
+    /// Value type from the MTX banner; `None` for cache and dense input.
+    pub matrix_value_type: Option<MtxField>,
+    /// Orientation stage2 reads the MTX in; `None` for cache and dense input.
+    pub matrix_orientation: Option<MtxOrientation>,
+}
+
+impl DatasetCtx {
+    /// Fraction of barcodes matched by a meta `cell_id`; `None` without meta.
+    pub fn meta_match_fraction(&self) -> Option<f32> {
+        self.meta_present
+            .then(|| meta_fraction(self.meta_cells_matched, self.n_cells))
+    }
 }
 
 pub fn run_stage1(
@@ -64,39 +184,71 @@ pub fn run_stage1(
     fast: bool,
     run_mode: RunMode,
     cache_override: Option<&Path>,
+) -> Result<DatasetCtx, Stage1Error> {
+    run_stage1_with_options(
+        input_dir,
+        meta_path,
+        out_dir,
+        fast,
+        run_mode,
+        cache_override,
+        &Stage1Options::default(),
+    )
+}
+
+pub fn run_stage1_with_options(
+    input_dir: &Path,
+    meta_path: Option<&Path>,
+    out_dir: &Path,
+    fast: bool,
+    run_mode: RunMode,
+    cache_override: Option<&Path>,
+    opts: &Stage1Options,
 ) -> Result<DatasetCtx, Stage1Error> {
     let _ = out_dir;
+    if opts.input_format == InputFormat::Dense {
+        let dense = read_dense_matrix(input_dir)?;
+        return run_stage1_dense(input_dir, TenXFormat::Dense, dense, meta_path, opts);
+    }
+    if is_h5ad(input_dir) {
+        let dense = read_h5ad(input_dir, opts.h5ad_layer)?;
+        return run_stage1_dense(input_dir, TenXFormat::H5ad, dense, meta_path, opts);
+    }
+    let input_dir = &resolve_input_dir(input_dir)?;
 
     if run_mode == RunMode::Pipeline {
         if let Some(cache_path) = cache_override {
-            return run_stage1_shared_cache(input_dir, cache_path.to_path_buf(), meta_path);
+            let cache_path = resolve_cache_path(cache_path)?;
+            return run_stage1_shared_cache(input_dir, cache_path, meta_path, opts);
         }
         let prefix = detect_prefix(input_dir)?;
         let cache_name = resolve_shared_cache_file_name(prefix.as_deref());
         let expected_cache = input_dir.join(cache_name);
         if let Some(cache_path) = find_shared_cache_file(input_dir, prefix.as_deref())? {
-            return run_stage1_shared_cache(input_dir, cache_path, meta_path);
+            return run_stage1_shared_cache(input_dir, cache_path, meta_path, opts);
         }
         warn!(
             expected_cache = %expected_cache.to_string_lossy(),
             "shared cache not found, falling back to MTX input"
         );
         let layout = detect_10x_dir(input_dir)?;
-        let mut ctx = run_stage1_layout(input_dir, layout, meta_path, fast)?;
+        let mut ctx = run_stage1_layout(input_dir, layout, meta_path, fast, opts)?;
         ctx.resolved_shared_cache_path = Some(expected_cache);
         return Ok(ctx);
     }
 
     let layout = detect_10x_dir(input_dir)?;
-    run_stage1_layout(input_dir, layout, meta_path, fast)
+    run_stage1_layout(input_dir, layout, meta_path, fast, opts)
 }
 
 fn run_stage1_shared_cache(
     input_dir: &Path,
     shared_cache_path: PathBuf,
     meta_path: Option<&Path>,
+    opts: &Stage1Options,
 ) -> Result<DatasetCtx, Stage1Error> {
-    let metadata = read_shared_cache_metadata(&shared_cache_path)?;
+    let mapped = map_shared_cache(&shared_cache_path, opts.cache_check)?;
+    let metadata = mapped.metadata();
 
     let rows: Vec<FeatureRow> = metadata
         .genes
@@ -113,20 +265,50 @@ fn run_stage1_shared_cache(
     let mut meta_present = false;
     let mut meta_cells_matched = 0usize;
     let mut meta_cells_missing = 0usize;
+    let mut meta_issues = MetaIssueCounts::default();
     if let Some(meta) = meta_path {
         meta_present = true;
         let stats = read_meta(meta, &metadata.barcodes)?;
+        meta_issues = check_meta_issues(&stats, opts.meta_strict)?;
+        check_meta_match(&stats, &metadata.barcodes, opts.min_meta_match_frac)?;
         meta_cells_matched = stats.matched;
         meta_cells_missing = stats.missing;
     }
 
+    let sidecar = upstream_sidecar_path(&shared_cache_path);
+    let upstream = if opts.upstream_columns && sidecar.is_file() {
+        match read_upstream_columns(&sidecar, &metadata.barcodes) {
+            Ok(upstream) => {
+                info!(
+                    path = %sidecar.display(),
+                    columns = upstream.columns.len(),
+                    "loaded upstream per-cell columns"
+                );
+                Some(upstream)
+            }
+            Err(err) => {
+                warn!(path = %sidecar.display(), error = %err, "upstream columns skipped");
+                None
+            }
+        }
+    } else {
+        None
+    };
+
     Ok(DatasetCtx {
         format: TenXFormat::Unknown,
         matrix_path: input_dir.join("matrix.mtx"),
         features_path: input_dir.join("features.tsv"),
         barcodes_path: input_dir.join("barcodes.tsv"),
+        features_delimiter: Delimiter::Tab,
+        barcodes_delimiter: Delimiter::Tab,
+        barcodes_header: None,
+        features_single_column_rows: 0,
         shared_cache_path: Some(shared_cache_path.clone()),
         resolved_shared_cache_path: Some(shared_cache_path),
+        shared_cache_identity: Some(mapped.identity()),
+        shared_cache: Some(mapped),
+        gene_set_hash: gene_index.identity_hash(),
         gene_index,
         barcodes: metadata.barcodes,
         n_genes: metadata.n_genes,
@@ -137,6 +319,109 @@ fn run_stage1_shared_cache(
         meta_present,
         meta_cells_matched,
         meta_cells_missing,
+        meta_issues,
+        upstream,
+        dense: None,
+        matrix_value_type: None,
+        matrix_orientation: None,
+    })
+}
+
+/// Takes input read whole into CSC form (a dense table or an `.h5ad` file);
+/// there is no shared cache or features and barcodes file, so every path
+/// points at the input file.
+fn run_stage1_dense(
+    path: &Path,
+    format: TenXFormat,
+    dense: DenseMatrix,
+    meta_path: Option<&Path>,
+    opts: &Stage1Options,
+) -> Result<DatasetCtx, Stage1Error> {
+    let gene_index = build_gene_index(dense.genes.clone());
+    let duplicate_gene_symbols_count = gene_index.duplicates.len();
+    let duplicate_gene_symbols = gene_index.duplicates.clone();
+    info!(
+        path = %path.display(),
+        n_genes = dense.expr.n_genes,
+        n_cells = dense.expr.n_cells,
+        nnz = dense.expr.nnz,
+        %format,
+        "read dense input"
+    );
+
+    let mut meta_present = false;
+    let mut meta_cells_matched = 0usize;
+    let mut meta_cells_missing = 0usize;
+    let mut meta_issues = MetaIssueCounts::default();
+    if let Some(meta) = meta_path {
+        meta_present = true;
+        let stats = read_meta(meta, &dense.barcodes)?;
+        meta_issues = check_meta_issues(&stats, opts.meta_strict)?;
+        check_meta_match(&stats, &dense.barcodes, opts.min_meta_match_frac)?;
+        meta_cells_matched = stats.matched;
+        meta_cells_missing = stats.missing;
+    }
+
+    Ok(DatasetCtx {
+        format,
+        matrix_path: path.to_path_buf(),
+        features_path: path.to_path_buf(),
+        barcodes_path: path.to_path_buf(),
+        features_delimiter: Delimiter::Tab,
+        barcodes_delimiter: Delimiter::Tab,
+        barcodes_header: None,
+        features_single_column_rows: 0,
+        shared_cache_path: None,
+        resolved_shared_cache_path: None,
+        shared_cache: None,
+        shared_cache_identity: None,
+        gene_set_hash: gene_index.identity_hash(),
+        gene_index,
+        barcodes: dense.barcodes.clone(),
+        n_genes: dense.expr.n_genes,
+        n_cells: dense.expr.n_cells,
+        nnz: dense.expr.nnz,
+        duplicate_gene_symbols_count,
+        duplicate_gene_symbols,
+        meta_present,
+        meta_cells_matched,
+        meta_cells_missing,
+        meta_issues,
+        upstream: None,
+        dense: Some(Arc::new(dense)),
+        matrix_value_type: None,
+        matrix_orientation: None,
+    })
+}
+
+/// Explains a column count off by one through the barcodes header handling.
+fn barcodes_header_hint(header: Option<&str>, matrix_cols: usize, n_barcodes: usize) -> String {
+    match header {
+        Some(header) if matrix_cols == n_barcodes + 1 => format!(
+            "; the first barcodes line '{header}' was skipped as a header, pass --barcodes-has-header false to keep it"
+        ),
+        None if matrix_cols + 1 == n_barcodes => "; barcodes.tsv has one line more than the matrix has columns, if its first line is a header pass --barcodes-has-header true".to_string(),
+        _ => String::new(),
+    }
+}
+
+/// Names the fix when the header dims are those of the other orientation.
+fn orientation_hint(
+    orientation: MtxOrientation,
+    header: &MatrixHeader,
+    n_genes: usize,
+    n_cells: usize,
+) -> Option<String> {
+    let other = match orientation {
+        MtxOrientation::GenesByCells => MtxOrientation::CellsByGenes,
+        MtxOrientation::CellsByGenes => MtxOrientation::GenesByCells,
+    };
+    if (header.n_rows, header.n_cols) != other.dims(n_genes, n_cells) {
+        return None;
+    }
+    Some(match orientation {
+        MtxOrientation::GenesByCells => "; the matrix appears transposed (cells as rows, genes as columns), pass --transpose-matrix to read it that way".to_string(),
+        MtxOrientation::CellsByGenes => "; the matrix is genes x cells already, drop --transpose-matrix".to_string(),
     })
 }
 
@@ -145,22 +430,57 @@ fn run_stage1_layout(
     layout: TenXLayout,
     meta_path: Option<&Path>,
     fast: bool,
+    opts: &Stage1Options,
 ) -> Result<DatasetCtx, Stage1Error> {
-    let barcodes = read_barcodes(&layout.barcodes_path)?;
-    let gene_index = read_features(&layout.features_path)?;
+    let BarcodesFile {
+        barcodes,
+        delimiter: barcodes_delimiter,
+        header: barcodes_header,
+    } = read_barcodes(&layout.barcodes_path, opts.barcodes_has_header)?;
+    let FeaturesFile {
+        gene_index,
+        delimiter: features_delimiter,
+        single_column_rows: features_single_column_rows,
+    } = read_features(&layout.features_path, opts.features_single_column)?;
+    info!(
+        features_delimiter = features_delimiter.as_str(),
+        barcodes_delimiter = barcodes_delimiter.as_str(),
+        "detected input delimiters"
+    );
     let n_genes = gene_index.rows.len();
+    let n_cells = barcodes.len();
     let duplicate_gene_symbols_count = gene_index.duplicates.len();
     let duplicate_gene_symbols = gene_index.duplicates.clone();
     let header = read_header(&layout.matrix_path)?;
 
-    if header.n_rows != gene_index.rows.len() || header.n_cols != barcodes.len() {
+    let orientation = if opts.transpose_matrix {
+        MtxOrientation::CellsByGenes
+    } else {
+        MtxOrientation::GenesByCells
+    };
+    let (expected_rows, expected_cols) = orientation.dims(n_genes, n_cells);
+    if (header.n_rows, header.n_cols) != (expected_rows, expected_cols) {
+        let matrix_cells = match orientation {
+            MtxOrientation::GenesByCells => header.n_cols,
+            MtxOrientation::CellsByGenes => header.n_rows,
+        };
         return Err(Stage1Error::DimensionMismatch {
-            expected_rows: gene_index.rows.len(),
-            expected_cols: barcodes.len(),
+            expected_rows,
+            expected_cols,
             found_rows: header.n_rows,
             found_cols: header.n_cols,
+            hint: orientation_hint(orientation, &header, n_genes, n_cells).unwrap_or_else(|| {
+                barcodes_header_hint(barcodes_header.as_deref(), matrix_cells, n_cells)
+            }),
         });
     }
+    if orientation == MtxOrientation::CellsByGenes {
+        warn!(
+            n_rows = header.n_rows,
+            n_cols = header.n_cols,
+            "--transpose-matrix: reading the matrix as cells x genes"
+        );
+    }
 
     if !fast {
         let counted = count_nnz_lines(&layout.matrix_path)?;
@@ -175,10 +495,13 @@ fn run_stage1_layout(
     let mut meta_present = false;
     let mut meta_cells_matched = 0usize;
     let mut meta_cells_missing = 0usize;
+    let mut meta_issues = MetaIssueCounts::default();
 
     if let Some(meta) = meta_path {
         meta_present = true;
         let stats = read_meta(meta, &barcodes)?;
+        meta_issues = check_meta_issues(&stats, opts.meta_strict)?;
+        check_meta_match(&stats, &barcodes, opts.min_meta_match_frac)?;
         meta_cells_matched = stats.matched;
         meta_cells_missing = stats.missing;
     }
@@ -188,24 +511,101 @@ fn run_stage1_layout(
         matrix_path: layout.matrix_path,
         features_path: layout.features_path,
         barcodes_path: layout.barcodes_path,
+        features_delimiter,
+        barcodes_delimiter,
+        barcodes_header,
+        features_single_column_rows,
         shared_cache_path: None,
         resolved_shared_cache_path: layout
             .prefix
             .as_deref()
             .map(|p| input_dir.join(resolve_shared_cache_file_name(Some(p)))),
+        shared_cache: None,
+        shared_cache_identity: None,
+        gene_set_hash: gene_index.identity_hash(),
         gene_index,
         barcodes,
         n_genes,
-        n_cells: header.n_cols,
+        n_cells,
         nnz: header.nnz,
         duplicate_gene_symbols_count,
         duplicate_gene_symbols,
         meta_present,
         meta_cells_matched,
         meta_cells_missing,
+        meta_issues,
+        upstream: None,
+        dense: None,
+        matrix_value_type: Some(header.field),
+        matrix_orientation: Some(orientation),
     })
 }
 
+fn meta_fraction(matched: usize, n_cells: usize) -> f32 {
+    if n_cells == 0 {
+        0.0
+    } else {
+        matched as f32 / n_cells as f32
+    }
+}
+
+/// Fails when too few barcodes have meta rows, showing examples from both
+/// sides so suffixing mismatches are obvious; warns on a partial match.
+fn check_meta_match(
+    stats: &MetaStats,
+    barcodes: &[String],
+    min_frac: f32,
+) -> Result<(), Stage1Error> {
+    let fraction = meta_fraction(stats.matched, barcodes.len());
+    if fraction < min_frac {
+        let barcode_examples: Vec<&str> = barcodes
+            .iter()
+            .take(META_EXAMPLE_COUNT)
+            .map(String::as_str)
+            .collect();
+        return Err(Stage1Error::MetaMismatch {
+            matched: stats.matched,
+            n_cells: barcodes.len(),
+            fraction,
+            threshold: min_frac,
+            barcode_examples: barcode_examples.join(", "),
+            meta_examples: stats.unmatched_examples.join(", "),
+        });
+    }
+    if stats.matched < barcodes.len() {
+        warn!(
+            fraction,
+            matched = stats.matched,
+            n_cells = barcodes.len(),
+            "meta covers only part of the barcodes"
+        );
+    }
+    Ok(())
+}
+
+/// Under `strict`, fails listing every problem row; otherwise warns with
+/// per-kind counts and returns them.
+fn check_meta_issues(stats: &MetaStats, strict: bool) -> Result<MetaIssueCounts, Stage1Error> {
+    let counts = stats.issue_counts();
+    if counts.total() == 0 {
+        return Ok(counts);
+    }
+    if strict {
+        let issues: Vec<String> = stats.issues.iter().map(|i| i.to_string()).collect();
+        return Err(Stage1Error::MetaStrict {
+            count: issues.len(),
+            issues: issues.join("; "),
+        });
+    }
+    warn!(
+        unknown_species = counts.unknown_species,
+        short_rows = counts.short_rows,
+        duplicate_cell_ids = counts.duplicate_cell_ids,
+        "meta file has problem rows; pass --meta-strict to reject them"
+    );
+    Ok(counts)
+}
+
 #[cfg(test)]
 #[path = "../../tests/src_inline/pipeline/stage1_load.rs"]
 mod tests;
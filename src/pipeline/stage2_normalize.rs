@@ -1,117 +1,375 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use thiserror::Error;
 
 use crate::expr::csc::{CellStats, ExprCsc};
 use crate::expr::normalize::Normalization;
 use crate::input::InputError;
-use crate::input::cache::{SharedCacheMapped, mmap_shared_cache, mmap_shared_cache_unchecked};
+use crate::input::cache::{
+    CacheError, CacheIdentity, SharedCacheMapped, mmap_shared_cache, mmap_shared_cache_unchecked,
+};
+use crate::input::dense::DenseMatrix;
+use crate::input::mtx::{MtxValueLimits, MtxValueWarnings};
+use crate::pipeline::cancel::{self, Cancelled};
 use crate::pipeline::stage1_load::DatasetCtx;
 
 #[derive(Debug, Error)]
 pub enum Stage2Error {
     #[error("input error: {0}")]
-    Input(#[from] InputError),
-    #[error("cache error: {0}")]
-    Cache(#[from] crate::input::cache::CacheError),
+    Input(#[source] InputError),
+    #[error("reopening shared cache {} ({}): {source}", path.display(), mode.as_str())]
+    CacheReopen {
+        path: PathBuf,
+        mode: CacheParseMode,
+        source: CacheError,
+    },
+    #[error(
+        "shared cache {} changed since stage1 validated it (stage1: {expected}; now: {found})",
+        path.display()
+    )]
+    CacheChanged {
+        path: PathBuf,
+        expected: CacheIdentity,
+        found: CacheIdentity,
+    },
+    #[error("{0}")]
+    Cancelled(#[from] Cancelled),
 }
 
+/// A cancellation seen while reading the matrix is the stage's own.
+impl From<InputError> for Stage2Error {
+    fn from(e: InputError) -> Self {
+        match e {
+            InputError::Cancelled(cancelled) => Stage2Error::Cancelled(cancelled),
+            e => Stage2Error::Input(e),
+        }
+    }
+}
+
+/// How stage2 parses a shared cache it has to map again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheParseMode {
+    /// Stage1 validated the file and recorded its identity: header and
+    /// tables only, then the identity check.
+    Unchecked,
+    /// No identity from stage1: the full structural validation.
+    Full,
+}
+
+impl CacheParseMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CacheParseMode::Unchecked => "unchecked parse of the file stage1 validated",
+            CacheParseMode::Full => "full validation, no stage1 identity",
+        }
+    }
+}
+
+/// Per-cell access to a raw count matrix. Stages 3-7 are generic over this
+/// trait, so external matrix backends can drive the pipeline without going
+/// through MTX or the shared cache.
+pub trait CellExprSource {
+    fn n_genes(&self) -> usize;
+    fn n_cells(&self) -> usize;
+    fn nnz(&self) -> usize;
+
+    /// Calls `f(row, raw_count)` for every stored entry of `cell_idx`.
+    fn for_each_cell_raw<F>(&self, cell_idx: usize, f: F)
+    where
+        F: FnMut(u32, u32);
+
+    fn for_each_cell_norm<F>(
+        &self,
+        cell_idx: usize,
+        norm: &Normalization,
+        cell_stats: &CellStats,
+        mut f: F,
+    ) where
+        F: FnMut(u32, f32),
+    {
+        let inv_denom = norm.inv_denom(cell_stats.libsize);
+        self.for_each_cell_raw(cell_idx, |row, raw| f(row, norm.apply(raw, inv_denom)));
+    }
+
+    fn compute_cell_stats(&self) -> Vec<CellStats> {
+        (0..self.n_cells())
+            .map(|cell| {
+                let mut stat = CellStats::default();
+                self.for_each_cell_raw(cell, |_, raw| {
+                    stat.libsize += raw as u64;
+                    stat.detected += 1;
+                });
+                stat
+            })
+            .collect()
+    }
+}
+
+impl CellExprSource for ExprCsc {
+    fn n_genes(&self) -> usize {
+        self.n_genes
+    }
+
+    fn n_cells(&self) -> usize {
+        self.n_cells
+    }
+
+    fn nnz(&self) -> usize {
+        self.nnz
+    }
+
+    fn for_each_cell_raw<F>(&self, cell_idx: usize, mut f: F)
+    where
+        F: FnMut(u32, u32),
+    {
+        for (row, value) in self.iter_cell_raw(cell_idx) {
+            f(row, value);
+        }
+    }
+
+    fn for_each_cell_norm<F>(
+        &self,
+        cell_idx: usize,
+        norm: &Normalization,
+        cell_stats: &CellStats,
+        mut f: F,
+    ) where
+        F: FnMut(u32, f32),
+    {
+        for (row, value) in self.iter_cell_norm(cell_idx, norm, cell_stats) {
+            f(row, value);
+        }
+    }
+}
+
+impl CellExprSource for SharedCacheMapped {
+    fn n_genes(&self) -> usize {
+        self.n_genes
+    }
+
+    fn n_cells(&self) -> usize {
+        self.n_cells
+    }
+
+    fn nnz(&self) -> usize {
+        self.nnz
+    }
+
+    fn for_each_cell_raw<F>(&self, cell_idx: usize, f: F)
+    where
+        F: FnMut(u32, u32),
+    {
+        SharedCacheMapped::for_each_cell_raw(self, cell_idx, f)
+    }
+
+    fn for_each_cell_norm<F>(
+        &self,
+        cell_idx: usize,
+        norm: &Normalization,
+        cell_stats: &CellStats,
+        f: F,
+    ) where
+        F: FnMut(u32, f32),
+    {
+        SharedCacheMapped::for_each_cell_norm(self, cell_idx, norm, cell_stats, f)
+    }
+
+    fn compute_cell_stats(&self) -> Vec<CellStats> {
+        SharedCacheMapped::compute_cell_stats(self)
+    }
+}
+
+/// Built-in matrix backends produced by `run_stage2`.
 #[derive(Debug, Clone)]
 pub enum ExprMatrix {
     Owned(ExprCsc),
     Shared(SharedCacheMapped),
+    /// Stage1's dense-table or `.h5ad` matrix, shared rather than copied.
+    Dense(Arc<DenseMatrix>),
 }
 
-impl ExprMatrix {
-    pub fn n_genes(&self) -> usize {
+impl CellExprSource for ExprMatrix {
+    fn n_genes(&self) -> usize {
         match self {
             ExprMatrix::Owned(e) => e.n_genes,
             ExprMatrix::Shared(e) => e.n_genes,
+            ExprMatrix::Dense(d) => d.expr.n_genes,
         }
     }
 
-    pub fn n_cells(&self) -> usize {
+    fn n_cells(&self) -> usize {
         match self {
             ExprMatrix::Owned(e) => e.n_cells,
             ExprMatrix::Shared(e) => e.n_cells,
+            ExprMatrix::Dense(d) => d.expr.n_cells,
         }
     }
 
-    pub fn nnz(&self) -> usize {
+    fn nnz(&self) -> usize {
         match self {
             ExprMatrix::Owned(e) => e.nnz,
             ExprMatrix::Shared(e) => e.nnz,
+            ExprMatrix::Dense(d) => d.expr.nnz,
         }
     }
 
-    pub fn for_each_cell_norm<F>(
+    fn for_each_cell_norm<F>(
         &self,
         cell_idx: usize,
         norm: &Normalization,
         cell_stats: &CellStats,
-        mut f: F,
+        f: F,
     ) where
         F: FnMut(u32, f32),
     {
         match self {
             ExprMatrix::Owned(e) => {
-                for (row, value) in e.iter_cell_norm(cell_idx, norm, cell_stats) {
-                    f(row, value);
-                }
+                CellExprSource::for_each_cell_norm(e, cell_idx, norm, cell_stats, f)
             }
             ExprMatrix::Shared(e) => e.for_each_cell_norm(cell_idx, norm, cell_stats, f),
+            ExprMatrix::Dense(d) => {
+                CellExprSource::for_each_cell_norm(&d.expr, cell_idx, norm, cell_stats, f)
+            }
         }
     }
 
-    pub fn for_each_cell_raw<F>(&self, cell_idx: usize, mut f: F)
+    fn for_each_cell_raw<F>(&self, cell_idx: usize, f: F)
     where
         F: FnMut(u32, u32),
     {
         match self {
-            ExprMatrix::Owned(e) => {
-                for (row, value) in e.iter_cell_raw(cell_idx) {
-                    f(row, value);
-                }
-            }
+            ExprMatrix::Owned(e) => CellExprSource::for_each_cell_raw(e, cell_idx, f),
             ExprMatrix::Shared(e) => e.for_each_cell_raw(cell_idx, f),
+            ExprMatrix::Dense(d) => CellExprSource::for_each_cell_raw(&d.expr, cell_idx, f),
+        }
+    }
+
+    fn compute_cell_stats(&self) -> Vec<CellStats> {
+        match self {
+            ExprMatrix::Owned(e) => e.compute_cell_stats(),
+            ExprMatrix::Shared(e) => e.compute_cell_stats(),
+            ExprMatrix::Dense(d) => d.expr.compute_cell_stats(),
         }
     }
 }
 
 #[derive(Debug, Clone)]
-pub struct ExprContext {
-    pub expr: ExprMatrix,
+pub struct ExprContext<M = ExprMatrix> {
+    pub expr: M,
     pub cell_stats: Vec<CellStats>,
     pub normalization: Normalization,
+    /// What reading the MTX counted: entries above the warning threshold,
+    /// rounded `real` entries (and those dropped as 0), merged duplicates.
+    /// All zero for the shared cache, dense and `.h5ad` input.
+    pub value_warnings: MtxValueWarnings,
+}
+
+/// Stage 2 knobs beyond normalization; the default matches [`run_stage2`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Stage2Options {
+    /// Per-entry bounds applied while reading MTX counts.
+    pub value_limits: MtxValueLimits,
 }
 
 pub fn run_stage2(
+    ctx: &DatasetCtx,
+    out_dir: &Path,
+    normalization: Normalization,
+    fast: bool,
+) -> Result<ExprContext, Stage2Error> {
+    run_stage2_with_options(ctx, out_dir, normalization, fast, &Stage2Options::default())
+}
+
+/// [`run_stage2`] with explicit options; MTX value checks are counted into
+/// [`ExprContext::value_warnings`] (the shared cache is not checked).
+pub fn run_stage2_with_options(
     ctx: &DatasetCtx,
     _out_dir: &Path,
     normalization: Normalization,
     fast: bool,
+    opts: &Stage2Options,
 ) -> Result<ExprContext, Stage2Error> {
     if let Some(shared_cache_path) = &ctx.shared_cache_path {
-        // Stage 1 already performed strict validation in pipeline mode.
-        let shared = mmap_shared_cache_unchecked(shared_cache_path)
-            .or_else(|_| mmap_shared_cache(shared_cache_path))?;
+        let shared = match &ctx.shared_cache {
+            Some(mapped) => mapped.clone(),
+            None => reopen_shared_cache(shared_cache_path, ctx.shared_cache_identity)?,
+        };
         let cell_stats = shared.compute_cell_stats();
+        cancel::check()?;
         return Ok(ExprContext {
             expr: ExprMatrix::Shared(shared),
             cell_stats,
             normalization,
+            value_warnings: MtxValueWarnings::default(),
+        });
+    }
+
+    if let Some(dense) = &ctx.dense {
+        return Ok(ExprContext {
+            expr: ExprMatrix::Dense(Arc::clone(dense)),
+            cell_stats: dense.cell_stats.clone(),
+            normalization,
+            value_warnings: MtxValueWarnings::default(),
         });
     }
 
-    let (expr, cell_stats) = ExprCsc::from_mtx(&ctx.matrix_path, ctx.n_genes, ctx.n_cells, fast)?;
+    let mut value_warnings = MtxValueWarnings::default();
+    let (expr, cell_stats) = ExprCsc::from_mtx_with_limits(
+        &ctx.matrix_path,
+        ctx.n_genes,
+        ctx.n_cells,
+        fast,
+        ctx.matrix_orientation.unwrap_or_default(),
+        &opts.value_limits,
+        &mut value_warnings,
+    )?;
+    cancel::check()?;
 
     Ok(ExprContext {
         expr: ExprMatrix::Owned(expr),
         cell_stats,
         normalization,
+        value_warnings,
     })
 }
 
+/// Maps the shared cache again when the context does not carry stage1's
+/// mapping, parsing it once. With a recorded identity the file must still be
+/// the one stage1 validated (so the cheap parser is enough); without one it
+/// is validated in full. A failure keeps the parser's own error, with the
+/// path and the mode.
+fn reopen_shared_cache(
+    path: &Path,
+    expected: Option<CacheIdentity>,
+) -> Result<SharedCacheMapped, Stage2Error> {
+    let mode = match expected {
+        Some(_) => CacheParseMode::Unchecked,
+        None => CacheParseMode::Full,
+    };
+    let parsed = match mode {
+        CacheParseMode::Unchecked => mmap_shared_cache_unchecked(path),
+        CacheParseMode::Full => mmap_shared_cache(path),
+    };
+    let shared = parsed.map_err(|source| Stage2Error::CacheReopen {
+        path: path.to_path_buf(),
+        mode,
+        source,
+    })?;
+    let Some(expected) = expected else {
+        return Ok(shared);
+    };
+    let found = shared.identity();
+    if found != expected {
+        return Err(Stage2Error::CacheChanged {
+            path: path.to_path_buf(),
+            expected,
+            found,
+        });
+    }
+    Ok(shared)
+}
+
 #[cfg(test)]
 #[path = "../../tests/src_inline/pipeline/stage2_normalize.rs"]
 mod tests;
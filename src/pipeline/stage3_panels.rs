@@ -1,21 +1,53 @@
+use std::collections::HashSet;
 use std::io::Write;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
+use tracing::warn;
 
 use crate::expr::csc::CellStats;
+use crate::expr::normalize::NormalizationMethod;
 use crate::input::InputError;
 use crate::input::features::GeneIndex;
+use crate::model::stats::{NanPolicy, median, spearman};
 use crate::panels::defs::PanelSet;
-use crate::panels::mapping::{GeneMapping, MappingWarning, map_panel};
-use crate::pipeline::stage2_normalize::ExprContext;
+use crate::panels::mapping::{
+    GeneMapping, MappingWarning, PanelGeneUniverse, map_panel, panel_gene_universe,
+};
+use crate::pipeline::cancel::{Cancelled, checkpoint};
+use crate::pipeline::chunk_progress;
+use crate::pipeline::outputs::per_cell_tables;
+use crate::pipeline::stage2_normalize::{CellExprSource, ExprContext};
+use crate::report::artifact::{Artifact, WriteError, into_write_error, write_artifact};
+use crate::report::format::{fixed6, signed_or_nan};
+use crate::report::tsv::{UnsafeField, field};
 
 #[derive(Debug, Error)]
 pub enum Stage3Error {
     #[error("io error: {0}")]
-    Io(#[from] std::io::Error),
+    Io(std::io::Error),
+    #[error("io error writing {}: {source}", path.display())]
+    Write {
+        path: PathBuf,
+        source: std::io::Error,
+    },
     #[error("input error: {0}")]
     Input(#[from] InputError),
+    #[error("{0}")]
+    Cancelled(#[from] Cancelled),
+    #[error("{0}")]
+    Field(#[from] UnsafeField),
+}
+
+/// Artifact write errors keep their path; other I/O errors stay `Io`.
+impl From<std::io::Error> for Stage3Error {
+    fn from(e: std::io::Error) -> Self {
+        match into_write_error(e) {
+            Ok(WriteError { path, source }) => Stage3Error::Write { path, source },
+            Err(e) => Stage3Error::Io(e),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -33,65 +65,166 @@ pub struct PanelCellResult {
     pub required_missing: u32,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PanelCellPacked {
     pub sums: Vec<f32>,
     pub hits: Vec<u32>,
     pub required_missing: Vec<u32>,
 }
 
-#[derive(Debug, Clone)]
+/// A panel counts as dead when its sum is zero in at least this fraction of cells.
+pub const DEAD_PANEL_ZERO_FRACTION: f32 = 0.99;
+
+/// A panel whose genes are absent or (almost) never expressed in the dataset.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeadPanel {
+    pub panel_id: String,
+    pub axis: String,
+    /// Fraction of cells with a nonzero panel sum.
+    #[serde(serialize_with = "crate::report::json::fixed6")]
+    pub nonzero_fraction: f32,
+    #[serde(serialize_with = "crate::report::json::fixed6")]
+    pub max_sum: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PanelsContext {
+    #[serde(with = "crate::pipeline::checkpoint_file::as_json")]
     pub panels: PanelSet,
     pub mappings: Vec<GeneMapping>,
     pub warnings: Vec<MappingWarning>,
     pub cell_ids: Vec<String>,
     pub per_cell: Vec<PanelCellPacked>,
+    /// Panels zero in at least [`DEAD_PANEL_ZERO_FRACTION`] of cells, in panel order.
+    pub dead_panels: Vec<DeadPanel>,
+}
+
+impl PanelsContext {
+    /// Unique panel genes mapped, overall and per axis tag.
+    pub fn gene_universe(&self) -> PanelGeneUniverse {
+        panel_gene_universe(&self.panels.panels, &self.mappings)
+    }
+}
+
+/// Matrix entries whose row is not a gene of the matrix, skipped while
+/// summing panels. Only a shared cache read without full CSC validation
+/// (`--verify-cache quick`) can deliver them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RowRangeWarnings {
+    pub n_out_of_range: usize,
+    pub max_row: u32,
+    /// Barcode of the first cell with such an entry.
+    pub first_cell: Option<String>,
+}
+
+impl RowRangeWarnings {
+    fn record(&mut self, row: u32, cell: impl FnOnce() -> String) {
+        if self.first_cell.is_none() {
+            self.first_cell = Some(cell());
+        }
+        self.n_out_of_range += 1;
+        self.max_row = self.max_row.max(row);
+    }
 }
 
-pub fn run_stage3_panels(
-    expr: &ExprContext,
+/// Stage 3 knobs; the default matches [`run_stage3_panels`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Stage3Options {
+    /// `--debug-dual-normalization`: also sum panels under this method and
+    /// write `normalization_sensitivity.tsv`. The primary outputs are unchanged.
+    pub dual_normalization: Option<NormalizationMethod>,
+}
+
+pub fn run_stage3_panels<M: CellExprSource>(
+    expr: &ExprContext<M>,
     panels: &PanelSet,
     gene_index: &GeneIndex,
     cell_ids: &[String],
     out_dir: &Path,
 ) -> Result<PanelsContext, Stage3Error> {
+    run_stage3_panels_with_options(
+        expr,
+        panels,
+        gene_index,
+        cell_ids,
+        out_dir,
+        &Stage3Options::default(),
+        &mut RowRangeWarnings::default(),
+    )
+}
+
+/// [`run_stage3_panels`] with explicit options; entries with a row outside
+/// the matrix are skipped and counted into `row_warnings`.
+pub fn run_stage3_panels_with_options<M: CellExprSource>(
+    expr: &ExprContext<M>,
+    panels: &PanelSet,
+    gene_index: &GeneIndex,
+    cell_ids: &[String],
+    out_dir: &Path,
+    opts: &Stage3Options,
+    row_warnings: &mut RowRangeWarnings,
+) -> Result<PanelsContext, Stage3Error> {
+    let secondary = opts.dual_normalization.map(|m| m.normalization());
+    // Secondary panel sums, cell-major; empty without the debug mode.
+    let mut secondary_sums: Vec<f32> = Vec::new();
     let (mappings, warnings, reverse_index) =
         build_mappings(panels, gene_index, expr.expr.n_genes());
     let mut per_cell = Vec::with_capacity(cell_ids.len());
+    let mut nonzero_cells = vec![0usize; panels.panels.len()];
+    let mut max_sums = vec![0.0f32; panels.panels.len()];
+    // Cells with a nonzero count, for panel gene rows only.
+    let mut detected_cells = vec![0u32; reverse_index.len()];
 
-    let report_path = out_dir.join("panels_report.tsv");
-    let mut writer = std::io::BufWriter::new(std::fs::File::create(&report_path)?);
-
-    write_warnings(&mut writer, &warnings)?;
-    writer.write_all(b"cell_id\tpanel_id\taxis\tsum\thits\tcoverage\trequired_missing\n")?;
+    let mut writer = per_cell_tables()
+        .then(|| Artifact::create(out_dir, "panels_report.tsv"))
+        .transpose()?;
+    if let Some(writer) = writer.as_mut() {
+        writer.write_all(b"cell_id\tpanel_id\taxis\tsum\thits\tcoverage\trequired_missing\n")?;
+    }
 
     for (cell_idx, barcode) in cell_ids.iter().enumerate() {
+        let barcode = field("cell_id", barcode)?;
         let mut accums = vec![PanelAccum { sum: 0.0, hits: 0 }; panels.panels.len()];
-        let mut last_row_hit = vec![u32::MAX; panels.panels.len()];
+        // Last row that counted as a hit, so a row stored twice counts once.
+        let mut last_row_hit: Vec<Option<u32>> = vec![None; panels.panels.len()];
         let cell_stats: &CellStats = &expr.cell_stats[cell_idx];
-        let inv_denom = if expr.normalization.enabled {
-            expr.normalization.scale / (cell_stats.libsize as f32 + expr.normalization.epsilon)
-        } else {
-            1.0
-        };
+        let inv_denom = expr.normalization.inv_denom(cell_stats.libsize);
+        let mut sums2 = vec![
+            0.0f32;
+            if secondary.is_some() {
+                panels.panels.len()
+            } else {
+                0
+            }
+        ];
+        let inv_denom2 = secondary.as_ref().map(|n| n.inv_denom(cell_stats.libsize));
 
         expr.expr.for_each_cell_raw(cell_idx, |row, raw_value| {
             let row_usize = row as usize;
-            if row_usize >= reverse_index.len() || reverse_index[row_usize].is_empty() {
+            // `reverse_index` has one entry per matrix row.
+            if row_usize >= reverse_index.len() {
+                row_warnings.record(row, || barcode.to_string());
                 return;
             }
-            let value = if expr.normalization.enabled {
-                (raw_value as f32 * inv_denom).ln_1p()
-            } else {
-                raw_value as f32
-            };
+            if reverse_index[row_usize].is_empty() {
+                return;
+            }
+            if raw_value != 0 {
+                detected_cells[row_usize] += 1;
+            }
+            let value = expr.normalization.apply(raw_value, inv_denom);
+            if let (Some(norm2), Some(inv2)) = (&secondary, inv_denom2) {
+                let value2 = norm2.apply(raw_value, inv2);
+                for (panel_idx, weight) in &reverse_index[row_usize] {
+                    sums2[*panel_idx] += value2 * *weight;
+                }
+            }
             for (panel_idx, weight) in &reverse_index[row_usize] {
                 let acc = &mut accums[*panel_idx];
                 acc.sum += value * *weight;
-                if last_row_hit[*panel_idx] != row {
+                if last_row_hit[*panel_idx] != Some(row) {
                     acc.hits += 1;
-                    last_row_hit[*panel_idx] = row;
+                    last_row_hit[*panel_idx] = Some(row);
                 }
             }
         });
@@ -109,27 +242,60 @@ pub fn run_stage3_panels(
             required_missing[panel_idx] = missing;
 
             let sum = accums[panel_idx].sum;
-            let line = format!(
-                "{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
-                barcode,
-                panel.id,
-                panel.axis,
-                format_f32(sum),
-                hits,
-                format_f32(coverage),
-                missing
-            );
-            writer.write_all(line.as_bytes())?;
+            if sum != 0.0 {
+                nonzero_cells[panel_idx] += 1;
+            }
+            max_sums[panel_idx] = max_sums[panel_idx].max(sum);
+            if let Some(writer) = writer.as_mut() {
+                let line = format!(
+                    "{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+                    barcode,
+                    field("panel_id", &panel.id)?,
+                    field("axis", &panel.axis)?,
+                    signed_or_nan(sum),
+                    hits,
+                    signed_or_nan(coverage),
+                    missing
+                );
+                writer.write_all(line.as_bytes())?;
+            }
         }
 
+        secondary_sums.extend_from_slice(&sums2);
         per_cell.push(PanelCellPacked {
             sums: accums.iter().map(|a| a.sum).collect(),
             hits: accums.iter().map(|a| a.hits).collect(),
             required_missing,
         });
+        chunk_progress("stage3_panels", cell_idx + 1, cell_ids.len());
+        checkpoint(cell_idx + 1)?;
     }
 
-    writer.flush()?;
+    if let Some(writer) = writer {
+        writer.commit()?;
+    }
+    write_panel_gene_mapping(
+        out_dir,
+        panels,
+        &mappings,
+        gene_index,
+        &detected_cells,
+        cell_ids.len(),
+    )?;
+    write_mapping_warnings(out_dir, &warnings)?;
+    if let Some(method) = opts.dual_normalization {
+        write_normalization_sensitivity(out_dir, panels, &per_cell, &secondary_sums, method)?;
+    }
+
+    let dead_panels = find_dead_panels(panels, &nonzero_cells, &max_sums, cell_ids.len());
+    if !dead_panels.is_empty() {
+        let ids = dead_panels
+            .iter()
+            .map(|p| p.panel_id.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        warn!(panels = %ids, "panels are zero in at least 99% of cells");
+    }
 
     Ok(PanelsContext {
         panels: panels.clone(),
@@ -137,9 +303,77 @@ pub fn run_stage3_panels(
         warnings,
         cell_ids: cell_ids.to_vec(),
         per_cell,
+        dead_panels,
     })
 }
 
+/// `normalization_sensitivity.tsv`: per panel, the median absolute
+/// difference and the Spearman correlation between the per-cell sums under
+/// the run's normalization and under `method`, in panel order.
+fn write_normalization_sensitivity(
+    out_dir: &Path,
+    panels: &PanelSet,
+    per_cell: &[PanelCellPacked],
+    secondary_sums: &[f32],
+    method: NormalizationMethod,
+) -> Result<(), Stage3Error> {
+    let n_panels = panels.panels.len();
+    let mut buf = String::from(
+        "panel_id\taxis\tsecondary\tmedian_abs_diff\tspearman_rho\tprimary_median\tsecondary_median\n",
+    );
+    for (panel_idx, panel) in panels.panels.iter().enumerate() {
+        let primary: Vec<f32> = per_cell.iter().map(|c| c.sums[panel_idx]).collect();
+        let secondary: Vec<f32> = secondary_sums
+            .iter()
+            .skip(panel_idx)
+            .step_by(n_panels.max(1))
+            .copied()
+            .collect();
+        let abs_diff: Vec<f32> = primary
+            .iter()
+            .zip(&secondary)
+            .map(|(a, b)| (a - b).abs())
+            .collect();
+        buf.push_str(&format!(
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+            field("panel_id", &panel.id)?,
+            field("axis", &panel.axis)?,
+            method.as_str(),
+            signed_or_nan(median(&abs_diff, NanPolicy::Skip)),
+            signed_or_nan(spearman(&primary, &secondary, NanPolicy::Skip)),
+            signed_or_nan(median(&primary, NanPolicy::Skip)),
+            signed_or_nan(median(&secondary, NanPolicy::Skip)),
+        ));
+    }
+    write_artifact(out_dir, "normalization_sensitivity.tsv", buf)?;
+    Ok(())
+}
+
+fn find_dead_panels(
+    panels: &PanelSet,
+    nonzero_cells: &[usize],
+    max_sums: &[f32],
+    n_cells: usize,
+) -> Vec<DeadPanel> {
+    if n_cells == 0 {
+        return Vec::new();
+    }
+    panels
+        .panels
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, panel)| {
+            let zero_fraction = (n_cells - nonzero_cells[idx]) as f32 / n_cells as f32;
+            (zero_fraction >= DEAD_PANEL_ZERO_FRACTION).then(|| DeadPanel {
+                panel_id: panel.id.clone(),
+                axis: panel.axis.clone(),
+                nonzero_fraction: nonzero_cells[idx] as f32 / n_cells as f32,
+                max_sum: max_sums[idx],
+            })
+        })
+        .collect()
+}
+
 fn build_mappings(
     panels: &PanelSet,
     gene_index: &GeneIndex,
@@ -178,32 +412,97 @@ fn build_mappings(
     (mappings, warnings, reverse_index)
 }
 
-fn format_f32(value: f32) -> String {
-    format!("{:.6}", value)
+/// How a panel gene symbol was resolved to a matrix row.
+fn resolution(mapped: Option<u32>, duplicated: bool) -> &'static str {
+    match (mapped, duplicated) {
+        (None, _) => "unmapped",
+        (Some(_), false) => "symbol",
+        (Some(_), true) => "symbol_first_duplicate",
+    }
 }
 
-fn write_warnings(
-    writer: &mut dyn std::io::Write,
-    warnings: &[MappingWarning],
-) -> Result<(), std::io::Error> {
-    if warnings.is_empty() {
-        return Ok(());
+/// Writes `panel_gene_mapping.tsv`: one row per (panel, gene) in panel and
+/// gene order with the 0-based matrix row it resolved to, how it resolved,
+/// its weight, whether it is required and the fraction of cells detecting it.
+/// Written under every `--outputs` set; it is panels x genes in size.
+fn write_panel_gene_mapping(
+    out_dir: &Path,
+    panels: &PanelSet,
+    mappings: &[GeneMapping],
+    gene_index: &GeneIndex,
+    detected_cells: &[u32],
+    n_cells: usize,
+) -> Result<(), Stage3Error> {
+    let duplicated: HashSet<&str> = gene_index
+        .duplicates
+        .iter()
+        .map(|d| d.symbol.as_str())
+        .collect();
+    let mut writer = Artifact::create(out_dir, "panel_gene_mapping.tsv")?;
+    writer.write_all(
+        b"panel_id\tgene\tmatrix_row\tfeature_id\tresolution\tweight\trequired\tdetected_fraction\n",
+    )?;
+    for (panel, mapping) in panels.panels.iter().zip(mappings) {
+        for (gene_pos, gene) in panel.genes.iter().enumerate() {
+            let mapped = mapping.mapped.get(gene_pos).copied().flatten();
+            let weight = panel
+                .weights
+                .as_ref()
+                .and_then(|w| w.get(gene_pos).copied())
+                .unwrap_or(1.0);
+            let (row, feature_id, detected) = match mapped {
+                Some(row) => {
+                    let row = row as usize;
+                    let feature_id = gene_index.rows.get(row).map_or(".", |r| r.id.as_str());
+                    let detected = match (detected_cells.get(row), n_cells) {
+                        (Some(&count), n) if n > 0 => count as f32 / n as f32,
+                        _ => f32::NAN,
+                    };
+                    (row.to_string(), field("feature_id", feature_id)?, detected)
+                }
+                None => (".".to_string(), ".".into(), f32::NAN),
+            };
+            writeln!(
+                writer,
+                "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                field("panel_id", &panel.id)?,
+                field("gene", &gene.symbol)?,
+                row,
+                feature_id,
+                resolution(mapped, duplicated.contains(gene.symbol.as_str())),
+                fixed6(weight),
+                panel.required.contains(&gene.symbol),
+                signed_or_nan(detected)
+            )?;
+        }
     }
-    writer.write_all(b"# warnings: missing required genes\n")?;
+    writer.commit()?;
+    Ok(())
+}
+
+/// Writes `mapping_warnings.tsv`: one row per panel with missing required
+/// genes (comma-separated), its axis and the fraction of its genes found in
+/// the features file. Written under every `--outputs` set, header only when
+/// nothing is missing.
+fn write_mapping_warnings(out_dir: &Path, warnings: &[MappingWarning]) -> Result<(), Stage3Error> {
+    let mut writer = Artifact::create(out_dir, "mapping_warnings.tsv")?;
+    writer.write_all(b"panel_id\taxis\tmissing_required\tmappable_fraction\n")?;
     for warn in warnings {
-        let mut line = String::new();
-        line.push_str("# ");
-        line.push_str(&warn.panel_id);
-        line.push(':');
-        for (i, gene) in warn.missing_required.iter().enumerate() {
-            if i > 0 {
-                line.push(',');
-            }
-            line.push_str(gene);
-        }
-        line.push('\n');
-        writer.write_all(line.as_bytes())?;
+        let missing = warn
+            .missing_required
+            .iter()
+            .map(|gene| field("missing_required", gene))
+            .collect::<Result<Vec<_>, _>>()?;
+        writeln!(
+            writer,
+            "{}\t{}\t{}\t{}",
+            field("panel_id", &warn.panel_id)?,
+            field("axis", &warn.axis)?,
+            missing.join(","),
+            fixed6(warn.mappable_fraction)
+        )?;
     }
+    writer.commit()?;
     Ok(())
 }
 
@@ -1,21 +1,64 @@
 use std::io::Write;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
-
-use crate::model::axes::{AxisConfig, AxisCoverage, AxisValues, saturating_map};
-use crate::model::drivers::{format_drivers, format_eeb_drivers, top_k_eeb_drivers, top_k_panels};
+use tracing::warn;
+
+use crate::model::axes::{AxisConfig, AxisCoverage, AxisValues, CoverageMode};
+use crate::model::axis_curves::AxisCurves;
+use crate::model::catalog::{N_REQUIRED_PANEL_AXES, PanelAxis, REQUIRED_PANEL_AXIS_TAGS};
+use crate::model::drivers::{
+    ZeroDrivers, format_drivers, format_eeb_drivers, top_k_eeb_drivers, top_k_panels,
+};
+use crate::model::stats::{NanPolicy, fraction_ge, percentiles_interpolated};
+use crate::pipeline::cancel::{Cancelled, checkpoint};
+use crate::pipeline::chunk_progress;
+use crate::pipeline::outputs::per_cell_tables;
+use crate::pipeline::runner::{StageCells, observe};
 use crate::pipeline::stage1_load::DatasetCtx;
 use crate::pipeline::stage3_panels::{PanelCellPacked, PanelsContext};
+use crate::report::artifact::{Artifact, WriteError, into_write_error, write_artifact};
+use crate::report::format::signed_or_nan;
+use crate::report::tsv::{UnsafeField, field};
 
 #[derive(Debug, Error)]
 pub enum Stage4Error {
     #[error("io error: {0}")]
-    Io(#[from] std::io::Error),
+    Io(std::io::Error),
+    #[error("io error writing {}: {source}", path.display())]
+    Write {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("json error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("{0}")]
+    Cancelled(#[from] Cancelled),
+    #[error("{0}")]
+    Field(#[from] UnsafeField),
+    #[error(
+        "core axes without a panel that has a mappable gene: {axes}; check the panels directory or pass --allow-missing-axes"
+    )]
+    MissingAxes { axes: String },
+}
+
+/// Artifact write errors keep their path; other I/O errors stay `Io`.
+impl From<std::io::Error> for Stage4Error {
+    fn from(e: std::io::Error) -> Self {
+        match into_write_error(e) {
+            Ok(WriteError { path, source }) => Stage4Error::Write { path, source },
+            Err(e) => Stage4Error::Io(e),
+        }
+    }
 }
 
-#[derive(Debug, Clone, Serialize)]
+/// Axes that must have at least one panel with a mappable gene: the panel
+/// axes of the catalog's required axes. APCI is optional and handled through
+/// `IaiWeightSet`.
+pub const CORE_AXES: [&str; N_REQUIRED_PANEL_AXES] = REQUIRED_PANEL_AXIS_TAGS;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AxisDrivers {
     pub sia: String,
     pub eeb: String,
@@ -26,32 +69,203 @@ pub struct AxisDrivers {
     pub gdi: String,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AxesContext {
     pub cell_ids: Vec<String>,
     pub values: Vec<AxisValues>,
     pub coverage: Vec<AxisCoverage>,
     pub drivers: Vec<AxisDrivers>,
     pub stats: AxesSummary,
+    pub coverage_mode: CoverageMode,
+    pub panel_counts: AxisPanelCounts,
+    /// Dead panels left out of the axes under `--drop-dead-panels`.
+    pub dropped_panels: Vec<String>,
+    /// `--axis-curves` used in place of `saturating_map`, for provenance.
+    pub curves: Option<AxisCurves>,
 }
 
-#[derive(Debug, Clone, Serialize)]
+/// Panels assigned to one axis: defined, with at least one mappable gene,
+/// and the mappable genes across them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AxisPanelCount {
+    pub panels: usize,
+    pub mapped_panels: usize,
+    pub mappable_genes: usize,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AxisPanelCounts {
+    #[serde(rename = "SIA")]
+    pub sia: AxisPanelCount,
+    #[serde(rename = "EEB_EXPORT")]
+    pub eeb_export: AxisPanelCount,
+    #[serde(rename = "EEB_DEGRADE")]
+    pub eeb_degrade: AxisPanelCount,
+    #[serde(rename = "SLI")]
+    pub sli: AxisPanelCount,
+    #[serde(rename = "MEI")]
+    pub mei: AxisPanelCount,
+    #[serde(rename = "ECMI")]
+    pub ecmi: AxisPanelCount,
+    #[serde(rename = "APCI")]
+    pub apci: AxisPanelCount,
+    #[serde(rename = "GDI")]
+    pub gdi: AxisPanelCount,
+}
+
+impl AxisPanelCounts {
+    /// `(panel axis name, count)` in panel-axis order.
+    pub fn entries(&self) -> [(&'static str, AxisPanelCount); 8] {
+        [
+            ("SIA", self.sia),
+            ("EEB_EXPORT", self.eeb_export),
+            ("EEB_DEGRADE", self.eeb_degrade),
+            ("SLI", self.sli),
+            ("MEI", self.mei),
+            ("ECMI", self.ecmi),
+            ("APCI", self.apci),
+            ("GDI", self.gdi),
+        ]
+    }
+
+    /// Entries of [`CORE_AXES`] without a mapped panel.
+    pub fn missing_core_axes(&self) -> Vec<(&'static str, AxisPanelCount)> {
+        self.entries()
+            .into_iter()
+            .filter(|(axis, count)| CORE_AXES.contains(axis) && count.mapped_panels == 0)
+            .collect()
+    }
+
+    /// Present axes as reported in [`AxesSummary`]. EEB needs both of its
+    /// panel axes; APCI is present whenever it has panels, mapped or not.
+    pub fn presence(&self) -> AxisPresence {
+        let mapped = |c: AxisPanelCount| c.mapped_panels > 0;
+        AxisPresence {
+            sia: mapped(self.sia),
+            eeb: mapped(self.eeb_export) && mapped(self.eeb_degrade),
+            sli: mapped(self.sli),
+            mei: mapped(self.mei),
+            ecmi: mapped(self.ecmi),
+            apci: self.apci.panels > 0,
+            gdi: mapped(self.gdi),
+        }
+    }
+}
+
+/// Panel sums per axis before `saturating_map`; EEB keeps its export and
+/// degrade sides. Written to `axes.tsv` only under `--axes-raw`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AxisRawSums {
+    pub sia: f32,
+    pub eeb_export: f32,
+    pub eeb_degrade: f32,
+    pub sli: f32,
+    pub mei: f32,
+    pub ecmi: f32,
+    pub apci: f32,
+    pub gdi: f32,
+}
+
+impl AxisRawSums {
+    const HEADER: &'static str =
+        "\traw_SIA\traw_EEB_EXPORT\traw_EEB_DEGRADE\traw_SLI\traw_MEI\traw_ECMI\traw_APCI\traw_GDI";
+
+    /// Tab-prefixed columns matching [`Self::HEADER`]; absent axes are NaN.
+    fn columns(&self) -> String {
+        [
+            self.sia,
+            self.eeb_export,
+            self.eeb_degrade,
+            self.sli,
+            self.mei,
+            self.ecmi,
+            self.apci,
+            self.gdi,
+        ]
+        .iter()
+        .map(|v| format!("\t{}", signed_or_nan(*v)))
+        .collect()
+    }
+}
+
+/// Which of the seven reported axes carry values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AxisPresence {
+    pub sia: bool,
+    pub eeb: bool,
+    pub sli: bool,
+    pub mei: bool,
+    pub ecmi: bool,
+    pub apci: bool,
+    pub gdi: bool,
+}
+
+impl AxisPresence {
+    /// Absent axes get NaN values, zero coverage and no drivers.
+    fn mask(
+        &self,
+        vals: &mut AxisValues,
+        cov: &mut AxisCoverage,
+        drv: &mut AxisDrivers,
+        raw: &mut AxisRawSums,
+    ) {
+        let axes = [
+            (self.sia, &mut vals.sia, &mut cov.sia, &mut drv.sia),
+            (self.eeb, &mut vals.eeb, &mut cov.eeb, &mut drv.eeb),
+            (self.sli, &mut vals.sli, &mut cov.sli, &mut drv.sli),
+            (self.mei, &mut vals.mei, &mut cov.mei, &mut drv.mei),
+            (self.ecmi, &mut vals.ecmi, &mut cov.ecmi, &mut drv.ecmi),
+            (self.apci, &mut vals.apci, &mut cov.apci, &mut drv.apci),
+            (self.gdi, &mut vals.gdi, &mut cov.gdi, &mut drv.gdi),
+        ];
+        for (present, value, coverage, drivers) in axes {
+            if !present {
+                *value = f32::NAN;
+                *coverage = 0.0;
+                *drivers = ".".to_string();
+            }
+        }
+        let raw_axes = [
+            (self.sia, &mut raw.sia),
+            (self.eeb, &mut raw.eeb_export),
+            (self.eeb, &mut raw.eeb_degrade),
+            (self.sli, &mut raw.sli),
+            (self.mei, &mut raw.mei),
+            (self.ecmi, &mut raw.ecmi),
+            (self.apci, &mut raw.apci),
+            (self.gdi, &mut raw.gdi),
+        ];
+        for (present, value) in raw_axes {
+            if !present {
+                *value = f32::NAN;
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AxisStats {
+    #[serde(serialize_with = "crate::report::json::fixed6")]
     pub median: f32,
+    #[serde(serialize_with = "crate::report::json::fixed6")]
     pub p90: f32,
+    #[serde(serialize_with = "crate::report::json::fixed6")]
     pub p99: f32,
+    #[serde(serialize_with = "crate::report::json::fixed6")]
     pub frac_ge_0_65: f32,
+    #[serde(serialize_with = "crate::report::json::fixed6")]
     pub frac_ge_0_80: f32,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AxisSummaryEntry {
     pub present: bool,
     pub value: AxisStats,
     pub coverage: AxisStats,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AxesSummary {
     pub sia: AxisSummaryEntry,
     pub eeb: AxisSummaryEntry,
@@ -63,60 +277,124 @@ pub struct AxesSummary {
 }
 
 pub fn run_stage4_axes(
+    ctx: &DatasetCtx,
+    panels_ctx: &PanelsContext,
+    out_dir: &Path,
+) -> Result<AxesContext, Stage4Error> {
+    run_stage4_axes_with_config(ctx, panels_ctx, out_dir, &AxisConfig::default())
+}
+
+pub fn run_stage4_axes_with_config(
     _ctx: &DatasetCtx,
     panels_ctx: &PanelsContext,
     out_dir: &Path,
+    cfg: &AxisConfig,
 ) -> Result<AxesContext, Stage4Error> {
-    let cfg = AxisConfig::default();
-    let indices = build_axis_indices(&panels_ctx.panels);
+    let dropped_panels: Vec<String> = if cfg.drop_dead_panels {
+        panels_ctx
+            .dead_panels
+            .iter()
+            .map(|p| p.panel_id.clone())
+            .collect()
+    } else {
+        Vec::new()
+    };
+    if !dropped_panels.is_empty() {
+        warn!(panels = %dropped_panels.join(", "), "dead panels excluded from axes");
+    }
+    let indices = build_axis_indices(&panels_ctx.panels, &dropped_panels);
+    let panel_counts = count_axis_panels(&indices, panels_ctx);
+    let missing = panel_counts.missing_core_axes();
+    if !missing.is_empty() {
+        let axes = missing
+            .iter()
+            .map(|(axis, c)| {
+                format!(
+                    "{axis} (panels={}, mappable_genes={})",
+                    c.panels, c.mappable_genes
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        if !cfg.allow_missing_axes {
+            return Err(Stage4Error::MissingAxes { axes });
+        }
+        warn!(axes = %axes, "core axes not present; their values are NaN with coverage 0");
+    }
+    let presence = panel_counts.presence();
 
     let mut values = Vec::with_capacity(panels_ctx.cell_ids.len());
     let mut coverage = Vec::with_capacity(panels_ctx.cell_ids.len());
     let mut drivers = Vec::with_capacity(panels_ctx.cell_ids.len());
 
-    let report_path = out_dir.join("axes.tsv");
-    let mut writer = std::io::BufWriter::new(std::fs::File::create(&report_path)?);
-    writer.write_all(b"cell_id\tSIA\tEEB\tSLI\tMEI\tECMI\tAPCI\tGDI\tcov_SIA\tcov_EEB\tcov_SLI\tcov_MEI\tcov_ECMI\tcov_APCI\tcov_GDI\tdrivers_SIA\tdrivers_EEB\tdrivers_SLI\tdrivers_MEI\tdrivers_ECMI\tdrivers_APCI\tdrivers_GDI\n")?;
+    let mut writer = per_cell_tables()
+        .then(|| Artifact::create(out_dir, "axes.tsv"))
+        .transpose()?;
+    if let Some(writer) = writer.as_mut() {
+        writer.write_all(b"cell_id\tSIA\tEEB\tSLI\tMEI\tECMI\tAPCI\tGDI\tcov_SIA\tcov_EEB\tcov_SLI\tcov_MEI\tcov_ECMI\tcov_APCI\tcov_GDI\tdrivers_SIA\tdrivers_EEB\tdrivers_SLI\tdrivers_MEI\tdrivers_ECMI\tdrivers_APCI\tdrivers_GDI")?;
+        if cfg.raw_sums {
+            writer.write_all(AxisRawSums::HEADER.as_bytes())?;
+        }
+        writer.write_all(b"\n")?;
+    }
 
     for (cell_idx, cell_id) in panels_ctx.cell_ids.iter().enumerate() {
         let packed = &panels_ctx.per_cell[cell_idx];
-        let (vals, cov, drv) = compute_cell_axes(&indices, panels_ctx, packed, &cfg);
-
-        let line = format!(
-            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
-            cell_id,
-            format_f32(vals.sia),
-            format_f32(vals.eeb),
-            format_f32(vals.sli),
-            format_f32(vals.mei),
-            format_f32(vals.ecmi),
-            format_f32(vals.apci),
-            format_f32(vals.gdi),
-            format_f32(cov.sia),
-            format_f32(cov.eeb),
-            format_f32(cov.sli),
-            format_f32(cov.mei),
-            format_f32(cov.ecmi),
-            format_f32(cov.apci),
-            format_f32(cov.gdi),
-            drv.sia,
-            drv.eeb,
-            drv.sli,
-            drv.mei,
-            drv.ecmi,
-            drv.apci,
-            drv.gdi
-        );
-        writer.write_all(line.as_bytes())?;
+        let (mut vals, mut cov, mut drv, mut raw) =
+            compute_cell_axes(&indices, panels_ctx, packed, cfg);
+        presence.mask(&mut vals, &mut cov, &mut drv, &mut raw);
+
+        if let Some(writer) = writer.as_mut() {
+            let mut line = format!(
+                "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                field("cell_id", cell_id)?,
+                signed_or_nan(vals.sia),
+                signed_or_nan(vals.eeb),
+                signed_or_nan(vals.sli),
+                signed_or_nan(vals.mei),
+                signed_or_nan(vals.ecmi),
+                signed_or_nan(vals.apci),
+                signed_or_nan(vals.gdi),
+                signed_or_nan(cov.sia),
+                signed_or_nan(cov.eeb),
+                signed_or_nan(cov.sli),
+                signed_or_nan(cov.mei),
+                signed_or_nan(cov.ecmi),
+                signed_or_nan(cov.apci),
+                signed_or_nan(cov.gdi),
+                field("drivers_SIA", &drv.sia)?,
+                field("drivers_EEB", &drv.eeb)?,
+                field("drivers_SLI", &drv.sli)?,
+                field("drivers_MEI", &drv.mei)?,
+                field("drivers_ECMI", &drv.ecmi)?,
+                field("drivers_APCI", &drv.apci)?,
+                field("drivers_GDI", &drv.gdi)?
+            );
+            if cfg.raw_sums {
+                line.push_str(&raw.columns());
+            }
+            line.push('\n');
+            writer.write_all(line.as_bytes())?;
+        }
 
         values.push(vals);
         coverage.push(cov);
         drivers.push(drv);
+        chunk_progress("stage4_axes", cell_idx + 1, panels_ctx.cell_ids.len());
+        observe(StageCells::Axes(&values));
+        checkpoint(cell_idx + 1)?;
     }
 
-    writer.flush()?;
+    if let Some(writer) = writer {
+        writer.commit()?;
+    }
 
-    let stats = compute_summary(&values, &coverage, &indices);
+    let stats = compute_summary(&values, &coverage, &presence);
+    write_artifact(
+        out_dir,
+        "axes_summary.json",
+        serde_json::to_string_pretty(&stats)?,
+    )?;
 
     Ok(AxesContext {
         cell_ids: panels_ctx.cell_ids.clone(),
@@ -124,6 +402,10 @@ pub fn run_stage4_axes(
         coverage,
         drivers,
         stats,
+        coverage_mode: cfg.coverage_mode,
+        panel_counts,
+        dropped_panels,
+        curves: cfg.curves.clone(),
     })
 }
 
@@ -132,7 +414,7 @@ fn compute_cell_axes(
     panels_ctx: &PanelsContext,
     packed: &PanelCellPacked,
     cfg: &AxisConfig,
-) -> (AxisValues, AxisCoverage, AxisDrivers) {
+) -> (AxisValues, AxisCoverage, AxisDrivers, AxisRawSums) {
     let sia_raw = sum_panels(&indices.sia, packed);
     let sli_raw = sum_panels(&indices.sli, packed);
     let mei_raw = sum_panels(&indices.mei, packed);
@@ -160,41 +442,43 @@ fn compute_cell_axes(
         0.0
     };
 
-    let sia = saturating_map(sia_raw, cfg.k);
-    let sli = saturating_map(sli_raw, cfg.k);
-    let mei = saturating_map(mei_raw, cfg.k);
-    let ecmi = saturating_map(ecmi_raw, cfg.k);
-    let gdi = saturating_map(gdi_raw, cfg.k);
+    let sia = cfg.map_axis("SIA", sia_raw);
+    let sli = cfg.map_axis("SLI", sli_raw);
+    let mei = cfg.map_axis("MEI", mei_raw);
+    let ecmi = cfg.map_axis("ECMI", ecmi_raw);
+    let gdi = cfg.map_axis("GDI", gdi_raw);
     let apci = if apci_present {
-        saturating_map(apci_raw, cfg.k)
+        cfg.map_axis("APCI", apci_raw)
     } else {
         f32::NAN
     };
 
-    let cov_sia = coverage_axis(&indices.sia, panels_ctx, packed);
-    let cov_sli = coverage_axis(&indices.sli, panels_ctx, packed);
-    let cov_mei = coverage_axis(&indices.mei, panels_ctx, packed);
-    let cov_ecmi = coverage_axis(&indices.ecmi, panels_ctx, packed);
-    let cov_gdi = coverage_axis(&indices.gdi, panels_ctx, packed);
+    let mode = cfg.coverage_mode;
+    let cov_sia = coverage_axis(&indices.sia, panels_ctx, packed, mode);
+    let cov_sli = coverage_axis(&indices.sli, panels_ctx, packed, mode);
+    let cov_mei = coverage_axis(&indices.mei, panels_ctx, packed, mode);
+    let cov_ecmi = coverage_axis(&indices.ecmi, panels_ctx, packed, mode);
+    let cov_gdi = coverage_axis(&indices.gdi, panels_ctx, packed, mode);
     let cov_eeb = coverage_axis_union(
         &indices.eeb_export,
         &indices.eeb_degrade,
         panels_ctx,
         packed,
+        mode,
     );
     let cov_apci = if apci_present {
-        coverage_axis(&indices.apci, panels_ctx, packed)
+        coverage_axis(&indices.apci, panels_ctx, packed, mode)
     } else {
         0.0
     };
 
-    let drivers_sia = drivers_for_axis(&indices.sia, panels_ctx, packed, 3);
-    let drivers_sli = drivers_for_axis(&indices.sli, panels_ctx, packed, 3);
-    let drivers_mei = drivers_for_axis(&indices.mei, panels_ctx, packed, 3);
-    let drivers_ecmi = drivers_for_axis(&indices.ecmi, panels_ctx, packed, 3);
-    let drivers_gdi = drivers_for_axis(&indices.gdi, panels_ctx, packed, 3);
+    let drivers_sia = drivers_for_axis(&indices.sia, panels_ctx, packed, 3, cfg.zero_drivers);
+    let drivers_sli = drivers_for_axis(&indices.sli, panels_ctx, packed, 3, cfg.zero_drivers);
+    let drivers_mei = drivers_for_axis(&indices.mei, panels_ctx, packed, 3, cfg.zero_drivers);
+    let drivers_ecmi = drivers_for_axis(&indices.ecmi, panels_ctx, packed, 3, cfg.zero_drivers);
+    let drivers_gdi = drivers_for_axis(&indices.gdi, panels_ctx, packed, 3, cfg.zero_drivers);
     let drivers_apci = if apci_present {
-        drivers_for_axis(&indices.apci, panels_ctx, packed, 3)
+        drivers_for_axis(&indices.apci, panels_ctx, packed, 3, cfg.zero_drivers)
     } else {
         ".".to_string()
     };
@@ -204,6 +488,7 @@ fn compute_cell_axes(
         &indices.eeb_degrade,
         panels_ctx,
         packed,
+        cfg.zero_drivers,
     );
 
     (
@@ -234,6 +519,16 @@ fn compute_cell_axes(
             apci: drivers_apci,
             gdi: drivers_gdi,
         },
+        AxisRawSums {
+            sia: sia_raw,
+            eeb_export: export_raw,
+            eeb_degrade: degrade_raw,
+            sli: sli_raw,
+            mei: mei_raw,
+            ecmi: ecmi_raw,
+            apci: if apci_present { apci_raw } else { f32::NAN },
+            gdi: gdi_raw,
+        },
     )
 }
 
@@ -245,14 +540,13 @@ fn sum_panels(indices: &[usize], packed: &PanelCellPacked) -> f32 {
     sum
 }
 
-fn coverage_axis(indices: &[usize], panels_ctx: &PanelsContext, packed: &PanelCellPacked) -> f32 {
-    let (required_total, required_missing) = coverage_counts(indices, panels_ctx, packed);
-    if required_total == 0 {
-        1.0
-    } else {
-        let cov = 1.0 - (required_missing as f32 / required_total as f32);
-        cov.min(1.0).max(0.0)
-    }
+fn coverage_axis(
+    indices: &[usize],
+    panels_ctx: &PanelsContext,
+    packed: &PanelCellPacked,
+    mode: CoverageMode,
+) -> f32 {
+    coverage_counts(indices, panels_ctx, packed).coverage(mode)
 }
 
 fn coverage_axis_union(
@@ -260,16 +554,49 @@ fn coverage_axis_union(
     degrade_idx: &[usize],
     panels_ctx: &PanelsContext,
     packed: &PanelCellPacked,
+    mode: CoverageMode,
 ) -> f32 {
-    let (total_a, missing_a) = coverage_counts(export_idx, panels_ctx, packed);
-    let (total_b, missing_b) = coverage_counts(degrade_idx, panels_ctx, packed);
-    let total = total_a + total_b;
-    let missing = missing_a + missing_b;
-    if total == 0 {
-        1.0
-    } else {
-        let cov = 1.0 - (missing as f32 / total as f32);
-        cov.min(1.0).max(0.0)
+    let a = coverage_counts(export_idx, panels_ctx, packed);
+    let b = coverage_counts(degrade_idx, panels_ctx, packed);
+    CoverageCounts {
+        required_total: a.required_total + b.required_total,
+        required_missing: a.required_missing + b.required_missing,
+        mappable_total: a.mappable_total + b.mappable_total,
+        hits_total: a.hits_total + b.hits_total,
+    }
+    .coverage(mode)
+}
+
+/// Counters for both coverage definitions, gathered in a single pass over an
+/// axis' panels so the mode choice does not require a second traversal.
+#[derive(Debug, Clone, Copy)]
+struct CoverageCounts {
+    required_total: u32,
+    required_missing: u32,
+    mappable_total: u32,
+    hits_total: u32,
+}
+
+impl CoverageCounts {
+    fn coverage(&self, mode: CoverageMode) -> f32 {
+        match mode {
+            CoverageMode::Required => {
+                if self.required_total == 0 {
+                    1.0
+                } else {
+                    let cov = 1.0 - (self.required_missing as f32 / self.required_total as f32);
+                    cov.min(1.0).max(0.0)
+                }
+            }
+            CoverageMode::Detection => {
+                if self.mappable_total == 0 {
+                    0.0
+                } else {
+                    let cov = self.hits_total as f32 / self.mappable_total as f32;
+                    cov.min(1.0).max(0.0)
+                }
+            }
+        }
     }
 }
 
@@ -277,14 +604,22 @@ fn coverage_counts(
     indices: &[usize],
     panels_ctx: &PanelsContext,
     packed: &PanelCellPacked,
-) -> (u32, u32) {
-    let mut total = 0u32;
-    let mut missing = 0u32;
+) -> CoverageCounts {
+    let mut counts = CoverageCounts {
+        required_total: 0,
+        required_missing: 0,
+        mappable_total: 0,
+        hits_total: 0,
+    };
     for idx in indices {
-        total += panels_ctx.mappings[*idx].required_total as u32;
-        missing += packed.required_missing[*idx];
+        let mapping = &panels_ctx.mappings[*idx];
+        let mappable = mapping.mapped.iter().filter(|m| m.is_some()).count() as u32;
+        counts.required_total += mapping.required_total as u32;
+        counts.required_missing += packed.required_missing[*idx];
+        counts.mappable_total += mappable;
+        counts.hits_total += packed.hits[*idx].min(mappable);
     }
-    (total, missing)
+    counts
 }
 
 fn drivers_for_axis(
@@ -292,6 +627,7 @@ fn drivers_for_axis(
     panels_ctx: &PanelsContext,
     packed: &PanelCellPacked,
     k: usize,
+    zero: ZeroDrivers,
 ) -> String {
     if indices.is_empty() {
         return ".".to_string();
@@ -302,7 +638,7 @@ fn drivers_for_axis(
         ids.push(panels_ctx.panels.panels[*idx].id.clone());
         vals.push(packed.sums[*idx]);
     }
-    let drivers = top_k_panels(&ids, &vals, k);
+    let drivers = top_k_panels(&ids, &vals, k, zero);
     format_drivers(&drivers)
 }
 
@@ -311,6 +647,7 @@ fn drivers_for_eeb(
     degrade_idx: &[usize],
     panels_ctx: &PanelsContext,
     packed: &PanelCellPacked,
+    zero: ZeroDrivers,
 ) -> String {
     let mut export_ids = Vec::with_capacity(export_idx.len());
     let mut export_vals = Vec::with_capacity(export_idx.len());
@@ -325,12 +662,18 @@ fn drivers_for_eeb(
         degrade_vals.push(packed.sums[*idx]);
     }
 
-    let (export, degrade) =
-        top_k_eeb_drivers(&export_ids, &export_vals, &degrade_ids, &degrade_vals, 2);
+    let (export, degrade) = top_k_eeb_drivers(
+        &export_ids,
+        &export_vals,
+        &degrade_ids,
+        &degrade_vals,
+        2,
+        zero,
+    );
     format_eeb_drivers(&export, &degrade)
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 struct AxisIndices {
     sia: Vec<usize>,
     eeb_export: Vec<usize>,
@@ -342,83 +685,107 @@ struct AxisIndices {
     gdi: Vec<usize>,
 }
 
-fn build_axis_indices(panels: &crate::panels::defs::PanelSet) -> AxisIndices {
-    let mut indices = AxisIndices {
-        sia: Vec::new(),
-        eeb_export: Vec::new(),
-        eeb_degrade: Vec::new(),
-        sli: Vec::new(),
-        mei: Vec::new(),
-        ecmi: Vec::new(),
-        apci: Vec::new(),
-        gdi: Vec::new(),
-    };
+impl AxisIndices {
+    fn slot_mut(&mut self, axis: PanelAxis) -> &mut Vec<usize> {
+        match axis {
+            PanelAxis::Sia => &mut self.sia,
+            PanelAxis::EebExport => &mut self.eeb_export,
+            PanelAxis::EebDegrade => &mut self.eeb_degrade,
+            PanelAxis::Sli => &mut self.sli,
+            PanelAxis::Mei => &mut self.mei,
+            PanelAxis::Ecmi => &mut self.ecmi,
+            PanelAxis::Apci => &mut self.apci,
+            PanelAxis::Gdi => &mut self.gdi,
+        }
+    }
+}
 
+/// Panel indices per [`PanelAxis`], skipping panels whose id is in
+/// `dropped`; panels with other axis tags are not summed.
+fn build_axis_indices(panels: &crate::panels::defs::PanelSet, dropped: &[String]) -> AxisIndices {
+    let mut indices = AxisIndices::default();
     for (idx, panel) in panels.panels.iter().enumerate() {
-        match panel.axis.as_str() {
-            "SIA" => indices.sia.push(idx),
-            "EEB_EXPORT" => indices.eeb_export.push(idx),
-            "EEB_DEGRADE" => indices.eeb_degrade.push(idx),
-            "SLI" => indices.sli.push(idx),
-            "MEI" => indices.mei.push(idx),
-            "ECMI" => indices.ecmi.push(idx),
-            "APCI" => indices.apci.push(idx),
-            "GDI" => indices.gdi.push(idx),
-            _ => {}
+        if dropped.contains(&panel.id) {
+            continue;
+        }
+        if let Some(axis) = PanelAxis::from_tag(&panel.axis) {
+            indices.slot_mut(axis).push(idx);
         }
     }
-
     indices
 }
 
-fn format_f32(value: f32) -> String {
-    if value.is_nan() {
-        "nan".to_string()
-    } else {
-        format!("{:.6}", value)
+fn count_axis_panels(indices: &AxisIndices, panels_ctx: &PanelsContext) -> AxisPanelCounts {
+    let count = |idx: &[usize]| {
+        let mut out = AxisPanelCount {
+            panels: idx.len(),
+            ..AxisPanelCount::default()
+        };
+        for &i in idx {
+            let mappable = panels_ctx.mappings[i]
+                .mapped
+                .iter()
+                .filter(|m| m.is_some())
+                .count();
+            if mappable > 0 {
+                out.mapped_panels += 1;
+            }
+            out.mappable_genes += mappable;
+        }
+        out
+    };
+    AxisPanelCounts {
+        sia: count(&indices.sia),
+        eeb_export: count(&indices.eeb_export),
+        eeb_degrade: count(&indices.eeb_degrade),
+        sli: count(&indices.sli),
+        mei: count(&indices.mei),
+        ecmi: count(&indices.ecmi),
+        apci: count(&indices.apci),
+        gdi: count(&indices.gdi),
     }
 }
 
-fn compute_summary(
+pub(crate) fn compute_summary(
     values: &[AxisValues],
     coverage: &[AxisCoverage],
-    indices: &AxisIndices,
+    presence: &AxisPresence,
 ) -> AxesSummary {
     AxesSummary {
         sia: summary_entry(
             values.iter().map(|v| v.sia),
             coverage.iter().map(|c| c.sia),
-            true,
+            presence.sia,
         ),
         eeb: summary_entry(
             values.iter().map(|v| v.eeb),
             coverage.iter().map(|c| c.eeb),
-            true,
+            presence.eeb,
         ),
         sli: summary_entry(
             values.iter().map(|v| v.sli),
             coverage.iter().map(|c| c.sli),
-            true,
+            presence.sli,
         ),
         mei: summary_entry(
             values.iter().map(|v| v.mei),
             coverage.iter().map(|c| c.mei),
-            true,
+            presence.mei,
         ),
         ecmi: summary_entry(
             values.iter().map(|v| v.ecmi),
             coverage.iter().map(|c| c.ecmi),
-            true,
+            presence.ecmi,
         ),
         apci: summary_entry(
             values.iter().map(|v| v.apci),
             coverage.iter().map(|c| c.apci),
-            !indices.apci.is_empty(),
+            presence.apci,
         ),
         gdi: summary_entry(
             values.iter().map(|v| v.gdi),
             coverage.iter().map(|c| c.gdi),
-            true,
+            presence.gdi,
         ),
     }
 }
@@ -428,10 +795,10 @@ where
     I1: Iterator<Item = f32>,
     I2: Iterator<Item = f32>,
 {
-    let mut vals: Vec<f32> = values.filter(|v| !v.is_nan()).collect();
-    let mut covs: Vec<f32> = coverage.filter(|v| !v.is_nan()).collect();
-    let value_stats = stats_from_vec(&mut vals);
-    let coverage_stats = stats_from_vec(&mut covs);
+    let vals: Vec<f32> = values.filter(|v| !v.is_nan()).collect();
+    let covs: Vec<f32> = coverage.filter(|v| !v.is_nan()).collect();
+    let value_stats = stats_from_vec(&vals);
+    let coverage_stats = stats_from_vec(&covs);
     AxisSummaryEntry {
         present,
         value: value_stats,
@@ -439,53 +806,19 @@ where
     }
 }
 
-fn stats_from_vec(values: &mut Vec<f32>) -> AxisStats {
-    if values.is_empty() {
-        return AxisStats {
-            median: f32::NAN,
-            p90: f32::NAN,
-            p99: f32::NAN,
-            frac_ge_0_65: 0.0,
-            frac_ge_0_80: 0.0,
-        };
-    }
-    values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
-    let median = percentile(values, 0.5);
-    let p90 = percentile(values, 0.9);
-    let p99 = percentile(values, 0.99);
-    let frac_ge_0_65 = fraction_ge(values, 0.65);
-    let frac_ge_0_80 = fraction_ge(values, 0.80);
+fn stats_from_vec(values: &[f32]) -> AxisStats {
+    let q = percentiles_interpolated(values, &[0.5, 0.9, 0.99], NanPolicy::Skip);
+    let frac_ge_0_65 = fraction_ge(values, 0.65, NanPolicy::Skip);
+    let frac_ge_0_80 = fraction_ge(values, 0.80, NanPolicy::Skip);
     AxisStats {
-        median,
-        p90,
-        p99,
+        median: q[0],
+        p90: q[1],
+        p99: q[2],
         frac_ge_0_65,
         frac_ge_0_80,
     }
 }
 
-fn percentile(values: &[f32], p: f32) -> f32 {
-    if values.is_empty() {
-        return f32::NAN;
-    }
-    let n = values.len();
-    let idx = ((p * (n as f32 - 1.0)).floor() as usize).min(n - 1);
-    values[idx]
-}
-
-fn fraction_ge(values: &[f32], threshold: f32) -> f32 {
-    if values.is_empty() {
-        return 0.0;
-    }
-    let mut count = 0usize;
-    for v in values {
-        if *v >= threshold {
-            count += 1;
-        }
-    }
-    count as f32 / values.len() as f32
-}
-
 #[cfg(test)]
 #[path = "../../tests/src_inline/pipeline/stage4_axes.rs"]
 mod tests;
@@ -1,35 +1,74 @@
 use std::io::Write;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use thiserror::Error;
+use tracing::warn;
 
-use crate::model::drivers::top_k_components;
-use crate::model::scores::{WeightsDefault, clamp01, pos_eeb};
+use crate::model::axes::AxisValues;
+use crate::model::catalog::{CompositeDef, WeightSet, composites};
+use crate::model::drivers::{ZeroDrivers, contribution_concentration, top_k_components};
+use crate::model::scores::{IaiWeightSet, WeightsDefault, clamp01, pos_eeb};
+use crate::model::stats::{NanPolicy, fraction_ge, percentiles_interpolated};
+use crate::pipeline::cancel::{Cancelled, checkpoint};
+use crate::pipeline::chunk_progress;
+use crate::pipeline::outputs::per_cell_tables;
+use crate::pipeline::runner::{StageCells, observe};
 use crate::pipeline::stage4_axes::AxesContext;
+use crate::report::artifact::{Artifact, WriteError, into_write_error, write_artifact};
+use crate::report::format::signed_or_nan;
+use crate::report::tsv::{UnsafeField, field};
 
 #[derive(Debug, Error)]
 pub enum Stage5Error {
     #[error("io error: {0}")]
-    Io(#[from] std::io::Error),
+    Io(std::io::Error),
+    #[error("io error writing {}: {source}", path.display())]
+    Write {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("json error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("{0}")]
+    Cancelled(#[from] Cancelled),
+    #[error("{0}")]
+    Field(#[from] UnsafeField),
 }
 
-#[derive(Debug, Clone, serde::Serialize)]
+/// Artifact write errors keep their path; other I/O errors stay `Io`.
+impl From<std::io::Error> for Stage5Error {
+    fn from(e: std::io::Error) -> Self {
+        match into_write_error(e) {
+            Ok(WriteError { path, source }) => Stage5Error::Write { path, source },
+            Err(e) => Stage5Error::Io(e),
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct CompositeStats {
+    #[serde(serialize_with = "crate::report::json::fixed6")]
     pub median: f32,
+    #[serde(serialize_with = "crate::report::json::fixed6")]
     pub p90: f32,
+    #[serde(serialize_with = "crate::report::json::fixed6")]
     pub p99: f32,
+    #[serde(serialize_with = "crate::report::json::fixed6")]
     pub frac_ge_0_65: f32,
+    #[serde(serialize_with = "crate::report::json::fixed6")]
     pub frac_ge_0_80: f32,
 }
 
-#[derive(Debug, Clone, serde::Serialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct CompositesSummary {
     pub oii: CompositeStats,
     pub iai: CompositeStats,
     pub esi: CompositeStats,
+    /// NaN cells (no positive OII contribution) are left out.
+    pub score_concentration: CompositeStats,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct ScoresContext {
     pub oii: Vec<f32>,
     pub iai: Vec<f32>,
@@ -40,14 +79,40 @@ pub struct ScoresContext {
     pub drivers_oii: Vec<String>,
     pub drivers_iai: Vec<String>,
     pub drivers_esi: Vec<String>,
+    /// Entropy-based concentration of each cell's weighted OII axis
+    /// contributions (see [`contribution_concentration`]); NaN when all are zero.
+    pub score_concentration: Vec<f32>,
     pub summary: CompositesSummary,
+    /// Dataset-level IAI weight set; every cell is scored with it.
+    pub iai_weightset: IaiWeightSet,
+    /// Cells whose APCI was NaN although the axis is present (scored as 0).
+    pub apci_nan_cells: usize,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ScoreOptions {
+    pub zero_drivers: ZeroDrivers,
 }
 
 pub fn run_stage5_scores(
     axes_ctx: &AxesContext,
     out_dir: &Path,
+) -> Result<ScoresContext, Stage5Error> {
+    run_stage5_scores_with_options(axes_ctx, out_dir, &ScoreOptions::default())
+}
+
+pub fn run_stage5_scores_with_options(
+    axes_ctx: &AxesContext,
+    out_dir: &Path,
+    opts: &ScoreOptions,
 ) -> Result<ScoresContext, Stage5Error> {
     let weights = WeightsDefault::default();
+    let iai_weightset = IaiWeightSet::from_apci_present(axes_ctx.stats.apci.present);
+    let catalog = composites(&weights);
+    let oii_terms = Terms::new(weight_set(&catalog, "OII", "default"));
+    let iai_terms = Terms::new(weight_set(&catalog, "IAI", iai_weightset.as_str()));
+    let esi_terms = Terms::new(weight_set(&catalog, "ESI", "default"));
+    let mut apci_nan_cells = 0usize;
 
     let mut oii = Vec::with_capacity(axes_ctx.values.len());
     let mut iai = Vec::with_capacity(axes_ctx.values.len());
@@ -58,96 +123,49 @@ pub fn run_stage5_scores(
     let mut drivers_oii = Vec::with_capacity(axes_ctx.values.len());
     let mut drivers_iai = Vec::with_capacity(axes_ctx.values.len());
     let mut drivers_esi = Vec::with_capacity(axes_ctx.values.len());
+    let mut score_concentration = Vec::with_capacity(axes_ctx.values.len());
 
-    let out_path = out_dir.join("composites.tsv");
-    let mut writer = std::io::BufWriter::new(std::fs::File::create(&out_path)?);
-    writer.write_all(b"cell_id\tOII\tIAI\tESI\tcov_OII\tcov_IAI\tcov_ESI\tdrivers_OII\tdrivers_IAI\tdrivers_ESI\n")?;
+    let mut writer = per_cell_tables()
+        .then(|| Artifact::create(out_dir, "composites.tsv"))
+        .transpose()?;
+    if let Some(writer) = writer.as_mut() {
+        writer.write_all(b"cell_id\tOII\tIAI\tESI\tcov_OII\tcov_IAI\tcov_ESI\tdrivers_OII\tdrivers_IAI\tdrivers_ESI\tscore_concentration\tiai_weightset\n")?;
+    }
 
     for (idx, cell_id) in axes_ctx.cell_ids.iter().enumerate() {
         let v = &axes_ctx.values[idx];
         let cov = &axes_ctx.coverage[idx];
 
-        let eeb_pos = pos_eeb(v.eeb);
-
-        let oii_val = clamp01(
-            weights.oii.sia * v.sia
-                + weights.oii.pos_eeb * eeb_pos
-                + weights.oii.sli * v.sli
-                + weights.oii.mei * v.mei
-                + weights.oii.ecmi * v.ecmi
-                + weights.oii.gdi * v.gdi,
-        );
-
-        let (iai_val, iai_driver) = if v.apci.is_nan() {
-            let val = clamp01(
-                weights.iai_no_apci.mei * v.mei
-                    + weights.iai_no_apci.gdi * v.gdi
-                    + weights.iai_no_apci.sia * v.sia
-                    + weights.iai_no_apci.pos_eeb * eeb_pos,
-            );
-            let names = ["MEI", "GDI", "SIA", "EEB_POS"];
-            let contribs = [
-                weights.iai_no_apci.mei * v.mei,
-                weights.iai_no_apci.gdi * v.gdi,
-                weights.iai_no_apci.sia * v.sia,
-                weights.iai_no_apci.pos_eeb * eeb_pos,
-            ];
-            (val, top_k_components(&names, &contribs, 3))
+        let apci = if iai_weightset == IaiWeightSet::WithApci && v.apci.is_nan() {
+            apci_nan_cells += 1;
+            0.0
         } else {
-            let val = clamp01(
-                weights.iai_with_apci.mei * v.mei
-                    + weights.iai_with_apci.gdi * v.gdi
-                    + weights.iai_with_apci.apci * v.apci
-                    + weights.iai_with_apci.sia * v.sia
-                    + weights.iai_with_apci.pos_eeb * eeb_pos,
-            );
-            let names = ["MEI", "GDI", "APCI", "SIA", "EEB_POS"];
-            let contribs = [
-                weights.iai_with_apci.mei * v.mei,
-                weights.iai_with_apci.gdi * v.gdi,
-                weights.iai_with_apci.apci * v.apci,
-                weights.iai_with_apci.sia * v.sia,
-                weights.iai_with_apci.pos_eeb * eeb_pos,
-            ];
-            (val, top_k_components(&names, &contribs, 3))
+            v.apci
+        };
+        let inputs = TermInputs {
+            values: v,
+            eeb_pos: pos_eeb(v.eeb),
+            apci,
         };
 
-        let esi_val = clamp01(
-            weights.esi.ecmi * v.ecmi
-                + weights.esi.mei * v.mei
-                + weights.esi.pos_eeb * eeb_pos
-                + weights.esi.sli * v.sli,
-        );
+        let oii_contribs = oii_terms.contribs(&inputs);
+        let oii_val = clamp01(oii_contribs.iter().sum());
+        let oii_driver = top_k_components(&oii_terms.names, &oii_contribs, 3, opts.zero_drivers);
+        let concentration = contribution_concentration(&oii_contribs);
 
-        let oii_driver = {
-            let names = ["SIA", "EEB_POS", "SLI", "MEI", "ECMI", "GDI"];
-            let contribs = [
-                weights.oii.sia * v.sia,
-                weights.oii.pos_eeb * eeb_pos,
-                weights.oii.sli * v.sli,
-                weights.oii.mei * v.mei,
-                weights.oii.ecmi * v.ecmi,
-                weights.oii.gdi * v.gdi,
-            ];
-            top_k_components(&names, &contribs, 3)
-        };
-        let esi_driver = {
-            let names = ["ECMI", "MEI", "EEB_POS", "SLI"];
-            let contribs = [
-                weights.esi.ecmi * v.ecmi,
-                weights.esi.mei * v.mei,
-                weights.esi.pos_eeb * eeb_pos,
-                weights.esi.sli * v.sli,
-            ];
-            top_k_components(&names, &contribs, 3)
-        };
+        let iai_contribs = iai_terms.contribs(&inputs);
+        let iai_val = clamp01(iai_contribs.iter().sum());
+        let iai_driver = top_k_components(&iai_terms.names, &iai_contribs, 3, opts.zero_drivers);
+
+        let esi_contribs = esi_terms.contribs(&inputs);
+        let esi_val = clamp01(esi_contribs.iter().sum());
+        let esi_driver = top_k_components(&esi_terms.names, &esi_contribs, 3, opts.zero_drivers);
 
         let cov_oii_val = weighted_cov_oii(cov, &weights);
         let cov_esi_val = weighted_cov_esi(cov, &weights);
-        let cov_iai_val = if v.apci.is_nan() {
-            weighted_cov_iai_no_apci(cov, &weights)
-        } else {
-            weighted_cov_iai(cov, &weights)
+        let cov_iai_val = match iai_weightset {
+            IaiWeightSet::WithApci => weighted_cov_iai(cov, &weights),
+            IaiWeightSet::NoApci => weighted_cov_iai_no_apci(cov, &weights),
         };
 
         oii.push(oii_val);
@@ -159,30 +177,48 @@ pub fn run_stage5_scores(
         drivers_oii.push(oii_driver.clone());
         drivers_iai.push(iai_driver.clone());
         drivers_esi.push(esi_driver.clone());
+        score_concentration.push(concentration);
 
-        let line = format!(
-            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
-            cell_id,
-            format_f32(oii_val),
-            format_f32(iai_val),
-            format_f32(esi_val),
-            format_f32(cov_oii_val),
-            format_f32(cov_iai_val),
-            format_f32(cov_esi_val),
-            oii_driver,
-            iai_driver,
-            esi_driver
-        );
-        writer.write_all(line.as_bytes())?;
+        if let Some(writer) = writer.as_mut() {
+            let line = format!(
+                "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+                field("cell_id", cell_id)?,
+                signed_or_nan(oii_val),
+                signed_or_nan(iai_val),
+                signed_or_nan(esi_val),
+                signed_or_nan(cov_oii_val),
+                signed_or_nan(cov_iai_val),
+                signed_or_nan(cov_esi_val),
+                oii_driver,
+                iai_driver,
+                esi_driver,
+                signed_or_nan(concentration),
+                iai_weightset.as_str()
+            );
+            writer.write_all(line.as_bytes())?;
+        }
+        chunk_progress("stage5_scores", idx + 1, axes_ctx.cell_ids.len());
+        observe(StageCells::SecretoryLoad(&oii));
+        checkpoint(idx + 1)?;
     }
 
-    writer.flush()?;
+    if let Some(writer) = writer {
+        writer.commit()?;
+    }
+
+    if apci_nan_cells > 0 {
+        warn!(
+            cells = apci_nan_cells,
+            "APCI axis is present but NaN for some cells; scored with APCI = 0 under the with_apci IAI weight set"
+        );
+    }
 
-    let summary = CompositesSummary {
-        oii: summary_stats(&oii),
-        iai: summary_stats(&iai),
-        esi: summary_stats(&esi),
-    };
+    let summary = compute_summary(&oii, &iai, &esi, &score_concentration);
+    write_artifact(
+        out_dir,
+        "composites_summary.json",
+        serde_json::to_string_pretty(&summary)?,
+    )?;
 
     Ok(ScoresContext {
         oii,
@@ -194,10 +230,102 @@ pub fn run_stage5_scores(
         drivers_oii,
         drivers_iai,
         drivers_esi,
+        score_concentration,
         summary,
+        iai_weightset,
+        apci_nan_cells,
     })
 }
 
+/// Axis value a composite term reads.
+#[derive(Debug, Clone, Copy)]
+enum TermInput {
+    Sia,
+    PosEeb,
+    Sli,
+    Mei,
+    Ecmi,
+    Apci,
+    Gdi,
+}
+
+impl TermInput {
+    fn from_catalog(input: &str) -> Self {
+        match input {
+            "SIA" => TermInput::Sia,
+            "pos(EEB)" => TermInput::PosEeb,
+            "SLI" => TermInput::Sli,
+            "MEI" => TermInput::Mei,
+            "ECMI" => TermInput::Ecmi,
+            "APCI" => TermInput::Apci,
+            "GDI" => TermInput::Gdi,
+            other => unreachable!("catalog composite term reads unknown input {other}"),
+        }
+    }
+}
+
+/// A cell's values as composite terms read them.
+struct TermInputs<'a> {
+    values: &'a AxisValues,
+    eeb_pos: f32,
+    /// APCI with NaN scored as 0 under the `with_apci` weight set.
+    apci: f32,
+}
+
+impl TermInputs<'_> {
+    fn get(&self, input: TermInput) -> f32 {
+        match input {
+            TermInput::Sia => self.values.sia,
+            TermInput::PosEeb => self.eeb_pos,
+            TermInput::Sli => self.values.sli,
+            TermInput::Mei => self.values.mei,
+            TermInput::Ecmi => self.values.ecmi,
+            TermInput::Apci => self.apci,
+            TermInput::Gdi => self.values.gdi,
+        }
+    }
+}
+
+/// A catalog weight set resolved once per run; driver names and
+/// contributions follow the catalog term order.
+pub(crate) struct Terms {
+    pub(crate) names: Vec<&'static str>,
+    weights: Vec<f32>,
+    inputs: Vec<TermInput>,
+}
+
+impl Terms {
+    pub(crate) fn new(set: &WeightSet) -> Self {
+        Self {
+            names: set.terms.iter().map(|t| t.driver_name()).collect(),
+            weights: set.terms.iter().map(|t| t.weight).collect(),
+            inputs: set
+                .terms
+                .iter()
+                .map(|t| TermInput::from_catalog(t.input))
+                .collect(),
+        }
+    }
+
+    /// `weight * input` per term; the composite is their clamped sum.
+    fn contribs(&self, inputs: &TermInputs) -> Vec<f32> {
+        self.weights
+            .iter()
+            .zip(&self.inputs)
+            .map(|(w, input)| w * inputs.get(*input))
+            .collect()
+    }
+}
+
+/// The `set` weight set of composite `id`; both come from the built-in catalog.
+pub(crate) fn weight_set<'a>(catalog: &'a [CompositeDef], id: &str, set: &str) -> &'a WeightSet {
+    catalog
+        .iter()
+        .find(|c| c.id == id)
+        .and_then(|c| c.weight_sets.iter().find(|s| s.name == set))
+        .unwrap_or_else(|| panic!("catalog has no {id} weight set {set}"))
+}
+
 fn weighted_cov_oii(cov: &crate::model::axes::AxisCoverage, w: &WeightsDefault) -> f32 {
     let weights = [
         w.oii.sia,
@@ -254,53 +382,33 @@ fn weighted_cov(weights: &[f32], values: &[f32]) -> f32 {
     }
 }
 
+pub(crate) fn compute_summary(
+    oii: &[f32],
+    iai: &[f32],
+    esi: &[f32],
+    score_concentration: &[f32],
+) -> CompositesSummary {
+    CompositesSummary {
+        oii: summary_stats(oii),
+        iai: summary_stats(iai),
+        esi: summary_stats(esi),
+        score_concentration: summary_stats(score_concentration),
+    }
+}
+
 fn summary_stats(values: &[f32]) -> CompositeStats {
-    let mut vals: Vec<f32> = values.iter().copied().filter(|v| !v.is_nan()).collect();
-    vals.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
-    let median = percentile(&vals, 0.5);
-    let p90 = percentile(&vals, 0.9);
-    let p99 = percentile(&vals, 0.99);
-    let frac_ge_0_65 = fraction_ge(&vals, 0.65);
-    let frac_ge_0_80 = fraction_ge(&vals, 0.80);
+    let q = percentiles_interpolated(values, &[0.5, 0.9, 0.99], NanPolicy::Skip);
+    let frac_ge_0_65 = fraction_ge(values, 0.65, NanPolicy::Skip);
+    let frac_ge_0_80 = fraction_ge(values, 0.80, NanPolicy::Skip);
     CompositeStats {
-        median,
-        p90,
-        p99,
+        median: q[0],
+        p90: q[1],
+        p99: q[2],
         frac_ge_0_65,
         frac_ge_0_80,
     }
 }
 
-fn percentile(values: &[f32], p: f32) -> f32 {
-    if values.is_empty() {
-        return f32::NAN;
-    }
-    let n = values.len();
-    let idx = ((p * (n as f32 - 1.0)).floor() as usize).min(n - 1);
-    values[idx]
-}
-
-fn fraction_ge(values: &[f32], threshold: f32) -> f32 {
-    if values.is_empty() {
-        return 0.0;
-    }
-    let mut count = 0usize;
-    for v in values {
-        if *v >= threshold {
-            count += 1;
-        }
-    }
-    count as f32 / values.len() as f32
-}
-
-fn format_f32(value: f32) -> String {
-    if value.is_nan() {
-        "nan".to_string()
-    } else {
-        format!("{:.6}", value)
-    }
-}
-
 #[cfg(test)]
 #[path = "../../tests/src_inline/pipeline/stage5_scores.rs"]
 mod tests;
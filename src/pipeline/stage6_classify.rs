@@ -1,46 +1,112 @@
 use std::io::Write;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use thiserror::Error;
+use tracing::warn;
 
+use crate::input::InputError;
+use crate::input::meta::read_meta_species;
 use crate::model::flags::Flags;
 use crate::model::regimes::{Regime, RuleId};
 use crate::model::scores::pos_eeb;
-use crate::model::thresholds::Thresholds;
+use crate::model::thresholds::{CellThresholds, Thresholds, ThresholdsConfig};
+use crate::pipeline::cancel::{Cancelled, checkpoint};
+use crate::pipeline::chunk_progress;
+use crate::pipeline::outputs::per_cell_tables;
+use crate::pipeline::runner::{StageCells, observe};
 use crate::pipeline::stage1_load::DatasetCtx;
-use crate::pipeline::stage2_normalize::ExprContext;
+use crate::pipeline::stage2_normalize::{CellExprSource, ExprContext};
 use crate::pipeline::stage4_axes::AxesContext;
 use crate::pipeline::stage5_scores::ScoresContext;
+use crate::report::artifact::{Artifact, WriteError, into_write_error};
+use crate::report::format::fixed6;
+use crate::report::tsv::{UnsafeField, field};
 
 #[derive(Debug, Error)]
 pub enum Stage6Error {
     #[error("io error: {0}")]
-    Io(#[from] std::io::Error),
+    Io(std::io::Error),
+    #[error("io error writing {}: {source}", path.display())]
+    Write {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("{0}")]
+    Cancelled(#[from] Cancelled),
+    #[error("{0}")]
+    Field(#[from] UnsafeField),
 }
 
-#[derive(Debug, Clone)]
+/// Artifact write errors keep their path; other I/O errors stay `Io`.
+impl From<std::io::Error> for Stage6Error {
+    fn from(e: std::io::Error) -> Self {
+        match into_write_error(e) {
+            Ok(WriteError { path, source }) => Stage6Error::Write { path, source },
+            Err(e) => Stage6Error::Io(e),
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct ClassifyContext {
     pub regimes: Vec<Regime>,
     pub rule_ids: Vec<RuleId>,
     pub flags: Vec<Flags>,
     pub summary: RegimeSummary,
+    /// Cut-offs each cell was classified with.
+    pub thresholds: CellThresholds,
 }
 
-#[derive(Debug, Clone, serde::Serialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct RegimeSummary {
     pub counts: Vec<(Regime, usize)>,
     pub fractions: Vec<(Regime, f32)>,
     pub flagged_fractions: Vec<(String, f32)>,
 }
 
-pub fn run_stage6_classify(
+pub fn run_stage6_classify<M: CellExprSource>(
+    dataset: &DatasetCtx,
+    expr: &ExprContext<M>,
+    axes: &AxesContext,
+    scores: &ScoresContext,
+    out_dir: &Path,
+) -> Result<ClassifyContext, Stage6Error> {
+    run_stage6_classify_with_thresholds(
+        dataset,
+        expr,
+        axes,
+        scores,
+        out_dir,
+        &Thresholds::default(),
+    )
+}
+
+pub fn run_stage6_classify_with_thresholds<M: CellExprSource>(
+    dataset: &DatasetCtx,
+    expr: &ExprContext<M>,
+    axes: &AxesContext,
+    scores: &ScoresContext,
+    out_dir: &Path,
+    thresholds: &Thresholds,
+) -> Result<ClassifyContext, Stage6Error> {
+    run_stage6_classify_with_cell_thresholds(
+        dataset,
+        expr,
+        axes,
+        scores,
+        out_dir,
+        &CellThresholds::uniform(thresholds.clone()),
+    )
+}
+
+pub fn run_stage6_classify_with_cell_thresholds<M: CellExprSource>(
     dataset: &DatasetCtx,
-    expr: &ExprContext,
+    expr: &ExprContext<M>,
     axes: &AxesContext,
     scores: &ScoresContext,
     out_dir: &Path,
+    cell_thresholds: &CellThresholds,
 ) -> Result<ClassifyContext, Stage6Error> {
-    let thresholds = Thresholds::default();
     let n = dataset.n_cells;
 
     let mut regimes = Vec::with_capacity(n);
@@ -49,11 +115,18 @@ pub fn run_stage6_classify(
 
     let cell_ids = &dataset.barcodes;
 
-    let out_path = out_dir.join("classify.tsv");
-    let mut writer = std::io::BufWriter::new(std::fs::File::create(&out_path)?);
-    writer.write_all(b"cell_id\tregime\trule_id\tflags\n")?;
+    let mut writer = per_cell_tables()
+        .then(|| Artifact::create(out_dir, "classify.tsv"))
+        .transpose()?;
+    if let Some(writer) = writer.as_mut() {
+        writer.write_all(
+            b"cell_id\tregime\trule_id\tflags\tambient_detected_genes\tambient_gdi\tambient_sia\tthresholds_id\n",
+        )?;
+    }
 
     for idx in 0..n {
+        let set = cell_thresholds.for_cell(idx);
+        let thresholds = &set.thresholds;
         let axis = &axes.values[idx];
         let cov = &axes.coverage[idx];
         let comp_oii = scores.oii[idx];
@@ -64,9 +137,21 @@ pub fn run_stage6_classify(
         if cell_stats.libsize < thresholds.low_counts as u64 {
             f.set(Flags::LOW_COUNTS);
         }
-        if cell_stats.detected < thresholds.few_detected {
+        let few_detected = match thresholds.low_complexity {
+            Some(min) => {
+                let complexity = cell_stats.genes_per_1k_umis();
+                complexity.is_nan() || complexity < min
+            }
+            None => cell_stats.detected < thresholds.few_detected,
+        };
+        if few_detected {
             f.set(Flags::FEW_DETECTED_GENES);
         }
+        if expr.normalization.single_count_scale(cell_stats.libsize)
+            > thresholds.extreme_count_scale
+        {
+            f.set(Flags::EXTREME_NORMALIZATION);
+        }
         if cov.sia < thresholds.cov_min
             || cov.eeb < thresholds.cov_min
             || cov.sli < thresholds.cov_min
@@ -78,30 +163,50 @@ pub fn run_stage6_classify(
             f.set(Flags::LOW_CONFIDENCE);
         }
         let eeb_pos = pos_eeb(axis.eeb);
-        if f.contains(Flags::FEW_DETECTED_GENES)
+        if cell_stats.detected < thresholds.ambient_detected_gate()
             && axis.gdi >= thresholds.ambient_gdi
             && axis.sia < thresholds.ambient_sia
         {
             f.set(Flags::HIGH_AMBIENT_RISK);
         }
 
-        let (regime, rule) = classify_cell(axis, eeb_pos, comp_oii, comp_esi, &thresholds);
+        let (regime, rule) = classify_cell(axis, eeb_pos, comp_oii, comp_esi, thresholds);
 
         regimes.push(regime);
         rule_ids.push(rule);
         flags.push(f);
 
-        let line = format!(
-            "{}\t{}\t{}\t{}\n",
-            cell_ids[idx],
-            regime.as_str(),
-            rule.as_str(),
-            f.to_csv()
-        );
-        writer.write_all(line.as_bytes())?;
+        if let Some(writer) = writer.as_mut() {
+            // The rule's inputs, for tuning it; only on flagged cells.
+            let ambient = if f.contains(Flags::HIGH_AMBIENT_RISK) {
+                format!(
+                    "{}\t{}\t{}",
+                    cell_stats.detected,
+                    fixed6(axis.gdi),
+                    fixed6(axis.sia)
+                )
+            } else {
+                ".\t.\t.".to_string()
+            };
+            let line = format!(
+                "{}\t{}\t{}\t{}\t{}\t{}\n",
+                field("cell_id", &cell_ids[idx])?,
+                regime.as_str(),
+                rule.as_str(),
+                f.to_csv(),
+                ambient,
+                set.id
+            );
+            writer.write_all(line.as_bytes())?;
+        }
+        chunk_progress("stage6_classify", idx + 1, n);
+        observe(StageCells::Regimes(&regimes));
+        checkpoint(idx + 1)?;
     }
 
-    writer.flush()?;
+    if let Some(writer) = writer {
+        writer.commit()?;
+    }
 
     let summary = summarize(&regimes, &flags);
 
@@ -110,9 +215,44 @@ pub fn run_stage6_classify(
         rule_ids,
         flags,
         summary,
+        thresholds: cell_thresholds.clone(),
     })
 }
 
+/// Per-cell cut-offs for `config`: species overrides need the meta
+/// `species` column, which is only read when the config has overrides.
+pub fn resolve_cell_thresholds(
+    config: &ThresholdsConfig,
+    meta_path: Option<&Path>,
+    barcodes: &[String],
+) -> Result<CellThresholds, InputError> {
+    match meta_path {
+        Some(path) if !config.species.is_empty() => Ok(CellThresholds::by_species(
+            config,
+            &read_meta_species(path, barcodes)?,
+        )),
+        None if !config.species.is_empty() => {
+            warn!("thresholds have species overrides but no --meta; using the global values");
+            Ok(CellThresholds::uniform(config.global.clone()))
+        }
+        _ => Ok(CellThresholds::uniform(config.global.clone())),
+    }
+}
+
+/// Regime rules in evaluation order; the first match classifies the cell.
+const RULES: [(Regime, RuleId); 7] = [
+    (Regime::SelfPreserving, RuleId::R1SelfPreserving),
+    (
+        Regime::SecretoryLysosomeActive,
+        RuleId::R2SecretoryLysosomeActive,
+    ),
+    (Regime::ExportDominant, RuleId::R3ExportDominant),
+    (Regime::MetabolicSuppressive, RuleId::R4MetabolicSuppressive),
+    (Regime::InflammatorySignaler, RuleId::R5InflammatorySignaler),
+    (Regime::PresentationHigh, RuleId::R6PresentationHigh),
+    (Regime::EnvironmentShaping, RuleId::R7EnvironmentShaping),
+];
+
 fn classify_cell(
     axis: &crate::model::axes::AxisValues,
     pos_eeb: f32,
@@ -120,39 +260,57 @@ fn classify_cell(
     esi: f32,
     t: &Thresholds,
 ) -> (Regime, RuleId) {
-    if axis.sia < t.sia_low
-        && pos_eeb < t.pos_eeb_low
-        && axis.mei < 0.45
-        && axis.ecmi < 0.45
-        && axis.gdi < 0.50
-    {
-        return (Regime::SelfPreserving, RuleId::R1SelfPreserving);
-    }
-    if axis.sli >= t.sli_hi && axis.sia >= 0.45 {
-        return (
-            Regime::SecretoryLysosomeActive,
-            RuleId::R2SecretoryLysosomeActive,
-        );
-    }
-    if pos_eeb >= t.pos_eeb_hi && axis.sia >= t.sia_hi && oii >= 0.60 {
-        return (Regime::ExportDominant, RuleId::R3ExportDominant);
-    }
-    if axis.mei >= t.mei_hi
-        && (pos_eeb >= t.pos_eeb_mid || axis.sia >= t.sia_hi)
-        && axis.gdi < t.gdi_hi
-    {
-        return (Regime::MetabolicSuppressive, RuleId::R4MetabolicSuppressive);
-    }
-    if axis.gdi >= t.gdi_hi && axis.sia >= t.sia_mid {
-        return (Regime::InflammatorySignaler, RuleId::R5InflammatorySignaler);
-    }
-    if !axis.apci.is_nan() && axis.apci >= t.apci_hi && (axis.sia >= 0.45 || axis.gdi >= 0.60) {
-        return (Regime::PresentationHigh, RuleId::R6PresentationHigh);
-    }
-    if (oii >= t.oii_hi && esi >= t.esi_hi) || esi >= t.esi_very {
-        return (Regime::EnvironmentShaping, RuleId::R7EnvironmentShaping);
+    RULES
+        .into_iter()
+        .find(|(_, rule)| rule_matches(*rule, axis, pos_eeb, oii, esi, t))
+        .unwrap_or((Regime::Unclassified, RuleId::R0Unclassified))
+}
+
+/// Every rule in evaluation order with whether the cell meets it; the first
+/// `true` is the rule [`ClassifyContext::rule_ids`] records.
+pub fn rule_trace(
+    axis: &crate::model::axes::AxisValues,
+    pos_eeb: f32,
+    oii: f32,
+    esi: f32,
+    t: &Thresholds,
+) -> Vec<(RuleId, bool)> {
+    RULES
+        .iter()
+        .map(|(_, rule)| (*rule, rule_matches(*rule, axis, pos_eeb, oii, esi, t)))
+        .collect()
+}
+
+fn rule_matches(
+    rule: RuleId,
+    axis: &crate::model::axes::AxisValues,
+    pos_eeb: f32,
+    oii: f32,
+    esi: f32,
+    t: &Thresholds,
+) -> bool {
+    match rule {
+        RuleId::R1SelfPreserving => {
+            axis.sia < t.sia_low
+                && pos_eeb < t.pos_eeb_low
+                && axis.mei < 0.45
+                && axis.ecmi < 0.45
+                && axis.gdi < 0.50
+        }
+        RuleId::R2SecretoryLysosomeActive => axis.sli >= t.sli_hi && axis.sia >= 0.45,
+        RuleId::R3ExportDominant => pos_eeb >= t.pos_eeb_hi && axis.sia >= t.sia_hi && oii >= 0.60,
+        RuleId::R4MetabolicSuppressive => {
+            axis.mei >= t.mei_hi
+                && (pos_eeb >= t.pos_eeb_mid || axis.sia >= t.sia_hi)
+                && axis.gdi < t.gdi_hi
+        }
+        RuleId::R5InflammatorySignaler => axis.gdi >= t.gdi_hi && axis.sia >= t.sia_mid,
+        RuleId::R6PresentationHigh => {
+            !axis.apci.is_nan() && axis.apci >= t.apci_hi && (axis.sia >= 0.45 || axis.gdi >= 0.60)
+        }
+        RuleId::R7EnvironmentShaping => (oii >= t.oii_hi && esi >= t.esi_hi) || esi >= t.esi_very,
+        RuleId::R0Unclassified => true,
     }
-    (Regime::Unclassified, RuleId::R0Unclassified)
 }
 
 fn summarize(regimes: &[Regime], flags: &[Flags]) -> RegimeSummary {
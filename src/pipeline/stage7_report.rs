@@ -1,32 +1,103 @@
-use std::collections::{BTreeMap, HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fmt::Write as FmtWrite;
-use std::io::{BufRead, BufWriter, Write};
-use std::path::Path;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
 
+use rayon::prelude::*;
 use serde::Serialize;
 use serde_json::json;
 use thiserror::Error;
+use tracing::{info, warn};
 
+use crate::aggregate::compare::{
+    DEFAULT_MIN_CELLS_TO_COMPARE, GroupComparison, mann_whitney_u, skip_reason, two_proportion_z,
+};
+use crate::aggregate::flag_combos::{FlagCombination, combination_key, count_combinations};
+use crate::aggregate::sample::{
+    DEFAULT_MIN_CELLS_FOR_STATS, INSUFFICIENT_CELLS, count_ge, gated_metric, ge_fraction,
+};
+use crate::input::meta::{MetaIssueCounts, normalize_species};
 use crate::input::open_reader;
+use crate::input::upstream::UpstreamColumn;
+use crate::model::axes::saturating_map;
+use crate::model::axis_curves::AxisCurves;
+use crate::model::chemistry::ChemistryChoice;
 use crate::model::flags::Flags;
+use crate::model::pipeline_regime::{PipelineRegime, RegimeLabels};
+use crate::model::qc_expectations::{QcExpectations, QcGate};
+use crate::model::reference::{
+    CohortReference, REFERENCE_AXES, REFERENCE_COMPOSITES, axis_value, grid_median, quantile_grid,
+    reference_percentile,
+};
 use crate::model::regimes::Regime;
+use crate::model::run_label::RunLabel;
 use crate::model::scores::pos_eeb;
+use crate::model::stats::{NanPolicy, median, percentiles_interpolated};
+use crate::model::thresholds::{FracGeThresholds, Thresholds, ThresholdsSet};
+use crate::panels::defs::{COVARIATE_AXIS, SkippedPanelFile};
+use crate::panels::mapping::{
+    DEFAULT_MIN_PANEL_GENE_FRACTION, GeneUniverseCount, PanelGeneUniverse,
+};
+use crate::pipeline::cancel::{Cancelled, checkpoint};
+use crate::pipeline::consistency::{ConsistencyError, check_secretion_tsv};
+use crate::pipeline::explain::{ExplainCells, write_explain};
+use crate::pipeline::fingerprint::RunFingerprint;
+use crate::pipeline::outputs::{self, Retention};
+use crate::pipeline::panel_expr::{PanelMatrixFormat, write_panel_expr};
+use crate::pipeline::reduce::fold_cell_chunks;
+use crate::pipeline::rng::RunRng;
 use crate::pipeline::stage1_load::DatasetCtx;
 use crate::pipeline::stage1_load::RunMode;
-use crate::pipeline::stage2_normalize::ExprContext;
-use crate::pipeline::stage3_panels::PanelsContext;
-use crate::pipeline::stage4_axes::AxesContext;
+use crate::pipeline::stage2_normalize::{CellExprSource, ExprContext};
+use crate::pipeline::stage3_panels::{DeadPanel, PanelsContext};
+use crate::pipeline::stage4_axes::{AxesContext, AxisPanelCounts};
 use crate::pipeline::stage5_scores::ScoresContext;
-use crate::pipeline::stage6_classify::ClassifyContext;
-use crate::report::text::render_report;
+use crate::pipeline::stage6_classify::{ClassifyContext, rule_trace};
+use crate::pipeline::{PROGRESS_CHUNK_CELLS, chunk_progress};
+use crate::report::artifact::{Artifact, WriteError, into_write_error, write_artifact};
+use crate::report::binfmt::{BinError, BinTable, ColumnData, SECRETION_BIN, encode_bin};
+use crate::report::compress::{CompressFormat, CompressedWriter};
+use crate::report::format::{clamped01, fixed6, signed_or_nan};
+use crate::report::render::{ReportFormat, render};
+use crate::report::tsv::{UnsafeField, field};
 use crate::simd;
 
 #[derive(Debug, Error)]
 pub enum Stage7Error {
     #[error("io error: {0}")]
-    Io(#[from] std::io::Error),
+    Io(std::io::Error),
+    #[error("io error writing {}: {source}", path.display())]
+    Write {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("io error reading {}: {source}", path.display())]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
     #[error("json error: {0}")]
     Json(#[from] serde_json::Error),
+    #[error("{0}")]
+    Cancelled(#[from] Cancelled),
+    #[error("{0}")]
+    Field(#[from] UnsafeField),
+    #[error("format error: {0}")]
+    Fmt(#[from] std::fmt::Error),
+    #[error("{0}")]
+    Bin(#[from] BinError),
+    #[error("{0}")]
+    Consistency(#[from] ConsistencyError),
+}
+
+/// Artifact write errors keep their path; other I/O errors stay `Io`.
+impl From<std::io::Error> for Stage7Error {
+    fn from(e: std::io::Error) -> Self {
+        match into_write_error(e) {
+            Ok(WriteError { path, source }) => Stage7Error::Write { path, source },
+            Err(e) => Stage7Error::Io(e),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -34,8 +105,37 @@ pub struct FinalSummary {
     pub tool: ToolSummary,
     pub input: InputSummary,
     pub distributions: DistributionSummary,
+    pub histograms: HistogramSummary,
     pub regimes: RegimeSummary,
     pub qc: QcSummary,
+    pub frac_ge: FracGeSummary,
+    pub caveats: CaveatsSummary,
+    pub provenance: ProvenanceSummary,
+    /// Present only when the run was given `--label`.
+    pub run: Option<RunLabel>,
+    /// Present when the run resolved `--chemistry` (not for reclassify).
+    pub chemistry: Option<ChemistrySummary>,
+    /// Present when stage1 loaded a shared cache sidecar.
+    pub upstream: Option<UpstreamSummary>,
+    /// Present only when the run was given `--reference`.
+    pub reference: Option<ReferenceSummary>,
+    /// Present only when the run was given `--qc-expectations`; also
+    /// written to `qc_gate.json`.
+    pub qc_gate: Option<QcGate>,
+    /// Present when the meta file has a condition column.
+    pub condition_comparison: Option<GroupComparison>,
+}
+
+impl FinalSummary {
+    /// Dataset-level flags for `pipeline_step.json`, so orchestrators can
+    /// quarantine a run without reading the whole summary.
+    pub fn dataset_flags(&self) -> Vec<&'static str> {
+        let mut flags = Vec::new();
+        if self.input.panel_genes.low_coverage {
+            flags.push(LOW_PANEL_GENE_COVERAGE);
+        }
+        flags
+    }
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -49,6 +149,83 @@ pub struct ToolSummary {
 pub struct InputSummary {
     pub n_cells: usize,
     pub species: String,
+    /// Fraction of barcodes matched by a meta `cell_id`; `None` without meta.
+    pub meta_match_fraction: Option<f32>,
+    /// Meta rows with unknown species, missing columns or repeated cell_ids;
+    /// `None` without meta.
+    pub meta_issues: Option<MetaIssueCounts>,
+    /// Digest of the gene list; see [`DatasetCtx::gene_set_hash`].
+    pub gene_set_hash: String,
+    /// Low tail of the per-cell libsize distribution, where CP10K scaling
+    /// blows single counts up; NaN without cells.
+    pub libsize_low_tail: LibsizeLowTail,
+    pub matrix_values: MatrixValuesSummary,
+    pub panel_genes: PanelGenesSummary,
+}
+
+/// How stage 2 read the matrix values.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MatrixValuesSummary {
+    /// MTX banner field (`integer`, `real`, `pattern`); `None` for the shared
+    /// cache and dense input.
+    pub value_type: Option<String>,
+    /// `real` entries with a fractional part, rounded to the nearest count.
+    pub rounded: usize,
+    /// Rounded entries that became 0 and were dropped.
+    pub rounded_to_zero: usize,
+}
+
+/// `pipeline_step.json` dataset flag of a run whose panel genes are mostly
+/// absent from the matrix.
+pub const LOW_PANEL_GENE_COVERAGE: &str = "LOW_PANEL_GENE_COVERAGE";
+
+/// How much of the panel gene universe the matrix carries.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PanelGenesSummary {
+    pub universe: PanelGeneUniverse,
+    /// `--min-panel-gene-fraction`.
+    pub min_mapped_fraction: f32,
+    /// The overall mapped fraction is below `min_mapped_fraction`.
+    pub low_coverage: bool,
+}
+
+impl PanelGenesSummary {
+    pub fn new(universe: PanelGeneUniverse, min_mapped_fraction: f32) -> Self {
+        let low_coverage = universe.overall.mapped_fraction() < min_mapped_fraction;
+        Self {
+            universe,
+            min_mapped_fraction,
+            low_coverage,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct LibsizeLowTail {
+    pub min: f32,
+    pub p1: f32,
+}
+
+/// Quantiles of the pass-through columns, keyed by their `upstream_` names in
+/// `secretion.tsv` order. Unlike the secretion metrics they are not clamped.
+#[derive(Debug, Clone, Serialize)]
+pub struct UpstreamSummary {
+    pub source: String,
+    pub columns: Vec<(String, Quantiles)>,
+}
+
+/// The `--chemistry` a run used and the cut-offs it resulted in.
+#[derive(Debug, Clone, Serialize)]
+pub struct ChemistrySummary {
+    pub name: String,
+    /// `explicit` or `inferred`.
+    pub source: String,
+    pub median_detected: f32,
+    pub median_libsize: f32,
+    /// Global `low_counts` / `few_detected` after the chemistry defaults and
+    /// any --thresholds fields; species overrides are in provenance.json.
+    pub low_counts: u64,
+    pub few_detected: u32,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -56,8 +233,107 @@ pub struct DistributionSummary {
     pub secretory_load: Quantiles,
     pub er_golgi_pressure: Quantiles,
     pub stress_secretion_index: Quantiles,
+    /// Stage 5 OII contribution concentration; cells without a positive
+    /// contribution (NaN) are left out.
+    pub score_concentration: Quantiles,
 }
 
+/// Fixed-width bin counts over [0, 1]; `HISTOGRAM_BINS` bins per metric.
+#[derive(Debug, Clone, Serialize)]
+pub struct HistogramSummary {
+    pub secretory_load: Vec<u32>,
+    pub er_golgi_pressure: Vec<u32>,
+    pub stress_secretion_index: Vec<u32>,
+    pub confidence: Vec<u32>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ReportOptions {
+    /// Render report.txt sparklines with plain ASCII characters.
+    pub ascii_only: bool,
+    /// Human-readable reports to write, each at [`ReportFormat::file_name`].
+    pub report_formats: Vec<ReportFormat>,
+    /// Cohort reference for `*_ref_pctl` columns and the drift section.
+    pub reference: Option<CohortReference>,
+    /// Samples with fewer cells get `NA` statistics in `samples.tsv`.
+    pub min_cells_for_stats: usize,
+    /// Thresholds the run classified with; `cov_min` drives the coverage caveat.
+    pub thresholds: Thresholds,
+    /// Run seed context; its master and derived seeds go to `provenance.json`.
+    pub rng: RunRng,
+    /// Also export normalized expression of the mapped panel genes.
+    pub panel_matrix: Option<PanelMatrixFormat>,
+    /// Gzip or BGZF for the panel expression export.
+    pub panel_matrix_compression: CompressFormat,
+    /// Also write `secretion.tsv.gz` in this format (per-cell tables only).
+    pub secretion_compression: Option<CompressFormat>,
+    /// Regime labels for secretion.tsv, samples.tsv, summary.json and
+    /// pipeline_step.json.
+    pub regime_labels: RegimeLabels,
+    /// Expected ranges checked into `qc_gate.json` and the report.
+    pub qc_expectations: Option<QcExpectations>,
+    /// Also write `flagged_cells.tsv` (per-cell tables only).
+    pub flagged_output: bool,
+    /// Also write `secretion.bin` (per-cell tables only), see
+    /// [`crate::report::binfmt`].
+    pub binary_output: bool,
+    /// Recorded in `provenance.json` so an identical re-run can be skipped.
+    pub fingerprint: Option<RunFingerprint>,
+    /// Cells to write `explain/<barcode>.json` sidecars for.
+    pub explain_cells: Option<ExplainCells>,
+    /// `--label` / `--description`, written to every JSON artifact and the
+    /// report titles.
+    pub run_label: Option<RunLabel>,
+    /// Chemistry behind `thresholds.low_counts` / `few_detected`, for
+    /// summary.json.
+    pub chemistry: Option<ChemistryChoice>,
+    /// `--retain` policy applied after verification, recorded so `verify`
+    /// does not expect the removed tables.
+    pub retention: Retention,
+    /// Conditions with fewer cells are not compared in summary.json.
+    pub compare_min_cells: usize,
+    /// Read secretion.tsv back and check it against summary.json before
+    /// writing the latter, see [`crate::pipeline::consistency`].
+    pub consistency_check: bool,
+    /// Mapped fraction of the panel gene universe below which the run is
+    /// flagged as [`LOW_PANEL_GENE_COVERAGE`].
+    pub min_panel_gene_fraction: f32,
+    /// Panel gene universe to report in place of the one computed from the
+    /// panel mappings; reclassify passes the source run's.
+    pub panel_gene_universe: Option<PanelGeneUniverse>,
+}
+
+impl Default for ReportOptions {
+    fn default() -> Self {
+        Self {
+            ascii_only: false,
+            report_formats: vec![ReportFormat::Txt],
+            reference: None,
+            min_cells_for_stats: DEFAULT_MIN_CELLS_FOR_STATS,
+            thresholds: Thresholds::default(),
+            rng: RunRng::default(),
+            panel_matrix: None,
+            panel_matrix_compression: CompressFormat::Gzip,
+            secretion_compression: None,
+            regime_labels: RegimeLabels::default(),
+            qc_expectations: None,
+            flagged_output: false,
+            binary_output: false,
+            fingerprint: None,
+            explain_cells: None,
+            run_label: None,
+            chemistry: None,
+            retention: Retention::All,
+            compare_min_cells: DEFAULT_MIN_CELLS_TO_COMPARE,
+            consistency_check: true,
+            min_panel_gene_fraction: DEFAULT_MIN_PANEL_GENE_FRACTION,
+            panel_gene_universe: None,
+        }
+    }
+}
+
+pub const HISTOGRAM_BINS: usize = 20;
+
 #[derive(Debug, Clone, Serialize)]
 pub struct Quantiles {
     pub median: f32,
@@ -75,72 +351,311 @@ pub struct RegimeSummary {
 pub struct QcSummary {
     pub low_confidence_fraction: f32,
     pub low_secretory_signal_fraction: f32,
+    pub high_proliferation_fraction: f32,
+    pub high_ambient_risk_fraction: f32,
+    pub extreme_normalization_fraction: f32,
+    /// Library complexity (`genes_per_1k_umis`); empty cells are left out.
+    pub genes_per_1k_umis: Quantiles,
+    /// `HIGH_AMBIENT_RISK` per sample, grouped like `frac_ge.by_sample`; a
+    /// library with ambient contamination stands out here.
+    pub high_ambient_risk_by_sample: BTreeMap<String, FlagCount>,
+    /// Flagged cells per exact flag combination (stage 6 and stage 7 flags),
+    /// most frequent first, capped with an `other` bucket.
+    pub flag_combinations: Vec<FlagCombination>,
+}
+
+impl QcSummary {
+    /// `HIGH_AMBIENT_RISK` fraction per sample for the reports; empty when
+    /// every cell is in the unnamed sample `.`.
+    pub fn ambient_risk_by_sample(&self) -> Vec<(&str, f32)> {
+        if self.high_ambient_risk_by_sample.keys().all(|s| s == ".") {
+            return Vec::new();
+        }
+        self.high_ambient_risk_by_sample
+            .iter()
+            .map(|(sample, count)| (sample.as_str(), count.fraction()))
+            .collect()
+    }
+}
+
+/// Cells in a group and how many of them carry a flag.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FlagCount {
+    pub n_cells: usize,
+    pub n_flagged: usize,
+}
+
+impl FlagCount {
+    pub fn fraction(&self) -> f32 {
+        ge_fraction(self.n_flagged, self.n_cells)
+    }
+}
+
+/// Fraction of cells at or above configured cut-offs, globally and per meta group.
+#[derive(Debug, Clone, Serialize)]
+pub struct FracGeSummary {
+    /// `(metric, cut-offs)` in `samples.tsv` metric order.
+    pub thresholds: Vec<(String, Vec<f32>)>,
+    pub global: FracGeGroup,
+    /// Every sample seen in the meta file or on a cell, including empty ones.
+    pub by_sample: BTreeMap<String, FracGeGroup>,
+    pub by_condition: BTreeMap<String, FracGeGroup>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FracGeGroup {
+    pub n_cells: usize,
+    /// Per metric, the number of cells at or above each cut-off.
+    pub n_ge: Vec<Vec<usize>>,
+}
+
+impl FracGeGroup {
+    pub fn fraction(&self, metric: usize, cutoff: usize) -> f32 {
+        ge_fraction(self.n_ge[metric][cutoff], self.n_cells)
+    }
 }
 
+#[derive(Debug, Clone, Serialize)]
+pub struct CaveatsSummary {
+    /// Axes without any contributing panel (`present == false` in stage4).
+    pub absent_axes: Vec<String>,
+    pub panels_missing_required: Vec<PanelWarningSummary>,
+    /// `panels_missing_required` aggregated per axis, in axis name order.
+    pub missing_required_by_axis: Vec<AxisMissingRequired>,
+    /// `None` when no axis is present.
+    pub worst_covered_axis: Option<WorstCoveredAxis>,
+    /// Fraction of cells with any axis coverage below `Thresholds::cov_min`.
+    pub low_axis_coverage_fraction: f32,
+    /// TSV fields whose tab/newline characters were replaced under `--lenient`.
+    pub sanitized_fields: usize,
+    /// Per panel axis: panels defined, panels with a mappable gene, mappable genes.
+    pub axis_panels: AxisPanelCounts,
+    /// Panel files dropped under `--panels-on-error skip`.
+    pub skipped_panel_files: Vec<SkippedPanelFile>,
+    /// Panels whose sum is zero in at least 99% of cells.
+    pub dead_panels: Vec<DeadPanel>,
+    /// Dead panels left out of the axes under `--drop-dead-panels`.
+    pub dropped_dead_panels: Vec<String>,
+}
+
+impl CaveatsSummary {
+    /// Missing required genes of the panels feeding `axis`; `EEB` covers
+    /// `EEB_EXPORT` and `EEB_DEGRADE`.
+    pub fn missing_required_for_axis(&self, axis: &str) -> Vec<&str> {
+        let mut genes: Vec<&str> = self
+            .missing_required_by_axis
+            .iter()
+            .filter(|a| {
+                a.axis == axis
+                    || a.axis
+                        .strip_prefix(axis)
+                        .is_some_and(|rest| rest.starts_with('_'))
+            })
+            .flat_map(|a| a.missing_required.iter().map(String::as_str))
+            .collect();
+        genes.sort_unstable();
+        genes.dedup();
+        genes
+    }
+
+    /// One-line note on [`CaveatsSummary::worst_covered_axis`] for the reports.
+    pub fn worst_covered_note(&self) -> Option<String> {
+        let worst = self.worst_covered_axis.as_ref()?;
+        let missing = self.missing_required_for_axis(&worst.axis);
+        Some(format!(
+            "Worst-covered axis: {} (median coverage {:.2}%){}",
+            worst.axis,
+            worst.coverage_median * 100.0,
+            if missing.is_empty() {
+                String::new()
+            } else {
+                format!(", missing required genes: {}", missing.join(", "))
+            }
+        ))
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PanelWarningSummary {
+    pub panel_id: String,
+    pub axis: String,
+    pub missing_required: Vec<String>,
+    pub mappable_fraction: f32,
+}
+
+/// Missing required genes of one axis, over all of its panels.
+#[derive(Debug, Clone, Serialize)]
+pub struct AxisMissingRequired {
+    pub axis: String,
+    /// Panels of the axis with missing required genes.
+    pub panels: Vec<String>,
+    /// Their missing required genes, sorted and deduplicated.
+    pub missing_required: Vec<String>,
+}
+
+/// Present axis with the lowest median coverage.
+#[derive(Debug, Clone, Serialize)]
+pub struct WorstCoveredAxis {
+    pub axis: String,
+    pub coverage_median: f32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReferenceSummary {
+    pub panel_set_hash: String,
+    pub n_cells: usize,
+    pub tool_version: String,
+    pub drift: Vec<MetricDrift>,
+}
+
+/// Current-run median against the reference grid median for one metric.
+#[derive(Debug, Clone, Serialize)]
+pub struct MetricDrift {
+    pub metric: String,
+    pub reference_median: f32,
+    pub current_median: f32,
+    pub delta: f32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProvenanceSummary {
+    pub coverage_mode: String,
+    /// `--mode` of the run (`cell` or `sample`).
+    pub mode: String,
+    /// `--outputs` set of the run; `verify` skips per-cell checks for `summary-only`.
+    pub outputs: String,
+    /// `--retain` policy of the run; `verify` skips checks on removed tables.
+    pub retain: String,
+}
+
+/// Bundled covariate panel behind `proliferation_score` and `HIGH_PROLIFERATION`.
+pub const PROLIFERATION_PANEL: &str = "PROLIFERATION";
+
+/// One secretion.tsv row; text fields borrow from the dataset and meta columns.
 #[derive(Debug, Clone)]
-struct CellOutput {
-    barcode: String,
-    sample: String,
-    condition: String,
-    species: String,
+struct CellOutput<'a> {
+    barcode: &'a str,
+    sample: &'a str,
+    condition: &'a str,
+    species: &'a str,
     libsize: u64,
     nnz: u32,
     expressed_genes: u32,
+    genes_per_1k_umis: f32,
     secretory_load: f32,
     exocytosis_bias: f32,
     vesicle_traffic_intensity: f32,
     er_golgi_pressure: f32,
     paracrine_signal_potential: f32,
     stress_secretion_index: f32,
-    regime: String,
+    regime: PipelineRegime,
     flags: String,
+    /// Stage6 flags, enumerated in `flagged_cells.tsv`.
+    classify_flags: Flags,
     confidence: f32,
     low_confidence: bool,
     low_secretory_signal: bool,
+    /// Saturated sum of the [`PROLIFERATION_PANEL`] covariate; NaN without it.
+    proliferation_score: f32,
+    high_proliferation: bool,
+    /// Reference percentiles in `REF_PCTL_COLUMNS` order.
+    ref_pctl: Option<[f32; 6]>,
+    /// Shared cache sidecar values in [`DatasetCtx::upstream`] column order.
+    upstream: Vec<f32>,
 }
 
+/// secretion.tsv metrics that get a `<name>_ref_pctl` column, with the axis or
+/// composite grid each is interpolated against.
+const REF_PCTL_COLUMNS: [(&str, &str); 6] = [
+    ("secretory_load", "OII"),
+    ("exocytosis_bias", "EEB"),
+    ("vesicle_traffic_intensity", "SLI"),
+    ("er_golgi_pressure", "SIA"),
+    ("paracrine_signal_potential", "ESI"),
+    ("stress_secretion_index", "GDI"),
+];
+
 #[derive(Debug, Clone, Default)]
 struct MetaColumns {
     sample: Vec<String>,
     condition: Vec<String>,
     species: Vec<String>,
+    /// Every sample/condition value in the meta file, matched to a cell or not.
+    samples: BTreeSet<String>,
+    conditions: BTreeSet<String>,
 }
 
-const PIPELINE_REGIMES: [&str; 6] = [
-    "HomeostaticSecretion",
-    "AdaptiveSecretion",
-    "InflammatorySecretion",
-    "HypersecretoryState",
-    "SecretoryCollapse",
-    "Unclassified",
-];
+pub fn run_stage7_report<M: CellExprSource>(
+    dataset: &DatasetCtx,
+    expr: &ExprContext<M>,
+    axes: &AxesContext,
+    scores: &ScoresContext,
+    classify: &ClassifyContext,
+    panels: &PanelsContext,
+    out_dir: &Path,
+    mode: &str,
+    run_mode: RunMode,
+    meta_path: Option<&Path>,
+) -> Result<FinalSummary, Stage7Error> {
+    run_stage7_report_with_options(
+        dataset,
+        expr,
+        axes,
+        scores,
+        classify,
+        panels,
+        out_dir,
+        mode,
+        run_mode,
+        meta_path,
+        &ReportOptions::default(),
+    )
+}
 
-pub fn run_stage7_report(
+#[allow(clippy::too_many_arguments)]
+pub fn run_stage7_report_with_options<M: CellExprSource>(
     dataset: &DatasetCtx,
-    expr: &ExprContext,
+    expr: &ExprContext<M>,
     axes: &AxesContext,
     scores: &ScoresContext,
     classify: &ClassifyContext,
     panels: &PanelsContext,
     out_dir: &Path,
-    _mode: &str,
+    mode: &str,
     run_mode: RunMode,
     meta_path: Option<&Path>,
+    opts: &ReportOptions,
 ) -> Result<FinalSummary, Stage7Error> {
     std::fs::create_dir_all(out_dir)?;
 
     let meta = if let Some(path) = meta_path {
-        read_meta_columns(path, &dataset.barcodes)?
+        read_meta_columns(path, &dataset.barcodes).map_err(|e| match e {
+            Stage7Error::Io(source) => Stage7Error::Read {
+                path: path.to_path_buf(),
+                source,
+            },
+            e => e,
+        })?
     } else {
         MetaColumns {
             sample: vec![".".to_string(); dataset.n_cells],
             condition: vec![".".to_string(); dataset.n_cells],
             species: vec!["unknown".to_string(); dataset.n_cells],
+            ..MetaColumns::default()
         }
     };
 
-    let mut rows = Vec::with_capacity(dataset.n_cells);
-    for i in 0..dataset.n_cells {
+    let cell_stats = &expr.cell_stats;
+    let proliferation_idx = panels
+        .panels
+        .panels
+        .iter()
+        .position(|p| p.id == PROLIFERATION_PANEL && p.axis == COVARIATE_AXIS);
+    let upstream_columns = dataset
+        .upstream
+        .as_ref()
+        .map_or(&[][..], |upstream| &upstream.columns[..]);
+    let build_row = |i: usize| -> CellOutput<'_> {
         let axis = &axes.values[i];
         let cov = &axes.coverage[i];
         let exo_bias = clamp01(pos_eeb(axis.eeb));
@@ -161,88 +676,1023 @@ pub fn run_stage7_report(
                 .min(scores.cov_esi[i]),
         );
 
-        let regime = to_pipeline_regime(classify.regimes[i], secretory_load, stress, paracrine);
+        // One count weighs too much to trust the scores: low signal.
+        let extreme = classify.flags[i].contains(Flags::EXTREME_NORMALIZATION);
+        let regime = if extreme {
+            opts.regime_labels
+                .resolve(PipelineRegime::SecretoryCollapse)
+        } else {
+            assign_pipeline_regime(
+                classify.regimes[i],
+                secretory_load,
+                stress,
+                paracrine,
+                &opts.regime_labels,
+            )
+        };
+        let ref_pctl = opts.reference.as_ref().map(|reference| {
+            REF_PCTL_COLUMNS.map(|(_, source)| match source {
+                "OII" => reference_percentile(reference.composite_grid("OII"), scores.oii[i]),
+                "ESI" => reference_percentile(reference.composite_grid("ESI"), scores.esi[i]),
+                axis_name => reference_percentile(
+                    reference.axis_grid(axis_name),
+                    axis_value(axis, axis_name),
+                ),
+            })
+        });
 
-        let mut flag_set = Vec::new();
         let low_conf = classify.flags[i].contains(Flags::LOW_CONFIDENCE) || confidence < 0.60;
-        let low_sig = secretory_load < 0.20 || vesicle < 0.20;
-        if low_conf {
-            flag_set.push("LOW_CONFIDENCE");
-        }
-        if low_sig {
-            flag_set.push("LOW_SECRETORY_SIGNAL");
-        }
-        let flags = if flag_set.is_empty() {
+        let low_sig = extreme || secretory_load < 0.20 || vesicle < 0.20;
+        let proliferation_score = proliferation_idx
+            .and_then(|p| panels.per_cell.get(i).map(|cell| cell.sums[p]))
+            .map_or(f32::NAN, |sum| clamp01(saturating_map(sum, 1.0)));
+        // NaN (no proliferation panel) never reaches the threshold.
+        let high_prolif = proliferation_score
+            >= classify
+                .thresholds
+                .for_cell(i)
+                .thresholds
+                .high_proliferation;
+        let flags = [
+            (low_conf, "LOW_CONFIDENCE"),
+            (low_sig, "LOW_SECRETORY_SIGNAL"),
+            (high_prolif, "HIGH_PROLIFERATION"),
+        ]
+        .into_iter()
+        .filter(|(set, _)| *set)
+        .map(|(_, name)| name)
+        .collect::<Vec<_>>()
+        .join(",");
+        let flags = if flags.is_empty() {
             ".".to_string()
         } else {
-            flag_set.join(",")
+            flags
         };
 
-        rows.push(CellOutput {
-            barcode: dataset.barcodes[i].clone(),
-            sample: meta.sample[i].clone(),
-            condition: meta.condition[i].clone(),
-            species: meta.species[i].clone(),
-            libsize: expr.cell_stats[i].libsize,
-            nnz: expr.cell_stats[i].detected,
-            expressed_genes: expr.cell_stats[i].detected,
+        CellOutput {
+            barcode: &dataset.barcodes[i],
+            sample: &meta.sample[i],
+            condition: &meta.condition[i],
+            species: &meta.species[i],
+            libsize: cell_stats[i].libsize,
+            nnz: cell_stats[i].detected,
+            expressed_genes: cell_stats[i].detected,
+            genes_per_1k_umis: cell_stats[i].genes_per_1k_umis(),
             secretory_load,
             exocytosis_bias: exo_bias,
             vesicle_traffic_intensity: vesicle,
             er_golgi_pressure: er_golgi,
             paracrine_signal_potential: paracrine,
             stress_secretion_index: stress,
-            regime: regime.to_string(),
+            regime,
             flags,
+            classify_flags: classify.flags[i],
             confidence,
             low_confidence: low_conf,
             low_secretory_signal: low_sig,
-        });
+            proliferation_score,
+            high_proliferation: high_prolif,
+            ref_pctl,
+            upstream: upstream_columns.iter().map(|c| c.values[i]).collect(),
+        }
+    };
+
+    // Rows are independent; build them in parallel batches so cancellation
+    // and progress are still checked on this thread between batches.
+    let mut rows = Vec::with_capacity(dataset.n_cells);
+    for start in (0..dataset.n_cells).step_by(PROGRESS_CHUNK_CELLS) {
+        let end = (start + PROGRESS_CHUNK_CELLS).min(dataset.n_cells);
+        rows.par_extend((start..end).into_par_iter().map(build_row));
+        chunk_progress("stage7_report", end, dataset.n_cells);
+        checkpoint(end)?;
     }
 
-    let mut sorted_rows = rows.clone();
-    sorted_rows.sort_by(|a, b| a.barcode.cmp(&b.barcode));
-    write_secretion_tsv(out_dir, &sorted_rows)?;
+    // Rows stay in matrix order for the aggregates below; the barcode-sorted
+    // tables stream through a u32 permutation instead of a sorted copy.
+    let mut order: Vec<u32> = (0..rows.len() as u32).collect();
+    order.sort_by(|&a, &b| rows[a as usize].barcode.cmp(rows[b as usize].barcode));
+    if outputs::per_cell_tables() {
+        write_secretion_tsv(
+            out_dir,
+            order.iter().map(|&i| &rows[i as usize]),
+            upstream_columns,
+            &opts.regime_labels,
+            opts.secretion_compression,
+        )?;
+    }
+    let mut extra_artifacts = match opts.panel_matrix {
+        Some(format) => write_panel_expr(
+            out_dir,
+            format,
+            opts.panel_matrix_compression,
+            expr,
+            panels,
+            &dataset.barcodes,
+            &order,
+        )?,
+        None => Vec::new(),
+    };
+    if opts.secretion_compression.is_some() && outputs::per_cell_tables() {
+        extra_artifacts.push(("secretion_gz", SECRETION_GZ));
+    }
+    if opts.binary_output && outputs::per_cell_tables() {
+        write_secretion_bin(
+            out_dir,
+            order.iter().map(|&i| &rows[i as usize]),
+            upstream_columns,
+            &opts.regime_labels,
+        )?;
+        extra_artifacts.push(("secretion_bin", SECRETION_BIN));
+    }
+    if opts.flagged_output && outputs::per_cell_tables() {
+        write_flagged_cells_tsv(out_dir, &rows, upstream_columns, &opts.regime_labels)?;
+        extra_artifacts.push(("flagged_cells", "flagged_cells.tsv"));
+    }
+    if let Some(requested) = &opts.explain_cells {
+        write_explain(out_dir, requested, &dataset.barcodes, |i| {
+            explain_cell_json(i, &rows[i], axes, scores, classify, opts)
+        })?;
+    }
+    if mode == "sample" {
+        if meta.samples.is_empty() {
+            warn!(
+                "--mode sample without a meta sample_id column; samples.tsv pools every cell \
+                 under sample '.'"
+            );
+        }
+        write_samples_tsv(
+            out_dir,
+            &rows,
+            opts.min_cells_for_stats,
+            &opts.regime_labels,
+        )?;
+    }
     write_panels_report(out_dir, panels)?;
+    if meta.samples.is_empty() {
+        info!("no meta sample_id column; skipping panels_by_sample.tsv");
+    } else {
+        write_panels_by_sample_tsv(out_dir, panels, &meta.sample)?;
+        extra_artifacts.push(("panels_by_sample", "panels_by_sample.tsv"));
+    }
+    write_provenance_json(
+        out_dir,
+        panels,
+        mode,
+        &opts.rng,
+        &dataset.gene_set_hash,
+        opts.fingerprint.as_ref(),
+        opts.run_label.as_ref(),
+        classify.thresholds.sets(),
+        axes.curves.as_ref(),
+    )?;
 
-    let summary = build_summary(&rows);
+    let frac_ge = build_frac_ge(&rows, &meta, &opts.thresholds.frac_ge);
+    write_condition_summary_tsv(out_dir, &frac_ge)?;
+    let mut summary = build_summary(
+        &rows,
+        axes,
+        panels,
+        &scores.score_concentration,
+        opts.thresholds.cov_min,
+        frac_ge,
+        &opts.regime_labels,
+    );
+    summary.input.meta_match_fraction = dataset.meta_match_fraction();
+    summary.input.meta_issues = dataset.meta_present.then_some(dataset.meta_issues);
+    summary.input.gene_set_hash = dataset.gene_set_hash.clone();
+    summary.input.matrix_values = MatrixValuesSummary {
+        value_type: dataset
+            .matrix_value_type
+            .map(|field| field.as_str().to_string()),
+        rounded: expr.value_warnings.n_rounded,
+        rounded_to_zero: expr.value_warnings.n_rounded_to_zero,
+    };
+    summary.input.panel_genes = PanelGenesSummary::new(
+        opts.panel_gene_universe
+            .clone()
+            .unwrap_or_else(|| panels.gene_universe()),
+        opts.min_panel_gene_fraction,
+    );
+    let panel_genes = &summary.input.panel_genes;
+    if panel_genes.low_coverage {
+        let overall = panel_genes.universe.overall;
+        warn!(
+            mapped = overall.mapped,
+            total = overall.total,
+            min_fraction = panel_genes.min_mapped_fraction,
+            "only {} of {} panel genes are in the matrix; axis scores rest on few genes",
+            overall.mapped,
+            overall.total
+        );
+    }
+    summary.provenance.mode = mode.to_string();
+    summary.provenance.retain = opts.retention.as_str().to_string();
+    summary.run = opts.run_label.clone();
+    summary.chemistry = opts.chemistry.map(|choice| ChemistrySummary {
+        name: choice.chemistry.as_str().to_string(),
+        source: choice.source.as_str().to_string(),
+        median_detected: choice.median_detected,
+        median_libsize: choice.median_libsize,
+        low_counts: opts.thresholds.low_counts,
+        few_detected: opts.thresholds.few_detected,
+    });
+    summary.upstream = dataset.upstream.as_ref().map(|upstream| UpstreamSummary {
+        source: upstream.source.display().to_string(),
+        columns: upstream
+            .columns
+            .iter()
+            .map(|c| (c.output_name(), stats(&c.values)))
+            .collect(),
+    });
+    summary.condition_comparison = (!meta.conditions.is_empty()).then(|| {
+        build_condition_comparison(&rows, &meta, &opts.regime_labels, opts.compare_min_cells)
+    });
+    summary.reference = opts
+        .reference
+        .as_ref()
+        .map(|reference| build_reference_summary(reference, axes, scores));
+    if let Some(expectations) = &opts.qc_expectations {
+        let gate = expectations.evaluate(&summary, &opts.regime_labels);
+        write_artifact(
+            out_dir,
+            "qc_gate.json",
+            serde_json::to_string_pretty(&gate)?,
+        )?;
+        if !gate.passed() {
+            let failed: Vec<String> = gate.failed_rules().map(|r| r.describe()).collect();
+            warn!(rules = %failed.join(", "), "qc gate failed");
+        }
+        extra_artifacts.push(("qc_gate", "qc_gate.json"));
+        summary.qc_gate = Some(gate);
+    }
+    if opts.consistency_check && outputs::per_cell_tables() {
+        check_secretion_tsv(
+            &out_dir.join("secretion.tsv"),
+            &summary.regimes,
+            &summary.distributions,
+        )?;
+    }
     write_summary_json(out_dir, &summary)?;
     if run_mode == RunMode::Pipeline {
-        write_pipeline_step_json(out_dir)?;
+        write_pipeline_step_json(
+            out_dir,
+            &extra_artifacts,
+            &opts.regime_labels,
+            &dataset.gene_set_hash,
+            opts.run_label.as_ref(),
+            &summary.dataset_flags(),
+        )?;
     }
 
-    std::fs::write(out_dir.join("report.txt"), render_report(&summary))?;
+    for &format in &opts.report_formats {
+        write_artifact(
+            out_dir,
+            format.file_name(),
+            render(format, &summary, &panels.panels, opts.ascii_only),
+        )?;
+    }
 
     Ok(summary)
 }
 
-fn write_secretion_tsv(out_dir: &Path, rows: &[CellOutput]) -> Result<(), Stage7Error> {
-    let mut writer = BufWriter::new(std::fs::File::create(out_dir.join("secretion.tsv"))?);
-    writer.write_all(b"barcode\tsample\tcondition\tspecies\tlibsize\tnnz\texpressed_genes\tsecretory_load\texocytosis_bias\tvesicle_traffic_intensity\ter_golgi_pressure\tparacrine_signal_potential\tstress_secretion_index\tregime\tflags\tconfidence\n")?;
+/// Compressed copy of `secretion.tsv` written under `--compress-output`.
+pub const SECRETION_GZ: &str = "secretion.tsv.gz";
+
+/// Writes `secretion.tsv` and, with `compressed`, the same bytes to
+/// [`SECRETION_GZ`] in one pass over the rows.
+fn write_secretion_tsv<'r, 'a: 'r>(
+    out_dir: &Path,
+    rows: impl ExactSizeIterator<Item = &'r CellOutput<'a>>,
+    upstream: &[UpstreamColumn],
+    labels: &RegimeLabels,
+    compressed: Option<CompressFormat>,
+) -> Result<(), Stage7Error> {
+    let mut rows = rows.peekable();
+    let mut writer = Artifact::create(out_dir, "secretion.tsv")?;
+    let mut gz = compressed
+        .map(|format| {
+            Artifact::create(out_dir, SECRETION_GZ)
+                .map(|artifact| CompressedWriter::new(artifact, format))
+        })
+        .transpose()?;
+    let mut write = |bytes: &[u8]| -> std::io::Result<()> {
+        writer.write_all(bytes)?;
+        match gz.as_mut() {
+            Some(gz) => gz.write_all(bytes),
+            None => Ok(()),
+        }
+    };
+    let with_reference = rows.peek().is_some_and(|r| r.ref_pctl.is_some());
+    let mut header = secretion_header(with_reference, upstream);
+    header.push('\n');
+    write(header.as_bytes())?;
 
     for row in rows {
-        let line = format!(
-            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
-            row.barcode,
-            row.sample,
-            row.condition,
-            row.species,
-            row.libsize,
-            row.nnz,
-            row.expressed_genes,
-            fmt6(row.secretory_load),
-            fmt6(row.exocytosis_bias),
-            fmt6(row.vesicle_traffic_intensity),
-            fmt6(row.er_golgi_pressure),
-            fmt6(row.paracrine_signal_potential),
-            fmt6(row.stress_secretion_index),
-            row.regime,
-            row.flags,
-            fmt6(row.confidence),
+        let mut line = secretion_line(row, labels)?;
+        line.push('\n');
+        write(line.as_bytes())?;
+    }
+    writer.commit()?;
+    if let Some(gz) = gz {
+        gz.finish()?.commit()?;
+    }
+    Ok(())
+}
+
+/// `secretion.tsv` as typed columns; converting it back with
+/// [`BinTable::write_tsv`] gives the same bytes.
+fn write_secretion_bin<'r, 'a: 'r>(
+    out_dir: &Path,
+    rows: impl ExactSizeIterator<Item = &'r CellOutput<'a>>,
+    upstream: &[UpstreamColumn],
+    labels: &RegimeLabels,
+) -> Result<(), Stage7Error> {
+    let rows: Vec<&CellOutput> = rows.collect();
+    let text = |column: &'static str, get: fn(&CellOutput<'a>) -> &'a str| {
+        rows.iter()
+            .map(|row| field(column, get(row)).map(|v| v.into_owned()))
+            .collect::<Result<Vec<_>, _>>()
+            .map(ColumnData::Str)
+    };
+    let metric = |get: fn(&CellOutput) -> f32| {
+        ColumnData::F32(rows.iter().map(|row| written01(get(row))).collect())
+    };
+    let signed =
+        |get: fn(&CellOutput) -> f32| ColumnData::F32(rows.iter().map(|row| get(row)).collect());
+
+    let mut table = BinTable::new(rows.len());
+    table.push("barcode", text("barcode", |r| r.barcode)?);
+    table.push("sample", text("sample", |r| r.sample)?);
+    table.push("condition", text("condition", |r| r.condition)?);
+    table.push("species", text("species", |r| r.species)?);
+    table.push(
+        "libsize",
+        ColumnData::U64(rows.iter().map(|r| r.libsize).collect()),
+    );
+    table.push("nnz", ColumnData::U32(rows.iter().map(|r| r.nnz).collect()));
+    table.push(
+        "expressed_genes",
+        ColumnData::U32(rows.iter().map(|r| r.expressed_genes).collect()),
+    );
+    table.push("secretory_load", metric(|r| r.secretory_load));
+    table.push("exocytosis_bias", metric(|r| r.exocytosis_bias));
+    table.push(
+        "vesicle_traffic_intensity",
+        metric(|r| r.vesicle_traffic_intensity),
+    );
+    table.push("er_golgi_pressure", metric(|r| r.er_golgi_pressure));
+    table.push(
+        "paracrine_signal_potential",
+        metric(|r| r.paracrine_signal_potential),
+    );
+    table.push(
+        "stress_secretion_index",
+        metric(|r| r.stress_secretion_index),
+    );
+    table.push(
+        "regime",
+        ColumnData::Code {
+            codes: rows
+                .iter()
+                .map(|r| {
+                    PipelineRegime::ALL
+                        .iter()
+                        .position(|&regime| regime == r.regime)
+                        .unwrap_or(0) as u8
+                })
+                .collect(),
+            labels: PipelineRegime::ALL
+                .iter()
+                .map(|&regime| labels.label(regime).to_string())
+                .collect(),
+        },
+    );
+    table.push(
+        "flags",
+        ColumnData::Flags {
+            bits: rows
+                .iter()
+                .map(|r| {
+                    u32::from(r.low_confidence)
+                        | u32::from(r.low_secretory_signal) << 1
+                        | u32::from(r.high_proliferation) << 2
+                })
+                .collect(),
+            names: [
+                "LOW_CONFIDENCE",
+                "LOW_SECRETORY_SIGNAL",
+                "HIGH_PROLIFERATION",
+            ]
+            .map(String::from)
+            .to_vec(),
+        },
+    );
+    table.push("confidence", metric(|r| r.confidence));
+    table.push("proliferation_score", signed(|r| r.proliferation_score));
+    table.push("genes_per_1k_umis", signed(|r| r.genes_per_1k_umis));
+    if rows.first().is_some_and(|r| r.ref_pctl.is_some()) {
+        for (k, (name, _)) in REF_PCTL_COLUMNS.iter().enumerate() {
+            table.push(
+                format!("{name}_ref_pctl"),
+                ColumnData::F32(
+                    rows.iter()
+                        .map(|r| r.ref_pctl.map_or(f32::NAN, |p| p[k]))
+                        .collect(),
+                ),
+            );
+        }
+    }
+    for (k, column) in upstream.iter().enumerate() {
+        table.push(
+            column.output_name(),
+            ColumnData::F32(rows.iter().map(|r| r.upstream[k]).collect()),
         );
+    }
+
+    let mut writer = Artifact::create(out_dir, SECRETION_BIN)?;
+    writer.write_all(&encode_bin(&table)?)?;
+    writer.commit()?;
+    Ok(())
+}
+
+/// The value [`clamped01`] prints: clamped to [0, 1], non-finite as 0.
+fn written01(value: f32) -> f32 {
+    if value.is_finite() {
+        value.clamp(0.0, 1.0)
+    } else {
+        0.0
+    }
+}
+
+/// Only cells with a stage6 or stage7 flag, with the secretion.tsv columns
+/// plus `triggered_flags`; lowest confidence first, ties by barcode.
+fn write_flagged_cells_tsv(
+    out_dir: &Path,
+    rows: &[CellOutput],
+    upstream: &[UpstreamColumn],
+    labels: &RegimeLabels,
+) -> Result<(), Stage7Error> {
+    let mut flagged: Vec<(&CellOutput, String)> = rows
+        .iter()
+        .filter_map(|row| {
+            let triggered = triggered_flags(row);
+            (!triggered.is_empty()).then(|| (row, triggered.join(",")))
+        })
+        .collect();
+    flagged.sort_by(|(a, _), (b, _)| {
+        a.confidence
+            .total_cmp(&b.confidence)
+            .then_with(|| a.barcode.cmp(b.barcode))
+    });
+
+    let mut writer = Artifact::create(out_dir, "flagged_cells.tsv")?;
+    let with_reference = rows.first().is_some_and(|r| r.ref_pctl.is_some());
+    writer.write_all(secretion_header(with_reference, upstream).as_bytes())?;
+    writer.write_all(b"\ttriggered_flags\n")?;
+    for (row, triggered) in flagged {
+        let mut line = secretion_line(row, labels)?;
+        line.push('\t');
+        line.push_str(&triggered);
+        line.push('\n');
         writer.write_all(line.as_bytes())?;
     }
-    writer.flush()?;
+    writer.commit()?;
+    Ok(())
+}
+
+/// Every flag set on a cell: the stage6 bits, then `LOW_CONFIDENCE` when
+/// stage7's confidence cut-off adds it, then `LOW_SECRETORY_SIGNAL` and
+/// `HIGH_PROLIFERATION`.
+fn triggered_flags(row: &CellOutput) -> Vec<&'static str> {
+    let mut out: Vec<&'static str> = [
+        (Flags::LOW_CONFIDENCE, "LOW_CONFIDENCE"),
+        (Flags::FEW_DETECTED_GENES, "FEW_DETECTED_GENES"),
+        (Flags::LOW_COUNTS, "LOW_COUNTS"),
+        (Flags::HIGH_AMBIENT_RISK, "HIGH_AMBIENT_RISK"),
+        (Flags::EXTREME_NORMALIZATION, "EXTREME_NORMALIZATION"),
+    ]
+    .into_iter()
+    .filter(|(bit, _)| row.classify_flags.contains(*bit))
+    .map(|(_, name)| name)
+    .collect();
+    if row.low_confidence && !out.contains(&"LOW_CONFIDENCE") {
+        out.insert(0, "LOW_CONFIDENCE");
+    }
+    if row.low_secretory_signal {
+        out.push("LOW_SECRETORY_SIGNAL");
+    }
+    if row.high_proliferation {
+        out.push("HIGH_PROLIFERATION");
+    }
+    out
+}
+
+/// Contract columns, then the `*_ref_pctl` columns with a reference, then
+/// the `upstream_*` columns.
+fn secretion_header(with_reference: bool, upstream: &[UpstreamColumn]) -> String {
+    let mut header = String::from(
+        "barcode\tsample\tcondition\tspecies\tlibsize\tnnz\texpressed_genes\tsecretory_load\texocytosis_bias\tvesicle_traffic_intensity\ter_golgi_pressure\tparacrine_signal_potential\tstress_secretion_index\tregime\tflags\tconfidence\tproliferation_score\tgenes_per_1k_umis",
+    );
+    if with_reference {
+        for (name, _) in REF_PCTL_COLUMNS {
+            header.push_str(&format!("\t{name}_ref_pctl"));
+        }
+    }
+    for column in upstream {
+        header.push('\t');
+        header.push_str(&column.output_name());
+    }
+    header
+}
+
+/// One secretion.tsv row without the trailing newline.
+fn secretion_line(row: &CellOutput, labels: &RegimeLabels) -> Result<String, Stage7Error> {
+    let mut line = format!(
+        "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+        field("barcode", row.barcode)?,
+        field("sample", row.sample)?,
+        field("condition", row.condition)?,
+        field("species", row.species)?,
+        row.libsize,
+        row.nnz,
+        row.expressed_genes,
+        clamped01(row.secretory_load),
+        clamped01(row.exocytosis_bias),
+        clamped01(row.vesicle_traffic_intensity),
+        clamped01(row.er_golgi_pressure),
+        clamped01(row.paracrine_signal_potential),
+        clamped01(row.stress_secretion_index),
+        labels.label(row.regime),
+        row.flags,
+        clamped01(row.confidence),
+        signed_or_nan(row.proliferation_score),
+        signed_or_nan(row.genes_per_1k_umis),
+    );
+    if let Some(pctl) = &row.ref_pctl {
+        for value in pctl {
+            line.push('\t');
+            line.push_str(&signed_or_nan(*value));
+        }
+    }
+    for value in &row.upstream {
+        line.push('\t');
+        line.push_str(&signed_or_nan(*value));
+    }
+    Ok(line)
+}
+
+/// Per-cell metrics summarized in samples.tsv, in column order.
+const SAMPLE_METRICS: [&str; 7] = [
+    "secretory_load",
+    "exocytosis_bias",
+    "vesicle_traffic_intensity",
+    "er_golgi_pressure",
+    "paracrine_signal_potential",
+    "stress_secretion_index",
+    "confidence",
+];
+
+fn sample_metric_values(row: &CellOutput) -> [f32; 7] {
+    [
+        row.secretory_load,
+        row.exocytosis_bias,
+        row.vesicle_traffic_intensity,
+        row.er_golgi_pressure,
+        row.paracrine_signal_potential,
+        row.stress_secretion_index,
+        row.confidence,
+    ]
+}
+
+/// One row per sample (sorted by sample id) with n_cells, median and IQR of
+/// each metric and the majority regime. Samples under `min_cells` get `NA`
+/// statistics and the `INSUFFICIENT_CELLS` regime.
+fn write_samples_tsv(
+    out_dir: &Path,
+    rows: &[CellOutput],
+    min_cells: usize,
+    labels: &RegimeLabels,
+) -> Result<(), Stage7Error> {
+    let mut by_sample: BTreeMap<&str, Vec<&CellOutput>> = BTreeMap::new();
+    for row in rows {
+        by_sample.entry(row.sample).or_default().push(row);
+    }
+
+    let mut writer = Artifact::create(out_dir, "samples.tsv")?;
+    writer.write_all(b"sample\tn_cells")?;
+    for name in SAMPLE_METRICS {
+        write!(writer, "\t{}_median\t{}_iqr", name, name)?;
+    }
+    writer.write_all(b"\tregime\n")?;
+
+    for (sample, cells) in by_sample {
+        let mut line = format!("{}\t{}", field("sample", sample)?, cells.len());
+        let gated = cells.len() < min_cells;
+        for (m, _) in SAMPLE_METRICS.iter().enumerate() {
+            let values: Vec<f32> = cells.iter().map(|c| sample_metric_values(c)[m]).collect();
+            match gated_metric(&values, min_cells).filter(|_| !gated) {
+                Some(stats) => {
+                    write!(
+                        line,
+                        "\t{}\t{}",
+                        signed_or_nan(stats.median),
+                        signed_or_nan(stats.iqr)
+                    )?;
+                }
+                None => line.push_str("\tNA\tNA"),
+            }
+        }
+        line.push('\t');
+        if gated {
+            line.push_str(INSUFFICIENT_CELLS);
+        } else {
+            line.push_str(labels.label(majority_pipeline_regime(&cells)));
+        }
+        line.push('\n');
+        writer.write_all(line.as_bytes())?;
+    }
+    writer.commit()?;
+    Ok(())
+}
+
+/// Every value the run computed for cell `i`, from the stage4-6 contexts
+/// and its secretion.tsv row.
+fn explain_cell_json(
+    i: usize,
+    row: &CellOutput,
+    axes: &AxesContext,
+    scores: &ScoresContext,
+    classify: &ClassifyContext,
+    opts: &ReportOptions,
+) -> serde_json::Value {
+    let (axis, cov, drivers) = (&axes.values[i], &axes.coverage[i], &axes.drivers[i]);
+    let axis_entries = [
+        ("SIA", axis.sia, cov.sia, &drivers.sia),
+        ("EEB", axis.eeb, cov.eeb, &drivers.eeb),
+        ("SLI", axis.sli, cov.sli, &drivers.sli),
+        ("MEI", axis.mei, cov.mei, &drivers.mei),
+        ("ECMI", axis.ecmi, cov.ecmi, &drivers.ecmi),
+        ("APCI", axis.apci, cov.apci, &drivers.apci),
+        ("GDI", axis.gdi, cov.gdi, &drivers.gdi),
+    ];
+    let composite_entries = [
+        (
+            "OII",
+            scores.oii[i],
+            scores.cov_oii[i],
+            &scores.drivers_oii[i],
+        ),
+        (
+            "IAI",
+            scores.iai[i],
+            scores.cov_iai[i],
+            &scores.drivers_iai[i],
+        ),
+        (
+            "ESI",
+            scores.esi[i],
+            scores.cov_esi[i],
+            &scores.drivers_esi[i],
+        ),
+    ];
+    let entry = |(name, value, coverage, drivers): (&str, f32, f32, &String)| {
+        (
+            name.to_string(),
+            json!({"value": value, "coverage": coverage, "drivers": drivers}),
+        )
+    };
+    let axes_json: serde_json::Map<_, _> = axis_entries.into_iter().map(entry).collect();
+    let mut composites_json: serde_json::Map<_, _> =
+        composite_entries.into_iter().map(entry).collect();
+    composites_json.insert(
+        "score_concentration".to_string(),
+        json!(scores.score_concentration[i]),
+    );
+    let thresholds = classify.thresholds.for_cell(i);
+    let rule_trace: Vec<serde_json::Value> = rule_trace(
+        axis,
+        pos_eeb(axis.eeb),
+        scores.oii[i],
+        scores.esi[i],
+        &thresholds.thresholds,
+    )
+    .into_iter()
+    .map(|(rule, matched)| json!({"rule": rule.as_str(), "matched": matched}))
+    .collect();
+    let mut secretion = json!({
+        "secretory_load": row.secretory_load,
+        "exocytosis_bias": row.exocytosis_bias,
+        "vesicle_traffic_intensity": row.vesicle_traffic_intensity,
+        "er_golgi_pressure": row.er_golgi_pressure,
+        "paracrine_signal_potential": row.paracrine_signal_potential,
+        "stress_secretion_index": row.stress_secretion_index,
+        "regime": opts.regime_labels.label(row.regime),
+        "flags": row.flags,
+        "confidence": row.confidence,
+        "proliferation_score": row.proliferation_score,
+    });
+    if let Some(pctl) = row.ref_pctl {
+        for ((name, _), value) in REF_PCTL_COLUMNS.iter().zip(pctl) {
+            secretion[format!("{name}_ref_pctl")] = json!(value);
+        }
+    }
+    json!({
+        "barcode": row.barcode,
+        "cell_index": i,
+        "sample": row.sample,
+        "condition": row.condition,
+        "species": row.species,
+        "counts": {
+            "libsize": row.libsize,
+            "nnz": row.nnz,
+            "expressed_genes": row.expressed_genes,
+            "genes_per_1k_umis": row.genes_per_1k_umis,
+        },
+        "axes": axes_json,
+        "composites": composites_json,
+        "classification": {
+            "regime": classify.regimes[i].as_str(),
+            "rule_id": classify.rule_ids[i].as_str(),
+            "rule_trace": rule_trace,
+            "flags": classify.flags[i].to_csv(),
+            "thresholds_id": thresholds.id,
+        },
+        "secretion": secretion,
+    })
+}
+
+/// Most frequent regime; ties resolve to the earlier entry of
+/// [`PipelineRegime::ALL`], whatever the display order.
+fn majority_pipeline_regime(cells: &[&CellOutput]) -> PipelineRegime {
+    let mut best = PipelineRegime::Unclassified;
+    let mut best_count = 0usize;
+    for regime in PipelineRegime::ALL {
+        let count = cells.iter().filter(|c| c.regime == regime).count();
+        if count > best_count {
+            best_count = count;
+            best = regime;
+        }
+    }
+    best
+}
+
+fn build_frac_ge<'a>(
+    rows: &[CellOutput<'a>],
+    meta: &MetaColumns,
+    cutoffs: &FracGeThresholds,
+) -> FracGeSummary {
+    let thresholds: Vec<(String, Vec<f32>)> = SAMPLE_METRICS
+        .iter()
+        .map(|name| (name.to_string(), cutoffs.get(name).to_vec()))
+        .collect();
+    let group = |cells: &[&CellOutput]| FracGeGroup {
+        n_cells: cells.len(),
+        n_ge: thresholds
+            .iter()
+            .enumerate()
+            .map(|(m, (_, cuts))| {
+                let values: Vec<f32> = cells.iter().map(|c| sample_metric_values(c)[m]).collect();
+                count_ge(&values, cuts)
+            })
+            .collect(),
+    };
+    let by = |names: &BTreeSet<String>, key: fn(&CellOutput<'a>) -> &'a str| {
+        let mut groups: BTreeMap<String, Vec<&CellOutput>> =
+            names.iter().map(|n| (n.clone(), Vec::new())).collect();
+        for row in rows {
+            groups.entry(key(row).to_string()).or_default().push(row);
+        }
+        groups
+            .into_iter()
+            .map(|(name, cells)| (name, group(&cells)))
+            .collect()
+    };
+
+    let all: Vec<&CellOutput> = rows.iter().collect();
+    FracGeSummary {
+        global: group(&all),
+        by_sample: by(&meta.samples, |r| r.sample),
+        by_condition: by(&meta.conditions, |r| r.condition),
+        thresholds,
+    }
+}
+
+/// Compares the two meta conditions: per regime label a two-proportion
+/// z-test, per metric in [`COMPARED_METRICS`] a Mann–Whitney U. Skipped
+/// unless there are exactly two conditions with `min_cells` cells each.
+fn build_condition_comparison(
+    rows: &[CellOutput],
+    meta: &MetaColumns,
+    labels: &RegimeLabels,
+    min_cells: usize,
+) -> GroupComparison {
+    let members: Vec<Vec<&CellOutput>> = meta
+        .conditions
+        .iter()
+        .map(|c| rows.iter().filter(|r| r.condition == c).collect())
+        .collect();
+    let groups: Vec<(String, usize)> = meta
+        .conditions
+        .iter()
+        .zip(&members)
+        .map(|(c, cells)| (c.clone(), cells.len()))
+        .collect();
+    if let Some(reason) = skip_reason(&groups, min_cells) {
+        return GroupComparison::skipped(groups, min_cells, reason);
+    }
+    let (a, b) = (&members[0], &members[1]);
+
+    let mut label_names = labels.ordered_labels();
+    let mut seen = BTreeSet::new();
+    label_names.retain(|label| seen.insert(*label));
+    let regimes = label_names
+        .into_iter()
+        .map(|label| {
+            let k = |cells: &[&CellOutput]| {
+                cells
+                    .iter()
+                    .filter(|c| labels.label(c.regime) == label)
+                    .count()
+            };
+            (
+                label.to_string(),
+                two_proportion_z(k(a), a.len(), k(b), b.len()),
+            )
+        })
+        .collect();
+    let metrics = COMPARED_METRICS
+        .iter()
+        .map(|&(name, value)| {
+            let values = |cells: &[&CellOutput]| cells.iter().map(|c| value(c)).collect::<Vec<_>>();
+            (name.to_string(), mann_whitney_u(&values(a), &values(b)))
+        })
+        .collect();
+    GroupComparison {
+        groups,
+        min_cells,
+        skipped: None,
+        regimes,
+        metrics,
+    }
+}
+
+type CellMetric = fn(&CellOutput) -> f32;
+
+/// Metrics the condition comparison ranks, by summary.json name.
+const COMPARED_METRICS: [(&str, CellMetric); 2] = [
+    ("secretory_load", |c| c.secretory_load),
+    ("stress_secretion_index", |c| c.stress_secretion_index),
+];
+
+/// `condition_comparison` section: groups in comparison order, then either
+/// the skip reason or the tests with a note on how many p-values there are.
+fn write_condition_comparison_json(
+    out: &mut String,
+    comparison: &GroupComparison,
+) -> Result<(), Stage7Error> {
+    out.push_str("  \"condition_comparison\": {\n    \"groups\": [");
+    for (i, (name, n)) in comparison.groups.iter().enumerate() {
+        out.push_str(if i > 0 { ", " } else { "" });
+        write!(
+            out,
+            "{{\"name\": {}, \"n_cells\": {}}}",
+            serde_json::to_string(name)?,
+            n
+        )?;
+    }
+    writeln!(out, "],\n    \"min_cells\": {},", comparison.min_cells)?;
+    if let Some(reason) = &comparison.skipped {
+        writeln!(out, "    \"skipped\": {}", serde_json::to_string(reason)?)?;
+        out.push_str("  },\n");
+        return Ok(());
+    }
+    out.push_str("    \"regimes\": {");
+    for (i, (label, t)) in comparison.regimes.iter().enumerate() {
+        out.push_str(if i > 0 { ",\n      " } else { "\n      " });
+        write!(
+            out,
+            "{}: {{\"fraction_a\": {}, \"fraction_b\": {}, \"z\": {}, \"p_value\": {}}}",
+            serde_json::to_string(label)?,
+            fmt_json_f64(t.fraction_a),
+            fmt_json_f64(t.fraction_b),
+            fmt_json_f64(t.z),
+            fmt_p_value(t.p_value)
+        )?;
+    }
+    out.push_str("\n    },\n    \"metrics\": {");
+    for (i, (metric, t)) in comparison.metrics.iter().enumerate() {
+        out.push_str(if i > 0 { ",\n      " } else { "\n      " });
+        write!(out, "\"{}\": ", metric)?;
+        match t {
+            Some(t) => write!(
+                out,
+                "{{\"n_a\": {}, \"n_b\": {}, \"u\": {}, \"z\": {}, \"p_value\": {}}}",
+                t.n_a,
+                t.n_b,
+                fmt_json_f64(t.u),
+                fmt_json_f64(t.z),
+                fmt_p_value(t.p_value)
+            )?,
+            None => out.push_str("null"),
+        }
+    }
+    out.push_str("\n    },\n");
+    writeln!(
+        out,
+        "    \"n_tests\": {},\n    \"note\": \"two-sided normal-approximation p-values, not adjusted for the {} tests\"",
+        comparison.n_tests(),
+        comparison.n_tests()
+    )?;
+    out.push_str("  },\n");
+    Ok(())
+}
+
+/// Long format, one line per group, metric and cut-off: the global group
+/// (`group_by = all`), then samples and conditions sorted by name.
+fn write_condition_summary_tsv(out_dir: &Path, frac_ge: &FracGeSummary) -> Result<(), Stage7Error> {
+    let mut writer = Artifact::create(out_dir, "condition_summary.tsv")?;
+    writer.write_all(b"group_by\tgroup\tn_cells\tmetric\tthreshold\tn_ge\tfrac_ge\n")?;
+    let global = ".".to_string();
+    for (group_by, groups) in [
+        ("all", vec![(&global, &frac_ge.global)]),
+        ("sample", frac_ge.by_sample.iter().collect()),
+        ("condition", frac_ge.by_condition.iter().collect()),
+    ] {
+        for (name, group) in groups {
+            let name = field(group_by, name)?;
+            for (m, (metric, cuts)) in frac_ge.thresholds.iter().enumerate() {
+                for (t, cut) in cuts.iter().enumerate() {
+                    writeln!(
+                        writer,
+                        "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                        group_by,
+                        name,
+                        group.n_cells,
+                        metric,
+                        fixed6(*cut),
+                        group.n_ge[m][t],
+                        clamped01(group.fraction(m, t))
+                    )?;
+                }
+            }
+        }
+    }
+    writer.commit()?;
+    Ok(())
+}
+
+/// `frac_ge` section: cut-offs per metric once, then per group the cell count
+/// and, per metric, the fractions in cut-off order.
+fn write_frac_ge_json(out: &mut String, frac_ge: &FracGeSummary) -> Result<(), Stage7Error> {
+    let write_group = |out: &mut String, group: &FracGeGroup| -> Result<(), Stage7Error> {
+        write!(out, "{{\"n_cells\": {}", group.n_cells)?;
+        for (m, (metric, cuts)) in frac_ge.thresholds.iter().enumerate() {
+            let fractions: Vec<String> = (0..cuts.len())
+                .map(|t| clamped01(group.fraction(m, t)))
+                .collect();
+            write!(out, ", \"{}\": [{}]", metric, fractions.join(", "))?;
+        }
+        out.push('}');
+        Ok(())
+    };
+    let write_groups = |out: &mut String,
+                        name: &str,
+                        groups: &BTreeMap<String, FracGeGroup>,
+                        last: bool|
+     -> Result<(), Stage7Error> {
+        write!(out, "    \"{}\": {{", name)?;
+        for (i, (group_name, group)) in groups.iter().enumerate() {
+            out.push_str(if i > 0 { ",\n      " } else { "\n      " });
+            out.push_str(&serde_json::to_string(group_name)?);
+            out.push_str(": ");
+            write_group(out, group)?;
+        }
+        if !groups.is_empty() {
+            out.push_str("\n    ");
+        }
+        out.push_str(if last { "}\n" } else { "},\n" });
+        Ok(())
+    };
+
+    out.push_str("  \"frac_ge\": {\n");
+    out.push_str("    \"thresholds\": {");
+    for (i, (metric, cuts)) in frac_ge.thresholds.iter().enumerate() {
+        let cuts: Vec<String> = cuts.iter().map(|c| fixed6(*c)).collect();
+        write!(
+            out,
+            "{}\"{}\": [{}]",
+            if i > 0 { ", " } else { "" },
+            metric,
+            cuts.join(", ")
+        )?;
+    }
+    out.push_str("},\n");
+    out.push_str("    \"global\": ");
+    write_group(out, &frac_ge.global)?;
+    out.push_str(",\n");
+    write_groups(out, "by_sample", &frac_ge.by_sample, false)?;
+    write_groups(out, "by_condition", &frac_ge.by_condition, true)?;
+    out.push_str("  },\n");
     Ok(())
 }
 
@@ -265,30 +1715,169 @@ fn write_summary_json(out_dir: &Path, summary: &FinalSummary) -> Result<(), Stag
     push_quoted(&mut out, &summary.tool.simd)?;
     out.push_str("\n");
     out.push_str("  },\n");
+    if let Some(run) = &summary.run {
+        out.push_str("  \"run\": {\n");
+        out.push_str("    \"label\": ");
+        push_quoted(&mut out, &run.label)?;
+        if let Some(description) = &run.description {
+            out.push_str(",\n    \"description\": ");
+            push_quoted(&mut out, description)?;
+        }
+        out.push_str("\n  },\n");
+    }
     out.push_str("  \"input\": {\n");
-    let _ = write!(out, "    \"n_cells\": {},\n", summary.input.n_cells);
+    write!(out, "    \"n_cells\": {},\n", summary.input.n_cells)?;
     out.push_str("    \"species\": ");
     push_quoted(&mut out, &summary.input.species)?;
-    out.push_str("\n");
+    out.push_str(",\n");
+    out.push_str("    \"gene_set_hash\": ");
+    push_quoted(&mut out, &summary.input.gene_set_hash)?;
+    out.push_str(",\n");
+    writeln!(
+        out,
+        "    \"libsize_low_tail\": {{\"min\": {}, \"p1\": {}}},",
+        fmt_json_f32(summary.input.libsize_low_tail.min),
+        fmt_json_f32(summary.input.libsize_low_tail.p1)
+    )?;
+    let matrix_values = &summary.input.matrix_values;
+    out.push_str("    \"matrix_values\": {\"value_type\": ");
+    match &matrix_values.value_type {
+        Some(value_type) => push_quoted(&mut out, value_type)?,
+        None => out.push_str("null"),
+    }
+    writeln!(
+        out,
+        ", \"rounded\": {}, \"rounded_to_zero\": {}}},",
+        matrix_values.rounded, matrix_values.rounded_to_zero
+    )?;
+    let panel_genes = &summary.input.panel_genes;
+    let count_json = |c: &GeneUniverseCount| {
+        format!(
+            "{{\"mapped\": {}, \"unmapped\": {}, \"total\": {}}}",
+            c.mapped,
+            c.unmapped(),
+            c.total
+        )
+    };
+    let overall = panel_genes.universe.overall;
+    write!(
+        out,
+        "    \"panel_genes\": {{\"mapped\": {}, \"unmapped\": {}, \"total\": {}, \"mapped_fraction\": {}, \"min_mapped_fraction\": {}, \"low_coverage\": {}, \"by_axis\": {{",
+        overall.mapped,
+        overall.unmapped(),
+        overall.total,
+        fmt_json_f32(overall.mapped_fraction()),
+        fmt_json_f32(panel_genes.min_mapped_fraction),
+        panel_genes.low_coverage
+    )?;
+    for (i, (axis, count)) in panel_genes.universe.by_axis.iter().enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        push_quoted(&mut out, axis)?;
+        write!(out, ": {}", count_json(count))?;
+    }
+    out.push_str("}},\n");
+    writeln!(
+        out,
+        "    \"meta_match_fraction\": {},",
+        summary
+            .input
+            .meta_match_fraction
+            .map_or_else(|| "null".to_string(), fmt_json_f32)
+    )?;
+    match &summary.input.meta_issues {
+        Some(issues) => writeln!(
+            out,
+            "    \"meta_issues\": {{\"unknown_species\": {}, \"short_rows\": {}, \"duplicate_cell_ids\": {}}}",
+            issues.unknown_species, issues.short_rows, issues.duplicate_cell_ids
+        )?,
+        None => out.push_str("    \"meta_issues\": null\n"),
+    }
     out.push_str("  },\n");
+    if let Some(chemistry) = &summary.chemistry {
+        out.push_str("  \"chemistry\": {\n");
+        out.push_str("    \"name\": ");
+        push_quoted(&mut out, &chemistry.name)?;
+        out.push_str(",\n    \"source\": ");
+        push_quoted(&mut out, &chemistry.source)?;
+        out.push_str(",\n");
+        writeln!(
+            out,
+            "    \"median_detected\": {},",
+            fmt_json_f32(chemistry.median_detected)
+        )?;
+        writeln!(
+            out,
+            "    \"median_libsize\": {},",
+            fmt_json_f32(chemistry.median_libsize)
+        )?;
+        writeln!(out, "    \"low_counts\": {},", chemistry.low_counts)?;
+        writeln!(out, "    \"few_detected\": {}", chemistry.few_detected)?;
+        out.push_str("  },\n");
+    }
+    if let Some(upstream) = &summary.upstream {
+        out.push_str("  \"upstream\": {\n");
+        out.push_str("    \"source\": ");
+        push_quoted(&mut out, &upstream.source)?;
+        out.push_str(",\n    \"columns\": {");
+        for (i, (name, q)) in upstream.columns.iter().enumerate() {
+            out.push_str(if i == 0 { "\n      " } else { ",\n      " });
+            push_quoted(&mut out, name)?;
+            write!(
+                out,
+                ": {{\"median\": {}, \"p90\": {}, \"p99\": {}}}",
+                fmt_json_f32(q.median),
+                fmt_json_f32(q.p90),
+                fmt_json_f32(q.p99),
+            )?;
+        }
+        if !upstream.columns.is_empty() {
+            out.push_str("\n    ");
+        }
+        out.push_str("}\n  },\n");
+    }
     out.push_str("  \"distributions\": {\n");
     out.push_str("    \"secretory_load\": {");
-    push_quantiles_json(&mut out, &summary.distributions.secretory_load);
+    push_quantiles_json(&mut out, &summary.distributions.secretory_load)?;
     out.push_str("},\n");
     out.push_str("    \"er_golgi_pressure\": {");
-    push_quantiles_json(&mut out, &summary.distributions.er_golgi_pressure);
+    push_quantiles_json(&mut out, &summary.distributions.er_golgi_pressure)?;
     out.push_str("},\n");
     out.push_str("    \"stress_secretion_index\": {");
-    push_quantiles_json(&mut out, &summary.distributions.stress_secretion_index);
+    push_quantiles_json(&mut out, &summary.distributions.stress_secretion_index)?;
+    out.push_str("},\n");
+    out.push_str("    \"score_concentration\": {");
+    push_quantiles_json(&mut out, &summary.distributions.score_concentration)?;
     out.push_str("}\n");
     out.push_str("  },\n");
+    out.push_str("  \"histograms\": {\n");
+    writeln!(out, "    \"bins\": {},", HISTOGRAM_BINS)?;
+    out.push_str("    \"range\": [0.0, 1.0],\n");
+    let h = &summary.histograms;
+    let histograms = [
+        ("secretory_load", &h.secretory_load),
+        ("er_golgi_pressure", &h.er_golgi_pressure),
+        ("stress_secretion_index", &h.stress_secretion_index),
+        ("confidence", &h.confidence),
+    ];
+    for (i, (name, counts)) in histograms.iter().enumerate() {
+        let joined: Vec<String> = counts.iter().map(|c| c.to_string()).collect();
+        write!(out, "    \"{}\": [{}]", name, joined.join(", "))?;
+        out.push_str(if i + 1 < histograms.len() {
+            ",\n"
+        } else {
+            "\n"
+        });
+    }
+    out.push_str("  },\n");
     out.push_str("  \"regimes\": {\n");
     out.push_str("    \"counts\": {\n");
     let mut counts_iter = summary.regimes.counts.iter().peekable();
     while let Some((name, count)) = counts_iter.next() {
         out.push_str("      ");
         push_quoted(&mut out, name)?;
-        let _ = write!(out, ": {}", count);
+        write!(out, ": {}", count)?;
         if counts_iter.peek().is_some() {
             out.push(',');
         }
@@ -300,7 +1889,7 @@ fn write_summary_json(out_dir: &Path, summary: &FinalSummary) -> Result<(), Stag
     while let Some((name, frac)) = fracs_iter.next() {
         out.push_str("      ");
         push_quoted(&mut out, name)?;
-        let _ = write!(out, ": {}", fmt6(*frac));
+        write!(out, ": {}", clamped01(*frac))?;
         if fracs_iter.peek().is_some() {
             out.push(',');
         }
@@ -309,34 +1898,253 @@ fn write_summary_json(out_dir: &Path, summary: &FinalSummary) -> Result<(), Stag
     out.push_str("    }\n");
     out.push_str("  },\n");
     out.push_str("  \"qc\": {\n");
-    let _ = write!(
+    write!(
         out,
         "    \"low_confidence_fraction\": {},\n",
-        fmt6(summary.qc.low_confidence_fraction)
-    );
-    let _ = write!(
+        clamped01(summary.qc.low_confidence_fraction)
+    )?;
+    write!(
         out,
-        "    \"low_secretory_signal_fraction\": {}\n",
-        fmt6(summary.qc.low_secretory_signal_fraction)
-    );
-    out.push_str("  }\n");
-    out.push_str("}\n");
-    std::fs::write(out_dir.join("summary.json"), out)?;
+        "    \"low_secretory_signal_fraction\": {},\n",
+        clamped01(summary.qc.low_secretory_signal_fraction)
+    )?;
+    writeln!(
+        out,
+        "    \"high_proliferation_fraction\": {},",
+        clamped01(summary.qc.high_proliferation_fraction)
+    )?;
+    writeln!(
+        out,
+        "    \"high_ambient_risk_fraction\": {},",
+        clamped01(summary.qc.high_ambient_risk_fraction)
+    )?;
+    writeln!(
+        out,
+        "    \"extreme_normalization_fraction\": {},",
+        clamped01(summary.qc.extreme_normalization_fraction)
+    )?;
+    let complexity = &summary.qc.genes_per_1k_umis;
+    writeln!(
+        out,
+        "    \"genes_per_1k_umis\": {{\"median\": {}, \"p90\": {}, \"p99\": {}}},",
+        fmt_json_f32(complexity.median),
+        fmt_json_f32(complexity.p90),
+        fmt_json_f32(complexity.p99)
+    )?;
+    out.push_str("    \"high_ambient_risk_by_sample\": {");
+    for (i, (sample, count)) in summary.qc.high_ambient_risk_by_sample.iter().enumerate() {
+        out.push_str(if i == 0 { "\n      " } else { ",\n      " });
+        push_quoted(&mut out, sample)?;
+        write!(
+            out,
+            ": {{\"n_cells\": {}, \"n_flagged\": {}, \"fraction\": {}}}",
+            count.n_cells,
+            count.n_flagged,
+            clamped01(count.fraction())
+        )?;
+    }
+    if summary.qc.high_ambient_risk_by_sample.is_empty() {
+        out.push_str("},\n");
+    } else {
+        out.push_str("\n    },\n");
+    }
+    out.push_str("    \"flag_combinations\": [");
+    for (i, combo) in summary.qc.flag_combinations.iter().enumerate() {
+        out.push_str(if i == 0 { "\n      " } else { ",\n      " });
+        out.push_str("{\"flags\": ");
+        push_quoted(&mut out, &combo.flags)?;
+        write!(
+            out,
+            ", \"n_cells\": {}, \"fraction\": {}}}",
+            combo.n_cells,
+            clamped01(combo.fraction)
+        )?;
+    }
+    if summary.qc.flag_combinations.is_empty() {
+        out.push_str("]\n");
+    } else {
+        out.push_str("\n    ]\n");
+    }
+    out.push_str("  },\n");
+    write_frac_ge_json(&mut out, &summary.frac_ge)?;
+    if let Some(comparison) = &summary.condition_comparison {
+        write_condition_comparison_json(&mut out, comparison)?;
+    }
+    out.push_str("  \"caveats\": {\n");
+    out.push_str("    \"absent_axes\": [");
+    for (i, axis) in summary.caveats.absent_axes.iter().enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        push_quoted(&mut out, axis)?;
+    }
+    out.push_str("],\n");
+    out.push_str("    \"panels_missing_required\": [");
+    for (i, w) in summary.caveats.panels_missing_required.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        out.push_str("\n      {\"panel_id\": ");
+        push_quoted(&mut out, &w.panel_id)?;
+        out.push_str(", \"axis\": ");
+        push_quoted(&mut out, &w.axis)?;
+        out.push_str(", \"missing_required\": ");
+        push_string_list(&mut out, &w.missing_required)?;
+        write!(
+            out,
+            ", \"mappable_fraction\": {}}}",
+            fmt_json_f32(w.mappable_fraction)
+        )?;
+    }
+    if !summary.caveats.panels_missing_required.is_empty() {
+        out.push_str("\n    ");
+    }
+    out.push_str("],\n");
+    out.push_str("    \"missing_required_by_axis\": {");
+    for (i, a) in summary.caveats.missing_required_by_axis.iter().enumerate() {
+        out.push_str(if i == 0 { "\n      " } else { ",\n      " });
+        push_quoted(&mut out, &a.axis)?;
+        out.push_str(": {\"panels\": ");
+        push_string_list(&mut out, &a.panels)?;
+        out.push_str(", \"missing_required\": ");
+        push_string_list(&mut out, &a.missing_required)?;
+        out.push('}');
+    }
+    if !summary.caveats.missing_required_by_axis.is_empty() {
+        out.push_str("\n    ");
+    }
+    out.push_str("},\n");
+    match &summary.caveats.worst_covered_axis {
+        Some(worst) => {
+            out.push_str("    \"worst_covered_axis\": {\"axis\": ");
+            push_quoted(&mut out, &worst.axis)?;
+            writeln!(
+                out,
+                ", \"coverage_median\": {}}},",
+                fmt_json_f32(worst.coverage_median)
+            )?;
+        }
+        None => out.push_str("    \"worst_covered_axis\": null,\n"),
+    }
+    writeln!(
+        out,
+        "    \"low_axis_coverage_fraction\": {},",
+        clamped01(summary.caveats.low_axis_coverage_fraction)
+    )?;
+    writeln!(
+        out,
+        "    \"sanitized_fields\": {},",
+        summary.caveats.sanitized_fields
+    )?;
+    out.push_str("    \"axis_panels\": {\n");
+    let entries = summary.caveats.axis_panels.entries();
+    for (i, (axis, count)) in entries.iter().enumerate() {
+        writeln!(
+            out,
+            "      \"{}\": {{\"panels\": {}, \"mapped_panels\": {}, \"mappable_genes\": {}}}{}",
+            axis,
+            count.panels,
+            count.mapped_panels,
+            count.mappable_genes,
+            if i + 1 < entries.len() { "," } else { "" }
+        )?;
+    }
+    out.push_str("    },\n");
+    out.push_str("    \"skipped_panel_files\": [");
+    for (i, skipped) in summary.caveats.skipped_panel_files.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        out.push_str("\n      {\"file\": ");
+        push_quoted(&mut out, &skipped.file)?;
+        out.push_str(", \"errors\": [");
+        for (j, error) in skipped.errors.iter().enumerate() {
+            if j > 0 {
+                out.push_str(", ");
+            }
+            push_quoted(&mut out, error)?;
+        }
+        out.push_str("]}");
+    }
+    if !summary.caveats.skipped_panel_files.is_empty() {
+        out.push_str("\n    ");
+    }
+    out.push_str("]\n");
+    out.push_str("  },\n");
+    out.push_str("  \"provenance\": {\n");
+    out.push_str("    \"coverage_mode\": ");
+    push_quoted(&mut out, &summary.provenance.coverage_mode)?;
+    out.push_str(",\n");
+    out.push_str("    \"mode\": ");
+    push_quoted(&mut out, &summary.provenance.mode)?;
+    out.push_str(",\n");
+    out.push_str("    \"outputs\": ");
+    push_quoted(&mut out, &summary.provenance.outputs)?;
+    out.push_str(",\n");
+    out.push_str("    \"retain\": ");
+    push_quoted(&mut out, &summary.provenance.retain)?;
+    out.push('\n');
+    out.push_str("  }");
+    if let Some(reference) = &summary.reference {
+        out.push_str(",\n  \"reference\": {\n");
+        out.push_str("    \"panel_set_hash\": ");
+        push_quoted(&mut out, &reference.panel_set_hash)?;
+        out.push_str(",\n");
+        writeln!(out, "    \"n_cells\": {},", reference.n_cells)?;
+        out.push_str("    \"tool_version\": ");
+        push_quoted(&mut out, &reference.tool_version)?;
+        out.push_str(",\n");
+        out.push_str("    \"drift\": {\n");
+        for (i, d) in reference.drift.iter().enumerate() {
+            out.push_str("      ");
+            push_quoted(&mut out, &d.metric)?;
+            write!(
+                out,
+                ": {{\"reference_median\": {}, \"current_median\": {}, \"delta\": {}}}",
+                fmt_json_f32(d.reference_median),
+                fmt_json_f32(d.current_median),
+                fmt_json_f32(d.delta),
+            )?;
+            out.push_str(if i + 1 < reference.drift.len() {
+                ",\n"
+            } else {
+                "\n"
+            });
+        }
+        out.push_str("    }\n");
+        out.push_str("  }");
+    }
+    out.push_str("\n}\n");
+    write_artifact(out_dir, "summary.json", out)?;
+    Ok(())
+}
+
+fn push_string_list(buf: &mut String, values: &[String]) -> Result<(), Stage7Error> {
+    buf.push_str(&serde_json::to_string(values)?);
     Ok(())
 }
 
-fn push_quantiles_json(buf: &mut String, q: &Quantiles) {
-    let _ = write!(
+fn push_quantiles_json(buf: &mut String, q: &Quantiles) -> Result<(), Stage7Error> {
+    write!(
         buf,
         "\"median\": {}, \"p90\": {}, \"p99\": {}",
-        fmt6(q.median),
-        fmt6(q.p90),
-        fmt6(q.p99),
-    );
+        clamped01(q.median),
+        clamped01(q.p90),
+        clamped01(q.p99),
+    )?;
+    Ok(())
 }
 
-fn write_pipeline_step_json(out_dir: &Path) -> Result<(), Stage7Error> {
-    let pipeline_step = json!({
+/// `extra_artifacts` adds optional files as `role: file name` entries.
+fn write_pipeline_step_json(
+    out_dir: &Path,
+    extra_artifacts: &[(&str, &str)],
+    labels: &RegimeLabels,
+    gene_set_hash: &str,
+    run_label: Option<&RunLabel>,
+    dataset_flags: &[&str],
+) -> Result<(), Stage7Error> {
+    let mut pipeline_step = json!({
         "tool": {
             "name": "kira-secretion",
             "stage": "secretion",
@@ -345,7 +2153,10 @@ fn write_pipeline_step_json(out_dir: &Path) -> Result<(), Stage7Error> {
         "artifacts": {
             "summary": "summary.json",
             "primary_metrics": "secretion.tsv",
-            "panels": "panels_report.tsv"
+            "panels": "panels_report.tsv",
+            "provenance": "provenance.json",
+            "axes_summary": "axes_summary.json",
+            "composites_summary": "composites_summary.json"
         },
         "cell_metrics": {
             "file": "secretion.tsv",
@@ -354,18 +2165,28 @@ fn write_pipeline_step_json(out_dir: &Path) -> Result<(), Stage7Error> {
             "confidence_column": "confidence",
             "flag_column": "flags"
         },
-        "regimes": PIPELINE_REGIMES
+        "contract_version": labels.contract().version(),
+        "regimes": labels.ordered_labels(),
+        "gene_set_hash": gene_set_hash,
+        "dataset_flags": dataset_flags
     });
-    std::fs::write(
-        out_dir.join("pipeline_step.json"),
+    for (role, file) in extra_artifacts {
+        pipeline_step["artifacts"][*role] = json!(file);
+    }
+    if let Some(run_label) = run_label {
+        pipeline_step["run"] = json!(run_label);
+    }
+    write_artifact(
+        out_dir,
+        "pipeline_step.json",
         serde_json::to_string_pretty(&pipeline_step)?,
     )?;
     Ok(())
 }
 
 fn write_panels_report(out_dir: &Path, panels: &PanelsContext) -> Result<(), Stage7Error> {
-    let mut writer = BufWriter::new(std::fs::File::create(out_dir.join("panels_report.tsv"))?);
-    writer.write_all(b"panel_id\tpanel_name\tpanel_group\tpanel_size_defined\tpanel_size_mappable\tmissing_genes\tcoverage_median\tcoverage_p10\tsum_median\tsum_p90\tsum_p99\n")?;
+    let mut writer = Artifact::create(out_dir, "panels_report.tsv")?;
+    writer.write_all(b"panel_id\tpanel_name\tpanel_axis\tpanel_group\tpanel_size_defined\tpanel_size_mappable\tmissing_genes\tcoverage_median\tcoverage_p10\tsum_median\tsum_p90\tsum_p99\tpanel_version\tpanel_source\n")?;
 
     for (panel_idx, panel) in panels.panels.panels.iter().enumerate() {
         let mapping = &panels.mappings[panel_idx];
@@ -374,14 +2195,10 @@ fn write_panels_report(out_dir: &Path, panels: &PanelsContext) -> Result<(), Sta
 
         for cell in &panels.per_cell {
             sums.push(cell.sums[panel_idx]);
-            let req_total = mapping.required_total as u32;
-            let missing = cell.required_missing[panel_idx];
-            let cov = if req_total == 0 {
-                1.0
-            } else {
-                1.0 - (missing as f32 / req_total as f32)
-            };
-            coverages.push(clamp01(cov));
+            coverages.push(required_coverage(
+                mapping.required_total,
+                cell.required_missing[panel_idx],
+            ));
         }
 
         let mut missing = Vec::new();
@@ -391,31 +2208,149 @@ fn write_panels_report(out_dir: &Path, panels: &PanelsContext) -> Result<(), Sta
             }
         }
 
-        coverages.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
-        sums.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        let coverage_q = percentiles_interpolated(&coverages, &[0.5, 0.10], NanPolicy::Skip);
+        let sum_q = percentiles_interpolated(&sums, &[0.5, 0.90, 0.99], NanPolicy::Skip);
 
         let line = format!(
-            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
-            panel.id,
-            panel.description,
-            panel.axis,
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+            field("panel_id", &panel.id)?,
+            field("panel_name", &panel.description)?,
+            field("panel_axis", &panel.axis)?,
+            field("panel_group", panel.group_name())?,
             panel.genes.len(),
             mapping.mapped.iter().filter(|m| m.is_some()).count(),
             if missing.is_empty() {
                 ".".to_string()
             } else {
-                missing.join(",")
+                field("missing_genes", &missing.join(","))?.into_owned()
             },
-            fmt6(percentile(&coverages, 0.5)),
-            fmt6(percentile(&coverages, 0.10)),
-            fmt6(percentile(&sums, 0.5)),
-            fmt6(percentile(&sums, 0.90)),
-            fmt6(percentile(&sums, 0.99)),
+            clamped01(coverage_q[0]),
+            clamped01(coverage_q[1]),
+            clamped01(sum_q[0]),
+            clamped01(sum_q[1]),
+            clamped01(sum_q[2]),
+            field("panel_version", panel.version.as_deref().unwrap_or("."))?,
+            field("panel_source", panel.source.as_deref().unwrap_or("."))?,
         );
         writer.write_all(line.as_bytes())?;
     }
 
-    writer.flush()?;
+    writer.commit()?;
+    Ok(())
+}
+
+/// Fraction of a panel's required genes detected in a cell; 1 when the
+/// panel has no required genes.
+fn required_coverage(required_total: usize, missing: u32) -> f32 {
+    if required_total == 0 {
+        1.0
+    } else {
+        clamp01(1.0 - (missing as f32 / required_total as f32))
+    }
+}
+
+/// Coverage at or above which a cell counts toward `frac_coverage_ge_0.8`.
+const PANELS_BY_SAMPLE_COVERAGE: f32 = 0.8;
+
+/// One row per (panel, sample), panels in panel order and samples sorted,
+/// with the median panel sum and coverage of the sample's cells.
+fn write_panels_by_sample_tsv(
+    out_dir: &Path,
+    panels: &PanelsContext,
+    cell_samples: &[String],
+) -> Result<(), Stage7Error> {
+    let mut by_sample: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+    for (cell_idx, sample) in cell_samples.iter().enumerate() {
+        by_sample.entry(sample.as_str()).or_default().push(cell_idx);
+    }
+
+    let mut writer = Artifact::create(out_dir, "panels_by_sample.tsv")?;
+    writer.write_all(
+        b"panel_id\tsample\tn_cells\tsum_median\tcoverage_median\tfrac_coverage_ge_0.8\n",
+    )?;
+    for (panel_idx, panel) in panels.panels.panels.iter().enumerate() {
+        let required_total = panels.mappings[panel_idx].required_total;
+        let panel_id = field("panel_id", &panel.id)?;
+        for (sample, cells) in &by_sample {
+            let sums: Vec<f32> = cells
+                .iter()
+                .map(|&i| panels.per_cell[i].sums[panel_idx])
+                .collect();
+            let coverages: Vec<f32> = cells
+                .iter()
+                .map(|&i| {
+                    required_coverage(
+                        required_total,
+                        panels.per_cell[i].required_missing[panel_idx],
+                    )
+                })
+                .collect();
+            let n_ge = count_ge(&coverages, &[PANELS_BY_SAMPLE_COVERAGE])[0];
+            let line = format!(
+                "{}\t{}\t{}\t{}\t{}\t{}\n",
+                panel_id,
+                field("sample", sample)?,
+                cells.len(),
+                signed_or_nan(median(&sums, NanPolicy::Skip)),
+                clamped01(median(&coverages, NanPolicy::Skip)),
+                fixed6(ge_fraction(n_ge, cells.len())),
+            );
+            writer.write_all(line.as_bytes())?;
+        }
+    }
+    writer.commit()?;
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn write_provenance_json(
+    out_dir: &Path,
+    panels: &PanelsContext,
+    mode: &str,
+    rng: &RunRng,
+    gene_set_hash: &str,
+    fingerprint: Option<&RunFingerprint>,
+    run_label: Option<&RunLabel>,
+    thresholds_sets: &[ThresholdsSet],
+    axis_curves: Option<&AxisCurves>,
+) -> Result<(), Stage7Error> {
+    let entries: Vec<serde_json::Value> = panels
+        .panels
+        .panels
+        .iter()
+        .map(|panel| {
+            json!({
+                "id": panel.id,
+                "axis": panel.axis,
+                "genes": panel.genes.len(),
+                "version": panel.version,
+                "source": panel.source,
+                "content_hash": panel.content_hash()
+            })
+        })
+        .collect();
+    let mut provenance = json!({
+        "gene_set_hash": gene_set_hash,
+        "mode": mode,
+        "panel_files": panels.panels.files,
+        "panels": entries,
+        "rng": rng.provenance(),
+        "thresholds_sets": thresholds_sets
+    });
+    if let Some(fingerprint) = fingerprint {
+        provenance["fingerprint"] = fingerprint.to_json();
+    }
+    if let Some(run_label) = run_label {
+        provenance["run"] = json!(run_label);
+    }
+    if let Some(curves) = axis_curves {
+        provenance["axis_curves"] = json!(curves);
+    }
+    write_artifact(
+        out_dir,
+        "provenance.json",
+        serde_json::to_string_pretty(&provenance)?,
+    )?;
     Ok(())
 }
 
@@ -429,6 +2364,8 @@ fn read_meta_columns(path: &Path, barcodes: &[String]) -> Result<MetaColumns, St
         index.insert(bc.as_str(), i);
     }
 
+    let mut samples = BTreeSet::new();
+    let mut conditions = BTreeSet::new();
     let mut seen: HashSet<String> = HashSet::new();
     let mut reader = open_reader(path).map_err(|e| std::io::Error::other(e.to_string()))?;
 
@@ -438,6 +2375,7 @@ fn read_meta_columns(path: &Path, barcodes: &[String]) -> Result<MetaColumns, St
             sample,
             condition,
             species,
+            ..MetaColumns::default()
         });
     }
 
@@ -452,6 +2390,7 @@ fn read_meta_columns(path: &Path, barcodes: &[String]) -> Result<MetaColumns, St
             sample,
             condition,
             species,
+            ..MetaColumns::default()
         });
     };
 
@@ -473,6 +2412,11 @@ fn read_meta_columns(path: &Path, barcodes: &[String]) -> Result<MetaColumns, St
         if cell.is_empty() || !seen.insert(cell.to_string()) {
             continue;
         }
+        for (col, groups) in [(sample_idx, &mut samples), (cond_idx, &mut conditions)] {
+            if let Some(value) = col.and_then(|idx| parts.get(idx)).filter(|v| !v.is_empty()) {
+                groups.insert(value.to_string());
+            }
+        }
         let Some(&i) = index.get(cell) else {
             continue;
         };
@@ -489,7 +2433,7 @@ fn read_meta_columns(path: &Path, barcodes: &[String]) -> Result<MetaColumns, St
         }
         if let Some(idx) = species_idx {
             if idx < parts.len() && !parts[idx].is_empty() {
-                species[i] = normalize_species(parts[idx]);
+                species[i] = normalize_species(parts[idx]).to_string();
             }
         }
     }
@@ -498,37 +2442,37 @@ fn read_meta_columns(path: &Path, barcodes: &[String]) -> Result<MetaColumns, St
         sample,
         condition,
         species,
+        samples,
+        conditions,
     })
 }
 
-fn normalize_species(s: &str) -> String {
-    let x = s.trim().to_ascii_lowercase();
-    if x.contains("human") || x == "hs" || x == "homo_sapiens" {
-        "human".to_string()
-    } else if x.contains("mouse") || x == "mm" || x == "mus_musculus" {
-        "mouse".to_string()
-    } else {
-        "unknown".to_string()
-    }
-}
-
-fn build_summary(rows: &[CellOutput]) -> FinalSummary {
+fn build_summary(
+    rows: &[CellOutput],
+    axes: &AxesContext,
+    panels: &PanelsContext,
+    score_concentration: &[f32],
+    cov_min: f32,
+    frac_ge: FracGeSummary,
+    labels: &RegimeLabels,
+) -> FinalSummary {
     let species = rows
         .iter()
         .find(|r| r.species == "human" || r.species == "mouse")
-        .map(|r| r.species.clone())
-        .unwrap_or_else(|| "unknown".to_string());
+        .map_or("unknown", |r| r.species)
+        .to_string();
 
     let secretory: Vec<f32> = rows.iter().map(|r| r.secretory_load).collect();
     let er_golgi: Vec<f32> = rows.iter().map(|r| r.er_golgi_pressure).collect();
     let stress: Vec<f32> = rows.iter().map(|r| r.stress_secretion_index).collect();
+    let confidence: Vec<f32> = rows.iter().map(|r| r.confidence).collect();
 
     let mut counts: BTreeMap<String, usize> = BTreeMap::new();
-    for name in PIPELINE_REGIMES {
-        counts.insert(name.to_string(), 0);
+    for regime in labels.ordered() {
+        counts.insert(labels.label(regime).to_string(), 0);
     }
     for row in rows {
-        if let Some(c) = counts.get_mut(&row.regime) {
+        if let Some(c) = counts.get_mut(labels.label(row.regime)) {
             *c += 1;
         }
     }
@@ -541,6 +2485,27 @@ fn build_summary(rows: &[CellOutput]) -> FinalSummary {
 
     let low_conf_count = rows.iter().filter(|r| r.low_confidence).count() as f32;
     let low_sig_count = rows.iter().filter(|r| r.low_secretory_signal).count() as f32;
+    let high_prolif_count = rows.iter().filter(|r| r.high_proliferation).count() as f32;
+    let mut ambient_by_sample: BTreeMap<String, FlagCount> = frac_ge
+        .by_sample
+        .keys()
+        .map(|sample| (sample.clone(), FlagCount::default()))
+        .collect();
+    for row in rows {
+        let count = ambient_by_sample.entry(row.sample.to_string()).or_default();
+        count.n_cells += 1;
+        if row.classify_flags.contains(Flags::HIGH_AMBIENT_RISK) {
+            count.n_flagged += 1;
+        }
+    }
+    let ambient_count: usize = ambient_by_sample.values().map(|c| c.n_flagged).sum();
+    let libsizes: Vec<f32> = rows.iter().map(|r| r.libsize as f32).collect();
+    let libsize_tail = percentiles_interpolated(&libsizes, &[0.0, 0.01], NanPolicy::Skip);
+    let complexity: Vec<f32> = rows.iter().map(|r| r.genes_per_1k_umis).collect();
+    let extreme_count = rows
+        .iter()
+        .filter(|r| r.classify_flags.contains(Flags::EXTREME_NORMALIZATION))
+        .count();
 
     FinalSummary {
         tool: ToolSummary {
@@ -551,11 +2516,27 @@ fn build_summary(rows: &[CellOutput]) -> FinalSummary {
         input: InputSummary {
             n_cells: rows.len(),
             species,
+            meta_match_fraction: None,
+            meta_issues: None,
+            gene_set_hash: String::new(),
+            libsize_low_tail: LibsizeLowTail {
+                min: libsize_tail[0],
+                p1: libsize_tail[1],
+            },
+            matrix_values: MatrixValuesSummary::default(),
+            panel_genes: PanelGenesSummary::default(),
         },
         distributions: DistributionSummary {
             secretory_load: stats(&secretory),
             er_golgi_pressure: stats(&er_golgi),
             stress_secretion_index: stats(&stress),
+            score_concentration: stats(score_concentration),
+        },
+        histograms: HistogramSummary {
+            secretory_load: histogram01(&secretory, HISTOGRAM_BINS),
+            er_golgi_pressure: histogram01(&er_golgi, HISTOGRAM_BINS),
+            stress_secretion_index: histogram01(&stress, HISTOGRAM_BINS),
+            confidence: histogram01(&confidence, HISTOGRAM_BINS),
         },
         regimes: RegimeSummary {
             counts,
@@ -564,7 +2545,158 @@ fn build_summary(rows: &[CellOutput]) -> FinalSummary {
         qc: QcSummary {
             low_confidence_fraction: if n == 0.0 { 0.0 } else { low_conf_count / n },
             low_secretory_signal_fraction: if n == 0.0 { 0.0 } else { low_sig_count / n },
+            high_proliferation_fraction: if n == 0.0 { 0.0 } else { high_prolif_count / n },
+            high_ambient_risk_fraction: ge_fraction(ambient_count, rows.len()),
+            extreme_normalization_fraction: ge_fraction(extreme_count, rows.len()),
+            genes_per_1k_umis: stats(&complexity),
+            high_ambient_risk_by_sample: ambient_by_sample,
+            flag_combinations: count_combinations(
+                rows.iter().map(|r| combination_key(&triggered_flags(r))),
+                rows.len(),
+            ),
+        },
+        frac_ge,
+        caveats: build_caveats(axes, panels, cov_min),
+        provenance: ProvenanceSummary {
+            coverage_mode: axes.coverage_mode.as_str().to_string(),
+            mode: String::new(),
+            outputs: outputs::current().as_str().to_string(),
+            retain: Retention::All.as_str().to_string(),
+        },
+        run: None,
+        chemistry: None,
+        upstream: None,
+        reference: None,
+        qc_gate: None,
+        condition_comparison: None,
+    }
+}
+
+fn build_reference_summary(
+    reference: &CohortReference,
+    axes: &AxesContext,
+    scores: &ScoresContext,
+) -> ReferenceSummary {
+    let mut drift = Vec::with_capacity(REFERENCE_AXES.len() + REFERENCE_COMPOSITES.len());
+    for name in REFERENCE_AXES {
+        let current: Vec<f32> = axes.values.iter().map(|v| axis_value(v, name)).collect();
+        drift.push(metric_drift(name, reference.axis_grid(name), &current));
+    }
+    for (name, current) in [
+        ("OII", &scores.oii),
+        ("IAI", &scores.iai),
+        ("ESI", &scores.esi),
+    ] {
+        drift.push(metric_drift(name, reference.composite_grid(name), current));
+    }
+    ReferenceSummary {
+        panel_set_hash: reference.panel_set_hash.clone(),
+        n_cells: reference.n_cells,
+        tool_version: reference.tool_version.clone(),
+        drift,
+    }
+}
+
+fn metric_drift(name: &str, reference_grid: &[f32], current: &[f32]) -> MetricDrift {
+    let reference_median = grid_median(reference_grid);
+    let current_median = grid_median(&quantile_grid(current));
+    MetricDrift {
+        metric: name.to_string(),
+        reference_median,
+        current_median,
+        delta: current_median - reference_median,
+    }
+}
+
+fn build_caveats(axes: &AxesContext, panels: &PanelsContext, cov_min: f32) -> CaveatsSummary {
+    let s = &axes.stats;
+    let absent_axes = [
+        ("SIA", &s.sia),
+        ("EEB", &s.eeb),
+        ("SLI", &s.sli),
+        ("MEI", &s.mei),
+        ("ECMI", &s.ecmi),
+        ("APCI", &s.apci),
+        ("GDI", &s.gdi),
+    ]
+    .into_iter()
+    .filter(|(_, entry)| !entry.present)
+    .map(|(name, _)| name.to_string())
+    .collect();
+
+    let panels_missing_required: Vec<PanelWarningSummary> = panels
+        .warnings
+        .iter()
+        .filter(|w| !w.missing_required.is_empty())
+        .map(|w| PanelWarningSummary {
+            panel_id: w.panel_id.clone(),
+            axis: w.axis.clone(),
+            missing_required: w.missing_required.clone(),
+            mappable_fraction: w.mappable_fraction,
+        })
+        .collect();
+    let mut by_axis: BTreeMap<&str, (Vec<String>, BTreeSet<String>)> = BTreeMap::new();
+    for w in &panels_missing_required {
+        let (ids, genes) = by_axis.entry(w.axis.as_str()).or_default();
+        ids.push(w.panel_id.clone());
+        genes.extend(w.missing_required.iter().cloned());
+    }
+    let missing_required_by_axis = by_axis
+        .into_iter()
+        .map(|(axis, (panels, genes))| AxisMissingRequired {
+            axis: axis.to_string(),
+            panels,
+            missing_required: genes.into_iter().collect(),
+        })
+        .collect();
+    let worst_covered_axis = [
+        ("SIA", &s.sia),
+        ("EEB", &s.eeb),
+        ("SLI", &s.sli),
+        ("MEI", &s.mei),
+        ("ECMI", &s.ecmi),
+        ("APCI", &s.apci),
+        ("GDI", &s.gdi),
+    ]
+    .into_iter()
+    .filter(|(_, entry)| entry.present && !entry.coverage.median.is_nan())
+    .min_by(|a, b| a.1.coverage.median.total_cmp(&b.1.coverage.median))
+    .map(|(axis, entry)| WorstCoveredAxis {
+        axis: axis.to_string(),
+        coverage_median: entry.coverage.median,
+    });
+
+    let low_cov = axes
+        .coverage
+        .iter()
+        .zip(&axes.values)
+        .filter(|(cov, value)| {
+            cov.sia < cov_min
+                || cov.eeb < cov_min
+                || cov.sli < cov_min
+                || cov.mei < cov_min
+                || cov.ecmi < cov_min
+                || cov.gdi < cov_min
+                || (!value.apci.is_nan() && cov.apci < cov_min)
+        })
+        .count();
+    let n = axes.coverage.len();
+
+    CaveatsSummary {
+        absent_axes,
+        panels_missing_required,
+        missing_required_by_axis,
+        worst_covered_axis,
+        low_axis_coverage_fraction: if n == 0 {
+            0.0
+        } else {
+            low_cov as f32 / n as f32
         },
+        sanitized_fields: crate::report::tsv::sanitized_fields(),
+        axis_panels: axes.panel_counts,
+        skipped_panel_files: panels.panels.skipped.clone(),
+        dead_panels: panels.dead_panels.clone(),
+        dropped_dead_panels: axes.dropped_panels.clone(),
     }
 }
 
@@ -572,60 +2704,111 @@ fn simd_name() -> String {
     simd::backend_name().to_string()
 }
 
-fn stats(values: &[f32]) -> Quantiles {
-    let mut vals: Vec<f32> = values.iter().copied().filter(|v| v.is_finite()).collect();
-    vals.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
-    Quantiles {
-        median: percentile(&vals, 0.5),
-        p90: percentile(&vals, 0.9),
-        p99: percentile(&vals, 0.99),
+/// Counts values into `bins` equal-width bins over [0, 1]; out-of-range values
+/// are clamped into the edge bins and non-finite values are skipped.
+pub fn histogram01(values: &[f32], bins: usize) -> Vec<u32> {
+    if bins == 0 {
+        return Vec::new();
     }
+    let chunk_counts = |range: std::ops::Range<usize>| {
+        let mut counts = vec![0u32; bins];
+        for v in values[range].iter().copied().filter(|v| v.is_finite()) {
+            let idx = ((v.clamp(0.0, 1.0) * bins as f32) as usize).min(bins - 1);
+            counts[idx] += 1;
+        }
+        counts
+    };
+    fold_cell_chunks(values.len(), chunk_counts, |mut total, part| {
+        for (t, p) in total.iter_mut().zip(part) {
+            *t += p;
+        }
+        total
+    })
+    .unwrap_or_else(|| vec![0; bins])
 }
 
-fn percentile(values: &[f32], p: f32) -> f32 {
-    if values.is_empty() {
-        return 0.0;
+pub(crate) fn stats(values: &[f32]) -> Quantiles {
+    let q = percentiles_interpolated(values, &[0.5, 0.9, 0.99], NanPolicy::Skip);
+    Quantiles {
+        median: q[0],
+        p90: q[1],
+        p99: q[2],
     }
-    let idx = ((p * (values.len() as f32 - 1.0)).floor() as usize).min(values.len() - 1);
-    values[idx]
 }
 
-fn to_pipeline_regime(
+/// Pipeline regime of one cell under the contract version `labels` carries;
+/// regimes newer than that version are folded through `labels`.
+fn assign_pipeline_regime(
+    old: Regime,
+    secretory_load: f32,
+    stress: f32,
+    paracrine: f32,
+    labels: &RegimeLabels,
+) -> PipelineRegime {
+    labels.resolve(extended_pipeline_regime(
+        old,
+        secretory_load,
+        stress,
+        paracrine,
+    ))
+}
+
+/// Pipeline regime under the newest contract version.
+fn extended_pipeline_regime(
     old: Regime,
     secretory_load: f32,
     stress: f32,
     paracrine: f32,
-) -> &'static str {
+) -> PipelineRegime {
     if secretory_load < 0.20 {
-        return "SecretoryCollapse";
+        return PipelineRegime::SecretoryCollapse;
     }
     if secretory_load >= 0.80 && stress >= 0.75 {
-        return "HypersecretoryState";
+        return PipelineRegime::HypersecretoryState;
     }
     if stress >= 0.75 {
-        return "InflammatorySecretion";
+        return PipelineRegime::InflammatorySecretion;
     }
 
     match old {
-        Regime::SelfPreserving => "HomeostaticSecretion",
-        Regime::InflammatorySignaler => "InflammatorySecretion",
-        Regime::MetabolicSuppressive => "SecretoryCollapse",
+        Regime::SelfPreserving => PipelineRegime::HomeostaticSecretion,
+        Regime::InflammatorySignaler => PipelineRegime::InflammatorySecretion,
+        Regime::MetabolicSuppressive => PipelineRegime::SecretoryCollapse,
+        Regime::SecretoryLysosomeActive => PipelineRegime::LysosomalSecretion,
         Regime::Unclassified => {
             if paracrine >= 0.65 {
-                "AdaptiveSecretion"
+                PipelineRegime::AdaptiveSecretion
             } else {
-                "Unclassified"
+                PipelineRegime::Unclassified
             }
         }
-        _ => "AdaptiveSecretion",
+        _ => PipelineRegime::AdaptiveSecretion,
+    }
+}
+
+/// Six-decimal JSON number without clamping; `null` when not finite.
+fn fmt_json_f32(v: f32) -> String {
+    if v.is_finite() {
+        fixed6(v)
+    } else {
+        "null".to_string()
+    }
+}
+
+fn fmt_json_f64(v: f64) -> String {
+    if v.is_finite() {
+        format!("{:.6}", v)
+    } else {
+        "null".to_string()
     }
 }
 
-fn fmt6(v: f32) -> String {
+/// Scientific notation so p-values far below 1e-6 keep their magnitude.
+fn fmt_p_value(v: f64) -> String {
     if v.is_finite() {
-        format!("{:.6}", clamp01(v))
+        format!("{:.6e}", v)
     } else {
-        "0.000000".to_string()
+        "null".to_string()
     }
 }
 
@@ -0,0 +1,314 @@
+use std::collections::HashSet;
+use std::io::BufRead;
+use std::path::Path;
+
+use thiserror::Error;
+
+use crate::input::open_reader;
+use crate::pipeline::outputs::{OutputSet, Retention};
+use crate::report::artifact::write_artifact;
+
+pub const SUCCESS_MARKER: &str = "_SUCCESS";
+
+#[derive(Debug, Error)]
+pub enum VerifyError {
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+#[derive(Debug, Clone)]
+pub struct CheckResult {
+    pub name: &'static str,
+    pub passed: bool,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct VerifyReport {
+    pub checks: Vec<CheckResult>,
+}
+
+impl VerifyReport {
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|c| c.passed)
+    }
+
+    pub fn failed(&self) -> impl Iterator<Item = &CheckResult> {
+        self.checks.iter().filter(|c| !c.passed)
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        for check in &self.checks {
+            out.push_str(if check.passed { "PASS" } else { "FAIL" });
+            out.push('\t');
+            out.push_str(check.name);
+            out.push('\t');
+            out.push_str(&check.detail);
+            out.push('\n');
+        }
+        out
+    }
+
+    fn push(&mut self, name: &'static str, passed: bool, detail: String) {
+        self.checks.push(CheckResult {
+            name,
+            passed,
+            detail,
+        });
+    }
+}
+
+struct TsvTable {
+    header: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+impl TsvTable {
+    fn column(&self, name: &str) -> Option<usize> {
+        self.header.iter().position(|c| c == name)
+    }
+}
+
+/// Re-reads the artifacts written by a run and cross-checks them.
+///
+/// When `expected_barcodes` is `None` (standalone `verify`), the expected cell
+/// count is taken from `summary.json` and barcode membership is not checked.
+/// Checks on per-cell tables are skipped when `summary.json` records a
+/// `summary-only` run, and checks on `classify.tsv` when its `retain` policy
+/// removed it.
+pub fn run_verify(
+    out_dir: &Path,
+    expected_barcodes: Option<&[String]>,
+) -> Result<VerifyReport, VerifyError> {
+    let mut report = VerifyReport::default();
+
+    let secretion = read_tsv(&out_dir.join("secretion.tsv"))?;
+    let classify = read_tsv(&out_dir.join("classify.tsv"))?;
+    let summary = read_json(&out_dir.join("summary.json"))?;
+
+    let summary_n_cells = summary
+        .as_ref()
+        .and_then(|v| v["input"]["n_cells"].as_u64())
+        .map(|v| v as usize);
+    let n_cells = expected_barcodes.map(|b| b.len()).or(summary_n_cells);
+    let summary_only = summary.as_ref().is_some_and(|v| {
+        v["provenance"]["outputs"].as_str() == Some(OutputSet::SummaryOnly.as_str())
+    });
+    let classify_removed = classify.is_none()
+        && summary.as_ref().is_some_and(|v| {
+            v["provenance"]["retain"]
+                .as_str()
+                .is_some_and(|r| r != Retention::All.as_str())
+        });
+
+    let secretion_ids: Option<Vec<&str>> = secretion.as_ref().map(|t| {
+        let col = t.column("barcode").unwrap_or(0);
+        t.rows
+            .iter()
+            .map(|r| r.get(col).map(String::as_str).unwrap_or(""))
+            .collect()
+    });
+
+    match (&secretion_ids, n_cells) {
+        _ if summary_only => report.push(
+            "secretion_row_count",
+            true,
+            "skipped (summary-only run)".to_string(),
+        ),
+        (None, _) => report.push(
+            "secretion_row_count",
+            false,
+            "secretion.tsv missing".to_string(),
+        ),
+        (Some(_), None) => report.push(
+            "secretion_row_count",
+            false,
+            "expected cell count unknown (summary.json missing)".to_string(),
+        ),
+        (Some(ids), Some(n)) => report.push(
+            "secretion_row_count",
+            ids.len() == n,
+            format!("rows={} n_cells={}", ids.len(), n),
+        ),
+    }
+
+    if let Some(ids) = &secretion_ids {
+        let unique: HashSet<&str> = ids.iter().copied().collect();
+        report.push(
+            "secretion_barcodes_unique",
+            unique.len() == ids.len(),
+            format!("rows={} unique={}", ids.len(), unique.len()),
+        );
+
+        match expected_barcodes {
+            Some(barcodes) => {
+                let known: HashSet<&str> = barcodes.iter().map(String::as_str).collect();
+                let unknown = ids.iter().filter(|id| !known.contains(**id)).count();
+                report.push(
+                    "secretion_barcodes_known",
+                    unknown == 0,
+                    format!("unknown={}", unknown),
+                );
+            }
+            None => report.push(
+                "secretion_barcodes_known",
+                true,
+                "skipped (dataset barcodes unavailable)".to_string(),
+            ),
+        }
+    }
+
+    match (&summary, n_cells) {
+        (None, _) => report.push(
+            "summary_regime_counts",
+            false,
+            "summary.json missing or invalid".to_string(),
+        ),
+        (Some(v), n) => {
+            let total: u64 = v["regimes"]["counts"]
+                .as_object()
+                .map(|m| m.values().filter_map(|c| c.as_u64()).sum())
+                .unwrap_or(0);
+            let n = n.unwrap_or(0) as u64;
+            report.push(
+                "summary_regime_counts",
+                total == n,
+                format!("sum={} n_cells={}", total, n),
+            );
+        }
+    }
+
+    match (&classify, &secretion_ids) {
+        _ if summary_only => report.push(
+            "classify_secretion_agreement",
+            true,
+            "skipped (summary-only run)".to_string(),
+        ),
+        _ if classify_removed => report.push(
+            "classify_secretion_agreement",
+            true,
+            "skipped (classify.tsv not retained)".to_string(),
+        ),
+        (Some(classify), Some(ids)) => {
+            let col = classify.column("cell_id").unwrap_or(0);
+            let classify_ids: HashSet<&str> = classify
+                .rows
+                .iter()
+                .map(|r| r.get(col).map(String::as_str).unwrap_or(""))
+                .collect();
+            let secretion_set: HashSet<&str> = ids.iter().copied().collect();
+            let agree = classify.rows.len() == ids.len() && classify_ids == secretion_set;
+            report.push(
+                "classify_secretion_agreement",
+                agree,
+                format!(
+                    "classify_rows={} secretion_rows={}",
+                    classify.rows.len(),
+                    ids.len()
+                ),
+            );
+        }
+        _ => report.push(
+            "classify_secretion_agreement",
+            false,
+            "classify.tsv or secretion.tsv missing".to_string(),
+        ),
+    }
+
+    let pipeline_step = read_json(&out_dir.join("pipeline_step.json"))?;
+    match pipeline_step {
+        None => report.push(
+            "pipeline_step_artifacts",
+            true,
+            "skipped (no pipeline_step.json)".to_string(),
+        ),
+        Some(step) => {
+            let mut files: Vec<String> = step["artifacts"]
+                .as_object()
+                .map(|m| {
+                    m.values()
+                        .filter_map(|v| v.as_str().map(str::to_string))
+                        .collect()
+                })
+                .unwrap_or_default();
+            if let Some(f) = step["cell_metrics"]["file"].as_str() {
+                files.push(f.to_string());
+            }
+            files.sort();
+            files.dedup();
+            let bad: Vec<String> = files
+                .into_iter()
+                .filter(|f| {
+                    std::fs::metadata(out_dir.join(f))
+                        .map(|m| m.len() == 0)
+                        .unwrap_or(true)
+                })
+                .collect();
+            report.push(
+                "pipeline_step_artifacts",
+                bad.is_empty(),
+                if bad.is_empty() {
+                    "all present".to_string()
+                } else {
+                    format!("missing_or_empty={}", bad.join(","))
+                },
+            );
+        }
+    }
+
+    Ok(report)
+}
+
+/// Writes `_SUCCESS` when every check passed, removes it otherwise.
+pub fn apply_success_marker(out_dir: &Path, report: &VerifyReport) -> Result<(), VerifyError> {
+    if report.passed() {
+        write_artifact(out_dir, SUCCESS_MARKER, b"")?;
+    } else {
+        remove_success_marker(out_dir)?;
+    }
+    Ok(())
+}
+
+pub fn remove_success_marker(out_dir: &Path) -> Result<(), VerifyError> {
+    match std::fs::remove_file(out_dir.join(SUCCESS_MARKER)) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn read_tsv(path: &Path) -> Result<Option<TsvTable>, VerifyError> {
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let reader = open_reader(path).map_err(|e| std::io::Error::other(e.to_string()))?;
+    let mut header: Option<Vec<String>> = None;
+    let mut rows = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        let raw = line.trim_end_matches('\r');
+        if raw.is_empty() || raw.starts_with('#') {
+            continue;
+        }
+        let parts: Vec<String> = raw.split('\t').map(str::to_string).collect();
+        if header.is_none() {
+            header = Some(parts);
+        } else {
+            rows.push(parts);
+        }
+    }
+    Ok(header.map(|header| TsvTable { header, rows }))
+}
+
+fn read_json(path: &Path) -> Result<Option<serde_json::Value>, VerifyError> {
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let bytes = std::fs::read(path)?;
+    Ok(serde_json::from_slice(&bytes).ok())
+}
+
+#[cfg(test)]
+#[path = "../../tests/src_inline/pipeline/verify.rs"]
+mod tests;
@@ -0,0 +1,269 @@
+//! Atomic writes for output artifacts.
+//!
+//! An [`Artifact`] writes to `<name>.tmp` next to its destination and renames
+//! it into place in [`Artifact::commit`], after the data has been flushed.
+//! Dropping an uncommitted artifact (e.g. when a write error is
+//! propagated with `?`) removes the temp file, so a failed stage never leaves
+//! a truncated file under the final name. Every I/O error wraps a
+//! [`WriteError`] carrying the artifact path; stage error enums unwrap it with
+//! [`into_write_error`].
+//!
+//! [`fail_after`] installs a per-thread byte budget after which writes fail
+//! with `StorageFull`, so tests can simulate a full disk in any stage.
+//!
+//! [`enter_write_options`] sets the buffer size (default 1 MiB, so network
+//! filesystems see few large writes) and whether commits sync the file
+//! before the rename and the directory after it, per thread like the byte
+//! budget. The rename alone keeps readers from seeing a partial file; the
+//! syncs only matter for surviving a crash and cost a round trip per file on
+//! network filesystems.
+
+use std::cell::Cell;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+
+/// Default [`WriteOptions::buffer_size`].
+pub const DEFAULT_IO_BUFFER_SIZE: usize = 1 << 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WriteOptions {
+    /// Bytes buffered before a write reaches the file.
+    pub buffer_size: usize,
+    /// Sync each file before its rename and the directory after it, so the
+    /// new contents and name survive a crash.
+    pub durable: bool,
+}
+
+impl Default for WriteOptions {
+    fn default() -> Self {
+        Self {
+            buffer_size: DEFAULT_IO_BUFFER_SIZE,
+            durable: false,
+        }
+    }
+}
+
+thread_local! {
+    static BYTE_BUDGET: Cell<Option<u64>> = const { Cell::new(None) };
+    static WRITE_OPTIONS: Cell<WriteOptions> = const {
+        Cell::new(WriteOptions {
+            buffer_size: DEFAULT_IO_BUFFER_SIZE,
+            durable: false,
+        })
+    };
+}
+
+/// Sets the write options for artifacts created on this thread until the
+/// guard is dropped.
+pub fn enter_write_options(options: WriteOptions) -> WriteOptionsGuard {
+    WriteOptionsGuard {
+        previous: WRITE_OPTIONS.replace(options),
+    }
+}
+
+pub struct WriteOptionsGuard {
+    previous: WriteOptions,
+}
+
+impl Drop for WriteOptionsGuard {
+    fn drop(&mut self) {
+        WRITE_OPTIONS.set(self.previous);
+    }
+}
+
+pub fn write_options() -> WriteOptions {
+    WRITE_OPTIONS.get()
+}
+
+/// Makes artifact writes on this thread fail once `bytes` more bytes have
+/// been written, until the guard is dropped.
+pub fn fail_after(bytes: u64) -> ByteBudgetGuard {
+    ByteBudgetGuard {
+        previous: BYTE_BUDGET.replace(Some(bytes)),
+    }
+}
+
+pub struct ByteBudgetGuard {
+    previous: Option<u64>,
+}
+
+impl Drop for ByteBudgetGuard {
+    fn drop(&mut self) {
+        BYTE_BUDGET.set(self.previous);
+    }
+}
+
+/// The temp file, charged against the thread's byte budget if one is set.
+struct Sink {
+    file: File,
+}
+
+impl Write for Sink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = match BYTE_BUDGET.get() {
+            None => buf.len(),
+            Some(0) if !buf.is_empty() => {
+                return Err(io::Error::new(
+                    io::ErrorKind::StorageFull,
+                    "no space left on device",
+                ));
+            }
+            Some(left) => buf.len().min(left as usize),
+        };
+        let written = self.file.write(&buf[..len])?;
+        if let Some(left) = BYTE_BUDGET.get() {
+            BYTE_BUDGET.set(Some(left - written as u64));
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// I/O failure while writing the artifact at `path`.
+#[derive(Debug, Error)]
+#[error("writing {}: {source}", path.display())]
+pub struct WriteError {
+    pub path: PathBuf,
+    pub source: io::Error,
+}
+
+/// The [`WriteError`] inside an error returned by an [`Artifact`], or the
+/// error unchanged if it did not come from one.
+pub fn into_write_error(e: io::Error) -> Result<WriteError, io::Error> {
+    if !e.get_ref().is_some_and(|inner| inner.is::<WriteError>()) {
+        return Err(e);
+    }
+    let inner = e.into_inner().expect("checked above");
+    Ok(*inner.downcast::<WriteError>().expect("checked above"))
+}
+
+pub struct Artifact {
+    tmp: PathBuf,
+    dest: PathBuf,
+    writer: Option<BufWriter<Sink>>,
+    durable: bool,
+}
+
+impl Artifact {
+    /// Starts `dir/name`; nothing appears under that name until [`commit`](Self::commit).
+    pub fn create(dir: &Path, name: &str) -> io::Result<Self> {
+        Self::create_with_capacity(dir, name, write_options().buffer_size)
+    }
+
+    fn create_with_capacity(dir: &Path, name: &str, capacity: usize) -> io::Result<Self> {
+        let dest = dir.join(name);
+        let tmp = dir.join(format!("{name}.tmp"));
+        let file = File::create(&tmp).map_err(|e| with_path(&dest, e))?;
+        Ok(Self {
+            tmp,
+            dest,
+            writer: Some(BufWriter::with_capacity(capacity, Sink { file })),
+            durable: write_options().durable,
+        })
+    }
+
+    /// Flushes and renames the temp file into place, syncing under
+    /// [`WriteOptions::durable`].
+    pub fn commit(mut self) -> io::Result<()> {
+        let writer = self.writer.take().expect("artifact already committed");
+        let result = writer
+            .into_inner()
+            .map_err(io::IntoInnerError::into_error)
+            .and_then(|sink| {
+                if self.durable {
+                    sink.file.sync_all()
+                } else {
+                    Ok(())
+                }
+            })
+            .and_then(|()| std::fs::rename(&self.tmp, &self.dest))
+            .and_then(|()| {
+                if self.durable {
+                    sync_parent_dir(&self.dest)
+                } else {
+                    Ok(())
+                }
+            });
+        if let Err(e) = result {
+            let _ = std::fs::remove_file(&self.tmp);
+            return Err(with_path(&self.dest, e));
+        }
+        Ok(())
+    }
+}
+
+impl Write for Artifact {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let writer = self.writer.as_mut().expect("artifact already committed");
+        writer.write(buf).map_err(|e| with_path(&self.dest, e))
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        let writer = self.writer.as_mut().expect("artifact already committed");
+        writer.write_all(buf).map_err(|e| with_path(&self.dest, e))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let writer = self.writer.as_mut().expect("artifact already committed");
+        writer.flush().map_err(|e| with_path(&self.dest, e))
+    }
+}
+
+impl Drop for Artifact {
+    fn drop(&mut self) {
+        if let Some(writer) = self.writer.take() {
+            // Discard buffered bytes instead of flushing them into a file
+            // that is removed anyway.
+            drop(writer.into_parts());
+            let _ = std::fs::remove_file(&self.tmp);
+        }
+    }
+}
+
+/// Writes `dir/name` in one go through an [`Artifact`]; the buffer is sized
+/// to the contents, up to the configured buffer size.
+pub fn write_artifact(dir: &Path, name: &str, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let contents = contents.as_ref();
+    let capacity = contents.len().clamp(1, write_options().buffer_size.max(1));
+    let mut artifact = Artifact::create_with_capacity(dir, name, capacity)?;
+    artifact.write_all(contents)?;
+    artifact.commit()
+}
+
+/// Makes a rename in `path`'s directory durable. Directories cannot be
+/// opened for syncing on Windows; renames there are left to the filesystem.
+fn sync_parent_dir(path: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        let dir = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        File::open(dir)?.sync_all()
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        Ok(())
+    }
+}
+
+fn with_path(path: &Path, source: io::Error) -> io::Error {
+    io::Error::new(
+        source.kind(),
+        WriteError {
+            path: path.to_path_buf(),
+            source,
+        },
+    )
+}
+
+#[cfg(test)]
+#[path = "../../tests/src_inline/report/artifact.rs"]
+mod tests;
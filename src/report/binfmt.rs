@@ -0,0 +1,484 @@
+//! `secretion.bin`: the per-cell results of `secretion.tsv` as typed columns,
+//! for consumers that would otherwise parse the TSV back into numbers.
+//!
+//! Layout, all integers little-endian:
+//!
+//! | bytes | field |
+//! |-------|-------|
+//! | 8 | magic `KSECBIN\0` |
+//! | 2 | format version (`u16`) |
+//! | 8 | `n_cells` (`u64`) |
+//! | 4 | `n_columns` (`u32`) |
+//! | ... | column directory, one entry per column |
+//! | ... | column data, each column starting at a multiple of 8 |
+//! | 8 | CRC-64/ECMA-182 of every preceding byte |
+//!
+//! A directory entry is the name length (`u16`), the UTF-8 name, the
+//! [`ColumnKind`] byte, then the column's absolute offset and length in bytes
+//! (`u64` each). Columns appear in `secretion.tsv` order, so the header of
+//! the TSV is the column names joined by tabs. Column data:
+//!
+//! - `str`: the KORG string table of the shared cache (`u32` count,
+//!   `count + 1` `u32` end offsets starting at 0, then the bytes).
+//! - `f32`, `u32`, `u64`: `n_cells` values. `f32` columns hold the value as
+//!   written to the TSV, already clamped for the contract metrics.
+//! - `code`: `n_cells` `u8` codes, padding to 4 bytes, then a string table of
+//!   the labels the codes index.
+//! - `flags`: `n_cells` `u32` bitsets, then a string table naming bit 0, 1, ...
+//!   A cell without bits set is written as `.`.
+
+use std::io::Write;
+use std::path::Path;
+
+use crc::{CRC_64_ECMA_182, Crc};
+use thiserror::Error;
+
+use crate::input::cache::{CacheError, encode_string_table, parse_string_table};
+use crate::report::artifact::Artifact;
+use crate::report::format::{NanToken, signed_or_nan_with};
+
+const CRC64: Crc<u64> = Crc::<u64>::new(&CRC_64_ECMA_182);
+
+pub const BIN_MAGIC: [u8; 8] = *b"KSECBIN\0";
+/// Bumped whenever the layout or a column kind changes.
+pub const BIN_VERSION: u16 = 1;
+pub const SECRETION_BIN: &str = "secretion.bin";
+
+const HEADER_LEN: usize = BIN_MAGIC.len() + 2 + 8 + 4;
+const CRC_LEN: usize = 8;
+const COLUMN_ALIGN: usize = 8;
+
+#[derive(Debug, Error)]
+pub enum BinError {
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("not a secretion.bin file (bad magic)")]
+    InvalidMagic,
+    #[error("secretion.bin format version {found}, this build reads version {expected}")]
+    VersionMismatch { found: u16, expected: u16 },
+    #[error("secretion.bin checksum mismatch: stored {stored:016x}, computed {computed:016x}")]
+    Checksum { stored: u64, computed: u64 },
+    #[error("invalid secretion.bin: {0}")]
+    InvalidFormat(String),
+}
+
+impl From<CacheError> for BinError {
+    fn from(e: CacheError) -> Self {
+        match e {
+            CacheError::InvalidFormat(msg) => BinError::InvalidFormat(msg),
+            other => BinError::InvalidFormat(other.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ColumnKind {
+    Str = 0,
+    F32 = 1,
+    U32 = 2,
+    U64 = 3,
+    Code = 4,
+    Flags = 5,
+}
+
+impl ColumnKind {
+    fn from_byte(byte: u8) -> Option<Self> {
+        Some(match byte {
+            0 => ColumnKind::Str,
+            1 => ColumnKind::F32,
+            2 => ColumnKind::U32,
+            3 => ColumnKind::U64,
+            4 => ColumnKind::Code,
+            5 => ColumnKind::Flags,
+            _ => return None,
+        })
+    }
+}
+
+/// Values of one column, `n_cells` long.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColumnData {
+    Str(Vec<String>),
+    F32(Vec<f32>),
+    U32(Vec<u32>),
+    U64(Vec<u64>),
+    /// Indices into `labels`.
+    Code {
+        codes: Vec<u8>,
+        labels: Vec<String>,
+    },
+    /// Bit `i` of a value stands for `names[i]`.
+    Flags {
+        bits: Vec<u32>,
+        names: Vec<String>,
+    },
+}
+
+impl ColumnData {
+    pub fn kind(&self) -> ColumnKind {
+        match self {
+            ColumnData::Str(_) => ColumnKind::Str,
+            ColumnData::F32(_) => ColumnKind::F32,
+            ColumnData::U32(_) => ColumnKind::U32,
+            ColumnData::U64(_) => ColumnKind::U64,
+            ColumnData::Code { .. } => ColumnKind::Code,
+            ColumnData::Flags { .. } => ColumnKind::Flags,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            ColumnData::Str(values) => values.len(),
+            ColumnData::F32(values) => values.len(),
+            ColumnData::U32(values) => values.len(),
+            ColumnData::U64(values) => values.len(),
+            ColumnData::Code { codes, .. } => codes.len(),
+            ColumnData::Flags { bits, .. } => bits.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Cell `i` as written to `secretion.tsv`.
+    fn tsv_field(&self, i: usize, nan: NanToken, out: &mut String) {
+        match self {
+            ColumnData::Str(values) => out.push_str(&values[i]),
+            ColumnData::F32(values) => out.push_str(&signed_or_nan_with(values[i], nan)),
+            ColumnData::U32(values) => out.push_str(&values[i].to_string()),
+            ColumnData::U64(values) => out.push_str(&values[i].to_string()),
+            ColumnData::Code { codes, labels } => out.push_str(&labels[codes[i] as usize]),
+            ColumnData::Flags { bits, names } => {
+                let start = out.len();
+                for (bit, name) in names.iter().enumerate() {
+                    if bits[i] & (1 << bit) != 0 {
+                        if out.len() > start {
+                            out.push(',');
+                        }
+                        out.push_str(name);
+                    }
+                }
+                if out.len() == start {
+                    out.push('.');
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BinColumn {
+    pub name: String,
+    pub data: ColumnData,
+}
+
+/// The contents of a `secretion.bin` file.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct BinTable {
+    pub n_cells: usize,
+    pub columns: Vec<BinColumn>,
+}
+
+impl BinTable {
+    pub fn new(n_cells: usize) -> Self {
+        Self {
+            n_cells,
+            columns: Vec::new(),
+        }
+    }
+
+    /// Appends a column; its length must be `n_cells`.
+    pub fn push(&mut self, name: impl Into<String>, data: ColumnData) {
+        debug_assert_eq!(data.len(), self.n_cells);
+        self.columns.push(BinColumn {
+            name: name.into(),
+            data,
+        });
+    }
+
+    pub fn column(&self, name: &str) -> Option<&ColumnData> {
+        self.columns
+            .iter()
+            .find(|c| c.name == name)
+            .map(|c| &c.data)
+    }
+
+    /// Writes the table as `secretion.tsv`: header, then one line per cell.
+    pub fn write_tsv<W: Write>(&self, mut writer: W, nan: NanToken) -> std::io::Result<()> {
+        let header: Vec<&str> = self.columns.iter().map(|c| c.name.as_str()).collect();
+        writeln!(writer, "{}", header.join("\t"))?;
+        let mut line = String::new();
+        for i in 0..self.n_cells {
+            line.clear();
+            for (j, column) in self.columns.iter().enumerate() {
+                if j > 0 {
+                    line.push('\t');
+                }
+                column.data.tsv_field(i, nan, &mut line);
+            }
+            line.push('\n');
+            writer.write_all(line.as_bytes())?;
+        }
+        Ok(())
+    }
+
+    pub fn to_tsv(&self, nan: NanToken) -> String {
+        let mut out = Vec::new();
+        self.write_tsv(&mut out, nan)
+            .expect("writing to a Vec cannot fail");
+        String::from_utf8(out).expect("columns hold UTF-8 text")
+    }
+}
+
+pub fn encode_bin(table: &BinTable) -> Result<Vec<u8>, BinError> {
+    let payloads = table
+        .columns
+        .iter()
+        .map(|column| {
+            if column.data.len() != table.n_cells {
+                return Err(BinError::InvalidFormat(format!(
+                    "column {} has {} values for {} cells",
+                    column.name,
+                    column.data.len(),
+                    table.n_cells
+                )));
+            }
+            encode_column(&column.name, &column.data)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let directory_len: usize = table
+        .columns
+        .iter()
+        .map(|c| 2 + c.name.len() + 1 + 8 + 8)
+        .sum();
+    let n_columns = u32::try_from(table.columns.len())
+        .map_err(|_| BinError::InvalidFormat("too many columns".to_string()))?;
+    let mut bytes = Vec::with_capacity(HEADER_LEN + directory_len);
+    bytes.extend_from_slice(&BIN_MAGIC);
+    bytes.extend_from_slice(&BIN_VERSION.to_le_bytes());
+    bytes.extend_from_slice(&(table.n_cells as u64).to_le_bytes());
+    bytes.extend_from_slice(&n_columns.to_le_bytes());
+
+    let mut offset = align(HEADER_LEN + directory_len);
+    for (column, payload) in table.columns.iter().zip(&payloads) {
+        let name_len = u16::try_from(column.name.len()).map_err(|_| {
+            BinError::InvalidFormat(format!("column name too long: {}", column.name))
+        })?;
+        bytes.extend_from_slice(&name_len.to_le_bytes());
+        bytes.extend_from_slice(column.name.as_bytes());
+        bytes.push(column.data.kind() as u8);
+        bytes.extend_from_slice(&(offset as u64).to_le_bytes());
+        bytes.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+        offset = align(offset + payload.len());
+    }
+    for payload in &payloads {
+        bytes.resize(align(bytes.len()), 0);
+        bytes.extend_from_slice(payload);
+    }
+    let crc = CRC64.checksum(&bytes);
+    bytes.extend_from_slice(&crc.to_le_bytes());
+    Ok(bytes)
+}
+
+/// Checks magic, version and checksum, then decodes every column.
+pub fn decode_bin(bytes: &[u8]) -> Result<BinTable, BinError> {
+    if bytes.len() < HEADER_LEN + CRC_LEN || bytes[..BIN_MAGIC.len()] != BIN_MAGIC {
+        return Err(BinError::InvalidMagic);
+    }
+    let mut cursor = Cursor {
+        bytes,
+        at: BIN_MAGIC.len(),
+    };
+    let found = u16::from_le_bytes(cursor.take()?);
+    if found != BIN_VERSION {
+        return Err(BinError::VersionMismatch {
+            found,
+            expected: BIN_VERSION,
+        });
+    }
+    let (body, stored) = bytes.split_at(bytes.len() - CRC_LEN);
+    let stored = u64::from_le_bytes(stored.try_into().expect("8 bytes"));
+    let computed = CRC64.checksum(body);
+    if stored != computed {
+        return Err(BinError::Checksum { stored, computed });
+    }
+
+    let n_cells = usize::try_from(u64::from_le_bytes(cursor.take()?))
+        .map_err(|_| BinError::InvalidFormat("n_cells overflows usize".to_string()))?;
+    let n_columns = u32::from_le_bytes(cursor.take()?) as usize;
+    let mut table = BinTable::new(n_cells);
+    for _ in 0..n_columns {
+        let name_len = u16::from_le_bytes(cursor.take()?) as usize;
+        let name = std::str::from_utf8(cursor.slice(name_len)?)
+            .map_err(|_| BinError::InvalidFormat("column name is not UTF-8".to_string()))?
+            .to_string();
+        let [kind] = cursor.take()?;
+        let kind = ColumnKind::from_byte(kind).ok_or_else(|| {
+            BinError::InvalidFormat(format!("column {name} has unknown kind {kind}"))
+        })?;
+        let offset = u64::from_le_bytes(cursor.take()?);
+        let len = u64::from_le_bytes(cursor.take()?);
+        let payload = usize::try_from(offset)
+            .ok()
+            .zip(usize::try_from(len).ok())
+            .and_then(|(offset, len)| body.get(offset..offset.checked_add(len)?))
+            .ok_or_else(|| {
+                BinError::InvalidFormat(format!("column {name} lies outside the file"))
+            })?;
+        let data = decode_column(&name, kind, payload, n_cells)?;
+        table.columns.push(BinColumn { name, data });
+    }
+    Ok(table)
+}
+
+/// Writes `table` to `dir/name` atomically (see [`Artifact`]).
+pub fn write_bin(dir: &Path, name: &str, table: &BinTable) -> Result<(), BinError> {
+    let bytes = encode_bin(table)?;
+    let mut artifact = Artifact::create(dir, name)?;
+    artifact.write_all(&bytes)?;
+    artifact.commit()?;
+    Ok(())
+}
+
+pub fn read_bin(path: &Path) -> Result<BinTable, BinError> {
+    decode_bin(&std::fs::read(path)?)
+}
+
+fn align(len: usize) -> usize {
+    len.next_multiple_of(COLUMN_ALIGN)
+}
+
+fn to_le_column<T: Copy, const N: usize>(values: &[T], to_le: fn(T) -> [u8; N]) -> Vec<u8> {
+    values.iter().flat_map(|&v| to_le(v)).collect()
+}
+
+fn from_le_column<T, const N: usize>(bytes: &[u8], from_le: fn([u8; N]) -> T) -> Vec<T> {
+    bytes
+        .chunks_exact(N)
+        .map(|b| from_le(b.try_into().expect("chunk of length N")))
+        .collect()
+}
+
+fn encode_column(name: &str, data: &ColumnData) -> Result<Vec<u8>, BinError> {
+    Ok(match data {
+        ColumnData::Str(values) => encode_string_table(values, name)?,
+        ColumnData::F32(values) => to_le_column(values, f32::to_le_bytes),
+        ColumnData::U32(values) => to_le_column(values, u32::to_le_bytes),
+        ColumnData::U64(values) => to_le_column(values, u64::to_le_bytes),
+        ColumnData::Code { codes, labels } => {
+            if let Some(&code) = codes.iter().find(|&&c| c as usize >= labels.len()) {
+                return Err(BinError::InvalidFormat(format!(
+                    "column {name}: code {code} without a label"
+                )));
+            }
+            let mut out = codes.clone();
+            out.resize(codes.len().next_multiple_of(4), 0);
+            out.extend(encode_string_table(labels, name)?);
+            out
+        }
+        ColumnData::Flags { bits, names } => {
+            if names.len() > 32 {
+                return Err(BinError::InvalidFormat(format!(
+                    "column {name}: {} flag names for 32 bits",
+                    names.len()
+                )));
+            }
+            let mut out = to_le_column(bits, u32::to_le_bytes);
+            out.extend(encode_string_table(names, name)?);
+            out
+        }
+    })
+}
+
+fn decode_column(
+    name: &str,
+    kind: ColumnKind,
+    payload: &[u8],
+    n_cells: usize,
+) -> Result<ColumnData, BinError> {
+    let fixed = |size: usize| -> Result<&[u8], BinError> {
+        n_cells
+            .checked_mul(size)
+            .and_then(|len| payload.get(..len))
+            .ok_or_else(|| BinError::InvalidFormat(format!("column {name} is truncated")))
+    };
+    let exact = |size: usize| -> Result<&[u8], BinError> {
+        let values = fixed(size)?;
+        if values.len() != payload.len() {
+            return Err(BinError::InvalidFormat(format!(
+                "column {name} has {} bytes for {n_cells} cells",
+                payload.len()
+            )));
+        }
+        Ok(values)
+    };
+    let table = |at: usize| -> Result<Vec<String>, BinError> {
+        let rest = &payload[at..];
+        let count = rest
+            .get(..4)
+            .map(|b| u32::from_le_bytes(b.try_into().expect("4 bytes")) as usize)
+            .ok_or_else(|| BinError::InvalidFormat(format!("column {name} is truncated")))?;
+        Ok(parse_string_table(payload, at, rest.len(), count, name)?)
+    };
+    Ok(match kind {
+        ColumnKind::Str => {
+            let values = parse_string_table(payload, 0, payload.len(), n_cells, name)?;
+            ColumnData::Str(values)
+        }
+        ColumnKind::F32 => ColumnData::F32(from_le_column(exact(4)?, f32::from_le_bytes)),
+        ColumnKind::U32 => ColumnData::U32(from_le_column(exact(4)?, u32::from_le_bytes)),
+        ColumnKind::U64 => ColumnData::U64(from_le_column(exact(8)?, u64::from_le_bytes)),
+        ColumnKind::Code => {
+            let codes = fixed(1)?.to_vec();
+            let labels = table(n_cells.next_multiple_of(4).min(payload.len()))?;
+            if let Some(&code) = codes.iter().find(|&&c| c as usize >= labels.len()) {
+                return Err(BinError::InvalidFormat(format!(
+                    "column {name}: code {code} without a label"
+                )));
+            }
+            ColumnData::Code { codes, labels }
+        }
+        ColumnKind::Flags => {
+            let bits = from_le_column(fixed(4)?, u32::from_le_bytes);
+            let names = table(n_cells * 4)?;
+            if names.len() > 32 {
+                return Err(BinError::InvalidFormat(format!(
+                    "column {name}: {} flag names for 32 bits",
+                    names.len()
+                )));
+            }
+            ColumnData::Flags { bits, names }
+        }
+    })
+}
+
+/// Reads the fixed-size header and directory fields in order.
+struct Cursor<'a> {
+    bytes: &'a [u8],
+    at: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn slice(&mut self, len: usize) -> Result<&'a [u8], BinError> {
+        let end = self
+            .at
+            .checked_add(len)
+            .filter(|&end| end <= self.bytes.len());
+        let end = end
+            .ok_or_else(|| BinError::InvalidFormat("column directory is truncated".to_string()))?;
+        let out = &self.bytes[self.at..end];
+        self.at = end;
+        Ok(out)
+    }
+
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], BinError> {
+        Ok(self.slice(N)?.try_into().expect("slice of length N"))
+    }
+}
+
+#[cfg(test)]
+#[path = "../../tests/src_inline/report/binfmt.rs"]
+mod tests;
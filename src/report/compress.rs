@@ -0,0 +1,157 @@
+//! Compressed artifact streams: plain gzip or BGZF.
+//!
+//! BGZF (the blocked gzip of SAM/BAM and tabix) is a series of gzip members,
+//! each holding at most [`BGZF_BLOCK_DATA_LEN`] input bytes and recording its
+//! own size in a `BC` extra subfield, followed by an empty [`BGZF_EOF`]
+//! member. Any multi-member gzip reader decompresses it; block-aware tools can
+//! seek by block. Blocks are cut at fixed input offsets only (never on
+//! `flush`), and headers carry no timestamp, so equal input gives equal bytes.
+
+use std::io::{self, Write};
+
+use flate2::write::{DeflateEncoder, GzEncoder};
+use flate2::{Compression, Crc};
+
+/// Input bytes per BGZF block; the htslib default, which keeps even a stored
+/// (incompressible) block under the 64 KiB block limit.
+pub const BGZF_BLOCK_DATA_LEN: usize = 0xff00;
+
+/// Largest BGZF block, header and trailer included.
+const BGZF_MAX_BLOCK_LEN: usize = 1 << 16;
+
+/// Gzip header with the `BC` subfield, minus the trailing `BSIZE`.
+const BGZF_HEADER: [u8; 16] = [
+    0x1f, 0x8b, 8, 4, 0, 0, 0, 0, 0, 0xff, 6, 0, b'B', b'C', 2, 0,
+];
+
+/// Header, `BSIZE`, CRC32 and ISIZE around the deflate data.
+const BGZF_OVERHEAD: usize = BGZF_HEADER.len() + 2 + 8;
+
+/// The empty block that ends every BGZF file.
+pub const BGZF_EOF: [u8; 28] = [
+    0x1f, 0x8b, 8, 4, 0, 0, 0, 0, 0, 0xff, 6, 0, b'B', b'C', 2, 0, 0x1b, 0, 3, 0, 0, 0, 0, 0, 0, 0,
+    0, 0,
+];
+
+/// Container for gzip-compressed artifacts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CompressFormat {
+    /// One gzip member.
+    #[default]
+    Gzip,
+    /// Block-compressed gzip, see the module docs.
+    Bgzf,
+}
+
+/// BGZF encoder over `W`; [`BgzfWriter::finish`] writes the last block and
+/// the EOF marker. Dropping the writer unfinished loses buffered data.
+pub struct BgzfWriter<W: Write> {
+    inner: W,
+    pending: Vec<u8>,
+    level: Compression,
+}
+
+impl<W: Write> BgzfWriter<W> {
+    pub fn new(inner: W, level: Compression) -> Self {
+        Self {
+            inner,
+            pending: Vec::with_capacity(BGZF_BLOCK_DATA_LEN),
+            level,
+        }
+    }
+
+    /// Writes the buffered block and the EOF marker, then returns `W`.
+    pub fn finish(mut self) -> io::Result<W> {
+        if !self.pending.is_empty() {
+            self.write_block()?;
+        }
+        self.inner.write_all(&BGZF_EOF)?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+
+    fn write_block(&mut self) -> io::Result<()> {
+        let mut deflate = DeflateEncoder::new(Vec::new(), self.level);
+        deflate.write_all(&self.pending)?;
+        let data = deflate.finish()?;
+        let block_len = data.len() + BGZF_OVERHEAD;
+        if block_len > BGZF_MAX_BLOCK_LEN {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("BGZF block of {block_len} bytes exceeds {BGZF_MAX_BLOCK_LEN}"),
+            ));
+        }
+        let mut crc = Crc::new();
+        crc.update(&self.pending);
+
+        self.inner.write_all(&BGZF_HEADER)?;
+        self.inner
+            .write_all(&((block_len - 1) as u16).to_le_bytes())?;
+        self.inner.write_all(&data)?;
+        self.inner.write_all(&crc.sum().to_le_bytes())?;
+        self.inner
+            .write_all(&(self.pending.len() as u32).to_le_bytes())?;
+        self.pending.clear();
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for BgzfWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = buf.len().min(BGZF_BLOCK_DATA_LEN - self.pending.len());
+        self.pending.extend_from_slice(&buf[..n]);
+        if self.pending.len() == BGZF_BLOCK_DATA_LEN {
+            self.write_block()?;
+        }
+        Ok(n)
+    }
+
+    /// Flushes completed blocks only; the partial block stays buffered so
+    /// block boundaries do not depend on when callers flush.
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Gzip or BGZF encoder, picked by [`CompressFormat`].
+pub enum CompressedWriter<W: Write> {
+    Gzip(GzEncoder<W>),
+    Bgzf(BgzfWriter<W>),
+}
+
+impl<W: Write> CompressedWriter<W> {
+    /// Gzip headers carry a zero timestamp, so reruns are byte-identical.
+    pub fn new(inner: W, format: CompressFormat) -> Self {
+        match format {
+            CompressFormat::Gzip => Self::Gzip(GzEncoder::new(inner, Compression::default())),
+            CompressFormat::Bgzf => Self::Bgzf(BgzfWriter::new(inner, Compression::default())),
+        }
+    }
+
+    pub fn finish(self) -> io::Result<W> {
+        match self {
+            Self::Gzip(writer) => writer.finish(),
+            Self::Bgzf(writer) => writer.finish(),
+        }
+    }
+}
+
+impl<W: Write> Write for CompressedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Gzip(writer) => writer.write(buf),
+            Self::Bgzf(writer) => writer.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Gzip(writer) => writer.flush(),
+            Self::Bgzf(writer) => writer.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "../../tests/src_inline/report/compress.rs"]
+mod tests;
@@ -0,0 +1,102 @@
+//! File names derived from user data (barcodes, sample ids) that are safe on
+//! every platform: characters outside `[A-Za-z0-9._-]` are percent-encoded,
+//! Windows device names and leading or trailing dots are escaped, and long
+//! names are cut with a hash of the full id so they stay unique. Writers list
+//! the names they derive in a [`NAME_INDEX`] next to the files.
+
+use std::fmt::Write as _;
+
+use crc::{CRC_64_ECMA_182, Crc};
+
+use crate::report::tsv::{UnsafeField, field};
+
+const CRC64: Crc<u64> = Crc::<u64>::new(&CRC_64_ECMA_182);
+
+/// Longest file name produced, extension included; well under `NAME_MAX`
+/// (255) and leaves room for the output directory within Windows `MAX_PATH`.
+pub const MAX_FILE_NAME_BYTES: usize = 120;
+
+/// Reverse-lookup manifest: `file` and the original id, one row per file.
+pub const NAME_INDEX: &str = "_index.tsv";
+
+/// Marks a truncated name (and stands for an empty id); never produced by the
+/// encoding itself (`~` is percent-encoded), so a cut name cannot equal an
+/// uncut one.
+const HASH_MARK: char = '~';
+
+const WINDOWS_DEVICE_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// `id` as a file name with `extension` (without the dot). Distinct ids give
+/// distinct names; ids that only use `[A-Za-z0-9._-]` and are short enough
+/// keep their spelling.
+pub fn safe_file_name(id: &str, extension: &str) -> String {
+    let mut stem = String::with_capacity(id.len());
+    let last = id.len().saturating_sub(1);
+    for (i, byte) in id.bytes().enumerate() {
+        let edge_dot = byte == b'.' && (i == 0 || i == last);
+        if byte.is_ascii_alphanumeric()
+            || matches!(byte, b'-' | b'_')
+            || (byte == b'.' && !edge_dot)
+        {
+            stem.push(byte as char);
+        } else {
+            let _ = write!(stem, "%{byte:02X}");
+        }
+    }
+    if stem.is_empty() {
+        stem.push(HASH_MARK);
+    }
+    let device = stem.split('.').next().unwrap_or_default();
+    if WINDOWS_DEVICE_NAMES
+        .iter()
+        .any(|name| name.eq_ignore_ascii_case(device))
+    {
+        let first = stem.as_bytes()[0];
+        stem.replace_range(..1, &format!("%{first:02X}"));
+    }
+
+    let suffix_len = if extension.is_empty() {
+        0
+    } else {
+        extension.len() + 1
+    };
+    let budget = MAX_FILE_NAME_BYTES.saturating_sub(suffix_len);
+    if stem.len() > budget {
+        let hash = format!("{HASH_MARK}{:016x}", CRC64.checksum(id.as_bytes()));
+        let mut cut = budget.saturating_sub(hash.len());
+        // Keep `%XX` escapes whole.
+        if let Some(percent) = stem[cut.saturating_sub(2)..cut].find('%') {
+            cut = cut - 2 + percent;
+        }
+        stem.truncate(cut);
+        stem.push_str(&hash);
+    }
+    if !extension.is_empty() {
+        stem.push('.');
+        stem.push_str(extension);
+    }
+    stem
+}
+
+/// [`NAME_INDEX`] contents for `(file, id)` pairs; `id_column` names the
+/// second column (`barcode`, `sample`, ...).
+pub fn name_index_tsv<'a>(
+    id_column: &'static str,
+    entries: impl IntoIterator<Item = (&'a str, &'a str)>,
+) -> Result<String, UnsafeField> {
+    let mut out = format!("file\t{id_column}\n");
+    for (file, id) in entries {
+        out.push_str(file);
+        out.push('\t');
+        out.push_str(&field(id_column, id)?);
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+#[path = "../../tests/src_inline/report/file_name.rs"]
+mod tests;
@@ -0,0 +1,99 @@
+//! Float formatting shared by every TSV writer.
+//!
+//! Two policies:
+//! - [`signed_or_nan`]: intermediate per-cell files (`panels_report.tsv` from stage3,
+//!   `axes.tsv`, `composites.tsv`, reference percentiles). Keeps the sign (EEB is
+//!   in [-1, 1]) and writes non-finite values as the configured [`NanToken`].
+//!   The token is per thread, installed with [`enter_nan_token`] like the TSV
+//!   field policy.
+//! - [`clamped01`]: contract metrics in `secretion.tsv` and the stage7 aggregates.
+//!   Clamps to [0, 1] and writes non-finite values as `0.000000`, so the contract
+//!   columns never contain a missing-value token.
+//!
+//! Both use six decimals and never emit `-0.000000`.
+
+use std::cell::Cell;
+
+/// Token written for NaN under the [`signed_or_nan`] policy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NanToken {
+    /// `nan`
+    #[default]
+    Nan,
+    /// Empty field
+    Empty,
+    /// `NA`, as read natively by R
+    Na,
+}
+
+impl NanToken {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            NanToken::Nan => "nan",
+            NanToken::Empty => "",
+            NanToken::Na => "NA",
+        }
+    }
+}
+
+thread_local! {
+    static NAN_TOKEN: Cell<NanToken> = const { Cell::new(NanToken::Nan) };
+}
+
+/// Sets the NaN token used by [`signed_or_nan`] on this thread until the guard
+/// is dropped.
+pub fn enter_nan_token(token: NanToken) -> NanTokenGuard {
+    NanTokenGuard {
+        previous: NAN_TOKEN.replace(token),
+    }
+}
+
+pub struct NanTokenGuard {
+    previous: NanToken,
+}
+
+impl Drop for NanTokenGuard {
+    fn drop(&mut self) {
+        NAN_TOKEN.set(self.previous);
+    }
+}
+
+pub fn nan_token() -> NanToken {
+    NAN_TOKEN.get()
+}
+
+/// Six decimals for a finite value, with negative zero normalized.
+pub fn fixed6(value: f32) -> String {
+    let text = format!("{:.6}", value);
+    if text == "-0.000000" {
+        "0.000000".to_string()
+    } else {
+        text
+    }
+}
+
+/// Signed six-decimal value; non-finite values become the configured token.
+pub fn signed_or_nan(value: f32) -> String {
+    signed_or_nan_with(value, nan_token())
+}
+
+pub fn signed_or_nan_with(value: f32, token: NanToken) -> String {
+    if value.is_finite() {
+        fixed6(value)
+    } else {
+        token.as_str().to_string()
+    }
+}
+
+/// Six-decimal value clamped to [0, 1]; non-finite values become `0.000000`.
+pub fn clamped01(value: f32) -> String {
+    if value.is_finite() {
+        fixed6(value.clamp(0.0, 1.0))
+    } else {
+        "0.000000".to_string()
+    }
+}
+
+#[cfg(test)]
+#[path = "../../tests/src_inline/report/format.rs"]
+mod tests;
@@ -0,0 +1,63 @@
+//! Self-contained `report.html` without scripts or external assets.
+
+use crate::panels::defs::PanelSet;
+use crate::pipeline::stage7_report::FinalSummary;
+use crate::report::render::report_sections;
+use crate::report::text::{DISCLAIMER, panel_gene_warning, report_title};
+
+pub fn render_html(summary: &FinalSummary, panels: &PanelSet) -> String {
+    let mut out = String::new();
+    out.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    let title = escape(&report_title(summary));
+    out.push_str(&format!("<title>{title}</title>\n</head>\n<body>\n"));
+    out.push_str(&format!("<h1>{title}</h1>\n"));
+    if let Some(description) = summary.run.as_ref().and_then(|r| r.description.as_ref()) {
+        out.push_str(&format!("<p>{}</p>\n", escape(description)));
+    }
+    out.push_str(&format!("<p>{}</p>\n", escape(DISCLAIMER)));
+    if let Some(warning) = panel_gene_warning(summary) {
+        out.push_str(&format!("<p><strong>{}</strong></p>\n", escape(&warning)));
+    }
+    for section in report_sections(summary, panels) {
+        out.push_str(&format!(
+            "<h2>{}</h2>\n<table>\n<thead>\n<tr>",
+            escape(section.title)
+        ));
+        for cell in &section.header {
+            out.push_str(&format!("<th>{}</th>", escape(cell)));
+        }
+        out.push_str("</tr>\n</thead>\n<tbody>\n");
+        for row in &section.rows {
+            out.push_str("<tr>");
+            for cell in row {
+                out.push_str(&format!("<td>{}</td>", escape(cell)));
+            }
+            out.push_str("</tr>\n");
+        }
+        out.push_str("</tbody>\n</table>\n");
+        if !section.notes.is_empty() {
+            out.push_str("<ul>\n");
+            for note in &section.notes {
+                out.push_str(&format!("<li>{}</li>\n", escape(note)));
+            }
+            out.push_str("</ul>\n");
+        }
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+pub fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
@@ -1,10 +1,38 @@
+use serde::Serializer;
+use serde_json::value::RawValue;
+
 use crate::pipeline::stage7_report::FinalSummary;
 
 pub type Summary = FinalSummary;
 
 pub fn write_summary(out_dir: &std::path::Path, summary: &Summary) -> anyhow::Result<()> {
     let json = serde_json::to_string_pretty(summary)?;
-    let path = out_dir.join("summary.json");
-    std::fs::write(path, json)?;
+    crate::report::artifact::write_artifact(out_dir, "summary.json", json)?;
     Ok(())
 }
+
+/// `serialize_with` helper writing an `f32` as a JSON number with exactly six
+/// decimals, matching the TSV outputs; non-finite values become `null`.
+/// Binary formats get the plain `f32`, so checkpoints round-trip exactly.
+pub fn fixed6<S: Serializer>(value: &f32, serializer: S) -> Result<S::Ok, S::Error> {
+    if !serializer.is_human_readable() {
+        return serializer.serialize_f32(*value);
+    }
+    if !value.is_finite() {
+        return serializer.serialize_none();
+    }
+    let raw = RawValue::from_string(crate::report::format::fixed6(*value))
+        .map_err(serde::ser::Error::custom)?;
+    serializer.serialize_some(&raw)
+}
+
+/// [`fixed6`] for optional values; `None` becomes `null`.
+pub fn fixed6_opt<S: Serializer>(value: &Option<f32>, serializer: S) -> Result<S::Ok, S::Error> {
+    if !serializer.is_human_readable() {
+        return serde::Serialize::serialize(value, serializer);
+    }
+    match value {
+        Some(v) => fixed6(v, serializer),
+        None => serializer.serialize_none(),
+    }
+}
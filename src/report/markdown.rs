@@ -0,0 +1,61 @@
+//! GitHub-flavored Markdown `report.md`.
+
+use crate::panels::defs::PanelSet;
+use crate::pipeline::stage7_report::FinalSummary;
+use crate::report::render::report_sections;
+use crate::report::text::{DISCLAIMER, panel_gene_warning, report_title};
+
+pub fn render_markdown(summary: &FinalSummary, panels: &PanelSet) -> String {
+    let mut out = String::new();
+    out.push_str(&format!("# {}\n\n", report_title(summary)));
+    if let Some(description) = summary.run.as_ref().and_then(|r| r.description.as_ref()) {
+        out.push_str(description);
+        out.push_str("\n\n");
+    }
+    out.push_str(DISCLAIMER);
+    out.push_str("\n\n");
+    if let Some(warning) = panel_gene_warning(summary) {
+        out.push_str(&format!("> **{}**\n\n", escape_cell(&warning)));
+    }
+    for section in report_sections(summary, panels) {
+        out.push_str(&format!("## {}\n\n", section.title));
+        push_row(&mut out, section.header.iter().copied());
+        push_row(&mut out, section.header.iter().map(|_| "---"));
+        for row in &section.rows {
+            push_row(&mut out, row.iter().map(String::as_str));
+        }
+        out.push('\n');
+        for note in &section.notes {
+            out.push_str(&format!("- {}\n", escape_cell(note)));
+        }
+        if !section.notes.is_empty() {
+            out.push('\n');
+        }
+    }
+    out
+}
+
+fn push_row<'a>(out: &mut String, cells: impl Iterator<Item = &'a str>) {
+    out.push('|');
+    for cell in cells {
+        out.push(' ');
+        out.push_str(&escape_cell(cell));
+        out.push_str(" |");
+    }
+    out.push('\n');
+}
+
+/// Escapes pipes and backslashes and folds line breaks, so free text such as
+/// panel descriptions cannot split a table row.
+pub fn escape_cell(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '|' => out.push_str("\\|"),
+            '\\' => out.push_str("\\\\"),
+            '\n' | '\r' | '\t' => out.push(' '),
+            _ => out.push(c),
+        }
+    }
+    out
+}
@@ -1,2 +1,11 @@
+pub mod artifact;
+pub mod binfmt;
+pub mod compress;
+pub mod file_name;
+pub mod format;
+pub mod html;
 pub mod json;
+pub mod markdown;
+pub mod render;
 pub mod text;
+pub mod tsv;
@@ -0,0 +1,282 @@
+//! Report formats selected with `--report-format`.
+//!
+//! `report.txt` keeps its own free-form layout ([`render_report`]). Markdown
+//! and HTML render the same [`ReportSection`] tables built from the
+//! [`FinalSummary`], so both carry identical numbers.
+
+use crate::panels::defs::PanelSet;
+use crate::pipeline::stage7_report::FinalSummary;
+use crate::report::text::{complexity_note, render_report, top_regimes};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ReportFormat {
+    Txt,
+    Md,
+    Html,
+}
+
+impl ReportFormat {
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            ReportFormat::Txt => "report.txt",
+            ReportFormat::Md => "report.md",
+            ReportFormat::Html => "report.html",
+        }
+    }
+}
+
+/// Renders `summary` in `format`; `ascii_only` applies to txt sparklines.
+pub fn render(
+    format: ReportFormat,
+    summary: &FinalSummary,
+    panels: &PanelSet,
+    ascii_only: bool,
+) -> String {
+    match format {
+        ReportFormat::Txt => render_report(summary, panels, ascii_only),
+        ReportFormat::Md => crate::report::markdown::render_markdown(summary, panels),
+        ReportFormat::Html => crate::report::html::render_html(summary, panels),
+    }
+}
+
+/// One titled block of a tabular report.
+#[derive(Debug, Clone)]
+pub struct ReportSection {
+    pub title: &'static str,
+    pub header: Vec<&'static str>,
+    pub rows: Vec<Vec<String>>,
+    /// Bullet points after the table.
+    pub notes: Vec<String>,
+}
+
+fn pct(fraction: f32) -> String {
+    format!("{:.2}%", fraction * 100.0)
+}
+
+/// Dataset overview, regime fractions, distribution quantiles, QC flags, flag
+/// combinations (when any cell is flagged), the QC gate (with `--qc-expectations`), panel coverage and the panel list, in
+/// that order.
+pub fn report_sections(summary: &FinalSummary, panels: &PanelSet) -> Vec<ReportSection> {
+    let overview = ReportSection {
+        title: "Dataset overview",
+        header: vec!["Field", "Value"],
+        rows: vec![
+            vec!["Cells".to_string(), summary.input.n_cells.to_string()],
+            vec!["Species".to_string(), summary.input.species.clone()],
+            vec![
+                "Meta match fraction".to_string(),
+                summary
+                    .input
+                    .meta_match_fraction
+                    .map_or_else(|| ".".to_string(), pct),
+            ],
+            {
+                let genes = summary.input.panel_genes.universe.overall;
+                vec![
+                    "Panel genes mapped".to_string(),
+                    format!(
+                        "{}/{} ({})",
+                        genes.mapped,
+                        genes.total,
+                        pct(genes.mapped_fraction())
+                    ),
+                ]
+            },
+        ],
+        notes: Vec::new(),
+    };
+
+    let regimes = ReportSection {
+        title: "Regime fractions",
+        header: vec!["Regime", "Cells", "Fraction"],
+        rows: top_regimes(&summary.regimes.fractions, usize::MAX)
+            .into_iter()
+            .map(|(name, frac)| {
+                let count = summary.regimes.counts.get(&name).copied().unwrap_or(0);
+                vec![name, count.to_string(), pct(frac)]
+            })
+            .collect(),
+        notes: Vec::new(),
+    };
+
+    let d = &summary.distributions;
+    let quantiles = ReportSection {
+        title: "Distribution quantiles",
+        header: vec!["Metric", "Median", "p90", "p99"],
+        rows: [
+            ("secretory_load", &d.secretory_load),
+            ("er_golgi_pressure", &d.er_golgi_pressure),
+            ("stress_secretion_index", &d.stress_secretion_index),
+        ]
+        .into_iter()
+        .map(|(name, q)| {
+            vec![
+                name.to_string(),
+                format!("{:.4}", q.median),
+                format!("{:.4}", q.p90),
+                format!("{:.4}", q.p99),
+            ]
+        })
+        .collect(),
+        notes: Vec::new(),
+    };
+
+    let qc = ReportSection {
+        title: "QC flags",
+        header: vec!["Flag", "Fraction of cells"],
+        rows: vec![
+            vec![
+                "LOW_CONFIDENCE".to_string(),
+                pct(summary.qc.low_confidence_fraction),
+            ],
+            vec![
+                "LOW_SECRETORY_SIGNAL".to_string(),
+                pct(summary.qc.low_secretory_signal_fraction),
+            ],
+            vec![
+                "HIGH_PROLIFERATION".to_string(),
+                pct(summary.qc.high_proliferation_fraction),
+            ],
+            vec![
+                "HIGH_AMBIENT_RISK".to_string(),
+                pct(summary.qc.high_ambient_risk_fraction),
+            ],
+            vec![
+                "EXTREME_NORMALIZATION".to_string(),
+                pct(summary.qc.extreme_normalization_fraction),
+            ],
+        ],
+        notes: {
+            let mut notes = vec![complexity_note(summary)];
+            let by_sample = summary.qc.ambient_risk_by_sample();
+            if !by_sample.is_empty() {
+                let parts: Vec<String> = by_sample
+                    .iter()
+                    .map(|(sample, fraction)| format!("{} {}", sample, pct(*fraction)))
+                    .collect();
+                notes.push(format!("HIGH_AMBIENT_RISK by sample: {}", parts.join(", ")));
+            }
+            notes
+        },
+    };
+
+    let combinations = (!summary.qc.flag_combinations.is_empty()).then(|| ReportSection {
+        title: "Flag combinations",
+        header: vec!["Flags", "Cells", "Fraction", "Reading"],
+        rows: summary
+            .qc
+            .flag_combinations
+            .iter()
+            .map(|c| {
+                vec![
+                    c.flags.clone(),
+                    c.n_cells.to_string(),
+                    pct(c.fraction),
+                    c.interpretation().unwrap_or(".").to_string(),
+                ]
+            })
+            .collect(),
+        notes: Vec::new(),
+    });
+
+    let qc_gate = summary.qc_gate.as_ref().map(|gate| ReportSection {
+        title: "QC gate",
+        header: vec!["Rule", "Value", "Min", "Max", "Result"],
+        rows: gate
+            .rules
+            .iter()
+            .map(|r| {
+                let bound = |b: Option<f32>| b.map_or_else(|| ".".to_string(), pct);
+                vec![
+                    format!("{}: {}", r.kind, r.label),
+                    pct(r.value),
+                    bound(r.min),
+                    bound(r.max),
+                    if r.pass { "pass" } else { "FAIL" }.to_string(),
+                ]
+            })
+            .collect(),
+        notes: vec![format!(
+            "Verdict: {} ({} of {} rules failed)",
+            gate.verdict, gate.n_failed, gate.n_rules
+        )],
+    });
+
+    let caveats = &summary.caveats;
+    let mut notes = Vec::new();
+    if !caveats.absent_axes.is_empty() {
+        notes.push(format!(
+            "Axes without panels (not scored): {}",
+            caveats.absent_axes.join(", ")
+        ));
+    }
+    for w in &caveats.panels_missing_required {
+        notes.push(format!(
+            "{} is missing required genes: {}",
+            w.panel_id,
+            w.missing_required.join(", ")
+        ));
+    }
+    notes.extend(caveats.worst_covered_note());
+    for p in &caveats.dead_panels {
+        let dropped = caveats.dropped_dead_panels.contains(&p.panel_id);
+        notes.push(format!(
+            "{} [{}] is nonzero in {} of cells{}",
+            p.panel_id,
+            p.axis,
+            pct(p.nonzero_fraction),
+            if dropped { ", excluded from axes" } else { "" }
+        ));
+    }
+    notes.push(format!(
+        "Cells with any axis coverage below threshold: {}",
+        pct(caveats.low_axis_coverage_fraction)
+    ));
+    let coverage = ReportSection {
+        title: "Panel coverage",
+        header: vec!["Axis", "Panels", "Mapped panels", "Mappable genes"],
+        rows: caveats
+            .axis_panels
+            .entries()
+            .into_iter()
+            .map(|(axis, c)| {
+                vec![
+                    axis.to_string(),
+                    c.panels.to_string(),
+                    c.mapped_panels.to_string(),
+                    c.mappable_genes.to_string(),
+                ]
+            })
+            .collect(),
+        notes,
+    };
+
+    let panel_list = ReportSection {
+        title: "Panels",
+        header: vec!["Panel", "Axis", "Group", "Genes", "Description"],
+        rows: panels
+            .panels
+            .iter()
+            .map(|p| {
+                vec![
+                    p.id.clone(),
+                    p.axis.clone(),
+                    p.group_name().to_string(),
+                    p.genes.len().to_string(),
+                    p.description.clone(),
+                ]
+            })
+            .collect(),
+        notes: Vec::new(),
+    };
+
+    let mut sections = vec![overview, regimes, quantiles, qc];
+    sections.extend(combinations);
+    sections.extend(qc_gate);
+    sections.extend([coverage, panel_list]);
+    sections
+}
+
+#[cfg(test)]
+#[path = "../../tests/src_inline/report/render.rs"]
+mod tests;
@@ -1,15 +1,82 @@
+use crate::panels::defs::{PanelDef, PanelSet};
 use crate::pipeline::stage7_report::FinalSummary;
 
-pub fn render_report(summary: &FinalSummary) -> String {
+const SPARK_UNICODE: [char; 9] = [' ', '▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+const SPARK_ASCII: [char; 9] = [' ', '.', ':', '-', '=', '+', '*', '#', '@'];
+
+/// Opening caveat of every report format.
+pub const DISCLAIMER: &str = "This report summarizes transcript-derived proxy signals. \
+    It does not measure proteins, does not establish causality, and should be interpreted conservatively.";
+
+/// Median library complexity, with the chemistry when known, to hold
+/// against what that chemistry usually reaches.
+pub fn complexity_note(summary: &FinalSummary) -> String {
+    let median = summary.qc.genes_per_1k_umis.median;
+    let median = if median.is_finite() {
+        format!("{median:.1}")
+    } else {
+        ".".to_string()
+    };
+    match &summary.chemistry {
+        Some(chemistry) => format!(
+            "Median genes per 1k UMIs: {median} (chemistry {})",
+            chemistry.name
+        ),
+        None => format!("Median genes per 1k UMIs: {median}"),
+    }
+}
+
+/// Headline warning when most panel genes are absent from the matrix
+/// (`--min-panel-gene-fraction`).
+pub fn panel_gene_warning(summary: &FinalSummary) -> Option<String> {
+    let panel_genes = &summary.input.panel_genes;
+    let overall = panel_genes.universe.overall;
+    panel_genes.low_coverage.then(|| {
+        format!(
+            "WARNING: only {} of {} panel genes ({:.2}%) are in this dataset, below the {:.2}% minimum. \
+             Axis scores rest on few genes; see panels_report.tsv before interpreting them.",
+            overall.mapped,
+            overall.total,
+            overall.mapped_fraction() * 100.0,
+            panel_genes.min_mapped_fraction * 100.0
+        )
+    })
+}
+
+/// Report heading, followed by the run's `--label` when it has one.
+pub fn report_title(summary: &FinalSummary) -> String {
+    match &summary.run {
+        Some(run) => format!("Kira Secretion Report: {}", run.label),
+        None => "Kira Secretion Report".to_string(),
+    }
+}
+
+pub fn render_report(summary: &FinalSummary, panels: &PanelSet, ascii_only: bool) -> String {
     let mut out = String::new();
-    out.push_str("Kira Secretion Report\n");
-    out.push_str("======================\n\n");
-    out.push_str("This report summarizes transcript-derived proxy signals. ");
-    out.push_str("It does not measure proteins, does not establish causality, and should be interpreted conservatively.\n\n");
+    let title = report_title(summary);
+    out.push_str(&title);
+    out.push('\n');
+    out.push_str(&"=".repeat(title.len() + 1));
+    out.push_str("\n\n");
+    if let Some(description) = summary.run.as_ref().and_then(|r| r.description.as_ref()) {
+        out.push_str(description);
+        out.push_str("\n\n");
+    }
+    out.push_str(DISCLAIMER);
+    out.push_str("\n\n");
+    if let Some(warning) = panel_gene_warning(summary) {
+        out.push_str(&warning);
+        out.push_str("\n\n");
+    }
 
     out.push_str("Dataset overview:\n");
     out.push_str(&format!("- Cells: {}\n", summary.input.n_cells));
-    out.push_str(&format!("- Species: {}\n\n", summary.input.species));
+    out.push_str(&format!("- Species: {}\n", summary.input.species));
+    let panel_genes = summary.input.panel_genes.universe.overall;
+    out.push_str(&format!(
+        "- Panel genes mapped: {}/{}\n\n",
+        panel_genes.mapped, panel_genes.total
+    ));
 
     out.push_str("Dominant regimes:\n");
     let top = top_regimes(&summary.regimes.fractions, 2);
@@ -33,6 +100,22 @@ pub fn render_report(summary: &FinalSummary) -> String {
     ));
     out.push_str("\n");
 
+    out.push_str("Distribution sparklines ([0,1], left to right):\n");
+    let h = &summary.histograms;
+    for (name, counts) in [
+        ("secretory_load", &h.secretory_load),
+        ("er_golgi_pressure", &h.er_golgi_pressure),
+        ("stress_secretion_index", &h.stress_secretion_index),
+        ("confidence", &h.confidence),
+    ] {
+        out.push_str(&format!(
+            "- {:<22} |{}|\n",
+            name,
+            sparkline(counts, ascii_only)
+        ));
+    }
+    out.push('\n');
+
     out.push_str("Confidence and QC flags:\n");
     out.push_str(&format!(
         "- LOW_CONFIDENCE: {:.2}%\n",
@@ -42,12 +125,200 @@ pub fn render_report(summary: &FinalSummary) -> String {
         "- LOW_SECRETORY_SIGNAL: {:.2}%\n",
         summary.qc.low_secretory_signal_fraction * 100.0
     ));
+    out.push_str(&format!(
+        "- HIGH_PROLIFERATION: {:.2}%\n",
+        summary.qc.high_proliferation_fraction * 100.0
+    ));
+    out.push_str(&format!(
+        "- HIGH_AMBIENT_RISK: {:.2}%\n",
+        summary.qc.high_ambient_risk_fraction * 100.0
+    ));
+    let by_sample = summary.qc.ambient_risk_by_sample();
+    if !by_sample.is_empty() {
+        let parts: Vec<String> = by_sample
+            .iter()
+            .map(|(sample, fraction)| format!("{} {:.2}%", sample, fraction * 100.0))
+            .collect();
+        out.push_str(&format!("  by sample: {}\n", parts.join(", ")));
+    }
+    out.push_str(&format!(
+        "- EXTREME_NORMALIZATION: {:.2}%\n",
+        summary.qc.extreme_normalization_fraction * 100.0
+    ));
+    out.push_str(&format!("{}\n", complexity_note(summary)));
     out.push_str("\n");
 
+    if !summary.qc.flag_combinations.is_empty() {
+        out.push_str("Flag combinations:\n");
+        for combo in &summary.qc.flag_combinations {
+            out.push_str(&format!(
+                "- {}: {} cells ({:.2}%)",
+                combo.flags,
+                combo.n_cells,
+                combo.fraction * 100.0
+            ));
+            if let Some(reading) = combo.interpretation() {
+                out.push_str(&format!(" - {reading}"));
+            }
+            out.push('\n');
+        }
+        out.push('\n');
+    }
+
+    if let Some(gate) = &summary.qc_gate {
+        out.push_str(&format!(
+            "QC gate: {} ({} of {} rules failed)\n",
+            gate.verdict.to_ascii_uppercase(),
+            gate.n_failed,
+            gate.n_rules
+        ));
+        for rule in gate.failed_rules() {
+            out.push_str(&format!("- out of range: {}\n", rule.describe()));
+        }
+        out.push('\n');
+    }
+
+    out.push_str("Cells at or above thresholds:\n");
+    let frac_ge = &summary.frac_ge;
+    // Meta without a condition column leaves every cell in ".".
+    let conditions: Vec<_> = frac_ge
+        .by_condition
+        .iter()
+        .filter(|(name, _)| name.as_str() != ".")
+        .collect();
+    for (m, (metric, cuts)) in frac_ge.thresholds.iter().enumerate() {
+        for (t, cut) in cuts.iter().enumerate() {
+            out.push_str(&format!(
+                "- {:<26} >= {:<4}: {:>6.2}%",
+                metric,
+                cut,
+                frac_ge.global.fraction(m, t) * 100.0
+            ));
+            if !conditions.is_empty() {
+                let parts: Vec<String> = conditions
+                    .iter()
+                    .map(|(name, g)| {
+                        format!(
+                            "{} {:.2}% (n={})",
+                            name,
+                            g.fraction(m, t) * 100.0,
+                            g.n_cells
+                        )
+                    })
+                    .collect();
+                out.push_str(&format!("  [{}]", parts.join(", ")));
+            }
+            out.push('\n');
+        }
+    }
+    out.push('\n');
+
+    out.push_str("Data caveats:\n");
+    let caveats = &summary.caveats;
+    if caveats.absent_axes.is_empty() {
+        out.push_str("- All axes have contributing panels\n");
+    } else {
+        out.push_str(&format!(
+            "- Axes without panels (not scored): {}\n",
+            caveats.absent_axes.join(", ")
+        ));
+        if caveats.absent_axes.iter().any(|a| a == "APCI") {
+            out.push_str(
+                "  APCI is absent, so IAI is computed without antigen-presentation input.\n",
+            );
+        }
+    }
+    if caveats.panels_missing_required.is_empty() {
+        out.push_str("- No panels with missing required genes\n");
+    } else {
+        out.push_str(&format!(
+            "- Panels with missing required genes: {}\n",
+            caveats.panels_missing_required.len()
+        ));
+        for w in &caveats.panels_missing_required {
+            out.push_str(&format!(
+                "  - {}: {}\n",
+                w.panel_id,
+                w.missing_required.join(", ")
+            ));
+        }
+    }
+    if let Some(note) = caveats.worst_covered_note() {
+        out.push_str(&format!("- {note}\n"));
+    }
+    if !caveats.dead_panels.is_empty() {
+        out.push_str(&format!(
+            "- Panels zero in at least 99% of cells: {}\n",
+            caveats.dead_panels.len()
+        ));
+        for p in &caveats.dead_panels {
+            let dropped = caveats.dropped_dead_panels.contains(&p.panel_id);
+            out.push_str(&format!(
+                "  - {} [{}]: nonzero in {:.2}% of cells{}\n",
+                p.panel_id,
+                p.axis,
+                p.nonzero_fraction * 100.0,
+                if dropped { ", excluded from axes" } else { "" }
+            ));
+        }
+    }
+    out.push_str(&format!(
+        "- Cells with any axis coverage below threshold: {:.2}%\n",
+        caveats.low_axis_coverage_fraction * 100.0
+    ));
+    out.push('\n');
+
+    out.push_str("Panels by group:\n");
+    for (group, members) in panel_groups(panels) {
+        out.push_str(&format!("- {}:\n", group));
+        for panel in members {
+            out.push_str(&format!(
+                "  - {} [{}] ({} genes)\n",
+                panel.id,
+                panel.axis,
+                panel.genes.len()
+            ));
+        }
+    }
+    out.push('\n');
+
     out
 }
 
-fn top_regimes(regimes: &std::collections::BTreeMap<String, f32>, k: usize) -> Vec<(String, f32)> {
+/// Panels grouped by `group_name()`, groups sorted by name, panels in load order.
+pub fn panel_groups(panels: &PanelSet) -> Vec<(&str, Vec<&PanelDef>)> {
+    let mut groups: std::collections::BTreeMap<&str, Vec<&PanelDef>> =
+        std::collections::BTreeMap::new();
+    for panel in &panels.panels {
+        groups.entry(panel.group_name()).or_default().push(panel);
+    }
+    groups.into_iter().collect()
+}
+
+/// One character per bin, scaled to the largest bin; empty bins render as spaces.
+pub fn sparkline(counts: &[u32], ascii_only: bool) -> String {
+    let levels = if ascii_only {
+        &SPARK_ASCII
+    } else {
+        &SPARK_UNICODE
+    };
+    let max = counts.iter().copied().max().unwrap_or(0) as u64;
+    counts
+        .iter()
+        .map(|&c| {
+            if max == 0 {
+                levels[0]
+            } else {
+                levels[((c as u64 * 8).div_ceil(max)) as usize]
+            }
+        })
+        .collect()
+}
+
+pub(crate) fn top_regimes(
+    regimes: &std::collections::BTreeMap<String, f32>,
+    k: usize,
+) -> Vec<(String, f32)> {
     let mut pairs: Vec<(String, f32)> = regimes.iter().map(|(r, f)| (r.clone(), *f)).collect();
     pairs.sort_by(
         |a, b| match b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal) {
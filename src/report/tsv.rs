@@ -0,0 +1,96 @@
+//! Guard for free-text fields interpolated into TSV rows.
+//!
+//! Barcodes, meta values and panel strings come from user files. An embedded
+//! tab, newline or carriage return would shift every following column or row,
+//! so writers pass such fields through [`field`]. Under [`FieldPolicy::Strict`]
+//! (the default) that is an error; under [`FieldPolicy::Lenient`] the
+//! characters become spaces and the field is counted.
+//!
+//! The policy and the counter are per thread, installed with [`enter`] like
+//! the cancellation flag.
+
+use std::borrow::Cow;
+use std::cell::Cell;
+
+use thiserror::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FieldPolicy {
+    /// Fail on tab/newline/carriage return.
+    #[default]
+    Strict,
+    /// Replace them with spaces and count the field.
+    Lenient,
+}
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+#[error(
+    "{column} value {value:?} contains a tab, newline or carriage return; rerun with --lenient to replace them with spaces"
+)]
+pub struct UnsafeField {
+    pub column: &'static str,
+    pub value: String,
+}
+
+thread_local! {
+    static POLICY: Cell<FieldPolicy> = const { Cell::new(FieldPolicy::Strict) };
+    static SANITIZED: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Sets the policy for this thread and zeroes the sanitized-field count until
+/// the guard is dropped.
+pub fn enter(policy: FieldPolicy) -> FieldPolicyGuard {
+    let previous = (POLICY.replace(policy), SANITIZED.replace(0));
+    FieldPolicyGuard { previous }
+}
+
+pub struct FieldPolicyGuard {
+    previous: (FieldPolicy, usize),
+}
+
+impl Drop for FieldPolicyGuard {
+    fn drop(&mut self) {
+        POLICY.set(self.previous.0);
+        SANITIZED.set(self.previous.1);
+    }
+}
+
+/// Fields rewritten under the lenient policy since [`enter`].
+pub fn sanitized_fields() -> usize {
+    SANITIZED.get()
+}
+
+fn is_separator(c: char) -> bool {
+    matches!(c, '\t' | '\n' | '\r')
+}
+
+/// `value` with tab/newline/carriage return replaced by spaces, as written
+/// under the lenient policy. Does not count.
+pub fn replace_separators(value: &str) -> Cow<'_, str> {
+    if value.contains(is_separator) {
+        Cow::Owned(value.replace(is_separator, " "))
+    } else {
+        Cow::Borrowed(value)
+    }
+}
+
+/// `value` as safe to place in a TSV cell of `column`.
+pub fn field<'a>(column: &'static str, value: &'a str) -> Result<Cow<'a, str>, UnsafeField> {
+    if !value.contains(is_separator) {
+        return Ok(Cow::Borrowed(value));
+    }
+    match POLICY.get() {
+        FieldPolicy::Strict => Err(UnsafeField {
+            column,
+            value: value.to_string(),
+        }),
+        FieldPolicy::Lenient => {
+            SANITIZED.set(SANITIZED.get() + 1);
+            Ok(replace_separators(value))
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "../../tests/src_inline/report/tsv.rs"]
+mod tests;
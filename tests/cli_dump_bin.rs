@@ -0,0 +1,85 @@
+use std::path::Path;
+use std::process::{Command, Output};
+
+const MATRIX: &str =
+    "%%MatrixMarket matrix coordinate integer general\n2 3 3\n1 1 4\n2 2 1\n1 3 2\n";
+
+fn kira(args: &[&std::ffi::OsStr]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_kira-secretion"))
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .args(args)
+        .output()
+        .expect("spawn")
+}
+
+fn write_input(dir: &Path) {
+    std::fs::create_dir_all(dir).expect("mkdir");
+    std::fs::write(dir.join("features.tsv"), "f1\tSEC23A\nf2\tSAR1A\n").expect("write");
+    std::fs::write(dir.join("barcodes.tsv"), "c1\nc2\nc3\n").expect("write");
+    std::fs::write(dir.join("matrix.mtx"), MATRIX).expect("write");
+}
+
+#[test]
+fn dump_bin_reproduces_secretion_tsv() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("in");
+    write_input(&input);
+    let out = dir.path().join("out");
+
+    let run = kira(&[
+        "run".as_ref(),
+        "--allow-missing-axes".as_ref(),
+        "--format".as_ref(),
+        "bin".as_ref(),
+        "--nan-token".as_ref(),
+        "na".as_ref(),
+        "--input".as_ref(),
+        input.as_os_str(),
+        "--out".as_ref(),
+        out.as_os_str(),
+    ]);
+    assert!(
+        run.status.success(),
+        "{}",
+        String::from_utf8_lossy(&run.stderr)
+    );
+    let bin = out.join("secretion.bin");
+    let tsv = std::fs::read_to_string(out.join("secretion.tsv")).expect("secretion.tsv");
+
+    let copy = dir.path().join("copy.tsv");
+    let dumped = kira(&[
+        "dump-bin".as_ref(),
+        bin.as_os_str(),
+        "--nan-token".as_ref(),
+        "na".as_ref(),
+        "--out".as_ref(),
+        copy.as_os_str(),
+    ]);
+    assert!(
+        dumped.status.success(),
+        "{}",
+        String::from_utf8_lossy(&dumped.stderr)
+    );
+    assert_eq!(std::fs::read_to_string(copy).expect("copy"), tsv);
+}
+
+#[test]
+fn dump_bin_rejects_other_files() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let not_bin = dir.path().join("secretion.bin");
+    std::fs::write(&not_bin, "barcode\n").expect("write");
+    let copy = dir.path().join("copy.tsv");
+    let dumped = kira(&[
+        "dump-bin".as_ref(),
+        not_bin.as_os_str(),
+        "--out".as_ref(),
+        copy.as_os_str(),
+    ]);
+    assert!(!dumped.status.success());
+    assert!(
+        String::from_utf8_lossy(&dumped.stderr).contains("not a secretion.bin file"),
+        "{}",
+        String::from_utf8_lossy(&dumped.stderr)
+    );
+    assert!(!copy.exists());
+}
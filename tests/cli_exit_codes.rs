@@ -0,0 +1,511 @@
+use std::path::Path;
+use std::process::Command;
+
+fn bin() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_kira-secretion"))
+}
+
+fn write_input(dir: &Path, matrix: &str) {
+    std::fs::create_dir_all(dir).expect("mkdir");
+    std::fs::write(dir.join("features.tsv"), "f1\tSEC23A\nf2\tSAR1A\n").expect("write");
+    std::fs::write(dir.join("barcodes.tsv"), "c1\nc2\nc3\n").expect("write");
+    std::fs::write(dir.join("matrix.mtx"), matrix).expect("write");
+}
+
+/// Runs with the bundled panels, which define only SIA and EEB panels.
+fn run_code(input: &Path, out: &Path, cwd: &Path) -> Option<i32> {
+    bin()
+        .current_dir(cwd)
+        .args(["run", "--allow-missing-axes", "--input"])
+        .arg(input)
+        .arg("--out")
+        .arg(out)
+        .output()
+        .expect("spawn")
+        .status
+        .code()
+}
+
+fn read_progress(out: &Path) -> serde_json::Value {
+    serde_json::from_slice(&std::fs::read(out.join("progress.json")).expect("progress.json"))
+        .expect("json")
+}
+
+#[test]
+fn help_documents_exit_codes() {
+    let out = bin().arg("--help").output().expect("spawn");
+    let text = String::from_utf8_lossy(&out.stdout);
+    assert!(text.contains("Exit codes:"));
+    assert!(text.contains("4    configuration error"));
+}
+
+#[test]
+fn usage_error_exits_2() {
+    let status = bin().arg("--no-such-flag").status().expect("spawn");
+    assert_eq!(status.code(), Some(2));
+}
+
+#[test]
+fn malformed_input_exits_3() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("in");
+    write_input(
+        &input,
+        "%%MatrixMarket matrix coordinate integer general\n5 9 1\n1 1 4\n",
+    );
+    let out = dir.path().join("out");
+    let code = run_code(&input, &out, env!("CARGO_MANIFEST_DIR").as_ref());
+    assert_eq!(code, Some(3));
+    let progress = read_progress(&out);
+    assert_eq!(progress["status"], "failed");
+    assert_eq!(progress["stage"], "stage1_load");
+    assert_eq!(progress["exit_category"], "input");
+    assert_eq!(progress["exit_code"], 3);
+}
+
+#[test]
+fn broken_panels_exit_4() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let panels = dir.path().join("assets").join("panels");
+    std::fs::create_dir_all(&panels).expect("mkdir");
+    std::fs::write(
+        panels.join("bad.toml"),
+        "[[panel]]\nid = \"X\"\ndescription = \"d\"\naxis = \"NOPE\"\ngenes = [\"G1\"]\n",
+    )
+    .expect("write");
+    let status = bin()
+        .current_dir(dir.path())
+        .args(["panels", "list"])
+        .status()
+        .expect("spawn");
+    assert_eq!(status.code(), Some(4));
+}
+
+#[test]
+fn unwritable_output_exits_5() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("in");
+    write_input(
+        &input,
+        "%%MatrixMarket matrix coordinate integer general\n2 3 3\n1 1 4\n2 2 1\n1 3 2\n",
+    );
+    let blocker = dir.path().join("file");
+    std::fs::write(&blocker, "x").expect("write");
+    let code = run_code(
+        &input,
+        &blocker.join("out"),
+        env!("CARGO_MANIFEST_DIR").as_ref(),
+    );
+    assert_eq!(code, Some(5));
+}
+
+#[test]
+fn unwritable_output_error_names_stage_and_path() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("in");
+    write_input(
+        &input,
+        "%%MatrixMarket matrix coordinate integer general\n2 3 3\n1 1 4\n2 2 1\n1 3 2\n",
+    );
+    let blocker = dir.path().join("file");
+    std::fs::write(&blocker, "x").expect("write");
+    let out = blocker.join("out");
+    let output = bin()
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .args(["run", "--allow-missing-axes", "--input"])
+        .arg(&input)
+        .arg("--out")
+        .arg(&out)
+        .output()
+        .expect("spawn");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let os_error = std::fs::create_dir_all(&out)
+        .expect_err("blocked")
+        .to_string();
+    assert!(
+        stderr.contains(&format!(
+            "Error: setup: creating output directory {}\n\nCaused by:\n    {}",
+            out.display(),
+            os_error
+        )),
+        "{stderr}"
+    );
+    assert!(!stderr.contains("hint:"), "{stderr}");
+}
+
+#[test]
+fn valid_run_exits_0() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("in");
+    write_input(
+        &input,
+        "%%MatrixMarket matrix coordinate integer general\n2 3 3\n1 1 4\n2 2 1\n1 3 2\n",
+    );
+    let out = dir.path().join("out");
+    let code = run_code(&input, &out, env!("CARGO_MANIFEST_DIR").as_ref());
+    assert_eq!(code, Some(0));
+    let progress = read_progress(&out);
+    assert_eq!(progress["status"], "complete");
+    assert_eq!(progress["stage"], "stage7_report");
+    assert_eq!(progress["stage_index"], 7);
+    assert_eq!(progress["n_stages"], 7);
+    assert_eq!(progress["cells_done"], 3);
+    assert_eq!(progress["cells_total"], 3);
+    assert_eq!(progress["eta_ms"], 0);
+    assert!(progress.get("exit_code").is_none());
+    assert!(!out.join("progress.json.tmp").exists());
+    assert!(out.join("flagged_cells.tsv").is_file());
+
+    let read_json = |name: &str| -> serde_json::Value {
+        serde_json::from_slice(&std::fs::read(out.join(name)).expect("read")).expect("json")
+    };
+    let gene_set_hash = read_json("summary.json")["input"]["gene_set_hash"].clone();
+    assert_eq!(gene_set_hash.as_str().map(str::len), Some(16));
+    assert_eq!(read_json("provenance.json")["gene_set_hash"], gene_set_hash);
+}
+
+#[test]
+fn input_pointing_at_a_file() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("in");
+    write_input(
+        &input,
+        "%%MatrixMarket matrix coordinate integer general\n2 3 3\n1 1 4\n2 2 1\n1 3 2\n",
+    );
+    let cwd: &Path = env!("CARGO_MANIFEST_DIR").as_ref();
+
+    let out = dir.path().join("out_matrix");
+    assert_eq!(run_code(&input.join("matrix.mtx"), &out, cwd), Some(0));
+    assert!(out.join("secretion.tsv").is_file());
+
+    let output = bin()
+        .current_dir(cwd)
+        .args(["run", "--allow-missing-axes", "--input"])
+        .arg(input.join("features.tsv"))
+        .arg("--out")
+        .arg(dir.path().join("out_features"))
+        .output()
+        .expect("spawn");
+    assert_eq!(output.status.code(), Some(3));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("is a file, expected a directory"),
+        "{stderr}"
+    );
+    assert!(
+        stderr.contains("[barcodes.tsv, features.tsv, matrix.mtx]"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn missing_core_axes_exit_4() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("in");
+    write_input(
+        &input,
+        "%%MatrixMarket matrix coordinate integer general\n2 3 3\n1 1 4\n2 2 1\n1 3 2\n",
+    );
+    let out = bin()
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .args(["run", "--input"])
+        .arg(&input)
+        .arg("--out")
+        .arg(dir.path().join("out"))
+        .output()
+        .expect("spawn");
+    assert_eq!(out.status.code(), Some(4));
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains("SLI (panels=0"), "{stderr}");
+}
+
+#[test]
+fn panels_on_error_skip_continues_past_broken_file() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("in");
+    write_input(
+        &input,
+        "%%MatrixMarket matrix coordinate integer general\n2 3 3\n1 1 4\n2 2 1\n1 3 2\n",
+    );
+    let panels = dir.path().join("assets").join("panels");
+    std::fs::create_dir_all(&panels).expect("mkdir");
+    std::fs::copy(
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/panels/core.toml"),
+        panels.join("core.toml"),
+    )
+    .expect("copy");
+    std::fs::write(panels.join("broken.toml"), "[[panel]\nid = \"X\"\n").expect("write");
+
+    assert_eq!(
+        run_code(&input, &dir.path().join("fail"), dir.path()),
+        Some(4)
+    );
+
+    let out = dir.path().join("skip");
+    let status = bin()
+        .current_dir(dir.path())
+        .args(["run", "--allow-missing-axes", "--panels-on-error", "skip"])
+        .arg("--input")
+        .arg(&input)
+        .arg("--out")
+        .arg(&out)
+        .status()
+        .expect("spawn");
+    assert_eq!(status.code(), Some(0));
+    let summary: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(out.join("summary.json")).expect("read"))
+            .expect("json");
+    let skipped = &summary["caveats"]["skipped_panel_files"];
+    assert_eq!(skipped[0]["file"], "broken.toml");
+    assert!(
+        skipped[0]["errors"][0]
+            .as_str()
+            .expect("error")
+            .starts_with("toml parse error at line 1")
+    );
+}
+
+#[test]
+fn summary_only_skips_per_cell_tables_and_refuses_pipeline_mode() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("in");
+    write_input(
+        &input,
+        "%%MatrixMarket matrix coordinate integer general\n2 3 3\n1 1 4\n2 2 1\n1 3 2\n",
+    );
+    let run = |out: &Path, extra: &[&str]| {
+        bin()
+            .current_dir(env!("CARGO_MANIFEST_DIR"))
+            .args(["run", "--allow-missing-axes", "--outputs", "summary-only"])
+            .args(extra)
+            .arg("--input")
+            .arg(&input)
+            .arg("--out")
+            .arg(out)
+            .output()
+            .expect("spawn")
+    };
+
+    let out = dir.path().join("summary");
+    assert_eq!(run(&out, &[]).status.code(), Some(0));
+    for file in [
+        "summary.json",
+        "report.txt",
+        "panels_report.tsv",
+        "_SUCCESS",
+    ] {
+        assert!(out.join(file).is_file(), "{file}");
+    }
+    for file in [
+        "expr_stats.tsv",
+        "axes.tsv",
+        "composites.tsv",
+        "classify.tsv",
+        "secretion.tsv",
+    ] {
+        assert!(!out.join(file).exists(), "{file}");
+    }
+    let summary: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(out.join("summary.json")).expect("read"))
+            .expect("json");
+    assert_eq!(summary["provenance"]["outputs"], "summary-only");
+    assert_eq!(summary["input"]["n_cells"], 3);
+
+    let pipeline = run(&dir.path().join("pipeline"), &["--run-mode", "pipeline"]);
+    assert_eq!(pipeline.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&pipeline.stderr);
+    assert!(stderr.contains("pipeline_step.json"), "{stderr}");
+    assert!(!dir.path().join("pipeline").exists());
+}
+
+#[test]
+fn report_format_list_writes_each_report() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("in");
+    write_input(
+        &input,
+        "%%MatrixMarket matrix coordinate integer general\n2 3 3\n1 1 4\n2 2 1\n1 3 2\n",
+    );
+    let out = dir.path().join("out");
+    let status = bin()
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .args(["run", "--allow-missing-axes", "--report-format", "md,html"])
+        .args(["--report-format", "md", "--input"])
+        .arg(&input)
+        .arg("--out")
+        .arg(&out)
+        .status()
+        .expect("spawn");
+    assert_eq!(status.code(), Some(0));
+    assert!(!out.join("report.txt").exists());
+    let md = std::fs::read_to_string(out.join("report.md")).expect("report.md");
+    assert!(md.contains("## Regime fractions"), "{md}");
+    let html = std::fs::read_to_string(out.join("report.html")).expect("report.html");
+    assert!(html.starts_with("<!DOCTYPE html>"), "{html}");
+}
+
+#[test]
+fn qc_gate_failure_exits_6_only_when_strict() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("in");
+    write_input(
+        &input,
+        "%%MatrixMarket matrix coordinate integer general\n2 3 3\n1 1 4\n2 2 1\n1 3 2\n",
+    );
+    let expectations = dir.path().join("qc.toml");
+    std::fs::write(
+        &expectations,
+        "[regimes]\nUnclassified = { min = 0.99 }\n\n[qc]\nlow_confidence_fraction = { max = 1.0 }\n",
+    )
+    .expect("write");
+    let run = |out: &Path, strict: bool| {
+        let mut cmd = bin();
+        cmd.current_dir(env!("CARGO_MANIFEST_DIR"))
+            .args(["run", "--allow-missing-axes", "--qc-expectations"])
+            .arg(&expectations)
+            .arg("--input")
+            .arg(&input)
+            .arg("--out")
+            .arg(out);
+        if strict {
+            cmd.arg("--qc-gate-strict");
+        }
+        cmd.output().expect("spawn")
+    };
+
+    let lenient = dir.path().join("lenient");
+    assert_eq!(run(&lenient, false).status.code(), Some(0));
+
+    let strict = dir.path().join("strict");
+    let output = run(&strict, true);
+    assert_eq!(output.status.code(), Some(6));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("qc gate failed"));
+    for out in [&lenient, &strict] {
+        for file in [
+            "secretion.tsv",
+            "summary.json",
+            "report.txt",
+            "qc_gate.json",
+        ] {
+            assert!(out.join(file).is_file(), "{file}");
+        }
+        let gate: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(out.join("qc_gate.json")).expect("read"))
+                .expect("json");
+        assert_eq!(gate["verdict"], "fail");
+        assert_eq!(gate["n_rules"], 2);
+        assert_eq!(gate["rules"][0]["metric"], "Unclassified");
+        assert_eq!(gate["rules"][0]["pass"], false);
+        assert_eq!(gate["rules"][1]["pass"], true);
+        let report = std::fs::read_to_string(out.join("report.txt")).expect("report");
+        assert!(
+            report.contains("QC gate: FAIL (1 of 2 rules failed)"),
+            "{report}"
+        );
+    }
+}
+
+#[test]
+fn append_cohort_rows_feed_cohort_report() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("week1");
+    write_input(
+        &input,
+        "%%MatrixMarket matrix coordinate integer general\n2 3 3\n1 1 4\n2 2 1\n1 3 2\n",
+    );
+    let cohort = dir.path().join("cohort.tsv");
+    for (out, label) in [("out1", None), ("out2", Some("week1 rerun"))] {
+        let mut cmd = bin();
+        cmd.current_dir(env!("CARGO_MANIFEST_DIR"))
+            .args(["run", "--allow-missing-axes", "--append-cohort"])
+            .arg(&cohort)
+            .arg("--input")
+            .arg(&input)
+            .arg("--out")
+            .arg(dir.path().join(out));
+        if let Some(label) = label {
+            cmd.args(["--label", label]);
+        }
+        let status = cmd.status().expect("spawn");
+        assert_eq!(status.code(), Some(0));
+    }
+    let text = std::fs::read_to_string(&cohort).expect("cohort");
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), 3, "{text}");
+    assert!(lines[0].starts_with("timestamp\tlabel\tn_cells\ttool_version\tpanels_hash\t"));
+    assert!(lines[0].contains("\tfrac_Unclassified"));
+    assert!(lines[1].contains("\tweek1\t3\t"), "{text}");
+    assert!(lines[2].contains("\tweek1 rerun\t3\t"), "{text}");
+
+    let output = bin()
+        .args(["cohort", "report"])
+        .arg(&cohort)
+        .output()
+        .expect("spawn");
+    assert_eq!(output.status.code(), Some(0));
+    let md = String::from_utf8_lossy(&output.stdout);
+    assert!(md.contains("## Regime trends"), "{md}");
+    assert!(md.contains("| week1 | 3 |"), "{md}");
+    assert!(md.contains("| week1 rerun | 3 |"), "{md}");
+
+    let bad = dir.path().join("bad.tsv");
+    std::fs::write(&bad, "label\nx\n").expect("write");
+    let status = bin()
+        .args(["cohort", "report"])
+        .arg(&bad)
+        .status()
+        .expect("spawn");
+    assert_eq!(status.code(), Some(3));
+}
+
+#[test]
+fn label_is_validated_and_embedded_in_artifacts() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("in");
+    write_input(
+        &input,
+        "%%MatrixMarket matrix coordinate integer general\n2 3 3\n1 1 4\n2 2 1\n1 3 2\n",
+    );
+    let run = |out: &str, extra: &[&str]| {
+        bin()
+            .current_dir(env!("CARGO_MANIFEST_DIR"))
+            .args([
+                "run",
+                "--allow-missing-axes",
+                "--run-mode",
+                "pipeline",
+                "--input",
+            ])
+            .arg(&input)
+            .arg("--out")
+            .arg(dir.path().join(out))
+            .args(extra)
+            .status()
+            .expect("spawn")
+            .code()
+    };
+    assert_eq!(run("bad", &["--label", "a/b"]), Some(2));
+    assert_eq!(run("orphan", &["--description", "no label"]), Some(2));
+
+    let labelled = ["--label", "PBMC-3k #2", "--description", "donor A, day 7"];
+    assert_eq!(run("out", &labelled), Some(0));
+    let out = dir.path().join("out").join("kira-secretion");
+    let read_json = |name: &str| -> serde_json::Value {
+        serde_json::from_slice(&std::fs::read(out.join(name)).expect("read")).expect("json")
+    };
+    for name in ["summary.json", "provenance.json", "pipeline_step.json"] {
+        let run = &read_json(name)["run"];
+        assert_eq!(run["label"], "PBMC-3k #2", "{name}");
+        assert_eq!(run["description"], "donor A, day 7", "{name}");
+    }
+    let report = std::fs::read_to_string(out.join("report.txt")).expect("report");
+    assert!(
+        report.starts_with("Kira Secretion Report: PBMC-3k #2\n"),
+        "{report}"
+    );
+
+    assert_eq!(run("plain", &[]), Some(0));
+    let plain = dir.path().join("plain").join("kira-secretion");
+    let summary = std::fs::read_to_string(plain.join("summary.json")).expect("summary");
+    assert!(!summary.contains("\"run\""), "{summary}");
+}
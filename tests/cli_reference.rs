@@ -0,0 +1,82 @@
+use std::path::Path;
+use std::process::{Command, Output};
+
+const MATRIX: &str =
+    "%%MatrixMarket matrix coordinate integer general\n2 3 3\n1 1 4\n2 2 1\n1 3 2\n";
+
+fn run(input: &Path, out: &Path, extra: &[&std::ffi::OsStr]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_kira-secretion"))
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .args(["run", "--allow-missing-axes", "--input"])
+        .arg(input)
+        .arg("--out")
+        .arg(out)
+        .args(extra)
+        .output()
+        .expect("spawn")
+}
+
+#[test]
+fn saved_reference_adds_percentiles_and_drift() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("in");
+    std::fs::create_dir_all(&input).expect("mkdir");
+    std::fs::write(input.join("features.tsv"), "f1\tSEC23A\nf2\tSAR1A\n").expect("write");
+    std::fs::write(input.join("barcodes.tsv"), "c1\nc2\nc3\n").expect("write");
+    std::fs::write(input.join("matrix.mtx"), MATRIX).expect("write");
+    let reference = dir.path().join("ref.json");
+
+    let out = run(
+        &input,
+        &dir.path().join("base"),
+        &["--save-reference".as_ref(), reference.as_os_str()],
+    );
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    let saved: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(&reference).expect("read")).expect("json");
+    assert_eq!(saved["format_version"], 1);
+    assert_eq!(
+        saved["composites"]["OII"].as_array().map(Vec::len),
+        Some(101)
+    );
+
+    let follow_up = dir.path().join("follow");
+    let out = run(
+        &input,
+        &follow_up,
+        &["--reference".as_ref(), reference.as_os_str()],
+    );
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    let tsv = std::fs::read_to_string(follow_up.join("secretion.tsv")).expect("read");
+    let header: Vec<&str> = tsv.lines().next().unwrap().split('\t').collect();
+    assert!(header.contains(&"secretory_load_ref_pctl"));
+    assert!(header.contains(&"stress_secretion_index_ref_pctl"));
+    assert!(
+        tsv.lines()
+            .skip(1)
+            .all(|l| l.split('\t').count() == header.len())
+    );
+    let summary: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(follow_up.join("summary.json")).expect("read"))
+            .expect("json");
+    assert_eq!(summary["reference"]["drift"]["OII"]["delta"], 0.0);
+
+    let mut tampered = saved.clone();
+    tampered["panel_set_hash"] = "0000000000000000".into();
+    std::fs::write(&reference, tampered.to_string()).expect("write");
+    let out = run(
+        &input,
+        &dir.path().join("mismatch"),
+        &["--reference".as_ref(), reference.as_os_str()],
+    );
+    assert_eq!(out.status.code(), Some(4));
+    assert!(String::from_utf8_lossy(&out.stderr).contains("panel set"));
+}
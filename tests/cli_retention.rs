@@ -0,0 +1,120 @@
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::process::Command;
+
+const MATRIX: &str =
+    "%%MatrixMarket matrix coordinate integer general\n2 3 3\n1 1 4\n2 2 1\n1 3 2\n";
+
+/// Files every policy keeps in a pipeline-mode stage directory.
+const KEPT: [&str; 11] = [
+    "_SUCCESS",
+    "axes_summary.json",
+    "composites_summary.json",
+    "condition_summary.tsv",
+    "mapping_warnings.tsv",
+    "panel_gene_mapping.tsv",
+    "pipeline_step.json",
+    "progress.json",
+    "provenance.json",
+    "report.txt",
+    "secretion.tsv",
+];
+
+fn run_with_retain(dir: &Path, retain: &str) -> std::path::PathBuf {
+    let input = dir.join("in");
+    std::fs::create_dir_all(&input).expect("mkdir");
+    std::fs::write(input.join("features.tsv"), "f1\tSEC23A\nf2\tSAR1A\n").expect("write");
+    std::fs::write(input.join("barcodes.tsv"), "c1\nc2\nc3\n").expect("write");
+    std::fs::write(input.join("matrix.mtx"), MATRIX).expect("write");
+    let out = dir.join(format!("out_{retain}"));
+    let output = Command::new(env!("CARGO_BIN_EXE_kira-secretion"))
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .args(["run", "--allow-missing-axes", "--run-mode", "pipeline"])
+        .args(["--retain", retain, "--input"])
+        .arg(&input)
+        .arg("--out")
+        .arg(&out)
+        .output()
+        .expect("spawn");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    out.join("kira-secretion")
+}
+
+fn files(dir: &Path) -> BTreeSet<String> {
+    std::fs::read_dir(dir)
+        .expect("read_dir")
+        .map(|e| e.expect("entry").file_name().to_string_lossy().into_owned())
+        .collect()
+}
+
+fn expected(extra: &[&str]) -> BTreeSet<String> {
+    KEPT.iter()
+        .chain(["summary.json"].iter())
+        .chain(extra)
+        .map(|f| f.to_string())
+        .collect()
+}
+
+fn listed_artifacts(stage: &Path) -> BTreeSet<String> {
+    let step: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(stage.join("pipeline_step.json")).expect("read"))
+            .expect("json");
+    step["artifacts"]
+        .as_object()
+        .expect("artifacts")
+        .values()
+        .map(|v| v.as_str().expect("file name").to_string())
+        .collect()
+}
+
+#[test]
+fn each_policy_keeps_exactly_its_file_set() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let intermediates = [
+        "axes.tsv",
+        "classify.tsv",
+        "composites.tsv",
+        "expr_stats.tsv",
+    ];
+    let cases: [(&str, Vec<&str>); 3] = [
+        ("all", [&intermediates[..], &["panels_report.tsv"]].concat()),
+        ("final", vec!["panels_report.tsv"]),
+        ("minimal", vec![]),
+    ];
+    for (retain, extra) in cases {
+        let stage = run_with_retain(dir.path(), retain);
+        assert_eq!(files(&stage), expected(&extra), "--retain {retain}");
+        let remaining = files(&stage);
+        for listed in listed_artifacts(&stage) {
+            assert!(
+                remaining.contains(&listed),
+                "--retain {retain}: pipeline_step.json lists removed {listed}"
+            );
+        }
+    }
+}
+
+#[test]
+fn verify_passes_after_retention() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let stage = run_with_retain(dir.path(), "minimal");
+    let output = Command::new(env!("CARGO_BIN_EXE_kira-secretion"))
+        .args(["verify", "--out"])
+        .arg(&stage)
+        .output()
+        .expect("spawn");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("classify_secretion_agreement\tskipped (classify.tsv not retained)"),
+        "{stdout}"
+    );
+}
@@ -0,0 +1,141 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::Path;
+use std::process::Command;
+
+const GENES: [&str; 12] = [
+    "SEC23A", "SEC24B", "SAR1A", "COPB1", "SNAP23", "STX3", "VAMP3", "LAMP1", "CTSD", "MKI67",
+    "TOP2A", "GAPDH",
+];
+
+/// Enough cells for several reduction chunks, with pseudo-random counts.
+fn write_input(dir: &Path, n_cells: usize) {
+    std::fs::create_dir_all(dir).expect("mkdir");
+    let features: String = GENES
+        .iter()
+        .enumerate()
+        .map(|(i, g)| format!("g{i}\t{g}\n"))
+        .collect();
+    std::fs::write(dir.join("features.tsv"), features).expect("write");
+    let barcodes: String = (0..n_cells).map(|c| format!("cell{c:06}\n")).collect();
+    std::fs::write(dir.join("barcodes.tsv"), barcodes).expect("write");
+
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    let mut entries = Vec::new();
+    for cell in 0..n_cells {
+        for gene in 0..GENES.len() {
+            state = state
+                .wrapping_mul(6_364_136_223_846_793_005)
+                .wrapping_add(1_442_695_040_888_963_407);
+            let count = (state >> 59) as u32;
+            if count > 0 {
+                entries.push((gene + 1, cell + 1, count));
+            }
+        }
+    }
+    let mut matrix = format!(
+        "%%MatrixMarket matrix coordinate integer general\n{} {} {}\n",
+        GENES.len(),
+        n_cells,
+        entries.len()
+    );
+    for (gene, cell, count) in entries {
+        writeln!(matrix, "{gene} {cell} {count}").expect("fmt");
+    }
+    std::fs::write(dir.join("matrix.mtx"), matrix).expect("write");
+}
+
+/// Every file under `dir` except progress.json, which carries timestamps.
+fn artifacts(dir: &Path) -> BTreeMap<String, Vec<u8>> {
+    let mut out = BTreeMap::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        for entry in std::fs::read_dir(&current).expect("read_dir").flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                pending.push(path);
+                continue;
+            }
+            let name = path
+                .strip_prefix(dir)
+                .expect("prefix")
+                .display()
+                .to_string();
+            if name != "progress.json" {
+                out.insert(name, std::fs::read(&path).expect("read"));
+            }
+        }
+    }
+    out
+}
+
+#[test]
+fn artifacts_are_byte_identical_across_thread_counts() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("in");
+    write_input(&input, 40_000);
+
+    let mut runs = Vec::new();
+    for threads in ["1", "3", "8"] {
+        let out = dir.path().join(format!("out{threads}"));
+        let output = Command::new(env!("CARGO_BIN_EXE_kira-secretion"))
+            .current_dir(env!("CARGO_MANIFEST_DIR"))
+            .args(["run", "--allow-missing-axes", "--threads", threads])
+            .args(["--report-format", "txt,md,html", "--input"])
+            .arg(&input)
+            .arg("--out")
+            .arg(&out)
+            .output()
+            .expect("spawn");
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        runs.push((threads, artifacts(&out)));
+    }
+
+    let (_, baseline) = &runs[0];
+    assert!(baseline.contains_key("secretion.tsv"));
+    assert!(baseline.contains_key("summary.json"));
+    for (threads, files) in &runs[1..] {
+        assert_eq!(
+            files.keys().collect::<Vec<_>>(),
+            baseline.keys().collect::<Vec<_>>(),
+            "{threads} threads"
+        );
+        for (name, bytes) in files {
+            assert!(
+                bytes == &baseline[name],
+                "{name} differs between 1 and {threads} threads"
+            );
+        }
+    }
+}
+
+#[test]
+fn verify_determinism_compares_two_runs() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("in");
+    write_input(&input, 200);
+    let scratch = dir.path().join("scratch");
+    let output = Command::new(env!("CARGO_BIN_EXE_kira-secretion"))
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .args(["verify-determinism", "--threads", "1,4", "--input"])
+        .arg(&input)
+        .arg("--scratch-dir")
+        .arg(&scratch)
+        .args(["--", "--allow-missing-axes"])
+        .output()
+        .expect("spawn");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let table = String::from_utf8_lossy(&output.stdout);
+    assert!(table.contains("file\tresult\tdetail\n"), "{table}");
+    assert!(table.contains("secretion.tsv\tPASS\t.\n"), "{table}");
+    assert!(!table.contains("FAIL"), "{table}");
+    assert!(!table.contains("progress.json"), "{table}");
+}
@@ -0,0 +1,100 @@
+use std::path::Path;
+use std::process::Command;
+
+use kira_secretion::expr::csc::ExprCsc;
+use kira_secretion::input::cache::write_shared_cache;
+
+fn bin() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_kira-secretion"))
+}
+
+/// Three cells; the first barcode carries an embedded tab. A tab in
+/// barcodes.tsv is a column delimiter, so the barcode comes from a shared
+/// cache (pipeline mode), whose string tables are not delimited.
+fn write_input(dir: &Path) {
+    std::fs::create_dir_all(dir).expect("mkdir");
+    let expr = ExprCsc {
+        n_genes: 2,
+        n_cells: 3,
+        nnz: 3,
+        col_ptr: vec![0, 1, 2, 3],
+        row_idx: vec![0, 1, 0],
+        values: vec![4, 1, 2],
+    };
+    write_shared_cache(
+        &dir.join("kira-organelle.bin"),
+        &["SEC23A".to_string(), "SAR1A".to_string()],
+        &["c\t1".to_string(), "c2".to_string(), "c3".to_string()],
+        &expr,
+    )
+    .expect("write cache");
+}
+
+fn run(input: &Path, out: &Path, extra: &[&str]) -> std::process::Output {
+    bin()
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .args([
+            "run",
+            "--run-mode",
+            "pipeline",
+            "--allow-missing-axes",
+            "--input",
+        ])
+        .arg(input)
+        .arg("--out")
+        .arg(out)
+        .args(extra)
+        .output()
+        .expect("spawn")
+}
+
+#[test]
+fn tab_in_barcode_is_an_input_error_by_default() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("in");
+    write_input(&input);
+    let out = run(&input, &dir.path().join("out"), &[]);
+    assert_eq!(out.status.code(), Some(3));
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains("--lenient"), "{stderr}");
+}
+
+#[test]
+fn lenient_writes_structurally_valid_tsv() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("in");
+    let out_dir = dir.path().join("out");
+    write_input(&input);
+    let out = run(&input, &out_dir, &["--lenient"]);
+    let out_dir = out_dir.join("kira-secretion");
+    assert_eq!(
+        out.status.code(),
+        Some(0),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+
+    for file in [
+        "secretion.tsv",
+        "classify.tsv",
+        "axes.tsv",
+        "composites.tsv",
+        "expr_stats.tsv",
+    ] {
+        let text = std::fs::read_to_string(out_dir.join(file)).expect(file);
+        let mut lines = text.lines();
+        let width = lines.next().expect("header").split('\t').count();
+        let rows: Vec<&str> = lines.collect();
+        assert_eq!(rows.len(), 3, "{file}");
+        for row in &rows {
+            assert_eq!(row.split('\t').count(), width, "{file}: {row:?}");
+        }
+        assert!(rows.iter().any(|r| r.starts_with("c 1\t")), "{file}");
+    }
+
+    let summary: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string(out_dir.join("summary.json")).expect("summary"),
+    )
+    .expect("json");
+    assert!(summary["caveats"]["sanitized_fields"].as_u64() > Some(0));
+}
@@ -0,0 +1,92 @@
+use std::path::Path;
+use std::process::{Command, Output};
+
+const MATRIX: &str =
+    "%%MatrixMarket matrix coordinate integer general\n2 3 3\n1 1 4\n2 2 1\n1 3 2\n";
+
+fn run(input: &Path, out: &Path, extra: &[&std::ffi::OsStr]) -> Output {
+    let output = Command::new(env!("CARGO_BIN_EXE_kira-secretion"))
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .args(["run", "--allow-missing-axes", "--input"])
+        .arg(input)
+        .arg("--out")
+        .arg(out)
+        .args(extra)
+        .output()
+        .expect("spawn");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    output
+}
+
+fn skipped(output: &Output) -> bool {
+    String::from_utf8_lossy(&output.stdout).contains("up to date")
+}
+
+/// Runs once, then removes progress.json: a skipped re-run leaves the
+/// directory untouched, a real one writes it again.
+fn first_run(dir: &Path, extra: &[&std::ffi::OsStr]) -> (std::path::PathBuf, std::path::PathBuf) {
+    let input = dir.join("in");
+    std::fs::create_dir_all(&input).expect("mkdir");
+    std::fs::write(input.join("features.tsv"), "f1\tSEC23A\nf2\tSAR1A\n").expect("write");
+    std::fs::write(input.join("barcodes.tsv"), "c1\nc2\nc3\n").expect("write");
+    std::fs::write(input.join("matrix.mtx"), MATRIX).expect("write");
+    let out = dir.join("out");
+    assert!(!skipped(&run(&input, &out, extra)));
+    let provenance: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(out.join("provenance.json")).expect("read"))
+            .expect("json");
+    assert_eq!(
+        provenance["fingerprint"]["digest"].as_str().map(str::len),
+        Some(16)
+    );
+    std::fs::remove_file(out.join("progress.json")).expect("remove progress.json");
+    (input, out)
+}
+
+#[test]
+fn identical_rerun_is_skipped() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let (input, out) = first_run(dir.path(), &[]);
+    assert!(skipped(&run(&input, &out, &[])));
+    assert!(!out.join("progress.json").exists());
+    assert!(out.join("_SUCCESS").is_file());
+}
+
+#[test]
+fn force_reruns() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let (input, out) = first_run(dir.path(), &[]);
+    assert!(!skipped(&run(&input, &out, &["--force".as_ref()])));
+    assert!(out.join("progress.json").is_file());
+}
+
+#[test]
+fn changed_thresholds_rerun() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let thresholds = dir.path().join("thresholds.json");
+    std::fs::write(&thresholds, "{}").expect("write");
+    let (input, out) = first_run(
+        dir.path(),
+        &["--thresholds".as_ref(), thresholds.as_os_str()],
+    );
+    std::fs::write(&thresholds, r#"{"cov_min": 0.9}"#).expect("write");
+    assert!(!skipped(&run(
+        &input,
+        &out,
+        &["--thresholds".as_ref(), thresholds.as_os_str()]
+    )));
+    assert!(out.join("progress.json").is_file());
+}
+
+#[test]
+fn partial_artifacts_rerun() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let (input, out) = first_run(dir.path(), &[]);
+    std::fs::remove_file(out.join("classify.tsv")).expect("remove classify.tsv");
+    assert!(!skipped(&run(&input, &out, &[])));
+    assert!(out.join("classify.tsv").is_file());
+}
@@ -0,0 +1,120 @@
+use std::path::Path;
+use std::process::Command;
+
+use kira_secretion::expr::csc::ExprCsc;
+use kira_secretion::input::cache::write_shared_cache;
+
+/// Shared cache for three cells plus a sidecar with two upstream columns and
+/// one non-numeric column that must be skipped.
+fn write_input(dir: &Path) {
+    std::fs::create_dir_all(dir).expect("mkdir");
+    let expr = ExprCsc {
+        n_genes: 2,
+        n_cells: 3,
+        nnz: 3,
+        col_ptr: vec![0, 1, 2, 3],
+        row_idx: vec![0, 1, 0],
+        values: vec![4, 1, 2],
+    };
+    write_shared_cache(
+        &dir.join("kira-organelle.bin"),
+        &["SEC23A".to_string(), "SAR1A".to_string()],
+        &["c1".to_string(), "c2".to_string(), "c3".to_string()],
+        &expr,
+    )
+    .expect("write cache");
+    std::fs::write(
+        dir.join("kira-organelle.cells.tsv"),
+        "barcode\tmito_stress\tlysosome_load\tcluster\n\
+         c2\t0.5\t2\tB\n\
+         c1\t0.25\t1\tA\n\
+         c3\t0.75\t3\tC\n",
+    )
+    .expect("write sidecar");
+}
+
+fn run(input: &Path, out: &Path, extra: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_kira-secretion"))
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .args([
+            "run",
+            "--run-mode",
+            "pipeline",
+            "--allow-missing-axes",
+            "--input",
+        ])
+        .arg(input)
+        .arg("--out")
+        .arg(out)
+        .args(extra)
+        .output()
+        .expect("spawn")
+}
+
+fn read_outputs(out: &Path) -> (String, serde_json::Value) {
+    let out = out.join("kira-secretion");
+    let tsv = std::fs::read_to_string(out.join("secretion.tsv")).expect("secretion.tsv");
+    let summary = serde_json::from_slice(&std::fs::read(out.join("summary.json")).expect("read"))
+        .expect("json");
+    (tsv, summary)
+}
+
+#[test]
+fn upstream_columns_flow_through_to_secretion_tsv() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("in");
+    write_input(&input);
+    let out = dir.path().join("out");
+    let output = run(&input, &out, &[]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let (tsv, summary) = read_outputs(&out);
+    let mut lines = tsv.lines();
+    let header: Vec<&str> = lines.next().expect("header").split('\t').collect();
+    assert_eq!(
+        header[header.len() - 2..],
+        ["upstream_mito_stress", "upstream_lysosome_load"]
+    );
+    let values: Vec<(String, String, String)> = lines
+        .map(|line| {
+            let fields: Vec<&str> = line.split('\t').collect();
+            let n = fields.len();
+            (
+                fields[0].to_string(),
+                fields[n - 2].to_string(),
+                fields[n - 1].to_string(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        values,
+        [
+            ("c1".into(), "0.250000".into(), "1.000000".into()),
+            ("c2".into(), "0.500000".into(), "2.000000".into()),
+            ("c3".into(), "0.750000".into(), "3.000000".into()),
+        ]
+    );
+
+    let columns = &summary["upstream"]["columns"];
+    assert_eq!(columns["upstream_mito_stress"]["median"], 0.5);
+    assert_eq!(columns["upstream_lysosome_load"]["median"], 2.0);
+    assert!(columns.get("upstream_cluster").is_none());
+}
+
+#[test]
+fn no_upstream_columns_leaves_them_out() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("in");
+    write_input(&input);
+    let out = dir.path().join("out");
+    let output = run(&input, &out, &["--no-upstream-columns"]);
+    assert!(output.status.success());
+
+    let (tsv, summary) = read_outputs(&out);
+    assert!(!tsv.contains("upstream_"));
+    assert!(summary.get("upstream").is_none());
+}
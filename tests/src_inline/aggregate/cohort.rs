@@ -0,0 +1,132 @@
+use super::*;
+use std::time::Duration;
+
+fn row(timestamp: &str, label: &str, extra: &[(&str, &str)]) -> CohortRow {
+    let mut columns = vec![
+        ("timestamp".to_string(), timestamp.to_string()),
+        ("label".to_string(), label.to_string()),
+        ("n_cells".to_string(), "10".to_string()),
+        ("frac_Homeostatic".to_string(), "0.500000".to_string()),
+    ];
+    columns.extend(extra.iter().map(|(c, v)| (c.to_string(), v.to_string())));
+    CohortRow { columns }
+}
+
+#[test]
+fn append_creates_header_then_adds_rows() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("cohort.tsv");
+    append_row(&path, &row("2026-01-01T00:00:00Z", "week1", &[])).expect("append");
+    append_row(&path, &row("2026-01-08T00:00:00Z", "week2", &[])).expect("append");
+    assert_eq!(
+        std::fs::read_to_string(&path).expect("read"),
+        "timestamp\tlabel\tn_cells\tfrac_Homeostatic\n\
+         2026-01-01T00:00:00Z\tweek1\t10\t0.500000\n\
+         2026-01-08T00:00:00Z\tweek2\t10\t0.500000\n"
+    );
+    assert!(!dir.path().join("cohort.tsv.tmp").exists());
+}
+
+#[test]
+fn older_header_is_extended_with_na() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("cohort.tsv");
+    std::fs::write(
+        &path,
+        "timestamp\tlabel\tretired\n2025-12-01T00:00:00Z\told\tx",
+    )
+    .expect("write");
+    append_row(
+        &path,
+        &row("2026-01-01T00:00:00Z", "new", &[("panels_hash", "abc")]),
+    )
+    .expect("append");
+    assert_eq!(
+        std::fs::read_to_string(&path).expect("read"),
+        "timestamp\tlabel\tretired\tn_cells\tfrac_Homeostatic\tpanels_hash\n\
+         2025-12-01T00:00:00Z\told\tx\tNA\tNA\tNA\n\
+         2026-01-01T00:00:00Z\tnew\tNA\t10\t0.500000\tabc\n"
+    );
+}
+
+#[test]
+fn concurrent_appends_do_not_interleave() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("cohort.tsv");
+    std::thread::scope(|scope| {
+        for i in 0..8 {
+            let path = &path;
+            scope.spawn(move || {
+                // Half the writers add a column, forcing header rewrites.
+                let extra: &[(&str, &str)] = if i % 2 == 0 {
+                    &[]
+                } else {
+                    &[("panels_hash", "h")]
+                };
+                append_row(
+                    path,
+                    &row("2026-01-01T00:00:00Z", &format!("job{i}"), extra),
+                )
+                .expect("append");
+            });
+        }
+    });
+    let table = CohortTable::read(&path).expect("read");
+    assert_eq!(table.rows.len(), 8);
+    let text = std::fs::read_to_string(&path).expect("read");
+    let width = table.header.len();
+    assert!(
+        text.lines().all(|l| l.split('\t').count() == width),
+        "{text}"
+    );
+    let mut labels: Vec<&str> = table.rows.iter().map(|r| r[1].as_str()).collect();
+    labels.sort();
+    assert_eq!(
+        labels,
+        (0..8).map(|i| format!("job{i}")).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn report_orders_runs_and_summarizes_trends() {
+    let table = CohortTable::parse(
+        "timestamp\tlabel\tn_cells\tfrac_Homeostatic\tfrac_Collapse\n\
+         2026-01-08T00:00:00Z\tweek2\t20\t0.400000\t0.100000\n\
+         2026-01-01T00:00:00Z\tweek1\t10\t0.500000\n",
+    )
+    .expect("parse");
+    assert_eq!(table.rows[1][4], NA);
+
+    let md = render_report(&table, CohortReportFormat::Md);
+    assert!(
+        md.contains("| Homeostatic | 2 | 50.00% | 40.00% | -10.00 pp | 40.00% | 50.00% |"),
+        "{md}"
+    );
+    assert!(
+        md.contains("| Collapse | 1 | 10.00% | 10.00% | +0.00 pp |"),
+        "{md}"
+    );
+    let week1 = md.find("| 2026-01-01T00:00:00Z | week1 | 10 | NA | 50.00% | NA |");
+    let week2 = md.find("| 2026-01-08T00:00:00Z | week2 | 20 |");
+    assert!(week1.is_some() && week1 < week2, "{md}");
+
+    let html = render_report(&table, CohortReportFormat::Html);
+    assert!(html.contains("<h2>Regime trends</h2>"));
+    assert!(html.contains("<td>week1</td>"));
+
+    assert!(CohortTable::parse("label\nx\n").is_err());
+    assert!(CohortTable::parse("timestamp\n1\t2\n").is_err());
+}
+
+#[test]
+fn utc_timestamp_is_rfc3339() {
+    assert_eq!(utc_timestamp(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+    assert_eq!(
+        utc_timestamp(UNIX_EPOCH + Duration::from_secs(1_700_000_000)),
+        "2023-11-14T22:13:20Z"
+    );
+    assert_eq!(
+        utc_timestamp(UNIX_EPOCH + Duration::from_secs(951_782_400)),
+        "2000-02-29T00:00:00Z"
+    );
+}
@@ -0,0 +1,82 @@
+use super::*;
+
+fn close(a: f64, b: f64, tol: f64) -> bool {
+    (a - b).abs() <= tol
+}
+
+#[test]
+fn normal_tail_matches_known_quantiles() {
+    assert!(close(two_sided_p(1.959_964), 0.05, 1e-6));
+    assert!(close(two_sided_p(-2.575_829), 0.01, 1e-6));
+    assert!(close(two_sided_p(0.0), 1.0, 1e-6));
+}
+
+#[test]
+fn two_proportion_z_uses_the_pooled_variance() {
+    let t = two_proportion_z(30, 100, 20, 100);
+    assert_eq!((t.fraction_a, t.fraction_b), (0.3, 0.2));
+    assert!(close(t.z, 1.632_993, 1e-6), "{}", t.z);
+    assert!(close(t.p_value, 0.102_470, 1e-5), "{}", t.p_value);
+
+    let flipped = two_proportion_z(20, 100, 30, 100);
+    assert_eq!(flipped.z, -t.z);
+    assert_eq!(flipped.p_value, t.p_value);
+
+    let none_anywhere = two_proportion_z(0, 40, 0, 25);
+    assert_eq!(none_anywhere.z, 0.0);
+    assert_eq!(none_anywhere.p_value, two_sided_p(0.0));
+}
+
+#[test]
+fn mann_whitney_separated_groups() {
+    let t = mann_whitney_u(&[1.0, 2.0, 3.0], &[4.0, 5.0, 6.0]).expect("both sides");
+    assert_eq!(t.u, 0.0);
+    assert!(close(t.z, -1.963_961, 1e-6), "{}", t.z);
+    assert!(close(t.p_value, 0.049_535, 1e-5), "{}", t.p_value);
+}
+
+#[test]
+fn mann_whitney_ties_get_midranks_and_corrected_variance() {
+    let a = [1.0, 2.0, 2.0];
+    let b = [2.0, 3.0, 3.0];
+    let t = mann_whitney_u(&a, &b).expect("both sides");
+    // Ranks 1, 3, 3 for a; variance 9/12 * (7 - 30/30) = 4.5.
+    assert_eq!(t.u, 1.0);
+    assert!(close(t.z, -3.5 / 4.5f64.sqrt(), 1e-12), "{}", t.z);
+
+    let swapped = mann_whitney_u(&b, &a).expect("both sides");
+    assert_eq!(swapped.u, 8.0);
+    assert_eq!(swapped.z, -t.z);
+    assert_eq!(swapped.p_value, t.p_value);
+
+    let all_tied = mann_whitney_u(&[0.5; 4], &[0.5; 7]).expect("both sides");
+    assert_eq!((all_tied.u, all_tied.z), (14.0, 0.0));
+}
+
+#[test]
+fn mann_whitney_handles_unequal_sizes_and_nan() {
+    let a = [0.1, f32::NAN, 0.4, 0.9];
+    let b = [0.2, 0.3, 0.5, 0.6, 0.7, f32::NAN, 0.8];
+    let t = mann_whitney_u(&a, &b).expect("both sides");
+    assert_eq!((t.n_a, t.n_b), (3, 6));
+    let reverse = mann_whitney_u(&b, &a).expect("both sides");
+    assert_eq!(t.u + reverse.u, 18.0);
+    assert_eq!(t.p_value, reverse.p_value);
+    assert_eq!(mann_whitney_u(&a, &b), Some(t), "deterministic");
+
+    assert_eq!(mann_whitney_u(&[f32::NAN], &b), None);
+}
+
+#[test]
+fn skip_reason_names_the_small_group() {
+    let groups = |a: usize, b: usize| vec![("ctrl".to_string(), a), ("treated".to_string(), b)];
+    assert_eq!(skip_reason(&groups(20, 20), 20), None);
+    assert_eq!(
+        skip_reason(&groups(20, 19), 20).as_deref(),
+        Some("group treated has 19 cells, fewer than 20")
+    );
+    assert_eq!(
+        skip_reason(&groups(5, 5)[..1], 1).as_deref(),
+        Some("needs exactly two groups, found 1")
+    );
+}
@@ -0,0 +1,61 @@
+use super::*;
+
+#[test]
+fn keys_are_sorted_and_order_independent() {
+    assert_eq!(combination_key(&[]), None);
+    assert_eq!(
+        combination_key(&["LOW_CONFIDENCE", "HIGH_AMBIENT_RISK", "FEW_DETECTED_GENES"]).as_deref(),
+        Some("FEW_DETECTED_GENES+HIGH_AMBIENT_RISK+LOW_CONFIDENCE")
+    );
+    assert_eq!(
+        combination_key(&["LOW_COUNTS", "FEW_DETECTED_GENES"]),
+        combination_key(&["FEW_DETECTED_GENES", "LOW_COUNTS"])
+    );
+}
+
+#[test]
+fn counts_rank_by_frequency_and_skip_unflagged_cells() {
+    let keys = ["B", "A", "B", "C", "A", "B"]
+        .into_iter()
+        .map(|k| Some(k.to_string()))
+        .chain([None, None]);
+    let combos = count_combinations(keys, 8);
+    let got: Vec<(&str, usize)> = combos
+        .iter()
+        .map(|c| (c.flags.as_str(), c.n_cells))
+        .collect();
+    assert_eq!(got, [("B", 3), ("A", 2), ("C", 1)]);
+    assert_eq!(combos[0].fraction, 3.0 / 8.0);
+}
+
+#[test]
+fn rare_combinations_pool_into_other() {
+    // 25 combinations: key k{i} seen 30 - i times.
+    let keys = (0..25).flat_map(|i| std::iter::repeat_n(Some(format!("k{i:02}")), 30 - i));
+    let total: usize = (0..25).map(|i| 30 - i).sum();
+    let combos = count_combinations(keys, total);
+    assert_eq!(combos.len(), MAX_FLAG_COMBINATIONS + 1);
+    assert_eq!(combos[0].flags, "k00");
+    assert_eq!(combos[MAX_FLAG_COMBINATIONS - 1].flags, "k19");
+    let other = combos.last().unwrap();
+    assert_eq!(other.flags, OTHER_COMBINATIONS);
+    assert_eq!(other.n_cells, 10 + 9 + 8 + 7 + 6);
+    assert_eq!(combos.iter().map(|c| c.n_cells).sum::<usize>(), total);
+}
+
+#[test]
+fn known_combinations_have_a_reading() {
+    assert!(interpretation("LOW_COUNTS").unwrap().contains("depth"));
+    assert!(
+        interpretation("LOW_CONFIDENCE+LOW_SECRETORY_SIGNAL")
+            .unwrap()
+            .contains("panel coverage")
+    );
+    assert!(
+        interpretation("FEW_DETECTED_GENES+HIGH_AMBIENT_RISK+LOW_COUNTS")
+            .unwrap()
+            .contains("empty droplets")
+    );
+    assert_eq!(interpretation("LOW_CONFIDENCE+LOW_COUNTS"), None);
+    assert_eq!(interpretation(OTHER_COMBINATIONS), None);
+}
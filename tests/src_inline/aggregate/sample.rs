@@ -0,0 +1,44 @@
+use super::*;
+
+#[test]
+fn iqr_interpolates_quartiles_and_skips_nan() {
+    let values = [4.0, f32::NAN, 1.0, 3.0, 2.0];
+    let summary = gated_metric(&values, 1).expect("four finite values");
+    assert_eq!(summary.iqr, 1.5);
+    assert_eq!(summary.median, 2.5);
+
+    let constant = gated_metric(&[0.5; 7], 1).expect("constant");
+    assert_eq!(constant.iqr, 0.0);
+}
+
+#[test]
+fn gate_boundary_is_inclusive() {
+    let values: Vec<f32> = (0..5).map(|i| i as f32 * 0.25).collect();
+
+    assert_eq!(gated_metric(&values, 6), None);
+    let at_gate = gated_metric(&values, 5).expect("exactly min_cells passes");
+    assert_eq!(at_gate.median, 0.5);
+    assert_eq!(at_gate.iqr, 0.5);
+}
+
+#[test]
+fn nan_heavy_sample_is_gated_on_finite_values() {
+    let mut values = vec![f32::NAN; 8];
+    values.extend([0.2, 0.4]);
+
+    assert_eq!(gated_metric(&values, 3), None);
+    let summary = gated_metric(&values, 2).expect("two finite values");
+    assert!((summary.median - 0.3).abs() < 1e-6);
+    assert!((summary.iqr - 0.1).abs() < 1e-6);
+
+    assert_eq!(gated_metric(&[f32::NAN; 4], 0), None);
+}
+
+#[test]
+fn count_ge_is_inclusive_and_empty_groups_are_zero() {
+    let values = [0.5, 0.65, 0.8, f32::NAN, 0.9];
+    assert_eq!(count_ge(&values, &[0.65, 0.80]), vec![3, 2]);
+    assert_eq!(count_ge(&[], &[0.65]), vec![0]);
+    assert_eq!(ge_fraction(0, 0), 0.0);
+    assert_eq!(ge_fraction(1, 4), 0.25);
+}
@@ -0,0 +1,63 @@
+use super::*;
+use tempfile::tempdir;
+
+#[test]
+fn compare_bytes_finds_first_difference() {
+    assert_eq!(compare_bytes(b"abc", b"abc"), Comparison::Identical);
+    assert_eq!(
+        compare_bytes(b"0.123456\n", b"0.123457\n"),
+        Comparison::Differs {
+            offset: 7,
+            a: b"6\n".to_vec(),
+            b: b"7\n".to_vec(),
+        }
+    );
+    // A prefix differs where the shorter one ends.
+    assert_eq!(
+        compare_bytes(b"ab", b"abcd"),
+        Comparison::Differs {
+            offset: 2,
+            a: Vec::new(),
+            b: b"cd".to_vec(),
+        }
+    );
+    match compare_bytes(&[0u8; 40], &[1u8; 40]) {
+        Comparison::Differs { offset, a, b } => {
+            assert_eq!(offset, 0);
+            assert_eq!((a.len(), b.len()), (HEXDUMP_BYTES, HEXDUMP_BYTES));
+        }
+        other => panic!("unexpected {other:?}"),
+    }
+}
+
+#[test]
+fn compare_dirs_skips_progress_and_reports_missing_files() {
+    let a = tempdir().expect("tempdir");
+    let b = tempdir().expect("tempdir");
+    for dir in [a.path(), b.path()] {
+        std::fs::create_dir_all(dir.join("explain")).expect("mkdir");
+        std::fs::write(dir.join("summary.json"), "{}\n").expect("write");
+        std::fs::write(dir.join("explain/c1.json"), "{}\n").expect("write");
+    }
+    std::fs::write(a.path().join("progress.json"), "1").expect("write");
+    std::fs::write(b.path().join("progress.json"), "2").expect("write");
+    std::fs::write(b.path().join("extra.tsv"), "x").expect("write");
+    std::fs::write(b.path().join("explain/c1.json"), "{ }\n").expect("write");
+
+    let comparisons = compare_dirs(a.path(), b.path()).expect("compare");
+    let names: Vec<&str> = comparisons.keys().map(String::as_str).collect();
+    assert_eq!(names, ["explain/c1.json", "extra.tsv", "summary.json"]);
+    assert_eq!(comparisons["extra.tsv"], Comparison::OnlyIn('b'));
+    assert_eq!(comparisons["summary.json"], Comparison::Identical);
+
+    let table = render(&comparisons);
+    assert!(table.starts_with("file\tresult\tdetail\n"));
+    assert!(table.contains("summary.json\tPASS\t.\n"));
+    assert!(table.contains("explain/c1.json\tFAIL\tfirst difference at 0x1\n"));
+    assert!(table.contains("extra.tsv\tFAIL\tonly written by run b\n"));
+    assert!(
+        table.contains("explain/c1.json at 0x00000001:\n  a: 7d 0a "),
+        "{table}"
+    );
+    assert!(table.contains("|}.|"), "{table}");
+}
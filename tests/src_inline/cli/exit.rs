@@ -0,0 +1,112 @@
+use super::*;
+use crate::panels::loader::{PanelLoadOptions, load_panels_with_options};
+use anyhow::Context;
+
+#[test]
+fn classify_by_outermost_known_error() {
+    let input = anyhow::Error::new(Stage1Error::DimensionMismatch {
+        expected_rows: 1,
+        expected_cols: 1,
+        found_rows: 2,
+        found_cols: 2,
+        hint: String::new(),
+    });
+    assert_eq!(ExitCategory::classify(&input), ExitCategory::Input);
+
+    let cache = anyhow::Error::new(Stage2Error::CacheReopen {
+        path: "kira-organelle.bin".into(),
+        mode: crate::pipeline::stage2_normalize::CacheParseMode::Full,
+        source: CacheError::InvalidMagic,
+    });
+    assert_eq!(ExitCategory::classify(&cache), ExitCategory::Input);
+
+    let panels =
+        anyhow::Error::new(PanelLoadError::Empty("dir".to_string())).context("loading panels");
+    assert_eq!(ExitCategory::classify(&panels), ExitCategory::Config);
+
+    let read_back = anyhow::Error::new(Stage7Error::from(
+        crate::pipeline::consistency::ConsistencyError::from(InputError::MissingFile {
+            path: "secretion.tsv".into(),
+        }),
+    ));
+    assert_eq!(ExitCategory::classify(&read_back), ExitCategory::Internal);
+
+    let io = anyhow::Error::new(std::io::Error::other("disk full"));
+    assert_eq!(ExitCategory::classify(&io), ExitCategory::Internal);
+    assert_eq!(ExitCategory::Internal.code(), 5);
+}
+
+#[test]
+fn cancellation_wins_over_stage_category() {
+    use crate::pipeline::stage4_axes::Stage4Error;
+
+    let stage3 = anyhow::Error::new(Stage3Error::Cancelled(Cancelled));
+    assert_eq!(ExitCategory::classify(&stage3), ExitCategory::Cancelled);
+
+    let stage4 = anyhow::Error::new(Stage4Error::from(Cancelled)).context("stage4");
+    assert_eq!(ExitCategory::classify(&stage4), ExitCategory::Cancelled);
+    assert_eq!(ExitCategory::Cancelled.code(), 130);
+}
+
+#[test]
+fn missing_panels_dir_names_stage_path_and_hint() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let missing = dir.path().join("assets").join("panels");
+    let err = load_panels_with_options(&missing, &PanelLoadOptions::default())
+        .with_context(|| StageContext::new("stage3_panels", StageAction::LoadPanels, &missing))
+        .expect_err("missing dir");
+
+    let os_error = std::fs::read_dir(&missing)
+        .expect_err("missing")
+        .to_string();
+    let chain: Vec<String> = err.chain().map(|c| c.to_string()).collect();
+    assert_eq!(
+        chain,
+        vec![
+            format!("stage3_panels: loading panels from {}", missing.display()),
+            format!("io error: {os_error}"),
+            os_error,
+        ]
+    );
+    assert_eq!(ExitCategory::classify(&err), ExitCategory::Config);
+    assert!(error_hint(&err).expect("hint").contains("assets/panels"));
+}
+
+#[test]
+fn write_errors_carry_the_artifact_path() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let out = dir.path().join("out");
+    let err = crate::report::artifact::write_artifact(&out, "panels_report.tsv", "x")
+        .map_err(Stage3Error::from)
+        .output_context("stage3_panels", &out)
+        .expect_err("no out dir");
+
+    let chain: Vec<String> = err.chain().map(|c| c.to_string()).collect();
+    assert_eq!(
+        chain[0],
+        format!("stage3_panels: writing to {}", out.display())
+    );
+    assert!(
+        chain[1].starts_with(&format!(
+            "io error writing {}: ",
+            out.join("panels_report.tsv").display()
+        )),
+        "{}",
+        chain[1]
+    );
+    assert_eq!(ExitCategory::classify(&err), ExitCategory::Internal);
+    assert_eq!(error_hint(&err), None);
+}
+
+#[test]
+fn non_write_stage_errors_do_not_read_as_writes() {
+    let out = PathBuf::from("out");
+    let err = Err::<(), _>(Stage4Error::MissingAxes {
+        axes: "SLI (panels=1, mappable_genes=0)".to_string(),
+    })
+    .output_context("stage4_axes", &out)
+    .expect_err("missing axes");
+    let chain: Vec<String> = err.chain().map(|c| c.to_string()).collect();
+    assert_eq!(chain[0], "stage4_axes: running, output in out");
+    assert_eq!(ExitCategory::classify(&err), ExitCategory::Config);
+}
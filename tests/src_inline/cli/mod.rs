@@ -31,3 +31,408 @@ fn run_mode_pipeline_parses() {
         _ => panic!("expected run command"),
     }
 }
+
+mod span_capture {
+    use std::sync::{Arc, Mutex};
+
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id};
+    use tracing::{Event, Subscriber};
+    use tracing_subscriber::layer::{Context, Layer};
+    use tracing_subscriber::registry::LookupSpan;
+
+    #[derive(Debug, Clone)]
+    pub struct SpanRecord {
+        pub name: String,
+        pub stage: Option<String>,
+        pub parent: Option<String>,
+        pub has_n_cells: bool,
+    }
+
+    #[derive(Debug, Clone)]
+    pub struct EventRecord {
+        pub message: String,
+        pub span_stage: Option<String>,
+    }
+
+    #[derive(Default, Clone)]
+    pub struct Capture {
+        pub spans: Arc<Mutex<Vec<SpanRecord>>>,
+        pub events: Arc<Mutex<Vec<EventRecord>>>,
+    }
+
+    struct StageName(String);
+
+    #[derive(Default)]
+    struct FieldVisitor {
+        stage: Option<String>,
+        message: Option<String>,
+        has_n_cells: bool,
+    }
+
+    impl Visit for FieldVisitor {
+        fn record_str(&mut self, field: &Field, value: &str) {
+            if field.name() == "stage" {
+                self.stage = Some(value.to_string());
+            }
+        }
+
+        fn record_u64(&mut self, field: &Field, _value: u64) {
+            if field.name() == "n_cells" {
+                self.has_n_cells = true;
+            }
+        }
+
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            match field.name() {
+                "message" => self.message = Some(format!("{value:?}")),
+                "stage" => self.stage = Some(format!("{value:?}").trim_matches('"').to_string()),
+                _ => {}
+            }
+        }
+    }
+
+    impl<S> Layer<S> for Capture
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+            let mut visitor = FieldVisitor::default();
+            attrs.record(&mut visitor);
+            let span = ctx.span(id).expect("span");
+            let parent = span.parent().map(|p| p.name().to_string());
+            if let Some(stage) = &visitor.stage {
+                span.extensions_mut().insert(StageName(stage.clone()));
+            }
+            self.spans.lock().expect("lock").push(SpanRecord {
+                name: attrs.metadata().name().to_string(),
+                stage: visitor.stage,
+                parent,
+                has_n_cells: visitor.has_n_cells,
+            });
+        }
+
+        fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+            let mut visitor = FieldVisitor::default();
+            event.record(&mut visitor);
+            let span_stage = ctx
+                .event_span(event)
+                .and_then(|s| s.extensions().get::<StageName>().map(|n| n.0.clone()));
+            self.events.lock().expect("lock").push(EventRecord {
+                message: visitor.message.unwrap_or_default(),
+                span_stage,
+            });
+        }
+    }
+}
+
+#[test]
+fn run_emits_stage_span_hierarchy() {
+    use tracing_subscriber::layer::SubscriberExt;
+
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("in");
+    std::fs::create_dir_all(&input).expect("mkdir");
+    std::fs::write(input.join("features.tsv"), "f1\tSEC23A\nf2\tSAR1A\n").expect("write");
+    std::fs::write(input.join("barcodes.tsv"), "c1\nc2\nc3\n").expect("write");
+    std::fs::write(
+        input.join("matrix.mtx"),
+        "%%MatrixMarket matrix coordinate integer general\n2 3 3\n1 1 4\n2 2 1\n1 3 2\n",
+    )
+    .expect("write");
+    let out = dir.path().join("out");
+
+    let cli = Cli::parse_from([
+        "kira-secretion",
+        "run",
+        "--allow-missing-axes",
+        "--input",
+        input.to_str().expect("utf8"),
+        "--out",
+        out.to_str().expect("utf8"),
+    ]);
+
+    let capture = span_capture::Capture::default();
+    let subscriber = tracing_subscriber::registry().with(capture.clone());
+    tracing::subscriber::with_default(subscriber, || cli.dispatch()).expect("run");
+
+    let spans = capture.spans.lock().expect("lock").clone();
+    assert_eq!(spans[0].name, "run");
+    assert!(spans[0].parent.is_none());
+
+    let stages: Vec<&span_capture::SpanRecord> =
+        spans.iter().filter(|s| s.name == "stage").collect();
+    let names: Vec<&str> = stages
+        .iter()
+        .map(|s| s.stage.as_deref().unwrap_or(""))
+        .collect();
+    assert_eq!(
+        names,
+        vec![
+            "stage1_load",
+            "stage2_normalize",
+            "stage3_panels",
+            "stage4_axes",
+            "stage5_scores",
+            "stage6_classify",
+            "stage7_report",
+        ]
+    );
+    for stage in &stages {
+        assert_eq!(stage.parent.as_deref(), Some("run"));
+    }
+    assert!(stages[1..].iter().all(|s| s.has_n_cells));
+
+    let events = capture.events.lock().expect("lock");
+    assert!(events.iter().any(
+        |e| e.message == "chunk processed" && e.span_stage.as_deref() == Some("stage3_panels")
+    ));
+}
+
+#[test]
+fn run_writes_success_marker_and_verify_detects_tampering() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("in");
+    std::fs::create_dir_all(&input).expect("mkdir");
+    std::fs::write(input.join("features.tsv"), "f1\tSEC23A\nf2\tSAR1A\n").expect("write");
+    std::fs::write(input.join("barcodes.tsv"), "c1\nc2\nc3\n").expect("write");
+    std::fs::write(
+        input.join("matrix.mtx"),
+        "%%MatrixMarket matrix coordinate integer general\n2 3 3\n1 1 4\n2 2 1\n1 3 2\n",
+    )
+    .expect("write");
+    let out = dir.path().join("out");
+    let out_str = out.to_str().expect("utf8");
+
+    Cli::parse_from([
+        "kira-secretion",
+        "run",
+        "--allow-missing-axes",
+        "--input",
+        input.to_str().expect("utf8"),
+        "--out",
+        out_str,
+    ])
+    .dispatch()
+    .expect("run");
+    assert!(out.join("_SUCCESS").is_file());
+
+    Cli::parse_from(["kira-secretion", "verify", "--out", out_str])
+        .dispatch()
+        .expect("verify");
+
+    let secretion = std::fs::read_to_string(out.join("secretion.tsv")).expect("read");
+    let truncated: Vec<&str> = secretion.lines().take(2).collect();
+    std::fs::write(out.join("secretion.tsv"), truncated.join("\n") + "\n").expect("write");
+
+    let err = Cli::parse_from(["kira-secretion", "verify", "--out", out_str])
+        .dispatch()
+        .expect_err("verify should fail");
+    assert!(err.to_string().contains("secretion_row_count"));
+    assert!(!out.join("_SUCCESS").exists());
+}
+
+/// Cancels `token` as soon as a stage event names `stage`.
+struct CancelAtStage {
+    stage: &'static str,
+    token: CancellationToken,
+}
+
+impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for CancelAtStage {
+    fn on_event(
+        &self,
+        event: &tracing::Event<'_>,
+        _ctx: tracing_subscriber::layer::Context<'_, S>,
+    ) {
+        struct Stage(Option<String>);
+        impl tracing::field::Visit for Stage {
+            fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+                if field.name() == "stage" {
+                    self.0 = Some(value.to_string());
+                }
+            }
+            fn record_debug(&mut self, _: &tracing::field::Field, _: &dyn std::fmt::Debug) {}
+        }
+        let mut visitor = Stage(None);
+        event.record(&mut visitor);
+        if visitor.0.as_deref() == Some(self.stage) {
+            self.token.cancel();
+        }
+    }
+}
+
+#[test]
+fn cancelled_token_stops_run_mid_stage3_without_final_artifacts() {
+    use tracing_subscriber::layer::SubscriberExt;
+
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("in");
+    std::fs::create_dir_all(&input).expect("mkdir");
+    let n_cells = 3 * crate::pipeline::cancel::CANCEL_CHECK_CELLS;
+    std::fs::write(input.join("features.tsv"), "f1\tSEC23A\nf2\tSAR1A\n").expect("write");
+    let barcodes: String = (0..n_cells).map(|c| format!("c{c}\n")).collect();
+    std::fs::write(input.join("barcodes.tsv"), barcodes).expect("write");
+    let mut matrix =
+        format!("%%MatrixMarket matrix coordinate integer general\n2 {n_cells} {n_cells}\n");
+    for cell in 1..=n_cells {
+        matrix.push_str(&format!("{} {cell} {}\n", cell % 2 + 1, cell % 5 + 1));
+    }
+    std::fs::write(input.join("matrix.mtx"), matrix).expect("write");
+    let out = dir.path().join("out");
+
+    let cli = Cli::parse_from([
+        "kira-secretion",
+        "run",
+        "--allow-missing-axes",
+        "--input",
+        input.to_str().expect("utf8"),
+        "--out",
+        out.to_str().expect("utf8"),
+    ]);
+    let token = CancellationToken::new();
+    let subscriber = tracing_subscriber::registry().with(CancelAtStage {
+        stage: "stage3_panels",
+        token: token.clone(),
+    });
+    let err =
+        tracing::subscriber::with_default(subscriber, || cli.run(&token)).expect_err("cancelled");
+    assert!(matches!(err, PipelineError::Cancelled), "{err:?}");
+    assert_eq!(err.category(), ExitCategory::Cancelled);
+
+    let marker: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string(out.join(crate::pipeline::cancel::ABORT_MARKER)).expect("marker"),
+    )
+    .expect("json");
+    assert_eq!(marker["aborted_stage"], "stage3_panels");
+    assert_eq!(marker["last_completed_stage"], "stage2_normalize");
+
+    let names: Vec<String> = std::fs::read_dir(&out)
+        .expect("read_dir")
+        .map(|e| e.expect("entry").file_name().to_string_lossy().into_owned())
+        .collect();
+    for name in &names {
+        assert!(!name.ends_with(".tmp"), "temp file left behind: {name}");
+    }
+    for absent in [
+        "_SUCCESS",
+        "panels_report.tsv",
+        "secretion.tsv",
+        "summary.json",
+    ] {
+        assert!(!names.iter().any(|n| n == absent), "{absent} in {names:?}");
+    }
+}
+
+#[test]
+fn mixed_species_run_records_two_thresholds_sets() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("in");
+    std::fs::create_dir_all(&input).expect("mkdir");
+    std::fs::write(input.join("features.tsv"), "f1\tSEC23A\nf2\tSAR1A\n").expect("write");
+    std::fs::write(input.join("barcodes.tsv"), "c1\nc2\nc3\n").expect("write");
+    std::fs::write(
+        input.join("matrix.mtx"),
+        "%%MatrixMarket matrix coordinate integer general\n2 3 3\n1 1 4\n2 2 1\n1 3 2\n",
+    )
+    .expect("write");
+    let meta = dir.path().join("meta.tsv");
+    std::fs::write(
+        &meta,
+        "cell_id\tspecies\nc1\thuman\nc2\tmouse\nc3\tHomo sapiens\n",
+    )
+    .expect("write");
+    let thresholds = dir.path().join("thresholds.json");
+    std::fs::write(
+        &thresholds,
+        r#"{"species": {"mouse": {"low_counts": 250}}}"#,
+    )
+    .expect("write");
+    let out = dir.path().join("out");
+
+    Cli::parse_from([
+        "kira-secretion",
+        "run",
+        "--allow-missing-axes",
+        "--input",
+        input.to_str().expect("utf8"),
+        "--meta",
+        meta.to_str().expect("utf8"),
+        "--thresholds",
+        thresholds.to_str().expect("utf8"),
+        "--out",
+        out.to_str().expect("utf8"),
+    ])
+    .dispatch()
+    .expect("run");
+
+    let provenance: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(out.join("provenance.json")).expect("read"))
+            .expect("json");
+    let sets = provenance["thresholds_sets"].as_array().expect("sets");
+    assert_eq!(sets.len(), 2, "{sets:?}");
+    assert_eq!(sets[0]["id"], "t0");
+    assert_eq!(sets[0]["applies_to"], serde_json::json!(["default"]));
+    assert_eq!(sets[1]["applies_to"], serde_json::json!(["mouse"]));
+    assert_eq!(sets[1]["thresholds"]["low_counts"], 250);
+
+    let classify = std::fs::read_to_string(out.join("classify.tsv")).expect("read");
+    let ids: Vec<&str> = classify
+        .lines()
+        .skip(1)
+        .map(|line| line.rsplit('\t').next().expect("column"))
+        .collect();
+    assert_eq!(ids, ["t0", "t1", "t0"]);
+}
+
+#[test]
+fn chemistry_is_inferred_or_explicit_in_summary_json() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("in");
+    std::fs::create_dir_all(&input).expect("mkdir");
+    std::fs::write(input.join("features.tsv"), "f1\tSEC23A\nf2\tSAR1A\n").expect("write");
+    std::fs::write(input.join("barcodes.tsv"), "c1\nc2\n").expect("write");
+    std::fs::write(
+        input.join("matrix.mtx"),
+        "%%MatrixMarket matrix coordinate integer general\n2 2 3\n1 1 4\n2 1 1\n1 2 2\n",
+    )
+    .expect("write");
+    let thresholds = dir.path().join("thresholds.json");
+    std::fs::write(&thresholds, r#"{"few_detected": 10}"#).expect("write");
+
+    let run = |name: &str, extra: &[&str]| {
+        let out = dir.path().join(name);
+        let mut argv = vec![
+            "kira-secretion",
+            "run",
+            "--allow-missing-axes",
+            "--input",
+            input.to_str().expect("utf8"),
+            "--out",
+            out.to_str().expect("utf8"),
+        ];
+        argv.extend_from_slice(extra);
+        Cli::parse_from(argv).dispatch().expect("run");
+        let summary: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(out.join("summary.json")).expect("read"))
+                .expect("json");
+        summary["chemistry"].clone()
+    };
+
+    // Tiny libraries look like nuclei.
+    let inferred = run("auto", &[]);
+    assert_eq!(inferred["name"], "snRNA");
+    assert_eq!(inferred["source"], "inferred");
+    assert_eq!(inferred["median_detected"], 1.5);
+    assert_eq!(inferred["median_libsize"], 3.5);
+    assert_eq!(inferred["low_counts"], 200);
+    assert_eq!(inferred["few_detected"], 150);
+
+    // An explicit chemistry wins over inference; --thresholds fields win
+    // over the chemistry defaults.
+    let thresholds = thresholds.to_str().expect("utf8");
+    let explicit = run("v3", &["--chemistry", "3p_v3", "--thresholds", thresholds]);
+    assert_eq!(explicit["name"], "3p_v3");
+    assert_eq!(explicit["source"], "explicit");
+    assert_eq!(explicit["low_counts"], 500);
+    assert_eq!(explicit["few_detected"], 10);
+}
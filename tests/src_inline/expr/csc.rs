@@ -22,6 +22,39 @@ fn build_csc_tiny() {
     assert_eq!(stats[1].detected, 2);
 }
 
+#[test]
+fn cells_by_genes_matrix_builds_the_same_csc() {
+    let dir = tempdir().expect("tempdir");
+    let path = dir.path().join("matrix.mtx");
+    // build_csc_tiny's matrix with rows and columns swapped.
+    fs::write(
+        &path,
+        "%%MatrixMarket matrix coordinate integer general\n2 3 4\n1 1 1\n1 2 2\n2 3 3\n2 1 4\n",
+    )
+    .expect("write file");
+
+    let build = |orientation| {
+        ExprCsc::from_mtx_with_limits(
+            &path,
+            3,
+            2,
+            false,
+            orientation,
+            &MtxValueLimits::default(),
+            &mut MtxValueWarnings::default(),
+        )
+    };
+    let (csc, stats) = build(MtxOrientation::CellsByGenes).expect("csc");
+    assert_eq!((csc.n_genes, csc.n_cells), (3, 2));
+    assert_eq!(csc.col_ptr, vec![0, 2, 4]);
+    assert_eq!(csc.row_idx, vec![0, 1, 0, 2]);
+    assert_eq!(csc.values, vec![1, 2, 4, 3]);
+    assert_eq!((stats[0].libsize, stats[0].detected), (3, 2));
+    assert_eq!((stats[1].libsize, stats[1].detected), (7, 2));
+
+    build(MtxOrientation::GenesByCells).expect_err("header is 2 x 3");
+}
+
 #[test]
 fn normalization_values() {
     let dir = tempdir().expect("tempdir");
@@ -43,6 +76,43 @@ fn normalization_values() {
     assert!((values[1].1 - v1).abs() < 1e-6);
 }
 
+#[test]
+fn duplicate_coordinates_are_summed_into_one_entry() {
+    let dir = tempdir().expect("tempdir");
+    let path = dir.path().join("matrix.mtx");
+    fs::write(
+        &path,
+        "%%MatrixMarket matrix coordinate integer general\n2 2 4\n1 1 2\n2 2 7\n1 1 3\n2 1 1\n",
+    )
+    .expect("write file");
+
+    let mut warnings = MtxValueWarnings::default();
+    let (csc, stats) = ExprCsc::from_mtx_with_limits(
+        &path,
+        2,
+        2,
+        false,
+        MtxOrientation::GenesByCells,
+        &MtxValueLimits::default(),
+        &mut warnings,
+    )
+    .expect("csc");
+    assert_eq!(csc.nnz, 3);
+    assert_eq!(csc.col_ptr, vec![0, 2, 3]);
+    assert_eq!(csc.row_idx, vec![0, 1, 1]);
+    assert_eq!(csc.values, vec![5, 1, 7]);
+    assert_eq!((stats[0].libsize, stats[0].detected), (6, 2));
+    assert_eq!((stats[1].libsize, stats[1].detected), (7, 1));
+    assert_eq!(warnings.duplicate_entries_merged, 1);
+}
+
+#[test]
+fn duplicate_sum_past_u32_fails_with_its_coordinate() {
+    let mut entries = vec![(0, 1, u32::MAX), (0, 1, 1)];
+    let err = merge_duplicate_entries(&mut entries).expect_err("overflow");
+    assert!(err.to_string().contains("row 2, column 1"), "{err}");
+}
+
 #[test]
 fn determinism_repeat_build() {
     let dir = tempdir().expect("tempdir");
@@ -62,3 +132,28 @@ fn determinism_repeat_build() {
     assert_eq!(stats1[0].libsize, stats2[0].libsize);
     assert_eq!(stats1[1].detected, stats2[1].detected);
 }
+
+#[test]
+fn normalization_large_libsize_matches_f64_reference() {
+    let csc = ExprCsc {
+        n_genes: 3,
+        n_cells: 1,
+        nnz: 3,
+        col_ptr: vec![0, 3],
+        row_idx: vec![0, 1, 2],
+        values: vec![u32::MAX, u32::MAX, 1],
+    };
+    let libsize: u64 = csc.values.iter().map(|v| *v as u64).sum();
+    assert_eq!(libsize, 2 * u32::MAX as u64 + 1);
+    let stats = CellStats {
+        libsize,
+        detected: 3,
+    };
+    let norm = Normalization::default();
+    let values: Vec<(u32, f32)> = csc.iter_cell_norm(0, &norm, &stats).collect();
+    for (pos, (_, got)) in values.iter().enumerate() {
+        let raw = csc.values[pos] as f64;
+        let want = (raw * norm.scale as f64 / libsize as f64).ln_1p();
+        assert!((*got as f64 - want).abs() <= want.abs() * 1e-6 + 1e-9);
+    }
+}
@@ -0,0 +1,41 @@
+use super::*;
+
+fn reference(raw: u32, libsize: u64, norm: &Normalization) -> f64 {
+    (raw as f64 * (norm.scale as f64 / (libsize as f64 + norm.epsilon as f64))).ln_1p()
+}
+
+#[test]
+fn inv_denom_exact_near_f32_integer_limit() {
+    let norm = Normalization::default();
+    for libsize in [
+        (1u64 << 24) - 1,
+        1u64 << 24,
+        (1u64 << 24) + 1,
+        (1u64 << 24) + 3,
+    ] {
+        let got = norm.apply(7, norm.inv_denom(libsize)) as f64;
+        let want = reference(7, libsize, &norm);
+        assert!(((got - want) / want).abs() < 1e-6, "libsize={libsize}");
+    }
+}
+
+#[test]
+fn inv_denom_exact_beyond_u32_limit() {
+    let norm = Normalization::default();
+    for libsize in [u32::MAX as u64, u32::MAX as u64 + 1, (1u64 << 33) + 5] {
+        let raw = u32::MAX;
+        let got = norm.apply(raw, norm.inv_denom(libsize)) as f64;
+        let want = reference(raw, libsize, &norm);
+        assert!(((got - want) / want).abs() < 1e-6, "libsize={libsize}");
+    }
+}
+
+#[test]
+fn disabled_normalization_passes_raw_counts() {
+    let norm = Normalization {
+        enabled: false,
+        ..Normalization::default()
+    };
+    assert_eq!(norm.inv_denom(1 << 30), 1.0);
+    assert_eq!(norm.apply(42, norm.inv_denom(1 << 30)), 42.0);
+}
@@ -0,0 +1,53 @@
+use super::*;
+
+fn strings(values: &[&str]) -> Vec<String> {
+    values.iter().map(|v| v.to_string()).collect()
+}
+
+#[test]
+fn header_names_and_pattern_outliers_are_detected() {
+    assert!(looks_like_header(&strings(&["Barcode", "c1", "c2"])));
+    assert!(looks_like_header(&strings(&["CELL_ID", "c1"])));
+    assert!(looks_like_header(&strings(&[
+        "cell",
+        "AAACCTGAGAAACCAT-1",
+        "s2_AAACCTGAGAAACCTA-1",
+    ])));
+
+    // Non-barcode names throughout, or a lone line, give no signal.
+    assert!(!looks_like_header(&strings(&["c1", "c2", "c3"])));
+    assert!(!looks_like_header(&strings(&["cellA"])));
+    // One odd barcode among several is not enough when another one is odd too.
+    assert!(!looks_like_header(&strings(&[
+        "cell",
+        "AAACCTGAGAAACCAT-1",
+        "other",
+    ])));
+    assert!(!looks_like_header(&strings(&[
+        "AAACCTGAGAAACCAT-1",
+        "AAACCTGAGAAACCTA-1",
+    ])));
+}
+
+#[test]
+fn forced_flag_overrides_detection() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("barcodes.tsv");
+    std::fs::write(&path, "barcode\nc1\nc2\n").expect("write");
+
+    let detected = read_barcodes(&path, None).expect("read");
+    assert_eq!(detected.barcodes, ["c1", "c2"]);
+    assert_eq!(detected.header.as_deref(), Some("barcode"));
+
+    let kept = read_barcodes(&path, Some(false)).expect("read");
+    assert_eq!(kept.barcodes, ["barcode", "c1", "c2"]);
+    assert_eq!(kept.header, None);
+
+    std::fs::write(&path, "id\nc1\nc2\n").expect("write");
+    let forced = read_barcodes(&path, Some(true)).expect("read");
+    assert_eq!(forced.barcodes, ["c1", "c2"]);
+    assert_eq!(forced.header.as_deref(), Some("id"));
+
+    std::fs::write(&path, "barcode\n").expect("write");
+    assert!(read_barcodes(&path, None).is_err(), "header only");
+}
@@ -3,9 +3,12 @@ use std::fs;
 use tempfile::tempdir;
 
 fn write_shared_cache(path: &Path, tamper_crc: bool) {
+    write_shared_cache_with_col_ptr(path, tamper_crc, [0, 2, 3]);
+}
+
+fn write_shared_cache_with_col_ptr(path: &Path, tamper_crc: bool, col_ptr: [u64; 3]) {
     let genes = ["G1", "G2", "G3"];
     let barcodes = ["C1", "C2"];
-    let col_ptr = [0u64, 2, 3];
     let row_idx = [0u32, 2, 1];
     let values = [5u32, 1, 7];
 
@@ -120,6 +123,24 @@ fn shared_cache_bad_crc_rejected() {
     assert!(format!("{err}").contains("CRC64"));
 }
 
+#[test]
+fn unchecked_load_rejects_col_ptr_past_nnz() {
+    let dir = tempdir().expect("tempdir");
+    let path = dir.path().join("kira-organelle.bin");
+    for (col_ptr, message) in [
+        ([0, 2, 9], "col_ptr[n_cells] must equal nnz"),
+        ([0, 9, 3], "col_ptr must be monotonic"),
+        ([1, 2, 3], "col_ptr[0] must be 0"),
+    ] {
+        write_shared_cache_with_col_ptr(&path, false, col_ptr);
+        let err = mmap_shared_cache_unchecked(&path).expect_err("expected error");
+        assert!(
+            matches!(&err, CacheError::InvalidFormat(m) if m == message),
+            "{col_ptr:?}: {err}"
+        );
+    }
+}
+
 #[test]
 fn cache_roundtrip_deterministic() {
     let dir = tempdir().expect("tempdir");
@@ -160,3 +181,87 @@ fn cache_roundtrip_deterministic() {
     assert_eq!(stats2[0].libsize, stats[0].libsize);
     assert_eq!(stats2[0].detected, stats[0].detected);
 }
+
+fn le_bytes<T: Copy, const N: usize>(values: &[T], to_le: fn(T) -> [u8; N]) -> Vec<u8> {
+    values.iter().flat_map(|&v| to_le(v)).collect()
+}
+
+/// `n_cells` cells of rows 0, 1, 2 each, as col_ptr and row_idx bytes.
+fn csc_bytes(n_cells: usize) -> (Vec<u8>, Vec<u32>) {
+    let col_ptr: Vec<u64> = (0..=n_cells as u64).map(|c| c * 3).collect();
+    let rows = (0..n_cells).flat_map(|_| [0u32, 1, 2]).collect();
+    (le_bytes(&col_ptr, u64::to_le_bytes), rows)
+}
+
+#[test]
+fn csc_check_names_the_first_bad_entry_across_chunks() {
+    let n_cells = 3 * CSC_CHECK_CHUNK_CELLS + 5;
+    let (col_ptr, mut rows) = csc_bytes(n_cells);
+    validate_csc(&col_ptr, &le_bytes(&rows, u32::to_le_bytes), 3).expect("valid");
+
+    // A repeat in the third chunk and an out-of-range row in the second:
+    // the earlier one is reported whichever task finishes first.
+    let late = 2 * CSC_CHECK_CHUNK_CELLS + 1;
+    rows[late * 3 + 2] = 1;
+    let early = CSC_CHECK_CHUNK_CELLS + 7;
+    rows[early * 3 + 1] = 3;
+    let err = validate_csc(&col_ptr, &le_bytes(&rows, u32::to_le_bytes), 3).expect_err("bad");
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "invalid cache format: row_idx out of bounds at cell {early}, entry {}: \
+             row 3, n_genes 3",
+            early * 3 + 1
+        )
+    );
+
+    rows[early * 3 + 1] = 1;
+    let err = validate_csc(&col_ptr, &le_bytes(&rows, u32::to_le_bytes), 3).expect_err("bad");
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "invalid cache format: row_idx must be strictly increasing per column at cell \
+             {late}, entry {}: row 1 after 1",
+            late * 3 + 2
+        )
+    );
+}
+
+#[test]
+fn csc_check_reads_unaligned_sections() {
+    let (col_ptr, rows) = csc_bytes(10);
+    let mut col_buf = vec![0u8];
+    col_buf.extend_from_slice(&col_ptr);
+    let mut row_buf = vec![0u8; 3];
+    row_buf.extend(le_bytes(&rows, u32::to_le_bytes));
+    validate_csc(&col_buf[1..], &row_buf[3..], 3).expect("valid");
+    let err = validate_csc(&col_buf[1..], &row_buf[3..], 2).expect_err("row 2 of 2 genes");
+    assert!(err.to_string().contains("at cell 0, entry 2"), "{err}");
+}
+
+#[test]
+fn sampled_csc_check_covers_the_ends_and_stops_early() {
+    let n_cells = 1000;
+    let (col_ptr, mut rows) = csc_bytes(n_cells);
+    // 100 samples: every 10th cell and the last one.
+    rows[(n_cells - 1) * 3] = 9;
+    let err = validate_csc_sampled(&col_ptr, &le_bytes(&rows, u32::to_le_bytes), 3, 100)
+        .expect_err("last cell");
+    assert!(err.to_string().contains("at cell 999"), "{err}");
+
+    rows[(n_cells - 1) * 3] = 0;
+    rows[5 * 3] = 9;
+    let bytes = le_bytes(&rows, u32::to_le_bytes);
+    validate_csc_sampled(&col_ptr, &bytes, 3, 100).expect("cell 5 is not sampled");
+    validate_csc(&col_ptr, &bytes, 3).expect_err("full check finds it");
+    validate_csc_sampled(&col_ptr, &bytes, 3, n_cells).expect_err("every cell sampled");
+}
+
+#[test]
+fn quick_check_maps_the_cache() {
+    let dir = tempdir().expect("tempdir");
+    let path = dir.path().join("kira-organelle.bin");
+    write_shared_cache(&path, false);
+    let mapped = mmap_shared_cache_sampled(&path).expect("shared cache");
+    assert_eq!(mapped.nnz, 3);
+}
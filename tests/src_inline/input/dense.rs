@@ -0,0 +1,94 @@
+use super::*;
+use tempfile::tempdir;
+
+fn write(dir: &Path, name: &str, text: &str) -> std::path::PathBuf {
+    let path = dir.join(name);
+    std::fs::write(&path, text).expect("write");
+    path
+}
+
+#[test]
+fn dense_table_becomes_csc_with_cell_stats() {
+    let dir = tempdir().expect("tempdir");
+    let path = write(
+        dir.path(),
+        "plate.tsv",
+        "gene\tA1\tA2\tA3\nSEC23A\t0\t4\t1\n\nSAR1A\t2\t0\t0\r\nGAPDH\t5\t1\t0\n",
+    );
+    let dense = read_dense_matrix(&path).expect("read");
+    assert_eq!(dense.barcodes, ["A1", "A2", "A3"]);
+    let symbols: Vec<&str> = dense.genes.iter().map(|g| g.symbol.as_str()).collect();
+    assert_eq!(symbols, ["SEC23A", "SAR1A", "GAPDH"]);
+    assert_eq!(dense.genes[1].id, "SAR1A");
+
+    let expr = &dense.expr;
+    assert_eq!((expr.n_genes, expr.n_cells, expr.nnz), (3, 3, 5));
+    assert_eq!(expr.col_ptr, [0, 2, 4, 5]);
+    assert_eq!(expr.row_idx, [1, 2, 0, 2, 0]);
+    assert_eq!(expr.values, [2, 5, 4, 1, 1]);
+    let stats: Vec<(u64, u32)> = dense
+        .cell_stats
+        .iter()
+        .map(|s| (s.libsize, s.detected))
+        .collect();
+    assert_eq!(stats, [(7, 2), (5, 2), (1, 1)]);
+}
+
+#[test]
+fn csv_tables_split_on_commas_and_drop_quotes() {
+    let dir = tempdir().expect("tempdir");
+    let path = write(
+        dir.path(),
+        "plate.csv",
+        "\"\",\"c 1\",\"c2\"\n\"SEC23A\",3,0\n",
+    );
+    let dense = read_dense_matrix(&path).expect("read");
+    assert_eq!(dense.barcodes, ["c 1", "c2"]);
+    assert_eq!(dense.genes[0].symbol, "SEC23A");
+    assert_eq!(dense.expr.values, [3]);
+}
+
+#[test]
+fn non_integer_counts_name_the_line_and_cell() {
+    let dir = tempdir().expect("tempdir");
+    for bad in ["1.5", "-2", "NA", ""] {
+        let path = write(
+            dir.path(),
+            "plate.tsv",
+            &format!("gene\tA1\tA2\nSEC23A\t1\t0\nSAR1A\t0\t{bad}\n"),
+        );
+        let err = read_dense_matrix(&path).expect_err(bad);
+        match &err {
+            InputError::InvalidTsvField { line, column, .. } => {
+                assert_eq!((*line, column.as_str()), (3, "A2"), "{bad}");
+            }
+            other => panic!("unexpected error for {bad:?}: {other}"),
+        }
+        assert!(err.to_string().contains("non-negative integer"), "{err}");
+    }
+}
+
+#[test]
+fn ragged_rows_and_empty_tables_are_rejected() {
+    let dir = tempdir().expect("tempdir");
+    let path = write(dir.path(), "plate.tsv", "gene\tA1\tA2\nSEC23A\t1\n");
+    match read_dense_matrix(&path).expect_err("short row") {
+        InputError::InvalidTsvRow { line, reason, .. } => {
+            assert_eq!(line, 2);
+            assert!(reason.contains("1 values"), "{reason}");
+        }
+        other => panic!("unexpected error: {other}"),
+    }
+
+    let path = write(dir.path(), "plate.tsv", "gene\tA1\tA2\n");
+    assert!(matches!(
+        read_dense_matrix(&path),
+        Err(InputError::EmptyFile { .. })
+    ));
+
+    let path = write(dir.path(), "plate.tsv", "gene\tA1\t\nSEC23A\t1\t2\n");
+    assert!(matches!(
+        read_dense_matrix(&path),
+        Err(InputError::EmptyBarcode { line: 1, .. })
+    ));
+}
@@ -42,3 +42,94 @@ fn finds_exact_cache_preferred() {
     let got = find_shared_cache_file(dir.path(), None).expect("find");
     assert_eq!(got, Some(dir.path().join("kira-organelle.bin")));
 }
+
+#[test]
+fn matrix_file_input_resolves_to_its_directory() {
+    let dir = tempdir().expect("tempdir");
+    for name in ["matrix.mtx", "ABC_matrix.mtx.gz"] {
+        let path = dir.path().join(name);
+        std::fs::write(&path, "x").expect("write");
+        assert_eq!(resolve_input_dir(&path).expect("resolve"), dir.path());
+    }
+    assert_eq!(resolve_input_dir(dir.path()).expect("dir"), dir.path());
+}
+
+#[test]
+fn other_file_input_lists_tenx_files_next_to_it() {
+    let dir = tempdir().expect("tempdir");
+    for name in ["features.tsv.gz", "barcodes.tsv.gz", "notes.txt"] {
+        std::fs::write(dir.path().join(name), "x").expect("write");
+    }
+    let err = resolve_input_dir(&dir.path().join("features.tsv.gz")).expect_err("file");
+    match &err {
+        InputError::InputIsFile { candidates, .. } => {
+            assert_eq!(candidates, &["barcodes.tsv.gz", "features.tsv.gz"]);
+        }
+        other => panic!("unexpected error: {other}"),
+    }
+    assert!(err.to_string().contains("expected a directory"), "{err}");
+}
+
+#[test]
+fn h5ad_input_is_recognized_by_extension() {
+    let dir = tempdir().expect("tempdir");
+    let path = dir.path().join("pbmc.H5AD");
+    std::fs::write(&path, "x").expect("write");
+    assert!(is_h5ad(&path));
+    assert!(!is_h5ad(&dir.path().join("missing.h5ad")));
+    assert!(!is_h5ad(dir.path()));
+}
+
+#[test]
+fn cache_directory_resolves_to_the_cache_in_it() {
+    let dir = tempdir().expect("tempdir");
+    std::fs::write(dir.path().join("ABC.kira-organelle.bin"), "x").expect("write");
+    assert_eq!(
+        resolve_cache_path(dir.path()).expect("resolve"),
+        dir.path().join("ABC.kira-organelle.bin")
+    );
+
+    let empty = tempdir().expect("tempdir");
+    std::fs::write(empty.path().join("matrix.mtx"), "x").expect("write");
+    match resolve_cache_path(empty.path()).expect_err("no cache") {
+        InputError::CacheIsDirectory { candidates, .. } => {
+            assert_eq!(candidates, ["matrix.mtx"]);
+        }
+        other => panic!("unexpected error: {other}"),
+    }
+}
+
+#[test]
+fn missing_barcodes_error_names_the_expected_path() {
+    let dir = tempdir().expect("tempdir");
+    std::fs::write(dir.path().join("S1_matrix.mtx"), "x").expect("write");
+    std::fs::write(dir.path().join("S1_features.tsv"), "x").expect("write");
+
+    let err = detect_10x_dir(dir.path()).expect_err("no barcodes");
+    assert!(matches!(err, InputError::MissingFile { .. }), "{err:?}");
+    let expected = dir.path().join("S1_barcodes.tsv[.gz]");
+    assert!(
+        err.to_string().contains(&expected.display().to_string()),
+        "{err}"
+    );
+}
+
+#[test]
+fn features_and_genes_together_are_ambiguous() {
+    let dir = tempdir().expect("tempdir");
+    for name in ["matrix.mtx", "features.tsv", "genes.tsv", "barcodes.tsv"] {
+        std::fs::write(dir.path().join(name), "x").expect("write");
+    }
+
+    match detect_10x_dir(dir.path()).expect_err("ambiguous") {
+        InputError::LayoutAmbiguous {
+            dir: got,
+            candidates,
+            ..
+        } => {
+            assert_eq!(got, dir.path());
+            assert_eq!(candidates, vec!["features.tsv", "genes.tsv"]);
+        }
+        other => panic!("unexpected error: {other:?}"),
+    }
+}
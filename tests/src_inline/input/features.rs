@@ -8,7 +8,13 @@ fn duplicate_gene_symbols_first_wins() {
     let path = dir.path().join("features.tsv");
     fs::write(&path, "f1\tG1\nf2\tG1\nf3\tG2\n").expect("write file");
 
-    let index = read_features(&path).expect("read features");
+    let FeaturesFile {
+        gene_index: index,
+        delimiter,
+        single_column_rows,
+    } = read_features(&path, false).expect("read features");
+    assert_eq!(delimiter, Delimiter::Tab);
+    assert_eq!(single_column_rows, 0);
     assert_eq!(index.rows.len(), 3);
     assert_eq!(index.duplicates.len(), 1);
     assert_eq!(index.duplicates[0].symbol, "G1");
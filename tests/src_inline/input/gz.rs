@@ -0,0 +1,47 @@
+use super::*;
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use std::io::Write;
+
+fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut enc = GzEncoder::new(Vec::new(), Compression::fast());
+    enc.write_all(data).unwrap();
+    enc.finish().unwrap()
+}
+
+#[test]
+fn pipelined_reader_matches_serial_decoder() {
+    // Spans several chunks with a ragged tail.
+    let data: Vec<u8> = (0..(2 * GZ_CHUNK_BYTES + 12_345))
+        .map(|i| (i % 251) as u8)
+        .collect();
+    let compressed = gzip(&data);
+
+    let mut out = Vec::new();
+    PipelinedGzReader::new(std::io::Cursor::new(compressed.clone()))
+        .read_to_end(&mut out)
+        .unwrap();
+    assert_eq!(out, data);
+
+    let mut serial = Vec::new();
+    flate2::read::GzDecoder::new(std::io::Cursor::new(compressed))
+        .read_to_end(&mut serial)
+        .unwrap();
+    assert_eq!(out, serial);
+}
+
+#[test]
+fn corrupt_stream_surfaces_error_and_early_drop_does_not_hang() {
+    let mut compressed = gzip(&vec![7u8; 4 * GZ_CHUNK_BYTES]);
+    let mid = compressed.len() / 2;
+    compressed.truncate(mid);
+    let mut out = Vec::new();
+    let err = PipelinedGzReader::new(std::io::Cursor::new(compressed)).read_to_end(&mut out);
+    assert!(err.is_err());
+
+    let big = gzip(&vec![1u8; (GZ_QUEUE_DEPTH + 4) * GZ_CHUNK_BYTES]);
+    let mut reader = PipelinedGzReader::new(std::io::Cursor::new(big));
+    let mut line = String::new();
+    let _ = reader.read_line(&mut line);
+    drop(reader);
+}
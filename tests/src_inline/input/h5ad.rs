@@ -0,0 +1,122 @@
+use super::*;
+
+fn names(prefix: &str, n: usize) -> Vec<String> {
+    (0..n).map(|i| format!("{prefix}{i}")).collect()
+}
+
+/// Cells × genes:
+///   c0: g0=2, g2=5
+///   c1: (empty)
+///   c2: g1=1, g2=3
+fn matrix(encoding: H5adEncoding) -> AnnDataMatrix {
+    let (indptr, indices, counts) = match encoding {
+        H5adEncoding::Csr => (vec![0, 2, 2, 4], vec![2, 0, 1, 2], vec![5, 2, 1, 3]),
+        H5adEncoding::Csc => (vec![0, 1, 2, 4], vec![0, 2, 0, 2], vec![2, 1, 5, 3]),
+        H5adEncoding::Dense => (Vec::new(), Vec::new(), vec![2, 0, 5, 0, 0, 0, 0, 1, 3]),
+    };
+    AnnDataMatrix {
+        encoding,
+        n_obs: 3,
+        n_vars: 3,
+        indptr,
+        indices,
+        counts,
+    }
+}
+
+#[test]
+fn float_count_takes_whole_values_only() {
+    assert_eq!(float_count(0.0), Some(0));
+    assert_eq!(float_count(3.0), Some(3));
+    assert_eq!(float_count(2.999_999_9), Some(3));
+    assert_eq!(float_count(f64::from(u32::MAX)), Some(u32::MAX));
+    for bad in [0.5, 1.25, -1.0, f64::NAN, f64::INFINITY, 1e12] {
+        assert_eq!(float_count(bad), None, "{bad}");
+    }
+}
+
+#[test]
+fn not_counts_hint_points_at_the_counts_layer_when_there_is_one() {
+    assert!(not_counts_hint(H5adLayer::X, true).contains("--layer counts"));
+    assert!(!not_counts_hint(H5adLayer::X, false).contains("--layer counts"));
+    assert!(not_counts_hint(H5adLayer::Counts, true).contains("layers/counts"));
+}
+
+#[test]
+fn every_encoding_adapts_to_the_same_csc() {
+    for encoding in [H5adEncoding::Csr, H5adEncoding::Csc, H5adEncoding::Dense] {
+        let dense = to_dense_matrix(
+            Path::new("pbmc.h5ad"),
+            matrix(encoding),
+            names("g", 3),
+            names("c", 3),
+        )
+        .expect("adapt");
+        assert_eq!(dense.barcodes, ["c0", "c1", "c2"], "{encoding:?}");
+        assert_eq!(dense.genes[2].id, "g2", "{encoding:?}");
+        assert_eq!(dense.genes[2].symbol, "g2", "{encoding:?}");
+        let expr = &dense.expr;
+        assert_eq!((expr.n_genes, expr.n_cells, expr.nnz), (3, 3, 4));
+        assert_eq!(expr.col_ptr, [0, 2, 2, 4], "{encoding:?}");
+        assert_eq!(expr.row_idx, [0, 2, 1, 2], "{encoding:?}");
+        assert_eq!(expr.values, [2, 5, 1, 3], "{encoding:?}");
+        let stats: Vec<(u64, u32)> = dense
+            .cell_stats
+            .iter()
+            .map(|s| (s.libsize, s.detected))
+            .collect();
+        assert_eq!(stats, [(7, 2), (0, 0), (4, 2)], "{encoding:?}");
+    }
+}
+
+#[test]
+fn stored_zeros_are_dropped_and_repeated_genes_summed() {
+    let matrix = AnnDataMatrix {
+        encoding: H5adEncoding::Csr,
+        n_obs: 1,
+        n_vars: 3,
+        indptr: vec![0, 4],
+        indices: vec![1, 0, 1, 2],
+        counts: vec![2, 0, 3, 4],
+    };
+    let dense =
+        to_dense_matrix(Path::new("a.h5ad"), matrix, names("g", 3), names("c", 1)).expect("adapt");
+    assert_eq!(dense.expr.row_idx, [1, 2]);
+    assert_eq!(dense.expr.values, [5, 4]);
+    assert_eq!(
+        (dense.cell_stats[0].libsize, dense.cell_stats[0].detected),
+        (9, 2)
+    );
+}
+
+#[test]
+fn mismatched_names_and_bad_indices_are_reported() {
+    let path = Path::new("a.h5ad");
+    let err = to_dense_matrix(
+        path,
+        matrix(H5adEncoding::Csr),
+        names("g", 2),
+        names("c", 3),
+    )
+    .expect_err("short var");
+    assert!(err.to_string().contains("var has 2 names"), "{err}");
+
+    let mut bad = matrix(H5adEncoding::Csr);
+    bad.indices[1] = 7;
+    let err =
+        to_dense_matrix(path, bad, names("g", 3), names("c", 3)).expect_err("index out of range");
+    assert!(err.to_string().contains("index 7 is out of range"), "{err}");
+
+    let mut bad = matrix(H5adEncoding::Csc);
+    bad.indptr = vec![0, 1, 2];
+    let err = to_dense_matrix(path, bad, names("g", 3), names("c", 3)).expect_err("short indptr");
+    assert!(matches!(err, InputError::InvalidH5ad { .. }), "{err}");
+}
+
+#[cfg(not(feature = "h5ad"))]
+#[test]
+fn default_builds_point_at_the_h5ad_feature() {
+    let err = read_h5ad(Path::new("pbmc.h5ad"), H5adLayer::X).expect_err("no h5ad feature");
+    assert!(matches!(err, InputError::H5adUnsupported { .. }), "{err}");
+    assert!(err.to_string().contains("--features h5ad"), "{err}");
+}
@@ -0,0 +1,33 @@
+use super::*;
+use tempfile::tempdir;
+
+#[test]
+fn unreadable_meta_error_names_the_file() {
+    let dir = tempdir().expect("tempdir");
+    // A directory opens but cannot be read as a file.
+    let meta = dir.path().join("meta.tsv");
+    std::fs::create_dir(&meta).expect("mkdir");
+
+    let err = read_meta(&meta, &["AAAC-1".to_string()]).expect_err("unreadable");
+    assert!(matches!(err, InputError::Io { .. }), "{err:?}");
+    assert!(
+        err.to_string().contains(&meta.display().to_string()),
+        "{err}"
+    );
+}
+
+#[test]
+fn missing_cell_id_column_names_the_file() {
+    let dir = tempdir().expect("tempdir");
+    let meta = dir.path().join("meta.tsv");
+    std::fs::write(&meta, "barcode\tsample_id\nAAAC-1\ts1\n").expect("write");
+
+    let err = read_meta(&meta, &["AAAC-1".to_string()]).expect_err("no cell_id");
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "{}: meta file missing required column cell_id",
+            meta.display()
+        )
+    );
+}
@@ -1,4 +1,5 @@
 use super::*;
+use crate::expr::csc::ExprCsc;
 use std::fs;
 use tempfile::tempdir;
 
@@ -17,3 +18,294 @@ fn parse_header_and_dims() {
     assert_eq!(header.n_cols, 3);
     assert_eq!(header.nnz, 4);
 }
+
+#[test]
+fn coordinate_parser_orders_by_column_and_validates() {
+    let text =
+        "%%MatrixMarket matrix coordinate integer general\n% c\n3 2 3\n2 2 5\n1 1 4\n3 1 1\n";
+    let (header, entries) = parse_coordinate_entries(text.as_bytes()).expect("parse");
+    assert_eq!((header.n_rows, header.n_cols, header.nnz), (3, 2, 3));
+    assert_eq!(header.field, MtxField::Integer);
+    assert_eq!(entries, vec![(0, 0, 4), (0, 2, 1), (1, 1, 5)]);
+
+    let fractional = "%%MatrixMarket matrix coordinate integer general\n1 1 1\n1 1 0.5\n";
+    let err = parse_coordinate_entries(fractional.as_bytes()).expect_err("fraction");
+    assert!(err.to_string().contains("`integer`"), "{err}");
+    let short = "%%MatrixMarket matrix coordinate integer general\n2 2 2\n1 1 1\n";
+    assert!(parse_coordinate_entries(short.as_bytes()).is_err());
+    let oob = "%%MatrixMarket matrix coordinate integer general\n2 2 1\n3 1 1\n";
+    assert!(parse_coordinate_entries(oob.as_bytes()).is_err());
+}
+
+#[test]
+fn overflowing_value_reports_line_and_token() {
+    let text =
+        "%%MatrixMarket matrix coordinate integer general\n% c\n2 1 2\n1 1 3\n2 1 4294967296\n";
+    let err = parse_coordinate_entries(text.as_bytes()).expect_err("overflow");
+    match &err {
+        InputError::InvalidMtxValue {
+            location, token, ..
+        } => {
+            assert_eq!(location, "line 5");
+            assert_eq!(token, "4294967296");
+        }
+        other => panic!("unexpected error: {other}"),
+    }
+    assert!(err.to_string().contains("u32"), "{err}");
+}
+
+#[test]
+fn value_above_hard_cap_fails_with_raw_count_guidance() {
+    let text = "%%MatrixMarket matrix coordinate integer general\n1 1 1\n1 1 5000\n";
+    let limits = MtxValueLimits {
+        max_value: 1_000,
+        warn_value: 100,
+    };
+    let err = parse_coordinate_entries_with_limits(
+        text.as_bytes(),
+        &limits,
+        &mut MtxValueWarnings::default(),
+    )
+    .expect_err("cap");
+    let msg = err.to_string();
+    assert!(msg.contains("5000") && msg.contains("line 3"), "{msg}");
+    assert!(msg.contains("may not contain raw counts"), "{msg}");
+
+    let at_cap = "%%MatrixMarket matrix coordinate integer general\n1 1 1\n1 1 1000\n";
+    assert!(
+        parse_coordinate_entries_with_limits(
+            at_cap.as_bytes(),
+            &limits,
+            &mut MtxValueWarnings::default()
+        )
+        .is_ok()
+    );
+}
+
+#[test]
+fn values_above_warn_threshold_are_counted() {
+    let dir = tempdir().expect("tempdir");
+    let path = dir.path().join("matrix.mtx");
+    fs::write(
+        &path,
+        "%%MatrixMarket matrix coordinate integer general\n3 1 3\n1 1 50\n2 1 300\n3 1 200\n",
+    )
+    .expect("write file");
+    let limits = MtxValueLimits {
+        max_value: 1_000,
+        warn_value: 100,
+    };
+    let mut warnings = MtxValueWarnings::default();
+    let (_, entries) = read_entries_with_limits(&path, &limits, &mut warnings).expect("read");
+    assert_eq!(entries.len(), 3);
+    assert_eq!(
+        warnings,
+        MtxValueWarnings {
+            n_above_warn: 2,
+            max_seen: 300,
+            first_location: Some("line 4".to_string()),
+            ..MtxValueWarnings::default()
+        }
+    );
+}
+
+#[test]
+fn banner_tolerates_bom_spacing_and_case() {
+    let dir = tempdir().expect("tempdir");
+    for (name, banner) in [
+        (
+            "bom",
+            "\u{feff}%%MatrixMarket matrix coordinate integer general",
+        ),
+        (
+            "spaces",
+            "%%MatrixMarket  matrix\tcoordinate   integer  general  ",
+        ),
+        ("upper", "%%MATRIXMARKET MATRIX COORDINATE INTEGER GENERAL"),
+    ] {
+        let path = dir.path().join(format!("{name}.mtx"));
+        fs::write(&path, format!("{banner}\n2 2 2\n1 1 3\n2 2 4\n")).expect("write file");
+        let header = read_header(&path).unwrap_or_else(|e| panic!("{name}: {e}"));
+        assert_eq!(
+            (header.n_rows, header.n_cols, header.nnz),
+            (2, 2, 2),
+            "{name}"
+        );
+        let (_, entries) = read_entries(&path).unwrap_or_else(|e| panic!("{name}: {e}"));
+        assert_eq!(entries, vec![(0, 0, 3), (1, 1, 4)], "{name}");
+    }
+}
+
+#[test]
+fn symmetric_banners_are_rejected_with_banner_text() {
+    for symmetry in ["symmetric", "skew-symmetric", "hermitian"] {
+        let banner = format!("%%MatrixMarket matrix coordinate integer {symmetry}");
+        let text = format!("{banner}\n2 2 1\n1 1 1\n");
+        let err = parse_coordinate_entries(text.as_bytes()).expect_err(symmetry);
+        let msg = err.to_string();
+        assert!(msg.contains("not supported"), "{msg}");
+        assert!(msg.contains(&banner), "{msg}");
+    }
+    let err = parse_banner("%%MatrixMarket tensor coordinate integer general").expect_err("kind");
+    assert!(err.to_string().contains("tensor"), "{err}");
+}
+
+#[test]
+fn real_matrix_rounds_to_the_integer_matrix_structure() {
+    let integer =
+        "%%MatrixMarket matrix coordinate integer general\n3 2 4\n1 1 1\n2 1 2\n3 2 3\n1 2 4\n";
+    let real = "%%MatrixMarket matrix coordinate real general\n3 2 4\n1 1 1.0\n2 1 1.6\n3 2 3\n1 2 3.5e0\n";
+    let (int_header, int_entries) = parse_coordinate_entries(integer.as_bytes()).expect("integer");
+
+    let mut warnings = MtxValueWarnings::default();
+    let (header, entries) = parse_coordinate_entries_with_limits(
+        real.as_bytes(),
+        &MtxValueLimits::default(),
+        &mut warnings,
+    )
+    .expect("real");
+    assert_eq!(header.field, MtxField::Real);
+    assert_eq!(header.nnz, int_header.nnz);
+    assert_eq!(entries, int_entries);
+    assert_eq!(warnings.n_rounded, 2);
+    assert_eq!(warnings.first_rounded_location.as_deref(), Some("line 4"));
+
+    let negative = "%%MatrixMarket matrix coordinate real general\n1 1 1\n1 1 -0.5\n";
+    assert!(parse_coordinate_entries(negative.as_bytes()).is_err());
+}
+
+#[test]
+fn real_matrix_file_builds_the_same_csc_as_the_integer_one() {
+    let dir = tempdir().expect("tempdir");
+    let integer = dir.path().join("integer.mtx");
+    let real = dir.path().join("real.mtx");
+    fs::write(
+        &integer,
+        "%%MatrixMarket matrix coordinate integer general\n3 2 4\n1 1 1\n2 1 2\n3 2 3\n1 2 4\n",
+    )
+    .expect("write integer");
+    fs::write(
+        &real,
+        "%%MatrixMarket matrix coordinate real general\n3 2 4\n1 1 0.9\n2 1 2.2\n3 2 3\n1 2 4.0\n",
+    )
+    .expect("write real");
+    assert_eq!(read_header(&real).expect("header").field, MtxField::Real);
+
+    let (int_csc, int_stats) = ExprCsc::from_mtx(&integer, 3, 2, false).expect("integer csc");
+    let mut warnings = MtxValueWarnings::default();
+    let (csc, stats) = ExprCsc::from_mtx_with_limits(
+        &real,
+        3,
+        2,
+        false,
+        MtxOrientation::GenesByCells,
+        &MtxValueLimits::default(),
+        &mut warnings,
+    )
+    .expect("real csc");
+    assert_eq!(csc.col_ptr, int_csc.col_ptr);
+    assert_eq!(csc.row_idx, int_csc.row_idx);
+    assert_eq!(csc.values, int_csc.values);
+    let totals = |stats: &[crate::expr::csc::CellStats]| -> Vec<(u64, u32)> {
+        stats.iter().map(|s| (s.libsize, s.detected)).collect()
+    };
+    assert_eq!(totals(&stats), totals(&int_stats));
+    assert_eq!(warnings.n_rounded, 2);
+}
+
+#[test]
+fn real_entries_rounding_to_zero_are_dropped() {
+    let dir = tempdir().expect("tempdir");
+    let path = dir.path().join("real.mtx");
+    fs::write(
+        &path,
+        "%%MatrixMarket matrix coordinate real general\n3 2 4\n1 1 2.0\n2 1 0.3\n3 1 0.5\n1 2 0.4\n",
+    )
+    .expect("write real");
+
+    let mut warnings = MtxValueWarnings::default();
+    let (header, entries) = parse_coordinate_entries_with_limits(
+        fs::read(&path).expect("read").as_slice(),
+        &MtxValueLimits::default(),
+        &mut warnings,
+    )
+    .expect("entries");
+    assert_eq!(header.nnz, 2);
+    assert_eq!(entries, vec![(0, 0, 2), (0, 2, 1)]);
+    assert_eq!(warnings.n_rounded, 3);
+    assert_eq!(warnings.n_rounded_to_zero, 2);
+    assert_eq!(warnings.first_rounded_location.as_deref(), Some("line 4"));
+
+    let mut warnings = MtxValueWarnings::default();
+    let (csc, stats) = ExprCsc::from_mtx_with_limits(
+        &path,
+        3,
+        2,
+        false,
+        MtxOrientation::GenesByCells,
+        &MtxValueLimits::default(),
+        &mut warnings,
+    )
+    .expect("real csc");
+    assert_eq!(csc.nnz, 2);
+    assert_eq!(csc.col_ptr, vec![0, 2, 2]);
+    assert_eq!(stats[0].detected, 2);
+    assert_eq!(stats[0].libsize, 3);
+    assert_eq!(stats[1].detected, 0);
+    assert_eq!(warnings.n_rounded_to_zero, 2);
+}
+
+#[test]
+fn cancelled_parse_stops_at_an_entry_checkpoint() {
+    use crate::pipeline::cancel::{self, CANCEL_CHECK_ENTRIES, CancellationToken};
+
+    let matrix = |nnz: usize| {
+        let mut text = format!("%%MatrixMarket matrix coordinate integer general\n1 {nnz} {nnz}\n");
+        for col in 1..=nnz {
+            text.push_str(&format!("1 {col} 1\n"));
+        }
+        text
+    };
+    let token = CancellationToken::new();
+    token.cancel();
+    let _guard = cancel::enter(&token);
+
+    // Below one interval there is no check to see the token.
+    parse_coordinate_entries(matrix(CANCEL_CHECK_ENTRIES - 1).as_bytes()).expect("no checkpoint");
+    let err = parse_coordinate_entries(matrix(CANCEL_CHECK_ENTRIES + 1).as_bytes())
+        .expect_err("cancelled");
+    assert!(matches!(err, InputError::Cancelled(_)), "{err}");
+}
+
+#[test]
+fn pattern_entries_count_as_one() {
+    let text = "%%MatrixMarket matrix coordinate pattern general\n2 2 2\n2 2\n1 1\n";
+    let mut warnings = MtxValueWarnings::default();
+    let (header, entries) = parse_coordinate_entries_with_limits(
+        text.as_bytes(),
+        &MtxValueLimits::default(),
+        &mut warnings,
+    )
+    .expect("pattern");
+    assert_eq!(header.field, MtxField::Pattern);
+    assert_eq!(entries, vec![(0, 0, 1), (1, 1, 1)]);
+    assert_eq!(warnings, MtxValueWarnings::default());
+
+    let with_value = "%%MatrixMarket matrix coordinate pattern general\n1 1 1\n1 1 3\n";
+    assert!(parse_coordinate_entries(with_value.as_bytes()).is_err());
+    let missing_value = "%%MatrixMarket matrix coordinate integer general\n1 1 1\n1 1\n";
+    assert!(parse_coordinate_entries(missing_value.as_bytes()).is_err());
+}
+
+#[test]
+fn count_nnz_lines_counts_entries_not_declared_nnz() {
+    let dir = tempdir().expect("tempdir");
+    let path = dir.path().join("matrix.mtx");
+    fs::write(
+        &path,
+        "%%MatrixMarket matrix coordinate integer general\n2 2 3\n1 1 1\n% c\n2 2 1\n",
+    )
+    .expect("write file");
+    assert_eq!(read_header(&path).expect("header").nnz, 3);
+    assert_eq!(count_nnz_lines(&path).expect("count"), 2);
+}
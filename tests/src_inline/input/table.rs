@@ -0,0 +1,126 @@
+use super::*;
+use std::fs;
+use tempfile::tempdir;
+
+const COLUMNS: [&str; 4] = ["cell_id", "libsize", "score", "label"];
+const ROWS: [[&str; 4]; 3] = [
+    ["c1", "10", "0.250000", "a"],
+    ["c2", "20", "nan", "b"],
+    ["c3", "30", "1.000000", "c"],
+];
+
+/// Writes the fixture with columns in `order` (indices into `COLUMNS`) and
+/// `extra` unknown columns appended.
+fn write_table(path: &Path, order: &[usize], extra: usize) {
+    let mut header: Vec<String> = order.iter().map(|&i| COLUMNS[i].to_string()).collect();
+    header.extend((0..extra).map(|i| format!("future_{i}")));
+    let mut text = header.join("\t");
+    text.push('\n');
+    for row in ROWS {
+        let mut fields: Vec<String> = order.iter().map(|&i| row[i].to_string()).collect();
+        fields.extend((0..extra).map(|i| format!("x{i}")));
+        text.push_str(&fields.join("\t"));
+        text.push('\n');
+    }
+    fs::write(path, text).expect("write");
+}
+
+fn read_back(path: &Path) -> Vec<(String, u64, f32, String)> {
+    let mut reader = TsvReader::open(path).expect("open");
+    let cols = reader.require(&COLUMNS).expect("columns");
+    let mut out = Vec::new();
+    while let Some(row) = reader.next_row().expect("row") {
+        out.push((
+            row.str(cols[0]).expect("cell").to_string(),
+            row.u64(cols[1]).expect("libsize"),
+            row.f32_or_nan(cols[2]).expect("score"),
+            row.str(cols[3]).expect("label").to_string(),
+        ));
+    }
+    out
+}
+
+#[test]
+fn reordered_and_extended_headers_read_the_same() {
+    let dir = tempdir().expect("tempdir");
+    let baseline_path = dir.path().join("baseline.tsv");
+    write_table(&baseline_path, &[0, 1, 2, 3], 0);
+    let baseline = read_back(&baseline_path);
+    assert_eq!(baseline.len(), 3);
+    assert!(baseline[1].2.is_nan());
+
+    // Every permutation of the four columns, with 0-2 unknown columns appended.
+    let mut seen = 0;
+    for a in 0..4 {
+        for b in (0..4).filter(|&b| b != a) {
+            for c in (0..4).filter(|&c| c != a && c != b) {
+                let d = 6 - a - b - c;
+                let path = dir.path().join(format!("p{seen}.tsv"));
+                write_table(&path, &[a, b, c, d], seen % 3);
+                let got = read_back(&path);
+                for (g, w) in got.iter().zip(&baseline) {
+                    assert_eq!((&g.0, g.1, &g.3), (&w.0, w.1, &w.3), "order {a}{b}{c}{d}");
+                    assert!(g.2 == w.2 || (g.2.is_nan() && w.2.is_nan()));
+                }
+                seen += 1;
+            }
+        }
+    }
+    assert_eq!(seen, 24);
+}
+
+#[test]
+fn missing_columns_list_expected_and_found() {
+    let dir = tempdir().expect("tempdir");
+    let path = dir.path().join("t.tsv");
+    write_table(&path, &[3, 0], 1);
+    let reader = TsvReader::open(&path).expect("open");
+    let err = reader.require(&COLUMNS).expect_err("missing columns");
+    let InputError::MissingColumns {
+        missing,
+        expected,
+        found,
+        ..
+    } = &err
+    else {
+        panic!("unexpected error: {err}");
+    };
+    assert_eq!(missing, &["libsize", "score"]);
+    assert_eq!(expected, &COLUMNS);
+    assert_eq!(found, &["label", "cell_id", "future_0"]);
+    let message = err.to_string();
+    assert!(
+        message.contains("t.tsv missing required column(s) libsize, score"),
+        "{message}"
+    );
+    assert!(
+        message.ends_with(
+            "expected [cell_id, libsize, score, label], found [label, cell_id, future_0]"
+        ),
+        "{message}"
+    );
+}
+
+#[test]
+fn field_errors_name_file_line_and_column() {
+    let dir = tempdir().expect("tempdir");
+    let path = dir.path().join("t.tsv");
+    fs::write(&path, "cell_id\tlibsize\tscore\nc1\tmany\t0.5\nc2\t3\n").expect("write");
+    let mut reader = TsvReader::open(&path).expect("open");
+    let cols = reader.require(&["libsize", "score"]).expect("columns");
+
+    let row = reader.next_row().expect("row").expect("some");
+    let err = row.u64(cols[0]).expect_err("not a number").to_string();
+    assert!(
+        err.ends_with("t.tsv line 2, column libsize: not a number: \"many\""),
+        "{err}"
+    );
+
+    let row = reader.next_row().expect("row").expect("some");
+    assert_eq!(row.u64(cols[0]).expect("libsize"), 3);
+    let err = row.f32_or_nan(cols[1]).expect_err("short row").to_string();
+    assert!(
+        err.ends_with("t.tsv line 3, column score: row has 2 fields, the header has 3"),
+        "{err}"
+    );
+}
@@ -0,0 +1,56 @@
+use super::*;
+use tempfile::tempdir;
+
+fn barcodes() -> Vec<String> {
+    ["c1", "c2", "c3"].iter().map(|b| b.to_string()).collect()
+}
+
+#[test]
+fn sidecar_sits_next_to_the_cache() {
+    assert_eq!(
+        upstream_sidecar_path(Path::new("/d/ABC.kira-organelle.bin")),
+        Path::new("/d/ABC.kira-organelle.cells.tsv")
+    );
+    assert_eq!(
+        upstream_sidecar_path(Path::new("kira-organelle.bin")),
+        Path::new("kira-organelle.cells.tsv")
+    );
+}
+
+#[test]
+fn columns_follow_cache_barcode_order() {
+    let dir = tempdir().expect("tempdir");
+    let path = dir.path().join("kira-organelle.cells.tsv");
+    std::fs::write(
+        &path,
+        "barcode\tmito_score\ter_score\nc3\t0.3\t3\nc1\t0.1\t1\nc2\tNaN\t2\n",
+    )
+    .expect("write");
+    let upstream = read_upstream_columns(&path, &barcodes()).expect("read");
+    assert_eq!(upstream.source, path);
+    let names: Vec<String> = upstream.columns.iter().map(|c| c.output_name()).collect();
+    assert_eq!(names, ["upstream_mito_score", "upstream_er_score"]);
+    assert_eq!(upstream.columns[1].values, [1.0, 2.0, 3.0]);
+    let mito = &upstream.columns[0].values;
+    assert_eq!((mito[0], mito[2]), (0.1, 0.3));
+    assert!(mito[1].is_nan());
+}
+
+#[test]
+fn bad_columns_are_skipped() {
+    let dir = tempdir().expect("tempdir");
+    let path = dir.path().join("kira-organelle.cells.tsv");
+    std::fs::write(
+        &path,
+        "barcode\tok\tshort\tlabel\tok\t\n\
+         c1\t1\t1\tA\t1\t1\n\
+         c2\t2\t2\tB\t2\t2\n\
+         c3\t3\n\
+         c9\t9\t9\tZ\t9\t9\n",
+    )
+    .expect("write");
+    let upstream = read_upstream_columns(&path, &barcodes()).expect("read");
+    let names: Vec<&str> = upstream.columns.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, ["ok"]);
+    assert_eq!(upstream.columns[0].values, [1.0, 2.0, 3.0]);
+}
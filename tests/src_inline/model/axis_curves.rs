@@ -0,0 +1,86 @@
+use super::*;
+
+fn curve(points: &[(f32, f32)]) -> CalibrationCurve {
+    CalibrationCurve {
+        breakpoints: points.to_vec(),
+    }
+}
+
+fn load(toml: &str) -> Result<AxisCurves, AxisCurvesError> {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("curves.toml");
+    std::fs::write(&path, toml).expect("write");
+    AxisCurves::load(&path)
+}
+
+#[test]
+fn interpolates_at_between_and_beyond_breakpoints() {
+    let c = curve(&[(-1.0, 0.0), (0.0, 0.1), (2.0, 0.5), (10.0, 0.9)]);
+    // At breakpoints.
+    for (raw, value) in [(-1.0, 0.0), (0.0, 0.1), (2.0, 0.5), (10.0, 0.9)] {
+        assert_eq!(c.apply(raw), value, "at {raw}");
+    }
+    // Between breakpoints.
+    assert!((c.apply(1.0) - 0.3).abs() < 1e-6);
+    assert!((c.apply(6.0) - 0.7).abs() < 1e-6);
+    assert!((c.apply(-0.5) - 0.05).abs() < 1e-6);
+    // Beyond the ends: clamped to the end values.
+    assert_eq!(c.apply(-5.0), 0.0);
+    assert_eq!(c.apply(1e9), 0.9);
+    assert_eq!(c.apply(f32::INFINITY), 0.9);
+    assert!(c.apply(f32::NAN).is_nan());
+}
+
+#[test]
+fn loads_per_axis_tables() {
+    let curves = load(
+        "[SIA]\nbreakpoints = [[0, 0.0], [2.0, 0.5], [8.0, 1.0]]\n\n[GDI]\nbreakpoints = [[0.0, 0.0], [1.0, 1.0]]\n",
+    )
+    .expect("load");
+    assert_eq!(curves.curves.len(), 2);
+    assert_eq!(curves.get("SIA").expect("SIA").apply(5.0), 0.75);
+    assert!(curves.get("SLI").is_none());
+    let json = serde_json::to_value(&curves).expect("json");
+    assert_eq!(
+        json["GDI"]["breakpoints"],
+        serde_json::json!([[0.0, 0.0], [1.0, 1.0]])
+    );
+}
+
+#[test]
+fn rejects_non_monotone_or_uncovering_curves() {
+    for (toml, needle) in [
+        ("[SIA]\nbreakpoints = [[0.0, 0.0]]\n", "at least two"),
+        (
+            "[SIA]\nbreakpoints = [[0.0, 0.0], [0.0, 1.0]]\n",
+            "must increase",
+        ),
+        (
+            "[SIA]\nbreakpoints = [[0.0, 0.6], [1.0, 0.4]]\n",
+            "must not decrease",
+        ),
+        (
+            "[SIA]\nbreakpoints = [[1.0, 0.0], [2.0, 1.0]]\n",
+            "at or below 0",
+        ),
+        (
+            "[SIA]\nbreakpoints = [[0.0, 0.0], [1.0, 1.5]]\n",
+            "outside [0, 1]",
+        ),
+        (
+            "[SIA]\nbreakpoints = [[0.0, 0.0], [nan, 1.0]]\n",
+            "not finite",
+        ),
+        (
+            "[EEB]\nbreakpoints = [[0.0, 0.0], [1.0, 1.0]]\n",
+            "unknown axis",
+        ),
+        (
+            "[SIA]\npoints = [[0.0, 0.0], [1.0, 1.0]]\n",
+            "unknown field",
+        ),
+    ] {
+        let err = load(toml).expect_err(toml).to_string();
+        assert!(err.contains(needle), "{toml}: {err}");
+    }
+}
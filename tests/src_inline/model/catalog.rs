@@ -0,0 +1,109 @@
+use super::*;
+use crate::panels::defs::KNOWN_AXES;
+use crate::pipeline::stage4_axes::CORE_AXES;
+use crate::pipeline::stage5_scores::{Terms, weight_set};
+
+#[test]
+fn panel_axis_tags_round_trip_and_match_serde() {
+    for axis in PanelAxis::ALL {
+        assert_eq!(PanelAxis::from_tag(axis.tag()), Some(axis));
+        assert_eq!(
+            serde_json::to_string(&axis).unwrap(),
+            format!("\"{}\"", axis.tag())
+        );
+    }
+    assert_eq!(PanelAxis::ALL.map(PanelAxis::tag), PANEL_AXIS_TAGS);
+    assert_eq!(PANEL_AXIS_TAGS, KNOWN_AXES);
+    assert_eq!(PanelAxis::from_tag("NONE"), None);
+    assert_eq!(PanelAxis::from_tag("sia"), None);
+}
+
+#[test]
+fn every_panel_axis_feeds_exactly_one_axis() {
+    for tag in PanelAxis::ALL {
+        let owners: Vec<&str> = AXES
+            .iter()
+            .filter(|axis| axis.panel_axes.contains(&tag))
+            .map(|axis| axis.id)
+            .collect();
+        assert_eq!(owners.len(), 1, "{} feeds {owners:?}", tag.tag());
+    }
+}
+
+#[test]
+fn required_panel_axes_are_the_core_axes() {
+    let required: Vec<&str> = AXES
+        .iter()
+        .filter(|axis| axis.required)
+        .flat_map(|axis| axis.panel_axes.iter().map(|a| a.tag()))
+        .collect();
+    assert_eq!(required, REQUIRED_PANEL_AXIS_TAGS);
+    assert_eq!(REQUIRED_PANEL_AXIS_TAGS, CORE_AXES);
+    assert!(!CORE_AXES.contains(&"APCI"));
+}
+
+#[test]
+fn stage5_reads_driver_names_from_the_composite_terms() {
+    let catalog = composites(&WeightsDefault::default());
+    for composite in &catalog {
+        for set in &composite.weight_sets {
+            let names = Terms::new(weight_set(&catalog, composite.id, set.name)).names;
+            let expected: Vec<&str> = set.terms.iter().map(WeightTerm::driver_name).collect();
+            assert_eq!(names, expected, "{} {}", composite.id, set.name);
+        }
+    }
+    let names = |id, set| Terms::new(weight_set(&catalog, id, set)).names;
+    assert_eq!(
+        names("OII", "default"),
+        ["SIA", "EEB_POS", "SLI", "MEI", "ECMI", "GDI"]
+    );
+    assert_eq!(
+        names("IAI", "with_apci"),
+        ["MEI", "GDI", "APCI", "SIA", "EEB_POS"]
+    );
+    assert_eq!(names("IAI", "no_apci"), ["MEI", "GDI", "SIA", "EEB_POS"]);
+    assert_eq!(names("ESI", "default"), ["ECMI", "MEI", "EEB_POS", "SLI"]);
+}
+
+#[test]
+fn composite_terms_carry_the_default_weights() {
+    let weights = WeightsDefault::default();
+    let composites = composites(&weights);
+    let ids: Vec<&str> = composites.iter().map(|c| c.id).collect();
+    assert_eq!(ids, ["OII", "IAI", "ESI"]);
+    for composite in &composites {
+        for set in &composite.weight_sets {
+            let total: f32 = set.terms.iter().map(|t| t.weight).sum();
+            assert!((total - 1.0).abs() < 1e-6, "{} {}", composite.id, set.name);
+            for term in &set.terms {
+                let axis = term.input.trim_start_matches("pos(").trim_end_matches(')');
+                assert!(AXES.iter().any(|a| a.id == axis), "{}", term.input);
+            }
+        }
+    }
+    assert_eq!(
+        composites[0].weight_sets[0].terms[0].weight,
+        weights.oii.sia
+    );
+    assert_eq!(
+        composites[2].weight_sets[0].formula(),
+        "clamp01(0.34 * ECMI + 0.26 * MEI + 0.2 * pos(EEB) + 0.2 * SLI)"
+    );
+}
+
+#[test]
+fn catalog_renders_every_axis_and_composite() {
+    let catalog = Catalog::current();
+    let markdown = catalog.to_markdown();
+    let json: serde_json::Value = serde_json::to_value(&catalog).unwrap();
+    for axis in &AXES {
+        assert!(
+            markdown.contains(&format!("| {} |", axis.id)),
+            "{}",
+            axis.id
+        );
+    }
+    assert!(markdown.contains("## IAI (Immune activation)"));
+    assert_eq!(json["axes"][1]["panel_axes"][1], "EEB_DEGRADE");
+    assert_eq!(json["composites"][1]["weight_sets"][1]["name"], "no_apci");
+}
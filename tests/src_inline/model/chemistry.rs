@@ -0,0 +1,49 @@
+use super::*;
+
+#[test]
+fn inference_boundaries() {
+    let cases = [
+        // (median detected, median libsize, expected)
+        (799.0, 1499.0, Chemistry::SnRna),
+        (800.0, 1499.0, Chemistry::ThreePrimeV2),
+        (799.0, 1500.0, Chemistry::ThreePrimeV2),
+        (2000.0, 4999.0, Chemistry::ThreePrimeV2),
+        (2000.0, 5000.0, Chemistry::ThreePrimeV3),
+        (100.0, 20_000.0, Chemistry::ThreePrimeV3),
+        (f32::NAN, f32::NAN, Chemistry::ThreePrimeV3),
+    ];
+    for (detected, libsize, expected) in cases {
+        assert_eq!(
+            infer_chemistry(detected, libsize),
+            expected,
+            "{detected} genes, {libsize} UMIs"
+        );
+    }
+}
+
+#[test]
+fn explicit_chemistry_overrides_inference() {
+    let detected = [200.0, 300.0, 400.0];
+    let libsize = [500.0, 600.0, 700.0];
+
+    let inferred = ChemistryChoice::resolve(None, &detected, &libsize);
+    assert_eq!(inferred.chemistry, Chemistry::SnRna);
+    assert_eq!(inferred.source, ChemistrySource::Inferred);
+    assert_eq!(inferred.median_detected, 300.0);
+    assert_eq!(inferred.median_libsize, 600.0);
+
+    let explicit = ChemistryChoice::resolve(Some(Chemistry::Flex), &detected, &libsize);
+    assert_eq!(explicit.chemistry, Chemistry::Flex);
+    assert_eq!(explicit.source, ChemistrySource::Explicit);
+    assert_eq!(explicit.median_detected, 300.0);
+}
+
+#[test]
+fn three_prime_v3_keeps_the_original_defaults() {
+    let defaults = crate::model::thresholds::Thresholds::default();
+    assert_eq!(Chemistry::ThreePrimeV3.low_counts(), defaults.low_counts);
+    assert_eq!(
+        Chemistry::ThreePrimeV3.few_detected(),
+        defaults.few_detected
+    );
+}
@@ -4,7 +4,7 @@ use super::*;
 fn drivers_tie_break() {
     let ids = vec!["B".to_string(), "A".to_string(), "C".to_string()];
     let vals = vec![1.0, 1.0, 0.5];
-    let drivers = top_k_panels(&ids, &vals, 2);
+    let drivers = top_k_panels(&ids, &vals, 2, ZeroDrivers::Drop);
     assert_eq!(drivers[0].panel_id, "A");
     assert_eq!(drivers[1].panel_id, "B");
 }
@@ -13,6 +13,69 @@ fn drivers_tie_break() {
 fn components_tie_break() {
     let names = vec!["B", "A", "C"];
     let vals = vec![0.5, 0.5, 0.4];
-    let out = top_k_components(&names, &vals, 2);
+    let out = top_k_components(&names, &vals, 2, ZeroDrivers::Drop);
     assert_eq!(out, "A=0.5000,B=0.5000");
 }
+
+#[test]
+fn zero_contributions_are_dropped_before_truncation() {
+    let ids = vec!["A".to_string(), "B".to_string(), "C".to_string()];
+    let vals = vec![0.0, 2.0, -1.0];
+    let drivers = top_k_panels(&ids, &vals, 3, ZeroDrivers::Drop);
+    assert_eq!(format_drivers(&drivers), "B=2.0000");
+
+    let kept = top_k_panels(&ids, &vals, 3, ZeroDrivers::Keep);
+    assert_eq!(format_drivers(&kept), "B=2.0000,A=0.0000,C=-1.0000");
+
+    let none = top_k_panels(&ids, &[0.0, 0.0, f32::NAN], 3, ZeroDrivers::Drop);
+    assert_eq!(format_drivers(&none), ".");
+}
+
+#[test]
+fn components_fall_back_to_dot() {
+    let names = vec!["SIA", "SLI", "GDI"];
+    assert_eq!(
+        top_k_components(&names, &[0.0, 0.3, 0.0], 3, ZeroDrivers::Drop),
+        "SLI=0.3000"
+    );
+    assert_eq!(
+        top_k_components(&names, &[0.0, 0.0, f32::NAN], 3, ZeroDrivers::Drop),
+        "."
+    );
+    assert_eq!(
+        top_k_components(&names, &[0.0, 0.3, 0.0], 3, ZeroDrivers::Keep),
+        "SLI=0.3000,GDI=0.0000,SIA=0.0000"
+    );
+}
+
+#[test]
+fn degrade_drivers_filter_on_raw_sums() {
+    let export = vec!["E1".to_string(), "E2".to_string()];
+    let degrade = vec!["D1".to_string(), "D2".to_string()];
+    let (e, d) = top_k_eeb_drivers(
+        &export,
+        &[0.0, 1.5],
+        &degrade,
+        &[0.8, 0.0],
+        2,
+        ZeroDrivers::Drop,
+    );
+    assert_eq!(
+        format_eeb_drivers(&e, &d),
+        "EXPORT:E2=1.5000;DEGRADE:D1=-0.8000"
+    );
+}
+
+#[test]
+fn concentration_spans_single_driver_to_even_spread() {
+    assert_eq!(contribution_concentration(&[0.0, 0.6, 0.0, 0.0]), 1.0);
+    assert!(contribution_concentration(&[0.2, 0.2, 0.2, 0.2]).abs() < 1e-6);
+    let skewed = contribution_concentration(&[0.5, 0.1, 0.1, 0.0]);
+    assert!(skewed > 0.0 && skewed < 1.0, "{skewed}");
+    // NaN and negative contributions carry no weight.
+    assert_eq!(
+        contribution_concentration(&[0.3, f32::NAN, -0.2]),
+        contribution_concentration(&[0.3, 0.0, 0.0])
+    );
+    assert!(contribution_concentration(&[0.0, 0.0, f32::NAN]).is_nan());
+}
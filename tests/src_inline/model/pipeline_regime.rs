@@ -0,0 +1,116 @@
+use super::*;
+
+fn labels(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+    pairs
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+fn none() -> BTreeMap<String, String> {
+    BTreeMap::new()
+}
+
+#[test]
+fn defaults_use_internal_names_in_contract_order() {
+    let labels = RegimeLabels::default();
+    assert_eq!(
+        labels.ordered_labels(),
+        [
+            "HomeostaticSecretion",
+            "AdaptiveSecretion",
+            "InflammatorySecretion",
+            "HypersecretoryState",
+            "SecretoryCollapse",
+            "Unclassified",
+        ]
+    );
+    let v2 = labels.with_contract(PipelineContract::V2);
+    let names: Vec<&str> = PipelineRegime::ALL.iter().map(|r| r.as_str()).collect();
+    assert_eq!(v2.ordered_labels(), names);
+    for regime in PipelineRegime::ALL {
+        assert_eq!(PipelineRegime::parse(regime.as_str()), Some(regime));
+    }
+}
+
+#[test]
+fn label_targets_must_be_unique_and_names_known() {
+    let err = RegimeLabels::from_parts(
+        &labels(&[("SecretoryCollapse", "Unclassified")]),
+        &[],
+        &none(),
+    )
+    .expect_err("collides with the default Unclassified label");
+    assert!(err.contains("'Unclassified'"), "{err}");
+
+    let err = RegimeLabels::from_parts(
+        &labels(&[
+            ("AdaptiveSecretion", "Active"),
+            ("HypersecretoryState", "Active"),
+        ]),
+        &[],
+        &none(),
+    )
+    .expect_err("duplicate target");
+    assert!(err.contains("more than one regime"), "{err}");
+
+    let err = RegimeLabels::from_parts(&labels(&[("Collapse", "Quiescent")]), &[], &none())
+        .expect_err("unknown name");
+    assert!(err.contains("unknown regime 'Collapse'"), "{err}");
+
+    let err = RegimeLabels::from_parts(&labels(&[("Unclassified", "a\tb")]), &[], &none())
+        .expect_err("tab in label");
+    assert!(err.contains("tabs"), "{err}");
+
+    let swapped = RegimeLabels::from_parts(
+        &labels(&[
+            ("SecretoryCollapse", "Unclassified"),
+            ("Unclassified", "SecretoryCollapse"),
+        ]),
+        &["Unclassified".to_string()],
+        &none(),
+    )
+    .expect("a permutation is unique");
+    assert_eq!(swapped.ordered_labels()[0], "SecretoryCollapse");
+}
+
+#[test]
+fn newer_regimes_fold_only_under_version_1() {
+    let default = RegimeLabels::default();
+    assert_eq!(default.contract(), PipelineContract::V1);
+    assert_eq!(
+        default.resolve(PipelineRegime::LysosomalSecretion),
+        PipelineRegime::AdaptiveSecretion
+    );
+    assert_eq!(
+        default.resolve(PipelineRegime::SecretoryCollapse),
+        PipelineRegime::SecretoryCollapse
+    );
+
+    let fold = labels(&[("LysosomalSecretion", "InflammatorySecretion")]);
+    let custom = RegimeLabels::from_parts(&none(), &[], &fold).expect("fold");
+    assert_eq!(
+        custom.resolve(PipelineRegime::LysosomalSecretion),
+        PipelineRegime::InflammatorySecretion
+    );
+    let v2 = custom.with_contract(PipelineContract::V2);
+    assert_eq!(
+        v2.resolve(PipelineRegime::LysosomalSecretion),
+        PipelineRegime::LysosomalSecretion
+    );
+
+    let err = RegimeLabels::from_parts(
+        &none(),
+        &[],
+        &labels(&[("AdaptiveSecretion", "Unclassified")]),
+    )
+    .expect_err("v1 regimes never fold");
+    assert!(err.contains("already a version 1 regime"), "{err}");
+    let err = RegimeLabels::from_parts(
+        &none(),
+        &[],
+        &labels(&[("LysosomalSecretion", "LysosomalSecretion")]),
+    )
+    .expect_err("target must be v1");
+    assert!(err.contains("must fold into a version 1 regime"), "{err}");
+}
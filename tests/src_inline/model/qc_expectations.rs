@@ -0,0 +1,48 @@
+use super::*;
+
+#[test]
+fn parses_regime_and_qc_ranges() {
+    let expectations = QcExpectations::parse(
+        r#"
+[regimes]
+HomeostaticSecretion = { min = 0.40, max = 0.70 }
+SecretoryCollapse = { max = 0.15 }
+
+[qc]
+low_confidence_fraction = { max = 0.30 }
+"#,
+    )
+    .expect("valid");
+    assert_eq!(
+        expectations.regimes,
+        [
+            (
+                PipelineRegime::HomeostaticSecretion,
+                FractionRange {
+                    min: Some(0.40),
+                    max: Some(0.70)
+                }
+            ),
+            (
+                PipelineRegime::SecretoryCollapse,
+                FractionRange {
+                    min: None,
+                    max: Some(0.15)
+                }
+            ),
+        ]
+    );
+    assert_eq!(expectations.qc.len(), 1);
+    assert!(QcExpectations::parse("").expect("empty").is_empty());
+}
+
+#[test]
+fn rejects_bad_ranges_and_unknown_names() {
+    let err = |text: &str| QcExpectations::parse(text).expect_err(text);
+    assert!(err("[regimes]\nUnclassified = { min = 0.5, max = 0.2 }").contains("greater than max"));
+    assert!(err("[regimes]\nUnclassified = {}").contains("set min, max or both"));
+    assert!(err("[regimes]\nUnclassified = { max = 15 }").contains("outside [0, 1]"));
+    assert!(err("[regimes]\nCollapse = { max = 0.1 }").contains("unknown regime 'Collapse'"));
+    assert!(err("[qc]\nlow_conf = { max = 0.1 }").contains("unknown qc metric 'low_conf'"));
+    assert!(err("[qc]\nlow_confidence_fraction = { maximum = 0.1 }").contains("maximum"));
+}
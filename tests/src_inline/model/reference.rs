@@ -0,0 +1,124 @@
+use super::*;
+use tempfile::tempdir;
+
+fn values(n: usize) -> Vec<AxisValues> {
+    (0..n)
+        .map(|i| {
+            let x = i as f32 / (n - 1) as f32;
+            AxisValues {
+                sia: x,
+                eeb: 2.0 * x - 1.0,
+                sli: x,
+                mei: x,
+                ecmi: x,
+                apci: f32::NAN,
+                gdi: 1.0 - x,
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn grid_and_percentile_interpolation() {
+    let column: Vec<f32> = (0..=100).map(|i| i as f32 / 100.0).collect();
+    let grid = quantile_grid(&column);
+    assert_eq!(grid.len(), REFERENCE_GRID_POINTS);
+    assert!((grid[50] - 0.5).abs() < 1e-6);
+
+    assert!((reference_percentile(&grid, 0.25) - 25.0).abs() < 1e-3);
+    assert!((reference_percentile(&grid, 0.255) - 25.5).abs() < 1e-2);
+    assert_eq!(reference_percentile(&grid, -1.0), 0.0);
+    assert_eq!(reference_percentile(&grid, 2.0), 100.0);
+    assert!(reference_percentile(&grid, f32::NAN).is_nan());
+    assert!(reference_percentile(&[], 0.5).is_nan());
+
+    // A constant reference maps its value to the middle of the tied run.
+    let flat = quantile_grid(&[0.3; 10]);
+    assert_eq!(reference_percentile(&flat, 0.3), 50.0);
+    assert!(quantile_grid(&[f32::NAN, f32::NAN]).is_empty());
+}
+
+#[test]
+fn save_load_roundtrip_and_absent_axis() {
+    let v = values(11);
+    let oii: Vec<f32> = (0..11).map(|i| i as f32 / 10.0).collect();
+    let reference = CohortReference::build(&v, &oii, &oii, &oii, "abc", "g1");
+    assert!(reference.axis_grid("APCI").is_empty());
+    assert_eq!(reference.axis_grid("EEB")[0], -1.0);
+
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("ref.json");
+    reference.save(&path).unwrap();
+    let loaded = CohortReference::load(&path).unwrap();
+    assert_eq!(loaded.format_version, REFERENCE_FORMAT_VERSION);
+    assert_eq!(loaded.n_cells, 11);
+    assert_eq!(
+        loaded.composite_grid("OII"),
+        reference.composite_grid("OII")
+    );
+    assert!(loaded.check_panel_set(&path, "abc").is_ok());
+    assert!(matches!(
+        loaded.check_panel_set(&path, "def"),
+        Err(ReferenceError::PanelSetMismatch { .. })
+    ));
+}
+
+#[test]
+fn load_rejects_other_versions_and_bad_grids() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("ref.json");
+    std::fs::write(&path, r#"{"format_version": 99}"#).unwrap();
+    assert!(matches!(
+        CohortReference::load(&path),
+        Err(ReferenceError::Version { found: 99, .. })
+    ));
+
+    std::fs::write(
+        &path,
+        r#"{"format_version": 1, "tool_version": "x", "panel_set_hash": "h", "n_cells": 1,
+            "axes": {"SIA": [0.1, 0.2]}, "composites": {}}"#,
+    )
+    .unwrap();
+    assert!(matches!(
+        CohortReference::load(&path),
+        Err(ReferenceError::Format { .. })
+    ));
+}
+
+#[test]
+fn gene_set_mismatch_refuses_unless_allowed() {
+    use crate::input::features::{FeatureRow, build_gene_index};
+
+    let single = |symbol: &str| {
+        build_gene_index(vec![FeatureRow {
+            id: "ENSG0001".to_string(),
+            symbol: symbol.to_string(),
+        }])
+        .identity_hash()
+    };
+    let (a, b) = (single("IL6"), single("IL-6"));
+    assert_ne!(a, b);
+    assert_eq!(a, single("IL6"));
+
+    let v = values(3);
+    let oii = vec![0.0, 0.5, 1.0];
+    let reference = CohortReference::build(&v, &oii, &oii, &oii, "abc", &a);
+    let path = Path::new("ref.json");
+    assert!(matches!(
+        reference.check_gene_set(path, &a, false),
+        Ok(None)
+    ));
+    let err = reference
+        .check_gene_set(path, &b, false)
+        .expect_err("mismatch");
+    assert!(matches!(err, ReferenceError::GeneSetMismatch { .. }));
+    assert!(err.to_string().contains(&b), "{err}");
+    assert!(matches!(
+        reference.check_gene_set(path, &b, true),
+        Ok(Some(ReferenceError::GeneSetMismatch { .. }))
+    ));
+
+    let mut legacy = reference.clone();
+    legacy.gene_set_hash.clear();
+    assert!(matches!(legacy.check_gene_set(path, &b, false), Ok(None)));
+}
@@ -0,0 +1,16 @@
+use super::*;
+
+#[test]
+fn labels_use_a_safe_character_set() {
+    let label = RunLabel::new("PBMC 3k #2 @lab:v1.0", Some("donor A, day 7")).expect("valid");
+    assert_eq!(label.label, "PBMC 3k #2 @lab:v1.0");
+    assert_eq!(label.description.as_deref(), Some("donor A, day 7"));
+
+    for bad in ["", " lead", "trail ", "tab\there", "a/b", "quote\"", "ünï"] {
+        assert!(RunLabel::new(bad, None).is_err(), "{bad:?}");
+    }
+    assert!(RunLabel::new(&"x".repeat(MAX_LABEL_LEN + 1), None).is_err());
+
+    let err = RunLabel::new("ok", Some("two\nlines")).expect_err("newline");
+    assert!(err.to_string().contains("--description"), "{err}");
+}
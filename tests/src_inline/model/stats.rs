@@ -0,0 +1,143 @@
+use super::*;
+
+const POLICIES: [NanPolicy; 3] = [NanPolicy::Skip, NanPolicy::Propagate, NanPolicy::Low];
+
+/// Seeded pseudo-random values in [0, 1) with about one NaN in eight.
+fn sample(seed: u64, n: usize) -> Vec<f32> {
+    let mut state = seed | 1;
+    (0..n)
+        .map(|_| {
+            state = state
+                .wrapping_mul(6_364_136_223_846_793_005)
+                .wrapping_add(1_442_695_040_888_963_407);
+            if state >> 61 == 0 {
+                f32::NAN
+            } else {
+                (state >> 40) as f32 / (1u64 << 24) as f32
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn percentiles_interpolate_between_ranks() {
+    let values = [4.0, 1.0, 3.0, 2.0];
+    assert_eq!(median(&values, NanPolicy::Skip), 2.5);
+    assert_eq!(percentile_interpolated(&values, 0.0, NanPolicy::Skip), 1.0);
+    assert_eq!(percentile_interpolated(&values, 1.0, NanPolicy::Skip), 4.0);
+    assert_eq!(
+        percentile_interpolated(&values, 0.25, NanPolicy::Skip),
+        1.75
+    );
+    assert_eq!(
+        percentiles_interpolated(&values, &[0.5, 0.9], NanPolicy::Skip),
+        [2.5, 3.7]
+    );
+    assert_eq!(median(&[7.0], NanPolicy::Propagate), 7.0);
+    assert_eq!(mad(&[1.0, 2.0, 3.0, 4.0, 100.0], NanPolicy::Skip), 1.0);
+}
+
+#[test]
+fn empty_input_is_nan_for_ranks_and_zero_for_fractions() {
+    for nan in POLICIES {
+        assert!(median(&[], nan).is_nan(), "{nan:?}");
+        assert!(mad(&[], nan).is_nan(), "{nan:?}");
+        assert!(percentile_interpolated(&[], 0.9, nan).is_nan(), "{nan:?}");
+        assert_eq!(percentiles_interpolated(&[], &[0.1, 0.9], nan).len(), 2);
+        assert_eq!(fraction_ge(&[], 0.5, nan), 0.0, "{nan:?}");
+    }
+    // All-NaN input is empty once NaN is skipped.
+    assert!(median(&[f32::NAN; 3], NanPolicy::Skip).is_nan());
+    assert_eq!(fraction_ge(&[f32::NAN; 3], 0.5, NanPolicy::Skip), 0.0);
+    assert_eq!(fraction_ge(&[f32::NAN; 3], 0.5, NanPolicy::Low), 0.0);
+}
+
+#[test]
+fn nan_policies_hold_on_random_inputs() {
+    for seed in 0..200u64 {
+        let values = sample(seed, 1 + (seed as usize % 37));
+        let finite: Vec<f32> = values.iter().copied().filter(|v| !v.is_nan()).collect();
+        let n_nan = values.len() - finite.len();
+
+        for p in [0.0, 0.1, 0.5, 0.9, 0.99, 1.0] {
+            // Skip is the statistic of the finite values alone.
+            let skip = percentile_interpolated(&values, p, NanPolicy::Skip);
+            let clean = percentile_interpolated(&finite, p, NanPolicy::Propagate);
+            assert!(
+                skip == clean || (skip.is_nan() && clean.is_nan()),
+                "seed {seed}"
+            );
+            if !finite.is_empty() {
+                let lo = finite.iter().copied().fold(f32::INFINITY, f32::min);
+                let hi = finite.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+                assert!((lo..=hi).contains(&skip), "seed {seed} p {p}");
+            }
+            // Propagate is NaN exactly when a NaN is present.
+            let propagate = percentile_interpolated(&values, p, NanPolicy::Propagate);
+            assert_eq!(propagate.is_nan(), n_nan > 0, "seed {seed}");
+        }
+
+        // Low keeps NaN in the denominator; Skip drops it.
+        let t = 0.5;
+        let n_ge = finite.iter().filter(|&&v| v >= t).count() as f32;
+        let low = fraction_ge(&values, t, NanPolicy::Low);
+        assert!(
+            (low - n_ge / values.len() as f32).abs() < 1e-6,
+            "seed {seed}"
+        );
+        if !finite.is_empty() {
+            let skip = fraction_ge(&values, t, NanPolicy::Skip);
+            assert!(
+                (skip - n_ge / finite.len() as f32).abs() < 1e-6,
+                "seed {seed}"
+            );
+        }
+        assert_eq!(
+            fraction_ge(&values, t, NanPolicy::Propagate).is_nan(),
+            n_nan > 0
+        );
+
+        // Low ranks NaN first: the minimum is NaN iff one is present, the
+        // maximum never is unless every value is NaN.
+        let low_min = percentile_interpolated(&values, 0.0, NanPolicy::Low);
+        assert_eq!(low_min.is_nan(), n_nan > 0, "seed {seed}");
+        let low_max = percentile_interpolated(&values, 1.0, NanPolicy::Low);
+        assert_eq!(low_max.is_nan(), finite.is_empty(), "seed {seed}");
+
+        let spread = mad(&values, NanPolicy::Skip);
+        assert!(finite.is_empty() || spread >= 0.0, "seed {seed}");
+    }
+}
+
+#[test]
+fn spearman_ranks_with_ties_and_nan_policies() {
+    let a = [1.0, 2.0, 3.0, 4.0];
+    assert_eq!(
+        spearman(&a, &[10.0, 20.0, 30.0, 1000.0], NanPolicy::Skip),
+        1.0
+    );
+    assert_eq!(spearman(&a, &[4.0, 3.0, 2.0, 1.0], NanPolicy::Skip), -1.0);
+    // Ranks 1, 2.5, 2.5, 4 against 1..4: r = 4.5 / sqrt(4.5 * 5).
+    let tied = spearman(&[1.0, 2.0, 2.0, 3.0], &a, NanPolicy::Skip);
+    assert!(
+        (tied - (4.5f64 / 22.5f64.sqrt()) as f32).abs() < 1e-6,
+        "{tied}"
+    );
+
+    let b = [1.0, f32::NAN, 3.0, 2.0];
+    let skip = spearman(&a, &b, NanPolicy::Skip);
+    assert!((skip - 0.5).abs() < 1e-6, "{skip}");
+    assert!(spearman(&a, &b, NanPolicy::Propagate).is_nan());
+    // NaN ranks lowest, so b ranks 2, 1, 4, 3.
+    let low = spearman(&a, &b, NanPolicy::Low);
+    assert!((low - 0.6).abs() < 1e-6, "{low}");
+
+    assert!(
+        spearman(&a, &[5.0; 4], NanPolicy::Skip).is_nan(),
+        "constant"
+    );
+    assert!(
+        spearman(&[1.0], &[1.0], NanPolicy::Skip).is_nan(),
+        "one pair"
+    );
+}
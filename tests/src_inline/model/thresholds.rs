@@ -0,0 +1,80 @@
+use super::*;
+
+fn write_config(json: &str) -> (tempfile::TempDir, std::path::PathBuf) {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("thresholds.json");
+    std::fs::write(&path, json).expect("write");
+    (dir, path)
+}
+
+#[test]
+fn species_overrides_apply_on_top_of_file_globals() {
+    let (_dir, path) = write_config(
+        r#"{"low_counts": 800, "oii_hi": 0.7, "species": {"mouse": {"low_counts": 400}}}"#,
+    );
+    let config = ThresholdsConfig::load(&path).expect("load");
+    assert_eq!(config.global.low_counts, 800);
+    let mouse = &config.species["mouse"];
+    assert_eq!(mouse.low_counts, 400);
+    assert_eq!(mouse.oii_hi, 0.7);
+    assert_eq!(config.for_species("human").1, DEFAULT_THRESHOLDS_SCOPE);
+    assert_eq!(config.for_species("mouse").1, "mouse");
+
+    for bad in [
+        r#"{"species": {"zebrafish": {}}}"#,
+        r#"{"species": {"mouse": {"low_count": 1}}}"#,
+        r#"{"species": []}"#,
+    ] {
+        let (_dir, path) = write_config(bad);
+        assert!(ThresholdsConfig::load(&path).is_err(), "{bad}");
+    }
+}
+
+#[test]
+fn registry_has_one_entry_per_distinct_set_used() {
+    let uniform = CellThresholds::uniform(Thresholds::default());
+    assert_eq!(uniform.sets().len(), 1);
+    assert_eq!(uniform.for_cell(7).id, "t0");
+
+    let mut config = ThresholdsConfig::default();
+    config.species.insert(
+        "mouse".to_string(),
+        Thresholds {
+            low_counts: 200,
+            ..Thresholds::default()
+        },
+    );
+    // Equal to the globals, so it shares their entry.
+    config
+        .species
+        .insert("human".to_string(), Thresholds::default());
+
+    let cells = CellThresholds::by_species(&config, &["unknown", "mouse", "human", "mouse"]);
+    let ids: Vec<&str> = (0..4).map(|i| cells.for_cell(i).id.as_str()).collect();
+    assert_eq!(ids, ["t0", "t1", "t0", "t1"]);
+    assert_eq!(cells.sets().len(), 2);
+    assert_eq!(cells.sets()[0].applies_to, ["default", "human"]);
+    assert_eq!(cells.sets()[1].applies_to, ["mouse"]);
+    assert_eq!(cells.sets()[1].thresholds.low_counts, 200);
+
+    let human_only = CellThresholds::by_species(&config, &["human", "unknown"]);
+    assert_eq!(human_only.sets().len(), 1);
+}
+
+#[test]
+fn chemistry_defaults_fill_only_fields_the_file_left_out() {
+    let (_dir, path) =
+        write_config(r#"{"low_counts": 800, "species": {"mouse": {"few_detected": 50}}}"#);
+    let mut config = ThresholdsConfig::load(&path).expect("load");
+    config.apply_chemistry(Chemistry::SnRna);
+    assert_eq!(config.global.low_counts, 800);
+    assert_eq!(config.global.few_detected, Chemistry::SnRna.few_detected());
+    let mouse = &config.species["mouse"];
+    assert_eq!(mouse.low_counts, 800);
+    assert_eq!(mouse.few_detected, 50);
+
+    let mut defaults = ThresholdsConfig::default();
+    defaults.apply_chemistry(Chemistry::Flex);
+    assert_eq!(defaults.global.low_counts, Chemistry::Flex.low_counts());
+    assert_eq!(defaults.global.few_detected, Chemistry::Flex.few_detected());
+}
@@ -8,3 +8,235 @@ fn load_core_panels() {
     assert_eq!(set.panels[0].id, "ER_GOLGI_TRAFFICKING");
     assert_eq!(set.panels[0].genes[0].symbol, "SEC23A");
 }
+
+#[test]
+fn source_attributed_per_file() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    std::fs::write(
+        dir.path().join("a.toml"),
+        "[[panel]]\nid = \"A1\"\ndescription = \"a\"\naxis = \"SIA\"\ngenes = [\"G1\"]\nversion = \"2\"\n",
+    )
+    .expect("write");
+    std::fs::write(
+        dir.path().join("b.toml"),
+        "[[panel]]\nid = \"B1\"\ndescription = \"b\"\naxis = \"GDI\"\ngenes = [\"G2\"]\n",
+    )
+    .expect("write");
+
+    let set = load_panels_from_dir(dir.path()).expect("load panels");
+    assert_eq!(set.panels[0].source.as_deref(), Some("a.toml"));
+    assert_eq!(set.panels[0].version.as_deref(), Some("2"));
+    assert_eq!(set.panels[1].source.as_deref(), Some("b.toml"));
+    assert_eq!(set.panels[1].version, None);
+    assert_ne!(set.panels[0].content_hash(), set.panels[1].content_hash());
+}
+
+fn write_panel(path: &Path, id: &str) {
+    std::fs::create_dir_all(path.parent().expect("parent")).expect("mkdir");
+    std::fs::write(
+        path,
+        format!(
+            "[[panel]]\nid = \"{id}\"\ndescription = \"d\"\naxis = \"SIA\"\ngenes = [\"G1\"]\n"
+        ),
+    )
+    .expect("write");
+}
+
+#[test]
+fn recursive_load_is_sorted_and_filterable() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    write_panel(&dir.path().join("tissue/pancreas/islet.toml"), "ISLET");
+    write_panel(&dir.path().join("core/base.toml"), "BASE");
+    write_panel(&dir.path().join("experimental/new.toml"), "NEW");
+
+    let set = load_panels_from_dir(dir.path()).expect("load panels");
+    assert_eq!(
+        set.files,
+        vec![
+            "core/base.toml",
+            "experimental/new.toml",
+            "tissue/pancreas/islet.toml"
+        ]
+    );
+    assert_eq!(
+        set.panels[2].source.as_deref(),
+        Some("tissue/pancreas/islet.toml")
+    );
+
+    let opts = PanelLoadOptions {
+        include: vec![],
+        exclude: vec!["experimental/**".to_string()],
+        ..PanelLoadOptions::default()
+    };
+    let set = load_panels_with_options(dir.path(), &opts).expect("load panels");
+    let ids: Vec<&str> = set.panels.iter().map(|p| p.id.as_str()).collect();
+    assert_eq!(ids, vec!["BASE", "ISLET"]);
+
+    let opts = PanelLoadOptions {
+        include: vec!["tissue/**".to_string()],
+        exclude: vec![],
+        ..PanelLoadOptions::default()
+    };
+    let set = load_panels_with_options(dir.path(), &opts).expect("load panels");
+    assert_eq!(set.files, vec!["tissue/pancreas/islet.toml"]);
+}
+
+#[test]
+fn duplicate_ids_across_files_are_rejected() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    write_panel(&dir.path().join("core/a.toml"), "DUP");
+    write_panel(&dir.path().join("extra/b.toml"), "DUP");
+
+    let err = load_panels_from_dir(dir.path()).expect_err("duplicate");
+    let msg = err.to_string();
+    assert!(msg.contains("DUP"));
+    assert!(msg.contains("core/a.toml"));
+    assert!(msg.contains("extra/b.toml"));
+}
+
+#[test]
+fn validation_reports_all_issues_with_context() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    std::fs::write(
+        dir.path().join("a.toml"),
+        "[[panel]]\nid = \"TYPO\"\ndescription = \"d\"\naxis = \"SIA\"\ngenes = [\"G1\"]\nrequried = [\"G1\"]\n",
+    )
+    .expect("write");
+    std::fs::write(
+        dir.path().join("b.toml"),
+        "[[panel]]\nid = \"BAD_AXIS\"\ndescription = \"d\"\naxis = \"XYZ\"\ngenes = [\"G1\"]\n\n\
+         [[panel]]\nid = \"BAD_REQ\"\ndescription = \"d\"\naxis = \"GDI\"\ngenes = []\nrequired = [\"G9\"]\n\n\
+         [[panel]]\nid = \"CUSTOM\"\ndescription = \"d\"\naxis = \"XYZ\"\ncustom_axis = true\ngenes = [\"G1\"]\n",
+    )
+    .expect("write");
+
+    let err = load_panels_from_dir(dir.path()).expect_err("invalid panels");
+    let PanelLoadError::Invalid(issues) = err else {
+        panic!("expected validation issues");
+    };
+    let rendered: Vec<String> = issues.iter().map(|i| i.to_string()).collect();
+    assert_eq!(rendered.len(), 4, "{rendered:?}");
+    assert!(rendered[0].starts_with("a.toml: toml parse error at line"));
+    assert!(rendered[0].contains("requried"));
+    assert!(rendered[1].starts_with("b.toml: panel 'BAD_AXIS': unknown axis 'XYZ'"));
+    assert_eq!(
+        rendered[2],
+        "b.toml: panel 'BAD_REQ': genes must not be empty"
+    );
+    assert_eq!(
+        rendered[3],
+        "b.toml: panel 'BAD_REQ': required gene 'G9' is not listed in genes"
+    );
+}
+
+#[test]
+fn group_defaults_to_axis() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    std::fs::write(
+        dir.path().join("a.toml"),
+        "[[panel]]\nid = \"CYTO\"\ndescription = \"d\"\naxis = \"SLI\"\ngroup = \"cytokines\"\ngenes = [\"IL6\"]\n\n\
+         [[panel]]\nid = \"PLAIN\"\ndescription = \"d\"\naxis = \"SIA\"\ngenes = [\"G1\"]\n",
+    )
+    .expect("write");
+
+    let set = load_panels_from_dir(dir.path()).expect("load panels");
+    assert_eq!(set.panels[0].group.as_deref(), Some("cytokines"));
+    assert_eq!(set.panels[0].group_name(), "cytokines");
+    assert_eq!(set.panels[1].group, None);
+    assert_eq!(set.panels[1].group_name(), "SIA");
+}
+
+#[test]
+fn skip_policy_drops_broken_files_and_keeps_the_rest() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    write_panel(&dir.path().join("a.toml"), "A");
+    std::fs::write(dir.path().join("b.toml"), "[[panel]\n").expect("write");
+    std::fs::write(
+        dir.path().join("c.toml"),
+        "[[panel]]\nid = \"C1\"\ndescription = \"c\"\naxis = \"SIA\"\ngenes = [\"G1\"]\n\n\
+         [[panel]]\nid = \"C2\"\ndescription = \"c\"\naxis = \"NOPE\"\ngenes = [\"G2\"]\n",
+    )
+    .expect("write");
+    write_panel(&dir.path().join("d.toml"), "C1");
+
+    let err = load_panels_from_dir(dir.path()).expect_err("fail policy");
+    let PanelLoadError::Invalid(issues) = err else {
+        panic!("expected invalid panels");
+    };
+    let files: Vec<&str> = issues.iter().map(|i| i.file.as_str()).collect();
+    assert_eq!(files, vec!["b.toml", "c.toml", "d.toml"]);
+
+    let opts = PanelLoadOptions {
+        on_error: PanelErrorPolicy::Skip,
+        ..PanelLoadOptions::default()
+    };
+    let set = load_panels_with_options(dir.path(), &opts).expect("skip policy");
+    let ids: Vec<&str> = set.panels.iter().map(|p| p.id.as_str()).collect();
+    // c.toml is dropped as a whole, so d.toml's C1 is not a duplicate.
+    assert_eq!(ids, vec!["A", "C1"]);
+    assert_eq!(set.files, vec!["a.toml", "d.toml"]);
+    let skipped: Vec<&str> = set.skipped.iter().map(|s| s.file.as_str()).collect();
+    assert_eq!(skipped, vec!["b.toml", "c.toml"]);
+    assert!(set.skipped[1].errors[0].starts_with("panel 'C2': unknown axis"));
+}
+
+#[test]
+fn skip_policy_still_fails_when_nothing_survives() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    std::fs::write(dir.path().join("b.toml"), "[[panel]\n").expect("write");
+    let opts = PanelLoadOptions {
+        on_error: PanelErrorPolicy::Skip,
+        ..PanelLoadOptions::default()
+    };
+    let err = load_panels_with_options(dir.path(), &opts).expect_err("empty");
+    assert!(matches!(err, PanelLoadError::Empty(_)));
+}
+
+#[test]
+fn autofix_appends_required_genes_missing_from_genes() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    std::fs::write(
+        dir.path().join("a.toml"),
+        "[[panel]]\nid = \"W\"\ndescription = \"d\"\naxis = \"SIA\"\ngenes = [\"G1\", \"G2\"]\nrequired = [\"G9\", \"G1\", \"G9\"]\nweights = [0.5]\n\n\
+         [[panel]]\nid = \"OK\"\ndescription = \"d\"\naxis = \"SIA\"\ngenes = [\"G1\"]\nrequired = [\"G1\"]\n",
+    )
+    .expect("write");
+
+    let err = load_panels_from_dir(dir.path()).expect_err("required not in genes");
+    assert_eq!(
+        err.to_string(),
+        "2 panel error(s):\n\
+         a.toml: panel 'W': required gene 'G9' is not listed in genes\n\
+         a.toml: panel 'W': required gene 'G9' is not listed in genes"
+    );
+
+    let opts = PanelLoadOptions {
+        autofix_required: true,
+        ..PanelLoadOptions::default()
+    };
+    let set = load_panels_with_options(dir.path(), &opts).expect("autofix");
+    let genes: Vec<&str> = set.panels[0].gene_symbols().collect();
+    assert_eq!(genes, ["G1", "G2", "G9"]);
+    assert_eq!(set.panels[0].weights, Some(vec![0.5, 1.0, 1.0]));
+    assert_eq!(
+        set.required_autofixes,
+        [RequiredAutofix {
+            file: "a.toml".to_string(),
+            panel_id: "W".to_string(),
+            genes: vec!["G9".to_string()],
+        }]
+    );
+    assert_eq!(set.panels[1].gene_symbols().count(), 1);
+}
+
+#[test]
+fn covariate_axis_panels_load() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    std::fs::write(
+        dir.path().join("c.toml"),
+        "[[panel]]\nid = \"CYCLE\"\ndescription = \"d\"\naxis = \"NONE\"\ngenes = [\"G1\"]\n",
+    )
+    .expect("write");
+    let set = load_panels_from_dir(dir.path()).expect("covariate panel is valid");
+    assert_eq!(set.panels[0].axis, crate::panels::defs::COVARIATE_AXIS);
+}
@@ -15,6 +15,7 @@ fn mapping_missing_required() {
         id: "P1".to_string(),
         description: "".to_string(),
         axis: "X".to_string(),
+        group: None,
         genes: vec![
             crate::panels::defs::PanelGene {
                 symbol: "A".to_string(),
@@ -25,12 +26,79 @@ fn mapping_missing_required() {
         ],
         required: vec!["A".to_string(), "C".to_string()],
         weights: None,
+        custom_axis: false,
+        version: None,
+        source: None,
     };
 
     let (mapping, warning) = map_panel(&panel, &index);
     assert_eq!(mapping.mapped.len(), 2);
     assert_eq!(mapping.required_hits, 1);
     assert_eq!(mapping.required_total, 2);
-    assert!(warning.is_some());
-    assert_eq!(warning.unwrap().missing_required, vec!["C".to_string()]);
+    let warning = warning.expect("warning");
+    assert_eq!(warning.missing_required, vec!["C".to_string()]);
+    assert_eq!(warning.axis, "X");
+    assert_eq!(warning.mappable_fraction, 0.5);
+}
+
+#[test]
+fn gene_universe_counts_shared_symbols_once() {
+    let panel = |id: &str, axis: &str, genes: &[&str]| PanelDef {
+        id: id.to_string(),
+        description: String::new(),
+        axis: axis.to_string(),
+        group: None,
+        genes: genes
+            .iter()
+            .map(|g| crate::panels::defs::PanelGene {
+                symbol: g.to_string(),
+            })
+            .collect(),
+        required: Vec::new(),
+        weights: None,
+        custom_axis: false,
+        version: None,
+        source: None,
+    };
+    let mapping = |mapped: Vec<Option<u32>>| GeneMapping {
+        panel_id: String::new(),
+        mapped,
+        required_hits: 0,
+        required_total: 0,
+    };
+    let panels = [
+        panel("P1", "SIA", &["A", "B"]),
+        panel("P2", "SIA", &["B", "C"]),
+        panel("P3", "GDI", &["A", "D"]),
+    ];
+    let mappings = [
+        mapping(vec![Some(0), None]),
+        mapping(vec![None, Some(2)]),
+        mapping(vec![Some(0), None]),
+    ];
+
+    let universe = panel_gene_universe(&panels, &mappings);
+    assert_eq!(
+        universe.overall,
+        GeneUniverseCount {
+            mapped: 2,
+            total: 4
+        }
+    );
+    assert_eq!(universe.overall.unmapped(), 2);
+    assert_eq!(
+        universe.by_axis["SIA"],
+        GeneUniverseCount {
+            mapped: 2,
+            total: 3
+        }
+    );
+    assert_eq!(
+        universe.by_axis["GDI"],
+        GeneUniverseCount {
+            mapped: 1,
+            total: 2
+        }
+    );
+    assert_eq!(GeneUniverseCount::default().mapped_fraction(), 1.0);
 }
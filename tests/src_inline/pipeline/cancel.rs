@@ -0,0 +1,57 @@
+use super::*;
+use tempfile::tempdir;
+
+#[test]
+fn checkpoint_consults_flag_only_at_interval() {
+    let flag = CancellationToken::new();
+    let _guard = enter(&flag);
+    assert_eq!(checkpoint(CANCEL_CHECK_CELLS), Ok(()));
+
+    flag.cancel();
+    assert_eq!(checkpoint(1), Ok(()));
+    assert_eq!(checkpoint(CANCEL_CHECK_CELLS - 1), Ok(()));
+    assert_eq!(checkpoint(CANCEL_CHECK_CELLS), Err(Cancelled));
+    assert_eq!(checkpoint(3 * CANCEL_CHECK_CELLS), Err(Cancelled));
+    assert_eq!(check(), Err(Cancelled));
+}
+
+#[test]
+fn guard_restores_previous_flag() {
+    assert_eq!(check(), Ok(()));
+    let outer = CancellationToken::new();
+    outer.cancel();
+    let _outer = enter(&outer);
+    {
+        let inner = CancellationToken::new();
+        let _inner = enter(&inner);
+        assert_eq!(check(), Ok(()));
+    }
+    assert_eq!(check(), Err(Cancelled));
+}
+
+#[test]
+fn abort_marker_records_stages_and_removes_partial_outputs() {
+    let dir = tempdir().unwrap();
+    for name in ["expr_stats.tsv", "panels_report.tsv", "axes.tsv"] {
+        std::fs::write(dir.path().join(name), "partial").unwrap();
+    }
+
+    write_abort_marker(
+        dir.path(),
+        &["stage1_load", "stage2_normalize", "stage3_panels"],
+    )
+    .unwrap();
+
+    assert!(dir.path().join("expr_stats.tsv").exists());
+    assert!(dir.path().join("panels_report.tsv").exists());
+    assert!(!dir.path().join("axes.tsv").exists());
+    let marker: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(dir.path().join(ABORT_MARKER)).unwrap()).unwrap();
+    assert_eq!(marker["reason"], "interrupted");
+    assert_eq!(marker["last_completed_stage"], "stage3_panels");
+    assert_eq!(marker["aborted_stage"], "stage4_axes");
+
+    remove_abort_marker(dir.path()).unwrap();
+    assert!(!dir.path().join(ABORT_MARKER).exists());
+    remove_abort_marker(dir.path()).unwrap();
+}
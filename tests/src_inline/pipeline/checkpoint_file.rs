@@ -0,0 +1,162 @@
+use super::*;
+use crate::expr::normalize::Normalization;
+use crate::model::axes::AxisConfig;
+use crate::panels::defs::{PanelDef, PanelGene, PanelSet, RequiredAutofix, SkippedPanelFile};
+use crate::pipeline::stage1_load::{RunMode, run_stage1};
+use crate::pipeline::stage2_normalize::run_stage2;
+use crate::pipeline::stage3_panels::run_stage3_panels;
+use crate::pipeline::stage4_axes::{CORE_AXES, run_stage4_axes_with_config};
+use crate::pipeline::stage5_scores::run_stage5_scores;
+use crate::pipeline::stage6_classify::run_stage6_classify;
+use crate::testing::{SyntheticDataset, SyntheticParams};
+
+/// Stage 3-6 contexts of a small synthetic run, with the optional panel
+/// fields filled in so their JSON layout is exercised too.
+fn contexts() -> (PanelsContext, AxesContext, ScoresContext, ClassifyContext) {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("input");
+    let out = dir.path().join("out");
+    std::fs::create_dir_all(&out).unwrap();
+    let data = SyntheticDataset::generate(
+        &SyntheticParams {
+            n_cells: 60,
+            n_genes: 80,
+            ..SyntheticParams::default()
+        },
+        3,
+    );
+    data.write_mtx(&input).unwrap();
+
+    let panels = PanelSet {
+        panels: CORE_AXES
+            .iter()
+            .enumerate()
+            .map(|(i, axis)| PanelDef {
+                id: format!("P_{axis}"),
+                description: format!("{axis} genes"),
+                axis: axis.to_string(),
+                group: (i % 2 == 0).then(|| "even".to_string()),
+                genes: (3 * i + 1..=3 * i + 3)
+                    .map(|g| PanelGene {
+                        symbol: format!("SYN{g:05}"),
+                    })
+                    .chain([PanelGene {
+                        symbol: "ABSENT".to_string(),
+                    }])
+                    .collect(),
+                required: vec!["ABSENT".to_string()],
+                weights: None,
+                custom_axis: false,
+                version: Some("1".to_string()),
+                source: Some("core.toml".to_string()),
+            })
+            .collect(),
+        files: vec!["core.toml".to_string()],
+        skipped: vec![SkippedPanelFile {
+            file: "broken.toml".to_string(),
+            errors: vec!["bad".to_string()],
+        }],
+        required_autofixes: vec![RequiredAutofix {
+            file: "core.toml".to_string(),
+            panel_id: "P_SIA".to_string(),
+            genes: vec!["ABSENT".to_string()],
+        }],
+    };
+
+    let dataset = run_stage1(&input, None, &out, false, RunMode::Standalone, None).unwrap();
+    let expr = run_stage2(&dataset, &out, Normalization::default(), false).unwrap();
+    let panels_ctx =
+        run_stage3_panels(&expr, &panels, &dataset.gene_index, &dataset.barcodes, &out).unwrap();
+    let axes =
+        run_stage4_axes_with_config(&dataset, &panels_ctx, &out, &AxisConfig::default()).unwrap();
+    let scores = run_stage5_scores(&axes, &out).unwrap();
+    let classify = run_stage6_classify(&dataset, &expr, &axes, &scores, &out).unwrap();
+    (panels_ctx, axes, scores, classify)
+}
+
+/// Decodes `ctx`'s checkpoint and checks it encodes to the same bytes; byte
+/// equality stands in for `PartialEq`, which NaN scores would defeat.
+fn round_trip<T: Checkpoint>(ctx: &T) -> T {
+    let bytes = encode_checkpoint(ctx).unwrap();
+    assert_eq!(&bytes[..8], &CHECKPOINT_MAGIC);
+    let back: T = decode_checkpoint(&bytes).unwrap();
+    assert_eq!(encode_checkpoint(&back).unwrap(), bytes, "{}", T::NAME);
+    back
+}
+
+#[test]
+fn stage_contexts_round_trip() {
+    let (panels, axes, scores, classify) = contexts();
+
+    let panels_back = round_trip(&panels);
+    assert_eq!(panels_back.cell_ids, panels.cell_ids);
+    assert_eq!(
+        panels_back.panels.content_hash(),
+        panels.panels.content_hash()
+    );
+    assert_eq!(panels_back.panels.skipped, panels.panels.skipped);
+    assert_eq!(panels_back.panels.panels[0].group.as_deref(), Some("even"));
+    assert_eq!(panels_back.panels.panels[1].group, None);
+    assert!(!panels_back.warnings.is_empty());
+    assert_eq!(panels_back.dead_panels, panels.dead_panels);
+
+    let axes_back = round_trip(&axes);
+    assert_eq!(axes_back.coverage_mode, axes.coverage_mode);
+    assert_eq!(axes_back.panel_counts, axes.panel_counts);
+    assert_eq!(
+        axes_back.values[5].sia.to_bits(),
+        axes.values[5].sia.to_bits()
+    );
+
+    let scores_back = round_trip(&scores);
+    assert_eq!(scores_back.iai_weightset, scores.iai_weightset);
+    assert_eq!(scores_back.drivers_oii, scores.drivers_oii);
+    assert!(scores.score_concentration.iter().any(|v| v.is_nan()));
+
+    let classify_back = round_trip(&classify);
+    assert_eq!(classify_back.regimes, classify.regimes);
+    assert_eq!(classify_back.rule_ids, classify.rule_ids);
+    assert_eq!(classify_back.flags, classify.flags);
+    assert_eq!(classify_back.thresholds, classify.thresholds);
+}
+
+#[test]
+fn checkpoint_files_round_trip_through_disk() {
+    let (_, _, scores, _) = contexts();
+    let dir = tempfile::tempdir().unwrap();
+    write_checkpoint(dir.path(), "scores.ckpt", &scores).unwrap();
+    let back: ScoresContext = read_checkpoint(&dir.path().join("scores.ckpt")).unwrap();
+    assert_eq!(back.oii, scores.oii);
+}
+
+#[test]
+fn header_mismatches_fail_before_decoding() {
+    let (_, _, scores, _) = contexts();
+    let bytes = encode_checkpoint(&scores).unwrap();
+
+    let mut newer = bytes.clone();
+    newer[8..10].copy_from_slice(&(CHECKPOINT_VERSION + 1).to_le_bytes());
+    let err = decode_checkpoint::<ScoresContext>(&newer).unwrap_err();
+    assert!(
+        matches!(
+            err,
+            CheckpointError::VersionMismatch { found, expected }
+                if found == CHECKPOINT_VERSION + 1 && expected == CHECKPOINT_VERSION
+        ),
+        "{err}"
+    );
+
+    let err = decode_checkpoint::<AxesContext>(&bytes).unwrap_err();
+    assert_eq!(err.to_string(), "checkpoint holds scores, expected axes");
+
+    let err = decode_checkpoint::<ScoresContext>(b"KSECCK").unwrap_err();
+    assert!(matches!(err, CheckpointError::InvalidMagic), "{err}");
+
+    let err = decode_checkpoint::<ScoresContext>(&bytes[..bytes.len() - 3]).unwrap_err();
+    assert!(matches!(err, CheckpointError::Payload(_)), "{err}");
+
+    let mut trailing = bytes;
+    trailing.push(0);
+    let err = decode_checkpoint::<ScoresContext>(&trailing).unwrap_err();
+    assert!(matches!(err, CheckpointError::Payload(_)), "{err}");
+}
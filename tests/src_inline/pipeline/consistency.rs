@@ -0,0 +1,112 @@
+use super::*;
+use std::fs;
+use tempfile::tempdir;
+
+const HEADER: &str = "barcode\tsecretory_load\ter_golgi_pressure\tstress_secretion_index\tregime";
+
+fn quantiles(median: f32) -> Quantiles {
+    Quantiles {
+        median,
+        p90: f32::NAN,
+        p99: f32::NAN,
+    }
+}
+
+fn distributions(secretory: f32, er_golgi: f32, stress: f32) -> DistributionSummary {
+    DistributionSummary {
+        secretory_load: quantiles(secretory),
+        er_golgi_pressure: quantiles(er_golgi),
+        stress_secretion_index: quantiles(stress),
+        score_concentration: quantiles(f32::NAN),
+    }
+}
+
+fn regimes(counts: &[(&str, usize)]) -> RegimeSummary {
+    RegimeSummary {
+        counts: counts.iter().map(|&(l, c)| (l.to_string(), c)).collect(),
+        fractions: BTreeMap::new(),
+    }
+}
+
+/// Three cells; column order differs from secretion.tsv on purpose.
+fn write_table(dir: &Path) -> PathBuf {
+    let path = dir.join("secretion.tsv");
+    fs::write(
+        &path,
+        format!(
+            "{HEADER}\nc1\t0.100000\t0.500000\t0.000000\tBalanced\n\
+             c2\t0.300000\t0.250000\t0.000000\tHighSecretion\n\
+             c3\t0.200000\t0.333333\t0.000000\tBalanced\n"
+        ),
+    )
+    .expect("write table");
+    path
+}
+
+#[test]
+fn matching_counts_and_medians_within_formatting_tolerance_pass() {
+    let dir = tempdir().expect("tempdir");
+    let path = write_table(dir.path());
+    check_secretion_tsv(
+        &path,
+        &regimes(&[("Balanced", 2), ("HighSecretion", 1), ("Quiescent", 0)]),
+        &distributions(0.2, 1.0 / 3.0, 0.0),
+    )
+    .expect("consistent");
+}
+
+#[test]
+fn diverging_counts_and_medians_are_all_listed() {
+    let dir = tempdir().expect("tempdir");
+    let path = write_table(dir.path());
+    let err = check_secretion_tsv(
+        &path,
+        &regimes(&[("Balanced", 3)]),
+        &distributions(0.2, 0.5, 0.0),
+    )
+    .expect_err("diverged");
+    match &err {
+        ConsistencyError::Diverged {
+            path: p,
+            mismatches,
+        } => {
+            assert_eq!(p, &path);
+            assert_eq!(mismatches.len(), 3, "{mismatches:?}");
+            assert!(mismatches[0].contains("regime Balanced: 2 rows, summary counts 3"));
+            assert!(mismatches[1].contains("regime HighSecretion: 1 rows, summary counts 0"));
+            assert!(mismatches[2].starts_with("er_golgi_pressure median"));
+        }
+        other => panic!("unexpected error: {other}"),
+    }
+    assert!(
+        err.to_string().contains("--skip-output-consistency-check"),
+        "{err}"
+    );
+}
+
+#[test]
+fn absent_axis_medians_are_not_compared() {
+    let dir = tempdir().expect("tempdir");
+    let path = write_table(dir.path());
+    check_secretion_tsv(
+        &path,
+        &regimes(&[("Balanced", 2), ("HighSecretion", 1)]),
+        &distributions(0.2, f32::NAN, f32::NAN),
+    )
+    .expect("NaN medians skipped");
+}
+
+#[test]
+fn missing_columns_are_read_errors() {
+    let dir = tempdir().expect("tempdir");
+    let path = dir.path().join("secretion.tsv");
+    fs::write(&path, "barcode\tregime\nc1\tBalanced\n").expect("write table");
+    let err = check_secretion_tsv(
+        &path,
+        &regimes(&[("Balanced", 1)]),
+        &distributions(0.0, 0.0, 0.0),
+    )
+    .expect_err("missing columns");
+    assert!(matches!(err, ConsistencyError::Read(_)), "{err}");
+    assert!(err.to_string().contains("secretory_load"), "{err}");
+}
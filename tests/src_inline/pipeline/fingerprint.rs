@@ -0,0 +1,82 @@
+use super::*;
+use tempfile::tempdir;
+
+fn write_input(dir: &Path, matrix: &str) {
+    std::fs::write(dir.join("features.tsv"), "f1\tSEC23A\nf2\tSAR1A\n").unwrap();
+    std::fs::write(dir.join("barcodes.tsv"), "c1\nc2\n").unwrap();
+    std::fs::write(dir.join("matrix.mtx"), matrix).unwrap();
+}
+
+fn fingerprint(dir: &Path, seed: &str) -> RunFingerprint {
+    let parameters = BTreeMap::from([("seed".to_string(), seed.to_string())]);
+    RunFingerprint::new(
+        dir,
+        RunMode::Standalone,
+        InputFormat::TenX,
+        None,
+        &Normalization::default(),
+        "0123456789abcdef".to_string(),
+        parameters,
+    )
+    .unwrap()
+}
+
+const MATRIX: &str = "%%MatrixMarket matrix coordinate integer general\n2 2 2\n1 1 3\n2 2 1\n";
+
+#[test]
+fn digest_tracks_inputs_and_parameters() {
+    let dir = tempdir().unwrap();
+    write_input(dir.path(), MATRIX);
+    let base = fingerprint(dir.path(), "1");
+    assert_eq!(base.nnz, 2);
+    assert_eq!(
+        base.inputs.keys().collect::<Vec<_>>(),
+        ["barcodes", "features", "matrix"]
+    );
+    assert_eq!(base.digest(), fingerprint(dir.path(), "1").digest());
+    assert_ne!(base.digest(), fingerprint(dir.path(), "2").digest());
+
+    // Same nnz, different counts.
+    write_input(
+        dir.path(),
+        "%%MatrixMarket matrix coordinate integer general\n2 2 2\n1 1 4\n2 2 1\n",
+    );
+    assert_ne!(base.digest(), fingerprint(dir.path(), "1").digest());
+
+    let json = base.to_json();
+    assert_eq!(json["digest"], base.digest());
+    assert_eq!(json["parameters"]["seed"], "1");
+}
+
+#[test]
+fn stale_reason_explains_what_is_missing() {
+    let dir = tempdir().unwrap();
+    assert_eq!(
+        stale_reason(dir.path(), "abc", false).as_deref(),
+        Some("no readable provenance.json")
+    );
+    std::fs::write(dir.path().join("provenance.json"), "{}").unwrap();
+    assert_eq!(
+        stale_reason(dir.path(), "abc", false).as_deref(),
+        Some("provenance.json has no fingerprint")
+    );
+    std::fs::write(
+        dir.path().join("provenance.json"),
+        r#"{"fingerprint": {"digest": "abc"}}"#,
+    )
+    .unwrap();
+    assert_eq!(
+        stale_reason(dir.path(), "def", false).as_deref(),
+        Some("fingerprint changed (abc -> def)")
+    );
+    assert_eq!(
+        stale_reason(dir.path(), "abc", false).as_deref(),
+        Some("no _SUCCESS marker")
+    );
+    std::fs::write(dir.path().join(SUCCESS_MARKER), "").unwrap();
+    std::fs::write(dir.path().join("secretion.tsv.tmp"), "barcode\n").unwrap();
+    assert_eq!(
+        stale_reason(dir.path(), "abc", false).as_deref(),
+        Some("partial artifact secretion.tsv.tmp")
+    );
+}
@@ -0,0 +1,167 @@
+use super::*;
+use crate::expr::csc::ExprCsc;
+use crate::expr::normalize::Normalization;
+use crate::input::features::GeneIndex;
+use crate::panels::defs::{PanelDef, PanelGene, PanelSet};
+use crate::pipeline::stage2_normalize::ExprMatrix;
+use crate::pipeline::stage3_panels::run_stage3_panels;
+use std::collections::HashMap;
+use std::io::Read;
+
+fn panel(id: &str, genes: &[&str]) -> PanelDef {
+    PanelDef {
+        id: id.to_string(),
+        description: String::new(),
+        axis: "SIA".to_string(),
+        group: None,
+        genes: genes
+            .iter()
+            .map(|g| PanelGene {
+                symbol: g.to_string(),
+            })
+            .collect(),
+        required: vec![],
+        weights: None,
+        custom_axis: false,
+        version: None,
+        source: None,
+    }
+}
+
+/// Genes A..D, two cells; P2 repeats B and lists an unmapped gene Z.
+fn fixture(dir: &Path) -> (ExprContext, PanelsContext) {
+    let mtx = dir.join("matrix.mtx");
+    std::fs::write(
+        &mtx,
+        "%%MatrixMarket matrix coordinate integer general\n4 2 5\n1 1 1\n2 1 2\n3 1 7\n2 2 3\n4 2 4\n",
+    )
+    .expect("write");
+    let (expr, stats) = ExprCsc::from_mtx(&mtx, 4, 2, false).expect("csc");
+    let expr = ExprContext {
+        expr: ExprMatrix::Owned(expr),
+        cell_stats: stats,
+        normalization: Normalization {
+            enabled: false,
+            ..Normalization::default()
+        },
+        value_warnings: Default::default(),
+    };
+    let mut gene_index = GeneIndex {
+        rows: Vec::new(),
+        duplicates: Vec::new(),
+        first_index_by_symbol: HashMap::new(),
+    };
+    for (i, symbol) in ["A", "B", "C", "D"].iter().enumerate() {
+        gene_index
+            .first_index_by_symbol
+            .insert(symbol.to_string(), i + 1);
+    }
+    let panels = PanelSet {
+        panels: vec![panel("P1", &["B", "A"]), panel("P2", &["B", "Z", "D"])],
+        files: vec![],
+        skipped: vec![],
+        required_autofixes: vec![],
+    };
+    let cell_ids = vec!["c1".to_string(), "c2".to_string()];
+    let ctx = run_stage3_panels(&expr, &panels, &gene_index, &cell_ids, dir).expect("stage3");
+    (expr, ctx)
+}
+
+fn gunzip(path: &Path) -> String {
+    let mut text = String::new();
+    flate2::read::GzDecoder::new(std::fs::File::open(path).expect("open"))
+        .read_to_string(&mut text)
+        .expect("gunzip");
+    text
+}
+
+#[test]
+fn columns_follow_panel_then_gene_order_without_duplicates() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let (_, panels) = fixture(dir.path());
+    let symbols: Vec<String> = panel_gene_columns(&panels)
+        .into_iter()
+        .map(|c| c.symbol)
+        .collect();
+    assert_eq!(symbols, vec!["B", "A", "D"]);
+}
+
+#[test]
+fn tsv_export_writes_cells_in_given_order() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let (expr, panels) = fixture(dir.path());
+    let barcodes = vec!["c1".to_string(), "c2".to_string()];
+    let artifacts = write_panel_expr(
+        dir.path(),
+        PanelMatrixFormat::Tsv,
+        CompressFormat::Gzip,
+        &expr,
+        &panels,
+        &barcodes,
+        &[1, 0],
+    )
+    .expect("export");
+    assert_eq!(artifacts, vec![("panel_expr", PANEL_EXPR_TSV)]);
+    assert_eq!(
+        gunzip(&dir.path().join(PANEL_EXPR_TSV)),
+        "barcode\tB\tA\tD\n\
+         c2\t3.000000\t0.000000\t4.000000\n\
+         c1\t2.000000\t1.000000\t0.000000\n"
+    );
+}
+
+#[test]
+fn mtx_export_is_sparse_genes_by_cells() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let (expr, panels) = fixture(dir.path());
+    let barcodes = vec!["c1".to_string(), "c2".to_string()];
+    write_panel_expr(
+        dir.path(),
+        PanelMatrixFormat::Mtx,
+        CompressFormat::Gzip,
+        &expr,
+        &panels,
+        &barcodes,
+        &[0, 1],
+    )
+    .expect("export");
+    assert_eq!(
+        gunzip(&dir.path().join(PANEL_EXPR_MTX)),
+        "%%MatrixMarket matrix coordinate real general\n3 2 4\n\
+         1 1 2.000000\n2 1 1.000000\n1 2 3.000000\n3 2 4.000000\n"
+    );
+    assert_eq!(gunzip(&dir.path().join(PANEL_EXPR_GENES)), "B\nA\nD\n");
+    assert_eq!(gunzip(&dir.path().join(PANEL_EXPR_BARCODES)), "c1\nc2\n");
+}
+
+#[test]
+fn bgzf_export_decodes_to_the_gzip_text() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let (expr, panels) = fixture(dir.path());
+    let barcodes = vec!["c1".to_string(), "c2".to_string()];
+    let export = |sub: &str, compression| {
+        let out = dir.path().join(sub);
+        std::fs::create_dir_all(&out).expect("mkdir");
+        write_panel_expr(
+            &out,
+            PanelMatrixFormat::Tsv,
+            compression,
+            &expr,
+            &panels,
+            &barcodes,
+            &[0, 1],
+        )
+        .expect("export");
+        std::fs::read(out.join(PANEL_EXPR_TSV)).expect("read")
+    };
+    let gzip = export("gzip", CompressFormat::Gzip);
+    let bgzf = export("bgzf", CompressFormat::Bgzf);
+    assert!(bgzf.ends_with(&crate::report::compress::BGZF_EOF));
+    let mut text = String::new();
+    flate2::read::MultiGzDecoder::new(&bgzf[..])
+        .read_to_string(&mut text)
+        .expect("multi gunzip");
+    assert_eq!(text, gunzip(&dir.path().join("gzip").join(PANEL_EXPR_TSV)));
+    assert_ne!(gzip, bgzf);
+    assert_eq!(export("bgzf_again", CompressFormat::Bgzf), bgzf);
+}
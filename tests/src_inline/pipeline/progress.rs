@@ -0,0 +1,74 @@
+use super::*;
+
+#[test]
+fn cells_updates_only_entered_counters_at_the_cadence() {
+    let counters = Arc::new(ProgressCounters::new());
+    cells(PROGRESS_UPDATE_CELLS, 5_000);
+    assert_eq!(counters.snapshot(RunStatus::Running).cells_done, 0);
+
+    let _guard = enter(&counters);
+    start_stage("stage3_panels");
+    cells(PROGRESS_UPDATE_CELLS, 5_000);
+    cells(PROGRESS_UPDATE_CELLS + 1, 5_000);
+    let snapshot = counters.snapshot(RunStatus::Running);
+    assert_eq!(snapshot.stage, Some("stage3_panels"));
+    assert_eq!(snapshot.stage_index, 3);
+    assert_eq!((snapshot.cells_done, snapshot.cells_total), (1_024, 5_000));
+
+    cells(5_000, 5_000);
+    assert_eq!(counters.snapshot(RunStatus::Running).cells_done, 5_000);
+    start_stage("stage4_axes");
+    let snapshot = counters.snapshot(RunStatus::Running);
+    assert_eq!((snapshot.cells_done, snapshot.eta_ms), (0, None));
+}
+
+#[test]
+fn snapshot_reports_status_and_exit_category() {
+    let counters = ProgressCounters::new();
+    let before = counters.snapshot(RunStatus::Running);
+    assert_eq!((before.stage, before.stage_index), (None, 0));
+
+    counters.start_stage("stage2_normalize");
+    counters.set_cells(10, 10);
+    let done = counters.snapshot(RunStatus::Running);
+    assert_eq!(done.eta_ms, Some(0));
+
+    let failed = serde_json::to_value(counters.snapshot(RunStatus::Failed {
+        category: "input",
+        exit_code: 3,
+    }))
+    .expect("json");
+    assert_eq!(failed["status"], "failed");
+    assert_eq!(failed["exit_category"], "input");
+    assert_eq!(failed["exit_code"], 3);
+    assert!(failed["eta_ms"].is_null());
+    let complete = serde_json::to_value(counters.snapshot(RunStatus::Complete)).expect("json");
+    assert!(complete.get("exit_code").is_none());
+}
+
+#[test]
+fn reporter_writes_initial_and_final_snapshots() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let read = || -> serde_json::Value {
+        serde_json::from_slice(&std::fs::read(dir.path().join(PROGRESS_FILE)).expect("read"))
+            .expect("json")
+    };
+    let reporter = ProgressReporter::start(dir.path(), Duration::from_millis(5));
+    reporter.counters().start_stage("stage1_load");
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while read_status(dir.path()).as_deref() != Some("running") {
+        assert!(Instant::now() < deadline, "no running snapshot");
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    reporter.finish(RunStatus::Complete);
+    let last = read();
+    assert_eq!(last["status"], "complete");
+    assert_eq!(last["stage"], "stage1_load");
+    assert!(!dir.path().join("progress.json.tmp").exists());
+}
+
+fn read_status(dir: &Path) -> Option<String> {
+    let bytes = std::fs::read(dir.join(PROGRESS_FILE)).ok()?;
+    let value: serde_json::Value = serde_json::from_slice(&bytes).ok()?;
+    value["status"].as_str().map(str::to_string)
+}
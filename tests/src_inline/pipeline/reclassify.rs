@@ -0,0 +1,369 @@
+use super::*;
+use crate::model::thresholds::Thresholds;
+use crate::panels::defs::{PanelDef, PanelGene};
+use crate::panels::mapping::GeneMapping;
+use crate::pipeline::stage3_panels::PanelCellPacked;
+use crate::pipeline::stage4_axes::run_stage4_axes;
+use crate::pipeline::stage5_scores::run_stage5_scores;
+use crate::pipeline::stage6_classify::run_stage6_classify_with_thresholds;
+use std::collections::HashMap;
+use std::fs;
+use tempfile::tempdir;
+
+const CELLS: [&str; 4] = ["c1", "c2", "c3", "c4"];
+
+fn panel(id: &str, axis: &str) -> PanelDef {
+    PanelDef {
+        id: id.to_string(),
+        description: String::new(),
+        axis: axis.to_string(),
+        group: None,
+        genes: vec![PanelGene {
+            symbol: id.to_string(),
+        }],
+        required: vec![id.to_string()],
+        weights: None,
+        custom_axis: false,
+        version: None,
+        source: None,
+    }
+}
+
+fn fixture_contexts() -> (DatasetCtx, ExprContext, PanelsContext) {
+    let panels = PanelSet {
+        panels: vec![
+            panel("P_SIA", "SIA"),
+            panel("P_EXP", "EEB_EXPORT"),
+            panel("P_DEG", "EEB_DEGRADE"),
+            panel("P_SLI", "SLI"),
+            panel("P_MEI", "MEI"),
+            panel("P_ECM", "ECMI"),
+            panel("P_GDI", "GDI"),
+        ],
+        files: vec![],
+        skipped: vec![],
+        required_autofixes: vec![],
+    };
+    let mappings = panels
+        .panels
+        .iter()
+        .map(|p| GeneMapping {
+            panel_id: p.id.clone(),
+            mapped: vec![Some(0)],
+            required_hits: 1,
+            required_total: 1,
+        })
+        .collect();
+    let sums = [
+        [0.1, 0.1, 2.0, 0.1, 0.1, 0.1, 0.1],
+        [4.0, 3.0, 0.5, 5.0, 0.4, 0.3, 0.2],
+        [1.5, 6.0, 0.1, 0.5, 2.0, 0.1, 0.3],
+        [0.3, 0.2, 0.2, 0.2, 0.1, 3.0, 9.0],
+    ];
+    let per_cell = sums
+        .iter()
+        .map(|s| PanelCellPacked {
+            sums: s.to_vec(),
+            hits: vec![1; 7],
+            required_missing: vec![0; 7],
+        })
+        .collect();
+    let cell_ids: Vec<String> = CELLS.iter().map(|c| c.to_string()).collect();
+    let panels_ctx = PanelsContext {
+        panels,
+        mappings,
+        warnings: Vec::new(),
+        cell_ids: cell_ids.clone(),
+        dead_panels: Vec::new(),
+        per_cell,
+    };
+
+    let libsizes = [300u64, 800, 2500, 9000];
+    let expr = ExprContext {
+        expr: ExprMatrix::Owned(ExprCsc {
+            n_genes: 1,
+            n_cells: CELLS.len(),
+            nnz: 0,
+            col_ptr: vec![0; CELLS.len() + 1],
+            row_idx: Vec::new(),
+            values: Vec::new(),
+        }),
+        cell_stats: libsizes
+            .iter()
+            .map(|&libsize| CellStats {
+                libsize,
+                detected: (libsize / 5) as u32,
+            })
+            .collect(),
+        normalization: Normalization::default(),
+        value_warnings: Default::default(),
+    };
+    let dataset = DatasetCtx {
+        format: TenXFormat::TenXv3,
+        matrix_path: "matrix.mtx".into(),
+        features_path: "features.tsv".into(),
+        barcodes_path: "barcodes.tsv".into(),
+        features_delimiter: Default::default(),
+        barcodes_delimiter: Default::default(),
+        barcodes_header: None,
+        features_single_column_rows: 0,
+        shared_cache_path: None,
+        resolved_shared_cache_path: None,
+        shared_cache: None,
+        shared_cache_identity: None,
+        gene_index: GeneIndex {
+            rows: vec![],
+            duplicates: vec![],
+            first_index_by_symbol: HashMap::new(),
+        },
+        barcodes: cell_ids,
+        n_genes: 1,
+        n_cells: CELLS.len(),
+        nnz: 0,
+        duplicate_gene_symbols_count: 0,
+        duplicate_gene_symbols: vec![],
+        meta_present: false,
+        meta_cells_matched: 0,
+        meta_cells_missing: 0,
+        meta_issues: Default::default(),
+        gene_set_hash: String::new(),
+        upstream: None,
+        dense: None,
+        matrix_value_type: None,
+        matrix_orientation: None,
+    };
+    (dataset, expr, panels_ctx)
+}
+
+/// Full stages 4-7 run into `dir`, plus the expr_stats.tsv `run` writes.
+fn direct_run(dir: &Path, thresholds: &Thresholds) {
+    let (dataset, expr, panels) = fixture_contexts();
+    let axes = run_stage4_axes(&dataset, &panels, dir).expect("stage4");
+    let scores = run_stage5_scores(&axes, dir).expect("stage5");
+    let classify =
+        run_stage6_classify_with_thresholds(&dataset, &expr, &axes, &scores, dir, thresholds)
+            .expect("stage6");
+    let opts = ReportOptions {
+        thresholds: thresholds.clone(),
+        ..ReportOptions::default()
+    };
+    run_stage7_report_with_options(
+        &dataset,
+        &expr,
+        &axes,
+        &scores,
+        &classify,
+        &panels,
+        dir,
+        "cell",
+        RunMode::Standalone,
+        None,
+        &opts,
+    )
+    .expect("stage7");
+
+    let mut stats = String::from("cell_id\tlibsize\tdetected\n");
+    for (cell, s) in CELLS.iter().zip(&expr.cell_stats) {
+        stats.push_str(&format!("{}\t{}\t{}\n", cell, s.libsize, s.detected));
+    }
+    fs::write(dir.join("expr_stats.tsv"), stats).expect("write expr_stats");
+}
+
+fn read(dir: &Path, name: &str) -> String {
+    fs::read_to_string(dir.join(name)).expect(name)
+}
+
+/// Same shape and text fields; numbers may differ in the last written decimal
+/// because reclassification starts from values already rounded to six.
+fn assert_tsv_matches(got: &str, want: &str) {
+    let (got, want): (Vec<&str>, Vec<&str>) = (got.lines().collect(), want.lines().collect());
+    assert_eq!(got.len(), want.len());
+    assert_eq!(got[0], want[0]);
+    for (g, w) in got.iter().zip(&want).skip(1) {
+        for (a, b) in g.split('\t').zip(w.split('\t')) {
+            match (a.parse::<f64>(), b.parse::<f64>()) {
+                (Ok(x), Ok(y)) if x.is_nan() || y.is_nan() => assert_eq!(a, b),
+                (Ok(x), Ok(y)) => assert!((x - y).abs() <= 1.5e-6, "{a} vs {b}"),
+                _ => assert_eq!(a, b),
+            }
+        }
+    }
+}
+
+#[test]
+fn reclassify_matches_direct_run_with_same_thresholds() {
+    let tmp = tempdir().expect("tempdir");
+    let custom = Thresholds {
+        low_counts: 1000,
+        sia_low: 0.5,
+        ..Thresholds::default()
+    };
+    for (name, thresholds) in [("default", Thresholds::default()), ("custom", custom)] {
+        let source = tmp.path().join(format!("{name}_source"));
+        let direct = tmp.path().join(format!("{name}_direct"));
+        fs::create_dir_all(&source).expect("mkdir");
+        fs::create_dir_all(&direct).expect("mkdir");
+        direct_run(&source, &Thresholds::default());
+        direct_run(&direct, &thresholds);
+
+        let new_out = tmp.path().join(format!("{name}_reclassified"));
+        run_reclassify(&source, &new_out, &thresholds.clone().into(), None).expect("reclassify");
+
+        for file in ["classify.tsv", "panels_report.tsv"] {
+            assert_eq!(read(&new_out, file), read(&direct, file), "{name}: {file}");
+        }
+        assert_tsv_matches(
+            &read(&new_out, "secretion.tsv"),
+            &read(&direct, "secretion.tsv"),
+        );
+        let summary = |dir: &Path| -> serde_json::Value {
+            serde_json::from_str(&read(dir, "summary.json")).expect("json")
+        };
+        let (got, want) = (summary(&new_out), summary(&direct));
+        for key in ["input", "regimes", "distributions", "qc", "caveats"] {
+            assert_eq!(got[key], want[key], "{name}: summary.{key}");
+        }
+    }
+    assert_ne!(
+        read(&tmp.path().join("default_direct"), "classify.tsv"),
+        read(&tmp.path().join("custom_direct"), "classify.tsv"),
+        "custom thresholds must change the fixture's classification"
+    );
+}
+
+#[test]
+fn provenance_points_back_to_source_run() {
+    let tmp = tempdir().expect("tempdir");
+    let source = tmp.path().join("kira-secretion");
+    fs::create_dir_all(&source).expect("mkdir");
+    direct_run(&source, &Thresholds::default());
+
+    let new_out = tmp.path().join("new");
+    run_reclassify(tmp.path(), &new_out, &ThresholdsConfig::default(), None).expect("reclassify");
+
+    let provenance: serde_json::Value =
+        serde_json::from_str(&read(&new_out, "provenance.json")).expect("json");
+    let from = &provenance["reclassified_from"];
+    let source_dir = from["source_dir"].as_str().expect("source_dir");
+    assert!(source_dir.ends_with("kira-secretion"), "{source_dir}");
+    assert_eq!(from["thresholds"]["cov_min"], serde_json::json!(0.6f32));
+    assert_eq!(provenance["panels"].as_array().map(Vec::len), Some(7));
+
+    let report = crate::pipeline::verify::run_verify(&new_out, None).expect("verify");
+    assert!(report.passed(), "{}", report.render());
+}
+
+#[test]
+fn source_run_mode_is_kept() {
+    let tmp = tempdir().expect("tempdir");
+    direct_run(tmp.path(), &Thresholds::default());
+    let summary =
+        read(tmp.path(), "summary.json").replace("\"mode\": \"cell\"", "\"mode\": \"sample\"");
+    fs::write(tmp.path().join("summary.json"), summary).expect("write");
+
+    let new_out = tmp.path().join("new");
+    run_reclassify(tmp.path(), &new_out, &ThresholdsConfig::default(), None).expect("reclassify");
+    let summary: serde_json::Value =
+        serde_json::from_str(&read(&new_out, "summary.json")).expect("json");
+    assert_eq!(summary["provenance"]["mode"], "sample");
+    assert!(new_out.join("samples.tsv").exists());
+}
+
+/// Reverses the column order and appends an unknown column.
+fn shuffle_columns(path: &Path) {
+    let text = fs::read_to_string(path).expect("read");
+    let mut out = String::new();
+    for (i, line) in text.lines().enumerate() {
+        let mut fields: Vec<&str> = line.split('\t').rev().collect();
+        fields.push(if i == 0 { "added_later" } else { "0" });
+        out.push_str(&fields.join("\t"));
+        out.push('\n');
+    }
+    fs::write(path, out).expect("write");
+}
+
+#[test]
+fn reordered_and_extended_columns_reclassify_the_same() {
+    let tmp = tempdir().expect("tempdir");
+    let (plain, shuffled) = (tmp.path().join("plain"), tmp.path().join("shuffled"));
+    for dir in [&plain, &shuffled] {
+        fs::create_dir_all(dir).expect("mkdir");
+        direct_run(dir, &Thresholds::default());
+    }
+    for file in ["axes.tsv", "composites.tsv", "expr_stats.tsv"] {
+        shuffle_columns(&shuffled.join(file));
+    }
+
+    let (a, b) = (tmp.path().join("a"), tmp.path().join("b"));
+    run_reclassify(&plain, &a, &ThresholdsConfig::default(), None).expect("plain");
+    run_reclassify(&shuffled, &b, &ThresholdsConfig::default(), None).expect("shuffled");
+    for file in ["classify.tsv", "secretion.tsv"] {
+        assert_eq!(read(&a, file), read(&b, file), "{file}");
+    }
+}
+
+#[test]
+fn missing_axes_columns_are_reported_together() {
+    let tmp = tempdir().expect("tempdir");
+    direct_run(tmp.path(), &Thresholds::default());
+    let axes = read(tmp.path(), "axes.tsv");
+    let mut lines = axes.lines();
+    let header = lines
+        .next()
+        .expect("header")
+        .replace("\tcov_SLI\t", "\tcov_sli\t");
+    let header = header.replace("\tGDI\t", "\tgdi\t");
+    let mut text = header;
+    for line in lines {
+        text.push('\n');
+        text.push_str(line);
+    }
+    fs::write(tmp.path().join("axes.tsv"), text).expect("write");
+
+    let err = run_reclassify(
+        tmp.path(),
+        &tmp.path().join("new"),
+        &ThresholdsConfig::default(),
+        None,
+    )
+    .expect_err("missing columns")
+    .to_string();
+    assert!(
+        err.contains("missing required column(s) GDI, cov_SLI"),
+        "{err}"
+    );
+    assert!(err.contains("found [cell_id, SIA, EEB, "), "{err}");
+}
+
+#[test]
+fn cell_order_mismatch_is_an_input_error() {
+    let tmp = tempdir().expect("tempdir");
+    direct_run(tmp.path(), &Thresholds::default());
+    let stats = read(tmp.path(), "expr_stats.tsv").replace("c2\t", "cX\t");
+    fs::write(tmp.path().join("expr_stats.tsv"), stats).expect("write");
+
+    let err = load_previous_run(tmp.path())
+        .err()
+        .expect("mismatched cell_id must fail");
+    assert!(
+        matches!(
+            &err,
+            ReclassifyError::CellMismatch { file: "expr_stats.tsv", found, .. } if found == "cX"
+        ),
+        "{err}"
+    );
+}
+
+#[test]
+fn thresholds_file_overrides_subset() {
+    let tmp = tempdir().expect("tempdir");
+    let path = tmp.path().join("thresholds.json");
+    fs::write(&path, r#"{"low_counts": 1000, "oii_hi": 0.7}"#).expect("write");
+    let t = Thresholds::load(&path).expect("load");
+    assert_eq!(t.low_counts, 1000);
+    assert_eq!(t.oii_hi, 0.7);
+    assert_eq!(t.cov_min, Thresholds::default().cov_min);
+
+    fs::write(&path, r#"{"low_count": 1000}"#).expect("write");
+    assert!(Thresholds::load(&path).is_err(), "unknown field rejected");
+}
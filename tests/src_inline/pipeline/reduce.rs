@@ -0,0 +1,55 @@
+use super::*;
+
+fn chunked_sum(values: &[f32]) -> f32 {
+    fold_cell_chunks(
+        values.len(),
+        |range| values[range].iter().sum::<f32>(),
+        |a, b| a + b,
+    )
+    .unwrap_or(0.0)
+}
+
+#[test]
+fn float_sums_are_identical_at_any_thread_count() {
+    // Values of very different magnitude make the result order-sensitive.
+    let values: Vec<f32> = (0..REDUCE_CHUNK_CELLS * 3 + 17)
+        .map(|i| {
+            if i % 97 == 0 {
+                1.0e7
+            } else {
+                0.1 + (i % 13) as f32 * 1.0e-3
+            }
+        })
+        .collect();
+    let sums: Vec<u32> = [1, 3, 8]
+        .into_iter()
+        .map(|threads| {
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .expect("pool")
+                .install(|| chunked_sum(&values))
+                .to_bits()
+        })
+        .collect();
+    assert_eq!(sums[0], sums[1]);
+    assert_eq!(sums[0], sums[2]);
+}
+
+#[test]
+fn chunks_cover_every_cell_once_in_order() {
+    let n = REDUCE_CHUNK_CELLS * 2 + 5;
+    let ranges = fold_cell_chunks(
+        n,
+        |range| vec![range],
+        |mut a, b| {
+            a.extend(b);
+            a
+        },
+    )
+    .expect("non-empty");
+    assert_eq!(ranges.len(), 3);
+    assert_eq!(ranges[0], 0..REDUCE_CHUNK_CELLS);
+    assert_eq!(ranges[2], REDUCE_CHUNK_CELLS * 2..n);
+    assert!(fold_cell_chunks(0, |_| 0u32, |a, b| a + b).is_none());
+}
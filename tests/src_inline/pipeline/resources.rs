@@ -0,0 +1,97 @@
+use super::*;
+use tempfile::tempdir;
+
+#[cfg(target_os = "linux")]
+#[test]
+fn linux_samples_are_plausible_and_monotonic() {
+    let mem = sample_memory();
+    assert!(mem.rss_bytes.unwrap_or(0) > 0);
+    assert!(mem.peak_rss_bytes.unwrap_or(0) >= mem.rss_bytes.unwrap_or(0));
+
+    let mut tracker = ResourceTracker::new();
+    for stage in ["stage1_load", "stage2_normalize", "stage3_panels"] {
+        let start = Instant::now();
+        let _buf = vec![1u8; 1 << 16];
+        tracker.record(stage, start, StageWork::default());
+    }
+    let stages = tracker.stages();
+    assert_eq!(stages.len(), 3);
+    for pair in stages.windows(2) {
+        assert!(pair[1].start_ms >= pair[0].start_ms);
+        assert!(pair[1].peak_rss_bytes >= pair[0].peak_rss_bytes);
+    }
+    assert!(stages.iter().all(|s| s.peak_rss_bytes.unwrap_or(0) > 0));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn begin_samples_start_rss_for_the_next_record() {
+    let mut tracker = ResourceTracker::new();
+    let start = tracker.begin();
+    let held = vec![1u8; 32 << 20];
+    tracker.record("stage7_report", start, StageWork::default());
+    tracker.record("stage_without_begin", Instant::now(), StageWork::default());
+    drop(held);
+
+    let stages = tracker.stages();
+    let (start_rss, end_rss) = (
+        stages[0].start_rss_bytes.expect("start sample"),
+        stages[0].rss_bytes.expect("end sample"),
+    );
+    assert!(
+        end_rss >= start_rss + (16 << 20),
+        "{start_rss} -> {end_rss}"
+    );
+    assert_eq!(stages[1].start_rss_bytes, None);
+}
+
+#[test]
+fn resources_key_added_to_pipeline_step() {
+    let dir = tempdir().unwrap();
+    std::fs::write(
+        dir.path().join("pipeline_step.json"),
+        "{\"artifacts\": {\"summary\": \"summary.json\"}}",
+    )
+    .unwrap();
+    let mut tracker = ResourceTracker::new();
+    tracker.record("stage1_load", Instant::now(), StageWork::default());
+    write_resources_to_pipeline_step(dir.path(), &tracker).unwrap();
+
+    let v: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(dir.path().join("pipeline_step.json")).unwrap())
+            .unwrap();
+    assert_eq!(v["artifacts"]["summary"], "summary.json");
+    assert_eq!(v["resources"]["stages"][0]["stage"], "stage1_load");
+    assert!(v["resources"]["total_elapsed_ms"].is_u64());
+    let sampled = sample_memory().peak_rss_bytes.is_some();
+    assert_eq!(
+        v["resources"]["stages"][0].get("peak_rss_bytes").is_some(),
+        sampled
+    );
+}
+
+#[test]
+fn throughput_follows_the_stage_work() {
+    let mut tracker = ResourceTracker::new();
+    let started = Instant::now()
+        .checked_sub(Duration::from_secs(2))
+        .expect("clock");
+    let stage = tracker.record(
+        "stage3_panels",
+        started,
+        StageWork {
+            cells: Some(1_000),
+            nnz: Some(40_000),
+        },
+    );
+    let cells = stage.cells_per_sec.expect("cells rate");
+    let nnz = stage.nnz_per_sec.expect("nnz rate");
+    assert!((400.0..=500.0).contains(&cells), "{cells}");
+    assert!((nnz / cells - 40.0).abs() < 1e-9, "{nnz} vs {cells}");
+
+    tracker.record("stage5_scores", started, StageWork::cells(10));
+    let json = tracker.to_json();
+    assert!(json["stages"][1]["cells_per_sec"].is_f64());
+    assert!(json["stages"][1].get("nnz_per_sec").is_none());
+    assert_eq!(per_sec(10, Duration::ZERO), None);
+}
@@ -0,0 +1,42 @@
+use super::*;
+
+#[test]
+fn child_seeds_are_stable_and_distinct_per_purpose() {
+    let rng = RunRng::new(42);
+    let a = rng.child("subsample");
+    assert_eq!(a, RunRng::new(42).child("subsample"));
+    assert_eq!(a, derive_seed(42, "subsample"));
+    assert_ne!(a, rng.child("bootstrap"));
+    assert_ne!(a, RunRng::new(43).child("subsample"));
+}
+
+#[test]
+fn derived_seeds_are_recorded() {
+    let rng = RunRng::default();
+    assert!(rng.provenance().derived.is_empty());
+    let seed = rng.child("permutation_null");
+    let clone = rng.clone();
+    let _ = clone.stream("bootstrap");
+
+    let prov = rng.provenance();
+    assert_eq!(prov.master_seed, DEFAULT_SEED);
+    assert_eq!(prov.scheme, SEED_SCHEME);
+    assert_eq!(
+        prov.derived.keys().collect::<Vec<_>>(),
+        ["bootstrap", "permutation_null"]
+    );
+    assert_eq!(prov.derived["permutation_null"], seed);
+}
+
+#[test]
+fn stream_is_reproducible_and_in_range() {
+    let mut a = SplitMix64::new(7);
+    let mut b = SplitMix64::new(7);
+    for _ in 0..1000 {
+        assert_eq!(a.next_u64(), b.next_u64());
+        let x = a.next_f64();
+        assert!((0.0..1.0).contains(&x));
+        assert!(a.below(10) < 10);
+        let _ = (b.next_f64(), b.below(10));
+    }
+}
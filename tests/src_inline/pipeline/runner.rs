@@ -0,0 +1,294 @@
+use super::*;
+use crate::panels::defs::{PanelDef, PanelGene};
+use crate::pipeline::cancel::{CANCEL_CHECK_CELLS, STAGE_ORDER};
+use crate::pipeline::stage4_axes::CORE_AXES;
+use crate::testing::{SyntheticDataset, SyntheticParams};
+
+/// One panel of three synthetic genes per core axis.
+fn panels() -> PanelSet {
+    PanelSet {
+        panels: CORE_AXES
+            .iter()
+            .enumerate()
+            .map(|(i, axis)| PanelDef {
+                id: format!("P_{axis}"),
+                description: format!("{axis} genes"),
+                axis: axis.to_string(),
+                group: None,
+                genes: (3 * i + 1..=3 * i + 3)
+                    .map(|g| PanelGene {
+                        symbol: format!("SYN{g:05}"),
+                    })
+                    .collect(),
+                required: Vec::new(),
+                weights: None,
+                custom_axis: false,
+                version: None,
+                source: Some("core.toml".to_string()),
+            })
+            .collect(),
+        files: vec!["core.toml".to_string()],
+        skipped: Vec::new(),
+        required_autofixes: Vec::new(),
+    }
+}
+
+/// Writes a dataset with several cancel checkpoints per stage loop.
+fn input(dir: &std::path::Path) -> PathBuf {
+    let input = dir.join("input");
+    SyntheticDataset::generate(
+        &SyntheticParams {
+            n_cells: 3 * CANCEL_CHECK_CELLS + 100,
+            n_genes: 60,
+            density: 0.2,
+            ..SyntheticParams::default()
+        },
+        11,
+    )
+    .write_mtx(&input)
+    .unwrap();
+    input
+}
+
+fn read(dir: &std::path::Path, name: &str) -> Vec<u8> {
+    std::fs::read(dir.join(name)).unwrap_or_else(|e| panic!("{name}: {e}"))
+}
+
+#[test]
+fn stepped_runs_write_the_same_artifacts_as_one_step() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = input(dir.path());
+    let one_shot = dir.path().join("one_shot");
+    let stepped = dir.path().join("stepped");
+    let tiny = dir.path().join("tiny");
+
+    Runner::start(RunnerConfig::new(&input, &one_shot, panels()))
+        .unwrap()
+        .run_to_end(Duration::from_secs(3600))
+        .unwrap();
+    Runner::start(RunnerConfig::new(&input, &stepped, panels()))
+        .unwrap()
+        .run_to_end(Duration::from_millis(50))
+        .unwrap();
+
+    let mut runner = Runner::start(RunnerConfig::new(&input, &tiny, panels())).unwrap();
+    let mut paused = 0;
+    while let StepStatus::Paused(_) = runner.step(Duration::ZERO).unwrap() {
+        paused += 1;
+    }
+    // A zero budget pauses at least before every stage.
+    assert!(paused >= STAGE_ORDER.len(), "{paused} pauses");
+
+    for name in ["secretion.tsv", "classify.tsv", "axes.tsv", "summary.json"] {
+        let expected = read(&one_shot, name);
+        assert_eq!(read(&stepped, name), expected, "{name}");
+        assert_eq!(read(&tiny, name), expected, "{name}");
+    }
+}
+
+#[test]
+fn paused_steps_report_the_stage_and_cells_reached() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = input(dir.path());
+    let mut runner =
+        Runner::start(RunnerConfig::new(&input, dir.path().join("out"), panels())).unwrap();
+
+    let mut stages = Vec::new();
+    let mut mid_stage = false;
+    loop {
+        match runner.step(Duration::ZERO).unwrap() {
+            StepStatus::Paused(snapshot) => {
+                assert_eq!(snapshot.status, "running");
+                assert!(snapshot.cells_done <= snapshot.cells_total);
+                mid_stage |= snapshot.cells_done > 0 && snapshot.cells_done < snapshot.cells_total;
+                if let Some(stage) = snapshot.stage
+                    && stages.last() != Some(&stage)
+                {
+                    stages.push(stage);
+                }
+            }
+            StepStatus::Finished(summary) => {
+                assert_eq!(summary.input.n_cells, 3 * CANCEL_CHECK_CELLS + 100);
+                break;
+            }
+        }
+    }
+    // Stage 7 checks only between its row batches, which this dataset fits
+    // in one of, so it never pauses inside stage 7.
+    assert_eq!(stages, STAGE_ORDER[..6]);
+    assert!(mid_stage, "no pause inside a per-cell loop");
+    assert_eq!(runner.progress().status, "complete");
+    assert!(matches!(
+        runner.step(Duration::ZERO).unwrap(),
+        StepStatus::Finished(_)
+    ));
+}
+
+#[test]
+fn partial_summary_fills_in_and_matches_the_final_summary() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = input(dir.path());
+    let mut runner =
+        Runner::start(RunnerConfig::new(&input, dir.path().join("out"), panels())).unwrap();
+    assert!(runner.partial().n_cells.is_none());
+
+    let mut saw_quantiles_before_regimes = false;
+    let summary = loop {
+        match runner.step(Duration::ZERO).unwrap() {
+            StepStatus::Paused(_) => {
+                let partial = runner.partial();
+                saw_quantiles_before_regimes |=
+                    partial.secretory_load.is_some() && partial.classify_regimes.is_empty();
+            }
+            StepStatus::Finished(summary) => break summary,
+        }
+    };
+    assert!(saw_quantiles_before_regimes);
+
+    let partial = runner.partial();
+    assert_eq!(partial.n_cells, Some(summary.input.n_cells));
+    let bits = |q: &Quantiles| [q.median.to_bits(), q.p90.to_bits(), q.p99.to_bits()];
+    let d = &summary.distributions;
+    assert_eq!(
+        bits(partial.secretory_load.as_ref().unwrap()),
+        bits(&d.secretory_load)
+    );
+    assert_eq!(
+        bits(partial.er_golgi_pressure.as_ref().unwrap()),
+        bits(&d.er_golgi_pressure)
+    );
+    assert_eq!(
+        bits(partial.stress_secretion_index.as_ref().unwrap()),
+        bits(&d.stress_secretion_index)
+    );
+    let classified: usize = partial.classify_regimes.values().sum();
+    assert_eq!(classified, summary.input.n_cells);
+}
+
+#[test]
+fn partial_summary_runs_while_stages_4_to_6_run() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = input(dir.path());
+    let mut runner =
+        Runner::start(RunnerConfig::new(&input, dir.path().join("out"), panels())).unwrap();
+
+    let mut mid_stage = BTreeMap::new();
+    while let StepStatus::Paused(snapshot) = runner.step(Duration::ZERO).unwrap() {
+        let done = snapshot.cells_done;
+        if done == 0 || done == snapshot.cells_total {
+            continue;
+        }
+        let partial = runner.partial();
+        match snapshot.stage {
+            Some("stage4_axes") => {
+                let q = partial
+                    .er_golgi_pressure
+                    .expect("running er_golgi_pressure");
+                assert!((0.0..=1.0).contains(&q.median), "{q:?}");
+                assert!(partial.stress_secretion_index.is_some());
+                assert!(partial.secretory_load.is_none());
+            }
+            Some("stage5_scores") => {
+                assert!(partial.secretory_load.is_some());
+                assert!(partial.classify_regimes.is_empty());
+            }
+            Some("stage6_classify") => {
+                let classified: usize = partial.classify_regimes.values().sum();
+                assert_eq!(classified, done);
+            }
+            _ => continue,
+        }
+        *mid_stage.entry(snapshot.stage.unwrap()).or_insert(0) += 1;
+    }
+    assert_eq!(mid_stage.len(), 3, "{mid_stage:?}");
+}
+
+#[test]
+fn quantile_sketch_is_within_a_bin_of_the_exact_quantiles() {
+    let values: Vec<f32> = (0..10_000u32)
+        .map(|i| ((i.wrapping_mul(2_654_435_761) % 10_007) as f32 / 10_007.0).powi(3))
+        .chain([f32::NAN, -0.5, 1.5])
+        .collect();
+    let mut sketch = QuantileSketch::default();
+    assert!(sketch.is_empty() && sketch.quantiles().median.is_nan());
+    for &v in &values {
+        sketch.push(v);
+    }
+    assert_eq!(sketch.len(), values.len() as u64 - 1);
+
+    let clamped: Vec<f32> = values.iter().map(|v| v.clamp(0.0, 1.0)).collect();
+    let exact = stats(&clamped);
+    let estimate = sketch.quantiles();
+    let bin = 1.0 / SKETCH_BINS as f32;
+    for (e, x) in [
+        (estimate.median, exact.median),
+        (estimate.p90, exact.p90),
+        (estimate.p99, exact.p99),
+    ] {
+        assert!((e - x).abs() <= bin, "{e} vs {x}");
+    }
+}
+
+#[test]
+fn a_failed_run_returns_its_error_once_then_stopped() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut runner = Runner::start(RunnerConfig::new(
+        dir.path().join("missing"),
+        dir.path().join("out"),
+        panels(),
+    ))
+    .unwrap();
+    assert!(matches!(
+        runner.step(Duration::from_secs(60)),
+        Err(RunnerError::Stage1(_))
+    ));
+    assert!(matches!(
+        runner.step(Duration::from_secs(60)),
+        Err(RunnerError::Stopped)
+    ));
+}
+
+#[test]
+fn axis_config_reaches_stage4() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = input(dir.path());
+    // The bundled panels map none of the synthetic genes.
+    let bundled =
+        crate::panels::loader::load_panels_from_dir(&crate::panels::loader::default_panels_dir())
+            .unwrap();
+
+    let mut runner = Runner::start(RunnerConfig::new(
+        &input,
+        dir.path().join("strict"),
+        bundled.clone(),
+    ))
+    .unwrap();
+    assert!(matches!(
+        runner.step(Duration::from_secs(3600)),
+        Err(RunnerError::Stage4(Stage4Error::MissingAxes { .. }))
+    ));
+
+    let mut config = RunnerConfig::new(&input, dir.path().join("allowed"), bundled);
+    config.axes.allow_missing_axes = true;
+    let summary = Runner::start(config)
+        .unwrap()
+        .run_to_end(Duration::from_secs(3600))
+        .unwrap();
+    assert_eq!(summary.input.n_cells, 3 * CANCEL_CHECK_CELLS + 100);
+}
+
+#[test]
+fn dropping_a_paused_runner_cancels_the_run() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = input(dir.path());
+    let out = dir.path().join("out");
+    let mut runner = Runner::start(RunnerConfig::new(&input, &out, panels())).unwrap();
+    while let StepStatus::Paused(snapshot) = runner.step(Duration::ZERO).unwrap() {
+        if snapshot.stage == Some("stage4_axes") {
+            break;
+        }
+    }
+    drop(runner);
+    assert!(!out.join("classify.tsv").exists());
+    assert!(!out.join("secretion.tsv").exists());
+}
@@ -122,6 +122,203 @@ fn stage1_v3_ok() {
     assert_eq!(ctx.nnz, 3);
 }
 
+#[test]
+fn stage1_dense_input_matches_the_equivalent_mtx() {
+    let dir = tempdir().expect("tempdir");
+    write_file(&dir.path().join("features.tsv"), "G1\tG1\nG2\tG2\n");
+    write_file(&dir.path().join("barcodes.tsv"), "c1\nc2\nc3\n");
+    write_file(
+        &dir.path().join("matrix.mtx"),
+        "%%MatrixMarket matrix coordinate integer general\n2 3 3\n1 1 4\n1 2 1\n2 3 2\n",
+    );
+    let dense_path = dir.path().join("plate.tsv");
+    write_file(&dense_path, "gene\tc1\tc2\tc3\nG1\t4\t1\t0\nG2\t0\t0\t2\n");
+
+    let opts = Stage1Options {
+        input_format: InputFormat::Dense,
+        ..Stage1Options::default()
+    };
+    let run = |input: &Path, opts: &Stage1Options| {
+        run_stage1_with_options(
+            input,
+            None,
+            dir.path(),
+            false,
+            RunMode::Standalone,
+            None,
+            opts,
+        )
+        .expect("stage1")
+    };
+    let dense = run(&dense_path, &opts);
+    let mtx = run(dir.path(), &Stage1Options::default());
+    assert_eq!(dense.format, TenXFormat::Dense);
+    assert_eq!(dense.barcodes, mtx.barcodes);
+    assert_eq!(dense.gene_set_hash, mtx.gene_set_hash);
+    assert_eq!((dense.n_genes, dense.n_cells, dense.nnz), (2, 3, 3));
+
+    let norm = crate::expr::normalize::Normalization::default();
+    let stage2 = |ctx: &DatasetCtx| {
+        crate::pipeline::stage2_normalize::run_stage2(ctx, dir.path(), norm.clone(), false)
+            .expect("stage2")
+    };
+    let (dense_expr, mtx_expr) = (stage2(&dense), stage2(&mtx));
+    for cell in 0..3 {
+        assert_eq!(
+            dense_expr.cell_stats[cell].libsize,
+            mtx_expr.cell_stats[cell].libsize
+        );
+        assert_eq!(
+            dense_expr.cell_stats[cell].detected,
+            mtx_expr.cell_stats[cell].detected
+        );
+    }
+    match (&dense_expr.expr, &mtx_expr.expr) {
+        (
+            crate::pipeline::stage2_normalize::ExprMatrix::Dense(a),
+            crate::pipeline::stage2_normalize::ExprMatrix::Owned(b),
+        ) => {
+            // Stage2 shares stage1's matrix instead of copying it.
+            assert!(std::sync::Arc::ptr_eq(a, dense.dense.as_ref().unwrap()));
+            assert_eq!(a.expr.col_ptr, b.col_ptr);
+            assert_eq!(a.expr.row_idx, b.row_idx);
+            assert_eq!(a.expr.values, b.values);
+        }
+        _ => panic!("expected the shared dense matrix and an owned one"),
+    }
+}
+
+/// Writes a 2-gene, 3-cell dataset with the given features and barcodes text.
+fn write_delimited_dataset(dir: &Path, features: &str, barcodes: &str) {
+    write_file(&dir.join("features.tsv"), features);
+    write_file(&dir.join("barcodes.tsv"), barcodes);
+    write_file(
+        &dir.join("matrix.mtx"),
+        "%%MatrixMarket matrix coordinate integer general\n2 3 3\n1 1 1\n1 2 1\n2 3 1\n",
+    );
+}
+
+#[test]
+fn stage1_reads_tab_and_space_delimited_features_and_barcodes() {
+    let cases = [
+        (
+            "f1\tG1\tGene Expression\nf2\tG2\tGene Expression\n",
+            "c1\tx\nc2\tx\nc3\tx\n",
+            Delimiter::Tab,
+            Delimiter::Tab,
+        ),
+        (
+            "f1 G1 Gene Expression\nf2  G2\n",
+            "c1\nc2\nc3\n",
+            Delimiter::Whitespace,
+            Delimiter::Whitespace,
+        ),
+    ];
+    for (features, barcodes, features_delimiter, barcodes_delimiter) in cases {
+        let dir = tempdir().expect("tempdir");
+        write_delimited_dataset(dir.path(), features, barcodes);
+        let ctx = run_stage1(
+            dir.path(),
+            None,
+            dir.path(),
+            false,
+            RunMode::Standalone,
+            None,
+        )
+        .expect("stage1 ok");
+        assert_eq!(ctx.gene_index.rows[1].id, "f2");
+        assert_eq!(ctx.gene_index.rows[1].symbol, "G2");
+        assert_eq!(ctx.barcodes, vec!["c1", "c2", "c3"]);
+        assert_eq!(ctx.features_delimiter, features_delimiter);
+        assert_eq!(ctx.barcodes_delimiter, barcodes_delimiter);
+    }
+}
+
+#[test]
+fn stage1_single_column_features_need_the_flag() {
+    let dir = tempdir().expect("tempdir");
+    write_delimited_dataset(dir.path(), "G1\nG1\n", "c1\nc2\nc3\n");
+
+    let err = run_stage1(
+        dir.path(),
+        None,
+        dir.path(),
+        false,
+        RunMode::Standalone,
+        None,
+    )
+    .expect_err("single column without the flag");
+    assert!(
+        matches!(
+            err,
+            Stage1Error::Input(InputError::InvalidTsvRow { line: 1, .. })
+        ),
+        "{err}"
+    );
+    assert!(
+        err.to_string().contains("--features-single-column"),
+        "{err}"
+    );
+
+    let ctx = run_stage1_with_options(
+        dir.path(),
+        None,
+        dir.path(),
+        false,
+        RunMode::Standalone,
+        None,
+        &Stage1Options {
+            features_single_column: true,
+            ..Stage1Options::default()
+        },
+    )
+    .expect("stage1 with the flag");
+    assert_eq!(ctx.features_single_column_rows, 2);
+    assert_eq!(ctx.gene_index.rows[0].id, "G1");
+    assert_eq!(ctx.gene_index.rows[0].symbol, "G1");
+    assert_eq!(ctx.duplicate_gene_symbols_count, 1);
+}
+
+#[test]
+fn stage1_mixed_delimiters_name_the_line() {
+    let dir = tempdir().expect("tempdir");
+    write_delimited_dataset(dir.path(), "f1\tG1\nf2 G2\n", "c1\nc2\nc3\n");
+    let err = run_stage1(
+        dir.path(),
+        None,
+        dir.path(),
+        true,
+        RunMode::Standalone,
+        None,
+    )
+    .expect_err("mixed features");
+    assert!(
+        matches!(
+            err,
+            Stage1Error::Input(InputError::InvalidTsvRow { line: 2, .. })
+        ),
+        "{err}"
+    );
+
+    write_delimited_dataset(dir.path(), "f1\tG1\nf2\tG2\n", "c1\nc2\nc3\textra\n");
+    let err = run_stage1(
+        dir.path(),
+        None,
+        dir.path(),
+        true,
+        RunMode::Standalone,
+        None,
+    )
+    .expect_err("mixed barcodes");
+    assert!(
+        matches!(
+            err,
+            Stage1Error::Input(InputError::InvalidTsvRow { line: 3, .. })
+        ),
+        "{err}"
+    );
+}
+
 #[test]
 fn stage1_dim_mismatch() {
     let dir = tempdir().expect("tempdir");
@@ -147,6 +344,126 @@ fn stage1_dim_mismatch() {
     }
 }
 
+#[test]
+fn stage1_transposed_matrix_needs_the_flag_and_reads_like_the_original() {
+    let dir = tempdir().expect("tempdir");
+    let transposed = dir.path().join("transposed");
+    fs::create_dir(&transposed).expect("mkdir");
+    write_delimited_dataset(dir.path(), "f1\tG1\nf2\tG2\n", "c1\nc2\nc3\n");
+    write_file(&transposed.join("features.tsv"), "f1\tG1\nf2\tG2\n");
+    write_file(&transposed.join("barcodes.tsv"), "c1\nc2\nc3\n");
+    write_file(
+        &transposed.join("matrix.mtx"),
+        "%%MatrixMarket matrix coordinate integer general\n3 2 3\n1 1 1\n2 1 1\n3 2 1\n",
+    );
+    let run = |input: &Path, transpose_matrix| {
+        run_stage1_with_options(
+            input,
+            None,
+            dir.path(),
+            false,
+            RunMode::Standalone,
+            None,
+            &Stage1Options {
+                transpose_matrix,
+                ..Stage1Options::default()
+            },
+        )
+    };
+
+    let err = run(&transposed, false).expect_err("transposed without the flag");
+    assert!(matches!(err, Stage1Error::DimensionMismatch { .. }));
+    let message = err.to_string();
+    assert!(message.contains("appears transposed"), "{message}");
+    assert!(message.contains("--transpose-matrix"), "{message}");
+    let err = run(dir.path(), true).expect_err("flag on a genes x cells matrix");
+    assert!(err.to_string().contains("drop --transpose-matrix"), "{err}");
+
+    let ctx = run(&transposed, true).expect("stage1 transposed");
+    let original = run(dir.path(), false).expect("stage1");
+    assert_eq!(ctx.matrix_orientation, Some(MtxOrientation::CellsByGenes));
+    assert_eq!(
+        original.matrix_orientation,
+        Some(MtxOrientation::GenesByCells)
+    );
+    assert_eq!((ctx.n_genes, ctx.n_cells, ctx.nnz), (2, 3, 3));
+
+    let norm = crate::expr::normalize::Normalization::default();
+    let stage2 = |ctx: &DatasetCtx| {
+        crate::pipeline::stage2_normalize::run_stage2(ctx, dir.path(), norm.clone(), false)
+            .expect("stage2")
+    };
+    let (transposed_expr, original_expr) = (stage2(&ctx), stage2(&original));
+    for cell in 0..3 {
+        let (a, b) = (
+            &transposed_expr.cell_stats[cell],
+            &original_expr.cell_stats[cell],
+        );
+        assert_eq!((a.libsize, a.detected), (b.libsize, b.detected));
+    }
+    match (&transposed_expr.expr, &original_expr.expr) {
+        (
+            crate::pipeline::stage2_normalize::ExprMatrix::Owned(a),
+            crate::pipeline::stage2_normalize::ExprMatrix::Owned(b),
+        ) => {
+            assert_eq!(a.col_ptr, b.col_ptr);
+            assert_eq!(a.row_idx, b.row_idx);
+            assert_eq!(a.values, b.values);
+        }
+        _ => panic!("expected owned matrices"),
+    }
+}
+
+#[test]
+fn stage1_barcodes_header_and_dimension_check() {
+    let dir = tempdir().expect("tempdir");
+    write_file(&dir.path().join("features.tsv"), "f1\tG1\nf2\tG2\n");
+    write_file(&dir.path().join("barcodes.tsv"), "barcode\nc1\nc2\n");
+    write_file(
+        &dir.path().join("matrix.mtx"),
+        "%%MatrixMarket matrix coordinate integer general\n2 2 2\n1 1 1\n2 2 1\n",
+    );
+    let with_header = |barcodes_has_header| {
+        run_stage1_with_options(
+            dir.path(),
+            None,
+            dir.path(),
+            true,
+            RunMode::Standalone,
+            None,
+            &Stage1Options {
+                barcodes_has_header,
+                ..Stage1Options::default()
+            },
+        )
+    };
+
+    let ctx = with_header(None).expect("detected header");
+    assert_eq!(ctx.barcodes, ["c1", "c2"]);
+    assert_eq!(ctx.barcodes_header.as_deref(), Some("barcode"));
+
+    let err = with_header(Some(false)).expect_err("header kept as a cell");
+    assert!(
+        err.to_string().contains("--barcodes-has-header true"),
+        "{err}"
+    );
+
+    // A matrix that really has a column per line: skipping is wrong and the
+    // error says how to keep the line.
+    write_file(
+        &dir.path().join("matrix.mtx"),
+        "%%MatrixMarket matrix coordinate integer general\n2 3 2\n1 1 1\n2 2 1\n",
+    );
+    let err = with_header(None).expect_err("header skipped");
+    assert!(
+        err.to_string().contains("--barcodes-has-header false"),
+        "{err}"
+    );
+    let ctx = with_header(Some(false)).expect("forced no header");
+    assert_eq!(ctx.n_cells, 3);
+    assert_eq!(ctx.barcodes_header, None);
+}
+
 #[test]
 fn stage1_duplicate_gene_symbols() {
     let dir = tempdir().expect("tempdir");
@@ -200,6 +517,126 @@ fn stage1_meta_validation() {
     assert_eq!(ctx.meta_cells_missing, 1);
 }
 
+#[test]
+fn meta_problem_rows_are_counted_or_rejected_in_strict_mode() {
+    let dir = tempdir().expect("tempdir");
+    write_shared_cache(&dir.path().join("kira-organelle.bin"));
+    let meta = dir.path().join("meta.tsv");
+    write_file(
+        &meta,
+        "cell_id\tsample_id\tspecies\nc1\ts1\thumna\nc2\ts1\nc1\ts1\tHuman\n",
+    );
+    let run = |meta_strict: bool| {
+        run_stage1_with_options(
+            dir.path(),
+            Some(&meta),
+            dir.path(),
+            true,
+            RunMode::Pipeline,
+            None,
+            &Stage1Options {
+                meta_strict,
+                ..Stage1Options::default()
+            },
+        )
+    };
+
+    let ctx = run(false).expect("lenient");
+    assert_eq!(
+        ctx.meta_issues,
+        MetaIssueCounts {
+            unknown_species: 1,
+            short_rows: 1,
+            duplicate_cell_ids: 1,
+        }
+    );
+
+    let err = run(true).expect_err("strict");
+    let Stage1Error::MetaStrict { count, issues } = &err else {
+        panic!("unexpected error: {err:?}");
+    };
+    assert_eq!(*count, 3);
+    assert_eq!(
+        issues,
+        "line 2: unknown species 'humna'; line 3: 2 columns, header has 3; \
+         line 4: duplicate cell_id 'c1'"
+    );
+}
+
+#[test]
+fn shared_cache_meta_prefix_mismatch_fails_with_examples() {
+    let dir = tempdir().expect("tempdir");
+    write_shared_cache(&dir.path().join("kira-organelle.bin"));
+    write_file(
+        &dir.path().join("meta.tsv"),
+        "cell_id\tsample_id\nS1_c1\ts1\nS1_c2\ts1\n",
+    );
+
+    let err = run_stage1(
+        dir.path(),
+        Some(&dir.path().join("meta.tsv")),
+        dir.path(),
+        true,
+        RunMode::Pipeline,
+        None,
+    )
+    .expect_err("mismatch must fail");
+    match &err {
+        Stage1Error::MetaMismatch {
+            matched,
+            n_cells,
+            barcode_examples,
+            meta_examples,
+            ..
+        } => {
+            assert_eq!((*matched, *n_cells), (0, 2));
+            assert_eq!(barcode_examples, "c1, c2");
+            assert_eq!(meta_examples, "S1_c1, S1_c2");
+        }
+        other => panic!("unexpected error: {other:?}"),
+    }
+    assert!(err.to_string().contains("--min-meta-match-frac"));
+}
+
+#[test]
+fn meta_match_fraction_threshold_is_configurable() {
+    let dir = tempdir().expect("tempdir");
+    write_shared_cache(&dir.path().join("kira-organelle.bin"));
+    write_file(&dir.path().join("meta.tsv"), "cell_id\nc1\nother\n");
+    let meta = dir.path().join("meta.tsv");
+
+    let strict = Stage1Options {
+        min_meta_match_frac: 0.75,
+        ..Stage1Options::default()
+    };
+    let err = run_stage1_with_options(
+        dir.path(),
+        Some(&meta),
+        dir.path(),
+        true,
+        RunMode::Pipeline,
+        None,
+        &strict,
+    )
+    .expect_err("0.5 < 0.75");
+    assert!(matches!(err, Stage1Error::MetaMismatch { .. }));
+
+    let ctx = run_stage1(
+        dir.path(),
+        Some(&meta),
+        dir.path(),
+        true,
+        RunMode::Pipeline,
+        None,
+    )
+    .expect("default threshold passes");
+    assert_eq!(ctx.meta_match_fraction(), Some(0.5));
+
+    let no_meta =
+        run_stage1(dir.path(), None, dir.path(), true, RunMode::Pipeline, None).expect("ctx");
+    assert_eq!(no_meta.meta_match_fraction(), None);
+}
+
 #[test]
 fn pipeline_mode_uses_cache_when_present() {
     let dir = tempdir().expect("tempdir");
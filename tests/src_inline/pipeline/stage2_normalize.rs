@@ -104,8 +104,14 @@ fn stage2_uses_shared_cache_when_present() {
         matrix_path: dir.path().join("matrix.mtx"),
         features_path: dir.path().join("features.tsv"),
         barcodes_path: dir.path().join("barcodes.tsv"),
+        features_delimiter: Default::default(),
+        barcodes_delimiter: Default::default(),
+        barcodes_header: None,
+        features_single_column_rows: 0,
         shared_cache_path: Some(cache.clone()),
         resolved_shared_cache_path: Some(cache),
+        shared_cache: None,
+        shared_cache_identity: None,
         gene_index: crate::input::features::GeneIndex {
             rows: Vec::new(),
             duplicates: Vec::new(),
@@ -120,6 +126,12 @@ fn stage2_uses_shared_cache_when_present() {
         meta_present: false,
         meta_cells_matched: 0,
         meta_cells_missing: 0,
+        meta_issues: Default::default(),
+        gene_set_hash: String::new(),
+        upstream: None,
+        dense: None,
+        matrix_value_type: None,
+        matrix_orientation: None,
     };
 
     let expr = run_stage2(&ctx, dir.path(), Normalization::default(), true).expect("stage2");
@@ -135,6 +147,381 @@ fn stage2_uses_shared_cache_when_present() {
             assert_eq!(shared.row_idx_at(0), 0);
             assert_eq!(shared.value_at(0), 5);
         }
-        ExprMatrix::Owned(_) => panic!("expected shared cache expression"),
+        ExprMatrix::Owned(_) | ExprMatrix::Dense(_) => panic!("expected shared cache expression"),
     }
 }
+
+#[test]
+fn stage2_reads_stage1_mapping_and_rejects_a_swapped_cache() {
+    use crate::pipeline::stage1_load::{RunMode, run_stage1};
+
+    let dir = tempdir().expect("tempdir");
+    let cache = dir.path().join("kira-organelle.bin");
+    let write = |path: &Path, barcodes: &[&str]| {
+        let n = barcodes.len();
+        let expr = ExprCsc {
+            n_genes: 1,
+            n_cells: n,
+            nnz: n,
+            col_ptr: (0..=n as u64).collect(),
+            row_idx: vec![0; n],
+            values: vec![5; n],
+        };
+        let barcodes: Vec<String> = barcodes.iter().map(|b| b.to_string()).collect();
+        crate::input::cache::write_shared_cache(path, &["G1".to_string()], &barcodes, &expr)
+            .expect("write cache");
+    };
+    write(&cache, &["c1"]);
+    let ctx = run_stage1(
+        dir.path(),
+        None,
+        dir.path(),
+        true,
+        RunMode::Pipeline,
+        Some(&cache),
+    )
+    .expect("stage1");
+
+    // Regenerated between the stages: a new file renamed over the old one.
+    let next = dir.path().join("next.bin");
+    write(&next, &["c1", "c2"]);
+    fs::rename(&next, &cache).expect("swap cache");
+
+    let expr = run_stage2(&ctx, dir.path(), Normalization::default(), true).expect("stage2");
+    match expr.expr {
+        ExprMatrix::Shared(ref shared) => assert_eq!(shared.barcodes, ctx.barcodes),
+        ExprMatrix::Owned(_) | ExprMatrix::Dense(_) => panic!("expected shared cache expression"),
+    }
+
+    let reopened = DatasetCtx {
+        shared_cache: None,
+        ..ctx.clone()
+    };
+    let err = run_stage2(&reopened, dir.path(), Normalization::default(), true)
+        .expect_err("swapped cache");
+    assert!(
+        matches!(&err, Stage2Error::CacheChanged { path, .. } if *path == cache),
+        "{err}"
+    );
+    assert!(
+        err.to_string()
+            .contains("changed since stage1 validated it")
+    );
+}
+
+/// Stage1 context over a one-gene cache at `dir/kira-organelle.bin`, without
+/// stage1's mapping so stage2 has to map the file again.
+#[test]
+fn stage2_returns_the_real_value_rounding_counts() {
+    use crate::pipeline::stage1_load::{RunMode, run_stage1};
+
+    let dir = tempdir().expect("tempdir");
+    fs::write(dir.path().join("features.tsv"), "G1\tG1\nG2\tG2\n").expect("features");
+    fs::write(dir.path().join("barcodes.tsv"), "c1\nc2\n").expect("barcodes");
+    fs::write(
+        dir.path().join("matrix.mtx"),
+        "%%MatrixMarket matrix coordinate real general\n2 2 3\n1 1 2.6\n2 1 0.3\n2 2 4\n",
+    )
+    .expect("matrix");
+    let ctx = run_stage1(
+        dir.path(),
+        None,
+        dir.path(),
+        false,
+        RunMode::Standalone,
+        None,
+    )
+    .expect("stage1");
+    let expr = run_stage2(&ctx, dir.path(), Normalization::default(), false).expect("stage2");
+    assert_eq!(expr.value_warnings.n_rounded, 2);
+    assert_eq!(expr.value_warnings.n_rounded_to_zero, 1);
+    assert_eq!(expr.cell_stats[0].detected, 1);
+    assert_eq!(expr.expr.nnz(), 2);
+}
+
+fn reopening_ctx(dir: &Path) -> (DatasetCtx, std::path::PathBuf) {
+    use crate::pipeline::stage1_load::{RunMode, run_stage1};
+
+    let cache = dir.join("kira-organelle.bin");
+    let expr = ExprCsc {
+        n_genes: 1,
+        n_cells: 2,
+        nnz: 2,
+        col_ptr: vec![0, 1, 2],
+        row_idx: vec![0, 0],
+        values: vec![3, 4],
+    };
+    let barcodes = vec!["c1".to_string(), "c2".to_string()];
+    crate::input::cache::write_shared_cache(&cache, &["G1".to_string()], &barcodes, &expr)
+        .expect("write cache");
+    let ctx = run_stage1(dir, None, dir, true, RunMode::Pipeline, Some(&cache)).expect("stage1");
+    (
+        DatasetCtx {
+            shared_cache: None,
+            ..ctx
+        },
+        cache,
+    )
+}
+
+#[test]
+fn corrupted_cache_without_identity_fails_full_validation_once() {
+    let dir = tempdir().expect("tempdir");
+    let (ctx, cache) = reopening_ctx(dir.path());
+    let ctx = DatasetCtx {
+        shared_cache_identity: None,
+        ..ctx
+    };
+    let mut bytes = fs::read(&cache).expect("read cache");
+    bytes[..4].copy_from_slice(b"XXXX");
+    fs::write(&cache, bytes).expect("corrupt cache");
+
+    let err =
+        run_stage2(&ctx, dir.path(), Normalization::default(), true).expect_err("corrupted cache");
+    match &err {
+        Stage2Error::CacheReopen { path, mode, source } => {
+            assert_eq!(path, &cache);
+            assert_eq!(*mode, CacheParseMode::Full);
+            assert!(matches!(source, CacheError::InvalidMagic), "{source}");
+        }
+        other => panic!("unexpected error: {other}"),
+    }
+    let message = err.to_string();
+    assert!(message.contains(&cache.display().to_string()), "{message}");
+    assert!(message.contains("full validation"), "{message}");
+    assert!(message.contains("invalid cache magic"), "{message}");
+}
+
+#[test]
+fn io_error_on_reopen_keeps_the_unchecked_mode_and_os_error() {
+    let dir = tempdir().expect("tempdir");
+    let (ctx, cache) = reopening_ctx(dir.path());
+    assert!(ctx.shared_cache_identity.is_some());
+    // Injected IO failure: the file is gone by the time stage2 maps it.
+    fs::remove_file(&cache).expect("remove cache");
+
+    let err =
+        run_stage2(&ctx, dir.path(), Normalization::default(), true).expect_err("missing cache");
+    match &err {
+        Stage2Error::CacheReopen {
+            path,
+            mode,
+            source: CacheError::Io(io),
+        } => {
+            assert_eq!(path, &cache);
+            assert_eq!(*mode, CacheParseMode::Unchecked);
+            assert_eq!(io.kind(), std::io::ErrorKind::NotFound);
+        }
+        other => panic!("unexpected error: {other}"),
+    }
+    assert!(err.to_string().contains("unchecked parse"), "{err}");
+}
+
+/// Dense column-per-cell matrix standing in for an external backend.
+struct DenseMockSource {
+    n_genes: usize,
+    cells: Vec<Vec<u32>>,
+}
+
+impl CellExprSource for DenseMockSource {
+    fn n_genes(&self) -> usize {
+        self.n_genes
+    }
+
+    fn n_cells(&self) -> usize {
+        self.cells.len()
+    }
+
+    fn nnz(&self) -> usize {
+        self.cells.iter().flatten().filter(|v| **v > 0).count()
+    }
+
+    fn for_each_cell_raw<F>(&self, cell_idx: usize, mut f: F)
+    where
+        F: FnMut(u32, u32),
+    {
+        for (row, &v) in self.cells[cell_idx].iter().enumerate() {
+            if v > 0 {
+                f(row as u32, v);
+            }
+        }
+    }
+}
+
+#[test]
+fn mock_source_drives_stages_3_to_7() {
+    use crate::input::detect::TenXFormat;
+    use crate::input::features::GeneIndex;
+    use crate::model::axes::AxisConfig;
+    use crate::panels::defs::{PanelDef, PanelGene, PanelSet};
+    use crate::pipeline::stage1_load::RunMode;
+    use crate::pipeline::stage3_panels::run_stage3_panels;
+    use crate::pipeline::stage4_axes::run_stage4_axes_with_config;
+    use crate::pipeline::stage5_scores::run_stage5_scores;
+    use crate::pipeline::stage6_classify::run_stage6_classify;
+    use crate::pipeline::stage7_report::run_stage7_report;
+    use crate::pipeline::verify::run_verify;
+
+    let dir = tempdir().unwrap();
+    let source = DenseMockSource {
+        n_genes: 2,
+        cells: vec![vec![4, 0], vec![1, 3], vec![0, 0]],
+    };
+    let cell_stats = source.compute_cell_stats();
+    assert_eq!(cell_stats[1].libsize, 4);
+    assert_eq!(cell_stats[1].detected, 2);
+    let expr = ExprContext {
+        expr: source,
+        cell_stats,
+        normalization: Normalization::default(),
+        value_warnings: Default::default(),
+    };
+
+    let barcodes: Vec<String> = ["c1", "c2", "c3"].iter().map(|s| s.to_string()).collect();
+    let mut first_index_by_symbol = HashMap::new();
+    first_index_by_symbol.insert("SEC23A".to_string(), 1);
+    first_index_by_symbol.insert("SAR1A".to_string(), 2);
+    let dataset = DatasetCtx {
+        format: TenXFormat::TenXv3,
+        matrix_path: "matrix.mtx".into(),
+        features_path: "features.tsv".into(),
+        barcodes_path: "barcodes.tsv".into(),
+        features_delimiter: Default::default(),
+        barcodes_delimiter: Default::default(),
+        barcodes_header: None,
+        features_single_column_rows: 0,
+        shared_cache_path: None,
+        resolved_shared_cache_path: None,
+        shared_cache: None,
+        shared_cache_identity: None,
+        gene_index: GeneIndex {
+            rows: vec![],
+            duplicates: vec![],
+            first_index_by_symbol,
+        },
+        barcodes: barcodes.clone(),
+        n_genes: 2,
+        n_cells: 3,
+        nnz: expr.expr.nnz(),
+        duplicate_gene_symbols_count: 0,
+        duplicate_gene_symbols: vec![],
+        meta_present: false,
+        meta_cells_matched: 0,
+        meta_cells_missing: 0,
+        meta_issues: Default::default(),
+        gene_set_hash: String::new(),
+        upstream: None,
+        dense: None,
+        matrix_value_type: None,
+        matrix_orientation: None,
+    };
+    let panels = PanelSet {
+        panels: vec![PanelDef {
+            id: "ER_GOLGI".to_string(),
+            description: "".to_string(),
+            axis: "SIA".to_string(),
+            group: None,
+            genes: vec![
+                PanelGene {
+                    symbol: "SEC23A".to_string(),
+                },
+                PanelGene {
+                    symbol: "SAR1A".to_string(),
+                },
+            ],
+            required: vec!["SEC23A".to_string()],
+            weights: None,
+            custom_axis: false,
+            version: None,
+            source: None,
+        }],
+        files: vec![],
+        skipped: vec![],
+        required_autofixes: vec![],
+    };
+
+    let panels_ctx =
+        run_stage3_panels(&expr, &panels, &dataset.gene_index, &barcodes, dir.path()).unwrap();
+    assert_eq!(panels_ctx.per_cell[1].hits, vec![2]);
+    let axis_cfg = AxisConfig {
+        allow_missing_axes: true,
+        ..AxisConfig::default()
+    };
+    let axes_ctx =
+        run_stage4_axes_with_config(&dataset, &panels_ctx, dir.path(), &axis_cfg).unwrap();
+    let scores_ctx = run_stage5_scores(&axes_ctx, dir.path()).unwrap();
+    let classify_ctx =
+        run_stage6_classify(&dataset, &expr, &axes_ctx, &scores_ctx, dir.path()).unwrap();
+    let summary = run_stage7_report(
+        &dataset,
+        &expr,
+        &axes_ctx,
+        &scores_ctx,
+        &classify_ctx,
+        &panels_ctx,
+        dir.path(),
+        "cell",
+        RunMode::Standalone,
+        None,
+    )
+    .unwrap();
+    assert_eq!(summary.input.n_cells, 3);
+
+    let report = run_verify(dir.path(), Some(&barcodes)).unwrap();
+    assert!(report.passed(), "{}", report.render());
+}
+
+#[test]
+fn cancelled_flag_stops_stage3_at_checkpoint() {
+    use crate::input::features::GeneIndex;
+    use crate::panels::defs::{PanelDef, PanelGene, PanelSet};
+    use crate::pipeline::cancel::{self, CANCEL_CHECK_CELLS, CancellationToken};
+    use crate::pipeline::stage3_panels::{Stage3Error, run_stage3_panels};
+
+    let dir = tempdir().unwrap();
+    let n_cells = CANCEL_CHECK_CELLS + 10;
+    let source = DenseMockSource {
+        n_genes: 1,
+        cells: vec![vec![1]; n_cells],
+    };
+    let cell_stats = source.compute_cell_stats();
+    let expr = ExprContext {
+        expr: source,
+        cell_stats,
+        normalization: Normalization::default(),
+        value_warnings: Default::default(),
+    };
+    let barcodes: Vec<String> = (0..n_cells).map(|i| format!("c{i}")).collect();
+    let mut first_index_by_symbol = HashMap::new();
+    first_index_by_symbol.insert("SEC23A".to_string(), 1);
+    let gene_index = GeneIndex {
+        rows: vec![],
+        duplicates: vec![],
+        first_index_by_symbol,
+    };
+    let panels = PanelSet {
+        panels: vec![PanelDef {
+            id: "ER_GOLGI".to_string(),
+            description: "".to_string(),
+            axis: "SIA".to_string(),
+            group: None,
+            genes: vec![PanelGene {
+                symbol: "SEC23A".to_string(),
+            }],
+            required: vec![],
+            weights: None,
+            custom_axis: false,
+            version: None,
+            source: None,
+        }],
+        files: vec![],
+        skipped: vec![],
+        required_autofixes: vec![],
+    };
+
+    let flag = CancellationToken::new();
+    flag.cancel();
+    let _guard = cancel::enter(&flag);
+    let err = run_stage3_panels(&expr, &panels, &gene_index, &barcodes, dir.path()).unwrap_err();
+    assert!(matches!(err, Stage3Error::Cancelled(_)));
+}
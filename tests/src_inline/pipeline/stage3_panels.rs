@@ -1,6 +1,7 @@
 use super::*;
 use crate::expr::csc::ExprCsc;
-use crate::expr::normalize::Normalization;
+use crate::expr::normalize::{Normalization, NormalizationMethod};
+use crate::panels::loader::{PanelLoadOptions, load_panels_with_options};
 use crate::pipeline::stage2_normalize::ExprMatrix;
 use std::collections::HashMap;
 use std::fs;
@@ -37,6 +38,7 @@ fn panel_accumulation_correctness() {
             scale: 10_000.0,
             epsilon: 1e-8,
         },
+        value_warnings: Default::default(),
     };
 
     let panels = PanelSet {
@@ -44,6 +46,7 @@ fn panel_accumulation_correctness() {
             id: "P1".to_string(),
             description: "".to_string(),
             axis: "X".to_string(),
+            group: None,
             genes: vec![
                 crate::panels::defs::PanelGene {
                     symbol: "A".to_string(),
@@ -57,7 +60,13 @@ fn panel_accumulation_correctness() {
             ],
             required: vec!["A".to_string()],
             weights: None,
+            custom_axis: false,
+            version: None,
+            source: None,
         }],
+        files: vec![],
+        skipped: vec![],
+        required_autofixes: vec![],
     };
 
     let cell_ids = vec!["c1".to_string(), "c2".to_string()];
@@ -89,12 +98,14 @@ fn determinism_report_bytes() {
         expr: ExprMatrix::Owned(expr),
         cell_stats: stats,
         normalization: Normalization::default(),
+        value_warnings: Default::default(),
     };
     let panels = PanelSet {
         panels: vec![crate::panels::defs::PanelDef {
             id: "P1".to_string(),
             description: "".to_string(),
             axis: "X".to_string(),
+            group: None,
             genes: vec![
                 crate::panels::defs::PanelGene {
                     symbol: "A".to_string(),
@@ -105,7 +116,13 @@ fn determinism_report_bytes() {
             ],
             required: vec!["A".to_string()],
             weights: None,
+            custom_axis: false,
+            version: None,
+            source: None,
         }],
+        files: vec![],
+        skipped: vec![],
+        required_autofixes: vec![],
     };
     let mut idx = GeneIndex {
         rows: Vec::new(),
@@ -129,3 +146,413 @@ fn determinism_report_bytes() {
     let bytes2 = fs::read(out2.join("panels_report.tsv")).expect("read2");
     assert_eq!(bytes1, bytes2);
 }
+
+#[test]
+fn full_disk_fails_with_report_name_and_no_temp_file() {
+    let dir = tempdir().expect("tempdir");
+    let mtx = dir.path().join("matrix.mtx");
+    fs::write(
+        &mtx,
+        "%%MatrixMarket matrix coordinate integer general\n2 1 2\n1 1 1\n2 1 2\n",
+    )
+    .expect("write file");
+    let (expr, stats) = ExprCsc::from_mtx(&mtx, 2, 1, false).expect("csc");
+    let expr_ctx = ExprContext {
+        expr: ExprMatrix::Owned(expr),
+        cell_stats: stats,
+        normalization: Normalization::default(),
+        value_warnings: Default::default(),
+    };
+    let panels = PanelSet {
+        panels: vec![],
+        files: vec![],
+        skipped: vec![],
+        required_autofixes: vec![],
+    };
+    let out = dir.path().join("out");
+    fs::create_dir_all(&out).expect("mkdir");
+
+    let _guard = crate::report::artifact::fail_after(16);
+    let err = run_stage3_panels(
+        &expr_ctx,
+        &panels,
+        &build_gene_index(),
+        &["c1".to_string()],
+        &out,
+    )
+    .expect_err("disk full");
+    assert!(err.to_string().contains("panels_report.tsv"), "{err}");
+    assert_eq!(fs::read_dir(&out).expect("read_dir").count(), 0);
+}
+
+#[test]
+fn autofixed_required_gene_contributes_to_panel_sums() {
+    let dir = tempdir().expect("tempdir");
+    let mtx = dir.path().join("matrix.mtx");
+    fs::write(
+        &mtx,
+        "%%MatrixMarket matrix coordinate integer general\n3 2 3\n1 1 1\n2 1 2\n3 2 3\n",
+    )
+    .expect("write file");
+    let (expr, stats) = ExprCsc::from_mtx(&mtx, 3, 2, false).expect("csc");
+    let expr_ctx = ExprContext {
+        expr: ExprMatrix::Owned(expr),
+        cell_stats: stats,
+        normalization: Normalization {
+            enabled: false,
+            scale: 10_000.0,
+            epsilon: 1e-8,
+        },
+        value_warnings: Default::default(),
+    };
+    let panels_dir = dir.path().join("panels");
+    fs::create_dir_all(&panels_dir).expect("mkdir");
+    fs::write(
+        panels_dir.join("p.toml"),
+        "[[panel]]\nid = \"P1\"\ndescription = \"d\"\naxis = \"SIA\"\ngenes = [\"A\"]\nrequired = [\"C\"]\nweights = [2.0]\n",
+    )
+    .expect("write panel");
+    let opts = PanelLoadOptions {
+        autofix_required: true,
+        ..PanelLoadOptions::default()
+    };
+    let panels = load_panels_with_options(&panels_dir, &opts).expect("autofix");
+
+    let out_dir = dir.path().join("out");
+    fs::create_dir_all(&out_dir).expect("mkdir");
+    let cell_ids = vec!["c1".to_string(), "c2".to_string()];
+    let ctx = run_stage3_panels(&expr_ctx, &panels, &build_gene_index(), &cell_ids, &out_dir)
+        .expect("stage3");
+    assert_eq!(ctx.mappings[0].required_hits, 1);
+    assert!(ctx.warnings.is_empty());
+    let report = fs::read_to_string(out_dir.join("panels_report.tsv")).expect("report");
+    assert!(report.contains("c1\tP1\tSIA\t2.000000\t1\t"), "{report}");
+    assert!(report.contains("c2\tP1\tSIA\t3.000000\t1\t"), "{report}");
+}
+
+#[test]
+fn panel_gene_mapping_ties_genes_to_rows() {
+    let dir = tempdir().expect("tempdir");
+    let mtx = dir.path().join("matrix.mtx");
+    // Rows: A, B, A (duplicate symbol); cell 1 has A and B, cell 2 has B only.
+    fs::write(
+        &mtx,
+        "%%MatrixMarket matrix coordinate integer general\n3 2 3\n1 1 1\n2 1 2\n2 2 3\n",
+    )
+    .expect("write file");
+    let (expr, stats) = ExprCsc::from_mtx(&mtx, 3, 2, false).expect("csc");
+    let expr_ctx = ExprContext {
+        expr: ExprMatrix::Owned(expr),
+        cell_stats: stats,
+        normalization: Normalization::default(),
+        value_warnings: Default::default(),
+    };
+    let gene_index = crate::input::features::build_gene_index(
+        ["A", "B", "A"]
+            .iter()
+            .enumerate()
+            .map(|(i, s)| crate::input::features::FeatureRow {
+                id: format!("ENSG{i}"),
+                symbol: s.to_string(),
+            })
+            .collect(),
+    );
+    let panels = PanelSet {
+        panels: vec![crate::panels::defs::PanelDef {
+            id: "P1".to_string(),
+            description: "".to_string(),
+            axis: "SIA".to_string(),
+            group: None,
+            genes: ["A", "B", "Z"]
+                .iter()
+                .map(|s| crate::panels::defs::PanelGene {
+                    symbol: s.to_string(),
+                })
+                .collect(),
+            required: vec!["B".to_string()],
+            weights: Some(vec![2.0, 1.0, 0.5]),
+            custom_axis: false,
+            version: None,
+            source: None,
+        }],
+        files: vec![],
+        skipped: vec![],
+        required_autofixes: vec![],
+    };
+    let cell_ids = vec!["c1".to_string(), "c2".to_string()];
+    run_stage3_panels(&expr_ctx, &panels, &gene_index, &cell_ids, dir.path()).expect("stage3");
+
+    let tsv = fs::read_to_string(dir.path().join("panel_gene_mapping.tsv")).expect("read");
+    assert_eq!(
+        tsv,
+        "panel_id\tgene\tmatrix_row\tfeature_id\tresolution\tweight\trequired\tdetected_fraction\n\
+         P1\tA\t0\tENSG0\tsymbol_first_duplicate\t2.000000\tfalse\t0.500000\n\
+         P1\tB\t1\tENSG1\tsymbol\t1.000000\ttrue\t1.000000\n\
+         P1\tZ\t.\t.\tunmapped\t0.500000\tfalse\tnan\n"
+    );
+}
+
+#[test]
+fn missing_required_genes_go_to_mapping_warnings_once() {
+    let dir = tempdir().expect("tempdir");
+    let mtx = dir.path().join("matrix.mtx");
+    fs::write(
+        &mtx,
+        "%%MatrixMarket matrix coordinate integer general\n3 2 2\n1 1 1\n2 2 3\n",
+    )
+    .expect("write file");
+    let (expr, stats) = ExprCsc::from_mtx(&mtx, 3, 2, false).expect("csc");
+    let expr_ctx = ExprContext {
+        expr: ExprMatrix::Owned(expr),
+        cell_stats: stats,
+        normalization: Normalization::default(),
+        value_warnings: Default::default(),
+    };
+    let panel = |id: &str, genes: &[&str], required: &[&str]| crate::panels::defs::PanelDef {
+        id: id.to_string(),
+        description: "".to_string(),
+        axis: "SIA".to_string(),
+        group: None,
+        genes: genes
+            .iter()
+            .map(|s| crate::panels::defs::PanelGene {
+                symbol: s.to_string(),
+            })
+            .collect(),
+        required: required.iter().map(|s| s.to_string()).collect(),
+        weights: None,
+        custom_axis: false,
+        version: None,
+        source: None,
+    };
+    let panels = PanelSet {
+        panels: vec![
+            panel("P1", &["A", "B", "Z"], &["B", "Z"]),
+            panel("P2", &["A"], &["A"]),
+        ],
+        files: vec![],
+        skipped: vec![],
+        required_autofixes: vec![],
+    };
+    let cell_ids = vec!["c1".to_string(), "c2".to_string()];
+    let ctx = run_stage3_panels(
+        &expr_ctx,
+        &panels,
+        &build_gene_index(),
+        &cell_ids,
+        dir.path(),
+    )
+    .expect("stage3");
+    assert_eq!(ctx.warnings.len(), 1);
+
+    let warnings = fs::read_to_string(dir.path().join("mapping_warnings.tsv")).expect("read");
+    assert_eq!(
+        warnings,
+        "panel_id\taxis\tmissing_required\tmappable_fraction\n\
+         P1\tSIA\tZ\t0.666667\n"
+    );
+    let report = fs::read_to_string(dir.path().join("panels_report.tsv")).expect("read");
+    assert!(
+        report.starts_with("cell_id\tpanel_id\taxis\tsum\thits\tcoverage\trequired_missing\n"),
+        "{report}"
+    );
+    assert!(!report.contains('#'), "{report}");
+}
+
+#[test]
+fn dual_normalization_leaves_primary_outputs_unchanged() {
+    let dir = tempdir().expect("tempdir");
+    let mtx = dir.path().join("matrix.mtx");
+    // Gene A counts 1, 4, 2, 3 at library sizes 1, 400, 100, 200 (gene C pads
+    // them): raw and CP10K order the cells in reverse.
+    fs::write(
+        &mtx,
+        "%%MatrixMarket matrix coordinate integer general\n3 4 7\n\
+         1 1 1\n1 2 4\n3 2 396\n1 3 2\n3 3 98\n1 4 3\n3 4 197\n",
+    )
+    .expect("write file");
+    let (expr, stats) = ExprCsc::from_mtx(&mtx, 3, 4, false).expect("csc");
+    let expr_ctx = ExprContext {
+        expr: ExprMatrix::Owned(expr),
+        cell_stats: stats,
+        normalization: Normalization::default(),
+        value_warnings: Default::default(),
+    };
+    let panel = |id: &str, gene: &str| crate::panels::defs::PanelDef {
+        id: id.to_string(),
+        description: "".to_string(),
+        axis: "X".to_string(),
+        group: None,
+        genes: vec![crate::panels::defs::PanelGene {
+            symbol: gene.to_string(),
+        }],
+        required: vec![],
+        weights: None,
+        custom_axis: false,
+        version: None,
+        source: None,
+    };
+    let panels = PanelSet {
+        panels: vec![panel("P_A", "A"), panel("P_B", "B")],
+        files: vec![],
+        skipped: vec![],
+        required_autofixes: vec![],
+    };
+    let cell_ids: Vec<String> = (1..=4).map(|i| format!("c{i}")).collect();
+
+    let plain = dir.path().join("plain");
+    let dual = dir.path().join("dual");
+    fs::create_dir_all(&plain).expect("mkdir");
+    fs::create_dir_all(&dual).expect("mkdir");
+    let idx = build_gene_index();
+    let ctx_plain = run_stage3_panels(&expr_ctx, &panels, &idx, &cell_ids, &plain).expect("stage3");
+    let opts = Stage3Options {
+        dual_normalization: Some(NormalizationMethod::Raw),
+    };
+    let ctx_dual = run_stage3_panels_with_options(
+        &expr_ctx,
+        &panels,
+        &idx,
+        &cell_ids,
+        &dual,
+        &opts,
+        &mut RowRangeWarnings::default(),
+    )
+    .expect("stage3 dual");
+
+    for (a, b) in ctx_plain.per_cell.iter().zip(&ctx_dual.per_cell) {
+        let bits = |s: &[f32]| s.iter().map(|v| v.to_bits()).collect::<Vec<_>>();
+        assert_eq!(bits(&a.sums), bits(&b.sums));
+        assert_eq!(a.hits, b.hits);
+    }
+    let mut names: Vec<String> = fs::read_dir(&plain)
+        .expect("read_dir")
+        .map(|e| e.expect("entry").file_name().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    assert!(!names.is_empty());
+    for name in &names {
+        assert_eq!(
+            fs::read(plain.join(name)).expect("read"),
+            fs::read(dual.join(name)).expect("read"),
+            "{name}"
+        );
+    }
+    assert!(!plain.join("normalization_sensitivity.tsv").exists());
+
+    let text = fs::read_to_string(dual.join("normalization_sensitivity.tsv")).expect("tsv");
+    let rows: Vec<Vec<&str>> = text.lines().map(|l| l.split('\t').collect()).collect();
+    assert_eq!(
+        rows[0],
+        [
+            "panel_id",
+            "axis",
+            "secondary",
+            "median_abs_diff",
+            "spearman_rho",
+            "primary_median",
+            "secondary_median"
+        ]
+    );
+    assert_eq!(rows[1][..3], ["P_A", "X", "raw"]);
+    assert_eq!(rows[1][4], "-1.000000");
+    assert_eq!(rows[1][6], "2.500000");
+    // Never expressed: no difference, no ranking.
+    assert_eq!(rows[2][3..5], ["0.000000", "nan"]);
+}
+
+#[test]
+fn out_of_range_cache_rows_are_skipped_and_counted() {
+    use crate::input::cache::{CscCheck, map_shared_cache, write_shared_cache};
+
+    let dir = tempdir().expect("tempdir");
+    let genes: Vec<String> = ["A", "B", "C"].map(String::from).to_vec();
+    let cell_ids = vec!["c1".to_string(), "c2".to_string()];
+    // The crafted cache adds rows u32::MAX and 7 to a 3-gene matrix; the
+    // clean one holds only the valid entries.
+    let crafted = ExprCsc {
+        n_genes: 3,
+        n_cells: 2,
+        nnz: 5,
+        col_ptr: vec![0, 3, 5],
+        row_idx: vec![0, 1, u32::MAX, 1, 7],
+        values: vec![1, 2, 5, 3, 4],
+    };
+    let clean = ExprCsc {
+        n_genes: 3,
+        n_cells: 2,
+        nnz: 3,
+        col_ptr: vec![0, 2, 3],
+        row_idx: vec![0, 1, 1],
+        values: vec![1, 2, 3],
+    };
+    let run = |name: &str, expr: &ExprCsc, warnings: &mut RowRangeWarnings| {
+        let path = dir.path().join(format!("{name}.bin"));
+        write_shared_cache(&path, &genes, &cell_ids, expr).expect("write cache");
+        // Column pointers only: the row indices are not checked.
+        let mapped = map_shared_cache(&path, CscCheck::ColPtr).expect("map");
+        let expr_ctx = ExprContext {
+            cell_stats: mapped.compute_cell_stats(),
+            expr: ExprMatrix::Shared(mapped),
+            normalization: Normalization {
+                enabled: false,
+                ..Normalization::default()
+            },
+            value_warnings: Default::default(),
+        };
+        let panels = PanelSet {
+            panels: vec![crate::panels::defs::PanelDef {
+                id: "P1".to_string(),
+                description: "".to_string(),
+                axis: "X".to_string(),
+                group: None,
+                genes: genes
+                    .iter()
+                    .map(|g| crate::panels::defs::PanelGene { symbol: g.clone() })
+                    .collect(),
+                required: vec!["A".to_string(), "B".to_string()],
+                weights: None,
+                custom_axis: false,
+                version: None,
+                source: None,
+            }],
+            files: vec![],
+            skipped: vec![],
+            required_autofixes: vec![],
+        };
+        let out = dir.path().join(name);
+        fs::create_dir_all(&out).expect("mkdir");
+        run_stage3_panels_with_options(
+            &expr_ctx,
+            &panels,
+            &build_gene_index(),
+            &cell_ids,
+            &out,
+            &Stage3Options::default(),
+            warnings,
+        )
+        .expect("stage3")
+    };
+
+    let mut warnings = RowRangeWarnings::default();
+    let crafted_ctx = run("crafted", &crafted, &mut warnings);
+    assert_eq!(
+        warnings,
+        RowRangeWarnings {
+            n_out_of_range: 2,
+            max_row: u32::MAX,
+            first_cell: Some("c1".to_string()),
+        }
+    );
+
+    let mut none = RowRangeWarnings::default();
+    let clean_ctx = run("clean", &clean, &mut none);
+    assert_eq!(none, RowRangeWarnings::default());
+    for (a, b) in crafted_ctx.per_cell.iter().zip(&clean_ctx.per_cell) {
+        assert_eq!(a.sums, b.sums);
+        assert_eq!(a.hits, b.hits);
+        assert_eq!(a.required_missing, b.required_missing);
+    }
+    assert_eq!(clean_ctx.per_cell[0].hits, vec![2]);
+    assert_eq!(clean_ctx.per_cell[1].sums, vec![3.0]);
+}
@@ -5,6 +5,14 @@ use std::collections::HashMap;
 use std::fs;
 use tempfile::tempdir;
 
+/// The fixtures cover only some core axes.
+fn partial_cfg() -> AxisConfig {
+    AxisConfig {
+        allow_missing_axes: true,
+        ..AxisConfig::default()
+    }
+}
+
 fn make_panels_ctx() -> PanelsContext {
     let panels = PanelSet {
         panels: vec![
@@ -12,33 +20,48 @@ fn make_panels_ctx() -> PanelsContext {
                 id: "P_SIA".to_string(),
                 description: "".to_string(),
                 axis: "SIA".to_string(),
+                group: None,
                 genes: vec![PanelGene {
                     symbol: "A".to_string(),
                 }],
                 required: vec!["A".to_string()],
                 weights: None,
+                custom_axis: false,
+                version: None,
+                source: None,
             },
             PanelDef {
                 id: "P_EXP".to_string(),
                 description: "".to_string(),
                 axis: "EEB_EXPORT".to_string(),
+                group: None,
                 genes: vec![PanelGene {
                     symbol: "B".to_string(),
                 }],
                 required: vec!["B".to_string()],
                 weights: None,
+                custom_axis: false,
+                version: None,
+                source: None,
             },
             PanelDef {
                 id: "P_DEG".to_string(),
                 description: "".to_string(),
                 axis: "EEB_DEGRADE".to_string(),
+                group: None,
                 genes: vec![PanelGene {
                     symbol: "C".to_string(),
                 }],
                 required: vec!["C".to_string()],
                 weights: None,
+                custom_axis: false,
+                version: None,
+                source: None,
             },
         ],
+        files: vec![],
+        skipped: vec![],
+        required_autofixes: vec![],
     };
     let mut mappings = Vec::new();
     for panel in &panels.panels {
@@ -54,6 +77,7 @@ fn make_panels_ctx() -> PanelsContext {
         mappings,
         warnings: Vec::new(),
         cell_ids: vec!["c1".to_string()],
+        dead_panels: Vec::new(),
         per_cell: vec![PanelCellPacked {
             sums: vec![2.0, 3.0, 1.0],
             hits: vec![1, 1, 1],
@@ -72,8 +96,14 @@ fn axis_correctness() {
         matrix_path: dir.path().join("matrix.mtx"),
         features_path: dir.path().join("features.tsv"),
         barcodes_path: dir.path().join("barcodes.tsv"),
+        features_delimiter: Default::default(),
+        barcodes_delimiter: Default::default(),
+        barcodes_header: None,
+        features_single_column_rows: 0,
         shared_cache_path: None,
         resolved_shared_cache_path: None,
+        shared_cache: None,
+        shared_cache_identity: None,
         gene_index: crate::input::features::GeneIndex {
             rows: Vec::new(),
             duplicates: Vec::new(),
@@ -88,8 +118,14 @@ fn axis_correctness() {
         meta_present: false,
         meta_cells_matched: 0,
         meta_cells_missing: 0,
+        meta_issues: Default::default(),
+        gene_set_hash: String::new(),
+        upstream: None,
+        dense: None,
+        matrix_value_type: None,
+        matrix_orientation: None,
     };
-    let axes = run_stage4_axes(&dummy, &ctx, dir.path()).expect("axes");
+    let axes = run_stage4_axes_with_config(&dummy, &ctx, dir.path(), &partial_cfg()).expect("axes");
     let sia = axes.values[0].sia;
     let eeb = axes.values[0].eeb;
     let sia_expected = 2.0 / (2.0 + 1.0);
@@ -98,11 +134,64 @@ fn axis_correctness() {
     assert!((eeb - eeb_expected).abs() < 1e-6);
 }
 
+#[test]
+fn axes_raw_appends_pre_map_sums() {
+    let ctx = make_panels_ctx();
+    let dir = tempdir().expect("tempdir");
+    let header = |path: &Path| {
+        fs::read_to_string(path.join("axes.tsv"))
+            .expect("read")
+            .lines()
+            .next()
+            .expect("header")
+            .to_string()
+    };
+
+    let plain = dir.path().join("plain");
+    fs::create_dir_all(&plain).expect("mkdir");
+    run_stage4_axes_with_config(&dataset_for(&ctx), &ctx, &plain, &partial_cfg()).expect("axes");
+    assert!(header(&plain).ends_with("\tdrivers_GDI"));
+
+    let raw = dir.path().join("raw");
+    fs::create_dir_all(&raw).expect("mkdir");
+    let cfg = AxisConfig {
+        raw_sums: true,
+        ..partial_cfg()
+    };
+    run_stage4_axes_with_config(&dataset_for(&ctx), &ctx, &raw, &cfg).expect("axes");
+    let tsv = fs::read_to_string(raw.join("axes.tsv")).expect("read");
+    let mut lines = tsv.lines();
+    let header: Vec<&str> = lines.next().expect("header").split('\t').collect();
+    let row: Vec<&str> = lines.next().expect("row").split('\t').collect();
+    assert_eq!(header.len(), row.len());
+    let raw_cols: Vec<(&str, &str)> = header
+        .iter()
+        .zip(&row)
+        .filter(|(h, _)| h.starts_with("raw_"))
+        .map(|(h, v)| (*h, *v))
+        .collect();
+    assert_eq!(
+        raw_cols,
+        [
+            ("raw_SIA", "2.000000"),
+            ("raw_EEB_EXPORT", "3.000000"),
+            ("raw_EEB_DEGRADE", "1.000000"),
+            ("raw_SLI", "nan"),
+            ("raw_MEI", "nan"),
+            ("raw_ECMI", "nan"),
+            ("raw_APCI", "nan"),
+            ("raw_GDI", "nan"),
+        ]
+    );
+    // The mapped SIA value is unchanged by the flag.
+    assert_eq!(row[1], "0.666667");
+}
+
 #[test]
 fn driver_determinism() {
     let ids = vec!["B".to_string(), "A".to_string()];
     let vals = vec![1.0, 1.0];
-    let drivers = top_k_panels(&ids, &vals, 2);
+    let drivers = top_k_panels(&ids, &vals, 2, ZeroDrivers::Drop);
     assert_eq!(drivers[0].panel_id, "A");
 }
 
@@ -116,8 +205,14 @@ fn axes_determinism_report() {
         matrix_path: dir.path().join("matrix.mtx"),
         features_path: dir.path().join("features.tsv"),
         barcodes_path: dir.path().join("barcodes.tsv"),
+        features_delimiter: Default::default(),
+        barcodes_delimiter: Default::default(),
+        barcodes_header: None,
+        features_single_column_rows: 0,
         shared_cache_path: None,
         resolved_shared_cache_path: None,
+        shared_cache: None,
+        shared_cache_identity: None,
         gene_index: crate::input::features::GeneIndex {
             rows: Vec::new(),
             duplicates: Vec::new(),
@@ -132,16 +227,36 @@ fn axes_determinism_report() {
         meta_present: false,
         meta_cells_matched: 0,
         meta_cells_missing: 0,
+        meta_issues: Default::default(),
+        gene_set_hash: String::new(),
+        upstream: None,
+        dense: None,
+        matrix_value_type: None,
+        matrix_orientation: None,
     };
     let out1 = dir.path().join("out1");
     let out2 = dir.path().join("out2");
     fs::create_dir_all(&out1).expect("mkdir");
     fs::create_dir_all(&out2).expect("mkdir");
-    run_stage4_axes(&dummy, &ctx, &out1).expect("axes1");
-    run_stage4_axes(&dummy, &ctx, &out2).expect("axes2");
+    run_stage4_axes_with_config(&dummy, &ctx, &out1, &partial_cfg()).expect("axes1");
+    run_stage4_axes_with_config(&dummy, &ctx, &out2, &partial_cfg()).expect("axes2");
     let a = fs::read(out1.join("axes.tsv")).expect("read1");
     let b = fs::read(out2.join("axes.tsv")).expect("read2");
     assert_eq!(a, b);
+
+    let a = fs::read_to_string(out1.join("axes_summary.json")).expect("read1");
+    let b = fs::read_to_string(out2.join("axes_summary.json")).expect("read2");
+    assert_eq!(a, b);
+    let summary: serde_json::Value = serde_json::from_str(&a).expect("json");
+    assert_eq!(summary["sia"]["present"], true);
+    assert!(summary["sia"]["coverage"]["median"].is_number());
+    let numbers = a
+        .lines()
+        .filter_map(|l| l.split_once(": ").map(|(_, v)| v.trim_end_matches(',')))
+        .filter(|v| v.starts_with(|c: char| c.is_ascii_digit()));
+    for value in numbers {
+        assert_eq!(value.split('.').nth(1).map(str::len), Some(6), "{value}");
+    }
 }
 
 #[test]
@@ -151,12 +266,19 @@ fn coverage_correctness() {
             id: "P1".to_string(),
             description: "".to_string(),
             axis: "SIA".to_string(),
+            group: None,
             genes: vec![PanelGene {
                 symbol: "A".to_string(),
             }],
             required: vec!["A".to_string(), "B".to_string()],
             weights: None,
+            custom_axis: false,
+            version: None,
+            source: None,
         }],
+        files: vec![],
+        skipped: vec![],
+        required_autofixes: vec![],
     };
     let mappings = vec![crate::panels::mapping::GeneMapping {
         panel_id: "P1".to_string(),
@@ -169,15 +291,459 @@ fn coverage_correctness() {
         mappings,
         warnings: Vec::new(),
         cell_ids: vec!["c1".to_string()],
+        dead_panels: Vec::new(),
         per_cell: vec![PanelCellPacked {
             sums: vec![1.0],
             hits: vec![1],
             required_missing: vec![1],
         }],
     };
-    let indices = build_axis_indices(&ctx.panels);
-    let (vals, cov, _) =
+    let indices = build_axis_indices(&ctx.panels, &[]);
+    let (vals, cov, _, _) =
         compute_cell_axes(&indices, &ctx, &ctx.per_cell[0], &AxisConfig::default());
     assert!((vals.sia - 0.5).abs() < 1e-6);
     assert!((cov.sia - 0.5).abs() < 1e-6);
 }
+
+#[test]
+fn axis_curve_replaces_saturating_map_for_its_axis() {
+    let (vals, _, _, raw) = {
+        let ctx = low_depth_panels_ctx();
+        let indices = build_axis_indices(&ctx.panels, &[]);
+        let curve = crate::model::axis_curves::CalibrationCurve {
+            breakpoints: vec![(0.0, 0.0), (4.0, 0.2), (8.0, 1.0)],
+        };
+        let cfg = AxisConfig {
+            curves: Some(crate::model::axis_curves::AxisCurves {
+                curves: [("SIA".to_string(), curve)].into(),
+            }),
+            ..AxisConfig::default()
+        };
+        compute_cell_axes(&indices, &ctx, &ctx.per_cell[0], &cfg)
+    };
+    let expected = if raw.sia >= 8.0 {
+        1.0
+    } else if raw.sia >= 4.0 {
+        0.2 + 0.8 * (raw.sia - 4.0) / 4.0
+    } else {
+        0.05 * raw.sia
+    };
+    assert!((vals.sia - expected).abs() < 1e-6, "{raw:?} {}", vals.sia);
+    // Axes without a curve keep the saturating map.
+    assert_eq!(vals.gdi, AxisConfig::default().map_axis("GDI", raw.gdi));
+}
+
+fn low_depth_panels_ctx() -> PanelsContext {
+    let panels = PanelSet {
+        panels: vec![PanelDef {
+            id: "P1".to_string(),
+            description: "".to_string(),
+            axis: "SIA".to_string(),
+            group: None,
+            genes: ["A", "B", "C", "D"]
+                .iter()
+                .map(|g| PanelGene {
+                    symbol: g.to_string(),
+                })
+                .collect(),
+            required: vec!["A".to_string()],
+            weights: None,
+            custom_axis: false,
+            version: None,
+            source: None,
+        }],
+        files: vec![],
+        skipped: vec![],
+        required_autofixes: vec![],
+    };
+    let mappings = vec![crate::panels::mapping::GeneMapping {
+        panel_id: "P1".to_string(),
+        mapped: vec![Some(0), Some(1), Some(2), Some(3)],
+        required_hits: 1,
+        required_total: 1,
+    }];
+    PanelsContext {
+        panels,
+        mappings,
+        warnings: Vec::new(),
+        cell_ids: vec!["shallow".to_string(), "deep".to_string()],
+        dead_panels: Vec::new(),
+        per_cell: vec![
+            PanelCellPacked {
+                sums: vec![0.7],
+                hits: vec![1],
+                required_missing: vec![0],
+            },
+            PanelCellPacked {
+                sums: vec![4.0],
+                hits: vec![4],
+                required_missing: vec![0],
+            },
+        ],
+    }
+}
+
+#[test]
+fn detection_coverage_penalizes_low_depth_cell() {
+    let ctx = low_depth_panels_ctx();
+    let indices = build_axis_indices(&ctx.panels, &[]);
+    let required = AxisConfig::default();
+    let detection = AxisConfig {
+        coverage_mode: CoverageMode::Detection,
+        ..AxisConfig::default()
+    };
+
+    let (_, cov_req, _, _) = compute_cell_axes(&indices, &ctx, &ctx.per_cell[0], &required);
+    let (_, cov_det, _, _) = compute_cell_axes(&indices, &ctx, &ctx.per_cell[0], &detection);
+    assert!((cov_req.sia - 1.0).abs() < 1e-6);
+    assert!((cov_det.sia - 0.25).abs() < 1e-6);
+
+    let (_, cov_deep, _, _) = compute_cell_axes(&indices, &ctx, &ctx.per_cell[1], &detection);
+    assert!((cov_deep.sia - 1.0).abs() < 1e-6);
+}
+
+#[test]
+fn detection_coverage_lowers_composite_confidence() {
+    let ctx = low_depth_panels_ctx();
+    let dir = tempdir().expect("tempdir");
+    let dummy = DatasetCtx {
+        format: crate::input::detect::TenXFormat::TenXv3,
+        matrix_path: dir.path().join("matrix.mtx"),
+        features_path: dir.path().join("features.tsv"),
+        barcodes_path: dir.path().join("barcodes.tsv"),
+        features_delimiter: Default::default(),
+        barcodes_delimiter: Default::default(),
+        barcodes_header: None,
+        features_single_column_rows: 0,
+        shared_cache_path: None,
+        resolved_shared_cache_path: None,
+        shared_cache: None,
+        shared_cache_identity: None,
+        gene_index: crate::input::features::GeneIndex {
+            rows: Vec::new(),
+            duplicates: Vec::new(),
+            first_index_by_symbol: HashMap::new(),
+        },
+        barcodes: ctx.cell_ids.clone(),
+        n_genes: 4,
+        n_cells: 2,
+        nnz: 5,
+        duplicate_gene_symbols_count: 0,
+        duplicate_gene_symbols: Vec::new(),
+        meta_present: false,
+        meta_cells_matched: 0,
+        meta_cells_missing: 0,
+        meta_issues: Default::default(),
+        gene_set_hash: String::new(),
+        upstream: None,
+        dense: None,
+        matrix_value_type: None,
+        matrix_orientation: None,
+    };
+    let detection = AxisConfig {
+        coverage_mode: CoverageMode::Detection,
+        ..partial_cfg()
+    };
+    let axes_req =
+        run_stage4_axes_with_config(&dummy, &ctx, dir.path(), &partial_cfg()).expect("axes");
+    let axes_det = run_stage4_axes_with_config(&dummy, &ctx, dir.path(), &detection).expect("axes");
+    assert_eq!(axes_req.coverage_mode, CoverageMode::Required);
+    assert_eq!(axes_det.coverage_mode, CoverageMode::Detection);
+
+    let scores_req =
+        crate::pipeline::stage5_scores::run_stage5_scores(&axes_req, dir.path()).expect("s");
+    let scores_det =
+        crate::pipeline::stage5_scores::run_stage5_scores(&axes_det, dir.path()).expect("s");
+    assert!(scores_det.cov_oii[0] < scores_req.cov_oii[0]);
+    assert!(scores_det.cov_oii[0] < scores_det.cov_oii[1]);
+}
+
+fn dataset_for(ctx: &PanelsContext) -> DatasetCtx {
+    DatasetCtx {
+        format: crate::input::detect::TenXFormat::TenXv3,
+        matrix_path: "matrix.mtx".into(),
+        features_path: "features.tsv".into(),
+        barcodes_path: "barcodes.tsv".into(),
+        features_delimiter: Default::default(),
+        barcodes_delimiter: Default::default(),
+        barcodes_header: None,
+        features_single_column_rows: 0,
+        shared_cache_path: None,
+        resolved_shared_cache_path: None,
+        shared_cache: None,
+        shared_cache_identity: None,
+        gene_index: crate::input::features::GeneIndex {
+            rows: Vec::new(),
+            duplicates: Vec::new(),
+            first_index_by_symbol: HashMap::new(),
+        },
+        barcodes: ctx.cell_ids.clone(),
+        n_genes: 3,
+        n_cells: ctx.cell_ids.len(),
+        nnz: 3,
+        duplicate_gene_symbols_count: 0,
+        duplicate_gene_symbols: Vec::new(),
+        meta_present: false,
+        meta_cells_matched: 0,
+        meta_cells_missing: 0,
+        meta_issues: Default::default(),
+        gene_set_hash: String::new(),
+        upstream: None,
+        dense: None,
+        matrix_value_type: None,
+        matrix_orientation: None,
+    }
+}
+
+#[test]
+fn missing_core_axes_fail_by_default() {
+    let ctx = make_panels_ctx();
+    let dir = tempdir().expect("tempdir");
+    let err = run_stage4_axes(&dataset_for(&ctx), &ctx, dir.path())
+        .expect_err("SLI/MEI/ECMI/GDI have no panels");
+    let Stage4Error::MissingAxes { axes } = &err else {
+        panic!("unexpected error: {err}");
+    };
+    assert!(
+        axes.starts_with("SLI (panels=0, mappable_genes=0)"),
+        "{axes}"
+    );
+    for axis in ["MEI", "ECMI", "GDI"] {
+        assert!(axes.contains(axis), "{axes}");
+    }
+    assert!(!axes.contains("SIA") && !axes.contains("EEB"), "{axes}");
+    assert!(err.to_string().contains("--allow-missing-axes"));
+}
+
+#[test]
+fn allowed_missing_axes_are_not_present() {
+    let mut ctx = make_panels_ctx();
+    // EEB_DEGRADE is defined but none of its genes map.
+    ctx.mappings[2].mapped = vec![None];
+    let dir = tempdir().expect("tempdir");
+    let axes = run_stage4_axes_with_config(&dataset_for(&ctx), &ctx, dir.path(), &partial_cfg())
+        .expect("allowed");
+
+    let counts = axes.panel_counts;
+    assert_eq!(
+        counts.sia,
+        AxisPanelCount {
+            panels: 1,
+            mapped_panels: 1,
+            mappable_genes: 1
+        }
+    );
+    assert_eq!(
+        counts.eeb_degrade,
+        AxisPanelCount {
+            panels: 1,
+            mapped_panels: 0,
+            mappable_genes: 0
+        }
+    );
+    assert_eq!(counts.sli, AxisPanelCount::default());
+
+    assert!(axes.stats.sia.present);
+    for entry in [&axes.stats.eeb, &axes.stats.sli, &axes.stats.gdi] {
+        assert!(!entry.present);
+    }
+    let (v, c, d) = (&axes.values[0], &axes.coverage[0], &axes.drivers[0]);
+    assert!((v.sia - 2.0 / 3.0).abs() < 1e-6);
+    assert!(v.eeb.is_nan() && v.sli.is_nan() && v.gdi.is_nan());
+    assert_eq!((c.eeb, c.sli, c.gdi), (0.0, 0.0, 0.0));
+    assert_eq!((d.eeb.as_str(), d.sli.as_str()), (".", "."));
+
+    let tsv = fs::read_to_string(dir.path().join("axes.tsv")).expect("read");
+    let row: Vec<&str> = tsv.lines().nth(1).expect("row").split('\t').collect();
+    assert_eq!(&row[2..4], ["nan", "nan"], "EEB and SLI values");
+}
+
+/// Genes A..C over two cells; C is never expressed, X is not in the dataset.
+fn dead_panel_fixture(dir: &Path) -> (DatasetCtx, PanelsContext) {
+    use crate::expr::csc::ExprCsc;
+    use crate::expr::normalize::Normalization;
+    use crate::pipeline::stage2_normalize::{ExprContext, ExprMatrix};
+    use crate::pipeline::stage3_panels::run_stage3_panels;
+
+    let mtx = dir.join("matrix.mtx");
+    fs::write(
+        &mtx,
+        "%%MatrixMarket matrix coordinate integer general\n3 2 3\n1 1 2\n2 1 3\n2 2 1\n",
+    )
+    .expect("write");
+    let (expr, stats) = ExprCsc::from_mtx(&mtx, 3, 2, false).expect("csc");
+    let expr = ExprContext {
+        expr: ExprMatrix::Owned(expr),
+        cell_stats: stats,
+        normalization: Normalization {
+            enabled: false,
+            ..Normalization::default()
+        },
+        value_warnings: Default::default(),
+    };
+    let mut first_index_by_symbol = HashMap::new();
+    for (i, symbol) in ["A", "B", "C"].iter().enumerate() {
+        first_index_by_symbol.insert(symbol.to_string(), i + 1);
+    }
+    let panel = |id: &str, axis: &str, genes: &[&str]| PanelDef {
+        id: id.to_string(),
+        description: String::new(),
+        axis: axis.to_string(),
+        group: None,
+        genes: genes
+            .iter()
+            .map(|g| PanelGene {
+                symbol: g.to_string(),
+            })
+            .collect(),
+        required: genes.iter().map(|g| g.to_string()).collect(),
+        weights: None,
+        custom_axis: false,
+        version: None,
+        source: None,
+    };
+    let panels = PanelSet {
+        panels: vec![
+            panel("P_SIA", "SIA", &["A"]),
+            panel("P_SIA_DEAD", "SIA", &["X"]),
+            panel("P_EXPORT", "EEB_EXPORT", &["B"]),
+            panel("P_DEGRADE", "EEB_DEGRADE", &["C"]),
+        ],
+        files: vec![],
+        skipped: vec![],
+        required_autofixes: vec![],
+    };
+    let barcodes = vec!["c1".to_string(), "c2".to_string()];
+    let dataset = DatasetCtx {
+        format: crate::input::detect::TenXFormat::TenXv3,
+        matrix_path: mtx,
+        features_path: dir.join("features.tsv"),
+        barcodes_path: dir.join("barcodes.tsv"),
+        features_delimiter: Default::default(),
+        barcodes_delimiter: Default::default(),
+        barcodes_header: None,
+        features_single_column_rows: 0,
+        shared_cache_path: None,
+        resolved_shared_cache_path: None,
+        shared_cache: None,
+        shared_cache_identity: None,
+        gene_index: crate::input::features::GeneIndex {
+            rows: Vec::new(),
+            duplicates: Vec::new(),
+            first_index_by_symbol,
+        },
+        barcodes: barcodes.clone(),
+        n_genes: 3,
+        n_cells: 2,
+        nnz: 3,
+        duplicate_gene_symbols_count: 0,
+        duplicate_gene_symbols: Vec::new(),
+        meta_present: false,
+        meta_cells_matched: 0,
+        meta_cells_missing: 0,
+        meta_issues: Default::default(),
+        gene_set_hash: String::new(),
+        upstream: None,
+        dense: None,
+        matrix_value_type: None,
+        matrix_orientation: None,
+    };
+    let panels_ctx =
+        run_stage3_panels(&expr, &panels, &dataset.gene_index, &barcodes, dir).expect("stage3");
+    (dataset, panels_ctx)
+}
+
+#[test]
+fn dead_panels_are_reported_and_dropped_on_request() {
+    let dir = tempdir().expect("tempdir");
+    let (dataset, panels_ctx) = dead_panel_fixture(dir.path());
+    let dead: Vec<&str> = panels_ctx
+        .dead_panels
+        .iter()
+        .map(|p| p.panel_id.as_str())
+        .collect();
+    assert_eq!(dead, vec!["P_SIA_DEAD", "P_DEGRADE"]);
+    assert_eq!(panels_ctx.dead_panels[1].nonzero_fraction, 0.0);
+
+    let kept =
+        run_stage4_axes_with_config(&dataset, &panels_ctx, dir.path(), &partial_cfg()).unwrap();
+    assert!(kept.dropped_panels.is_empty());
+    // The never-expressed degrade panel pins EEB to its export-only maximum
+    // and the absent SIA panel halves SIA coverage.
+    assert!((kept.values[0].eeb - 1.0).abs() < 1e-6);
+    assert!((kept.coverage[0].sia - 0.5).abs() < 1e-6);
+
+    let cfg = AxisConfig {
+        drop_dead_panels: true,
+        ..partial_cfg()
+    };
+    let dropped = run_stage4_axes_with_config(&dataset, &panels_ctx, dir.path(), &cfg).unwrap();
+    assert_eq!(dropped.dropped_panels, vec!["P_SIA_DEAD", "P_DEGRADE"]);
+    assert!(dropped.values[0].eeb.is_nan());
+    assert!(!dropped.stats.eeb.present);
+    assert_eq!(dropped.values[0].sia, kept.values[0].sia);
+    assert!((dropped.coverage[0].sia - 1.0).abs() < 1e-6);
+}
+
+#[test]
+fn covariate_panels_are_left_out_of_axes() {
+    let base = make_panels_ctx();
+    let base_indices = build_axis_indices(&base.panels, &[]);
+    let (expected, _, _, _) = compute_cell_axes(
+        &base_indices,
+        &base,
+        &base.per_cell[0],
+        &AxisConfig::default(),
+    );
+
+    let mut ctx = make_panels_ctx();
+    let mut covariate = ctx.panels.panels[0].clone();
+    covariate.id = "PROLIFERATION".to_string();
+    covariate.axis = crate::panels::defs::COVARIATE_AXIS.to_string();
+    ctx.panels.panels.push(covariate);
+    ctx.mappings.push(ctx.mappings[0].clone());
+    ctx.per_cell[0].sums.push(50.0);
+    ctx.per_cell[0].hits.push(1);
+    ctx.per_cell[0].required_missing.push(0);
+
+    let indices = build_axis_indices(&ctx.panels, &[]);
+    assert_eq!(indices.sia, vec![0]);
+    let (values, _, _, _) =
+        compute_cell_axes(&indices, &ctx, &ctx.per_cell[0], &AxisConfig::default());
+    assert_eq!(values.sia, expected.sia);
+    assert_eq!(values.eeb, expected.eeb);
+}
+
+#[test]
+fn every_registry_panel_axis_is_handled_by_build_axis_indices() {
+    use crate::model::catalog::{AXES, PanelAxis};
+
+    let tags: Vec<PanelAxis> = AXES
+        .iter()
+        .flat_map(|axis| axis.panel_axes.iter().copied())
+        .collect();
+    let panels = PanelSet {
+        panels: tags
+            .iter()
+            .map(|tag| PanelDef {
+                id: format!("P_{}", tag.tag()),
+                description: String::new(),
+                axis: tag.tag().to_string(),
+                group: None,
+                genes: Vec::new(),
+                required: Vec::new(),
+                weights: None,
+                custom_axis: false,
+                version: None,
+                source: None,
+            })
+            .collect(),
+        files: Vec::new(),
+        skipped: Vec::new(),
+        required_autofixes: Vec::new(),
+    };
+    let mut indices = build_axis_indices(&panels, &[]);
+    for (idx, tag) in tags.iter().enumerate() {
+        assert_eq!(indices.slot_mut(*tag), &vec![idx], "{}", tag.tag());
+    }
+}
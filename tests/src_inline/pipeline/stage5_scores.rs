@@ -1,5 +1,5 @@
 use super::*;
-use crate::model::axes::{AxisCoverage, AxisValues};
+use crate::model::axes::{AxisCoverage, AxisValues, CoverageMode};
 use crate::pipeline::stage4_axes::{
     AxesContext, AxesSummary, AxisDrivers, AxisStats, AxisSummaryEntry,
 };
@@ -140,6 +140,10 @@ fn dummy_axes(values: AxisValues, coverage: AxisCoverage) -> AxesContext {
                 },
             },
         },
+        coverage_mode: CoverageMode::Required,
+        panel_counts: Default::default(),
+        dropped_panels: Vec::new(),
+        curves: None,
     }
 }
 
@@ -175,7 +179,7 @@ fn composite_correctness_apci_present() {
 
 #[test]
 fn composite_correctness_apci_absent() {
-    let axes = dummy_axes(
+    let mut axes = dummy_axes(
         AxisValues {
             sia: 0.2,
             eeb: -0.2,
@@ -195,11 +199,17 @@ fn composite_correctness_apci_absent() {
             gdi: 1.0,
         },
     );
+    axes.stats.apci.present = false;
     let dir = tempdir().expect("tempdir");
     let scores = run_stage5_scores(&axes, dir.path()).expect("scores");
     let eeb_pos = pos_eeb(-0.2);
     let expected = clamp01(0.30 * 0.4 + 0.30 * 0.5 + 0.25 * 0.2 + 0.15 * eeb_pos);
     assert!((scores.iai[0] - expected).abs() < 1e-6);
+    assert_eq!(scores.iai_weightset, IaiWeightSet::NoApci);
+    assert_eq!(scores.apci_nan_cells, 0);
+    let tsv = std::fs::read_to_string(dir.path().join("composites.tsv")).unwrap();
+    assert!(tsv.lines().next().unwrap().ends_with("\tiai_weightset"));
+    assert!(tsv.lines().nth(1).unwrap().ends_with("\tno_apci"));
 }
 
 #[test]
@@ -263,4 +273,151 @@ fn determinism_composites_tsv() {
     let a = std::fs::read(out1.join("composites.tsv")).expect("read1");
     let b = std::fs::read(out2.join("composites.tsv")).expect("read2");
     assert_eq!(a, b);
+
+    let a = std::fs::read_to_string(out1.join("composites_summary.json")).expect("read1");
+    let b = std::fs::read_to_string(out2.join("composites_summary.json")).expect("read2");
+    assert_eq!(a, b);
+    let summary: serde_json::Value = serde_json::from_str(&a).expect("json");
+    assert!(summary["oii"]["median"].is_number());
+    assert!(a.contains("\"frac_ge_0_65\": 0.000000") || a.contains("\"frac_ge_0_65\": 1.000000"));
+}
+
+#[test]
+fn iai_weightset_follows_dataset_apci_presence() {
+    let values = AxisValues {
+        sia: 0.2,
+        eeb: 0.0,
+        sli: 0.1,
+        mei: 0.4,
+        ecmi: 0.3,
+        apci: 0.6,
+        gdi: 0.5,
+    };
+    let coverage = AxisCoverage {
+        sia: 1.0,
+        eeb: 1.0,
+        sli: 1.0,
+        mei: 1.0,
+        ecmi: 1.0,
+        apci: 1.0,
+        gdi: 1.0,
+    };
+    let axes = dummy_axes(values.clone(), coverage.clone());
+    let dir = tempdir().expect("tempdir");
+    let scores = run_stage5_scores(&axes, dir.path()).expect("scores");
+    assert_eq!(scores.iai_weightset, IaiWeightSet::WithApci);
+    let expected = clamp01(0.22 * 0.4 + 0.22 * 0.5 + 0.26 * 0.6 + 0.18 * 0.2 + 0.12 * 0.5);
+    assert!((scores.iai[0] - expected).abs() < 1e-6);
+    let tsv = std::fs::read_to_string(dir.path().join("composites.tsv")).unwrap();
+    assert!(tsv.lines().nth(1).unwrap().ends_with("\twith_apci"));
+
+    // A finite APCI value is ignored when the dataset has no APCI panels.
+    let mut absent = dummy_axes(values, coverage);
+    absent.stats.apci.present = false;
+    let scores = run_stage5_scores(&absent, dir.path()).expect("scores");
+    assert_eq!(scores.iai_weightset, IaiWeightSet::NoApci);
+    let expected = clamp01(0.30 * 0.4 + 0.30 * 0.5 + 0.25 * 0.2 + 0.15 * 0.5);
+    assert!((scores.iai[0] - expected).abs() < 1e-6);
+}
+
+#[test]
+fn apci_nan_under_present_axis_is_counted_and_scored_as_zero() {
+    let axes = dummy_axes(
+        AxisValues {
+            sia: 0.2,
+            eeb: 0.0,
+            sli: 0.1,
+            mei: 0.4,
+            ecmi: 0.3,
+            apci: f32::NAN,
+            gdi: 0.5,
+        },
+        AxisCoverage {
+            sia: 1.0,
+            eeb: 1.0,
+            sli: 1.0,
+            mei: 1.0,
+            ecmi: 1.0,
+            apci: 0.0,
+            gdi: 1.0,
+        },
+    );
+    let dir = tempdir().expect("tempdir");
+    let scores = run_stage5_scores(&axes, dir.path()).expect("scores");
+    assert_eq!(scores.iai_weightset, IaiWeightSet::WithApci);
+    assert_eq!(scores.apci_nan_cells, 1);
+    let expected = clamp01(0.22 * 0.4 + 0.22 * 0.5 + 0.18 * 0.2 + 0.12 * 0.5);
+    assert!((scores.iai[0] - expected).abs() < 1e-6);
+}
+
+#[test]
+fn single_axis_oii_is_fully_concentrated() {
+    let axes = dummy_axes(
+        AxisValues {
+            sia: 0.5,
+            eeb: -1.0,
+            sli: 0.0,
+            mei: 0.0,
+            ecmi: 0.0,
+            apci: f32::NAN,
+            gdi: 0.0,
+        },
+        AxisCoverage {
+            sia: 1.0,
+            eeb: 1.0,
+            sli: 1.0,
+            mei: 1.0,
+            ecmi: 1.0,
+            apci: 0.0,
+            gdi: 1.0,
+        },
+    );
+    let dir = tempdir().expect("tempdir");
+    let scores = run_stage5_scores(&axes, dir.path()).expect("scores");
+    assert_eq!(scores.score_concentration, vec![1.0]);
+    assert_eq!(scores.summary.score_concentration.median, 1.0);
+
+    let tsv = std::fs::read_to_string(dir.path().join("composites.tsv")).expect("read");
+    let mut lines = tsv.lines();
+    assert!(
+        lines
+            .next()
+            .unwrap_or("")
+            .ends_with("\tscore_concentration\tiai_weightset")
+    );
+    assert!(
+        lines
+            .next()
+            .unwrap_or("")
+            .ends_with("\t1.000000\twith_apci")
+    );
+}
+
+#[test]
+fn full_disk_fails_with_artifact_name_and_no_temp_file() {
+    let axes = dummy_axes(
+        AxisValues {
+            sia: 0.1,
+            eeb: 0.2,
+            sli: 0.3,
+            mei: 0.4,
+            ecmi: 0.5,
+            apci: 0.6,
+            gdi: 0.7,
+        },
+        AxisCoverage {
+            sia: 1.0,
+            eeb: 1.0,
+            sli: 1.0,
+            mei: 1.0,
+            ecmi: 1.0,
+            apci: 1.0,
+            gdi: 1.0,
+        },
+    );
+    let dir = tempdir().expect("tempdir");
+    let _guard = crate::report::artifact::fail_after(0);
+    let err = run_stage5_scores(&axes, dir.path()).expect_err("disk full");
+    assert!(err.to_string().contains("composites.tsv"), "{err}");
+    assert_eq!(std::fs::read_dir(dir.path()).expect("read_dir").count(), 0);
 }
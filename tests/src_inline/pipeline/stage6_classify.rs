@@ -1,5 +1,5 @@
 use super::*;
-use crate::model::axes::{AxisCoverage, AxisValues};
+use crate::model::axes::{AxisCoverage, AxisValues, CoverageMode};
 use crate::pipeline::stage2_normalize::ExprMatrix;
 use crate::pipeline::stage4_axes::{
     AxesContext, AxesSummary, AxisDrivers, AxisStats, AxisSummaryEntry,
@@ -151,6 +151,10 @@ fn dummy_axes(values: AxisValues) -> AxesContext {
                 },
             },
         },
+        coverage_mode: CoverageMode::Required,
+        panel_counts: Default::default(),
+        dropped_panels: Vec::new(),
+        curves: None,
     }
 }
 
@@ -165,6 +169,7 @@ fn dummy_scores(oii: f32, esi: f32) -> ScoresContext {
         drivers_oii: vec!["".to_string()],
         drivers_iai: vec!["".to_string()],
         drivers_esi: vec!["".to_string()],
+        score_concentration: vec![f32::NAN],
         summary: CompositesSummary {
             oii: CompositeStats {
                 median: 0.0,
@@ -187,7 +192,16 @@ fn dummy_scores(oii: f32, esi: f32) -> ScoresContext {
                 frac_ge_0_65: 0.0,
                 frac_ge_0_80: 0.0,
             },
+            score_concentration: CompositeStats {
+                median: 0.0,
+                p90: 0.0,
+                p99: 0.0,
+                frac_ge_0_65: 0.0,
+                frac_ge_0_80: 0.0,
+            },
         },
+        iai_weightset: crate::model::scores::IaiWeightSet::NoApci,
+        apci_nan_cells: 0,
     }
 }
 
@@ -200,8 +214,14 @@ fn dummy_dataset(n: usize) -> DatasetCtx {
         matrix_path: dir.path().join("matrix.mtx"),
         features_path: dir.path().join("features.tsv"),
         barcodes_path: barcodes,
+        features_delimiter: Default::default(),
+        barcodes_delimiter: Default::default(),
+        barcodes_header: None,
+        features_single_column_rows: 0,
         shared_cache_path: None,
         resolved_shared_cache_path: None,
+        shared_cache: None,
+        shared_cache_identity: None,
         gene_index: crate::input::features::GeneIndex {
             rows: Vec::new(),
             duplicates: Vec::new(),
@@ -216,6 +236,12 @@ fn dummy_dataset(n: usize) -> DatasetCtx {
         meta_present: false,
         meta_cells_matched: 0,
         meta_cells_missing: 0,
+        meta_issues: Default::default(),
+        gene_set_hash: String::new(),
+        upstream: None,
+        dense: None,
+        matrix_value_type: None,
+        matrix_orientation: None,
     }
 }
 
@@ -253,6 +279,29 @@ fn rule_boundary_secretory_lysosome_active() {
     assert_eq!(rule, RuleId::R2SecretoryLysosomeActive);
 }
 
+#[test]
+fn rule_trace_first_match_is_the_assigned_rule() {
+    let t = Thresholds::default();
+    let axis = AxisValues {
+        sia: 0.5,
+        eeb: 0.0,
+        sli: 0.8,
+        mei: 0.2,
+        ecmi: 0.2,
+        apci: 0.0,
+        gdi: 0.2,
+    };
+    let trace = rule_trace(&axis, pos_eeb(axis.eeb), 0.0, 0.0, &t);
+    assert_eq!(trace.len(), 7);
+    assert_eq!(trace[0], (RuleId::R1SelfPreserving, false));
+    let first = trace
+        .iter()
+        .find(|(_, matched)| *matched)
+        .map(|(rule, _)| *rule);
+    let (_, rule) = classify_cell(&axis, pos_eeb(axis.eeb), 0.0, 0.0, &t);
+    assert_eq!(first, Some(rule));
+}
+
 #[test]
 fn rule_boundary_export_dominant() {
     let t = Thresholds::default();
@@ -365,12 +414,51 @@ fn flags_low_counts_and_detected() {
             detected: 10,
         }],
         normalization: crate::expr::normalize::Normalization::default(),
+        value_warnings: Default::default(),
     };
     let dir = tempdir().expect("tempdir");
     let ctx = run_stage6_classify(&dataset, &expr, &axes, &scores, dir.path()).expect("classify");
     let f = ctx.flags[0];
     assert!(f.contains(Flags::LOW_COUNTS));
     assert!(f.contains(Flags::FEW_DETECTED_GENES));
+    // 100 counts scale each to exactly 100, not above the bound.
+    assert!(!f.contains(Flags::EXTREME_NORMALIZATION));
+}
+
+#[test]
+fn ten_count_cell_flags_extreme_normalization() {
+    let axes = dummy_axes(AxisValues {
+        sia: 0.9,
+        eeb: 0.5,
+        sli: 0.9,
+        mei: 0.9,
+        ecmi: 0.9,
+        apci: 0.5,
+        gdi: 0.9,
+    });
+    let scores = dummy_scores(0.9, 0.9);
+    let dataset = dummy_dataset(1);
+    let expr = ExprContext {
+        expr: ExprMatrix::Owned(crate::expr::csc::ExprCsc {
+            n_genes: 0,
+            n_cells: 1,
+            nnz: 0,
+            col_ptr: vec![0, 0],
+            row_idx: vec![],
+            values: vec![],
+        }),
+        cell_stats: vec![crate::expr::csc::CellStats {
+            libsize: 10,
+            detected: 10,
+        }],
+        normalization: crate::expr::normalize::Normalization::default(),
+        value_warnings: Default::default(),
+    };
+    let dir = tempdir().expect("tempdir");
+    let ctx = run_stage6_classify(&dataset, &expr, &axes, &scores, dir.path()).expect("classify");
+    // CP10K turns each of the 10 counts into 1000.
+    assert!(ctx.flags[0].contains(Flags::EXTREME_NORMALIZATION));
+    assert!(ctx.flags[0].contains(Flags::LOW_COUNTS));
 }
 
 #[test]
@@ -401,6 +489,7 @@ fn flags_low_confidence_and_ambient() {
             detected: 10,
         }],
         normalization: crate::expr::normalize::Normalization::default(),
+        value_warnings: Default::default(),
     };
     let dir = tempdir().expect("tempdir");
     let ctx = run_stage6_classify(&dataset, &expr, &axes, &scores, dir.path()).expect("classify");
@@ -437,6 +526,7 @@ fn determinism_classify_tsv() {
             detected: 1000,
         }],
         normalization: crate::expr::normalize::Normalization::default(),
+        value_warnings: Default::default(),
     };
     let dir = tempdir().expect("tempdir");
     let out1 = dir.path().join("out1");
@@ -481,3 +571,155 @@ fn summary_counts_and_fractions() {
         .1;
     assert!((frac_un - 2.0 / 3.0).abs() < 1e-6);
 }
+
+#[test]
+fn ambient_rule_is_tunable_for_nuclei() {
+    // Nuclei-like: every cell detects fewer than 300 genes, and 8 of 20 have
+    // moderately high GDI with middling SIA.
+    let n = 20;
+    let mut axes = dummy_axes(AxisValues {
+        sia: 0.5,
+        eeb: 0.0,
+        sli: 0.1,
+        mei: 0.1,
+        ecmi: 0.1,
+        apci: 0.0,
+        gdi: 0.2,
+    });
+    axes.values = (0..n)
+        .map(|i| AxisValues {
+            gdi: if i % 5 < 2 { 0.78 } else { 0.2 },
+            sia: if i % 5 < 2 { 0.40 } else { 0.5 },
+            ..axes.values[0]
+        })
+        .collect();
+    axes.coverage = vec![axes.coverage[0].clone(); n];
+    axes.cell_ids = (0..n).map(|i| format!("c{}", i + 1)).collect();
+    let mut scores = dummy_scores(0.0, 0.0);
+    scores.oii = vec![0.0; n];
+    scores.esi = vec![0.0; n];
+    let expr = ExprContext {
+        expr: ExprMatrix::Owned(crate::expr::csc::ExprCsc {
+            n_genes: 0,
+            n_cells: n,
+            nnz: 0,
+            col_ptr: vec![0; n + 1],
+            row_idx: vec![],
+            values: vec![],
+        }),
+        cell_stats: (0..n)
+            .map(|i| crate::expr::csc::CellStats {
+                libsize: 2000,
+                detected: 150 + 5 * i as u32,
+            })
+            .collect(),
+        normalization: crate::expr::normalize::Normalization::default(),
+        value_warnings: Default::default(),
+    };
+    let dataset = dummy_dataset(n);
+    let ambient_fraction = |ctx: &ClassifyContext| {
+        ctx.flags
+            .iter()
+            .filter(|f| f.contains(Flags::HIGH_AMBIENT_RISK))
+            .count() as f32
+            / n as f32
+    };
+
+    let dir = tempdir().expect("tempdir");
+    let default = run_stage6_classify(&dataset, &expr, &axes, &scores, dir.path()).expect("run");
+    assert!((ambient_fraction(&default) - 0.4).abs() < 1e-6);
+    let classify = std::fs::read_to_string(dir.path().join("classify.tsv")).expect("read");
+    let mut lines = classify.lines();
+    assert!(
+        lines
+            .next()
+            .expect("header")
+            .ends_with("\tflags\tambient_detected_genes\tambient_gdi\tambient_sia\tthresholds_id")
+    );
+    let flagged = lines.next().expect("c1");
+    assert!(
+        flagged.ends_with("HIGH_AMBIENT_RISK\t150\t0.780000\t0.400000\tt0"),
+        "{flagged}"
+    );
+    let clean = lines.nth(1).expect("c3");
+    assert!(clean.ends_with("\t.\t.\t.\tt0"), "{clean}");
+
+    let tuned: Thresholds = serde_json::from_str(
+        r#"{"ambient_detected": 120, "ambient_gdi": 0.85, "ambient_sia": 0.35}"#,
+    )
+    .expect("thresholds");
+    assert_eq!(tuned.few_detected, 300);
+    let ctx =
+        run_stage6_classify_with_thresholds(&dataset, &expr, &axes, &scores, dir.path(), &tuned)
+            .expect("run");
+    assert!(ambient_fraction(&ctx) < 0.05);
+    assert!(
+        ctx.flags
+            .iter()
+            .all(|f| f.contains(Flags::FEW_DETECTED_GENES))
+    );
+}
+
+#[test]
+fn low_complexity_replaces_the_detected_count_rule() {
+    let n = 3;
+    let mut axes = dummy_axes(AxisValues {
+        sia: 0.5,
+        eeb: 0.0,
+        sli: 0.1,
+        mei: 0.1,
+        ecmi: 0.1,
+        apci: 0.0,
+        gdi: 0.1,
+    });
+    axes.values = vec![axes.values[0].clone(); n];
+    axes.coverage = vec![axes.coverage[0].clone(); n];
+    axes.cell_ids = (0..n).map(|i| format!("c{}", i + 1)).collect();
+    let mut scores = dummy_scores(0.0, 0.0);
+    scores.oii = vec![0.0; n];
+    scores.esi = vec![0.0; n];
+    // Shallow but complex, deep but simple, empty.
+    let cell_stats = [(600, 250), (20_000, 1000), (0, 0)]
+        .map(|(libsize, detected)| crate::expr::csc::CellStats { libsize, detected });
+    assert!((cell_stats[0].genes_per_1k_umis() - 416.666_66).abs() < 1e-3);
+    assert!(cell_stats[2].genes_per_1k_umis().is_nan());
+    let expr = ExprContext {
+        expr: ExprMatrix::Owned(crate::expr::csc::ExprCsc {
+            n_genes: 0,
+            n_cells: n,
+            nnz: 0,
+            col_ptr: vec![0; n + 1],
+            row_idx: vec![],
+            values: vec![],
+        }),
+        cell_stats: cell_stats.to_vec(),
+        normalization: crate::expr::normalize::Normalization::default(),
+        value_warnings: Default::default(),
+    };
+    let dataset = dummy_dataset(n);
+    let few = |ctx: &ClassifyContext| -> Vec<bool> {
+        ctx.flags
+            .iter()
+            .map(|f| f.contains(Flags::FEW_DETECTED_GENES))
+            .collect()
+    };
+
+    let dir = tempdir().expect("tempdir");
+    let by_count = run_stage6_classify(&dataset, &expr, &axes, &scores, dir.path()).expect("run");
+    assert_eq!(few(&by_count), [true, false, true]);
+
+    let thresholds = Thresholds {
+        low_complexity: Some(100.0),
+        ..Thresholds::default()
+    };
+    let by_complexity = run_stage6_classify_with_thresholds(
+        &dataset,
+        &expr,
+        &axes,
+        &scores,
+        dir.path(),
+        &thresholds,
+    )
+    .expect("run");
+    assert_eq!(few(&by_complexity), [false, true, true]);
+}
@@ -3,10 +3,14 @@ use crate::expr::csc::{CellStats, ExprCsc};
 use crate::expr::normalize::Normalization;
 use crate::input::detect::TenXFormat;
 use crate::input::features::GeneIndex;
-use crate::model::axes::{AxisCoverage, AxisValues};
+use crate::model::axes::{AxisCoverage, AxisValues, CoverageMode};
+use crate::model::pipeline_regime::PipelineContract;
 use crate::model::regimes::RuleId;
+use crate::model::thresholds::CellThresholds;
 use crate::panels::defs::{PanelDef, PanelGene, PanelSet};
-use crate::panels::mapping::GeneMapping;
+use crate::panels::mapping::{GeneMapping, MappingWarning};
+use crate::pipeline::explain::ExplainCells;
+use crate::pipeline::rng::derive_seed;
 use crate::pipeline::stage2_normalize::ExprMatrix;
 use crate::pipeline::stage3_panels::{PanelCellPacked, PanelsContext};
 use crate::pipeline::stage4_axes::{
@@ -22,8 +26,14 @@ fn dummy_dataset() -> DatasetCtx {
         matrix_path: "matrix.mtx".into(),
         features_path: "features.tsv".into(),
         barcodes_path: "barcodes.tsv".into(),
+        features_delimiter: Default::default(),
+        barcodes_delimiter: Default::default(),
+        barcodes_header: None,
+        features_single_column_rows: 0,
         shared_cache_path: None,
         resolved_shared_cache_path: None,
+        shared_cache: None,
+        shared_cache_identity: None,
         gene_index: GeneIndex {
             rows: vec![],
             duplicates: vec![],
@@ -38,6 +48,12 @@ fn dummy_dataset() -> DatasetCtx {
         meta_present: false,
         meta_cells_matched: 0,
         meta_cells_missing: 0,
+        meta_issues: Default::default(),
+        gene_set_hash: String::new(),
+        upstream: None,
+        dense: None,
+        matrix_value_type: None,
+        matrix_orientation: None,
     }
 }
 
@@ -62,6 +78,7 @@ fn dummy_expr() -> ExprContext {
             },
         ],
         normalization: Normalization::default(),
+        value_warnings: Default::default(),
     }
 }
 
@@ -137,6 +154,10 @@ fn dummy_axes() -> AxesContext {
             apci: zero_axis_summary(),
             gdi: zero_axis_summary(),
         },
+        coverage_mode: CoverageMode::Required,
+        panel_counts: Default::default(),
+        dropped_panels: Vec::new(),
+        curves: None,
     }
 }
 
@@ -171,6 +192,7 @@ fn dummy_scores() -> ScoresContext {
         drivers_oii: vec!["".to_string(), "".to_string()],
         drivers_iai: vec!["".to_string(), "".to_string()],
         drivers_esi: vec!["".to_string(), "".to_string()],
+        score_concentration: vec![0.4, 0.8],
         summary: CompositesSummary {
             oii: CompositeStats {
                 median: 0.0,
@@ -193,7 +215,16 @@ fn dummy_scores() -> ScoresContext {
                 frac_ge_0_65: 0.0,
                 frac_ge_0_80: 0.0,
             },
+            score_concentration: CompositeStats {
+                median: 0.0,
+                p90: 0.0,
+                p99: 0.0,
+                frac_ge_0_65: 0.0,
+                frac_ge_0_80: 0.0,
+            },
         },
+        iai_weightset: crate::model::scores::IaiWeightSet::NoApci,
+        apci_nan_cells: 0,
     }
 }
 
@@ -207,6 +238,7 @@ fn dummy_classify() -> ClassifyContext {
             fractions: vec![],
             flagged_fractions: vec![],
         },
+        thresholds: CellThresholds::uniform(Thresholds::default()),
     }
 }
 
@@ -217,12 +249,19 @@ fn dummy_panels() -> PanelsContext {
                 id: "P1".to_string(),
                 description: "Panel One".to_string(),
                 axis: "SIA".to_string(),
+                group: None,
                 genes: vec![PanelGene {
                     symbol: "G1".to_string(),
                 }],
                 required: vec!["G1".to_string()],
                 weights: None,
+                custom_axis: false,
+                version: None,
+                source: None,
             }],
+            files: vec![],
+            skipped: vec![],
+            required_autofixes: vec![],
         },
         mappings: vec![GeneMapping {
             panel_id: "P1".to_string(),
@@ -232,6 +271,7 @@ fn dummy_panels() -> PanelsContext {
         }],
         warnings: vec![],
         cell_ids: vec!["c1".to_string(), "c2".to_string()],
+        dead_panels: Vec::new(),
         per_cell: vec![
             PanelCellPacked {
                 sums: vec![1.0],
@@ -268,16 +308,21 @@ fn secretion_tsv_header_exact_order() {
     let header = txt.lines().next().unwrap_or("");
     assert_eq!(
         header,
-        "barcode\tsample\tcondition\tspecies\tlibsize\tnnz\texpressed_genes\tsecretory_load\texocytosis_bias\tvesicle_traffic_intensity\ter_golgi_pressure\tparacrine_signal_potential\tstress_secretion_index\tregime\tflags\tconfidence"
+        "barcode\tsample\tcondition\tspecies\tlibsize\tnnz\texpressed_genes\tsecretory_load\texocytosis_bias\tvesicle_traffic_intensity\ter_golgi_pressure\tparacrine_signal_potential\tstress_secretion_index\tregime\tflags\tconfidence\tproliferation_score\tgenes_per_1k_umis"
     );
 }
 
 #[test]
 fn summary_json_schema() {
     let dir = tempdir().expect("tempdir");
+    let mut dataset = dummy_dataset();
+    dataset.matrix_value_type = Some(crate::input::mtx::MtxField::Real);
+    let mut expr = dummy_expr();
+    expr.value_warnings.n_rounded = 3;
+    expr.value_warnings.n_rounded_to_zero = 1;
     run_stage7_report(
-        &dummy_dataset(),
-        &dummy_expr(),
+        &dataset,
+        &expr,
         &dummy_axes(),
         &dummy_scores(),
         &dummy_classify(),
@@ -298,6 +343,16 @@ fn summary_json_schema() {
     assert!(v.get("regimes").is_some());
     assert!(v.get("qc").is_some());
     assert!(v["distributions"]["secretory_load"]["median"].is_number());
+    let concentration = v["distributions"]["score_concentration"]["median"]
+        .as_f64()
+        .expect("score_concentration median");
+    // Linear interpolation: the median of [0.4, 0.8] is their mean.
+    assert!((concentration - 0.6).abs() < 1e-6);
+    assert_eq!(v["provenance"]["coverage_mode"], "required");
+    assert_eq!(
+        v["input"]["matrix_values"],
+        serde_json::json!({"value_type": "real", "rounded": 3, "rounded_to_zero": 1})
+    );
 }
 
 #[test]
@@ -413,3 +468,1295 @@ fn pipeline_step_written_only_in_pipeline_mode() {
     .expect("stage7");
     assert!(dir.path().join("pipeline_step.json").exists());
 }
+
+#[test]
+fn panel_provenance_written() {
+    let dir = tempdir().expect("tempdir");
+    let mut panels = dummy_panels();
+    panels.panels.panels[0].version = Some("1.2".to_string());
+    panels.panels.panels[0].source = Some("core.toml".to_string());
+    run_stage7_report(
+        &dummy_dataset(),
+        &dummy_expr(),
+        &dummy_axes(),
+        &dummy_scores(),
+        &dummy_classify(),
+        &panels,
+        dir.path(),
+        "cell",
+        RunMode::Standalone,
+        None,
+    )
+    .expect("stage7");
+
+    let report = std::fs::read_to_string(dir.path().join("panels_report.tsv")).expect("read");
+    let mut lines = report.lines();
+    assert!(
+        lines
+            .next()
+            .unwrap_or("")
+            .ends_with("\tpanel_version\tpanel_source")
+    );
+    assert!(lines.next().unwrap_or("").ends_with("\t1.2\tcore.toml"));
+
+    let v: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(dir.path().join("provenance.json")).expect("read"))
+            .expect("json");
+    let entry = &v["panels"][0];
+    assert_eq!(entry["id"], "P1");
+    assert_eq!(entry["axis"], "SIA");
+    assert_eq!(entry["genes"], 1);
+    assert_eq!(entry["version"], "1.2");
+    assert_eq!(entry["source"], "core.toml");
+    assert_eq!(
+        entry["content_hash"],
+        panels.panels.panels[0].content_hash().as_str()
+    );
+}
+
+#[test]
+fn panels_report_axis_and_group_columns() {
+    let dir = tempdir().expect("tempdir");
+    let mut panels = dummy_panels();
+    let mut grouped = panels.panels.panels[0].clone();
+    grouped.id = "P2".to_string();
+    grouped.group = Some("cytokines".to_string());
+    panels.panels.panels.push(grouped);
+    panels.mappings.push(panels.mappings[0].clone());
+    for cell in &mut panels.per_cell {
+        cell.sums.push(cell.sums[0]);
+        cell.hits.push(cell.hits[0]);
+        cell.required_missing.push(cell.required_missing[0]);
+    }
+    run_stage7_report(
+        &dummy_dataset(),
+        &dummy_expr(),
+        &dummy_axes(),
+        &dummy_scores(),
+        &dummy_classify(),
+        &panels,
+        dir.path(),
+        "cell",
+        RunMode::Standalone,
+        None,
+    )
+    .expect("stage7");
+
+    let report = std::fs::read_to_string(dir.path().join("panels_report.tsv")).expect("read");
+    let rows: Vec<Vec<&str>> = report.lines().map(|l| l.split('\t').collect()).collect();
+    assert_eq!(rows[0][2..4], ["panel_axis", "panel_group"]);
+    assert_eq!(rows[1][..4], ["P1", "Panel One", "SIA", "SIA"]);
+    assert_eq!(rows[2][..4], ["P2", "Panel One", "SIA", "cytokines"]);
+
+    let text = std::fs::read_to_string(dir.path().join("report.txt")).expect("read");
+    let groups = text.split("Panels by group:\n").nth(1).expect("section");
+    assert!(groups.starts_with("- SIA:\n  - P1 [SIA] (1 genes)\n- cytokines:\n  - P2 [SIA]"));
+}
+
+#[test]
+fn data_caveats_in_summary_and_report() {
+    let dir = tempdir().expect("tempdir");
+    let mut axes = dummy_axes();
+    axes.stats.apci.present = false;
+    for v in &mut axes.values {
+        v.apci = f32::NAN;
+    }
+    let mut panels = dummy_panels();
+    panels.warnings.push(MappingWarning {
+        panel_id: "P1".to_string(),
+        axis: "SIA".to_string(),
+        missing_required: vec!["G1".to_string()],
+        mappable_fraction: 0.5,
+    });
+    run_stage7_report(
+        &dummy_dataset(),
+        &dummy_expr(),
+        &axes,
+        &dummy_scores(),
+        &dummy_classify(),
+        &panels,
+        dir.path(),
+        "cell",
+        RunMode::Standalone,
+        None,
+    )
+    .expect("stage7");
+
+    let v: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(dir.path().join("summary.json")).expect("read"))
+            .expect("json");
+    assert_eq!(v["caveats"]["absent_axes"], serde_json::json!(["APCI"]));
+    assert_eq!(v["caveats"]["panels_missing_required"][0]["panel_id"], "P1");
+    assert_eq!(
+        v["caveats"]["panels_missing_required"][0]["missing_required"],
+        serde_json::json!(["G1"])
+    );
+    assert_eq!(
+        v["caveats"]["missing_required_by_axis"]["SIA"],
+        serde_json::json!({"panels": ["P1"], "missing_required": ["G1"]})
+    );
+    assert_eq!(
+        v["caveats"]["panels_missing_required"][0]["mappable_fraction"],
+        0.5
+    );
+    assert_eq!(v["caveats"]["low_axis_coverage_fraction"], 0.5);
+
+    let text = std::fs::read_to_string(dir.path().join("report.txt")).expect("read");
+    assert!(text.contains("Data caveats:\n- Axes without panels (not scored): APCI\n"));
+    assert!(text.contains("  - P1: G1\n"));
+    assert!(text.contains("- Worst-covered axis: "), "{text}");
+    assert!(text.contains("- Cells with any axis coverage below threshold: 50.00%\n"));
+}
+
+#[test]
+fn histogram01_bins_and_clamps() {
+    let h = histogram01(&[0.0, 0.049, 0.05, 0.5, 1.0, 1.5, -0.2, f32::NAN], 20);
+    assert_eq!(h.len(), 20);
+    assert_eq!(h[0], 3);
+    assert_eq!(h[1], 1);
+    assert_eq!(h[10], 1);
+    assert_eq!(h[19], 2);
+    assert_eq!(h.iter().sum::<u32>(), 7);
+}
+
+#[test]
+fn histograms_and_sparklines_written() {
+    let dir = tempdir().expect("tempdir");
+    let opts = ReportOptions {
+        ascii_only: true,
+        ..Default::default()
+    };
+    run_stage7_report_with_options(
+        &dummy_dataset(),
+        &dummy_expr(),
+        &dummy_axes(),
+        &dummy_scores(),
+        &dummy_classify(),
+        &dummy_panels(),
+        dir.path(),
+        "cell",
+        RunMode::Standalone,
+        None,
+        &opts,
+    )
+    .expect("stage7");
+
+    let v: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(dir.path().join("summary.json")).expect("read"))
+            .expect("json");
+    assert_eq!(v["histograms"]["bins"], 20);
+    for key in [
+        "secretory_load",
+        "er_golgi_pressure",
+        "stress_secretion_index",
+        "confidence",
+    ] {
+        let bins = v["histograms"][key].as_array().expect("bins");
+        assert_eq!(bins.len(), 20);
+        assert_eq!(bins.iter().filter_map(|b| b.as_u64()).sum::<u64>(), 2);
+    }
+
+    let text = std::fs::read_to_string(dir.path().join("report.txt")).expect("read");
+    let line = text
+        .lines()
+        .find(|l| l.starts_with("- secretory_load"))
+        .expect("sparkline");
+    let spark = line.split('|').nth(1).expect("bars");
+    assert_eq!(spark.chars().count(), 20);
+    assert!(spark.is_ascii());
+    assert_eq!(spark.matches('@').count(), 2);
+}
+
+#[test]
+fn samples_tsv_gates_small_samples() {
+    let run = |min_cells_for_stats: usize, mode: &str| {
+        let dir = tempdir().expect("tempdir");
+        let opts = ReportOptions {
+            min_cells_for_stats,
+            ..Default::default()
+        };
+        run_stage7_report_with_options(
+            &dummy_dataset(),
+            &dummy_expr(),
+            &dummy_axes(),
+            &dummy_scores(),
+            &dummy_classify(),
+            &dummy_panels(),
+            dir.path(),
+            mode,
+            RunMode::Standalone,
+            None,
+            &opts,
+        )
+        .expect("stage7");
+        std::fs::read_to_string(dir.path().join("samples.tsv")).ok()
+    };
+
+    assert_eq!(run(2, "cell"), None);
+
+    let gated = run(3, "sample").expect("samples.tsv");
+    let lines: Vec<&str> = gated.lines().collect();
+    assert_eq!(lines.len(), 2);
+    let header: Vec<&str> = lines[0].split('\t').collect();
+    assert_eq!(
+        header[..4],
+        [
+            "sample",
+            "n_cells",
+            "secretory_load_median",
+            "secretory_load_iqr"
+        ]
+    );
+    assert_eq!(header.last(), Some(&"regime"));
+    assert_eq!(header.len(), 2 + 2 * SAMPLE_METRICS.len() + 1);
+    let row: Vec<&str> = lines[1].split('\t').collect();
+    assert_eq!(row.len(), header.len());
+    assert_eq!(row[..2], [".", "2"]);
+    assert!(row[2..row.len() - 1].iter().all(|v| *v == "NA"));
+    assert_eq!(row.last(), Some(&INSUFFICIENT_CELLS));
+
+    let reported = run(2, "sample").expect("samples.tsv");
+    let row: Vec<&str> = reported.lines().nth(1).expect("row").split('\t').collect();
+    assert!(
+        row[2..row.len() - 1]
+            .iter()
+            .all(|v| v.parse::<f32>().is_ok())
+    );
+    assert!(PipelineRegime::parse(row.last().expect("regime")).is_some());
+}
+
+#[test]
+fn regime_labels_apply_to_every_contract_artifact() {
+    let dir = tempdir().expect("tempdir");
+    let labels_path = dir.path().join("labels.json");
+    std::fs::write(
+        &labels_path,
+        r#"{"labels": {"SecretoryCollapse": "Quiescent/Collapsed",
+                       "AdaptiveSecretion": "Adaptive",
+                       "HomeostaticSecretion": "Homeostatic"},
+            "order": ["SecretoryCollapse", "Unclassified"]}"#,
+    )
+    .expect("write");
+    let opts = ReportOptions {
+        regime_labels: RegimeLabels::load(&labels_path).expect("labels"),
+        ..Default::default()
+    };
+    let out = dir.path().join("out");
+    run_stage7_report_with_options(
+        &dummy_dataset(),
+        &dummy_expr(),
+        &dummy_axes(),
+        &dummy_scores(),
+        &dummy_classify(),
+        &dummy_panels(),
+        &out,
+        "cell",
+        RunMode::Pipeline,
+        None,
+        &opts,
+    )
+    .expect("stage7");
+
+    let read_json = |name: &str| -> serde_json::Value {
+        serde_json::from_slice(&std::fs::read(out.join(name)).expect("read")).expect("json")
+    };
+    let step = read_json("pipeline_step.json");
+    let listed: Vec<&str> = step["regimes"]
+        .as_array()
+        .expect("regimes")
+        .iter()
+        .map(|v| v.as_str().expect("label"))
+        .collect();
+    assert_eq!(
+        listed,
+        [
+            "Quiescent/Collapsed",
+            "Unclassified",
+            "Homeostatic",
+            "Adaptive",
+            "InflammatorySecretion",
+            "HypersecretoryState"
+        ]
+    );
+
+    let summary = read_json("summary.json");
+    let counts = summary["regimes"]["counts"].as_object().expect("counts");
+    let mut keys: Vec<&str> = counts.keys().map(String::as_str).collect();
+    let mut sorted_listed = listed.clone();
+    keys.sort_unstable();
+    sorted_listed.sort_unstable();
+    assert_eq!(keys, sorted_listed);
+
+    let tsv = std::fs::read_to_string(out.join("secretion.tsv")).expect("read");
+    let header: Vec<&str> = tsv.lines().next().expect("header").split('\t').collect();
+    let regime_col = header.iter().position(|c| *c == "regime").expect("regime");
+    let mut tallies: BTreeMap<&str, u64> = BTreeMap::new();
+    for line in tsv.lines().skip(1) {
+        let regime = line.split('\t').nth(regime_col).expect("regime");
+        *tallies.entry(regime).or_default() += 1;
+    }
+    assert!(!tallies.is_empty());
+    for (label, n) in &tallies {
+        assert!(listed.contains(label), "{label} not in pipeline_step.json");
+        assert_eq!(counts[*label].as_u64(), Some(*n), "{label}");
+    }
+    assert!(!tsv.contains("SecretoryCollapse") && !tsv.contains("AdaptiveSecretion"));
+}
+
+#[test]
+fn seed_changes_only_rng_provenance() {
+    let run = |seed: u64| {
+        let dir = tempdir().expect("tempdir");
+        let rng = RunRng::new(seed);
+        let _ = rng.child("subsample");
+        let opts = ReportOptions {
+            rng,
+            ..Default::default()
+        };
+        run_stage7_report_with_options(
+            &dummy_dataset(),
+            &dummy_expr(),
+            &dummy_axes(),
+            &dummy_scores(),
+            &dummy_classify(),
+            &dummy_panels(),
+            dir.path(),
+            "sample",
+            RunMode::Pipeline,
+            None,
+            &opts,
+        )
+        .expect("stage7");
+        let mut files = BTreeMap::new();
+        for entry in std::fs::read_dir(dir.path()).expect("read_dir") {
+            let path = entry.expect("entry").path();
+            let name = path.file_name().unwrap().to_string_lossy().to_string();
+            files.insert(name, std::fs::read(&path).expect("read"));
+        }
+        files
+    };
+
+    let a = run(1);
+    assert_eq!(a, run(1));
+
+    let b = run(2);
+    assert_eq!(a.keys().collect::<Vec<_>>(), b.keys().collect::<Vec<_>>());
+    for (name, bytes) in &a {
+        if name != "provenance.json" {
+            assert_eq!(bytes, &b[name], "{name} must not depend on the seed");
+        }
+    }
+    let prov = |files: &BTreeMap<String, Vec<u8>>| -> serde_json::Value {
+        serde_json::from_slice(&files["provenance.json"]).expect("json")
+    };
+    let (mut pa, mut pb) = (prov(&a), prov(&b));
+    assert_eq!(pa["rng"]["master_seed"], 1);
+    assert_eq!(
+        pa["rng"]["derived"]["subsample"],
+        serde_json::json!(derive_seed(1, "subsample"))
+    );
+    assert_ne!(pa["rng"], pb["rng"]);
+    pa.as_object_mut().unwrap().remove("rng");
+    pb.as_object_mut().unwrap().remove("rng");
+    assert_eq!(pa, pb);
+}
+
+#[test]
+fn frac_ge_per_group_with_configured_cutoffs() {
+    let dir = tempdir().expect("tempdir");
+    let meta = dir.path().join("meta.tsv");
+    std::fs::write(
+        &meta,
+        "cell_id\tsample_id\tcondition\nc1\tS1\tctrl\nc2\tS2\ttreated\nc9\tS3\ttreated\n",
+    )
+    .expect("write");
+    let mut thresholds = Thresholds::default();
+    thresholds.frac_ge.secretory_load = vec![0.0, 1.5];
+    thresholds.frac_ge.confidence = vec![];
+    let opts = ReportOptions {
+        thresholds,
+        ..Default::default()
+    };
+    let out = dir.path().join("out");
+    let summary = run_stage7_report_with_options(
+        &dummy_dataset(),
+        &dummy_expr(),
+        &dummy_axes(),
+        &dummy_scores(),
+        &dummy_classify(),
+        &dummy_panels(),
+        &out,
+        "cell",
+        RunMode::Standalone,
+        Some(&meta),
+        &opts,
+    )
+    .expect("stage7");
+
+    // S3 is in the meta file but matches no cell: reported, with 0 cells.
+    let s3 = &summary.frac_ge.by_sample["S3"];
+    assert_eq!(s3.n_cells, 0);
+    assert_eq!(s3.fraction(0, 0), 0.0);
+    assert_eq!(summary.frac_ge.by_condition["treated"].n_cells, 1);
+
+    let json: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(out.join("summary.json")).expect("read"))
+            .expect("json");
+    let frac_ge = &json["frac_ge"];
+    assert_eq!(
+        frac_ge["thresholds"]["secretory_load"],
+        serde_json::json!([0.0, 1.5])
+    );
+    assert_eq!(
+        frac_ge["thresholds"]["exocytosis_bias"],
+        serde_json::json!([0.65, 0.8])
+    );
+    assert_eq!(frac_ge["global"]["n_cells"], 2);
+    assert_eq!(
+        frac_ge["global"]["secretory_load"],
+        serde_json::json!([1.0, 0.0])
+    );
+    assert_eq!(frac_ge["global"]["confidence"], serde_json::json!([]));
+    assert_eq!(
+        frac_ge["by_sample"]["S3"]["secretory_load"],
+        serde_json::json!([0.0, 0.0])
+    );
+    assert_eq!(frac_ge["by_condition"]["ctrl"]["n_cells"], 1);
+
+    let tsv = std::fs::read_to_string(out.join("condition_summary.tsv")).expect("read");
+    let lines: Vec<&str> = tsv.lines().collect();
+    assert_eq!(
+        lines[0],
+        "group_by\tgroup\tn_cells\tmetric\tthreshold\tn_ge\tfrac_ge"
+    );
+    assert!(lines.contains(&"all\t.\t2\tsecretory_load\t0.000000\t2\t1.000000"));
+    assert!(lines.contains(&"sample\tS3\t0\tsecretory_load\t0.000000\t0\t0.000000"));
+    assert!(lines.contains(&"condition\ttreated\t1\tsecretory_load\t1.500000\t0\t0.000000"));
+    // 2 + 5 * 2 cut-offs per group, for all + 3 samples + 2 conditions.
+    assert_eq!(lines.len(), 1 + 6 * 12);
+
+    let report = std::fs::read_to_string(out.join("report.txt")).expect("read");
+    assert!(report.contains("Cells at or above thresholds:"));
+    assert!(report.contains("treated 0.00% (n=1)"));
+}
+
+#[test]
+fn condition_comparison_is_gated_by_min_cells() {
+    let dir = tempdir().expect("tempdir");
+    let meta = dir.path().join("meta.tsv");
+    std::fs::write(
+        &meta,
+        "cell_id\tsample_id\tcondition\nc1\tS1\tctrl\nc2\tS2\ttreated\n",
+    )
+    .expect("write");
+    let run = |min_cells: usize, name: &str| {
+        let opts = ReportOptions {
+            compare_min_cells: min_cells,
+            ..Default::default()
+        };
+        let out = dir.path().join(name);
+        run_stage7_report_with_options(
+            &dummy_dataset(),
+            &dummy_expr(),
+            &dummy_axes(),
+            &dummy_scores(),
+            &dummy_classify(),
+            &dummy_panels(),
+            &out,
+            "cell",
+            RunMode::Standalone,
+            Some(&meta),
+            &opts,
+        )
+        .expect("stage7");
+        let json: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(out.join("summary.json")).expect("read"))
+                .expect("json");
+        json["condition_comparison"].clone()
+    };
+
+    let skipped = run(DEFAULT_MIN_CELLS_TO_COMPARE, "gated");
+    assert_eq!(
+        skipped["skipped"],
+        format!("group ctrl has 1 cells, fewer than {DEFAULT_MIN_CELLS_TO_COMPARE}")
+    );
+    assert_eq!(skipped["groups"][1]["name"], "treated");
+    assert!(skipped.get("regimes").is_none());
+
+    let compared = run(1, "compared");
+    assert!(compared.get("skipped").is_none());
+    let load = &compared["metrics"]["secretory_load"];
+    assert_eq!(
+        (load["n_a"].as_u64(), load["n_b"].as_u64()),
+        (Some(1), Some(1))
+    );
+    assert!(load["p_value"].as_f64().expect("p") <= 1.0);
+    let n_regimes = compared["regimes"].as_object().expect("regimes").len();
+    assert_eq!(compared["n_tests"], n_regimes + 2);
+}
+
+#[test]
+fn full_disk_mid_report_names_artifact_and_leaves_no_temp_files() {
+    let full = tempdir().expect("tempdir");
+    run_stage7_report(
+        &dummy_dataset(),
+        &dummy_expr(),
+        &dummy_axes(),
+        &dummy_scores(),
+        &dummy_classify(),
+        &dummy_panels(),
+        full.path(),
+        "cell",
+        RunMode::Pipeline,
+        None,
+    )
+    .expect("stage7");
+    let secretion = std::fs::metadata(full.path().join("secretion.tsv"))
+        .expect("secretion")
+        .len();
+
+    // Room for secretion.tsv and a few bytes of the next artifact.
+    let dir = tempdir().expect("tempdir");
+    let _guard = crate::report::artifact::fail_after(secretion + 8);
+    let err = run_stage7_report(
+        &dummy_dataset(),
+        &dummy_expr(),
+        &dummy_axes(),
+        &dummy_scores(),
+        &dummy_classify(),
+        &dummy_panels(),
+        dir.path(),
+        "cell",
+        RunMode::Pipeline,
+        None,
+    )
+    .expect_err("disk full");
+    match &err {
+        Stage7Error::Write { path, .. } => assert_eq!(path, &dir.path().join("panels_report.tsv")),
+        other => panic!("expected a write error, got {other}"),
+    }
+    let mut left: Vec<String> = std::fs::read_dir(dir.path())
+        .expect("read_dir")
+        .map(|e| e.expect("entry").file_name().to_string_lossy().into_owned())
+        .collect();
+    left.sort();
+    assert_eq!(left, vec!["secretion.tsv"]);
+}
+
+#[test]
+fn flagged_cells_lists_only_flagged_rows_worst_first() {
+    let dir = tempdir().expect("tempdir");
+    let mut classify = dummy_classify();
+    classify.flags[0].set(Flags::LOW_COUNTS);
+    let opts = ReportOptions {
+        flagged_output: true,
+        ..Default::default()
+    };
+    let run = |classify: &ClassifyContext, out: &Path| {
+        run_stage7_report_with_options(
+            &dummy_dataset(),
+            &dummy_expr(),
+            &dummy_axes(),
+            &dummy_scores(),
+            classify,
+            &dummy_panels(),
+            out,
+            "cell",
+            RunMode::Pipeline,
+            None,
+            &opts,
+        )
+        .expect("stage7");
+    };
+    run(&classify, dir.path());
+
+    let secretion = std::fs::read_to_string(dir.path().join("secretion.tsv")).expect("read");
+    let flagged = std::fs::read_to_string(dir.path().join("flagged_cells.tsv")).expect("read");
+    let lines: Vec<&str> = flagged.lines().collect();
+    assert_eq!(
+        lines[0],
+        format!("{}\ttriggered_flags", secretion.lines().next().unwrap())
+    );
+    assert_eq!(lines.len(), 3, "{flagged}");
+    // c2 (confidence 0.5) before c1 (0.9); rows match secretion.tsv otherwise.
+    assert!(lines[1].starts_with("c2\t"));
+    assert!(
+        lines[1].ends_with("\tLOW_CONFIDENCE,LOW_SECRETORY_SIGNAL"),
+        "{}",
+        lines[1]
+    );
+    assert!(lines[2].ends_with("\tLOW_COUNTS"), "{}", lines[2]);
+    let c1 = secretion.lines().find(|l| l.starts_with("c1\t")).unwrap();
+    assert_eq!(lines[2], format!("{c1}\tLOW_COUNTS"));
+    let step = std::fs::read_to_string(dir.path().join("pipeline_step.json")).expect("read");
+    assert!(step.contains("\"flagged_cells.tsv\""), "{step}");
+
+    let clean = tempdir().expect("tempdir");
+    let mut classify = dummy_classify();
+    classify.flags[0] = Flags::empty();
+    run(&classify, clean.path());
+    let again = std::fs::read_to_string(clean.path().join("flagged_cells.tsv")).expect("read");
+    assert_eq!(again.lines().count(), 2);
+}
+
+#[test]
+fn panels_by_sample_rows_in_panel_then_sample_order() {
+    let dir = tempdir().expect("tempdir");
+    let meta = dir.path().join("meta.tsv");
+    std::fs::write(&meta, "cell_id\tsample_id\nc1\tS2\nc2\tS1\n").expect("write");
+    let mut panels = dummy_panels();
+    let mut second = panels.panels.panels[0].clone();
+    second.id = "A0".to_string();
+    second.required = vec![];
+    panels.panels.panels.push(second);
+    panels.mappings.push(GeneMapping {
+        panel_id: "A0".to_string(),
+        mapped: vec![Some(0)],
+        required_hits: 0,
+        required_total: 0,
+    });
+    panels.per_cell[0].sums.push(3.0);
+    panels.per_cell[0].hits.push(1);
+    panels.per_cell[0].required_missing.push(0);
+    panels.per_cell[1].sums.push(4.0);
+    panels.per_cell[1].hits.push(0);
+    panels.per_cell[1].required_missing.push(0);
+    panels.per_cell[1].required_missing[0] = 1;
+
+    let out = dir.path().join("out");
+    run_stage7_report(
+        &dummy_dataset(),
+        &dummy_expr(),
+        &dummy_axes(),
+        &dummy_scores(),
+        &dummy_classify(),
+        &panels,
+        &out,
+        "cell",
+        RunMode::Standalone,
+        Some(&meta),
+    )
+    .expect("stage7");
+    let tsv = std::fs::read_to_string(out.join("panels_by_sample.tsv")).expect("read");
+    assert_eq!(
+        tsv.lines().collect::<Vec<_>>(),
+        vec![
+            "panel_id\tsample\tn_cells\tsum_median\tcoverage_median\tfrac_coverage_ge_0.8",
+            "P1\tS1\t1\t2.000000\t0.000000\t0.000000",
+            "P1\tS2\t1\t1.000000\t1.000000\t1.000000",
+            "A0\tS1\t1\t4.000000\t1.000000\t1.000000",
+            "A0\tS2\t1\t3.000000\t1.000000\t1.000000",
+        ]
+    );
+
+    let no_meta = dir.path().join("no_meta");
+    run_stage7_report(
+        &dummy_dataset(),
+        &dummy_expr(),
+        &dummy_axes(),
+        &dummy_scores(),
+        &dummy_classify(),
+        &panels,
+        &no_meta,
+        "cell",
+        RunMode::Standalone,
+        None,
+    )
+    .expect("stage7");
+    assert!(!no_meta.join("panels_by_sample.tsv").exists());
+}
+
+#[test]
+fn ambient_risk_is_summarized_per_sample() {
+    let dir = tempdir().expect("tempdir");
+    let meta = dir.path().join("meta.tsv");
+    std::fs::write(&meta, "cell_id\tsample_id\nc1\tS2\nc2\tS1\n").expect("write");
+    let mut classify = dummy_classify();
+    let mut ambient = Flags::empty();
+    ambient.set(Flags::HIGH_AMBIENT_RISK);
+    classify.flags[0] = ambient;
+
+    let out = dir.path().join("out");
+    let summary = run_stage7_report(
+        &dummy_dataset(),
+        &dummy_expr(),
+        &dummy_axes(),
+        &dummy_scores(),
+        &classify,
+        &dummy_panels(),
+        &out,
+        "cell",
+        RunMode::Standalone,
+        Some(&meta),
+    )
+    .expect("stage7");
+    assert_eq!(summary.qc.high_ambient_risk_fraction, 0.5);
+    assert_eq!(
+        summary.qc.ambient_risk_by_sample(),
+        vec![("S1", 0.0), ("S2", 1.0)]
+    );
+
+    let json: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(out.join("summary.json")).expect("read"))
+            .expect("json");
+    assert_eq!(json["qc"]["high_ambient_risk_fraction"], 0.5);
+    assert_eq!(
+        json["qc"]["high_ambient_risk_by_sample"]["S2"],
+        serde_json::json!({"n_cells": 1, "n_flagged": 1, "fraction": 1.0})
+    );
+    let report = std::fs::read_to_string(out.join("report.txt")).expect("report");
+    assert!(
+        report.contains("  by sample: S1 0.00%, S2 100.00%"),
+        "{report}"
+    );
+}
+
+#[test]
+fn proliferation_covariate_scores_and_flags_cells() {
+    let mut panels = dummy_panels();
+    let mut prolif = panels.panels.panels[0].clone();
+    prolif.id = PROLIFERATION_PANEL.to_string();
+    prolif.axis = COVARIATE_AXIS.to_string();
+    prolif.required = vec![];
+    panels.panels.panels.push(prolif);
+    panels.mappings.push(GeneMapping {
+        panel_id: PROLIFERATION_PANEL.to_string(),
+        mapped: vec![Some(0)],
+        required_hits: 0,
+        required_total: 0,
+    });
+    for (cell, sum) in panels.per_cell.iter_mut().zip([0.5, 9.0]) {
+        cell.sums.push(sum);
+        cell.hits.push(1);
+        cell.required_missing.push(0);
+    }
+
+    let dir = tempdir().expect("tempdir");
+    let summary = run_stage7_report(
+        &dummy_dataset(),
+        &dummy_expr(),
+        &dummy_axes(),
+        &dummy_scores(),
+        &dummy_classify(),
+        &panels,
+        dir.path(),
+        "cell",
+        RunMode::Standalone,
+        None,
+    )
+    .expect("stage7");
+    assert_eq!(summary.qc.high_proliferation_fraction, 0.5);
+
+    let tsv = std::fs::read_to_string(dir.path().join("secretion.tsv")).expect("read");
+    let rows: Vec<Vec<&str>> = tsv
+        .lines()
+        .skip(1)
+        .map(|l| l.split('\t').collect())
+        .collect();
+    assert_eq!(rows[0][16], "0.333333");
+    assert!(
+        !rows[0][14].contains("HIGH_PROLIFERATION"),
+        "{}",
+        rows[0][14]
+    );
+    assert_eq!(rows[1][16], "0.900000");
+    assert!(
+        rows[1][14].ends_with("HIGH_PROLIFERATION"),
+        "{}",
+        rows[1][14]
+    );
+
+    let json: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(dir.path().join("summary.json")).expect("read"))
+            .expect("json");
+    assert_eq!(json["qc"]["high_proliferation_fraction"], 0.5);
+
+    // Without the covariate panel the score is NaN and nothing is flagged.
+    let plain = tempdir().expect("tempdir");
+    let summary = run_stage7_report(
+        &dummy_dataset(),
+        &dummy_expr(),
+        &dummy_axes(),
+        &dummy_scores(),
+        &dummy_classify(),
+        &dummy_panels(),
+        plain.path(),
+        "cell",
+        RunMode::Standalone,
+        None,
+    )
+    .expect("stage7");
+    assert_eq!(summary.qc.high_proliferation_fraction, 0.0);
+    let tsv = std::fs::read_to_string(plain.path().join("secretion.tsv")).expect("read");
+    let first = tsv.lines().nth(1).expect("row");
+    assert_eq!(
+        first.rsplit('\t').nth(1),
+        Some(crate::report::format::nan_token().as_str()),
+        "{first}"
+    );
+}
+
+#[test]
+fn mode_recorded_in_summary_and_provenance() {
+    for mode in ["cell", "sample"] {
+        let dir = tempdir().expect("tempdir");
+        let summary = run_stage7_report(
+            &dummy_dataset(),
+            &dummy_expr(),
+            &dummy_axes(),
+            &dummy_scores(),
+            &dummy_classify(),
+            &dummy_panels(),
+            dir.path(),
+            mode,
+            RunMode::Standalone,
+            None,
+        )
+        .expect("stage7");
+        assert_eq!(summary.provenance.mode, mode);
+        for name in ["summary.json", "provenance.json"] {
+            let v: serde_json::Value =
+                serde_json::from_slice(&std::fs::read(dir.path().join(name)).expect("read"))
+                    .expect("json");
+            let recorded = if name == "summary.json" {
+                &v["provenance"]["mode"]
+            } else {
+                &v["mode"]
+            };
+            assert_eq!(recorded, mode, "{name}");
+        }
+    }
+}
+
+#[test]
+fn contract_version_folds_or_emits_extended_regimes() {
+    let mut classify = dummy_classify();
+    classify.regimes[0] = Regime::SecretoryLysosomeActive;
+    classify.rule_ids[0] = RuleId::R2SecretoryLysosomeActive;
+    let run = |contract: PipelineContract| {
+        let dir = tempdir().expect("tempdir");
+        let opts = ReportOptions {
+            regime_labels: RegimeLabels::default().with_contract(contract),
+            ..Default::default()
+        };
+        run_stage7_report_with_options(
+            &dummy_dataset(),
+            &dummy_expr(),
+            &dummy_axes(),
+            &dummy_scores(),
+            &classify,
+            &dummy_panels(),
+            dir.path(),
+            "cell",
+            RunMode::Pipeline,
+            None,
+            &opts,
+        )
+        .expect("stage7");
+        let read_json = |name: &str| -> serde_json::Value {
+            serde_json::from_slice(&std::fs::read(dir.path().join(name)).expect("read"))
+                .expect("json")
+        };
+        let secretion = std::fs::read_to_string(dir.path().join("secretion.tsv")).expect("read");
+        let mut lines = secretion.lines();
+        let column = lines
+            .next()
+            .and_then(|header| header.split('\t').position(|c| c == "regime"))
+            .expect("regime column");
+        let first_regime = lines
+            .next()
+            .and_then(|line| line.split('\t').nth(column))
+            .expect("row")
+            .to_string();
+        (
+            read_json("pipeline_step.json"),
+            read_json("summary.json"),
+            first_regime,
+        )
+    };
+
+    let (step, summary, regime) = run(PipelineContract::V1);
+    assert_eq!(step["contract_version"], 1);
+    assert_eq!(step["regimes"].as_array().expect("regimes").len(), 6);
+    assert!(!step["regimes"].to_string().contains("LysosomalSecretion"));
+    assert!(
+        summary["regimes"]["counts"]
+            .get("LysosomalSecretion")
+            .is_none()
+    );
+    assert_eq!(regime, "AdaptiveSecretion");
+
+    let (step, summary, regime) = run(PipelineContract::V2);
+    assert_eq!(step["contract_version"], 2);
+    assert_eq!(step["regimes"].as_array().expect("regimes").len(), 7);
+    assert_eq!(summary["regimes"]["counts"]["LysosomalSecretion"], 1);
+    assert_eq!(regime, "LysosomalSecretion");
+}
+
+#[test]
+fn explain_cells_match_secretion_tsv() {
+    let dir = tempdir().expect("tempdir");
+    let opts = ReportOptions {
+        explain_cells: Some(ExplainCells {
+            barcodes: vec!["c2".to_string(), "nope".to_string(), "c1".to_string()],
+            max_cells: 1,
+        }),
+        ..Default::default()
+    };
+    run_stage7_report_with_options(
+        &dummy_dataset(),
+        &dummy_expr(),
+        &dummy_axes(),
+        &dummy_scores(),
+        &dummy_classify(),
+        &dummy_panels(),
+        dir.path(),
+        "cell",
+        RunMode::Standalone,
+        None,
+        &opts,
+    )
+    .expect("stage7");
+
+    let explain = dir.path().join("explain");
+    assert!(
+        !explain.join("c1.json").exists(),
+        "over --explain-max-cells"
+    );
+    assert_eq!(
+        std::fs::read_to_string(explain.join("_missing.txt")).expect("missing"),
+        "nope\n"
+    );
+    assert_eq!(
+        std::fs::read_to_string(explain.join("_index.tsv")).expect("index"),
+        "file\tbarcode\nc2.json\tc2\n"
+    );
+    let cell: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(explain.join("c2.json")).expect("read"))
+            .expect("json");
+    assert_eq!(cell["barcode"], "c2");
+    assert_eq!(cell["cell_index"], 1);
+    for key in ["SIA", "EEB", "SLI", "MEI", "ECMI", "APCI", "GDI"] {
+        assert!(cell["axes"][key]["coverage"].is_number(), "{key}");
+        assert!(cell["axes"][key]["drivers"].is_string(), "{key}");
+    }
+    assert_eq!(cell["classification"]["regime"], "SelfPreserving");
+    assert_eq!(cell["classification"]["rule_id"], "R1_SELF_PRESERVING");
+    let trace = cell["classification"]["rule_trace"]
+        .as_array()
+        .expect("trace");
+    assert_eq!(trace.len(), 7);
+    assert_eq!(trace[0]["rule"], "R1_SELF_PRESERVING");
+    assert!(trace.iter().all(|step| step["matched"].is_boolean()));
+
+    let secretion = std::fs::read_to_string(dir.path().join("secretion.tsv")).expect("read");
+    let mut lines = secretion.lines();
+    let header: Vec<&str> = lines.next().expect("header").split('\t').collect();
+    let row: Vec<&str> = lines
+        .find(|line| line.starts_with("c2\t"))
+        .expect("c2 row")
+        .split('\t')
+        .collect();
+    for (column, value) in header.iter().zip(&row) {
+        let explained = &cell["secretion"][*column];
+        match *column {
+            "barcode" | "sample" | "condition" | "species" => {
+                assert_eq!(cell[*column], *value, "{column}")
+            }
+            "libsize" | "nnz" | "expressed_genes" => {
+                assert_eq!(cell["counts"][*column].to_string(), *value, "{column}")
+            }
+            "regime" | "flags" => assert_eq!(explained, *value, "{column}"),
+            "genes_per_1k_umis" => {
+                let tsv: f64 = value.parse().expect("number");
+                if tsv.is_nan() {
+                    assert!(cell["counts"][*column].is_null(), "{column}");
+                    continue;
+                }
+                let json = cell["counts"][*column].as_f64().expect(column);
+                assert!((tsv - json).abs() < 1e-6, "{column}: {tsv} vs {json}");
+            }
+            _ => {
+                let tsv: f64 = value.parse().expect("number");
+                if tsv.is_nan() {
+                    assert!(explained.is_null(), "{column}");
+                    continue;
+                }
+                let json = explained.as_f64().expect(column);
+                assert!((tsv - json).abs() < 1e-6, "{column}: {tsv} vs {json}");
+            }
+        }
+    }
+}
+
+#[test]
+fn extreme_normalization_reads_as_low_signal_not_hypersecretory() {
+    let mut axes = dummy_axes();
+    axes.values[0] = AxisValues {
+        sia: 1.0,
+        eeb: 1.0,
+        sli: 1.0,
+        mei: 1.0,
+        ecmi: 1.0,
+        apci: 1.0,
+        gdi: 1.0,
+    };
+    let mut scores = dummy_scores();
+    scores.oii[0] = 0.95;
+    let run = |extreme: bool| {
+        let dir = tempdir().expect("tempdir");
+        let mut classify = dummy_classify();
+        if extreme {
+            classify.flags[0].set(Flags::EXTREME_NORMALIZATION);
+        }
+        run_stage7_report(
+            &dummy_dataset(),
+            &dummy_expr(),
+            &axes,
+            &scores,
+            &classify,
+            &dummy_panels(),
+            dir.path(),
+            "cell",
+            RunMode::Standalone,
+            None,
+        )
+        .expect("stage7");
+        let tsv = std::fs::read_to_string(dir.path().join("secretion.tsv")).expect("read");
+        let header: Vec<&str> = tsv.lines().next().expect("header").split('\t').collect();
+        let col = |name: &str| header.iter().position(|c| *c == name).expect(name);
+        let row: Vec<String> = tsv
+            .lines()
+            .nth(1)
+            .expect("row")
+            .split('\t')
+            .map(str::to_string)
+            .collect();
+        let summary: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(dir.path().join("summary.json")).expect("read"))
+                .expect("json");
+        (
+            row[col("regime")].clone(),
+            row[col("flags")].clone(),
+            summary["qc"]["extreme_normalization_fraction"].as_f64(),
+        )
+    };
+
+    let (regime, _, fraction) = run(false);
+    assert_eq!(regime, "HypersecretoryState");
+    assert_eq!(fraction, Some(0.0));
+
+    let (regime, flags, fraction) = run(true);
+    assert_eq!(regime, "SecretoryCollapse");
+    assert!(flags.contains("LOW_SECRETORY_SIGNAL"), "{flags}");
+    assert_eq!(fraction, Some(0.5));
+}
+
+#[test]
+fn secretion_bin_dumps_back_to_secretion_tsv() {
+    let dir = tempdir().expect("tempdir");
+    let opts = ReportOptions {
+        binary_output: true,
+        ..Default::default()
+    };
+    run_stage7_report_with_options(
+        &dummy_dataset(),
+        &dummy_expr(),
+        &dummy_axes(),
+        &dummy_scores(),
+        &dummy_classify(),
+        &dummy_panels(),
+        dir.path(),
+        "cell",
+        RunMode::Standalone,
+        None,
+        &opts,
+    )
+    .expect("stage7");
+
+    let tsv = std::fs::read_to_string(dir.path().join("secretion.tsv")).expect("read");
+    let table = crate::report::binfmt::read_bin(&dir.path().join("secretion.bin")).expect("bin");
+    assert_eq!(table.to_tsv(crate::report::format::NanToken::Nan), tsv);
+    assert!(matches!(
+        table.column("regime"),
+        Some(crate::report::binfmt::ColumnData::Code { .. })
+    ));
+}
+
+#[test]
+fn compressed_secretion_tsv_is_bgzf_of_the_plain_table() {
+    use std::io::Read;
+
+    let dir = tempdir().expect("tempdir");
+    let opts = ReportOptions {
+        secretion_compression: Some(CompressFormat::Bgzf),
+        ..Default::default()
+    };
+    run_stage7_report_with_options(
+        &dummy_dataset(),
+        &dummy_expr(),
+        &dummy_axes(),
+        &dummy_scores(),
+        &dummy_classify(),
+        &dummy_panels(),
+        dir.path(),
+        "cell",
+        RunMode::Pipeline,
+        None,
+        &opts,
+    )
+    .expect("stage7");
+
+    let tsv = std::fs::read(dir.path().join("secretion.tsv")).expect("read");
+    let gz = std::fs::read(dir.path().join(SECRETION_GZ)).expect("read gz");
+    assert!(gz.ends_with(&crate::report::compress::BGZF_EOF));
+    let mut decoded = Vec::new();
+    flate2::read::MultiGzDecoder::new(gz.as_slice())
+        .read_to_end(&mut decoded)
+        .expect("gunzip");
+    assert_eq!(decoded, tsv);
+
+    let step: serde_json::Value = serde_json::from_slice(
+        &std::fs::read(dir.path().join("pipeline_step.json")).expect("read"),
+    )
+    .expect("json");
+    assert_eq!(step["artifacts"]["secretion_gz"], SECRETION_GZ);
+}
+
+#[test]
+fn summary_counts_flag_combinations_with_canonical_keys() {
+    let dir = tempdir().expect("tempdir");
+    let mut classify = dummy_classify();
+    // Set in non-alphabetical order; the key must still be sorted.
+    classify.flags[0].set(Flags::HIGH_AMBIENT_RISK);
+    classify.flags[0].set(Flags::FEW_DETECTED_GENES);
+    classify.flags[1].set(Flags::LOW_COUNTS);
+    run_stage7_report_with_options(
+        &dummy_dataset(),
+        &dummy_expr(),
+        &dummy_axes(),
+        &dummy_scores(),
+        &classify,
+        &dummy_panels(),
+        dir.path(),
+        "cell",
+        RunMode::Standalone,
+        None,
+        &ReportOptions::default(),
+    )
+    .expect("stage7");
+
+    let v: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(dir.path().join("summary.json")).expect("read"))
+            .expect("json");
+    assert_eq!(
+        v["qc"]["flag_combinations"],
+        json!([
+            {"flags": "FEW_DETECTED_GENES+HIGH_AMBIENT_RISK", "n_cells": 1, "fraction": 0.5},
+            // Stage 7 adds its own flags to the second cell.
+            {"flags": "LOW_CONFIDENCE+LOW_COUNTS+LOW_SECRETORY_SIGNAL", "n_cells": 1, "fraction": 0.5},
+        ])
+    );
+    let text = std::fs::read_to_string(dir.path().join("report.txt")).expect("read");
+    assert!(
+        text.contains("- FEW_DETECTED_GENES+HIGH_AMBIENT_RISK: 1 cells (50.00%) - likely empty"),
+        "{text}"
+    );
+}
+
+/// `dummy_panels` plus a GDI panel whose last two genes are not in the
+/// matrix: two of the four unique panel genes map.
+fn half_mapped_panels() -> PanelsContext {
+    let mut panels = dummy_panels();
+    panels.panels.panels.push(PanelDef {
+        id: "P2".to_string(),
+        description: "Panel Two".to_string(),
+        axis: "GDI".to_string(),
+        group: None,
+        genes: ["G1", "G2", "G3", "G4"]
+            .into_iter()
+            .map(|symbol| PanelGene {
+                symbol: symbol.to_string(),
+            })
+            .collect(),
+        required: Vec::new(),
+        weights: None,
+        custom_axis: false,
+        version: None,
+        source: None,
+    });
+    panels.mappings.push(GeneMapping {
+        panel_id: "P2".to_string(),
+        mapped: vec![Some(0), Some(1), None, None],
+        required_hits: 0,
+        required_total: 0,
+    });
+    for cell in &mut panels.per_cell {
+        cell.sums.push(1.0);
+        cell.hits.push(2);
+        cell.required_missing.push(0);
+    }
+    panels
+}
+
+#[test]
+fn missing_half_the_panel_genes_flags_the_dataset() {
+    let run = |min_panel_gene_fraction: f32| {
+        let dir = tempdir().expect("tempdir");
+        let opts = ReportOptions {
+            min_panel_gene_fraction,
+            ..Default::default()
+        };
+        let summary = run_stage7_report_with_options(
+            &dummy_dataset(),
+            &dummy_expr(),
+            &dummy_axes(),
+            &dummy_scores(),
+            &dummy_classify(),
+            &half_mapped_panels(),
+            dir.path(),
+            "cell",
+            RunMode::Pipeline,
+            None,
+            &opts,
+        )
+        .expect("stage7");
+        (dir, summary)
+    };
+    let read_json = |dir: &tempfile::TempDir, name: &str| -> serde_json::Value {
+        serde_json::from_slice(&std::fs::read(dir.path().join(name)).expect("read")).expect("json")
+    };
+
+    let (dir, summary) = run(0.6);
+    assert_eq!(summary.dataset_flags(), vec![LOW_PANEL_GENE_COVERAGE]);
+    let genes = &read_json(&dir, "summary.json")["input"]["panel_genes"];
+    assert_eq!(genes["mapped"], 2);
+    assert_eq!(genes["unmapped"], 2);
+    assert_eq!(genes["total"], 4);
+    assert_eq!(genes["mapped_fraction"].as_f64(), Some(0.5));
+    assert_eq!(genes["low_coverage"], true);
+    assert_eq!(
+        genes["by_axis"],
+        serde_json::json!({
+            "GDI": {"mapped": 2, "unmapped": 2, "total": 4},
+            "SIA": {"mapped": 1, "unmapped": 0, "total": 1},
+        })
+    );
+    assert_eq!(
+        read_json(&dir, "pipeline_step.json")["dataset_flags"],
+        serde_json::json!([LOW_PANEL_GENE_COVERAGE])
+    );
+    let report = std::fs::read_to_string(dir.path().join("report.txt")).expect("report");
+    assert!(report.contains("WARNING: only 2 of 4 panel genes (50.00%)"));
+    assert!(report.contains("- Panel genes mapped: 2/4"));
+
+    // Exactly half is not below the default minimum of one half.
+    let (dir, summary) = run(DEFAULT_MIN_PANEL_GENE_FRACTION);
+    assert!(summary.dataset_flags().is_empty());
+    assert_eq!(
+        read_json(&dir, "pipeline_step.json")["dataset_flags"],
+        serde_json::json!([])
+    );
+    let report = std::fs::read_to_string(dir.path().join("report.txt")).expect("report");
+    assert!(!report.contains("WARNING"));
+}
@@ -0,0 +1,95 @@
+use super::*;
+use tempfile::tempdir;
+
+fn write_artifacts(dir: &Path, secretion_ids: &[&str], classify_ids: &[&str], counts: &str) {
+    let mut secretion = String::from("barcode\tregime\n");
+    for id in secretion_ids {
+        secretion.push_str(&format!("{}\tBasalSecretory\n", id));
+    }
+    std::fs::write(dir.join("secretion.tsv"), secretion).unwrap();
+
+    let mut classify = String::from("cell_id\tregime\trule_id\tflags\n");
+    for id in classify_ids {
+        classify.push_str(&format!("{}\tBasalSecretory\tR0\t\n", id));
+    }
+    std::fs::write(dir.join("classify.tsv"), classify).unwrap();
+
+    std::fs::write(
+        dir.join("summary.json"),
+        format!(
+            "{{\"input\": {{\"n_cells\": 2}}, \"regimes\": {{\"counts\": {}}}}}",
+            counts
+        ),
+    )
+    .unwrap();
+}
+
+fn barcodes() -> Vec<String> {
+    vec!["c1".to_string(), "c2".to_string()]
+}
+
+#[test]
+fn consistent_artifacts_pass_and_write_marker() {
+    let dir = tempdir().unwrap();
+    write_artifacts(
+        dir.path(),
+        &["c1", "c2"],
+        &["c1", "c2"],
+        "{\"BasalSecretory\": 2}",
+    );
+    let report = run_verify(dir.path(), Some(&barcodes())).unwrap();
+    assert!(report.passed(), "{}", report.render());
+    apply_success_marker(dir.path(), &report).unwrap();
+    assert!(dir.path().join(SUCCESS_MARKER).is_file());
+}
+
+#[test]
+fn inconsistent_artifacts_fail_and_remove_marker() {
+    let dir = tempdir().unwrap();
+    std::fs::write(dir.path().join(SUCCESS_MARKER), b"").unwrap();
+    write_artifacts(
+        dir.path(),
+        &["c1", "c1", "c9"],
+        &["c1", "c2"],
+        "{\"BasalSecretory\": 1}",
+    );
+    let report = run_verify(dir.path(), Some(&barcodes())).unwrap();
+    let failed: Vec<&str> = report.failed().map(|c| c.name).collect();
+    assert_eq!(
+        failed,
+        vec![
+            "secretion_row_count",
+            "secretion_barcodes_unique",
+            "secretion_barcodes_known",
+            "summary_regime_counts",
+            "classify_secretion_agreement",
+        ]
+    );
+    apply_success_marker(dir.path(), &report).unwrap();
+    assert!(!dir.path().join(SUCCESS_MARKER).exists());
+}
+
+#[test]
+fn pipeline_step_flags_empty_artifacts() {
+    let dir = tempdir().unwrap();
+    write_artifacts(
+        dir.path(),
+        &["c1", "c2"],
+        &["c1", "c2"],
+        "{\"BasalSecretory\": 2}",
+    );
+    std::fs::write(dir.path().join("panels_report.tsv"), b"").unwrap();
+    std::fs::write(
+        dir.path().join("pipeline_step.json"),
+        "{\"artifacts\": {\"summary\": \"summary.json\", \"panels\": \"panels_report.tsv\"}, \"cell_metrics\": {\"file\": \"secretion.tsv\"}}",
+    )
+    .unwrap();
+    let report = run_verify(dir.path(), None).unwrap();
+    let check = report
+        .checks
+        .iter()
+        .find(|c| c.name == "pipeline_step_artifacts")
+        .unwrap();
+    assert!(!check.passed);
+    assert!(check.detail.contains("panels_report.tsv"));
+}
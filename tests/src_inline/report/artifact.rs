@@ -0,0 +1,82 @@
+use super::*;
+use tempfile::tempdir;
+
+fn names(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = std::fs::read_dir(dir)
+        .expect("read_dir")
+        .map(|e| e.expect("entry").file_name().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn commit_renames_temp_file_into_place() {
+    let dir = tempdir().expect("tempdir");
+    let mut artifact = Artifact::create(dir.path(), "out.tsv").expect("create");
+    artifact.write_all(b"a\tb\n").expect("write");
+    assert_eq!(names(dir.path()), vec!["out.tsv.tmp"]);
+    artifact.commit().expect("commit");
+    assert_eq!(names(dir.path()), vec!["out.tsv"]);
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("out.tsv")).expect("read"),
+        "a\tb\n"
+    );
+}
+
+#[test]
+fn dropped_artifact_leaves_nothing_behind() {
+    let dir = tempdir().expect("tempdir");
+    std::fs::write(dir.path().join("out.tsv"), "old").expect("write");
+    let mut artifact = Artifact::create(dir.path(), "out.tsv").expect("create");
+    artifact.write_all(b"new").expect("write");
+    drop(artifact);
+    assert_eq!(names(dir.path()), vec!["out.tsv"]);
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("out.tsv")).expect("read"),
+        "old"
+    );
+}
+
+#[test]
+fn full_disk_error_names_the_artifact() {
+    let dir = tempdir().expect("tempdir");
+    let _guard = fail_after(4);
+    let err = write_artifact(dir.path(), "summary.json", "{\"n\": 1}").expect_err("budget");
+    assert_eq!(err.kind(), io::ErrorKind::StorageFull);
+    let err = into_write_error(err).expect("artifact error");
+    assert_eq!(err.path, dir.path().join("summary.json"));
+    assert_eq!(err.source.kind(), io::ErrorKind::StorageFull);
+    assert!(names(dir.path()).is_empty());
+}
+
+#[test]
+fn budget_is_restored_when_guard_drops() {
+    let dir = tempdir().expect("tempdir");
+    drop(fail_after(0));
+    write_artifact(dir.path(), "a.txt", "fine").expect("no budget");
+}
+
+#[test]
+fn write_options_apply_to_artifacts_created_under_the_guard() {
+    assert_eq!(write_options(), WriteOptions::default());
+    let dir = tempdir().expect("tempdir");
+    {
+        let _guard = enter_write_options(WriteOptions {
+            buffer_size: 4,
+            durable: true,
+        });
+        let mut artifact = Artifact::create(dir.path(), "small.tsv").expect("create");
+        assert!(artifact.durable);
+        // Past the 4-byte buffer, bytes reach the temp file before commit.
+        artifact.write_all(b"abcdefgh").expect("write");
+        assert_eq!(
+            std::fs::read(dir.path().join("small.tsv.tmp")).expect("read tmp"),
+            b"abcdefgh"
+        );
+        artifact.commit().expect("durable commit");
+        write_artifact(dir.path(), "whole.json", "{}").expect("write_artifact");
+    }
+    assert_eq!(write_options(), WriteOptions::default());
+    assert_eq!(names(dir.path()), vec!["small.tsv", "whole.json"]);
+}
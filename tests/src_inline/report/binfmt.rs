@@ -0,0 +1,116 @@
+use super::*;
+
+fn sample_table() -> BinTable {
+    let mut table = BinTable::new(3);
+    table.push(
+        "barcode",
+        ColumnData::Str(vec!["AAAC-1".into(), "".into(), "ü-3".into()]),
+    );
+    table.push("libsize", ColumnData::U64(vec![10, 0, u64::MAX]));
+    table.push("nnz", ColumnData::U32(vec![4, 0, 7]));
+    table.push("score", ColumnData::F32(vec![0.5, f32::NAN, -0.0]));
+    table.push(
+        "regime",
+        ColumnData::Code {
+            codes: vec![1, 0, 1],
+            labels: vec!["Quiet".into(), "Busy".into()],
+        },
+    );
+    table.push(
+        "flags",
+        ColumnData::Flags {
+            bits: vec![0, 0b101, 0b010],
+            names: vec!["A".into(), "B".into(), "C".into()],
+        },
+    );
+    table
+}
+
+#[test]
+fn table_round_trips_and_prints_as_tsv() {
+    let table = sample_table();
+    let bytes = encode_bin(&table).unwrap();
+    assert_eq!(&bytes[..8], &BIN_MAGIC);
+    let back = decode_bin(&bytes).unwrap();
+    assert_eq!(back.n_cells, 3);
+    assert_eq!(back.column("barcode"), table.column("barcode"));
+    match back.column("score") {
+        Some(ColumnData::F32(values)) => {
+            assert_eq!(values[0], 0.5);
+            assert!(values[1].is_nan());
+            assert_eq!(values[2].to_bits(), (-0.0f32).to_bits());
+        }
+        other => panic!("{other:?}"),
+    }
+    assert_eq!(encode_bin(&back).unwrap(), bytes);
+
+    assert_eq!(
+        back.to_tsv(NanToken::Na),
+        "barcode\tlibsize\tnnz\tscore\tregime\tflags\n\
+         AAAC-1\t10\t4\t0.500000\tBusy\t.\n\
+         \t0\t0\tNA\tQuiet\tA,C\n\
+         ü-3\t18446744073709551615\t7\t0.000000\tBusy\tB\n"
+    );
+}
+
+#[test]
+fn columns_start_on_eight_byte_boundaries() {
+    let bytes = encode_bin(&sample_table()).unwrap();
+    // First directory entry: "barcode" (7 bytes) after the 22-byte header.
+    let offset_at = HEADER_LEN + 2 + 7 + 1;
+    let offset = u64::from_le_bytes(bytes[offset_at..offset_at + 8].try_into().unwrap());
+    assert_eq!(offset % 8, 0);
+    // No columns, no padding before the checksum.
+    assert_eq!(
+        encode_bin(&BinTable::new(0)).unwrap().len(),
+        HEADER_LEN + CRC_LEN
+    );
+}
+
+#[test]
+fn damaged_files_are_rejected() {
+    let bytes = encode_bin(&sample_table()).unwrap();
+
+    let mut flipped = bytes.clone();
+    let last_value = bytes.len() - 9;
+    flipped[last_value] ^= 1;
+    let err = decode_bin(&flipped).unwrap_err();
+    assert!(matches!(err, BinError::Checksum { .. }), "{err}");
+
+    let mut newer = bytes.clone();
+    newer[8..10].copy_from_slice(&(BIN_VERSION + 1).to_le_bytes());
+    let err = decode_bin(&newer).unwrap_err();
+    assert!(
+        matches!(err, BinError::VersionMismatch { found, .. } if found == BIN_VERSION + 1),
+        "{err}"
+    );
+
+    let err = decode_bin(&bytes[..bytes.len() - 4]).unwrap_err();
+    assert!(matches!(err, BinError::Checksum { .. }), "{err}");
+    let err = decode_bin(b"KSECCKPT").unwrap_err();
+    assert!(matches!(err, BinError::InvalidMagic), "{err}");
+}
+
+#[test]
+fn inconsistent_columns_are_not_encoded() {
+    let mut table = BinTable::new(2);
+    table.columns.push(BinColumn {
+        name: "regime".into(),
+        data: ColumnData::Code {
+            codes: vec![0, 2],
+            labels: vec!["A".into(), "B".into()],
+        },
+    });
+    let err = encode_bin(&table).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid secretion.bin: column regime: code 2 without a label"
+    );
+
+    let mut short = BinTable::new(2);
+    short.columns.push(BinColumn {
+        name: "nnz".into(),
+        data: ColumnData::U32(vec![1]),
+    });
+    assert!(encode_bin(&short).is_err());
+}
@@ -0,0 +1,111 @@
+use super::*;
+use std::io::Read;
+
+/// Mixed text that compresses but not trivially.
+fn sample(len: usize) -> Vec<u8> {
+    let mut state = 0x2545_f491u32;
+    (0..len)
+        .map(|i| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            if i % 61 == 60 {
+                b'\n'
+            } else {
+                b"ACGT\t0123456789."[(state % 16) as usize]
+            }
+        })
+        .collect()
+}
+
+fn bgzf(data: &[u8], chunk: usize) -> Vec<u8> {
+    let mut writer = BgzfWriter::new(Vec::new(), Compression::default());
+    for part in data.chunks(chunk.max(1)) {
+        writer.write_all(part).unwrap();
+        writer.flush().unwrap();
+    }
+    writer.finish().unwrap()
+}
+
+fn multi_gunzip(bytes: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    flate2::read::MultiGzDecoder::new(bytes)
+        .read_to_end(&mut out)
+        .unwrap();
+    out
+}
+
+/// `(block length, ISIZE)` per block, following each `BSIZE`.
+fn blocks(bytes: &[u8]) -> Vec<(usize, u32)> {
+    let mut blocks = Vec::new();
+    let mut at = 0;
+    while at < bytes.len() {
+        assert_eq!(&bytes[at..at + 16], &BGZF_HEADER, "block at {at}");
+        let len = u16::from_le_bytes([bytes[at + 16], bytes[at + 17]]) as usize + 1;
+        let isize = u32::from_le_bytes(bytes[at + len - 4..at + len].try_into().unwrap());
+        blocks.push((len, isize));
+        at += len;
+    }
+    assert_eq!(at, bytes.len());
+    blocks
+}
+
+#[test]
+fn bgzf_round_trips_through_a_multi_member_gzip_reader() {
+    let data = sample(3 * BGZF_BLOCK_DATA_LEN + 1234);
+    let bytes = bgzf(&data, 10_000);
+    assert_eq!(multi_gunzip(&bytes), data);
+
+    let blocks = blocks(&bytes);
+    let sizes: Vec<u32> = blocks.iter().map(|&(_, isize)| isize).collect();
+    let full = BGZF_BLOCK_DATA_LEN as u32;
+    assert_eq!(sizes, vec![full, full, full, 1234, 0]);
+    assert!(bytes.ends_with(&BGZF_EOF));
+}
+
+#[test]
+fn bgzf_blocks_do_not_depend_on_write_sizes_or_flushes() {
+    let data = sample(2 * BGZF_BLOCK_DATA_LEN + 17);
+    let whole = bgzf(&data, data.len());
+    assert_eq!(bgzf(&data, 1), whole);
+    assert_eq!(bgzf(&data, 4099), whole);
+}
+
+#[test]
+fn incompressible_blocks_stay_under_the_block_limit() {
+    let mut state = 7u64;
+    let noise: Vec<u8> = (0..BGZF_BLOCK_DATA_LEN * 2)
+        .map(|_| {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (state >> 56) as u8
+        })
+        .collect();
+    let bytes = bgzf(&noise, noise.len());
+    assert_eq!(multi_gunzip(&bytes), noise);
+    assert!(blocks(&bytes).iter().all(|&(len, _)| len <= 1 << 16));
+}
+
+#[test]
+fn empty_bgzf_is_only_the_eof_marker() {
+    let bytes = bgzf(&[], 1);
+    assert_eq!(bytes, BGZF_EOF);
+    assert!(multi_gunzip(&bytes).is_empty());
+}
+
+#[test]
+fn compressed_writer_formats_decode_to_the_same_text() {
+    let data = sample(BGZF_BLOCK_DATA_LEN + 99);
+    let encode = |format| {
+        let mut writer = CompressedWriter::new(Vec::new(), format);
+        writer.write_all(&data).unwrap();
+        writer.finish().unwrap()
+    };
+    let gzip = encode(CompressFormat::Gzip);
+    let bgzf = encode(CompressFormat::Bgzf);
+    assert_ne!(gzip, bgzf);
+    assert_eq!(multi_gunzip(&gzip), data);
+    assert_eq!(multi_gunzip(&bgzf), data);
+    assert_eq!(encode(CompressFormat::Bgzf), bgzf);
+}
@@ -0,0 +1,77 @@
+use super::*;
+
+#[test]
+fn plain_ids_keep_their_spelling() {
+    assert_eq!(
+        safe_file_name("AAACCTGAGAAACCAT-1", "json"),
+        "AAACCTGAGAAACCAT-1.json"
+    );
+    assert_eq!(safe_file_name("s1.v2_b", "tsv"), "s1.v2_b.tsv");
+    assert_eq!(safe_file_name("noext", ""), "noext");
+}
+
+#[test]
+fn unsafe_characters_are_percent_encoded() {
+    assert_eq!(safe_file_name("lane1/AAAC", "json"), "lane1%2FAAAC.json");
+    assert_eq!(safe_file_name(r"a\b:c*d?", "json"), "a%5Cb%3Ac%2Ad%3F.json");
+    assert_eq!(safe_file_name("50%~", "json"), "50%25%7E.json");
+    assert_eq!(safe_file_name("zellé", "json"), "zell%C3%A9.json");
+    assert_eq!(safe_file_name("..", "json"), "%2E%2E.json");
+    assert_eq!(safe_file_name("trail.", "json"), "trail%2E.json");
+    assert_eq!(safe_file_name("", "json"), "~.json");
+    // Distinct from the slash-free spelling it used to share.
+    assert_ne!(
+        safe_file_name("lane1/AAAC", "json"),
+        safe_file_name("lane1_AAAC", "json")
+    );
+}
+
+#[test]
+fn windows_device_names_are_escaped() {
+    assert_eq!(safe_file_name("CON", "json"), "%43ON.json");
+    assert_eq!(safe_file_name("nul.txt", "json"), "%6Eul.txt.json");
+    assert_eq!(safe_file_name("CONSOLE", "json"), "CONSOLE.json");
+}
+
+#[test]
+fn long_ids_are_cut_with_a_hash() {
+    let sample = "s".repeat(300);
+    let name = safe_file_name(&sample, "json");
+    assert_eq!(name.len(), MAX_FILE_NAME_BYTES);
+    assert!(name.starts_with("sss"), "{name}");
+    assert!(name.ends_with(".json"), "{name}");
+    assert_eq!(name.matches(HASH_MARK).count(), 1, "{name}");
+    assert_eq!(safe_file_name(&sample, "json"), name, "deterministic");
+
+    // Same first 300 characters, different tails: equal after truncation
+    // alone, told apart by the hash.
+    let a = safe_file_name(&format!("{sample}-a"), "json");
+    let b = safe_file_name(&format!("{sample}-b"), "json");
+    assert_eq!(a.len(), MAX_FILE_NAME_BYTES);
+    assert_ne!(a, b);
+    assert_eq!(a[..90], b[..90]);
+}
+
+#[test]
+fn truncation_keeps_escapes_whole() {
+    for pad in 0..3 {
+        let id = format!("{}{}", "x".repeat(pad), "/".repeat(200));
+        let name = safe_file_name(&id, "json");
+        let stem = &name[..name.find(HASH_MARK).expect("cut")];
+        assert!(stem.len() <= MAX_FILE_NAME_BYTES, "{name}");
+        let escapes = stem.trim_start_matches('x');
+        assert_eq!(escapes.len() % 3, 0, "{name}");
+        assert!(escapes.split('%').skip(1).all(|e| e == "2F"), "{name}");
+    }
+}
+
+#[test]
+fn index_maps_names_back_to_ids() {
+    let ids = ["lane1/AAAC", "CON"];
+    let names: Vec<String> = ids.iter().map(|id| safe_file_name(id, "json")).collect();
+    let tsv = name_index_tsv("barcode", names.iter().map(String::as_str).zip(ids)).expect("tsv");
+    assert_eq!(
+        tsv,
+        "file\tbarcode\nlane1%2FAAAC.json\tlane1/AAAC\n%43ON.json\tCON\n"
+    );
+}
@@ -0,0 +1,52 @@
+use super::*;
+
+#[test]
+fn policies_on_edge_values() {
+    let cases: [(f32, &str, &str); 10] = [
+        (0.0, "0.000000", "0.000000"),
+        (-0.0, "0.000000", "0.000000"),
+        (-1e-9, "0.000000", "0.000000"),
+        (0.1234565, "0.123457", "0.123457"),
+        (1.0, "1.000000", "1.000000"),
+        (1.5, "1.500000", "1.000000"),
+        (-0.25, "-0.250000", "0.000000"),
+        (-1.0, "-1.000000", "0.000000"),
+        (f32::NAN, "nan", "0.000000"),
+        (f32::INFINITY, "nan", "0.000000"),
+    ];
+    for (value, signed, clamped) in cases {
+        assert_eq!(
+            signed_or_nan_with(value, NanToken::Nan),
+            signed,
+            "signed_or_nan({value})"
+        );
+        assert_eq!(clamped01(value), clamped, "clamped01({value})");
+    }
+}
+
+#[test]
+fn nan_tokens() {
+    assert_eq!(signed_or_nan_with(f32::NAN, NanToken::Nan), "nan");
+    assert_eq!(signed_or_nan_with(f32::NAN, NanToken::Empty), "");
+    assert_eq!(signed_or_nan_with(f32::NAN, NanToken::Na), "NA");
+    assert_eq!(signed_or_nan_with(0.5, NanToken::Na), "0.500000");
+    assert_eq!(nan_token(), NanToken::Nan);
+}
+
+#[test]
+fn nan_token_guard_is_per_thread_and_restores() {
+    {
+        let _outer = enter_nan_token(NanToken::Na);
+        assert_eq!(signed_or_nan(f32::NAN), "NA");
+        {
+            let _inner = enter_nan_token(NanToken::Empty);
+            assert_eq!(signed_or_nan(f32::NAN), "");
+        }
+        assert_eq!(signed_or_nan(f32::NAN), "NA");
+        let other = std::thread::spawn(|| signed_or_nan(f32::NAN))
+            .join()
+            .expect("thread");
+        assert_eq!(other, "nan");
+    }
+    assert_eq!(nan_token(), NanToken::Nan);
+}
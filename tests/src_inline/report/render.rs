@@ -0,0 +1,268 @@
+use super::*;
+use crate::aggregate::flag_combos::FlagCombination;
+use crate::panels::defs::{PanelDef, PanelGene};
+use crate::panels::mapping::{GeneUniverseCount, PanelGeneUniverse};
+use crate::pipeline::stage3_panels::DeadPanel;
+use crate::pipeline::stage4_axes::{AxisPanelCount, AxisPanelCounts};
+use crate::pipeline::stage7_report::{
+    AxisMissingRequired, CaveatsSummary, DistributionSummary, FlagCount, FracGeGroup,
+    FracGeSummary, HistogramSummary, InputSummary, LibsizeLowTail, PanelGenesSummary,
+    PanelWarningSummary, ProvenanceSummary, QcSummary, Quantiles, RegimeSummary, ToolSummary,
+    WorstCoveredAxis,
+};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+fn quantiles(median: f32, p90: f32, p99: f32) -> Quantiles {
+    Quantiles { median, p90, p99 }
+}
+
+fn fixture_summary() -> FinalSummary {
+    let counts: BTreeMap<String, usize> = [
+        ("BaselineSecretory", 6),
+        ("HypersecretoryState", 3),
+        ("Unclassified", 1),
+    ]
+    .into_iter()
+    .map(|(k, v)| (k.to_string(), v))
+    .collect();
+    let fractions = counts
+        .iter()
+        .map(|(k, v)| (k.clone(), *v as f32 / 10.0))
+        .collect();
+    let count = AxisPanelCount {
+        panels: 1,
+        mapped_panels: 1,
+        mappable_genes: 2,
+    };
+    FinalSummary {
+        tool: ToolSummary {
+            name: "kira-secretion".to_string(),
+            version: "0.0.0".to_string(),
+            simd: "scalar".to_string(),
+        },
+        input: InputSummary {
+            n_cells: 10,
+            species: "human".to_string(),
+            meta_match_fraction: Some(0.9),
+            meta_issues: None,
+            gene_set_hash: String::new(),
+            libsize_low_tail: LibsizeLowTail {
+                min: 40.0,
+                p1: 120.0,
+            },
+            matrix_values: Default::default(),
+            panel_genes: PanelGenesSummary::new(
+                PanelGeneUniverse {
+                    overall: GeneUniverseCount {
+                        mapped: 5,
+                        total: 6,
+                    },
+                    by_axis: BTreeMap::new(),
+                },
+                0.5,
+            ),
+        },
+        distributions: DistributionSummary {
+            secretory_load: quantiles(0.25, 0.5, 0.75),
+            er_golgi_pressure: quantiles(0.125, 0.375, 0.625),
+            stress_secretion_index: quantiles(0.0, 0.25, 0.5),
+            score_concentration: quantiles(0.5, 0.75, 0.875),
+        },
+        histograms: HistogramSummary {
+            secretory_load: vec![4, 3, 2, 1],
+            er_golgi_pressure: vec![1, 2, 3, 4],
+            stress_secretion_index: vec![10, 0, 0, 0],
+            confidence: vec![0, 0, 0, 10],
+        },
+        regimes: RegimeSummary { counts, fractions },
+        qc: QcSummary {
+            low_confidence_fraction: 0.2,
+            low_secretory_signal_fraction: 0.1,
+            high_proliferation_fraction: 0.05,
+            high_ambient_risk_fraction: 0.1,
+            extreme_normalization_fraction: 0.1,
+            genes_per_1k_umis: quantiles(400.0, 650.0, 900.0),
+            high_ambient_risk_by_sample: BTreeMap::from([
+                (
+                    "s1".to_string(),
+                    FlagCount {
+                        n_cells: 5,
+                        n_flagged: 0,
+                    },
+                ),
+                (
+                    "s2".to_string(),
+                    FlagCount {
+                        n_cells: 5,
+                        n_flagged: 1,
+                    },
+                ),
+            ]),
+            flag_combinations: vec![
+                FlagCombination {
+                    flags: "LOW_CONFIDENCE+LOW_SECRETORY_SIGNAL".to_string(),
+                    n_cells: 2,
+                    fraction: 0.2,
+                },
+                FlagCombination {
+                    flags: "HIGH_AMBIENT_RISK+LOW_COUNTS".to_string(),
+                    n_cells: 1,
+                    fraction: 0.1,
+                },
+            ],
+        },
+        frac_ge: FracGeSummary {
+            thresholds: vec![("secretory_load".to_string(), vec![0.5])],
+            global: FracGeGroup {
+                n_cells: 10,
+                n_ge: vec![vec![3]],
+            },
+            by_sample: BTreeMap::new(),
+            by_condition: BTreeMap::new(),
+        },
+        caveats: CaveatsSummary {
+            absent_axes: vec!["APCI".to_string()],
+            panels_missing_required: vec![PanelWarningSummary {
+                panel_id: "ER_GOLGI".to_string(),
+                axis: "SIA".to_string(),
+                missing_required: vec!["SAR1A".to_string()],
+                mappable_fraction: 0.5,
+            }],
+            missing_required_by_axis: vec![AxisMissingRequired {
+                axis: "SIA".to_string(),
+                panels: vec!["ER_GOLGI".to_string()],
+                missing_required: vec!["SAR1A".to_string()],
+            }],
+            worst_covered_axis: Some(WorstCoveredAxis {
+                axis: "SIA".to_string(),
+                coverage_median: 0.25,
+            }),
+            low_axis_coverage_fraction: 0.3,
+            sanitized_fields: 0,
+            axis_panels: AxisPanelCounts {
+                sia: count,
+                eeb_export: count,
+                ..AxisPanelCounts::default()
+            },
+            skipped_panel_files: vec![],
+            dead_panels: vec![DeadPanel {
+                panel_id: "EXPORT".to_string(),
+                axis: "EEB_EXPORT".to_string(),
+                nonzero_fraction: 0.0,
+                max_sum: 0.0,
+            }],
+            dropped_dead_panels: vec![],
+        },
+        provenance: ProvenanceSummary {
+            coverage_mode: "required".to_string(),
+            mode: "cell".to_string(),
+            outputs: "standard".to_string(),
+            retain: "all".to_string(),
+        },
+        run: None,
+        chemistry: None,
+        upstream: None,
+        reference: None,
+        qc_gate: None,
+        condition_comparison: None,
+    }
+}
+
+fn fixture_panels() -> PanelSet {
+    let panel = |id: &str, axis: &str, description: &str| PanelDef {
+        id: id.to_string(),
+        description: description.to_string(),
+        axis: axis.to_string(),
+        group: None,
+        genes: ["SEC23A", "SAR1A"]
+            .iter()
+            .map(|g| PanelGene {
+                symbol: g.to_string(),
+            })
+            .collect(),
+        required: vec![],
+        weights: None,
+        custom_axis: false,
+        version: None,
+        source: None,
+    };
+    PanelSet {
+        panels: vec![
+            panel("ER_GOLGI", "SIA", "COPII | ER exit <core> & cargo"),
+            panel("EXPORT", "EEB_EXPORT", "Exocytosis\nmachinery"),
+        ],
+        files: vec![],
+        skipped: vec![],
+        required_autofixes: vec![],
+    }
+}
+
+/// Compares against `tests/golden/<name>`; `UPDATE_GOLDEN=1` rewrites it.
+fn assert_golden(name: &str, actual: &str) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(name);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::create_dir_all(path.parent().unwrap()).expect("mkdir");
+        std::fs::write(&path, actual).expect("write golden");
+    }
+    let expected = std::fs::read_to_string(&path).expect("read golden");
+    assert_eq!(actual, expected, "{name} differs from its golden file");
+}
+
+#[test]
+fn reports_match_golden_files() {
+    let summary = fixture_summary();
+    let panels = fixture_panels();
+    for format in [ReportFormat::Txt, ReportFormat::Md, ReportFormat::Html] {
+        let rendered = render(format, &summary, &panels, false);
+        assert_eq!(rendered, render(format, &summary, &panels, false));
+        assert_golden(format.file_name(), &rendered);
+    }
+}
+
+#[test]
+fn markdown_table_cells_escape_pipes() {
+    let md = render(
+        ReportFormat::Md,
+        &fixture_summary(),
+        &fixture_panels(),
+        false,
+    );
+    let row = md
+        .lines()
+        .find(|l| l.starts_with("| ER_GOLGI |"))
+        .expect("panel row");
+    assert!(row.contains("COPII \\| ER exit"), "{row}");
+    // Five columns: six unescaped pipes.
+    assert_eq!(row.replace("\\|", "").matches('|').count(), 6, "{row}");
+    assert!(md.contains("| EXPORT | EEB_EXPORT | EEB_EXPORT | 2 | Exocytosis machinery |"));
+}
+
+#[test]
+fn low_panel_gene_coverage_heads_every_format() {
+    let mut summary = fixture_summary();
+    summary.input.panel_genes = PanelGenesSummary::new(
+        PanelGeneUniverse {
+            overall: GeneUniverseCount {
+                mapped: 60,
+                total: 320,
+            },
+            by_axis: BTreeMap::new(),
+        },
+        0.5,
+    );
+    let warning =
+        "only 60 of 320 panel genes (18.75%) are in this dataset, below the 50.00% minimum";
+    for format in [ReportFormat::Txt, ReportFormat::Md, ReportFormat::Html] {
+        let rendered = render(format, &summary, &fixture_panels(), false);
+        let at = rendered.find(warning).expect("warning");
+        let overview = rendered.find("Dataset overview").expect("overview");
+        assert!(
+            at < overview,
+            "{}: warning after the overview",
+            format.file_name()
+        );
+    }
+}
@@ -0,0 +1,44 @@
+use super::*;
+
+#[test]
+fn clean_fields_are_borrowed() {
+    let _guard = enter(FieldPolicy::Strict);
+    assert!(matches!(
+        field("barcode", "AAAC-1"),
+        Ok(Cow::Borrowed("AAAC-1"))
+    ));
+    assert_eq!(sanitized_fields(), 0);
+}
+
+#[test]
+fn strict_rejects_each_separator() {
+    let _guard = enter(FieldPolicy::Strict);
+    for value in ["a\tb", "a\nb", "a\rb", "trailing\r"] {
+        let err = field("sample", value).expect_err(value);
+        assert_eq!(err.column, "sample");
+        assert_eq!(err.value, value);
+        assert!(err.to_string().contains("--lenient"));
+    }
+}
+
+#[test]
+fn lenient_replaces_and_counts_fields() {
+    let _guard = enter(FieldPolicy::Lenient);
+    assert_eq!(field("barcode", "c\t1").unwrap(), "c 1");
+    assert_eq!(field("sample", "s\r\n2").unwrap(), "s  2");
+    assert_eq!(field("sample", "ok").unwrap(), "ok");
+    assert_eq!(sanitized_fields(), 2);
+}
+
+#[test]
+fn guard_restores_previous_policy() {
+    let _outer = enter(FieldPolicy::Lenient);
+    field("x", "a\tb").unwrap();
+    {
+        let _inner = enter(FieldPolicy::Strict);
+        assert!(field("x", "a\tb").is_err());
+        assert_eq!(sanitized_fields(), 0);
+    }
+    assert_eq!(sanitized_fields(), 1);
+    assert!(field("x", "a\tb").is_ok());
+}
@@ -11,3 +11,12 @@ fn sum_u32_matches_scalar() {
     let expected: u64 = data.iter().map(|v| *v as u64).sum();
     assert_eq!(sum_u32(&data), expected);
 }
+
+#[test]
+fn sum_u32_exact_past_u32_range() {
+    let data = vec![u32::MAX; 19];
+    assert_eq!(sum_u32(&data), u32::MAX as u64 * 19);
+
+    let near_f32_limit = vec![(1u32 << 24) + 1; 9];
+    assert_eq!(sum_u32(&near_f32_limit), ((1u64 << 24) + 1) * 9);
+}
@@ -0,0 +1,167 @@
+use super::*;
+use crate::expr::normalize::Normalization;
+use crate::input::cache::mmap_shared_cache;
+use crate::model::axes::AxisConfig;
+use crate::panels::defs::{PanelDef, PanelGene, PanelSet};
+use crate::pipeline::stage1_load::{RunMode, run_stage1};
+use crate::pipeline::stage2_normalize::run_stage2;
+use crate::pipeline::stage3_panels::run_stage3_panels;
+use crate::pipeline::stage4_axes::{CORE_AXES, run_stage4_axes_with_config};
+use crate::pipeline::stage5_scores::run_stage5_scores;
+use crate::pipeline::stage6_classify::run_stage6_classify;
+use crate::pipeline::stage7_report::run_stage7_report;
+
+fn panel(axis: &str, genes: &[String]) -> PanelDef {
+    PanelDef {
+        id: format!("SYN_{axis}"),
+        description: String::new(),
+        axis: axis.to_string(),
+        group: None,
+        genes: genes
+            .iter()
+            .map(|g| PanelGene { symbol: g.clone() })
+            .collect(),
+        required: vec![],
+        weights: None,
+        custom_axis: false,
+        version: None,
+        source: None,
+    }
+}
+
+/// Three boosted genes per core axis except EEB_DEGRADE, which gets
+/// background-only genes so planted cells export more than they degrade.
+fn planted_panels(params: &mut SyntheticParams) -> PanelSet {
+    let mut panels = Vec::new();
+    for axis in CORE_AXES {
+        let genes: Vec<String> = (1..=3).map(|i| format!("{axis}_{i}")).collect();
+        if axis != "EEB_DEGRADE" {
+            params.boosted_genes.extend(genes.iter().cloned());
+        }
+        panels.push(panel(axis, &genes));
+    }
+    // Degrade genes sit right after the boosted ones in the gene list.
+    let n_boosted = params.boosted_genes.len();
+    let degrade: Vec<String> = (n_boosted + 1..=n_boosted + 3)
+        .map(|i| format!("SYN{i:05}"))
+        .collect();
+    let slot = CORE_AXES.iter().position(|a| *a == "EEB_DEGRADE").unwrap();
+    panels[slot] = panel("EEB_DEGRADE", &degrade);
+    PanelSet {
+        panels,
+        files: vec![],
+        skipped: vec![],
+        required_autofixes: vec![],
+    }
+}
+
+#[test]
+fn same_seed_gives_same_dataset() {
+    let params = SyntheticParams {
+        n_cells: 50,
+        n_genes: 100,
+        ..SyntheticParams::default()
+    };
+    let a = SyntheticDataset::generate(&params, 7);
+    let b = SyntheticDataset::generate(&params, 7);
+    assert_eq!(a.expr.col_ptr, b.expr.col_ptr);
+    assert_eq!(a.expr.row_idx, b.expr.row_idx);
+    assert_eq!(a.expr.values, b.expr.values);
+    assert_eq!(a.planted, b.planted);
+    assert_eq!(a.n_planted(), 5);
+
+    let c = SyntheticDataset::generate(&params, 8);
+    assert_ne!(a.expr.row_idx, c.expr.row_idx);
+}
+
+#[test]
+fn shared_cache_round_trips() {
+    let dir = tempfile::tempdir().unwrap();
+    let data = SyntheticDataset::generate(
+        &SyntheticParams {
+            n_cells: 20,
+            n_genes: 30,
+            ..SyntheticParams::default()
+        },
+        1,
+    );
+    let path = dir.path().join("synthetic.kira-organelle.bin");
+    data.write_shared_cache(&path).unwrap();
+
+    let shared = mmap_shared_cache(&path).unwrap();
+    assert_eq!(shared.genes, data.genes);
+    assert_eq!(shared.barcodes, data.barcodes);
+    assert_eq!(shared.nnz, data.expr.nnz);
+    for cell in 0..=data.expr.n_cells {
+        assert_eq!(shared.col_ptr_at(cell), data.expr.col_ptr[cell]);
+    }
+    for i in 0..data.expr.nnz {
+        assert_eq!(shared.row_idx_at(i), data.expr.row_idx[i]);
+        assert_eq!(shared.value_at(i), data.expr.values[i]);
+    }
+}
+
+#[test]
+fn planted_cells_rank_high_in_secretory_load() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("input");
+    let out = dir.path().join("out");
+    std::fs::create_dir_all(&out).unwrap();
+
+    let mut params = SyntheticParams {
+        n_cells: 200,
+        n_genes: 300,
+        ..SyntheticParams::default()
+    };
+    let panels = planted_panels(&mut params);
+    let data = SyntheticDataset::generate(&params, 42);
+    data.write_mtx(&input).unwrap();
+
+    let dataset = run_stage1(&input, None, &out, false, RunMode::Standalone, None).unwrap();
+    assert_eq!(dataset.n_cells, 200);
+    let expr = run_stage2(&dataset, &out, Normalization::default(), false).unwrap();
+    let panels_ctx =
+        run_stage3_panels(&expr, &panels, &dataset.gene_index, &dataset.barcodes, &out).unwrap();
+    let axes =
+        run_stage4_axes_with_config(&dataset, &panels_ctx, &out, &AxisConfig::default()).unwrap();
+    let scores = run_stage5_scores(&axes, &out).unwrap();
+    let classify = run_stage6_classify(&dataset, &expr, &axes, &scores, &out).unwrap();
+    run_stage7_report(
+        &dataset,
+        &expr,
+        &axes,
+        &scores,
+        &classify,
+        &panels_ctx,
+        &out,
+        "cell",
+        RunMode::Standalone,
+        None,
+    )
+    .unwrap();
+
+    let text = std::fs::read_to_string(out.join("secretion.tsv")).unwrap();
+    let mut lines = text.lines();
+    let header: Vec<&str> = lines.next().unwrap().split('\t').collect();
+    let id_col = header.iter().position(|c| *c == "barcode").unwrap();
+    let load_col = header.iter().position(|c| *c == "secretory_load").unwrap();
+    let mut loads: Vec<(f32, bool)> = lines
+        .map(|line| {
+            let fields: Vec<&str> = line.split('\t').collect();
+            let cell = data
+                .barcodes
+                .iter()
+                .position(|b| b == fields[id_col])
+                .unwrap();
+            (fields[load_col].parse().unwrap(), data.planted[cell])
+        })
+        .collect();
+    assert_eq!(loads.len(), 200);
+
+    // Every cell in the top `n_planted` by secretory_load was planted.
+    loads.sort_by(|a, b| b.0.total_cmp(&a.0));
+    let n_planted = data.n_planted();
+    assert_eq!(n_planted, 20);
+    let hits = loads[..n_planted].iter().filter(|(_, p)| *p).count();
+    assert_eq!(hits, n_planted, "top loads: {:?}", &loads[..n_planted]);
+}